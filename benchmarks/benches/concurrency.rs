@@ -0,0 +1,45 @@
+//! Benchmark for how well a single client scales with the number of requests
+//! executed concurrently.
+
+use criterion::*;
+use isahc::prelude::*;
+use isahc_benchmarks::TestServer;
+use rayon::prelude::*;
+use std::io::sink;
+
+static DATA: [u8; 0x1000] = [1; 0x1000]; // 4K
+
+const CONCURRENCY: &[usize] = &[1, 4, 16, 64];
+
+fn benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("concurrency");
+
+    let server = TestServer::static_response(&DATA);
+    let endpoint = server.endpoint();
+    let client = isahc::HttpClient::new().unwrap();
+
+    for &n in CONCURRENCY {
+        // Use a dedicated thread for each concurrent request so that the
+        // client is the bottleneck rather than the thread pool.
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(n)
+            .build()
+            .unwrap();
+
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::new("isahc", n), &n, |b, &n| {
+            b.iter(|| {
+                pool.install(|| {
+                    (0..n).into_par_iter().for_each(|_| {
+                        client.get(&endpoint).unwrap().copy_to(sink()).unwrap();
+                    })
+                })
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark);
+criterion_main!(benches);
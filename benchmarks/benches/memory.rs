@@ -0,0 +1,147 @@
+//! Benchmark for the amount of memory allocated per request.
+//!
+//! Instead of wall-clock time, this benchmark measures the total number of
+//! bytes allocated by the whole process (including the agent thread) while
+//! executing a request. Deallocations are not subtracted, so this is a measure
+//! of allocator pressure rather than of peak memory usage.
+
+use criterion::{
+    measurement::{Measurement, ValueFormatter},
+    *,
+};
+use isahc::prelude::*;
+use isahc_benchmarks::TestServer;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    io::sink,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static DATA: [u8; 0x1000] = [1; 0x1000]; // 4K
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+/// Allocator that keeps a running total of bytes allocated.
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if new_size > layout.size() {
+            ALLOCATED.fetch_add(new_size - layout.size(), Ordering::Relaxed);
+        }
+
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Criterion measurement of bytes allocated.
+struct AllocatedBytes;
+
+impl Measurement for AllocatedBytes {
+    type Intermediate = usize;
+    type Value = usize;
+
+    fn start(&self) -> Self::Intermediate {
+        ALLOCATED.load(Ordering::SeqCst)
+    }
+
+    fn end(&self, start: Self::Intermediate) -> Self::Value {
+        ALLOCATED.load(Ordering::SeqCst) - start
+    }
+
+    fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+        v1 + v2
+    }
+
+    fn zero(&self) -> Self::Value {
+        0
+    }
+
+    fn to_f64(&self, value: &Self::Value) -> f64 {
+        *value as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        &BytesFormatter
+    }
+}
+
+struct BytesFormatter;
+
+impl ValueFormatter for BytesFormatter {
+    fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> &'static str {
+        let (factor, unit) = if typical_value < 1024.0 {
+            (1.0, "B")
+        } else if typical_value < 1024.0 * 1024.0 {
+            (1024.0, "KiB")
+        } else {
+            (1024.0 * 1024.0, "MiB")
+        };
+
+        for value in values {
+            *value /= factor;
+        }
+
+        unit
+    }
+
+    fn scale_throughputs(
+        &self,
+        _typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        let (n, unit) = match throughput {
+            Throughput::Bytes(n) => (*n, "B/byte"),
+            Throughput::Elements(n) => (*n, "B/elem"),
+        };
+
+        for value in values {
+            *value /= n as f64;
+        }
+
+        unit
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "B"
+    }
+}
+
+fn benchmark(c: &mut Criterion<AllocatedBytes>) {
+    let server = TestServer::static_response(&DATA);
+    let endpoint = server.endpoint();
+    let client = isahc::HttpClient::new().unwrap();
+
+    c.bench_function("memory per GET: isahc", |b| {
+        b.iter(|| {
+            client.get(&endpoint).unwrap().copy_to(sink()).unwrap();
+        })
+    });
+}
+
+fn benches() {
+    // The plotting backend panics on the distributions produced by this
+    // measurement, so disable plots. This must happen after parsing arguments,
+    // which would otherwise re-enable them.
+    let mut criterion = Criterion::default()
+        .with_measurement(AllocatedBytes)
+        .configure_from_args()
+        .without_plots();
+
+    benchmark(&mut criterion);
+}
+
+criterion_main!(benches);
@@ -0,0 +1,50 @@
+//! Benchmark for the per-request overhead of small requests over localhost.
+//!
+//! Unlike the download benchmark, a single client is reused for every
+//! iteration so that the cost measured is dominated by request submission and
+//! response handling rather than client setup.
+
+use criterion::*;
+use isahc_benchmarks::TestServer;
+use std::io::{sink, Write};
+
+static DATA: [u8; 0] = [];
+
+fn benchmark(c: &mut Criterion) {
+    c.bench_function("empty GET: curl", move |b| {
+        let server = TestServer::static_response(&DATA);
+        let endpoint = server.endpoint();
+        let mut easy = curl::easy::Easy::new();
+
+        b.iter(|| {
+            easy.url(&endpoint).unwrap();
+
+            let mut sink = sink();
+            let mut transfer = easy.transfer();
+
+            transfer
+                .write_function(|bytes| {
+                    sink.write_all(bytes).unwrap();
+                    Ok(bytes.len())
+                })
+                .unwrap();
+
+            transfer.perform().unwrap();
+        })
+    });
+
+    c.bench_function("empty GET: isahc", move |b| {
+        use isahc::prelude::*;
+
+        let server = TestServer::static_response(&DATA);
+        let endpoint = server.endpoint();
+        let client = isahc::HttpClient::new().unwrap();
+
+        b.iter(|| {
+            client.get(&endpoint).unwrap().copy_to(sink()).unwrap();
+        })
+    });
+}
+
+criterion_group!(benches, benchmark);
+criterion_main!(benches);
@@ -0,0 +1,32 @@
+//! Benchmark for download throughput of various response sizes over
+//! localhost.
+
+use criterion::*;
+use isahc::prelude::*;
+use isahc_benchmarks::TestServer;
+use std::io::sink;
+
+const SIZES: &[usize] = &[0x10000, 0x100000, 0x1000000]; // 64K, 1M, 16M
+
+fn benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("throughput");
+
+    for &size in SIZES {
+        let data: &'static [u8] = Box::leak(vec![1; size].into_boxed_slice());
+        let server = TestServer::static_response(data);
+        let endpoint = server.endpoint();
+        let client = isahc::HttpClient::new().unwrap();
+
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::new("isahc", size), &size, |b, _| {
+            b.iter(|| {
+                client.get(&endpoint).unwrap().copy_to(sink()).unwrap();
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark);
+criterion_main!(benches);
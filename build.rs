@@ -3,6 +3,10 @@ use std::{env, error::Error};
 fn main() -> Result<(), Box<dyn Error>> {
     println!("cargo:rustc-env=ISAHC_FEATURES={}", get_feature_string());
 
+    // Declare custom cfgs set by external tools so that newer compilers don't
+    // warn about them.
+    println!("cargo:rustc-check-cfg=cfg(tarpaulin)");
+
     Ok(())
 }
 
@@ -6,7 +6,7 @@
 //! isahc = { version = "*", features = ["json"]}
 //! ```
 
-use isahc::prelude::*;
+use isahc::{prelude::*, Request};
 
 #[derive(Debug, serde::Serialize)]
 #[serde(rename_all = "UPPERCASE")]
@@ -32,7 +32,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .send()?
         .json::<ShoutCloudResponse>()?;
 
-    println!("Response: {:?}", response);
+    println!("{} -> {}", response.input, response.output);
 
     Ok(())
 }
@@ -0,0 +1,111 @@
+//! Routing of requests with the same affinity key to the same connection.
+
+use crate::{
+    body::AsyncBody,
+    config::{request::RequestConfig, AffinityKey, Dialer},
+    error::{Error, ErrorKind},
+    interceptor::{Context, Interceptor, InterceptorFuture},
+    response::ResponseExt,
+};
+use http::{Request, Uri};
+use std::{collections::HashMap, net::SocketAddr, sync::Mutex};
+
+/// Maximum number of key and host pairs to remember addresses for. Once
+/// reached, everything is forgotten so that unbounded numbers of keys do not
+/// use unbounded memory.
+const MAX_ENTRIES: usize = 4096;
+
+/// Interceptor that connects requests with an [`AffinityKey`] to the address
+/// that the last request with the same key was sent to for the same host.
+///
+/// Curl reuses an open connection to that address if there is one. This is
+/// placed after the redirect interceptor so that every hop is routed.
+#[derive(Default)]
+pub(crate) struct AffinityInterceptor {
+    addrs: Mutex<HashMap<(AffinityKey, String), SocketAddr>>,
+}
+
+impl Interceptor for AffinityInterceptor {
+    type Err = Error;
+
+    fn intercept<'a>(
+        &'a self,
+        mut request: Request<AsyncBody>,
+        ctx: Context<'a>,
+    ) -> InterceptorFuture<'a, Self::Err> {
+        Box::pin(async move {
+            let config = request.extensions().get::<RequestConfig>();
+
+            // Affinity cannot be combined with other ways of choosing where to
+            // connect.
+            let is_routed = matches!(
+                config,
+                Some(config) if config.dial.is_some() || matches!(config.proxy, Some(Some(_)))
+            );
+
+            let (key, authority, port) = match (
+                request.extensions().get::<AffinityKey>(),
+                request.uri().authority(),
+                port(request.uri()),
+            ) {
+                (Some(key), Some(authority), Some(port)) if !is_routed => (
+                    key.clone(),
+                    authority.as_str().to_ascii_lowercase(),
+                    port,
+                ),
+                _ => return ctx.send(request).await,
+            };
+
+            let entry = (key, authority);
+            let pinned = self.addrs.lock().unwrap().get(&entry).copied();
+
+            if let Some(addr) = pinned {
+                tracing::debug!(
+                    "connecting to {} for affinity key `{}`",
+                    addr,
+                    entry.0.as_str()
+                );
+
+                if let Some(config) = request.extensions_mut().get_mut::<RequestConfig>() {
+                    config.dial = Some(Dialer::ip_socket(addr));
+                }
+            }
+
+            match ctx.send(request).await {
+                Ok(response) => {
+                    // Only remember addresses of the server itself, not of a
+                    // proxy found in the environment.
+                    let addr = response.remote_addr().filter(|addr| addr.port() == port);
+
+                    if let Some(addr) = addr {
+                        let mut addrs = self.addrs.lock().unwrap();
+
+                        if addrs.len() >= MAX_ENTRIES && !addrs.contains_key(&entry) {
+                            addrs.clear();
+                        }
+
+                        addrs.insert(entry, addr);
+                    }
+
+                    Ok(response)
+                }
+                Err(e) => {
+                    if pinned.is_some() && e == ErrorKind::ConnectionFailed {
+                        self.addrs.lock().unwrap().remove(&entry);
+                    }
+
+                    Err(e)
+                }
+            }
+        })
+    }
+}
+
+/// Get the port a request URI connects to.
+fn port(uri: &Uri) -> Option<u16> {
+    uri.port_u16().or_else(|| match uri.scheme_str() {
+        Some(scheme) if scheme.eq_ignore_ascii_case("http") => Some(80),
+        Some(scheme) if scheme.eq_ignore_ascii_case("https") => Some(443),
+        _ => None,
+    })
+}
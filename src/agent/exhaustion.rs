@@ -0,0 +1,230 @@
+//! Retrying requests that could not connect because a resource limit was hit.
+//!
+//! When a process runs out of file descriptors or the system runs out of
+//! local ports, every new connection attempt fails until some connections are
+//! closed. Rather than failing a burst of requests at once, the agent can hold
+//! them back and retry them after an increasing delay, giving the connections
+//! in use time to close.
+
+use super::EasyHandle;
+use crate::config::{AgentEventKind, ResourceLimit};
+use std::{
+    collections::VecDeque,
+    net::{Ipv4Addr, UdpSocket},
+    time::{Duration, Instant},
+};
+
+/// Delay before retrying the first time after a resource limit was hit.
+const INITIAL_DELAY: Duration = Duration::from_millis(10);
+
+/// Maximum delay between retries.
+const MAX_DELAY: Duration = Duration::from_secs(1);
+
+/// Get the resource limit that caused connecting for the given request to
+/// fail, if any.
+pub(super) fn resource_limit(request: &mut EasyHandle) -> Option<ResourceLimit> {
+    match request.os_errno() {
+        // Curl does not record why opening a socket failed, so check whether
+        // a new one can be opened right now instead.
+        Ok(0) => match UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)) {
+            Ok(_) => None,
+            Err(e) => e.raw_os_error().and_then(ResourceLimit::from_os_error),
+        },
+        Ok(code) => ResourceLimit::from_os_error(code),
+        Err(_) => None,
+    }
+}
+
+/// Requests waiting to retry connecting after a resource limit was hit, along
+/// with the quota they count towards.
+pub(super) struct Backoff {
+    /// How long a request may wait for resources to become available before
+    /// it fails.
+    max_wait: Duration,
+
+    /// Delay to wait the next time a resource limit is hit.
+    delay: Duration,
+
+    /// When the current backoff ends, if any.
+    until: Option<Instant>,
+
+    waiting: VecDeque<(EasyHandle, Option<String>)>,
+}
+
+impl Backoff {
+    pub(super) fn new(max_wait: Duration) -> Self {
+        Self {
+            max_wait,
+            delay: INITIAL_DELAY,
+            until: None,
+            waiting: VecDeque::new(),
+        }
+    }
+
+    /// Returns true if new connection attempts are being held back.
+    pub(super) fn is_active(&self, now: Instant) -> bool {
+        self.until.map_or(false, |until| now < until)
+    }
+
+    /// Get the number of requests waiting for the backoff to end.
+    pub(super) fn len(&self) -> usize {
+        self.waiting.len()
+    }
+
+    /// Get how long until the backoff ends, if any requests are waiting.
+    pub(super) fn remaining(&self, now: Instant) -> Option<Duration> {
+        if self.waiting.is_empty() {
+            None
+        } else {
+            Some(self.until?.saturating_duration_since(now))
+        }
+    }
+
+    /// Hold a new request back until the backoff ends.
+    pub(super) fn hold(&mut self, request: EasyHandle, quota: Option<String>) {
+        tracing::debug!("resource limit hit recently, request waiting to begin");
+        self.waiting.push_back((request, quota));
+    }
+
+    /// Schedule a request that could not connect because the given resource
+    /// limit was hit to be retried.
+    ///
+    /// If the request has already waited too long, it is handed back to be
+    /// failed instead.
+    pub(super) fn retry(
+        &mut self,
+        mut request: EasyHandle,
+        quota: Option<String>,
+        limit: ResourceLimit,
+        now: Instant,
+    ) -> Result<(), EasyHandle> {
+        request.get_mut().deinit();
+        let since = request.get_mut().set_exhausted_at(now);
+
+        if now.saturating_duration_since(since) >= self.max_wait {
+            tracing::warn!(
+                %limit,
+                "connecting failed because a resource limit was hit, giving up after {:?}",
+                self.max_wait,
+            );
+            request.get_ref().emit(AgentEventKind::ResourceExhausted {
+                limit,
+                retry_in: None,
+            });
+
+            return Err(request);
+        }
+
+        // Requests failing during the same backoff share its end, so that the
+        // delay only grows once per backoff.
+        let retry_in = match self.until {
+            Some(until) if until > now => until - now,
+            _ => {
+                let delay = self.delay;
+                self.delay = (delay * 2).min(MAX_DELAY);
+                self.until = Some(now + delay);
+                delay
+            }
+        };
+
+        tracing::warn!(
+            %limit,
+            "connecting failed because a resource limit was hit, retrying in {:?}",
+            retry_in,
+        );
+        request.get_ref().emit(AgentEventKind::ResourceExhausted {
+            limit,
+            retry_in: Some(retry_in),
+        });
+
+        self.waiting.push_back((request, quota));
+
+        Ok(())
+    }
+
+    /// Start over with the initial delay, after a request completed without
+    /// hitting a resource limit.
+    pub(super) fn reset(&mut self) {
+        self.delay = INITIAL_DELAY;
+    }
+
+    /// Take the waiting requests if the backoff has ended.
+    pub(super) fn take_ready(&mut self, now: Instant) -> Vec<(EasyHandle, Option<String>)> {
+        if self.is_active(now) {
+            Vec::new()
+        } else {
+            self.waiting.drain(..).collect()
+        }
+    }
+
+    /// Remove and return the waiting requests matching the given predicate.
+    pub(super) fn remove_where(
+        &mut self,
+        mut predicate: impl FnMut(&EasyHandle) -> bool,
+    ) -> Vec<(EasyHandle, Option<String>)> {
+        let (removed, waiting) = self
+            .waiting
+            .drain(..)
+            .partition::<Vec<_>, _>(|(request, _)| predicate(request));
+
+        self.waiting = waiting.into();
+
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{body::AsyncBody, handler::RequestHandler};
+
+    fn request() -> EasyHandle {
+        curl::easy::Easy2::new(RequestHandler::new(AsyncBody::empty(), Default::default()).0)
+    }
+
+    #[test]
+    fn retries_with_growing_delay() {
+        let mut backoff = Backoff::new(Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert!(backoff
+            .retry(request(), None, ResourceLimit::FileDescriptors, start)
+            .is_ok());
+        assert!(backoff.is_active(start));
+        assert_eq!(backoff.remaining(start), Some(INITIAL_DELAY));
+
+        // Failing again during the same backoff does not extend it.
+        assert!(backoff
+            .retry(request(), None, ResourceLimit::FileDescriptors, start)
+            .is_ok());
+        assert_eq!(backoff.remaining(start), Some(INITIAL_DELAY));
+        assert!(backoff.take_ready(start).is_empty());
+
+        let later = start + INITIAL_DELAY;
+        assert_eq!(backoff.take_ready(later).len(), 2);
+
+        assert!(backoff
+            .retry(request(), None, ResourceLimit::EphemeralPorts, later)
+            .is_ok());
+        assert_eq!(backoff.remaining(later), Some(INITIAL_DELAY * 2));
+    }
+
+    #[test]
+    fn gives_up_after_max_wait() {
+        let mut backoff = Backoff::new(Duration::from_millis(100));
+        let start = Instant::now();
+        let mut handle = request();
+
+        handle.get_mut().set_exhausted_at(start);
+
+        assert!(backoff
+            .retry(
+                handle,
+                None,
+                ResourceLimit::FileDescriptors,
+                start + Duration::from_millis(100),
+            )
+            .is_err());
+        assert_eq!(backoff.len(), 0);
+    }
+}
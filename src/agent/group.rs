@@ -0,0 +1,148 @@
+use super::{AgentStats, Handle};
+use crate::{
+    config::{AgentDispatch, QuotaTag},
+    error::Error,
+};
+use crossbeam_utils::atomic::AtomicCell;
+use std::{collections::HashMap, sync::Arc, time::Instant};
+
+/// A set of agent threads that share the requests sent by a client.
+///
+/// Each agent runs its own multi handle with its own connection cache, so
+/// that transfers can be driven on multiple cores at once.
+#[derive(Debug)]
+pub(crate) struct Group {
+    agents: Vec<Arc<Handle>>,
+
+    /// How to choose the agent that executes a request.
+    dispatch: AgentDispatch,
+
+    /// Index of the agent to dispatch to next when dispatching round-robin.
+    next: AtomicCell<usize>,
+
+    /// Index of the agent that enforces each quota. Requests assigned to a
+    /// quota must always be executed by the same agent, since quota state is
+    /// not shared between agents.
+    quotas: HashMap<String, usize>,
+}
+
+impl Group {
+    pub(crate) fn new(
+        agents: Vec<Handle>,
+        dispatch: AgentDispatch,
+        quotas: impl IntoIterator<Item = String>,
+    ) -> Self {
+        assert!(!agents.is_empty(), "agent group must not be empty");
+
+        let quotas = quotas
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| (name, i % agents.len()))
+            .collect();
+
+        Self {
+            agents: agents.into_iter().map(Arc::new).collect(),
+            dispatch,
+            next: AtomicCell::new(0),
+            quotas,
+        }
+    }
+
+    /// Create a group without any agents, for a client that executes requests
+    /// on the threads that send them.
+    pub(crate) fn empty() -> Self {
+        Self {
+            agents: Vec::new(),
+            dispatch: AgentDispatch::default(),
+            next: AtomicCell::new(0),
+            quotas: HashMap::new(),
+        }
+    }
+
+    /// Returns true if the group has no agents.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.agents.is_empty()
+    }
+
+    /// Choose the agent to execute a request assigned to the given quota, if
+    /// any.
+    pub(crate) fn select(&self, quota: Option<&QuotaTag>) -> Arc<Handle> {
+        if let Some(&index) = quota.and_then(|tag| self.quotas.get(tag.name())) {
+            return self.agents[index].clone();
+        }
+
+        if self.agents.len() == 1 {
+            return self.agents[0].clone();
+        }
+
+        let index = match self.dispatch {
+            AgentDispatch::RoundRobin => self.next.fetch_add(1) % self.agents.len(),
+            AgentDispatch::LeastLoaded => {
+                // Start scanning at a rotating offset so that ties are not
+                // always broken in favor of the first agent.
+                let offset = self.next.fetch_add(1);
+
+                (0..self.agents.len())
+                    .map(|i| (offset + i) % self.agents.len())
+                    .min_by_key(|&i| self.agents[i].load())
+                    .unwrap()
+            }
+        };
+
+        self.agents[index].clone()
+    }
+
+    /// Returns true if any agent in the group runs on the current thread.
+    pub(crate) fn is_current_thread(&self) -> bool {
+        self.agents.iter().any(|agent| agent.is_current_thread())
+    }
+
+    /// Returns true if this group was inherited from a parent process.
+    pub(crate) fn is_inherited(&self) -> bool {
+        self.agents.first().map_or(false, |agent| agent.is_inherited())
+    }
+
+    /// Get the diagnostic counters of all agents in the group added together.
+    pub(crate) fn stats(&self) -> AgentStats {
+        let mut stats = AgentStats::default();
+
+        for agent in &self.agents {
+            stats.add(&agent.stats());
+        }
+
+        stats
+    }
+
+    /// Ask every agent in the group to close its idle connections.
+    pub(crate) fn clear_connections(&self) -> Result<(), Error> {
+        for agent in &self.agents {
+            agent.clear_connections()?;
+        }
+
+        Ok(())
+    }
+
+    /// Shut down every agent in the group, letting their requests complete
+    /// until the given deadline.
+    ///
+    /// Returns true if all requests completed in time.
+    pub(crate) fn shutdown(&self, deadline: Instant) -> bool {
+        for agent in &self.agents {
+            agent.begin_shutdown(deadline);
+        }
+
+        // An agent thread cannot wait for itself to stop.
+        if self.is_current_thread() {
+            tracing::warn!("client shut down from its own agent thread, not waiting for it");
+            return false;
+        }
+
+        let mut clean = true;
+
+        for agent in &self.agents {
+            clean &= agent.join();
+        }
+
+        clean
+    }
+}
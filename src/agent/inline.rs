@@ -0,0 +1,225 @@
+//! Executing a request on the thread that sent it, without an agent.
+//!
+//! A client built with caller thread execution does not spawn any agent
+//! threads. Instead, each request gets a multi handle of its own, which is
+//! driven whenever the future for the response or the response body is polled
+//! and not ready yet. Driving the transfer blocks for a short time at most, so
+//! that other futures polled by the same executor, such as the one writing a
+//! blocking request body, still get their turn.
+
+use super::EasyHandle;
+use crate::{
+    config::clock::SharedClock,
+    error::{Error, ErrorKind},
+    handler::RequestHandler,
+};
+use curl::multi::{Easy2Handle, Multi};
+use futures_lite::{future::poll_fn, AsyncRead};
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+        Mutex,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+/// Maximum amount of time to block waiting for activity on the connection in
+/// a single turn.
+const WAIT_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// A single transfer driven by the thread that sent it.
+pub(crate) struct InlineTransfer {
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    /// The transfer, until it completes. Declared before the multi handle so
+    /// that it is removed from it before the multi handle is cleaned up.
+    handle: Option<Easy2Handle<RequestHandler>>,
+
+    multi: Multi,
+
+    /// Clock of the client that sent the request.
+    clock: SharedClock,
+
+    /// Set by the handler when reading the request body should be resumed.
+    unpause_read: Arc<AtomicBool>,
+
+    /// Set by the handler when writing the response body should be resumed.
+    unpause_write: Arc<AtomicBool>,
+
+    /// Set when the request has been canceled.
+    canceled: Arc<AtomicBool>,
+}
+
+impl InlineTransfer {
+    /// Begin executing a request sent by a client with the given clock.
+    pub(crate) fn start(mut request: EasyHandle, clock: SharedClock) -> Result<Arc<Self>, Error> {
+        let unpause_read = Arc::new(AtomicBool::new(false));
+        let unpause_write = Arc::new(AtomicBool::new(false));
+        let canceled = Arc::new(AtomicBool::new(false));
+        let handle = request.raw();
+
+        request.get_mut().init(
+            0,
+            handle,
+            flag_waker(&unpause_read),
+            flag_waker(&unpause_write),
+            flag_waker(&canceled),
+        );
+        request.get_mut().set_dequeued_at(clock.now());
+
+        let multi = Multi::new();
+        let handle = multi.add2(request).map_err(Error::from_any)?;
+
+        Ok(Arc::new(Self {
+            inner: Mutex::new(Inner {
+                handle: Some(handle),
+                multi,
+                clock,
+                unpause_read,
+                unpause_write,
+                canceled,
+            }),
+        }))
+    }
+
+    /// Drive the transfer until the given future completes.
+    pub(crate) async fn drive<F: Future>(self: &Arc<Self>, future: F) -> Result<F::Output, Error> {
+        let mut future = Box::pin(future);
+
+        poll_fn(|cx| match future.as_mut().poll(cx) {
+            Poll::Ready(output) => Poll::Ready(Ok(output)),
+            Poll::Pending => match self.poll_turn(cx) {
+                Ok(()) => Poll::Pending,
+                Err(e) => Poll::Ready(Err(e)),
+            },
+        })
+        .await
+    }
+
+    /// Drive the transfer for one turn and schedule another one.
+    pub(crate) fn poll_turn(&self, cx: &mut Context<'_>) -> Result<(), Error> {
+        self.turn()?;
+        cx.waker().wake_by_ref();
+
+        Ok(())
+    }
+
+    /// Read from the given reader of the response body, driving the transfer
+    /// whenever the reader has to wait for data.
+    pub(crate) fn poll_read<R: AsyncRead>(
+        &self,
+        reader: Pin<&mut R>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        match reader.poll_read(cx, buf) {
+            Poll::Pending => match self.poll_turn(cx) {
+                Ok(()) => Poll::Pending,
+                Err(e) => Poll::Ready(Err(e.into())),
+            },
+            ready => ready,
+        }
+    }
+
+    /// Make as much progress on the transfer as possible without blocking for
+    /// longer than the wait timeout.
+    fn turn(&self) -> Result<(), Error> {
+        let mut inner = self.inner.lock().unwrap();
+        let inner = &mut *inner;
+
+        let handle = match inner.handle.as_ref() {
+            Some(handle) => handle,
+            None => return Ok(()),
+        };
+
+        if inner.canceled.swap(false, Ordering::SeqCst) || handle.get_ref().is_interrupted() {
+            tracing::debug!("request canceled, aborting");
+            return inner.complete(Err(ErrorKind::Interrupted.into()));
+        }
+
+        if handle
+            .get_ref()
+            .headers_deadline()
+            .map_or(false, |deadline| deadline <= inner.clock.now())
+        {
+            tracing::debug!("headers timeout expired, aborting request");
+            return inner.complete(Err(ErrorKind::Timeout.into()));
+        }
+
+        let mut resumed = false;
+
+        // Errors from unpausing come from our own callbacks, and fail the
+        // transfer through the normal means.
+        if inner.unpause_read.swap(false, Ordering::SeqCst) {
+            let _ = handle.unpause_read();
+            resumed = true;
+        }
+
+        if inner.unpause_write.swap(false, Ordering::SeqCst) {
+            let _ = handle.unpause_write();
+            resumed = true;
+        }
+
+        inner.multi.perform().map_err(Error::from_any)?;
+
+        let mut result = None;
+
+        inner.multi.messages(|message| {
+            if let Some(r) = message.result_for2(handle) {
+                result = Some(r);
+            }
+        });
+
+        if let Some(result) = result {
+            return inner.complete(result.map_err(Error::from_any));
+        }
+
+        // Progress may be possible right away after resuming.
+        if !resumed {
+            inner
+                .multi
+                .wait(&mut [], WAIT_TIMEOUT)
+                .map_err(Error::from_any)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Curl handles may be used from any thread, as long as they are not used by
+// more than one at a time, which the mutex around them ensures.
+#[allow(unsafe_code)]
+unsafe impl Send for Inner {}
+
+impl Inner {
+    fn complete(&mut self, result: Result<(), Error>) -> Result<(), Error> {
+        let mut handle = self.handle.take().unwrap();
+
+        // Take a final snapshot of the transfer's metrics while curl still
+        // considers it active.
+        handle.get_mut().snapshot_metrics();
+
+        let mut request = self.multi.remove2(handle).map_err(Error::from_any)?;
+        request.get_mut().set_result(result);
+
+        // Dropping the handler signals the end of the response body stream to
+        // its reader.
+        drop(request);
+
+        Ok(())
+    }
+}
+
+/// Create a waker that sets the given flag.
+fn flag_waker(flag: &Arc<AtomicBool>) -> std::task::Waker {
+    let flag = flag.clone();
+
+    waker_fn::waker_fn(move || flag.store(true, Ordering::SeqCst))
+}
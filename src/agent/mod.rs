@@ -8,36 +8,122 @@
 //! Since request executions are driven through futures, the agent also acts as
 //! a specialized task executor for tasks related to requests.
 
-use crate::{error::Error, handler::RequestHandler, task::WakerExt};
+use crate::{
+    body::AsyncBody,
+    config::{
+        clock::SharedClock,
+        dial::ConnectorObj,
+        instrument::SharedInstrumentation,
+        pacing::PacingState,
+        adaptive::{AdaptiveLimit, Signal},
+        quota::QuotaState,
+        AdaptiveConcurrency,
+        AgentDispatch,
+        AgentEventKind,
+        Pacing,
+        Quota,
+        WakeupCause,
+    },
+    error::{Error, ErrorKind},
+    handler::RequestHandler,
+    task::WakerExt,
+};
 use async_channel::{Receiver, Sender};
 use crossbeam_utils::{atomic::AtomicCell, sync::WaitGroup};
-use curl::multi::{Events, Multi, Socket, SocketEvents};
-use futures_lite::future::block_on;
+use curl::multi::Events;
+use futures_lite::future::{self, block_on};
 use slab::Slab;
 use std::{
+    cell::Cell,
+    collections::{HashMap, VecDeque},
     io,
-    sync::{Arc, Mutex},
+    iter,
+    mem,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+        Mutex,
+    },
     task::Waker,
     thread,
     time::{Duration, Instant},
 };
 
-use self::{selector::Selector, timer::Timer};
+use self::{
+    exhaustion::Backoff,
+    pool::{Pool, PoolOptions, SocketUpdate},
+    selector::Selector,
+    stats::StatsRecorder,
+};
+
+pub use self::stats::AgentStats;
+pub(crate) use self::{group::Group, inline::InlineTransfer, watchdog::Liveness};
 
+mod exhaustion;
+mod group;
+mod inline;
+mod pool;
 mod selector;
+mod stats;
 mod timer;
+mod watchdog;
 
 static NEXT_AGENT_ID: AtomicCell<usize> = AtomicCell::new(0);
-const WAIT_TIMEOUT: Duration = Duration::from_millis(1000);
+
+thread_local! {
+    /// ID of the agent running on the current thread, if any.
+    // Const initializers need a newer Rust than our minimum supported version.
+    #[allow(clippy::missing_const_for_thread_local)]
+    static CURRENT_AGENT_ID: Cell<Option<usize>> = Cell::new(None);
+}
+
+/// Default maximum amount of time to block waiting for activity, if curl does
+/// not give us a shorter timeout.
+const DEFAULT_WAIT_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// A wakeup that returns faster than this without any work being done is
+/// considered spurious.
+const SPURIOUS_WAKEUP_DURATION: Duration = Duration::from_millis(1);
+
+/// Number of consecutive spurious wakeups tolerated before the agent assumes it
+/// is stuck in a busy loop and starts backing off.
+const SPURIOUS_WAKEUP_THRESHOLD: u32 = 64;
+
+/// Maximum amount of time to back off for when a busy loop is detected.
+const MAX_BACKOFF: Duration = Duration::from_millis(50);
+
+/// Default number of submitted requests that may be queued up waiting for the
+/// agent to pick them up before submitters must wait.
+const DEFAULT_REQUEST_QUEUE_CAPACITY: usize = 1024;
+
+/// Maximum number of completed easy handles to keep around for reuse by future
+/// requests.
+const MAX_IDLE_HANDLES: usize = 32;
 
 type EasyHandle = curl::easy::Easy2<RequestHandler>;
 
 /// Builder for configuring and spawning an agent.
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub(crate) struct AgentBuilder {
     max_connections: usize,
     max_connections_per_host: usize,
     connection_cache_size: usize,
+    transfer_quantum: usize,
+    wait_timeout: Option<Duration>,
+    request_queue_capacity: Option<usize>,
+    expected_concurrency: usize,
+    quotas: HashMap<String, Quota>,
+    clock: SharedClock,
+    connector: Option<Arc<ConnectorObj>>,
+    instrumentation: Option<SharedInstrumentation>,
+    threads: usize,
+    dispatch: AgentDispatch,
+    sequential: bool,
+    caller_thread: bool,
+    max_in_flight: usize,
+    adaptive_concurrency: Option<AdaptiveConcurrency>,
+    pacing: Option<Pacing>,
+    resource_exhaustion_retry: Option<Duration>,
 }
 
 impl AgentBuilder {
@@ -56,6 +142,112 @@ impl AgentBuilder {
         self
     }
 
+    pub(crate) fn transfer_quantum(mut self, bytes: usize) -> Self {
+        self.transfer_quantum = bytes;
+        self
+    }
+
+    pub(crate) fn wait_timeout(mut self, timeout: Duration) -> Self {
+        self.wait_timeout = Some(timeout);
+        self
+    }
+
+    pub(crate) fn request_queue_capacity(mut self, capacity: usize) -> Self {
+        self.request_queue_capacity = Some(capacity);
+        self
+    }
+
+    pub(crate) fn expected_concurrency(mut self, n: usize) -> Self {
+        self.expected_concurrency = n;
+        self
+    }
+
+    pub(crate) fn quota(mut self, name: String, quota: Quota) -> Self {
+        self.quotas.insert(name, quota);
+        self
+    }
+
+    /// Keep the given connector alive for as long as connections opened by it
+    /// may exist.
+    pub(crate) fn connector(mut self, connector: Arc<ConnectorObj>) -> Self {
+        self.connector = Some(connector);
+        self
+    }
+
+    pub(crate) fn clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub(crate) fn instrumentation(mut self, instrumentation: SharedInstrumentation) -> Self {
+        self.instrumentation = Some(instrumentation);
+        self
+    }
+
+    pub(crate) fn threads(mut self, n: usize) -> Self {
+        self.threads = n;
+        self
+    }
+
+    pub(crate) fn dispatch(mut self, dispatch: AgentDispatch) -> Self {
+        self.dispatch = dispatch;
+        self
+    }
+
+    pub(crate) fn sequential(mut self, enable: bool) -> Self {
+        self.sequential = enable;
+        self
+    }
+
+    pub(crate) fn caller_thread(mut self, enable: bool) -> Self {
+        self.caller_thread = enable;
+        self
+    }
+
+    pub(crate) fn max_in_flight(mut self, max: usize) -> Self {
+        self.max_in_flight = max;
+        self
+    }
+
+    pub(crate) fn adaptive_concurrency(mut self, config: AdaptiveConcurrency) -> Self {
+        self.adaptive_concurrency = Some(config);
+        self
+    }
+
+    pub(crate) fn pacing(mut self, pacing: Pacing) -> Self {
+        self.pacing = Some(pacing);
+        self
+    }
+
+    pub(crate) fn resource_exhaustion_retry(mut self, max_wait: Duration) -> Self {
+        self.resource_exhaustion_retry = Some(max_wait);
+        self
+    }
+
+    /// Spawn as many agents as configured, at least one, and return a group
+    /// for dispatching requests to them.
+    ///
+    /// Executing requests sequentially requires a single agent, since agents
+    /// run independently of each other. Executing requests on the caller
+    /// thread requires none.
+    pub(crate) fn spawn_group(&self) -> io::Result<Group> {
+        if self.caller_thread {
+            return Ok(Group::empty());
+        }
+
+        let threads = if self.sequential {
+            1
+        } else {
+            self.threads.max(1)
+        };
+
+        let agents = (0..threads)
+            .map(|_| self.spawn())
+            .collect::<io::Result<Vec<_>>>()?;
+
+        Ok(Group::new(agents, self.dispatch, self.quotas.keys().cloned()))
+    }
+
     /// Spawn a new agent using the configuration in this builder and return a
     /// handle for communicating with the agent.
     pub(crate) fn spawn(&self) -> io::Result<Handle> {
@@ -78,14 +270,37 @@ impl AgentBuilder {
         // Create an I/O selector for driving curl's sockets.
         let selector = Selector::new()?;
 
+        // Control messages are small and may be sent from within curl
+        // callbacks on the agent thread itself, so they must never block.
         let (message_tx, message_rx) = async_channel::unbounded();
 
+        // New requests on the other hand are bounded, so that producers
+        // submitting requests faster than the agent can accept them are forced
+        // to wait.
+        let (request_tx, request_rx) = async_channel::bounded(
+            self.request_queue_capacity
+                .unwrap_or(DEFAULT_REQUEST_QUEUE_CAPACITY)
+                .max(1),
+        );
+
         let wait_group = WaitGroup::new();
         let wait_group_thread = wait_group.clone();
 
         let max_connections = self.max_connections;
         let max_connections_per_host = self.max_connections_per_host;
         let connection_cache_size = self.connection_cache_size;
+        let transfer_quantum = self.transfer_quantum;
+        let wait_timeout = self.wait_timeout.unwrap_or(DEFAULT_WAIT_TIMEOUT);
+        let expected_concurrency = self.expected_concurrency;
+        let quotas = self.quotas.clone();
+        let clock = self.clock.clone();
+        let connector = self.connector.clone();
+        let instrumentation = self.instrumentation.clone();
+        let sequential = self.sequential;
+        let max_in_flight = self.max_in_flight;
+        let adaptive_concurrency = self.adaptive_concurrency.clone();
+        let pacing = self.pacing.clone();
+        let resource_exhaustion_retry = self.resource_exhaustion_retry;
 
         // Create a span for the agent thread that outlives this method call,
         // but rather was caused by it.
@@ -94,31 +309,58 @@ impl AgentBuilder {
 
         let waker = selector.waker();
         let message_tx_clone = message_tx.clone();
+        let idle_handles = Arc::new(Mutex::new(Vec::new()));
+        let idle_handles_clone = idle_handles.clone();
+        let liveness = Arc::new(Liveness::new(wait_timeout));
+        let liveness_clone = liveness.clone();
+        let stats = Arc::new(StatsRecorder::default());
+        let stats_clone = stats.clone();
+        let drained = Arc::new(AtomicBool::new(false));
+        let drained_clone = drained.clone();
+
+        watchdog::watch(&liveness);
 
         let thread_main = move || {
             let _enter = agent_span.enter();
-            let mut multi = Multi::new();
-
-            if max_connections > 0 {
-                multi
-                    .set_max_total_connections(max_connections)
-                    .map_err(Error::from_any)?;
-            }
-
-            if max_connections_per_host > 0 {
-                multi
-                    .set_max_host_connections(max_connections_per_host)
-                    .map_err(Error::from_any)?;
-            }
-
-            // Only set maxconnects if greater than 0, because 0 actually means unlimited.
-            if connection_cache_size > 0 {
-                multi
-                    .set_max_connects(connection_cache_size)
-                    .map_err(Error::from_any)?;
-            }
-
-            let agent = AgentContext::new(multi, selector, message_tx_clone, message_rx)?;
+            CURRENT_AGENT_ID.with(|current| current.set(Some(id)));
+            let mut agent = AgentContext::new(
+                PoolOptions {
+                    max_connections,
+                    max_connections_per_host,
+                    connection_cache_size,
+                },
+                selector,
+                message_tx_clone,
+                message_rx,
+                request_rx,
+                idle_handles_clone,
+                liveness_clone,
+            )?;
+            agent.stats = stats_clone;
+            agent.drained = drained_clone;
+            agent.transfer_quantum = transfer_quantum;
+            agent.wait_timeout = wait_timeout;
+            agent.reserve(expected_concurrency);
+            agent.quotas = quotas
+                .into_iter()
+                .map(|(name, quota)| {
+                    let queue = QuotaQueue {
+                        state: QuotaState::new(quota, clock.now()),
+                        waiting: VecDeque::new(),
+                    };
+
+                    (name, queue)
+                })
+                .collect();
+            agent.clock = clock;
+            agent.connector = connector;
+            agent.instrumentation = instrumentation;
+            agent.sequential = sequential.then(VecDeque::new);
+            agent.max_in_flight = max_in_flight;
+            agent.adaptive_limit = adaptive_concurrency.map(AdaptiveLimit::new);
+            agent.pacing = pacing.map(PacingState::new);
+            agent.record_concurrency_limit();
+            agent.backoff = resource_exhaustion_retry.map(Backoff::new);
 
             drop(wait_group_thread);
 
@@ -135,7 +377,15 @@ impl AgentBuilder {
 
         let handle = Handle {
             message_tx,
+            request_tx,
+            idle_handles,
+            liveness,
+            stats,
+            drained,
+            shutting_down: AtomicBool::new(false),
             waker,
+            id,
+            pid: std::process::id(),
             join_handle: Mutex::new(Some(
                 thread::Builder::new()
                     .name(format!("isahc-agent-{}", id))
@@ -159,9 +409,34 @@ pub(crate) struct Handle {
     /// Used to send messages to the agent thread.
     message_tx: Sender<Message>,
 
+    /// Used to submit new requests to the agent thread.
+    request_tx: Sender<EasyHandle>,
+
+    /// Easy handles from completed requests that are available for reuse.
+    idle_handles: Arc<Mutex<Vec<EasyHandle>>>,
+
+    /// Tracks whether the agent thread is still alive.
+    liveness: Arc<Liveness>,
+
+    /// Diagnostic counters about the agent's behavior.
+    stats: Arc<StatsRecorder>,
+
+    /// Set by the agent thread if it shut down after draining all of its
+    /// requests.
+    drained: Arc<AtomicBool>,
+
+    /// Set once a shutdown of the agent has begun.
+    shutting_down: AtomicBool,
+
     /// A waker that can wake up the agent thread while it is polling.
     waker: Waker,
 
+    /// Unique ID of the agent.
+    id: usize,
+
+    /// ID of the process the agent thread was spawned in.
+    pid: u32,
+
     /// A join handle for the agent thread.
     join_handle: Mutex<Option<thread::JoinHandle<Result<(), Error>>>>,
 }
@@ -172,8 +447,19 @@ pub(crate) struct Handle {
 /// traditional curl multi event loop with some extra bookkeeping and async
 /// features like wakers.
 struct AgentContext {
-    /// A curl multi handle, of course.
-    multi: curl::multi::Multi,
+    /// The connection pool that new requests are executed on.
+    pool: Pool,
+
+    /// Pools replaced by clearing connections that still have requests
+    /// running on them. Each is dropped once its last request completes.
+    retired_pools: Vec<Pool>,
+
+    /// Limits to apply to newly created connection pools.
+    pool_options: PoolOptions,
+
+    /// Used to give newly created connection pools a way to send socket
+    /// updates.
+    socket_updates_tx: Sender<SocketUpdate>,
 
     /// Used to send messages to the agent thread.
     message_tx: Sender<Message>,
@@ -181,23 +467,138 @@ struct AgentContext {
     /// Incoming messages from the agent handle.
     message_rx: Receiver<Message>,
 
+    /// Incoming requests to execute from the agent handle.
+    request_rx: Receiver<EasyHandle>,
+
+    /// Easy handles from completed requests that are available for reuse.
+    idle_handles: Arc<Mutex<Vec<EasyHandle>>>,
+
+    /// Used to report to request handlers that the agent is still alive.
+    liveness: Arc<Liveness>,
+
     /// Contains all of the active requests.
-    requests: Slab<curl::multi::Easy2Handle<RequestHandler>>,
+    requests: Slab<ActiveRequest>,
+
+    /// Generation to assign to the next request that begins. Combined with
+    /// the slab key to form a token that uniquely identifies a request, even
+    /// if its slot is later reused.
+    next_generation: u64,
 
     /// Indicates if the thread has been requested to stop.
     close_requested: bool,
 
+    /// If set, the agent is no longer accepting new requests and stops once
+    /// the requests it has finish, or at this deadline at the latest.
+    drain_deadline: Option<Instant>,
+
+    /// Set if the agent stopped after draining all of its requests.
+    drained: Arc<AtomicBool>,
+
     /// A waker that can wake up the agent thread while it is polling.
     waker: Waker,
 
     /// This is the poller we use to poll for socket activity!
     selector: Selector,
 
-    /// A timer we use to keep track of curl's timeouts.
-    timer: Arc<Timer>,
+    /// Queue of socket registration updates from the multi handles.
+    socket_updates: Receiver<SocketUpdate>,
+
+    /// Requests waiting to have reading their request body resumed. Unpause
+    /// requests are batched up and applied once per turn of the event loop,
+    /// rather than one at a time as messages arrive.
+    pending_unpause_reads: Vec<Token>,
+
+    /// Requests waiting to have writing their response body resumed.
+    pending_unpause_writes: Vec<Token>,
+
+    /// Maximum number of response body bytes a single transfer may receive
+    /// per turn of the event loop. Zero means no limit.
+    transfer_quantum: usize,
+
+    /// Number of turns of the event loop completed so far. Used to rotate the
+    /// order in which batched unpauses are applied.
+    turn: usize,
+
+    /// Maximum amount of time to block waiting for activity if curl does not
+    /// ask for a shorter timeout.
+    wait_timeout: Duration,
+
+    /// Number of consecutive turns that woke up early without doing any work.
+    spurious_wakeup_streak: u32,
+
+    /// Quotas registered with the client, by name.
+    quotas: HashMap<String, QuotaQueue>,
+
+    /// If executing requests one at a time, the requests waiting for the
+    /// request in progress to complete, in the order they were submitted.
+    sequential: Option<VecDeque<EasyHandle>>,
+
+    /// Maximum number of requests that may be in progress at once. Zero means
+    /// no limit.
+    max_in_flight: usize,
+
+    /// Requests waiting for the number of requests in progress to drop below
+    /// the limit, along with the quota they count towards. Ordered by
+    /// priority, highest first, and then in the order they were submitted.
+    in_flight_queue: VecDeque<(EasyHandle, Option<String>)>,
+
+    /// If adjusting the number of requests that may be in progress at once
+    /// to how the servers are coping, the current limit.
+    adaptive_limit: Option<AdaptiveLimit>,
+
+    /// If spreading out the starts of requests to the same host, when
+    /// requests were last started to each host.
+    pacing: Option<PacingState>,
 
-    /// Queue of socket registration updates from the multi handle.
-    socket_updates: Receiver<(Socket, SocketEvents, usize)>,
+    /// If retrying requests that could not connect because a resource limit
+    /// was hit, the requests waiting to be retried.
+    backoff: Option<Backoff>,
+
+    /// Clock used for quota rate limits.
+    clock: SharedClock,
+
+    /// Where to report agent wakeups, if anywhere.
+    instrumentation: Option<SharedInstrumentation>,
+
+    /// Diagnostic counters about the agent's behavior.
+    stats: Arc<StatsRecorder>,
+
+    /// Connector that opened connections in the connection cache, if any.
+    /// Declared after the multi handle so that it outlives all connections.
+    connector: Option<Arc<ConnectorObj>>,
+}
+
+/// A request being executed by an agent.
+struct ActiveRequest {
+    /// The generation the request was assigned when it began.
+    generation: u64,
+
+    /// Epoch of the connection pool the request is running on.
+    pool: u64,
+
+    /// The curl handle for the request, registered with the multi handle.
+    handle: curl::multi::Easy2Handle<RequestHandler>,
+
+    /// Name of the quota the request counts towards, if any.
+    quota: Option<String>,
+}
+
+/// A quota and the requests waiting for it to allow them to begin.
+struct QuotaQueue {
+    state: QuotaState,
+    waiting: VecDeque<EasyHandle>,
+}
+
+/// Identifies a request being executed by an agent.
+///
+/// Slab slots are reused once a request completes, so the slot index alone is
+/// not enough to identify a request; a message for a completed request that is
+/// received late could otherwise affect an unrelated request that happens to
+/// occupy the same slot. The generation disambiguates between the two.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Token {
+    index: usize,
+    generation: u64,
 }
 
 /// A message sent from the main thread to the agent thread.
@@ -206,16 +607,26 @@ enum Message {
     /// Requests the agent to close.
     Close,
 
+    /// Requests the agent to close once its requests have completed, or at
+    /// the given deadline, whichever comes first.
+    Drain(Instant),
+
     /// Begin executing a new request.
     Execute(EasyHandle),
 
     /// Request to resume reading the request body for the request with the
     /// given ID.
-    UnpauseRead(usize),
+    UnpauseRead(Token),
 
     /// Request to resume writing the response body for the request with the
     /// given ID.
-    UnpauseWrite(usize),
+    UnpauseWrite(Token),
+
+    /// Close all idle connections and stop reusing existing ones.
+    ClearConnections,
+
+    /// Abort the request with the given ID, since it has been canceled.
+    Cancel(Token),
 }
 
 #[derive(Debug)]
@@ -227,9 +638,111 @@ enum JoinResult {
 }
 
 impl Handle {
+    /// Take a previously used easy handle that is ready to be reused for a new
+    /// request, if one is available.
+    ///
+    /// The handle will have been reset to its default state, but the handler
+    /// it contains is a placeholder that should be replaced.
+    pub(crate) fn take_idle_handle(&self) -> Option<EasyHandle> {
+        self.idle_handles.lock().unwrap().pop()
+    }
+
+    /// Returns true if the current thread is the agent thread.
+    ///
+    /// Blocking the agent thread on a request sent to the same agent would
+    /// deadlock, since the agent could no longer make progress on it.
+    pub(crate) fn is_current_thread(&self) -> bool {
+        CURRENT_AGENT_ID.with(Cell::get) == Some(self.id)
+    }
+
+    /// Returns true if this handle was inherited from a parent process.
+    ///
+    /// A process created with `fork` only inherits the thread that called it,
+    /// so an agent thread spawned before forking only exists in the parent.
+    pub(crate) fn is_inherited(&self) -> bool {
+        self.pid != std::process::id()
+    }
+
+    /// Get a snapshot of the diagnostic counters of the agent thread.
+    pub(crate) fn stats(&self) -> AgentStats {
+        self.stats.snapshot()
+    }
+
+    /// Get the number of requests submitted to the agent that have not
+    /// completed yet.
+    pub(crate) fn load(&self) -> usize {
+        self.request_tx.len() + self.stats.requests.load()
+    }
+
+    /// Get the liveness tracker for the agent thread.
+    pub(crate) fn liveness(&self) -> &Arc<Liveness> {
+        &self.liveness
+    }
+
+    /// Ask the agent to close its idle connections and to open new ones for
+    /// all requests that begin afterwards.
+    pub(crate) fn clear_connections(&self) -> Result<(), Error> {
+        self.send_message(Message::ClearConnections)
+    }
+
+    /// Stop accepting new requests and ask the agent to close once the
+    /// requests it already has complete, or at the given deadline at the
+    /// latest.
+    pub(crate) fn begin_shutdown(&self, deadline: Instant) {
+        if self.shutting_down.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        // Close the queue first, so that every request the agent will ever
+        // receive is already queued by the time it begins draining.
+        self.request_tx.close();
+
+        let _ = self.send_message(Message::Drain(deadline));
+    }
+
+    /// Wait for the agent thread to stop after beginning a shutdown.
+    ///
+    /// Returns true if the agent stopped after all of its requests completed.
+    pub(crate) fn join(&self) -> bool {
+        match self.try_join() {
+            JoinResult::Ok | JoinResult::AlreadyJoined => self.drained.load(Ordering::SeqCst),
+            JoinResult::Err(e) => {
+                tracing::error!("agent thread terminated with error: {}", e);
+                false
+            }
+            JoinResult::Panic => {
+                tracing::error!("agent thread panicked");
+                false
+            }
+        }
+    }
+
     /// Begin executing a request with this agent.
-    pub(crate) fn submit_request(&self, request: EasyHandle) -> Result<(), Error> {
-        self.send_message(Message::Execute(request))
+    ///
+    /// If the agent already has too many requests queued up waiting to be
+    /// started, this will wait until there is room for another.
+    pub(crate) async fn submit_request(&self, request: EasyHandle) -> Result<(), Error> {
+        let request = match self.request_tx.try_send(request) {
+            Ok(()) => {
+                self.waker.wake_by_ref();
+                return Ok(());
+            }
+            Err(async_channel::TrySendError::Full(request)) => request,
+            Err(async_channel::TrySendError::Closed(_)) => return self.closed(),
+        };
+
+        tracing::debug!("agent request queue is full, waiting for room");
+
+        // Make sure the agent is awake and draining the queue, then wait.
+        self.waker.wake_by_ref();
+
+        match self.request_tx.send(request).await {
+            Ok(()) => {
+                self.waker.wake_by_ref();
+                Ok(())
+            }
+            Err(_) => self.closed(),
+        }
     }
 
     /// Send a message to the agent thread.
@@ -242,11 +755,25 @@ impl Handle {
                 self.waker.wake_by_ref();
                 Ok(())
             }
-            Err(_) => match self.try_join() {
-                JoinResult::Err(e) => panic!("agent thread terminated with error: {:?}", e),
-                JoinResult::Panic => panic!("agent thread panicked"),
-                _ => panic!("agent thread terminated prematurely"),
-            },
+            Err(_) => self.closed(),
+        }
+    }
+
+    /// Called when the agent no longer accepts messages or requests.
+    fn closed(&self) -> Result<(), Error> {
+        if self.shutting_down.load(Ordering::SeqCst) {
+            Err(ErrorKind::ClientClosed.into())
+        } else {
+            self.agent_terminated()
+        }
+    }
+
+    /// Called when the agent thread is found to have shut down unexpectedly.
+    fn agent_terminated(&self) -> ! {
+        match self.try_join() {
+            JoinResult::Err(e) => panic!("agent thread terminated with error: {:?}", e),
+            JoinResult::Panic => panic!("agent thread panicked"),
+            _ => panic!("agent thread terminated prematurely"),
         }
     }
 
@@ -267,7 +794,28 @@ impl Handle {
 
 impl Drop for Handle {
     fn drop(&mut self) {
-        // Request the agent thread to shut down.
+        // The agent thread belongs to the parent process. It does not exist
+        // here to be shut down, and waking it would wake up the parent's agent
+        // instead since the underlying poller is shared. Leak everything that
+        // it still owns rather than tearing down state that is not ours.
+        if self.is_inherited() {
+            if let Ok(mut idle_handles) = self.idle_handles.try_lock() {
+                mem::forget(mem::take(&mut *idle_handles));
+            }
+
+            if let Some(join_handle) = self.join_handle.get_mut().ok().and_then(Option::take) {
+                mem::forget(join_handle);
+            }
+
+            return;
+        }
+
+        // Request the agent thread to shut down, unless it has already been
+        // shut down and joined.
+        if let Ok(None) = self.join_handle.get_mut() {
+            return;
+        }
+
         if self.send_message(Message::Close).is_err() {
             tracing::error!("agent thread terminated prematurely");
         }
@@ -282,59 +830,321 @@ impl Drop for Handle {
     }
 }
 
+impl Drop for AgentContext {
+    fn drop(&mut self) {
+        // The only way for the agent to stop without being asked to is by
+        // returning an error or panicking. Flag the crash before any requests
+        // are dropped, so that their consumers can tell what happened.
+        if !self.close_requested {
+            self.liveness.mark_crashed();
+        }
+    }
+}
+
 impl AgentContext {
     fn new(
-        mut multi: Multi,
+        pool_options: PoolOptions,
         selector: Selector,
         message_tx: Sender<Message>,
         message_rx: Receiver<Message>,
+        request_rx: Receiver<EasyHandle>,
+        idle_handles: Arc<Mutex<Vec<EasyHandle>>>,
+        liveness: Arc<Liveness>,
     ) -> Result<Self, Error> {
-        let timer = Arc::new(Timer::new());
         let (socket_updates_tx, socket_updates_rx) = async_channel::unbounded();
-
-        multi
-            .socket_function(move |socket, events, key| {
-                let _ = socket_updates_tx.try_send((socket, events, key));
-            })
-            .map_err(Error::from_any)?;
-
-        multi
-            .timer_function({
-                let timer = timer.clone();
-
-                move |timeout| match timeout {
-                    Some(timeout) => {
-                        timer.start(timeout);
-                        true
-                    }
-                    None => {
-                        timer.stop();
-                        true
-                    }
-                }
-            })
-            .map_err(Error::from_any)?;
+        let pool = Pool::new(0, pool_options, socket_updates_tx.clone())?;
 
         Ok(Self {
-            multi,
+            pool,
+            retired_pools: Vec::new(),
+            pool_options,
+            socket_updates_tx,
             message_tx,
             message_rx,
+            request_rx,
+            idle_handles,
+            liveness,
             requests: Slab::new(),
+            next_generation: 0,
             close_requested: false,
+            drain_deadline: None,
+            drained: Arc::new(AtomicBool::new(false)),
             waker: selector.waker(),
             selector,
-            timer,
             socket_updates: socket_updates_rx,
+            pending_unpause_reads: Vec::new(),
+            pending_unpause_writes: Vec::new(),
+            transfer_quantum: 0,
+            turn: 0,
+            wait_timeout: DEFAULT_WAIT_TIMEOUT,
+            spurious_wakeup_streak: 0,
+            quotas: HashMap::new(),
+            sequential: None,
+            max_in_flight: 0,
+            in_flight_queue: VecDeque::new(),
+            adaptive_limit: None,
+            pacing: None,
+            backoff: None,
+            clock: SharedClock::default(),
+            instrumentation: None,
+            stats: Arc::default(),
+            connector: None,
         })
     }
 
+    /// Reserve room for at least the given number of concurrent requests.
+    fn reserve(&mut self, additional: usize) {
+        self.requests.reserve(additional);
+        self.pending_unpause_reads.reserve(additional);
+        self.pending_unpause_writes.reserve(additional);
+    }
+
+    /// Begin executing a request, unless it has to wait for its turn when
+    /// executing requests one at a time, or for its quota.
+    fn schedule_request(&mut self, request: EasyHandle) -> Result<(), Error> {
+        if let Some(queue) = self.sequential.as_mut() {
+            if !queue.is_empty()
+                || !self.requests.is_empty()
+                || !self.in_flight_queue.is_empty()
+                || self.quotas.values().any(|queue| !queue.waiting.is_empty())
+            {
+                queue.push_back(request);
+                return Ok(());
+            }
+        }
+
+        self.admit_request(request)
+    }
+
+    /// Begin executing a request, unless it is assigned to a quota that does
+    /// not allow it to begin yet, in which case it waits for the quota.
+    fn admit_request(&mut self, request: EasyHandle) -> Result<(), Error> {
+        let name = match request.get_ref().quota() {
+            Some(tag) if self.quotas.contains_key(tag.name()) => tag.name().to_owned(),
+            _ => return self.start_request(request, None),
+        };
+
+        let queue = self.quotas.get_mut(&name).unwrap();
+
+        // Requests already waiting go first.
+        if queue.waiting.is_empty() && queue.state.try_acquire(self.clock.now()) {
+            self.start_request(request, Some(name))
+        } else {
+            tracing::debug!(quota = %name, "quota exceeded, request waiting to begin");
+            queue.waiting.push_back(request);
+            Ok(())
+        }
+    }
+
+    /// Begin executing requests waiting for a quota that now allows them to.
+    ///
+    /// Returns true if any requests were started.
+    fn dispatch_quota_queues(&mut self) -> Result<bool, Error> {
+        let now = self.clock.now();
+        let mut ready = Vec::new();
+
+        for (name, queue) in self.quotas.iter_mut() {
+            while !queue.waiting.is_empty() && queue.state.try_acquire(now) {
+                ready.push((name.clone(), queue.waiting.pop_front().unwrap()));
+            }
+        }
+
+        let started = !ready.is_empty();
+
+        for (name, request) in ready {
+            self.start_request(request, Some(name))?;
+        }
+
+        Ok(started)
+    }
+
+    /// Begin executing a request, unless the limit on requests in progress has
+    /// been reached or a request to the same host was started too recently, in
+    /// which case it waits for its turn according to its priority.
+    fn start_request(&mut self, request: EasyHandle, quota: Option<String>) -> Result<(), Error> {
+        // New connection attempts would likely fail the same way as the ones
+        // that just hit a resource limit.
+        if let Some(backoff) = self.backoff.as_mut() {
+            if backoff.is_active(Instant::now()) {
+                backoff.hold(request, quota);
+                return Ok(());
+            }
+        }
+
+        if self.in_flight_limit() == 0 && self.pacing.is_none() {
+            return self.begin_request(request, quota);
+        }
+
+        // Requests of the same priority keep the order they were submitted.
+        let priority = request.get_ref().priority();
+        let index = self
+            .in_flight_queue
+            .iter()
+            .position(|(waiting, _)| waiting.get_ref().priority() < priority)
+            .unwrap_or(self.in_flight_queue.len());
+
+        self.in_flight_queue.insert(index, (request, quota));
+        self.dispatch_in_flight_queue()?;
+
+        if !self.in_flight_queue.is_empty() {
+            tracing::debug!(
+                waiting = self.in_flight_queue.len(),
+                "request waiting for its turn to begin"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Begin executing the highest priority requests waiting for the number of
+    /// requests in progress to drop below the limit, skipping over requests to
+    /// hosts that a request was started to too recently.
+    ///
+    /// Returns true if any requests were started.
+    fn dispatch_in_flight_queue(&mut self) -> Result<bool, Error> {
+        let now = self.clock.now();
+        let mut started = false;
+
+        loop {
+            let limit = self.in_flight_limit();
+
+            if limit > 0 && self.requests.len() >= limit {
+                break;
+            }
+
+            let index = match self.pacing.as_ref() {
+                Some(pacing) => self.in_flight_queue.iter().position(|(request, _)| {
+                    pacing.time_until_ready(request.get_ref().host_key(), now) == Duration::ZERO
+                }),
+                None if self.in_flight_queue.is_empty() => None,
+                None => Some(0),
+            };
+
+            match index.and_then(|index| self.in_flight_queue.remove(index)) {
+                Some((request, quota)) => {
+                    if let Some(pacing) = self.pacing.as_mut() {
+                        pacing.record(request.get_ref().host_key(), now);
+                    }
+
+                    self.begin_request(request, quota)?;
+                    started = true;
+                }
+                None => break,
+            }
+        }
+
+        Ok(started)
+    }
+
+    /// Get how long until pacing allows a waiting request to begin, if any
+    /// request is waiting only for that.
+    fn next_pacing_wait(&self) -> Option<Duration> {
+        let pacing = self.pacing.as_ref()?;
+        let limit = self.in_flight_limit();
+
+        if limit > 0 && self.requests.len() >= limit {
+            return None;
+        }
+
+        let now = self.clock.now();
+
+        self.in_flight_queue
+            .iter()
+            .map(|(request, _)| pacing.time_until_ready(request.get_ref().host_key(), now))
+            .min()
+    }
+
+    /// Get the maximum number of requests that may be in progress at once,
+    /// which is the lower of the fixed and adaptive limits. Zero means no
+    /// limit.
+    fn in_flight_limit(&self) -> usize {
+        match &self.adaptive_limit {
+            Some(limit) if self.max_in_flight > 0 => limit.get().min(self.max_in_flight),
+            Some(limit) => limit.get(),
+            None => self.max_in_flight,
+        }
+    }
+
+    /// Retry the requests waiting for resources to become available, once the
+    /// backoff after hitting a resource limit has ended.
+    ///
+    /// Returns true if any requests were started.
+    fn dispatch_backoff(&mut self) -> Result<bool, Error> {
+        let ready = match self.backoff.as_mut() {
+            Some(backoff) => backoff.take_ready(Instant::now()),
+            None => return Ok(false),
+        };
+        let started = !ready.is_empty();
+
+        for (request, quota) in ready {
+            self.start_request(request, quota)?;
+        }
+
+        Ok(started)
+    }
+
+    /// Begin the next request waiting for its turn when executing requests
+    /// one at a time, if the previous request has completed.
+    ///
+    /// Returns true if a request was scheduled.
+    fn dispatch_sequential_queue(&mut self) -> Result<bool, Error> {
+        if !self.requests.is_empty()
+            || !self.in_flight_queue.is_empty()
+            || self.quotas.values().any(|queue| !queue.waiting.is_empty())
+            || self.backoff.as_ref().map_or(false, |backoff| backoff.len() > 0)
+        {
+            return Ok(false);
+        }
+
+        match self.sequential.as_mut().and_then(VecDeque::pop_front) {
+            Some(request) => {
+                self.admit_request(request)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Returns true if any requests are waiting for a quota, for the number
+    /// of requests in progress to drop below the limit, for their turn when
+    /// executing requests one at a time, or for resources to become available.
+    fn has_waiting_requests(&self) -> bool {
+        !self.in_flight_queue.is_empty()
+            || self.quotas.values().any(|queue| !queue.waiting.is_empty())
+            || self.sequential.as_ref().map_or(false, |queue| !queue.is_empty())
+            || self.backoff.as_ref().map_or(false, |backoff| backoff.len() > 0)
+    }
+
+    /// Get how long until a rate limited quota allows a waiting request to
+    /// begin, if any.
+    fn next_quota_wait(&mut self) -> Option<Duration> {
+        let now = self.clock.now();
+
+        self.quotas
+            .values_mut()
+            .filter(|queue| !queue.waiting.is_empty())
+            .filter_map(|queue| queue.state.time_until_available(now))
+            .min()
+    }
+
     #[tracing::instrument(level = "trace", skip(self))]
-    fn begin_request(&mut self, mut request: EasyHandle) -> Result<(), Error> {
+    fn begin_request(
+        &mut self,
+        mut request: EasyHandle,
+        quota: Option<String>,
+    ) -> Result<(), Error> {
         // Prepare an entry for storing this request while it executes.
         let entry = self.requests.vacant_entry();
         let id = entry.key();
+        let generation = self.next_generation;
+        let token = Token {
+            index: id,
+            generation,
+        };
         let handle = request.raw();
 
+        self.next_generation = self.next_generation.wrapping_add(1);
+
         // Initialize the handler.
         request.get_mut().init(
             id,
@@ -343,7 +1153,7 @@ impl AgentContext {
                 let tx = self.message_tx.clone();
 
                 self.waker
-                    .chain(move |inner| match tx.try_send(Message::UnpauseRead(id)) {
+                    .chain(move |inner| match tx.try_send(Message::UnpauseRead(token)) {
                         Ok(()) => inner.wake_by_ref(),
                         Err(_) => {
                             tracing::warn!(id, "agent went away while resuming read for request")
@@ -354,39 +1164,359 @@ impl AgentContext {
                 let tx = self.message_tx.clone();
 
                 self.waker
-                    .chain(move |inner| match tx.try_send(Message::UnpauseWrite(id)) {
+                    .chain(move |inner| match tx.try_send(Message::UnpauseWrite(token)) {
                         Ok(()) => inner.wake_by_ref(),
                         Err(_) => {
                             tracing::warn!(id, "agent went away while resuming write for request")
                         }
                     })
             },
+            {
+                let tx = self.message_tx.clone();
+
+                self.waker
+                    .chain(move |inner| match tx.try_send(Message::Cancel(token)) {
+                        Ok(()) => inner.wake_by_ref(),
+                        Err(_) => {
+                            tracing::debug!(id, "agent went away while canceling request")
+                        }
+                    })
+            },
         );
 
+        request.get_mut().set_write_quantum(self.transfer_quantum);
+        request.get_mut().set_dequeued_at(self.clock.now());
+
         // Register the request with curl.
-        let mut handle = self.multi.add2(request).map_err(Error::from_any)?;
+        let mut handle = self.pool.multi.add2(request).map_err(Error::from_any)?;
         handle.set_token(id).map_err(Error::from_any)?;
 
         // Add the handle to our bookkeeping structure.
-        entry.insert(handle);
+        entry.insert(ActiveRequest {
+            generation,
+            pool: self.pool.epoch,
+            handle,
+            quota,
+        });
 
         Ok(())
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    fn complete_request(
-        &mut self,
-        token: usize,
-        result: Result<(), curl::Error>,
-    ) -> Result<(), Error> {
-        let handle = self.requests.remove(token);
-        let mut handle = self.multi.remove2(handle).map_err(Error::from_any)?;
+    fn complete_request(&mut self, token: usize, result: Result<(), Error>) -> Result<(), Error> {
+        let mut request = self.requests.remove(token);
+
+        // Take a final snapshot of the transfer's metrics while curl still
+        // considers it active.
+        request.handle.get_mut().snapshot_metrics();
+
+        let quota = request.quota;
+        let epoch = request.pool;
+        let mut handle = if epoch == self.pool.epoch {
+            self.pool.multi.remove2(request.handle)
+        } else {
+            let index = self
+                .retired_pools
+                .iter()
+                .position(|pool| pool.epoch == epoch)
+                .expect("request belongs to an unknown pool");
+            let handle = self.retired_pools[index].multi.remove2(request.handle);
+
+            // Close the retired pool's connections once nothing uses it.
+            if !self.requests.iter().any(|(_, r)| r.pool == epoch) {
+                let pool = self.retired_pools.swap_remove(index);
+                self.close_pool(pool);
+            }
+
+            handle
+        }
+        .map_err(Error::from_any)?;
+
+        // Nothing was sent if connecting failed, so the request can be retried
+        // once resources become available again.
+        let limit = match &result {
+            Err(e) if self.backoff.is_some() && *e.kind() == ErrorKind::ConnectionFailed => {
+                exhaustion::resource_limit(&mut handle)
+            }
+            _ => None,
+        };
+
+        let mut handle = match (self.backoff.as_mut(), limit) {
+            (Some(backoff), Some(limit)) => {
+                match backoff.retry(handle, quota.clone(), limit, Instant::now()) {
+                    Ok(()) => return Ok(()),
+                    Err(handle) => handle,
+                }
+            }
+            (Some(backoff), None) if result.is_ok() => {
+                backoff.reset();
+                handle
+            }
+            _ => handle,
+        };
+
+        if let Some(queue) = quota.and_then(|name| self.quotas.get_mut(&name)) {
+            queue.state.release();
+        }
 
-        handle.get_mut().set_result(result.map_err(Error::from_any));
+        self.adjust_concurrency_limit(handle.get_ref(), &result);
+
+        handle.get_mut().set_result(result);
+        self.recycle_handle(handle);
 
         Ok(())
     }
 
+    /// Raise or lower the adaptive limit on requests in progress according to
+    /// how a completed request reflects on the load of the server.
+    fn adjust_concurrency_limit(&mut self, handler: &RequestHandler, result: &Result<(), Error>) {
+        let limit = match self.adaptive_limit.as_mut() {
+            Some(limit) => limit,
+            None => return,
+        };
+
+        let began_at = match handler.dequeued_at() {
+            Some(at) => at,
+            None => return,
+        };
+
+        let signal = match result {
+            Err(e) => match e.kind() {
+                ErrorKind::ConnectionFailed | ErrorKind::Io | ErrorKind::Timeout => {
+                    Signal::Overloaded
+                }
+                // Other errors, such as the request being canceled, say
+                // nothing about the server.
+                _ => return,
+            },
+            Ok(()) => match handler.response_status() {
+                Some(
+                    http::StatusCode::TOO_MANY_REQUESTS
+                    | http::StatusCode::BAD_GATEWAY
+                    | http::StatusCode::SERVICE_UNAVAILABLE
+                    | http::StatusCode::GATEWAY_TIMEOUT,
+                ) => Signal::Overloaded,
+                _ if handler.response_latency().map_or(false, |l| limit.is_slow(l)) => {
+                    Signal::Overloaded
+                }
+                _ => Signal::Healthy,
+            },
+        };
+
+        let previous = limit.get();
+        limit.record(signal, began_at, self.clock.now());
+
+        if limit.get() < previous {
+            tracing::debug!(limit = limit.get(), "server overloaded, lowering concurrency limit");
+        }
+
+        self.record_concurrency_limit();
+    }
+
+    /// Publish the current adaptive limit on requests in progress.
+    fn record_concurrency_limit(&self) {
+        if let Some(limit) = self.adaptive_limit.as_ref() {
+            self.stats.concurrency_limit.store(Some(limit.get()));
+        }
+    }
+
+    /// Abort a request that has been canceled, if it is still in progress.
+    fn cancel_request(&mut self, token: Token) -> Result<(), Error> {
+        let in_progress = self
+            .requests
+            .get(token.index)
+            .filter(|request| request.generation == token.generation)
+            .is_some();
+
+        if in_progress {
+            tracing::debug!(id = token.index, "request canceled, aborting");
+            self.complete_request(token.index, Err(ErrorKind::Interrupted.into()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Replace the current connection pool with a new, empty one, so that no
+    /// future requests reuse any existing connections.
+    ///
+    /// Idle connections are closed right away, while connections used by
+    /// requests still in progress are closed when those requests complete.
+    fn clear_connections(&mut self) -> Result<(), Error> {
+        let epoch = self.pool.epoch + 1;
+        let pool = Pool::new(epoch, self.pool_options, self.socket_updates_tx.clone())?;
+        let old = mem::replace(&mut self.pool, pool);
+
+        tracing::debug!(epoch, "clearing connections");
+
+        if self.requests.iter().any(|(_, r)| r.pool == old.epoch) {
+            self.retired_pools.push(old);
+        } else {
+            self.close_pool(old);
+        }
+
+        Ok(())
+    }
+
+    /// Drop a connection pool that no longer has any requests, closing all of
+    /// its connections.
+    fn close_pool(&mut self, pool: Pool) {
+        // Stop polling the pool's sockets while they are still open, since
+        // the poller may no longer be able to identify them once closed.
+        for &socket in &pool.sockets {
+            self.selector.deregister(socket).unwrap();
+        }
+
+        drop(pool);
+    }
+
+    /// Abort any requests whose interrupt flag has been set.
+    ///
+    /// This is checked once per turn rather than from a curl callback, since
+    /// curl does not invoke any callbacks for a transfer that is idly waiting
+    /// on the server. The agent wakes up at least once every wait timeout, so
+    /// an interrupt is noticed within that long.
+    ///
+    /// Returns true if any requests were aborted.
+    fn abort_interrupted_requests(&mut self) -> Result<bool, Error> {
+        let interrupted = self
+            .requests
+            .iter()
+            .filter(|(_, request)| request.handle.get_ref().is_interrupted())
+            .map(|(token, _)| token)
+            .collect::<Vec<_>>();
+
+        for &token in &interrupted {
+            tracing::debug!(token, "interrupt flag set, aborting request");
+            self.complete_request(token, Err(ErrorKind::Interrupted.into()))?;
+        }
+
+        // Requests waiting for a quota have not begun yet, so they can be
+        // failed right away.
+        let mut waiting_interrupted = Vec::new();
+
+        for queue in self.quotas.values_mut() {
+            if queue.waiting.iter().any(|r| r.get_ref().is_interrupted()) {
+                let (interrupted, waiting) = queue
+                    .waiting
+                    .drain(..)
+                    .partition::<Vec<_>, _>(|r| r.get_ref().is_interrupted());
+
+                queue.waiting = waiting.into();
+                waiting_interrupted.extend(interrupted);
+            }
+        }
+
+        if self.in_flight_queue.iter().any(|(r, _)| r.get_ref().is_interrupted()) {
+            let (interrupted, waiting) = self
+                .in_flight_queue
+                .drain(..)
+                .partition::<Vec<_>, _>(|(r, _)| r.get_ref().is_interrupted());
+
+            self.in_flight_queue = waiting.into();
+
+            for (request, quota) in interrupted {
+                // The quota was already acquired for the request.
+                if let Some(queue) = quota.and_then(|name| self.quotas.get_mut(&name)) {
+                    queue.state.release();
+                }
+
+                waiting_interrupted.push(request);
+            }
+        }
+
+        if let Some(backoff) = self.backoff.as_mut() {
+            for (request, quota) in backoff.remove_where(|r| r.get_ref().is_interrupted()) {
+                // The quota was already acquired for the request.
+                if let Some(queue) = quota.and_then(|name| self.quotas.get_mut(&name)) {
+                    queue.state.release();
+                }
+
+                waiting_interrupted.push(request);
+            }
+        }
+
+        if let Some(queue) = self.sequential.as_mut() {
+            if queue.iter().any(|r| r.get_ref().is_interrupted()) {
+                let (interrupted, waiting) = queue
+                    .drain(..)
+                    .partition::<Vec<_>, _>(|r| r.get_ref().is_interrupted());
+
+                *queue = waiting.into();
+                waiting_interrupted.extend(interrupted);
+            }
+        }
+
+        let aborted_waiting = !waiting_interrupted.is_empty();
+
+        for mut handle in waiting_interrupted {
+            tracing::debug!("interrupt flag set, aborting request waiting to begin");
+            handle
+                .get_mut()
+                .set_result(Err(ErrorKind::Interrupted.into()));
+            self.recycle_handle(handle);
+        }
+
+        Ok(!interrupted.is_empty() || aborted_waiting)
+    }
+
+    /// Abort any requests whose server has not begun responding within their
+    /// headers timeout.
+    ///
+    /// Returns true if any requests were aborted.
+    fn abort_headers_timed_out_requests(&mut self) -> Result<bool, Error> {
+        let now = self.clock.now();
+        let timed_out = self
+            .requests
+            .iter()
+            .filter(|(_, request)| {
+                request
+                    .handle
+                    .get_ref()
+                    .headers_deadline()
+                    .map_or(false, |deadline| deadline <= now)
+            })
+            .map(|(token, _)| token)
+            .collect::<Vec<_>>();
+
+        for &token in &timed_out {
+            tracing::debug!(token, "headers timeout expired, aborting request");
+            self.complete_request(token, Err(ErrorKind::Timeout.into()))?;
+        }
+
+        Ok(!timed_out.is_empty())
+    }
+
+    /// Get the amount of time until the next headers timeout of an active
+    /// request expires, if any.
+    fn next_headers_deadline(&self) -> Option<Duration> {
+        let now = self.clock.now();
+
+        self.requests
+            .iter()
+            .filter_map(|(_, request)| request.handle.get_ref().headers_deadline())
+            .map(|deadline| deadline.saturating_duration_since(now))
+            .min()
+    }
+
+    /// Reset a completed easy handle and make it available for reuse by
+    /// future requests.
+    ///
+    /// Reusing handles avoids the setup cost of creating a new one for every
+    /// request, and allows curl to hold on to per-handle caches such as TLS
+    /// session IDs.
+    fn recycle_handle(&mut self, mut handle: EasyHandle) {
+        // Replace the handler right away so that the old one is dropped, which
+        // signals the end of the response body stream to its reader.
+        *handle.get_mut() = RequestHandler::new(AsyncBody::empty(), Default::default()).0;
+        handle.reset();
+
+        let mut idle_handles = self.idle_handles.lock().unwrap();
+
+        if idle_handles.len() < MAX_IDLE_HANDLES {
+            idle_handles.push(handle);
+        }
+    }
+
     /// Polls the message channel for new messages from any agent handles.
     ///
     /// If there are no active requests right now, this function will block
@@ -394,8 +1524,24 @@ impl AgentContext {
     #[tracing::instrument(level = "trace", skip(self))]
     fn poll_messages(&mut self) -> Result<(), Error> {
         while !self.close_requested {
-            if self.requests.is_empty() {
-                match block_on(self.message_rx.recv()) {
+            if self.requests.is_empty()
+                && !self.has_waiting_requests()
+                && self.drain_deadline.is_none()
+            {
+                // Nothing can be waiting on us while we have no requests, so
+                // blocking indefinitely here is not a hang.
+                self.liveness.idle();
+
+                let message = block_on(future::or(self.message_rx.recv(), async {
+                    match self.request_rx.recv().await {
+                        Ok(request) => Ok(Message::Execute(request)),
+                        // The request queue is closed when shutting down,
+                        // which is followed by a message saying how.
+                        Err(_) => self.message_rx.recv().await,
+                    }
+                }));
+
+                match message {
                     Ok(message) => self.handle_message(message)?,
                     _ => {
                         tracing::warn!("agent handle disconnected without close message");
@@ -404,7 +1550,18 @@ impl AgentContext {
                     }
                 }
             } else {
-                match self.message_rx.try_recv() {
+                // Control messages take priority over new requests.
+                let message = match self.message_rx.try_recv() {
+                    Err(async_channel::TryRecvError::Empty) => match self.request_rx.try_recv() {
+                        // The request queue is closed when shutting down,
+                        // which is followed by a message saying how.
+                        Err(async_channel::TryRecvError::Closed) => break,
+                        result => result.map(Message::Execute),
+                    },
+                    result => result,
+                };
+
+                match message {
                     Ok(message) => self.handle_message(message)?,
                     Err(async_channel::TryRecvError::Empty) => break,
                     Err(async_channel::TryRecvError::Closed) => {
@@ -425,29 +1582,77 @@ impl AgentContext {
 
         match message {
             Message::Close => self.close_requested = true,
-            Message::Execute(request) => self.begin_request(request)?,
-            Message::UnpauseRead(token) => {
-                if let Some(request) = self.requests.get(token) {
-                    if let Err(e) = request.unpause_read() {
-                        // If unpausing returned an error, it is likely because
-                        // curl called our callback inline and the callback
-                        // returned an error. Unfortunately this does not affect
-                        // the normal state of the transfer, so we need to keep
-                        // the transfer alive until it errors through the normal
-                        // means, which is likely to happen this turn of the
-                        // event loop anyway.
-                        tracing::debug!(id = token, "error unpausing read for request: {:?}", e);
-                    }
-                } else {
-                    tracing::warn!(
-                        "received unpause request for unknown request token: {}",
-                        token
-                    );
+            Message::Drain(deadline) => self.drain_deadline = Some(deadline),
+            Message::Execute(request) => self.schedule_request(request)?,
+            Message::UnpauseRead(token) => self.pending_unpause_reads.push(token),
+            Message::UnpauseWrite(token) => self.pending_unpause_writes.push(token),
+            Message::ClearConnections => self.clear_connections()?,
+            Message::Cancel(token) => self.cancel_request(token)?,
+        }
+
+        Ok(())
+    }
+
+    /// Begin a new turn of the event loop.
+    ///
+    /// If a transfer quantum is configured, transfers that were paused for
+    /// using up their share of the previous turn are queued to be resumed.
+    fn begin_turn(&mut self) {
+        self.turn = self.turn.wrapping_add(1);
+
+        if self.transfer_quantum > 0 {
+            for (index, request) in self.requests.iter_mut() {
+                if request.handle.get_mut().begin_turn() {
+                    self.pending_unpause_writes.push(Token {
+                        index,
+                        generation: request.generation,
+                    });
                 }
             }
-            Message::UnpauseWrite(token) => {
-                if let Some(request) = self.requests.get(token) {
-                    if let Err(e) = request.unpause_write() {
+        }
+    }
+
+    /// Apply all unpause requests that have been batched up since the previous
+    /// turn.
+    ///
+    /// Duplicate requests for the same transfer are coalesced, and the order
+    /// in which transfers are resumed is rotated every turn so that the same
+    /// transfer does not always get the first opportunity to make progress.
+    ///
+    /// Returns true if any transfers were resumed.
+    fn dispatch_unpauses(&mut self) -> bool {
+        let turn = self.turn;
+        let mut dispatched = false;
+
+        for (reads, mut tokens) in [
+            (true, mem::take(&mut self.pending_unpause_reads)),
+            (false, mem::take(&mut self.pending_unpause_writes)),
+        ] {
+            if tokens.is_empty() {
+                continue;
+            }
+
+            dispatched = true;
+            tokens.sort_unstable();
+            tokens.dedup();
+
+            let len = tokens.len();
+            tokens.rotate_left(turn % len);
+
+            for token in tokens.drain(..) {
+                let request = self
+                    .requests
+                    .get(token.index)
+                    .filter(|request| request.generation == token.generation);
+
+                if let Some(request) = request {
+                    let result = if reads {
+                        request.handle.unpause_read()
+                    } else {
+                        request.handle.unpause_write()
+                    };
+
+                    if let Err(e) = result {
                         // If unpausing returned an error, it is likely because
                         // curl called our callback inline and the callback
                         // returned an error. Unfortunately this does not affect
@@ -455,18 +1660,29 @@ impl AgentContext {
                         // the transfer alive until it errors through the normal
                         // means, which is likely to happen this turn of the
                         // event loop anyway.
-                        tracing::debug!(id = token, "error unpausing write for request: {:?}", e);
+                        tracing::debug!(
+                            id = token.index,
+                            reads,
+                            "error unpausing request: {:?}",
+                            e
+                        );
                     }
                 } else {
-                    tracing::warn!(
-                        "received unpause request for unknown request token: {}",
-                        token
-                    );
+                    // This is expected if the request completed after the
+                    // unpause was requested but before we got to it.
+                    tracing::debug!(?token, "ignoring unpause request for stale request token");
                 }
             }
+
+            // Hang onto the allocation for next time.
+            if reads {
+                self.pending_unpause_reads = tokens;
+            } else {
+                self.pending_unpause_writes = tokens;
+            }
         }
 
-        Ok(())
+        dispatched
     }
 
     /// Run the agent in the current thread until requested to stop.
@@ -475,73 +1691,302 @@ impl AgentContext {
 
         // Agent main loop.
         loop {
+            // Time spent here is mostly spent blocked waiting for a request
+            // while there is nothing else to do.
+            let messages_start = Instant::now();
             self.poll_messages()?;
+            self.stats.add_wait_time(messages_start.elapsed());
+            self.record_load();
+            self.liveness.beat();
 
-            if self.close_requested {
+            if self.close_requested || self.check_drained() {
                 break;
             }
 
+            let turn_start = Instant::now();
+
+            self.begin_turn();
+            let mut active = self.dispatch_backoff()?;
+            active |= self.dispatch_in_flight_queue()?;
+            active |= self.dispatch_quota_queues()?;
+            active |= self.dispatch_sequential_queue()?;
+            active |= self.dispatch_unpauses();
+
             // Block until activity is detected or the timeout passes.
-            self.poll()?;
+            let poll_start = Instant::now();
+            active |= self.poll()?;
+            let poll_elapsed = poll_start.elapsed();
 
             // Collect messages from curl about requests that have completed,
-            // whether successfully or with an error.
-            self.multi.messages(|message| {
-                if let Some(result) = message.result() {
+            // whether successfully or with an error. Results are taken using
+            // their handle so that the detailed message curl writes to the
+            // handle's error buffer is attached to the error.
+            let requests = &self.requests;
+            for pool in iter::once(&self.pool).chain(&self.retired_pools) {
+                pool.multi.messages(|message| {
                     if let Ok(token) = message.token() {
-                        multi_messages.push((token, result));
+                        let result = match requests.get(token) {
+                            Some(request) => message.result_for2(&request.handle),
+                            None => message.result(),
+                        };
+
+                        if let Some(result) = result {
+                            multi_messages.push((token, result));
+                        }
                     }
-                }
-            });
+                });
+            }
+
+            active |= !multi_messages.is_empty();
 
             for (token, result) in multi_messages.drain(..) {
-                self.complete_request(token, result)?;
+                self.complete_request(token, result.map_err(Error::from_any))?;
             }
+
+            active |= self.abort_interrupted_requests()?;
+            active |= self.abort_headers_timed_out_requests()?;
+            self.record_load();
+
+            self.stats.iterations.fetch_add(1);
+            self.stats.add_wait_time(poll_elapsed);
+            self.stats.add_busy_time(turn_start.elapsed() - poll_elapsed);
+
+            self.check_busy_loop(active, poll_elapsed);
         }
 
-        tracing::debug!("agent shutting down");
+        tracing::debug!(stats = ?self.stats, "agent shutting down");
+
+        // Fail any transfers still in progress with a specific error, so that
+        // their consumers can tell why they stopped.
+        for (_, request) in self.requests.iter_mut() {
+            request
+                .handle
+                .get_mut()
+                .set_result(Err(ErrorKind::ClientClosed.into()));
+        }
 
         self.requests.clear();
 
+        for (mut handle, _) in self.in_flight_queue.drain(..) {
+            handle
+                .get_mut()
+                .set_result(Err(ErrorKind::ClientClosed.into()));
+        }
+
+        for queue in self.quotas.values_mut() {
+            for mut handle in queue.waiting.drain(..) {
+                handle
+                    .get_mut()
+                    .set_result(Err(ErrorKind::ClientClosed.into()));
+            }
+        }
+
+        for mut handle in self.sequential.iter_mut().flat_map(|queue| queue.drain(..)) {
+            handle
+                .get_mut()
+                .set_result(Err(ErrorKind::ClientClosed.into()));
+        }
+
+        if let Some(backoff) = self.backoff.as_mut() {
+            for (mut handle, _) in backoff.remove_where(|_| true) {
+                handle
+                    .get_mut()
+                    .set_result(Err(ErrorKind::ClientClosed.into()));
+            }
+        }
+
         Ok(())
     }
 
+    /// If draining, check whether the agent is done and should stop.
+    fn check_drained(&mut self) -> bool {
+        let deadline = match self.drain_deadline {
+            Some(deadline) => deadline,
+            None => return false,
+        };
+
+        if self.requests.is_empty() && !self.has_waiting_requests() && self.request_rx.is_empty()
+        {
+            tracing::debug!("agent drained all requests, shutting down");
+            self.drained.store(true, Ordering::SeqCst);
+        } else if Instant::now() >= deadline {
+            tracing::debug!("agent drain deadline passed, aborting remaining requests");
+        } else {
+            return false;
+        }
+
+        self.close_requested = true;
+
+        true
+    }
+
+    /// Publish how many requests this agent has picked up that have not
+    /// completed yet.
+    fn record_load(&self) {
+        let waiting = self
+            .quotas
+            .values()
+            .map(|queue| queue.waiting.len())
+            .chain(self.sequential.as_ref().map(VecDeque::len))
+            .chain(self.backoff.as_ref().map(Backoff::len))
+            .sum::<usize>();
+
+        self.stats.requests.store(self.requests.len() + waiting);
+    }
+
+    /// Detect and protect against pathological wakeup storms.
+    ///
+    /// Under some conditions (such as with certain curl versions, or a notify
+    /// mechanism that keeps firing) the agent may be woken up continuously
+    /// without any actual work to do, burning CPU in a tight loop. If too many
+    /// such wakeups happen in a row, sleep for an exponentially increasing
+    /// amount of time to break the cycle.
+    fn check_busy_loop(&mut self, active: bool, poll_elapsed: Duration) {
+        if active || poll_elapsed >= SPURIOUS_WAKEUP_DURATION {
+            self.spurious_wakeup_streak = 0;
+            return;
+        }
+
+        self.stats.spurious_wakeups.fetch_add(1);
+        self.spurious_wakeup_streak = self.spurious_wakeup_streak.saturating_add(1);
+
+        if self.spurious_wakeup_streak < SPURIOUS_WAKEUP_THRESHOLD {
+            return;
+        }
+
+        if self.spurious_wakeup_streak == SPURIOUS_WAKEUP_THRESHOLD {
+            tracing::warn!(
+                streak = self.spurious_wakeup_streak,
+                "agent appears to be stuck in a busy loop, backing off"
+            );
+        }
+
+        let exponent = (self.spurious_wakeup_streak - SPURIOUS_WAKEUP_THRESHOLD).min(6);
+        let backoff = (Duration::from_millis(1) * (1 << exponent)).min(MAX_BACKOFF);
+
+        self.stats.backoffs.fetch_add(1);
+        tracing::trace!(?backoff, "backing off after spurious wakeup");
+        thread::sleep(backoff);
+    }
+
     /// Block until activity is detected or a timeout passes.
-    fn poll(&mut self) -> Result<(), Error> {
+    ///
+    /// Returns true if any socket activity occurred.
+    fn poll(&mut self) -> Result<bool, Error> {
         let now = Instant::now();
-        let timeout = self.timer.get_remaining(now);
+        let timeout = iter::once(&self.pool)
+            .chain(&self.retired_pools)
+            .filter_map(|pool| pool.timer.get_remaining(now))
+            .min();
+        let mut active = false;
 
         // Get the latest timeout value from curl that we should use, limited to
         // a maximum we chose.
-        let poll_timeout = timeout.map(|t| t.min(WAIT_TIMEOUT)).unwrap_or(WAIT_TIMEOUT);
+        let mut poll_timeout = timeout
+            .map(|t| t.min(self.wait_timeout))
+            .unwrap_or(self.wait_timeout);
+
+        // Wake up in time to begin requests waiting for a rate limit.
+        if let Some(wait) = self.next_quota_wait() {
+            poll_timeout = poll_timeout.min(wait);
+        }
+
+        // Wake up in time to begin requests waiting to be paced.
+        if let Some(wait) = self.next_pacing_wait() {
+            poll_timeout = poll_timeout.min(wait);
+        }
+
+        // Wake up in time to retry requests after hitting a resource limit.
+        if let Some(wait) = self.backoff.as_ref().and_then(|backoff| backoff.remaining(now)) {
+            poll_timeout = poll_timeout.min(wait);
+        }
+
+        // Wake up in time to abort requests whose headers timeout expires.
+        if let Some(wait) = self.next_headers_deadline() {
+            poll_timeout = poll_timeout.min(wait);
+        }
+
+        // Wake up in time to stop draining at the deadline.
+        if let Some(deadline) = self.drain_deadline {
+            poll_timeout = poll_timeout.min(deadline.saturating_duration_since(now));
+        }
+
+        // If any transfers are waiting to be resumed next turn, don't block.
+        // Curl may already have buffered data for them, in which case no
+        // socket activity would wake us up.
+        if self.transfer_quantum > 0
+            && self
+                .requests
+                .iter()
+                .any(|(_, request)| request.handle.get_ref().is_write_quantum_exhausted())
+        {
+            poll_timeout = Duration::from_millis(0);
+        }
 
         // Block until either an I/O event occurs on a socket, the timeout is
         // reached, or the agent handle interrupts us.
-        if self.selector.poll(poll_timeout)? {
+        let cause = if self.selector.poll(poll_timeout)? {
+            active = true;
+            self.stats.socket_wakeups.fetch_add(1);
+
             // At least one I/O event occurred, handle them.
             for (socket, readable, writable) in self.selector.events() {
                 tracing::trace!(socket, readable, writable, "socket event");
                 let mut events = Events::new();
                 events.input(readable);
                 events.output(writable);
-                self.multi
-                    .action(socket, &events)
-                    .map_err(Error::from_any)?;
+
+                let pool = iter::once(&self.pool)
+                    .chain(&self.retired_pools)
+                    .find(|pool| pool.sockets.contains(&socket));
+
+                if let Some(pool) = pool {
+                    pool.multi
+                        .action(socket, &events)
+                        .map_err(Error::from_any)?;
+                }
             }
+
+            WakeupCause::Socket
+        } else if self.selector.was_notified() {
+            self.stats.notify_wakeups.fetch_add(1);
+            WakeupCause::Notify
+        } else {
+            self.stats.timeout_wakeups.fetch_add(1);
+            WakeupCause::Timeout
+        };
+
+        if let Some(instrumentation) = self.instrumentation.as_ref() {
+            instrumentation.emit(AgentEventKind::AgentWakeup { cause }, None);
         }
 
         // If curl gave us a timeout, check if it has expired.
-        if self.timer.is_expired(now) {
-            self.timer.stop();
-            self.multi.timeout().map_err(Error::from_any)?;
+        for pool in iter::once(&self.pool).chain(&self.retired_pools) {
+            if pool.timer.is_expired(now) {
+                pool.timer.stop();
+                pool.multi.timeout().map_err(Error::from_any)?;
+            }
         }
 
         // Apply any requested socket updates now.
-        while let Ok((socket, events, _)) = self.socket_updates.try_recv() {
+        while let Ok((epoch, socket, events)) = self.socket_updates.try_recv() {
+            let pool = iter::once(&mut self.pool)
+                .chain(&mut self.retired_pools)
+                .find(|pool| pool.epoch == epoch);
+
+            // Updates from a pool that has since been closed are stale.
+            let pool = match pool {
+                Some(pool) => pool,
+                None => continue,
+            };
+
             // Curl is asking us to stop polling this socket.
             if events.remove() {
+                pool.sockets.remove(&socket);
                 self.selector.deregister(socket).unwrap();
             } else {
+                pool.sockets.insert(socket);
+
                 let readable = events.input() || events.input_and_output();
                 let writable = events.output() || events.input_and_output();
 
@@ -549,7 +1994,7 @@ impl AgentContext {
             }
         }
 
-        Ok(())
+        Ok(active)
     }
 }
 
@@ -0,0 +1,102 @@
+use super::timer::Timer;
+use crate::error::Error;
+use async_channel::Sender;
+use curl::multi::{Multi, Socket, SocketEvents};
+use std::{collections::HashSet, sync::Arc};
+
+/// A socket registration update from a pool's multi handle, tagged with the
+/// epoch of the pool it came from.
+pub(crate) type SocketUpdate = (u64, Socket, SocketEvents);
+
+/// Limits applied to every connection pool created by an agent.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct PoolOptions {
+    pub(crate) max_connections: usize,
+    pub(crate) max_connections_per_host: usize,
+    pub(crate) connection_cache_size: usize,
+}
+
+/// A curl multi handle along with the connection cache it owns.
+///
+/// Curl offers no way to flush the connection cache of a multi handle, so
+/// clearing connections is done by replacing the pool with a new one. The old
+/// pool is kept around until the requests still running on it complete, and
+/// its idle connections are closed once it is dropped.
+pub(crate) struct Pool {
+    /// Identifies this pool among all pools created by the same agent.
+    pub(crate) epoch: u64,
+
+    /// The multi handle that executes requests in this pool.
+    pub(crate) multi: Multi,
+
+    /// A timer we use to keep track of curl's timeouts for this pool.
+    pub(crate) timer: Arc<Timer>,
+
+    /// Sockets belonging to this pool that are registered with the selector.
+    pub(crate) sockets: HashSet<Socket>,
+}
+
+impl Pool {
+    pub(crate) fn new(
+        epoch: u64,
+        options: PoolOptions,
+        socket_updates: Sender<SocketUpdate>,
+    ) -> Result<Self, Error> {
+        let mut multi = Multi::new();
+        let timer = Arc::new(Timer::new());
+
+        // Send concurrent requests over a single HTTP/2 connection whenever
+        // possible, rather than opening a connection for each one. This is
+        // the default in newer curl versions, but not in older ones.
+        multi.pipelining(false, true).map_err(Error::from_any)?;
+
+        if options.max_connections > 0 {
+            multi
+                .set_max_total_connections(options.max_connections)
+                .map_err(Error::from_any)?;
+        }
+
+        if options.max_connections_per_host > 0 {
+            multi
+                .set_max_host_connections(options.max_connections_per_host)
+                .map_err(Error::from_any)?;
+        }
+
+        // Only set maxconnects if greater than 0, because 0 actually means unlimited.
+        if options.connection_cache_size > 0 {
+            multi
+                .set_max_connects(options.connection_cache_size)
+                .map_err(Error::from_any)?;
+        }
+
+        multi
+            .socket_function(move |socket, events, _| {
+                let _ = socket_updates.try_send((epoch, socket, events));
+            })
+            .map_err(Error::from_any)?;
+
+        multi
+            .timer_function({
+                let timer = timer.clone();
+
+                move |timeout| match timeout {
+                    Some(timeout) => {
+                        timer.start(timeout);
+                        true
+                    }
+                    None => {
+                        timer.stop();
+                        true
+                    }
+                }
+            })
+            .map_err(Error::from_any)?;
+
+        Ok(Self {
+            epoch,
+            multi,
+            timer,
+            sockets: HashSet::new(),
+        })
+    }
+}
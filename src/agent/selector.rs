@@ -1,3 +1,4 @@
+use crossbeam_utils::atomic::AtomicCell;
 use curl::multi::Socket;
 use polling::{Event, Poller};
 use std::{
@@ -21,6 +22,11 @@ pub(crate) struct Selector {
     /// This is the poller we use to poll for socket activity!
     poller: Arc<Poller>,
 
+    /// Set when a notification has been posted to the poller that has not been
+    /// consumed yet. Used to coalesce multiple wakeups into a single
+    /// notification.
+    notified: Arc<AtomicCell<bool>>,
+
     /// All of the sockets that we have been asked to keep track of.
     sockets: HashMap<Socket, Registration, BuildHasherDefault<IntHasher>>,
 
@@ -34,6 +40,9 @@ pub(crate) struct Selector {
 
     /// Incrementing counter used to deduplicate registration operations.
     tick: usize,
+
+    /// Whether the most recent call to `poll` consumed a notification.
+    was_notified: bool,
 }
 
 /// Information stored about each registered socket.
@@ -48,21 +57,36 @@ impl Selector {
     pub(crate) fn new() -> io::Result<Self> {
         Ok(Self {
             poller: Arc::new(Poller::new()?),
+            notified: Arc::new(AtomicCell::new(false)),
             sockets: HashMap::with_hasher(Default::default()),
             bad_sockets: HashSet::with_hasher(Default::default()),
             events: Vec::new(),
             tick: 0,
+            was_notified: false,
         })
     }
 
     /// Get a task waker that will interrupt this selector whenever it is
     /// waiting for activity.
+    ///
+    /// Notifications are delivered using the poller's native mechanism (an
+    /// eventfd or pipe on Unix, or a completion packet posted to the I/O
+    /// completion port on Windows). Repeated wakeups before the selector has
+    /// had a chance to wake up are coalesced into a single notification, so
+    /// that a burst of wakeups does not flood the poller.
     pub(crate) fn waker(&self) -> Waker {
         waker_fn::waker_fn({
             let poller_ref = self.poller.clone();
+            let notified_ref = self.notified.clone();
 
             move || {
-                let _ = poller_ref.notify();
+                if !notified_ref.swap(true) {
+                    if let Err(e) = poller_ref.notify() {
+                        // Allow a future wakeup to try again.
+                        notified_ref.store(false);
+                        tracing::debug!(error = ?e, "failed to notify poller");
+                    }
+                }
             }
         })
     }
@@ -187,7 +211,14 @@ impl Selector {
 
         // Block until either an I/O event occurs on a socket, the timeout is
         // reached, or the agent handle interrupts us.
-        match self.poller.wait(&mut self.events, Some(timeout)) {
+        let result = self.poller.wait(&mut self.events, Some(timeout));
+
+        // Any pending notification has now been consumed, so allow wakers to
+        // post a new one. This must happen before the caller checks for new
+        // work so that no wakeups are lost.
+        self.was_notified = self.notified.swap(false);
+
+        match result {
             Ok(0) => Ok(false),
             Ok(_) => Ok(true),
             Err(e) if e.kind() == io::ErrorKind::Interrupted => Ok(false),
@@ -195,6 +226,12 @@ impl Selector {
         }
     }
 
+    /// Returns true if the most recent call to `poll` was interrupted by a
+    /// waker, or would have been if no socket events had occurred.
+    pub(crate) fn was_notified(&self) -> bool {
+        self.was_notified
+    }
+
     /// Get an iterator over the socket events that occurred during the most
     /// recent call to `poll`.
     pub(crate) fn events(&self) -> impl Iterator<Item = (Socket, bool, bool)> + '_ {
@@ -305,7 +342,7 @@ impl Hasher for IntHasher {
             }
         }
 
-        (&mut self.0[..bytes.len()]).copy_from_slice(bytes);
+        self.0[..bytes.len()].copy_from_slice(bytes);
     }
 
     #[inline]
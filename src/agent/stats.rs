@@ -0,0 +1,168 @@
+use crossbeam_utils::atomic::AtomicCell;
+use std::{fmt, time::Duration};
+
+/// Statistics about the event loop of a client's background agent thread.
+///
+/// These are useful for tuning options like
+/// [`HttpClientBuilder::agent_poll_timeout`](crate::HttpClientBuilder::agent_poll_timeout)
+/// and for diagnosing problems like the agent burning CPU while there is
+/// little to do, or requests taking longer than expected because the agent
+/// is slow to react.
+///
+/// Statistics are counted from when the agent was started. Use
+/// [`HttpClient::agent_stats`](crate::HttpClient::agent_stats) to get the
+/// current statistics of a client.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct AgentStats {
+    iterations: u64,
+    notify_wakeups: u64,
+    timeout_wakeups: u64,
+    socket_wakeups: u64,
+    spurious_wakeups: u64,
+    backoffs: u64,
+    busy_time: Duration,
+    wait_time: Duration,
+    concurrency_limit: Option<usize>,
+}
+
+impl AgentStats {
+    /// Number of turns of the event loop completed so far.
+    pub fn iterations(&self) -> u64 {
+        self.iterations
+    }
+
+    /// Number of times the agent stopped waiting because it was notified,
+    /// such as about a new request or about a response body being read.
+    pub fn notify_wakeups(&self) -> u64 {
+        self.notify_wakeups
+    }
+
+    /// Number of times the agent stopped waiting because a timeout passed,
+    /// either one requested by curl or the poll timeout.
+    pub fn timeout_wakeups(&self) -> u64 {
+        self.timeout_wakeups
+    }
+
+    /// Number of times the agent stopped waiting because a socket became
+    /// ready to read from or write to.
+    pub fn socket_wakeups(&self) -> u64 {
+        self.socket_wakeups
+    }
+
+    /// Number of turns of the event loop that stopped waiting early without
+    /// doing any work.
+    ///
+    /// A high number relative to [`AgentStats::iterations`] means that the
+    /// agent is using CPU without making progress.
+    pub fn spurious_wakeups(&self) -> u64 {
+        self.spurious_wakeups
+    }
+
+    /// Number of times the agent slept to escape from a busy loop of
+    /// spurious wakeups.
+    pub fn backoffs(&self) -> u64 {
+        self.backoffs
+    }
+
+    /// Total time spent doing work, such as driving transfers with curl.
+    pub fn busy_time(&self) -> Duration {
+        self.busy_time
+    }
+
+    /// Total time spent waiting for new requests or for activity on the
+    /// active requests.
+    pub fn wait_time(&self) -> Duration {
+        self.wait_time
+    }
+
+    /// Current number of requests that may be in progress at once, if the
+    /// limit is adjusted using
+    /// [`HttpClientBuilder::adaptive_concurrency`](crate::HttpClientBuilder::adaptive_concurrency).
+    ///
+    /// For a client with several agent threads, this is the sum of the limits
+    /// of all of them.
+    pub fn concurrency_limit(&self) -> Option<usize> {
+        self.concurrency_limit
+    }
+
+    /// Add the statistics of another agent to these.
+    pub(crate) fn add(&mut self, other: &Self) {
+        self.iterations += other.iterations;
+        self.notify_wakeups += other.notify_wakeups;
+        self.timeout_wakeups += other.timeout_wakeups;
+        self.socket_wakeups += other.socket_wakeups;
+        self.spurious_wakeups += other.spurious_wakeups;
+        self.backoffs += other.backoffs;
+        self.busy_time += other.busy_time;
+        self.wait_time += other.wait_time;
+        self.concurrency_limit = match (self.concurrency_limit, other.concurrency_limit) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        };
+    }
+}
+
+impl fmt::Debug for AgentStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AgentStats")
+            .field("iterations", &self.iterations)
+            .field("notify_wakeups", &self.notify_wakeups)
+            .field("timeout_wakeups", &self.timeout_wakeups)
+            .field("socket_wakeups", &self.socket_wakeups)
+            .field("spurious_wakeups", &self.spurious_wakeups)
+            .field("backoffs", &self.backoffs)
+            .field("busy_time", &self.busy_time)
+            .field("wait_time", &self.wait_time)
+            .field("concurrency_limit", &self.concurrency_limit)
+            .finish()
+    }
+}
+
+/// Counters updated by an agent thread that can be read from other threads.
+#[derive(Default)]
+pub(crate) struct StatsRecorder {
+    pub(crate) iterations: AtomicCell<u64>,
+    pub(crate) notify_wakeups: AtomicCell<u64>,
+    pub(crate) timeout_wakeups: AtomicCell<u64>,
+    pub(crate) socket_wakeups: AtomicCell<u64>,
+    pub(crate) spurious_wakeups: AtomicCell<u64>,
+    pub(crate) backoffs: AtomicCell<u64>,
+    pub(crate) busy_time: AtomicCell<Duration>,
+    pub(crate) wait_time: AtomicCell<Duration>,
+    pub(crate) concurrency_limit: AtomicCell<Option<usize>>,
+
+    /// Number of requests the agent has picked up that have not completed
+    /// yet, including requests waiting for a quota.
+    pub(crate) requests: AtomicCell<usize>,
+}
+
+impl StatsRecorder {
+    pub(crate) fn add_busy_time(&self, duration: Duration) {
+        self.busy_time.store(self.busy_time.load() + duration);
+    }
+
+    pub(crate) fn add_wait_time(&self, duration: Duration) {
+        self.wait_time.store(self.wait_time.load() + duration);
+    }
+
+    /// Take a snapshot of the current values.
+    pub(crate) fn snapshot(&self) -> AgentStats {
+        AgentStats {
+            iterations: self.iterations.load(),
+            notify_wakeups: self.notify_wakeups.load(),
+            timeout_wakeups: self.timeout_wakeups.load(),
+            socket_wakeups: self.socket_wakeups.load(),
+            spurious_wakeups: self.spurious_wakeups.load(),
+            backoffs: self.backoffs.load(),
+            busy_time: self.busy_time.load(),
+            wait_time: self.wait_time.load(),
+            concurrency_limit: self.concurrency_limit.load(),
+        }
+    }
+}
+
+impl fmt::Debug for StatsRecorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.snapshot().fmt(f)
+    }
+}
@@ -0,0 +1,248 @@
+//! Detection of agent threads that have crashed or stopped making progress.
+//!
+//! Requests being executed by an agent can only make progress while the agent
+//! thread is running its event loop. If the agent thread panics, or gets stuck
+//! somewhere and never returns to its event loop, anyone waiting on one of its
+//! requests would otherwise wait forever. Each agent shares a [`Liveness`]
+//! with the requests it executes so that waiters can be woken up with an error
+//! instead.
+//!
+//! A hang may also just be a callback that took a very long time, so an agent
+//! that makes progress again is no longer considered hung. Only the requests
+//! that were waiting on it in the meantime fail.
+
+use crossbeam_utils::atomic::AtomicCell;
+use event_listener::{Event, EventListener};
+use once_cell::sync::Lazy;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+        Mutex,
+        Weak,
+    },
+    task::{Context, Poll},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Minimum amount of time an agent with active requests may go without
+/// completing a turn of its event loop before it is considered hung.
+const HANG_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often the watchdog checks on agents.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Agents being watched by the watchdog thread.
+static WATCHED: Lazy<Mutex<Vec<Weak<Liveness>>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// ID of the process the watchdog thread was spawned in, if any.
+static WATCHDOG_PID: Lazy<Mutex<Option<u32>>> = Lazy::new(|| Mutex::new(None));
+
+/// Liveness state of a single agent thread.
+#[derive(Debug)]
+pub(crate) struct Liveness {
+    /// When the agent last reported making progress. Set to `None` while the
+    /// agent is idle waiting for new requests, since there is nothing that
+    /// could be stuck.
+    heartbeat: AtomicCell<Option<Instant>>,
+
+    /// How long the agent may go without reporting progress before it is
+    /// considered hung.
+    hang_timeout: Duration,
+
+    /// Set once the agent is known to have crashed.
+    crashed: AtomicBool,
+
+    /// Set while the agent has gone too long without making progress.
+    hung: AtomicBool,
+
+    /// Notified when the agent crashes or hangs.
+    event: Event,
+}
+
+impl Liveness {
+    /// Create a new liveness tracker for an agent that wakes up at least once
+    /// every `wait_timeout`.
+    pub(crate) fn new(wait_timeout: Duration) -> Self {
+        Self {
+            heartbeat: AtomicCell::new(None),
+            hang_timeout: HANG_TIMEOUT.max(wait_timeout * 2),
+            crashed: AtomicBool::new(false),
+            hung: AtomicBool::new(false),
+            event: Event::new(),
+        }
+    }
+
+    /// Report that the agent is making progress.
+    pub(crate) fn beat(&self) {
+        self.heartbeat.store(Some(Instant::now()));
+        self.resume();
+    }
+
+    /// Report that the agent is idle and is about to wait for new requests.
+    pub(crate) fn idle(&self) {
+        self.heartbeat.store(None);
+        self.resume();
+    }
+
+    /// Clear the hung state after the agent made progress again.
+    fn resume(&self) {
+        if self.hung.load(Ordering::SeqCst) && self.hung.swap(false, Ordering::SeqCst) {
+            tracing::warn!("agent thread is making progress again");
+        }
+    }
+
+    /// Mark the agent as crashed, waking up anything waiting on it.
+    pub(crate) fn mark_crashed(&self) {
+        if !self.crashed.swap(true, Ordering::SeqCst) {
+            self.event.notify(usize::MAX);
+        }
+    }
+
+    /// Returns true if the agent has crashed or is currently hung.
+    pub(crate) fn is_crashed(&self) -> bool {
+        self.crashed.load(Ordering::SeqCst) || self.hung.load(Ordering::SeqCst)
+    }
+
+    /// Poll for the agent to crash or hang, using the given listener slot to
+    /// register for a wakeup.
+    pub(crate) fn poll_crashed(
+        &self,
+        listener: &mut Option<EventListener>,
+        cx: &mut Context<'_>,
+    ) -> Poll<()> {
+        loop {
+            if self.is_crashed() {
+                *listener = None;
+                return Poll::Ready(());
+            }
+
+            match listener {
+                Some(l) => match Pin::new(l).poll(cx) {
+                    Poll::Ready(()) => *listener = None,
+                    Poll::Pending => return Poll::Pending,
+                },
+                // Check the flag again after registering, in case it was set
+                // in between.
+                None => *listener = Some(self.event.listen()),
+            }
+        }
+    }
+
+    /// Wait for the agent to crash or hang.
+    pub(crate) async fn crashed(&self) {
+        let mut listener = None;
+
+        futures_lite::future::poll_fn(|cx| self.poll_crashed(&mut listener, cx)).await
+    }
+
+    /// Check whether the agent has gone too long without making progress, and
+    /// if so, mark it as hung until it does.
+    fn check(&self, now: Instant) {
+        if let Some(heartbeat) = self.heartbeat.load() {
+            let elapsed = now.saturating_duration_since(heartbeat);
+
+            if elapsed > self.hang_timeout && !self.hung.swap(true, Ordering::SeqCst) {
+                tracing::error!(
+                    "agent thread has not made progress in {:?}, failing requests waiting on it",
+                    elapsed
+                );
+                self.event.notify(usize::MAX);
+            }
+        }
+    }
+}
+
+/// Start watching the given agent for hangs.
+pub(crate) fn watch(liveness: &Arc<Liveness>) {
+    let pid = std::process::id();
+    let mut watchdog_pid = WATCHDOG_PID.lock().unwrap();
+
+    // A process created with `fork` only inherits the thread that called it,
+    // so a watchdog thread spawned before forking only exists in the parent.
+    if *watchdog_pid != Some(pid) {
+        thread::Builder::new()
+            .name(String::from("isahc-watchdog"))
+            .spawn(run)
+            .expect("failed to spawn watchdog thread");
+
+        *watchdog_pid = Some(pid);
+    }
+
+    WATCHED.lock().unwrap().push(Arc::downgrade(liveness));
+}
+
+/// Main loop of the watchdog thread.
+fn run() {
+    loop {
+        thread::sleep(CHECK_INTERVAL);
+
+        let now = Instant::now();
+
+        // Stop watching agents that have gone away or that have crashed. Hung
+        // agents are still watched, since they may recover.
+        WATCHED
+            .lock()
+            .unwrap()
+            .retain(|liveness| match liveness.upgrade() {
+                Some(liveness) => {
+                    liveness.check(now);
+                    !liveness.crashed.load(Ordering::SeqCst)
+                }
+                None => false,
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_lite::future::block_on;
+
+    #[test]
+    fn idle_agent_is_never_hung() {
+        let liveness = Liveness::new(Duration::from_secs(1));
+
+        liveness.check(Instant::now() + Duration::from_secs(3600));
+
+        assert!(!liveness.is_crashed());
+    }
+
+    #[test]
+    fn stalled_agent_is_marked_hung() {
+        let liveness = Arc::new(Liveness::new(Duration::from_secs(1)));
+        liveness.beat();
+
+        liveness.check(Instant::now() + Duration::from_secs(5));
+        assert!(!liveness.is_crashed());
+
+        let waiter = thread::spawn({
+            let liveness = liveness.clone();
+            move || block_on(liveness.crashed())
+        });
+
+        liveness.check(Instant::now() + HANG_TIMEOUT * 2);
+        assert!(liveness.is_crashed());
+
+        waiter.join().unwrap();
+    }
+
+    #[test]
+    fn hung_agent_recovers_once_it_makes_progress() {
+        let liveness = Liveness::new(Duration::from_secs(1));
+        liveness.beat();
+
+        liveness.check(Instant::now() + HANG_TIMEOUT * 2);
+        assert!(liveness.is_crashed());
+
+        liveness.beat();
+        assert!(!liveness.is_crashed());
+
+        liveness.mark_crashed();
+        liveness.beat();
+        assert!(liveness.is_crashed());
+    }
+}
@@ -0,0 +1,657 @@
+//! Answering authentication challenges sent by servers and proxies.
+
+use super::{store, Credentials};
+use crate::{
+    body::AsyncBody,
+    config::request::RequestConfig,
+    error::Error,
+    handler::RequestBody,
+    hash::{base64_encode, md5},
+    interceptor::{Context, Interceptor, InterceptorFuture},
+    request::RequestExt,
+};
+use http::{
+    header::{
+        HeaderName,
+        HeaderValue,
+        AUTHORIZATION,
+        PROXY_AUTHENTICATE,
+        PROXY_AUTHORIZATION,
+        WWW_AUTHENTICATE,
+    },
+    Request,
+    StatusCode,
+};
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::SystemTime,
+};
+
+/// An authentication challenge sent by a server in a `WWW-Authenticate`
+/// header, or by a proxy in a `Proxy-Authenticate` header.
+#[derive(Clone, Debug)]
+pub struct Challenge {
+    scheme: String,
+    token68: Option<String>,
+    params: Vec<(String, String)>,
+    proxy: bool,
+    origin: Option<String>,
+}
+
+impl Challenge {
+    /// Get the authentication scheme being requested, such as `Basic` or
+    /// `Digest`.
+    ///
+    /// Schemes are case-insensitive, so compare them using
+    /// [`str::eq_ignore_ascii_case`].
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    /// Get the value of a parameter of the challenge by name. Parameter names
+    /// are case-insensitive.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Iterate over all parameters of the challenge, in the order the server
+    /// sent them.
+    pub fn params(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.params
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Get the value of the `realm` parameter, if present.
+    pub fn realm(&self) -> Option<&str> {
+        self.param("realm")
+    }
+
+    /// Get the opaque token sent with the challenge in place of parameters,
+    /// if the scheme uses one.
+    pub fn token68(&self) -> Option<&str> {
+        self.token68.as_deref()
+    }
+
+    /// Returns true if this challenge was sent by a proxy rather than the
+    /// origin server.
+    ///
+    /// Answers to proxy challenges are sent in a `Proxy-Authorization` header
+    /// to the proxy and not to the server.
+    pub fn is_proxy(&self) -> bool {
+        self.proxy
+    }
+
+    /// Get the origin of the server or proxy that sent this challenge, such
+    /// as `https://example.org:443`, if known.
+    ///
+    /// The origin of a proxy is only known if the proxy was configured
+    /// explicitly using [`Configurable::proxy`](crate::config::Configurable::proxy).
+    pub fn origin(&self) -> Option<&str> {
+        self.origin.as_deref()
+    }
+
+    /// Parse all challenges contained in an authenticate header value.
+    ///
+    /// Malformed input stops parsing, keeping any challenges parsed so far.
+    pub(crate) fn parse_all(value: &str, proxy: bool) -> Vec<Self> {
+        let mut parser = Parser {
+            input: value.as_bytes(),
+            pos: 0,
+        };
+        let mut challenges = Vec::new();
+
+        loop {
+            while parser.eat(b',') || parser.eat(b' ') || parser.eat(b'\t') {}
+
+            let scheme = parser.token();
+
+            if scheme.is_empty() {
+                break;
+            }
+
+            let mut challenge = Challenge {
+                scheme,
+                token68: None,
+                params: Vec::new(),
+                proxy,
+                origin: None,
+            };
+
+            parser.skip_whitespace();
+
+            let mut separated = false;
+
+            while let Some(param) = parser.param() {
+                challenge.params.push(param);
+                parser.skip_whitespace();
+                separated = parser.eat(b',');
+
+                if !separated {
+                    break;
+                }
+            }
+
+            if challenge.params.is_empty() {
+                challenge.token68 = parser.token68();
+            }
+
+            challenges.push(challenge);
+            parser.skip_whitespace();
+
+            if !separated && !matches!(parser.peek(), None | Some(b',')) {
+                break;
+            }
+        }
+
+        challenges
+    }
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.eat(b' ') || self.eat(b'\t') {}
+    }
+
+    fn take_while(&mut self, f: impl Fn(u8) -> bool) -> String {
+        let start = self.pos;
+
+        while let Some(byte) = self.peek() {
+            if !f(byte) {
+                break;
+            }
+
+            self.pos += 1;
+        }
+
+        String::from_utf8_lossy(&self.input[start..self.pos]).into_owned()
+    }
+
+    fn token(&mut self) -> String {
+        self.take_while(is_tchar)
+    }
+
+    /// Parse an `auth-param`, or leave the position unchanged if there isn't
+    /// one.
+    fn param(&mut self) -> Option<(String, String)> {
+        let start = self.pos;
+
+        self.skip_whitespace();
+
+        let name = self.token();
+
+        self.skip_whitespace();
+
+        if name.is_empty() || !self.eat(b'=') {
+            self.pos = start;
+            return None;
+        }
+
+        self.skip_whitespace();
+
+        let value = if self.eat(b'"') {
+            let mut value = Vec::new();
+
+            loop {
+                match self.peek() {
+                    Some(b'"') => {
+                        self.pos += 1;
+                        break;
+                    }
+                    Some(b'\\') if self.pos + 1 < self.input.len() => {
+                        value.push(self.input[self.pos + 1]);
+                        self.pos += 2;
+                    }
+                    Some(byte) => {
+                        value.push(byte);
+                        self.pos += 1;
+                    }
+                    // Unterminated quoted string.
+                    None => {
+                        self.pos = start;
+                        return None;
+                    }
+                }
+            }
+
+            String::from_utf8_lossy(&value).into_owned()
+        } else {
+            let value = self.token();
+
+            if value.is_empty() {
+                self.pos = start;
+                return None;
+            }
+
+            value
+        };
+
+        Some((name, value))
+    }
+
+    /// Parse a `token68`, or leave the position unchanged if there isn't one.
+    fn token68(&mut self) -> Option<String> {
+        let start = self.pos;
+        let mut token = self.take_while(|b| b.is_ascii_alphanumeric() || b"-._~+/".contains(&b));
+
+        if token.is_empty() {
+            return None;
+        }
+
+        token.push_str(&self.take_while(|b| b == b'='));
+        self.skip_whitespace();
+
+        match self.peek() {
+            None | Some(b',') => Some(token),
+            _ => {
+                self.pos = start;
+                None
+            }
+        }
+    }
+}
+
+fn is_tchar(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte)
+}
+
+/// Computes credentials in response to authentication challenges.
+///
+/// When a response has the status `401 Unauthorized` or `407 Proxy
+/// Authentication Required`, the challenges sent by the server or proxy are
+/// passed to the configured authenticator. If it returns a value, the request
+/// is transparently sent once more with that value in an `Authorization` (or
+/// `Proxy-Authorization`) header. If it returns `None`, the original response
+/// is returned as-is.
+///
+/// The request is only retried if its body can be sent again. Bodies read
+/// from arbitrary streams can't be rewound, and in that case the original
+/// response is returned instead.
+///
+/// [`Credentials`] implement this trait by answering `Digest` or `Basic`
+/// challenges from the origin server, preferring `Digest`. Closures can be
+/// used to answer other schemes.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{auth::*, prelude::*, HttpClient};
+///
+/// let client = HttpClient::builder()
+///     .authenticator(|challenges: &[Challenge], _: &isahc::Request<()>| {
+///         challenges
+///             .iter()
+///             .find(|c| c.scheme().eq_ignore_ascii_case("ApiKey"))
+///             .map(|_| String::from("ApiKey hunter2"))
+///     })
+///     .build()?;
+/// # Ok::<(), isahc::Error>(())
+/// ```
+pub trait Authenticator: Send + Sync + 'static {
+    /// Compute the value of an authorization header answering one of the
+    /// given challenges for a request, or `None` if none of the challenges
+    /// are supported.
+    ///
+    /// The request passed contains the method, URI, and headers of the
+    /// request that was challenged.
+    fn authenticate(&self, challenges: &[Challenge], request: &Request<()>) -> Option<String>;
+}
+
+impl<F> Authenticator for F
+where
+    F: Fn(&[Challenge], &Request<()>) -> Option<String> + Send + Sync + 'static,
+{
+    fn authenticate(&self, challenges: &[Challenge], request: &Request<()>) -> Option<String> {
+        self(challenges, request)
+    }
+}
+
+impl Authenticator for Credentials {
+    fn authenticate(&self, challenges: &[Challenge], request: &Request<()>) -> Option<String> {
+        answer_with(challenges, request, |challenge| {
+            if challenge.is_proxy() {
+                None
+            } else {
+                Some(self.clone())
+            }
+        })
+    }
+}
+
+/// Answer the first `Digest` or `Basic` challenge, preferring `Digest`, for
+/// which credentials can be found.
+pub(super) fn answer_with(
+    challenges: &[Challenge],
+    request: &Request<()>,
+    credentials_for: impl Fn(&Challenge) -> Option<Credentials>,
+) -> Option<String> {
+    let uri = request
+        .uri()
+        .path_and_query()
+        .map(|p| p.as_str())
+        .unwrap_or("/");
+
+    challenges
+        .iter()
+        .filter(|c| c.scheme().eq_ignore_ascii_case("Digest"))
+        .find_map(|c| {
+            let credentials = credentials_for(c)?;
+
+            digest_response(&credentials, c, request.method().as_str(), uri, &cnonce())
+        })
+        .or_else(|| {
+            challenges
+                .iter()
+                .filter(|c| c.scheme().eq_ignore_ascii_case("Basic"))
+                .find_map(|c| credentials_for(c).map(|credentials| basic_response(&credentials)))
+        })
+}
+
+fn basic_response(credentials: &Credentials) -> String {
+    let plain = format!("{}:{}", credentials.username, credentials.password);
+
+    format!("Basic {}", base64_encode(plain.as_bytes()))
+}
+
+/// Compute a response to a Digest challenge as described in RFC 2617.
+///
+/// Only the `MD5` and `MD5-sess` algorithms and the `auth` quality of
+/// protection are supported.
+fn digest_response(
+    credentials: &Credentials,
+    challenge: &Challenge,
+    method: &str,
+    uri: &str,
+    cnonce: &str,
+) -> Option<String> {
+    let realm = challenge.realm().unwrap_or_default();
+    let nonce = challenge.param("nonce")?;
+    let algorithm = challenge.param("algorithm").unwrap_or("MD5");
+
+    let mut ha1 = md5::hex_digest(
+        format!(
+            "{}:{}:{}",
+            credentials.username, realm, credentials.password
+        )
+        .as_bytes(),
+    );
+
+    if algorithm.eq_ignore_ascii_case("MD5-sess") {
+        ha1 = md5::hex_digest(format!("{}:{}:{}", ha1, nonce, cnonce).as_bytes());
+    } else if !algorithm.eq_ignore_ascii_case("MD5") {
+        return None;
+    }
+
+    let ha2 = md5::hex_digest(format!("{}:{}", method, uri).as_bytes());
+
+    // Servers that don't send qop are following the older RFC 2069.
+    let qop = match challenge.param("qop") {
+        Some(qop) => Some(qop.split(',').map(str::trim).find(|q| *q == "auth")?),
+        None => None,
+    };
+
+    let response = match qop {
+        Some(qop) => md5::hex_digest(
+            format!("{}:{}:00000001:{}:{}:{}", ha1, nonce, cnonce, qop, ha2).as_bytes(),
+        ),
+        None => md5::hex_digest(format!("{}:{}:{}", ha1, nonce, ha2).as_bytes()),
+    };
+
+    let mut value = format!(
+        "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", algorithm={}, response=\"{}\"",
+        quote(&credentials.username),
+        quote(realm),
+        quote(nonce),
+        quote(uri),
+        algorithm,
+        response,
+    );
+
+    if let Some(qop) = qop {
+        value.push_str(&format!(
+            ", qop={}, nc=00000001, cnonce=\"{}\"",
+            qop, cnonce
+        ));
+    }
+
+    if let Some(opaque) = challenge.param("opaque") {
+        value.push_str(&format!(", opaque=\"{}\"", quote(opaque)));
+    }
+
+    Some(value)
+}
+
+/// Escape a string for use inside a quoted-string.
+fn quote(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Generate a client nonce for Digest authentication.
+fn cnonce() -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    md5::hex_digest(format!("{}:{}:{:p}", nanos, count, &COUNTER).as_bytes())[..16].to_owned()
+}
+
+/// A type-erased authenticator that can be stored in a request
+/// configuration.
+#[derive(Clone)]
+pub(crate) struct AuthenticatorObj(Arc<dyn Authenticator>);
+
+impl AuthenticatorObj {
+    pub(crate) fn new(authenticator: impl Authenticator) -> Self {
+        Self(Arc::new(authenticator))
+    }
+}
+
+impl fmt::Debug for AuthenticatorObj {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Authenticator")
+    }
+}
+
+/// Interceptor that answers authentication challenges using a configured
+/// authenticator.
+pub(crate) struct AuthenticatorInterceptor;
+
+impl Interceptor for AuthenticatorInterceptor {
+    type Err = Error;
+
+    fn intercept<'a>(
+        &'a self,
+        request: Request<AsyncBody>,
+        ctx: Context<'a>,
+    ) -> InterceptorFuture<'a, Self::Err> {
+        Box::pin(async move {
+            let config = request.extensions().get::<RequestConfig>();
+            let authenticator = match config.and_then(|config| config.authenticator.clone()) {
+                Some(authenticator) => authenticator,
+                None => return ctx.send(request).await,
+            };
+            let proxy_uri = config.and_then(|config| config.proxy.clone()).flatten();
+
+            // Preserve a clone of the request before sending it.
+            let request_builder = request.to_builder();
+            let mut head = Request::new(());
+            *head.method_mut() = request.method().clone();
+            *head.uri_mut() = request.uri().clone();
+            *head.headers_mut() = request.headers().clone();
+
+            let mut response = ctx.send(request).await?;
+
+            let (authenticate, authorization): (HeaderName, HeaderName) = match response.status() {
+                StatusCode::UNAUTHORIZED => (WWW_AUTHENTICATE, AUTHORIZATION),
+                StatusCode::PROXY_AUTHENTICATION_REQUIRED => {
+                    (PROXY_AUTHENTICATE, PROXY_AUTHORIZATION)
+                }
+                _ => return Ok(response),
+            };
+            let proxy = authorization == PROXY_AUTHORIZATION;
+            let origin = if proxy {
+                proxy_uri.as_ref().and_then(store::origin)
+            } else {
+                store::origin(head.uri())
+            };
+
+            let challenges = response
+                .headers()
+                .get_all(&authenticate)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| Challenge::parse_all(value, proxy))
+                .map(|mut challenge| {
+                    challenge.origin = origin.clone();
+                    challenge
+                })
+                .collect::<Vec<_>>();
+
+            if challenges.is_empty() {
+                return Ok(response);
+            }
+
+            let value = match authenticator.0.authenticate(&challenges, &head) {
+                Some(value) => HeaderValue::from_str(&value).map_err(http::Error::from)?,
+                None => return Ok(response),
+            };
+
+            // Grab the request body back from the internal handler so that we
+            // can send it again, if possible.
+            let mut request_body = response
+                .extensions_mut()
+                .remove::<RequestBody>()
+                .map(|v| v.0)
+                .unwrap_or_default();
+
+            if !request_body.reset() {
+                tracing::debug!("request body is not rewindable, not retrying with credentials");
+                return Ok(response);
+            }
+
+            let mut request = request_builder.body(request_body)?;
+            request.headers_mut().insert(authorization, value);
+
+            ctx.send(request).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_single_challenge() {
+        let challenges = Challenge::parse_all(r#"Basic realm="simple", charset=UTF-8"#, false);
+
+        assert_eq!(challenges.len(), 1);
+        assert_eq!(challenges[0].scheme(), "Basic");
+        assert_eq!(challenges[0].realm(), Some("simple"));
+        assert_eq!(challenges[0].param("Charset"), Some("UTF-8"));
+        assert_eq!(challenges[0].token68(), None);
+    }
+
+    #[test]
+    fn parse_multiple_challenges() {
+        let challenges = Challenge::parse_all(
+            r#"Newauth realm="apps", type=1, title="Login to \"apps\"", Basic realm="simple", Negotiate abc123==, Bearer"#,
+            false,
+        );
+
+        assert_eq!(challenges.len(), 4);
+        assert_eq!(challenges[0].scheme(), "Newauth");
+        assert_eq!(
+            challenges[0].params().collect::<Vec<_>>(),
+            vec![
+                ("realm", "apps"),
+                ("type", "1"),
+                ("title", "Login to \"apps\"")
+            ]
+        );
+        assert_eq!(challenges[1].scheme(), "Basic");
+        assert_eq!(challenges[1].realm(), Some("simple"));
+        assert_eq!(challenges[2].scheme(), "Negotiate");
+        assert_eq!(challenges[2].token68(), Some("abc123=="));
+        assert_eq!(challenges[3].scheme(), "Bearer");
+        assert_eq!(challenges[3].params().count(), 0);
+    }
+
+    #[test]
+    fn parse_malformed_challenge() {
+        let challenges = Challenge::parse_all(r#"Basic realm="unterminated"#, false);
+
+        assert_eq!(challenges.len(), 1);
+        assert_eq!(challenges[0].realm(), None);
+    }
+
+    #[test]
+    fn basic_encoding() {
+        assert_eq!(
+            basic_response(&Credentials::new("Aladdin", "open sesame")),
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="
+        );
+        assert_eq!(base64_encode(b"a"), "YQ==");
+        assert_eq!(base64_encode(b"ab"), "YWI=");
+    }
+
+    #[test]
+    fn digest_rfc2617_example() {
+        let challenge = &Challenge::parse_all(
+            r#"Digest realm="testrealm@host.com", qop="auth,auth-int", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", opaque="5ccc069c403ebaf9f0171e9517f40e41""#,
+            false,
+        )[0];
+
+        let value = digest_response(
+            &Credentials::new("Mufasa", "Circle Of Life"),
+            challenge,
+            "GET",
+            "/dir/index.html",
+            "0a4f113b",
+        )
+        .unwrap();
+
+        assert!(value.contains(r#"response="6629fae49393a05397450978507c4ef1""#));
+        assert!(value.contains("qop=auth, nc=00000001, cnonce=\"0a4f113b\""));
+        assert!(value.contains(r#"opaque="5ccc069c403ebaf9f0171e9517f40e41""#));
+    }
+
+    #[test]
+    fn digest_unsupported_algorithm() {
+        let challenge = &Challenge::parse_all(
+            r#"Digest realm="r", nonce="n", algorithm=SHA-512-256"#,
+            false,
+        )[0];
+
+        assert!(digest_response(&Credentials::new("a", "b"), challenge, "GET", "/", "c").is_none());
+    }
+}
@@ -0,0 +1,148 @@
+//! Looking up credentials in the operating system keychain.
+//!
+//! Rather than linking to platform libraries, the command-line tools that
+//! ship with each platform are used: `security` on macOS and `secret-tool`
+//! (from libsecret) elsewhere.
+
+use super::Credentials;
+use std::process::{Command, Stdio};
+
+/// Look up credentials for an origin of the form `scheme://host:port`.
+pub(crate) fn lookup(origin: &str) -> Option<Credentials> {
+    let (scheme, authority) = origin.split_once("://")?;
+    let (host, port) = authority.rsplit_once(':')?;
+
+    let credentials = query(scheme, host, port);
+
+    if credentials.is_none() {
+        tracing::debug!("no credentials found in keychain for {}", origin);
+    }
+
+    credentials
+}
+
+#[cfg(target_os = "macos")]
+fn query(scheme: &str, host: &str, port: &str) -> Option<Credentials> {
+    // Keychain protocols are four-character codes.
+    let protocol = match scheme {
+        "https" => "htps",
+        _ => "http",
+    };
+
+    let output = Command::new("security")
+        .args(["find-internet-password", "-g", "-s", host, "-P", port, "-r"])
+        .arg(protocol)
+        .stdin(Stdio::null())
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    parse_security_output(
+        &String::from_utf8_lossy(&output.stdout),
+        &String::from_utf8_lossy(&output.stderr),
+    )
+}
+
+#[cfg(not(target_os = "macos"))]
+fn query(scheme: &str, host: &str, port: &str) -> Option<Credentials> {
+    let output = Command::new("secret-tool")
+        .args(["search", "--unlock", "server", host, "port", port, "protocol", scheme])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    parse_secret_tool_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse the output of `security find-internet-password -g`. The account is
+/// printed as an attribute on stdout, and the password on stderr.
+#[cfg_attr(not(any(test, target_os = "macos")), allow(dead_code))]
+fn parse_security_output(stdout: &str, stderr: &str) -> Option<Credentials> {
+    let username = stdout
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("\"acct\"<blob>="))
+        .and_then(unquote)?;
+
+    let password = stderr
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("password: "))
+        .and_then(unquote)?;
+
+    Some(Credentials::new(username, password))
+}
+
+/// Parse the output of `secret-tool search`, using the first item that has
+/// both a user and a secret.
+#[cfg_attr(target_os = "macos", allow(dead_code))]
+fn parse_secret_tool_output(stdout: &str) -> Option<Credentials> {
+    let mut username = None;
+    let mut password = None;
+
+    for line in stdout.lines() {
+        if line.starts_with('[') {
+            if let (Some(username), Some(password)) = (username.take(), password.take()) {
+                return Some(Credentials::new(username, password));
+            }
+
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once(" = ") {
+            match key {
+                "secret" => password = Some(value.to_owned()),
+                "attribute.user" => username = Some(value.to_owned()),
+                _ => {}
+            }
+        }
+    }
+
+    Some(Credentials::new(username?, password?))
+}
+
+fn unquote(s: &str) -> Option<&str> {
+    s.strip_prefix('"')?.strip_suffix('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_security() {
+        let stdout = "keychain: \"/Users/clark/Library/Keychains/login.keychain-db\"\n\
+                      attributes:\n    \"acct\"<blob>=\"clark\"\n    \"srvr\"<blob>=\"example.org\"\n";
+        let stderr = "password: \"querty\"\n";
+
+        let credentials = parse_security_output(stdout, stderr).unwrap();
+
+        assert_eq!(credentials.username, "clark");
+        assert_eq!(credentials.password, "querty");
+        assert!(parse_security_output(stdout, "").is_none());
+    }
+
+    #[test]
+    fn parse_secret_tool() {
+        let stdout = "[/org/freedesktop/secrets/collection/login/1]\n\
+                      label = example.org\n\
+                      secret = querty\n\
+                      attribute.server = example.org\n\
+                      attribute.user = clark\n\
+                      [/org/freedesktop/secrets/collection/login/2]\n\
+                      secret = other\n\
+                      attribute.user = lois\n";
+
+        let credentials = parse_secret_tool_output(stdout).unwrap();
+
+        assert_eq!(credentials.username, "clark");
+        assert_eq!(credentials.password, "querty");
+        assert!(parse_secret_tool_output("").is_none());
+    }
+}
@@ -0,0 +1,246 @@
+//! A store of credentials for answering authentication challenges.
+
+use super::{challenge::answer_with, Authenticator, Challenge, Credentials};
+use crate::error::Error;
+use http::{Request, Uri};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+/// An origin and an optional realm.
+type Key = (String, Option<String>);
+
+/// A store of credentials keyed by origin and realm.
+///
+/// A credential store can be used as an [`Authenticator`] to answer `Digest`
+/// and `Basic` challenges sent by servers, and by explicitly configured
+/// proxies. When a challenge is received, credentials registered for the
+/// origin and realm of the challenge are used, falling back to credentials
+/// registered for the origin without a realm.
+///
+/// Credentials can be added and removed at any time. Credential stores are
+/// cheap to clone, and clones share the same credentials, so one clone can be
+/// given to a client while another is kept around to update it.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{auth::*, prelude::*, HttpClient};
+///
+/// let store = CredentialStore::new();
+/// store.insert("https://example.org", None, Credentials::new("clark", "querty"))?;
+/// store.insert("http://proxy.local:3128", None, Credentials::new("proxy", "hunter2"))?;
+///
+/// let client = HttpClient::builder()
+///     .proxy(Some("http://proxy.local:3128".parse()?))
+///     .authenticator(store.clone())
+///     .build()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Default)]
+pub struct CredentialStore {
+    entries: Arc<RwLock<HashMap<Key, Credentials>>>,
+    #[cfg(feature = "keychain")]
+    keychain: bool,
+}
+
+impl CredentialStore {
+    /// Create a new empty credential store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fall back to looking up credentials in the operating system keychain
+    /// when none are found in this store.
+    ///
+    /// Internet passwords in the macOS keychain and network passwords in the
+    /// Secret Service (GNOME Keyring, KWallet) on other Unix platforms are
+    /// supported, matched by the host and port of the origin. Looking up
+    /// credentials blocks the current thread while the platform tools are
+    /// run, and may prompt the user for permission.
+    ///
+    /// # Availability
+    ///
+    /// This method is only available when the
+    /// [`keychain`](../index.html#keychain) feature is enabled.
+    #[cfg(feature = "keychain")]
+    #[must_use = "builders have no effect if unused"]
+    pub fn keychain(mut self, enable: bool) -> Self {
+        self.keychain = enable;
+        self
+    }
+
+    /// Add credentials for an origin, optionally only for a specific realm.
+    /// Any credentials previously added for the same origin and realm are
+    /// replaced.
+    ///
+    /// Only the scheme, host, and port of the given URI are used.
+    pub fn insert<U>(
+        &self,
+        origin: U,
+        realm: Option<&str>,
+        credentials: Credentials,
+    ) -> Result<(), Error>
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        let key = key(origin, realm)?;
+
+        self.entries.write().unwrap().insert(key, credentials);
+
+        Ok(())
+    }
+
+    /// Remove the credentials for an origin and realm, returning them if
+    /// present.
+    pub fn remove<U>(&self, origin: U, realm: Option<&str>) -> Result<Option<Credentials>, Error>
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        let key = key(origin, realm)?;
+
+        Ok(self.entries.write().unwrap().remove(&key))
+    }
+
+    /// Get the credentials that would be used to answer a challenge from an
+    /// origin with the given realm, if any.
+    ///
+    /// The operating system keychain is not consulted by this method.
+    pub fn get<U>(&self, origin: U, realm: Option<&str>) -> Result<Option<Credentials>, Error>
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        let (origin, realm) = key(origin, realm)?;
+
+        Ok(self.lookup(&origin, realm.as_deref()))
+    }
+
+    /// Remove all credentials from the store.
+    pub fn clear(&self) {
+        self.entries.write().unwrap().clear();
+    }
+
+    fn lookup(&self, origin: &str, realm: Option<&str>) -> Option<Credentials> {
+        let entries = self.entries.read().unwrap();
+
+        realm
+            .and_then(|realm| entries.get(&(origin.to_owned(), Some(realm.to_owned()))))
+            .or_else(|| entries.get(&(origin.to_owned(), None)))
+            .cloned()
+    }
+}
+
+impl Authenticator for CredentialStore {
+    fn authenticate(&self, challenges: &[Challenge], request: &Request<()>) -> Option<String> {
+        answer_with(challenges, request, |challenge| {
+            let origin = challenge.origin()?;
+
+            if let Some(credentials) = self.lookup(origin, challenge.realm()) {
+                return Some(credentials);
+            }
+
+            #[cfg(feature = "keychain")]
+            {
+                if self.keychain {
+                    return super::keychain::lookup(origin);
+                }
+            }
+
+            None
+        })
+    }
+}
+
+impl fmt::Debug for CredentialStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = self.entries.read().unwrap();
+        let mut keys = entries.keys().collect::<Vec<_>>();
+        keys.sort();
+
+        f.debug_struct("CredentialStore")
+            .field("entries", &keys)
+            .finish()
+    }
+}
+
+fn key<U>(origin: U, realm: Option<&str>) -> Result<Key, Error>
+where
+    Uri: TryFrom<U>,
+    <Uri as TryFrom<U>>::Error: Into<http::Error>,
+{
+    let uri = Uri::try_from(origin).map_err(|e| e.into())?;
+    let origin = self::origin(&uri).ok_or(crate::error::ErrorKind::InvalidRequest)?;
+
+    Ok((origin, realm.map(ToOwned::to_owned)))
+}
+
+/// Get the normalized origin of a URI in the form `scheme://host:port`, with
+/// the default port made explicit.
+pub(crate) fn origin(uri: &Uri) -> Option<String> {
+    let scheme = uri.scheme_str()?.to_ascii_lowercase();
+    let host = uri.host()?.to_ascii_lowercase();
+    let port = match (uri.port_u16(), scheme.as_str()) {
+        (Some(port), _) => port,
+        (None, "http") => 80,
+        (None, "https") => 443,
+        _ => return None,
+    };
+
+    Some(format!("{}://{}:{}", scheme, host, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn origin_normalization() {
+        let origin = |s: &str| origin(&s.parse().unwrap());
+
+        assert_eq!(
+            origin("HTTPS://Example.org/path?q"),
+            Some("https://example.org:443".into())
+        );
+        assert_eq!(
+            origin("http://example.org:8080"),
+            Some("http://example.org:8080".into())
+        );
+        assert_eq!(origin("ftp://example.org"), None);
+        assert_eq!(origin("/relative"), None);
+    }
+
+    #[test]
+    fn realm_falls_back_to_origin() {
+        let store = CredentialStore::new();
+        store
+            .insert("https://example.org", None, Credentials::new("any", "a"))
+            .unwrap();
+        store
+            .insert(
+                "https://example.org:443/",
+                Some("admin"),
+                Credentials::new("admin", "b"),
+            )
+            .unwrap();
+
+        let username = |realm| {
+            store
+                .get("https://example.org", realm)
+                .unwrap()
+                .map(|c| c.username)
+        };
+
+        assert_eq!(username(Some("admin")), Some("admin".into()));
+        assert_eq!(username(Some("other")), Some("any".into()));
+        assert_eq!(username(None), Some("any".into()));
+
+        store.remove("https://example.org", None).unwrap();
+        assert_eq!(username(Some("other")), None);
+    }
+}
@@ -0,0 +1,226 @@
+//! Splitting one body into several bodies that all produce the same bytes.
+
+use super::AsyncBody;
+use futures_lite::io::AsyncRead;
+use std::{
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+/// Size of the chunks read from the source body at a time.
+const CHUNK_SIZE: usize = 16384;
+
+/// Split a streaming body into `count` bodies.
+pub(super) fn fan_out(source: AsyncBody, count: usize) -> Vec<AsyncBody> {
+    let len = source.len();
+    let shared = Arc::new(Mutex::new(Shared {
+        source,
+        chunk: vec![0; CHUNK_SIZE].into_boxed_slice(),
+        filled: 0,
+        branches: (0..count).map(|_| Some(BranchState::default())).collect(),
+        eof: false,
+        error: None,
+    }));
+
+    (0..count)
+        .map(|index| {
+            let branch = Branch {
+                shared: shared.clone(),
+                index,
+            };
+
+            match len {
+                Some(len) => AsyncBody::from_reader_sized(branch, len),
+                None => AsyncBody::from_reader(branch),
+            }
+        })
+        .collect()
+}
+
+/// State shared between all branches of a source body.
+struct Shared {
+    source: AsyncBody,
+
+    /// The chunk most recently read from the source. A new chunk is only read
+    /// once every branch has consumed this one, so the slowest branch governs
+    /// how fast the source is read.
+    chunk: Box<[u8]>,
+    filled: usize,
+
+    /// State of each branch, or `None` if the branch has been dropped.
+    branches: Vec<Option<BranchState>>,
+
+    eof: bool,
+
+    /// Error returned by the source, given to every branch. I/O errors cannot
+    /// be cloned, so the parts needed to create a new one are kept instead.
+    error: Option<(io::ErrorKind, String)>,
+}
+
+#[derive(Default)]
+struct BranchState {
+    /// Position of the branch within the current chunk.
+    position: usize,
+
+    /// Waker to notify when a new chunk is available.
+    waker: Option<Waker>,
+}
+
+impl Shared {
+    fn wake_all(&mut self) {
+        for state in self.branches.iter_mut().flatten() {
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// One of the bodies produced by fanning out a source body.
+struct Branch {
+    shared: Arc<Mutex<Shared>>,
+    index: usize,
+}
+
+impl AsyncRead for Branch {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut guard = self.shared.lock().unwrap();
+        let shared = &mut *guard;
+
+        loop {
+            let position = shared.branches[self.index].as_ref().unwrap().position;
+
+            if position < shared.filled {
+                let amount = buf.len().min(shared.filled - position);
+
+                buf[..amount].copy_from_slice(&shared.chunk[position..position + amount]);
+                shared.branches[self.index].as_mut().unwrap().position += amount;
+
+                return Poll::Ready(Ok(amount));
+            }
+
+            if let Some((kind, message)) = &shared.error {
+                return Poll::Ready(Err(io::Error::new(*kind, message.clone())));
+            }
+
+            if shared.eof {
+                return Poll::Ready(Ok(0));
+            }
+
+            let filled = shared.filled;
+            let others_behind = shared
+                .branches
+                .iter()
+                .flatten()
+                .any(|state| state.position < filled);
+
+            // Wait for the other branches to catch up before reading more.
+            if others_behind {
+                shared.branches[self.index].as_mut().unwrap().waker = Some(cx.waker().clone());
+
+                return Poll::Pending;
+            }
+
+            match Pin::new(&mut shared.source).poll_read(cx, &mut shared.chunk) {
+                Poll::Ready(Ok(0)) => {
+                    shared.eof = true;
+                    shared.wake_all();
+                }
+                Poll::Ready(Ok(amount)) => {
+                    shared.filled = amount;
+
+                    for state in shared.branches.iter_mut().flatten() {
+                        state.position = 0;
+                    }
+
+                    shared.wake_all();
+                }
+                Poll::Ready(Err(e)) => {
+                    shared.error = Some((e.kind(), e.to_string()));
+                    shared.wake_all();
+
+                    return Poll::Ready(Err(e));
+                }
+                Poll::Pending => {
+                    // Any branch may be the one to continue reading from the
+                    // source, so whichever is woken up first does so.
+                    shared.branches[self.index].as_mut().unwrap().waker =
+                        Some(cx.waker().clone());
+
+                    return Poll::Pending;
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Branch {
+    fn drop(&mut self) {
+        if let Ok(mut shared) = self.shared.lock() {
+            // Stop holding back the other branches.
+            shared.branches[self.index] = None;
+            shared.wake_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_lite::{
+        future::{block_on, zip},
+        io::AsyncReadExt,
+    };
+
+    fn source() -> (AsyncBody, Vec<u8>) {
+        let data = (0..100_000u32).map(|i| i as u8).collect::<Vec<_>>();
+
+        (
+            AsyncBody::from_reader(futures_lite::io::Cursor::new(data.clone())),
+            data,
+        )
+    }
+
+    #[test]
+    fn all_branches_read_the_same_bytes() {
+        let (body, data) = source();
+        let mut bodies = fan_out(body, 2);
+        let mut second = bodies.pop().unwrap();
+        let mut first = bodies.pop().unwrap();
+
+        let (a, b) = block_on(zip(
+            async {
+                let mut buf = Vec::new();
+                first.read_to_end(&mut buf).await.unwrap();
+                buf
+            },
+            async {
+                let mut buf = Vec::new();
+                second.read_to_end(&mut buf).await.unwrap();
+                buf
+            },
+        ));
+
+        assert_eq!(a, data);
+        assert_eq!(b, data);
+    }
+
+    #[test]
+    fn dropped_branch_does_not_hold_back_others() {
+        let (body, data) = source();
+        let mut bodies = fan_out(body, 3);
+
+        bodies.truncate(1);
+
+        let mut buf = Vec::new();
+        block_on(bodies[0].read_to_end(&mut buf)).unwrap();
+
+        assert_eq!(buf, data);
+    }
+}
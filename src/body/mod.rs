@@ -1,17 +1,27 @@
 //! Provides types for working with request and response bodies.
 
-use futures_lite::io::{AsyncRead, BlockOn};
+#[cfg(feature = "blocking")]
+use futures_lite::io::BlockOn;
+use futures_lite::io::AsyncRead;
 use std::{
     borrow::Cow,
     fmt,
     io::{self, Cursor, Read},
     pin::Pin,
     str,
+    sync::Arc,
     task::{Context, Poll},
 };
 
+#[cfg(feature = "blocking")]
+use std::time::Duration;
+
+mod fan_out;
+
+#[cfg(feature = "blocking")]
 mod sync;
 
+#[cfg(feature = "blocking")]
 #[allow(unreachable_pub)]
 pub use sync::Body;
 
@@ -40,6 +50,43 @@ enum Inner {
 
     /// An asynchronous reader.
     Reader(Pin<Box<dyn AsyncRead + Send + Sync>>, Option<u64>),
+
+    /// A body produced on demand by a function.
+    Deferred(Deferred),
+}
+
+/// A body whose contents are produced by a function when first read, and
+/// produced again each time the body is reset.
+///
+/// Shared by the asynchronous and synchronous body types.
+struct Deferred {
+    produce: Arc<dyn Fn() -> Vec<u8> + Send + Sync>,
+    buffer: Option<Cursor<Vec<u8>>>,
+}
+
+impl Deferred {
+    fn new<F, B>(f: F) -> Self
+    where
+        F: Fn() -> B + Send + Sync + 'static,
+        B: Into<Vec<u8>>,
+    {
+        Self {
+            produce: Arc::new(move || f().into()),
+            buffer: None,
+        }
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let produce = &self.produce;
+
+        self.buffer
+            .get_or_insert_with(|| Cursor::new(produce()))
+            .read(buf)
+    }
+
+    fn reset(&mut self) {
+        self.buffer = None;
+    }
 }
 
 impl AsyncBody {
@@ -119,6 +166,34 @@ impl AsyncBody {
         Self(Inner::Reader(Box::pin(read), Some(length)))
     }
 
+    /// Create a body whose contents are produced by the given function when
+    /// the body is first read.
+    ///
+    /// The function is called again each time the body is
+    /// [reset](AsyncBody::reset), such as when the request is retried or
+    /// redirected and the body needs to be sent again. This is useful for
+    /// bodies containing signatures or timestamps that should be generated as
+    /// close to being sent as possible.
+    ///
+    /// The body will have an unknown length, since it is not known until the
+    /// function is called.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::AsyncBody;
+    /// use std::time::SystemTime;
+    ///
+    /// let body = AsyncBody::from_fn(|| format!("sent at {:?}", SystemTime::now()));
+    /// ```
+    pub fn from_fn<F, B>(f: F) -> Self
+    where
+        F: Fn() -> B + Send + Sync + 'static,
+        B: Into<Vec<u8>>,
+    {
+        Self(Inner::Deferred(Deferred::new(f)))
+    }
+
     /// Report if this body is empty.
     ///
     /// This is not necessarily the same as checking for `self.len() ==
@@ -150,9 +225,38 @@ impl AsyncBody {
             Inner::Empty => Some(0),
             Inner::Buffer(bytes) => Some(bytes.get_ref().len() as u64),
             Inner::Reader(_, len) => *len,
+            Inner::Deferred(_) => None,
+        }
+    }
+
+    /// Get the contents of this body if it is stored in memory.
+    pub(crate) fn as_bytes(&self) -> Option<&[u8]> {
+        match &self.0 {
+            Inner::Buffer(cursor) => Some(cursor.get_ref()),
+            _ => None,
+        }
+    }
+
+    /// Create a copy of this body that starts from the beginning of the
+    /// content, if this body is repeatable.
+    pub(crate) fn try_clone(&self) -> Option<Self> {
+        match &self.0 {
+            Inner::Empty => Some(Self::empty()),
+            Inner::Buffer(cursor) => Some(Self(Inner::Buffer(Cursor::new(cursor.get_ref().clone())))),
+            Inner::Reader(_, _) => None,
+            Inner::Deferred(deferred) => Some(Self(Inner::Deferred(Deferred {
+                produce: deferred.produce.clone(),
+                buffer: None,
+            }))),
         }
     }
 
+    /// Returns true if this body can be [reset](AsyncBody::reset) to be read
+    /// again from the start.
+    pub(crate) fn is_repeatable(&self) -> bool {
+        !matches!(self.0, Inner::Reader(_, _))
+    }
+
     /// If this body is repeatable, reset the body stream back to the start of
     /// the content. Returns `false` if the body cannot be reset.
     pub fn reset(&mut self) -> bool {
@@ -163,6 +267,61 @@ impl AsyncBody {
                 true
             }
             Inner::Reader(_, _) => false,
+            Inner::Deferred(deferred) => {
+                deferred.reset();
+                true
+            }
+        }
+    }
+
+    /// Split this body into `count` bodies that each produce the same bytes.
+    ///
+    /// This can be used to send one body to several destinations at once,
+    /// such as when replicating a downloaded response to multiple storage
+    /// backends without buffering it in memory. Each resulting body has the
+    /// same length as this one.
+    ///
+    /// Bodies stored in memory are simply copied. Otherwise, this body is read
+    /// a chunk at a time, and the next chunk is only read once every resulting
+    /// body has consumed the current one, so the slowest reader governs how
+    /// fast this body is read. The resulting bodies must therefore be read
+    /// concurrently, such as by sending all of them with
+    /// [`send_async`](crate::HttpClient::send_async) and awaiting the
+    /// responses together. A body that is dropped no longer holds back the
+    /// others.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use futures_lite::future::zip;
+    /// use isahc::{prelude::*, AsyncBody, Request};
+    ///
+    /// # async fn run() -> Result<(), isahc::Error> {
+    /// let response = isahc::get_async("https://example.org/source").await?;
+    /// let mut bodies = AsyncBody::from(response).fan_out(2);
+    ///
+    /// let (first, second) = zip(
+    ///     isahc::put_async("https://one.example.org/mirror", bodies.remove(0)),
+    ///     isahc::put_async("https://two.example.org/mirror", bodies.remove(0)),
+    /// )
+    /// .await;
+    ///
+    /// first?;
+    /// second?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn fan_out(self, count: usize) -> Vec<Self> {
+        match self.0 {
+            Inner::Empty => (0..count).map(|_| Self::empty()).collect(),
+            Inner::Buffer(cursor) => {
+                let bytes = cursor.into_inner();
+
+                (0..count)
+                    .map(|_| Self(Inner::Buffer(Cursor::new(bytes.clone()))))
+                    .collect()
+            }
+            _ => fan_out::fan_out(self, count),
         }
     }
 
@@ -173,14 +332,25 @@ impl AsyncBody {
     /// implementation works for the bodies _we_ create, it may not work
     /// generally if the underlying reader only supports blocking under a
     /// specific runtime.
-    pub(crate) fn into_sync(self) -> sync::Body {
-        match self.0 {
-            Inner::Empty => sync::Body::empty(),
-            Inner::Buffer(cursor) => sync::Body::from_bytes_static(cursor.into_inner()),
-            Inner::Reader(reader, Some(len)) => {
+    ///
+    /// If a read timeout is given, each read gives up if no data arrives
+    /// within the timeout.
+    #[cfg(feature = "blocking")]
+    pub(crate) fn into_sync(self, read_timeout: Option<Duration>) -> sync::Body {
+        match (self.0, read_timeout) {
+            (Inner::Empty, _) => sync::Body::empty(),
+            (Inner::Buffer(cursor), _) => sync::Body::from_bytes_static(cursor.into_inner()),
+            (Inner::Reader(reader, Some(len)), Some(timeout)) => {
+                sync::Body::from_reader_sized(sync::TimeoutReader::new(reader, timeout), len)
+            }
+            (Inner::Reader(reader, None), Some(timeout)) => {
+                sync::Body::from_reader(sync::TimeoutReader::new(reader, timeout))
+            }
+            (Inner::Reader(reader, Some(len)), None) => {
                 sync::Body::from_reader_sized(BlockOn::new(reader), len)
             }
-            Inner::Reader(reader, None) => sync::Body::from_reader(BlockOn::new(reader)),
+            (Inner::Reader(reader, None), None) => sync::Body::from_reader(BlockOn::new(reader)),
+            (Inner::Deferred(deferred), _) => sync::Body::from_deferred(deferred),
         }
     }
 }
@@ -195,6 +365,7 @@ impl AsyncRead for AsyncBody {
             Inner::Empty => Poll::Ready(Ok(0)),
             Inner::Buffer(cursor) => Poll::Ready(cursor.read(buf)),
             Inner::Reader(read, _) => AsyncRead::poll_read(read.as_mut(), cx, buf),
+            Inner::Deferred(deferred) => Poll::Ready(deferred.read(buf)),
         }
     }
 }
@@ -244,6 +415,38 @@ impl<T: Into<Self>> From<Option<T>> for AsyncBody {
     }
 }
 
+/// Use the body of a response as the body of another request.
+///
+/// The response body is streamed into the new request as it is received
+/// without being buffered in memory, which makes it easy to proxy or mirror
+/// a resource from one server to another. The two transfers are linked
+/// together: the upload only proceeds as fast as the download produces data,
+/// and the download is paused while the upload is waiting to send.
+///
+/// The length of the response body, if known, becomes the `Content-Length` of
+/// the request. Headers such as `Content-Type` are not carried over.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{prelude::*, Request};
+///
+/// # async fn run() -> Result<(), isahc::Error> {
+/// let response = isahc::get_async("https://example.org/source").await?;
+///
+/// Request::put("https://example.org/mirror")
+///     .body(response)?
+///     .send_async()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+impl From<http::Response<AsyncBody>> for AsyncBody {
+    fn from(response: http::Response<AsyncBody>) -> Self {
+        response.into_body()
+    }
+}
+
 impl fmt::Debug for AsyncBody {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self.len() {
@@ -260,6 +463,10 @@ mod tests {
         future::{block_on, zip},
         io::AsyncReadExt,
     };
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
 
     static_assertions::assert_impl_all!(AsyncBody: Send, Sync);
 
@@ -314,10 +521,35 @@ mod tests {
     fn cannot_reset_reader() {
         let mut body = AsyncBody::from_reader(futures_lite::io::empty());
 
-        assert_eq!(body.reset(), false);
+        assert!(!body.reset());
+    }
+
+    #[test]
+    fn reset_deferred_body_produces_again() {
+        block_on(async {
+            let calls = Arc::new(AtomicUsize::new(0));
+            let mut body = AsyncBody::from_fn({
+                let calls = calls.clone();
+                move || {
+                    let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                    format!("call {}", n)
+                }
+            });
+            let mut buf = String::new();
+
+            assert_eq!(body.len(), None);
+            assert_eq!(calls.load(Ordering::SeqCst), 0);
+            body.read_to_string(&mut buf).await.unwrap();
+            assert_eq!(buf, "call 1");
+            assert!(body.reset());
+            buf.clear();
+            body.read_to_string(&mut buf).await.unwrap();
+            assert_eq!(buf, "call 2");
+        });
     }
 
     #[test]
+    #[cfg(feature = "blocking")]
     fn sync_memory_into_async() {
         let (body, writer) = Body::from("hello world").into_async();
 
@@ -326,6 +558,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "blocking")]
     fn sync_reader_into_async() {
         block_on(async {
             let (mut body, writer) = Body::from_reader("hello world".as_bytes()).into_async();
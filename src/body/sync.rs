@@ -1,12 +1,20 @@
-use super::AsyncBody;
-use futures_lite::{future::yield_now, io::AsyncWriteExt};
+use super::{AsyncBody, Deferred};
+use futures_lite::{
+    future::yield_now,
+    io::{AsyncRead, AsyncWriteExt},
+};
 use sluice::pipe::{pipe, PipeWriter};
 use std::{
     borrow::Cow,
     fmt,
     fs::File,
     io::{Cursor, ErrorKind, Read, Result},
+    pin::Pin,
+    task::{Context, Poll},
+    thread,
+    time::{Duration, Instant},
 };
+use waker_fn::waker_fn;
 
 /// Contains the body of a synchronous HTTP request or response.
 ///
@@ -23,6 +31,7 @@ enum Inner {
     Empty,
     Buffer(Cursor<Cow<'static, [u8]>>),
     Reader(Box<dyn Read + Send + Sync>, Option<u64>),
+    Deferred(Deferred),
 }
 
 impl Body {
@@ -95,6 +104,38 @@ impl Body {
         Self(Inner::Reader(Box::new(reader), Some(length)))
     }
 
+    /// Create a body whose contents are produced by the given function when
+    /// the body is first read.
+    ///
+    /// The function is called again each time the body is
+    /// [reset](Body::reset), such as when the request is retried or redirected
+    /// and the body needs to be sent again. This is useful for bodies
+    /// containing signatures or timestamps that should be generated as close to
+    /// being sent as possible.
+    ///
+    /// The body will have an unknown length, since it is not known until the
+    /// function is called.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::Body;
+    /// use std::time::SystemTime;
+    ///
+    /// let body = Body::from_fn(|| format!("sent at {:?}", SystemTime::now()));
+    /// ```
+    pub fn from_fn<F, B>(f: F) -> Self
+    where
+        F: Fn() -> B + Send + Sync + 'static,
+        B: Into<Vec<u8>>,
+    {
+        Self(Inner::Deferred(Deferred::new(f)))
+    }
+
+    pub(super) fn from_deferred(deferred: Deferred) -> Self {
+        Self(Inner::Deferred(deferred))
+    }
+
     /// Report if this body is empty.
     ///
     /// This is not necessarily the same as checking for `self.len() ==
@@ -126,6 +167,7 @@ impl Body {
             Inner::Empty => Some(0),
             Inner::Buffer(bytes) => Some(bytes.get_ref().len() as u64),
             Inner::Reader(_, len) => *len,
+            Inner::Deferred(_) => None,
         }
     }
 
@@ -138,6 +180,10 @@ impl Body {
                 cursor.set_position(0);
                 true
             }
+            Inner::Deferred(deferred) => {
+                deferred.reset();
+                true
+            }
             _ => false,
         }
     }
@@ -157,6 +203,7 @@ impl Body {
         match self.0 {
             Inner::Empty => (AsyncBody::empty(), None),
             Inner::Buffer(cursor) => (AsyncBody::from_bytes_static(cursor.into_inner()), None),
+            Inner::Deferred(deferred) => (AsyncBody(super::Inner::Deferred(deferred)), None),
             Inner::Reader(reader, len) => {
                 let (pipe_reader, writer) = pipe();
 
@@ -182,6 +229,7 @@ impl Read for Body {
             Inner::Empty => Ok(0),
             Inner::Buffer(cursor) => cursor.read(buf),
             Inner::Reader(reader, _) => reader.read(buf),
+            Inner::Deferred(deferred) => deferred.read(buf),
         }
     }
 }
@@ -232,6 +280,31 @@ impl From<File> for Body {
     }
 }
 
+/// Use the body of a response as the body of another request.
+///
+/// The response body is streamed into the new request as it is received
+/// without being buffered in memory, and the upload only proceeds as fast as
+/// the download produces data. See the equivalent conversion for
+/// [`AsyncBody`] for details.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{prelude::*, Request};
+///
+/// let response = isahc::get("https://example.org/source")?;
+///
+/// Request::put("https://example.org/mirror")
+///     .body(response)?
+///     .send()?;
+/// # Ok::<(), isahc::Error>(())
+/// ```
+impl From<http::Response<Body>> for Body {
+    fn from(response: http::Response<Body>) -> Self {
+        response.into_body()
+    }
+}
+
 impl fmt::Debug for Body {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self.len() {
@@ -280,6 +353,46 @@ impl Writer {
     }
 }
 
+/// Blocking adapter for an asynchronous reader that gives up on a read if no
+/// data arrives within a timeout.
+pub(super) struct TimeoutReader<R> {
+    inner: R,
+    timeout: Duration,
+}
+
+impl<R> TimeoutReader<R> {
+    pub(super) fn new(inner: R, timeout: Duration) -> Self {
+        Self { inner, timeout }
+    }
+}
+
+impl<R: AsyncRead + Unpin> Read for TimeoutReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let deadline = Instant::now() + self.timeout;
+        let waker = waker_fn({
+            let thread = thread::current();
+            move || thread.unpark()
+        });
+        let mut cx = Context::from_waker(&waker);
+
+        loop {
+            if let Poll::Ready(result) = Pin::new(&mut self.inner).poll_read(&mut cx, buf) {
+                return result;
+            }
+
+            // Parking may wake up spuriously, so re-check the deadline every
+            // time around.
+            let now = Instant::now();
+
+            if now >= deadline {
+                return Err(crate::Error::from(crate::error::ErrorKind::ReadTimeout).into());
+            }
+
+            thread::park_timeout(deadline - now);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -330,10 +443,22 @@ mod tests {
         assert_eq!(buf, "hello worldhello world");
     }
 
+    #[test]
+    fn deferred_body_is_produced_when_read() {
+        let mut body = Body::from_fn(|| "hello world");
+        let mut buf = String::new();
+
+        assert_eq!(body.len(), None);
+        assert_eq!(body.read_to_string(&mut buf).unwrap(), 11);
+        assert!(body.reset());
+        assert_eq!(body.read_to_string(&mut buf).unwrap(), 11);
+        assert_eq!(buf, "hello worldhello world");
+    }
+
     #[test]
     fn cannot_reset_reader() {
         let mut body = Body::from_reader(std::io::empty());
 
-        assert_eq!(body.reset(), false);
+        assert!(!body.reset());
     }
 }
@@ -0,0 +1,201 @@
+//! Canceling requests that are in progress.
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+        Mutex,
+    },
+    task::Waker,
+};
+
+/// A handle for canceling a request that is in progress.
+///
+/// Canceling a request aborts its transfer right away, even if it is idly
+/// waiting on the server. A pending response future then returns an error of
+/// kind [`ErrorKind::Interrupted`](crate::error::ErrorKind::Interrupted), and
+/// reading from the response body fails with an I/O error wrapping it.
+/// Canceling a request that has already completed has no effect.
+///
+/// A handle for an asynchronous request can be obtained from its
+/// [`ResponseFuture`](crate::ResponseFuture::cancel_handle). A handle can also
+/// be created ahead of time and attached to a request as an extension, which
+/// also works for synchronous requests sent from another thread. Cloning a
+/// handle produces a handle to the same request.
+///
+/// Dropping a response future before it completes cancels its request the
+/// same way.
+///
+/// A [`CancelReason`] can be given when canceling, which is carried by the
+/// resulting [error](crate::Error::cancel_reason) and reported to
+/// [instrumentation](crate::config::Instrumentation), so that aborted traffic
+/// can be told apart afterwards.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{prelude::*, CancelHandle, Request};
+/// use std::{thread, time::Duration};
+///
+/// let cancel = CancelHandle::new();
+///
+/// thread::spawn({
+///     let cancel = cancel.clone();
+///     move || {
+///         thread::sleep(Duration::from_secs(5));
+///         cancel.cancel();
+///     }
+/// });
+///
+/// let result = Request::get("https://example.org/slow")
+///     .extension(cancel)
+///     .body(())?
+///     .send();
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Clone, Default)]
+pub struct CancelHandle(pub(crate) Arc<Cancellation>);
+
+impl CancelHandle {
+    /// Create a new handle for a request that has not been sent yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the request, because the user asked to abort it.
+    ///
+    /// This is the same as canceling with [`CancelReason::UserAbort`].
+    pub fn cancel(&self) {
+        self.cancel_with_reason(CancelReason::UserAbort);
+    }
+
+    /// Cancel the request for the given reason.
+    ///
+    /// If the request has been canceled already, the reason it was first
+    /// canceled for is kept.
+    pub fn cancel_with_reason(&self, reason: CancelReason) {
+        self.0.cancel(reason);
+    }
+
+    /// Returns true if the request has been canceled.
+    pub fn is_canceled(&self) -> bool {
+        self.0.is_canceled()
+    }
+
+    /// Get the reason the request was canceled for, if it has been canceled.
+    pub fn reason(&self) -> Option<CancelReason> {
+        self.0.reason()
+    }
+}
+
+impl fmt::Debug for CancelHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancelHandle")
+            .field("canceled", &self.is_canceled())
+            .field("reason", &self.reason())
+            .finish()
+    }
+}
+
+/// Why a request was canceled.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum CancelReason {
+    /// The user asked to abort the request, or the response future was
+    /// dropped before it completed.
+    UserAbort,
+
+    /// A deadline for the request passed, such as one enforced by the
+    /// application across several requests.
+    Deadline,
+
+    /// The client or application is shutting down. Requests aborted by
+    /// [`HttpClient::shutdown`](crate::HttpClient::shutdown) carry this
+    /// reason.
+    Shutdown,
+
+    /// A policy of the application decided against completing the request,
+    /// such as one shedding load.
+    Policy,
+}
+
+impl fmt::Display for CancelReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::UserAbort => "user abort",
+            Self::Deadline => "deadline",
+            Self::Shutdown => "shutdown",
+            Self::Policy => "policy",
+        })
+    }
+}
+
+/// Cancellation state shared between a request handler and the handles for
+/// its request.
+#[derive(Debug, Default)]
+pub(crate) struct Cancellation {
+    canceled: AtomicBool,
+
+    /// The reason the request was first canceled for.
+    reason: Mutex<Option<CancelReason>>,
+
+    /// Wakes the agent to abort the transfer currently executing the request,
+    /// if any.
+    waker: Mutex<Option<Waker>>,
+}
+
+impl Cancellation {
+    pub(crate) fn cancel(&self, reason: CancelReason) {
+        self.reason.lock().unwrap().get_or_insert(reason);
+        self.canceled.store(true, Ordering::SeqCst);
+
+        if let Some(waker) = self.waker.lock().unwrap().as_ref() {
+            waker.wake_by_ref();
+        }
+    }
+
+    pub(crate) fn is_canceled(&self) -> bool {
+        self.canceled.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn reason(&self) -> Option<CancelReason> {
+        *self.reason.lock().unwrap()
+    }
+
+    /// Set the waker to use to abort the transfer that has begun executing
+    /// the request.
+    ///
+    /// If the request was canceled before the transfer began, the waker is
+    /// woken right away.
+    pub(crate) fn set_waker(&self, waker: Waker) {
+        let mut slot = self.waker.lock().unwrap();
+
+        if self.is_canceled() {
+            waker.wake_by_ref();
+        }
+
+        *slot = Some(waker);
+    }
+}
+
+/// Cancels a request when dropped, unless disarmed first.
+pub(crate) struct CancelOnDrop(Option<Arc<Cancellation>>);
+
+impl CancelOnDrop {
+    pub(crate) fn new(cancellation: Arc<Cancellation>) -> Self {
+        Self(Some(cancellation))
+    }
+
+    pub(crate) fn disarm(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(cancellation) = self.0.take() {
+            cancellation.cancel(CancelReason::UserAbort);
+        }
+    }
+}
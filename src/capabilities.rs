@@ -0,0 +1,47 @@
+//! Learning what servers support from their responses.
+
+use crate::{
+    body::AsyncBody,
+    config::request::RequestConfig,
+    error::Error,
+    interceptor::{Context, Interceptor, InterceptorFuture},
+};
+use http::Request;
+
+/// Interceptor that records the capabilities revealed by each response in the
+/// capability cache, if the client has one.
+pub(crate) struct CapabilityInterceptor;
+
+impl Interceptor for CapabilityInterceptor {
+    type Err = Error;
+
+    fn intercept<'a>(
+        &'a self,
+        request: Request<AsyncBody>,
+        ctx: Context<'a>,
+    ) -> InterceptorFuture<'a, Self::Err> {
+        Box::pin(async move {
+            let config = request.extensions().get::<RequestConfig>();
+            let cache = match config.and_then(|config| config.capability_cache.clone()) {
+                Some(cache) => cache,
+                None => return ctx.send(request).await,
+            };
+            let clock = config
+                .and_then(|config| config.clock.clone())
+                .unwrap_or_default();
+            let uri = request.uri().clone();
+
+            let response = ctx.send(request).await?;
+
+            cache.record(
+                &uri,
+                response.status(),
+                response.version(),
+                response.headers(),
+                clock.now(),
+            );
+
+            Ok(response)
+        })
+    }
+}
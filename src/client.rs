@@ -1,24 +1,33 @@
 //! The HTTP client implementation.
 
 use crate::{
-    agent::{self, AgentBuilder},
-    body::{AsyncBody, Body},
+    agent::{self, AgentBuilder, AgentStats, InlineTransfer},
+    body::AsyncBody,
+    cancel::CancelHandle,
     config::{
         client::ClientConfig,
+        conflicts::ConfigConflicts,
+        dial::{ConnectorObj, TransportSocket},
         request::{RequestConfig, SetOpt, WithRequestConfig},
         *,
     },
+    decode,
     default_headers::DefaultHeadersInterceptor,
     error::{Error, ErrorKind},
     handler::{RequestHandler, ResponseBodyReader},
     headers::HasHeaders,
+    host_policy::PinnedAddresses,
     interceptor::{self, Interceptor, InterceptorObj},
-    parsing::header_to_curl_string,
-};
-use futures_lite::{
-    future::{block_on, try_zip},
-    io::AsyncRead,
+    parsing::write_curl_header,
+    response::ContentEncoding,
+    session::Session,
+    testing::MockHandler,
 };
+#[cfg(feature = "blocking")]
+use crate::body::Body;
+#[cfg(feature = "blocking")]
+use futures_lite::future::{block_on, try_zip};
+use futures_lite::io::{AsyncRead, AsyncReadExt};
 use http::{
     header::{HeaderMap, HeaderName, HeaderValue},
     Request,
@@ -31,20 +40,26 @@ use std::{
     future::Future,
     io,
     pin::Pin,
-    sync::Arc,
+    sync::{Arc, Mutex, RwLock},
     task::{Context, Poll},
-    time::Duration,
+    time::{Duration, Instant},
 };
 use tracing_futures::Instrument;
 
-static USER_AGENT: Lazy<String> = Lazy::new(|| {
+static USER_AGENT: Lazy<HeaderValue> = Lazy::new(|| {
     format!(
         "curl/{} isahc/{}",
         curl::Version::get().version(),
         env!("CARGO_PKG_VERSION")
     )
+    .parse()
+    .unwrap()
 });
 
+type EasyHandle = curl::easy::Easy2<RequestHandler>;
+type ResponseResult = Result<Response<ResponseBodyReader>, Error>;
+type ShutdownHook = Box<dyn FnOnce() + Send>;
+
 /// An HTTP client builder, capable of creating custom [`HttpClient`] instances
 /// with customized behavior.
 ///
@@ -76,6 +91,7 @@ pub struct HttpClientBuilder {
     client_config: ClientConfig,
     request_config: RequestConfig,
     interceptors: Vec<InterceptorObj>,
+    shutdown_hooks: Vec<ShutdownHook>,
     default_headers: HeaderMap<HeaderValue>,
     error: Option<Error>,
 
@@ -95,17 +111,62 @@ impl HttpClientBuilder {
     ///
     /// This is equivalent to the [`Default`] implementation.
     pub fn new() -> Self {
+        let mut interceptors = vec![
+            // Add redirect support. Note that this is _always_ the first,
+            // and thus the outermost, interceptor. Also note that this does
+            // not enable redirect following, it just implements support for
+            // it, if a request asks for it.
+            InterceptorObj::new(crate::retry::RetryBudgetInterceptor),
+            InterceptorObj::new(crate::redirect::RedirectInterceptor),
+        ];
+
+        // Record a span for each request sent, including each redirect, if a
+        // request asks for it.
+        #[cfg(feature = "trace-context")]
+        interceptors.push(InterceptorObj::new(
+            crate::trace_context::TraceContextInterceptor,
+        ));
+
+        interceptors.extend([
+            // Log the lifecycle of each request sent, including each
+            // redirect, if a request asks for it.
+            InterceptorObj::new(crate::event_log::EventLogInterceptor),
+            // Turn error responses into errors, if a request asks for it.
+            InterceptorObj::new(crate::fail_with_body::FailWithBodyInterceptor),
+            // Answer requests for byte ranges from the range cache, if the
+            // client has one.
+            InterceptorObj::new(crate::range_cache::RangeCacheInterceptor),
+            // Learn what servers support from their responses, if the client
+            // has a capability cache.
+            InterceptorObj::new(crate::capabilities::CapabilityInterceptor),
+            // Retry requests that failed transiently, if a request asks for
+            // it.
+            InterceptorObj::new(crate::retry::RetryInterceptor),
+            // Add bearer tokens to requests, if a request asks for it.
+            InterceptorObj::new(crate::auth::TokenInterceptor),
+            // Answer authentication challenges, if a request asks for it.
+            InterceptorObj::new(crate::auth::AuthenticatorInterceptor),
+            // Fail fast for hosts that recently failed to resolve, if a
+            // request asks for it.
+            InterceptorObj::new(crate::dns_negative_cache::DnsNegativeCacheInterceptor),
+            // Reject malformed requests, if a request asks for it.
+            InterceptorObj::new(crate::validation::ValidationInterceptor),
+            // Check internationalized host names, if a request asks for it.
+            InterceptorObj::new(crate::idna::IdnaInterceptor),
+            // Normalize request URLs, if a request asks for it.
+            InterceptorObj::new(crate::normalize::NormalizeInterceptor),
+            // Connect to the same address as earlier requests with the
+            // same affinity key, if a request asks for it.
+            InterceptorObj::new(crate::affinity::AffinityInterceptor::default()),
+            InterceptorObj::new(crate::host_policy::HostPolicyInterceptor),
+        ]);
+
         Self {
             agent_builder: AgentBuilder::default(),
             client_config: ClientConfig::default(),
             request_config: RequestConfig::client_defaults(),
-            interceptors: vec![
-                // Add redirect support. Note that this is _always_ the first,
-                // and thus the outermost, interceptor. Also note that this does
-                // not enable redirect following, it just implements support for
-                // it, if a request asks for it.
-                InterceptorObj::new(crate::redirect::RedirectInterceptor),
-            ],
+            interceptors,
+            shutdown_hooks: Vec::new(),
             default_headers: HeaderMap::new(),
             error: None,
 
@@ -143,108 +204,1020 @@ impl HttpClientBuilder {
     ///
     /// # Availability
     ///
-    /// This method is only available when the [`cookies`](index.html#cookies)
-    /// feature is enabled.
-    #[cfg(feature = "cookies")]
-    pub fn cookies(self) -> Self {
-        // Note: this method is now essentially the same as setting a default
-        // cookie jar, but remains for backwards compatibility.
-        self.cookie_jar(Default::default())
+    /// This method is only available when the [`cookies`](index.html#cookies)
+    /// feature is enabled.
+    #[cfg(feature = "cookies")]
+    pub fn cookies(self) -> Self {
+        // Note: this method is now essentially the same as setting a default
+        // cookie jar, but remains for backwards compatibility.
+        self.cookie_jar(Default::default())
+    }
+
+    /// Add a request interceptor to the client.
+    ///
+    /// # Availability
+    ///
+    /// This method is only available when the
+    /// [`unstable-interceptors`](index.html#unstable-interceptors) feature is
+    /// enabled.
+    #[cfg(feature = "unstable-interceptors")]
+    #[inline]
+    pub fn interceptor(self, interceptor: impl Interceptor + 'static) -> Self {
+        self.interceptor_impl(interceptor)
+    }
+
+    /// Answer requests sent by the client with the given handler, instead of
+    /// sending them over the network.
+    ///
+    /// See [`HttpClient::with_handler`] for details.
+    pub fn handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(Request<AsyncBody>) -> Response<AsyncBody> + Send + Sync + 'static,
+    {
+        self.client_config.handler = Some(MockHandler(Arc::new(handler)));
+        self
+    }
+
+    #[allow(unused)]
+    pub(crate) fn interceptor_impl(mut self, interceptor: impl Interceptor + 'static) -> Self {
+        self.interceptors.push(InterceptorObj::new(interceptor));
+        self
+    }
+
+    /// Set a maximum number of simultaneous connections that this client is
+    /// allowed to keep open at one time.
+    ///
+    /// If set to a value greater than zero, no more than `max` connections will
+    /// be opened at one time. If executing a new request would require opening
+    /// a new connection, then the request will stay in a "pending" state until
+    /// an existing connection can be used or an active request completes and
+    /// can be closed, making room for a new connection.
+    ///
+    /// Setting this value to `0` disables the limit entirely.
+    ///
+    /// This is an effective way of limiting the number of sockets or file
+    /// descriptors that this client will open, though note that the client may
+    /// use file descriptors for purposes other than just HTTP connections.
+    ///
+    /// By default this value is `0` and no limit is enforced.
+    ///
+    /// To apply a limit per-host, see
+    /// [`HttpClientBuilder::max_connections_per_host`].
+    pub fn max_connections(mut self, max: usize) -> Self {
+        self.agent_builder = self.agent_builder.max_connections(max);
+        self
+    }
+
+    /// Set a maximum number of simultaneous connections that this client is
+    /// allowed to keep open to individual hosts at one time.
+    ///
+    /// If set to a value greater than zero, no more than `max` connections will
+    /// be opened to a single host at one time. If executing a new request would
+    /// require opening a new connection, then the request will stay in a
+    /// "pending" state until an existing connection can be used or an active
+    /// request completes and can be closed, making room for a new connection.
+    ///
+    /// Setting this value to `0` disables the limit entirely. By default this
+    /// value is `0` and no limit is enforced.
+    ///
+    /// To set a global limit across all hosts, see
+    /// [`HttpClientBuilder::max_connections`].
+    pub fn max_connections_per_host(mut self, max: usize) -> Self {
+        self.agent_builder = self.agent_builder.max_connections_per_host(max);
+        self
+    }
+
+    /// Set the size of the connection cache.
+    ///
+    /// After requests are completed, if the underlying connection is reusable,
+    /// it is added to the connection cache to be reused to reduce latency for
+    /// future requests.
+    ///
+    /// Setting the size to `0` disables connection caching for all requests
+    /// using this client.
+    ///
+    /// By default this value is unspecified. A reasonable default size will be
+    /// chosen.
+    pub fn connection_cache_size(mut self, size: usize) -> Self {
+        self.agent_builder = self.agent_builder.connection_cache_size(size);
+        self.client_config.close_connections = size == 0;
+        self
+    }
+
+    /// Set a limit on how many bytes of response body data a single transfer
+    /// may receive during one turn of the client's event loop.
+    ///
+    /// When many transfers become readable at the same time, a single very
+    /// fast download could otherwise monopolize the agent thread and delay
+    /// progress on all other requests. Once a transfer has received `bytes`
+    /// bytes in the current turn, it is paused and resumed again on the next
+    /// turn, after every other ready transfer has had a chance to run.
+    ///
+    /// The limit is approximate, as curl may hand over data in chunks that
+    /// exceed the remaining allowance. Smaller values improve fairness at the
+    /// cost of some per-transfer throughput.
+    ///
+    /// Setting this value to `0` disables the limit entirely. By default this
+    /// value is `0` and no limit is enforced.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::HttpClient;
+    ///
+    /// let client = HttpClient::builder()
+    ///     // Allow each transfer to receive up to 256 KiB per turn.
+    ///     .transfer_quantum(256 * 1024)
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn transfer_quantum(mut self, bytes: usize) -> Self {
+        self.agent_builder = self.agent_builder.transfer_quantum(bytes);
+        self
+    }
+
+    /// Set the maximum amount of time the client's background agent will wait
+    /// for network activity before checking in again.
+    ///
+    /// The agent normally sleeps until curl asks to be woken up, but never
+    /// longer than this. Lowering this value may help work around curl
+    /// versions that fail to report timeouts correctly, at the cost of more
+    /// frequent wakeups when idle.
+    ///
+    /// The default timeout is 1 second.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::HttpClient;
+    /// use std::time::Duration;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .agent_poll_timeout(Duration::from_millis(250))
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn agent_poll_timeout(mut self, timeout: Duration) -> Self {
+        self.agent_builder = self.agent_builder.wait_timeout(timeout);
+        self
+    }
+
+    /// Set the maximum number of newly sent requests that may be queued up
+    /// waiting for the client's background agent to begin executing them.
+    ///
+    /// If requests are sent faster than the agent can accept them and the
+    /// queue fills up, sending further requests will wait until room is
+    /// available. Synchronous sends will block, while asynchronous sends will
+    /// return a future that does not make progress until then. This prevents
+    /// memory usage from growing without bound when requests are being
+    /// produced faster than they can be handled.
+    ///
+    /// Requests that have already been started by the agent do not count
+    /// towards this limit.
+    ///
+    /// The default capacity is 1024. A capacity of zero is treated as one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::HttpClient;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .request_queue_capacity(64)
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn request_queue_capacity(mut self, capacity: usize) -> Self {
+        self.agent_builder = self.agent_builder.request_queue_capacity(capacity);
+        self
+    }
+
+    /// Set the number of background agent threads used to execute requests.
+    ///
+    /// A single agent thread drives all of the client's transfers, so with
+    /// thousands of concurrent transfers it can become a bottleneck. Using
+    /// more threads spreads transfers across multiple cores, chosen for each
+    /// request according to the [`AgentDispatch`] strategy.
+    ///
+    /// Each thread has its own connection cache, so connections are only
+    /// reused by requests executed on the same thread, and the limits set by
+    /// [`max_connections`](HttpClientBuilder::max_connections),
+    /// [`max_connections_per_host`](HttpClientBuilder::max_connections_per_host)
+    /// and [`connection_cache_size`](HttpClientBuilder::connection_cache_size)
+    /// apply to each thread separately. Requests assigned to a
+    /// [`quota`](HttpClientBuilder::quota) are always executed on the same
+    /// thread, so that the quota is enforced for the whole client.
+    ///
+    /// The default is one thread. Zero is treated as one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::HttpClient;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .agent_threads(4)
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn agent_threads(mut self, n: usize) -> Self {
+        self.agent_builder = self.agent_builder.threads(n);
+        self
+    }
+
+    /// Set how requests are assigned to agent threads when using more than
+    /// one. See [`AgentDispatch`] for the available strategies.
+    ///
+    /// This has no effect unless
+    /// [`agent_threads`](HttpClientBuilder::agent_threads) is greater than
+    /// one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::{config::AgentDispatch, HttpClient};
+    ///
+    /// let client = HttpClient::builder()
+    ///     .agent_threads(4)
+    ///     .agent_dispatch(AgentDispatch::LeastLoaded)
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn agent_dispatch(mut self, dispatch: AgentDispatch) -> Self {
+        self.agent_builder = self.agent_builder.dispatch(dispatch);
+        self
+    }
+
+    /// Execute requests strictly one at a time, in the order they were sent.
+    ///
+    /// This is meant for tests. When requests are sent concurrently, such as
+    /// from multiple threads or tasks, the order in which they reach a server
+    /// and their responses arrive normally varies from run to run. With this
+    /// enabled, each request only begins once the previous one has completed,
+    /// so that tests against a mock server see the same interleaving every
+    /// time. It also implies a single [agent
+    /// thread](HttpClientBuilder::agent_threads).
+    ///
+    /// A request is in progress until its response body has been received
+    /// completely, so a response must be read to the end or dropped before
+    /// the next request can begin. Waiting for the response to a request
+    /// while holding on to the unread body of an earlier one can hang.
+    ///
+    /// Disabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::HttpClient;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .sequential_execution(true)
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn sequential_execution(mut self, enable: bool) -> Self {
+        self.agent_builder = self.agent_builder.sequential(enable);
+        self
+    }
+
+    /// Execute each request on the thread that sends it, instead of on a
+    /// background agent thread.
+    ///
+    /// This is meant for tools that send a request or two and exit, and do
+    /// not want a background thread or to wait for it to shut down. No agent
+    /// threads are spawned, and each request gets a connection of its own,
+    /// which is driven while waiting for the response and while reading the
+    /// response body. The transfer makes no progress while the response body
+    /// is not being read, and is aborted if the response is dropped before
+    /// its body has been read to the end.
+    ///
+    /// Since connections are not shared between requests, options that
+    /// concern the agent, such as connection limits, the connection cache,
+    /// quotas and the [in-flight limit](HttpClientBuilder::max_in_flight),
+    /// have no effect. Driving a transfer blocks the thread for short periods
+    /// of time, so asynchronous requests sent by such a client block the
+    /// executor they run on.
+    ///
+    /// Disabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::{prelude::*, HttpClient};
+    ///
+    /// let client = HttpClient::builder()
+    ///     .caller_thread_execution(true)
+    ///     .build()?;
+    ///
+    /// let text = client.get("https://example.org")?.text()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn caller_thread_execution(mut self, enable: bool) -> Self {
+        self.agent_builder = self.agent_builder.caller_thread(enable);
+        self
+    }
+
+    /// Set a limit on the number of requests that may be in progress at once.
+    ///
+    /// Without a limit, every request sent begins right away, which under a
+    /// sudden burst of requests can overwhelm servers or exhaust the file
+    /// descriptors available to the process. Once the limit is reached, new
+    /// requests wait in a queue until a request in progress completes, and
+    /// are then begun in order of their
+    /// [priority](crate::config::Configurable::priority).
+    ///
+    /// A request is in progress until its response body has been received
+    /// completely. Time spent waiting in the queue does not count towards the
+    /// request's [timeout](crate::config::Configurable::timeout). Like the
+    /// connection limits, the limit applies to each [agent
+    /// thread](HttpClientBuilder::agent_threads) separately.
+    ///
+    /// Setting this value to `0` disables the limit, which is the default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::HttpClient;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .max_in_flight(32)
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn max_in_flight(mut self, max: usize) -> Self {
+        self.agent_builder = self.agent_builder.max_in_flight(max);
+        self
+    }
+
+    /// Spread out the starts of requests to the same host over time.
+    ///
+    /// Requests sent while another request to the same host was started
+    /// less than the pacing interval ago wait until the interval has passed,
+    /// and are then begun one at a time in order of their
+    /// [priority](crate::config::Configurable::priority). This smooths out
+    /// bursts of requests independently of any rate limit. See [`Pacing`] for
+    /// details. Like the [in-flight limit](HttpClientBuilder::max_in_flight),
+    /// pacing applies to each [agent thread](HttpClientBuilder::agent_threads)
+    /// separately.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::{config::Pacing, HttpClient};
+    /// use std::time::Duration;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .pacing(Pacing::per_host(Duration::from_millis(100)))
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn pacing(mut self, pacing: Pacing) -> Self {
+        self.agent_builder = self.agent_builder.pacing(pacing);
+        self
+    }
+
+    /// Adjust the number of requests that may be in progress at once based on
+    /// whether the servers the client talks to show signs of being
+    /// overloaded.
+    ///
+    /// This protects struggling servers automatically: when requests start to
+    /// fail or slow down, fewer of them are sent at once, and as they recover
+    /// the limit is raised again. Requests above the limit wait to begin like
+    /// requests above the [in-flight limit](HttpClientBuilder::max_in_flight).
+    /// See [`AdaptiveConcurrency`](crate::config::AdaptiveConcurrency) for how
+    /// the limit is adjusted, and
+    /// [`AgentStats::concurrency_limit`] for getting the current limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::{config::AdaptiveConcurrency, HttpClient};
+    ///
+    /// let client = HttpClient::builder()
+    ///     .adaptive_concurrency(AdaptiveConcurrency::new().max_limit(32))
+    ///     .build()?;
+    ///
+    /// assert_eq!(client.agent_stats().concurrency_limit(), Some(16));
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn adaptive_concurrency(mut self, config: AdaptiveConcurrency) -> Self {
+        self.agent_builder = self.agent_builder.adaptive_concurrency(config);
+        self
+    }
+
+    /// Retry requests that fail to connect because the process ran out of
+    /// file descriptors or the system ran out of local ports, for up to the
+    /// given amount of time, instead of failing them right away.
+    ///
+    /// Under a burst of requests, such resource limits are usually only hit
+    /// until some of the connections in use are closed. When connecting fails
+    /// this way, the agent thread holds back new connection attempts, and
+    /// retries the failed requests after a delay that grows with every
+    /// failure. Since nothing has been sent when connecting fails, this is
+    /// safe for any request. Requests that keep failing for longer than the
+    /// given amount of time fail with an error of kind
+    /// [`ErrorKind::ConnectionFailed`](crate::error::ErrorKind::ConnectionFailed)
+    /// as usual.
+    ///
+    /// Every time a resource limit is hit, a warning identifying the limit is
+    /// logged, and an
+    /// [`AgentEventKind::ResourceExhausted`](crate::config::AgentEventKind::ResourceExhausted)
+    /// event is reported to the client's
+    /// [instrumentation](HttpClientBuilder::instrumentation).
+    ///
+    /// Disabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::HttpClient;
+    /// use std::time::Duration;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .retry_on_resource_exhaustion(Duration::from_secs(30))
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn retry_on_resource_exhaustion(mut self, max_wait: Duration) -> Self {
+        self.agent_builder = self.agent_builder.resource_exhaustion_retry(max_wait);
+        self
+    }
+
+    /// Set a limit on the total number of response body bytes that may be
+    /// buffered in memory at once across all responses from this client.
+    ///
+    /// Response body data is buffered as it is received until it is read by
+    /// the consumer. If consumers read more slowly than data arrives, the
+    /// amount of memory used can add up when many responses are in flight at
+    /// once. When this limit is reached, transfers that already have data
+    /// buffered are paused until consumers make room by reading, applying
+    /// backpressure to the server instead of using more memory.
+    ///
+    /// A response with no data buffered is always allowed to receive more, so
+    /// the limit may be exceeded by a small amount.
+    ///
+    /// By default no limit is enforced.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::HttpClient;
+    ///
+    /// let client = HttpClient::builder()
+    ///     // Buffer no more than 8 MiB of response data at once.
+    ///     .max_buffered_response_bytes(8 * 1024 * 1024)
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn max_buffered_response_bytes(mut self, bytes: usize) -> Self {
+        self.client_config.response_buffer_budget =
+            Some(Arc::new(crate::handler::BufferBudget::new(bytes)));
+        self
+    }
+
+    /// Set a function to run callbacks and complete response futures with,
+    /// instead of running them on the client's agent thread.
+    ///
+    /// The agent thread drives all of the client's transfers, so anything
+    /// slow that runs on it stalls every transfer in progress. This includes
+    /// callbacks such as the one set with
+    /// [`Configurable::on_stats`](crate::config::Configurable::on_stats), as
+    /// well as waking up the tasks waiting for responses, which some
+    /// executors do inline. When an executor is set, each of these is wrapped
+    /// in a function and handed to the executor to run instead, such as on a
+    /// thread pool or an async runtime.
+    ///
+    /// The executor must run every function it is given eventually, or
+    /// responses may never be received. Since statistics callbacks no longer
+    /// run before the response completes, they may be invoked after the
+    /// response has been received.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::HttpClient;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .callback_executor(|task| {
+    ///         std::thread::spawn(task);
+    ///     })
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn callback_executor<F>(mut self, executor: F) -> Self
+    where
+        F: Fn(Box<dyn FnOnce() + Send>) + Send + Sync + 'static,
+    {
+        self.client_config.callback_executor =
+            Some(crate::handler::CallbackExecutor(Arc::new(executor)));
+        self
+    }
+
+    /// Set instrumentation to receive events about the lifecycle of every
+    /// request sent by this client, and about the agent threads executing
+    /// them.
+    ///
+    /// Events include requests being enqueued, connections becoming ready,
+    /// response headers and body chunks arriving, requests completing or
+    /// failing, and agent threads waking up, along with the ID of the request
+    /// and timing information. This is meant for diagnosing latency in
+    /// production, where log messages alone are too coarse. See
+    /// [`Instrumentation`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::{config::{AgentEvent, AgentEventKind}, HttpClient};
+    ///
+    /// let client = HttpClient::builder()
+    ///     .instrumentation(|event: &AgentEvent| {
+    ///         if let AgentEventKind::RequestFailed { error } = event.kind() {
+    ///             eprintln!("request {:?} failed: {}", event.request_id(), error);
+    ///         }
+    ///     })
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn instrumentation(mut self, instrumentation: impl Instrumentation) -> Self {
+        let instrumentation = instrument::SharedInstrumentation::new(instrumentation);
+        self.agent_builder = self.agent_builder.instrumentation(instrumentation.clone());
+        self.client_config.instrumentation = Some(instrumentation);
+        self
+    }
+
+    /// Give a hint for how many requests this client is expected to execute
+    /// concurrently.
+    ///
+    /// The client will pre-allocate its internal bookkeeping for this many
+    /// active requests up front, avoiding the need to grow it as load
+    /// increases. This is only a hint for performance and does not limit how
+    /// many requests can actually be executed at once.
+    ///
+    /// By default no space is reserved up front.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::HttpClient;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .expected_concurrency(100)
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn expected_concurrency(mut self, n: usize) -> Self {
+        self.agent_builder = self.agent_builder.expected_concurrency(n);
+        self
+    }
+
+    /// Register a named quota limiting the requests assigned to it.
+    ///
+    /// Requests are assigned to the quota by adding a [`QuotaTag`] with the
+    /// same name to their extensions. Registering a quota with the same name
+    /// again replaces the earlier one. See [`Quota`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::{config::Quota, HttpClient};
+    ///
+    /// let client = HttpClient::builder()
+    ///     .quota("search", Quota::new().max_concurrent(8))
+    ///     .quota("indexing", Quota::new().max_concurrent(2).max_per_second(5))
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn quota(mut self, name: impl Into<String>, quota: Quota) -> Self {
+        self.agent_builder = self.agent_builder.quota(name.into(), quota);
+        self
+    }
+
+    /// Set what happens to responses that are still being received when the
+    /// last handle to the client is dropped.
+    ///
+    /// By default, responses keep the client alive until they are fully
+    /// received or dropped. See [`ShutdownPolicy`] for the available options.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::{config::ShutdownPolicy, HttpClient};
+    ///
+    /// let client = HttpClient::builder()
+    ///     // Abort outstanding responses as soon as the client goes away.
+    ///     .shutdown_policy(ShutdownPolicy::Abort)
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn shutdown_policy(mut self, policy: ShutdownPolicy) -> Self {
+        self.client_config.shutdown_policy = policy;
+        self
+    }
+
+    /// Add a function to call when the client is shut down using
+    /// [`HttpClient::shutdown`].
+    ///
+    /// Hooks are called once, after the client's requests have completed or
+    /// been aborted, in the order they were added. This allows subsystems used
+    /// alongside the client, such as caches or metrics exporters, to write out
+    /// their state deterministically before the process exits. Interceptors
+    /// and [`Instrumentation`] are notified of the shutdown before any hooks
+    /// are called.
+    ///
+    /// Hooks are not called if the client is simply dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::{config::RangeCache, HttpClient};
+    /// use std::time::Duration;
+    ///
+    /// let cache = RangeCache::new();
+    ///
+    /// let client = HttpClient::builder()
+    ///     .range_cache(cache.clone())
+    ///     .on_shutdown(move || {
+    ///         println!("cached {} bytes", cache.size());
+    ///     })
+    ///     .build()?;
+    ///
+    /// client.shutdown(Duration::from_secs(10));
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn on_shutdown(mut self, hook: impl FnOnce() + Send + 'static) -> Self {
+        self.shutdown_hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Set the maximum time-to-live (TTL) for connections to remain in the
+    /// connection cache.
+    ///
+    /// After requests are completed, if the underlying connection is
+    /// reusable, it is added to the connection cache to be reused to reduce
+    /// latency for future requests. This option controls how long such
+    /// connections should be still considered valid before being discarded.
+    ///
+    /// Old connections have a high risk of not working any more and thus
+    /// attempting to use them wastes time if the server has disconnected.
+    ///
+    /// The default TTL is 118 seconds.
+    pub fn connection_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.client_config.connection_cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Set a connector to open connections with, in place of the sockets curl
+    /// would otherwise create and connect itself.
+    ///
+    /// This allows supplying connections established in a special way, such
+    /// as through a VPN library or with custom socket options, while requests
+    /// are still sent over them as usual. Name resolution, proxies, and
+    /// [`Dialer`](crate::config::Dialer)s are applied first, and the connector
+    /// is asked to connect to the resulting IP address. See [`Connector`] for
+    /// details.
+    ///
+    /// Only TCP connections to IP addresses are supported when a connector is
+    /// set, so Unix sockets and HTTP/3 cannot be used.
+    pub fn connector(mut self, connector: impl Connector) -> Self {
+        let connector = Arc::new(ConnectorObj::new(connector));
+
+        self.agent_builder = self.agent_builder.connector(connector.clone());
+        self.client_config.connector = Some(connector);
+        self
+    }
+
+    /// Set the maximum amount of time a connection may be reused for, counted
+    /// from when it was established.
+    ///
+    /// Connections older than this are not used for new requests and are
+    /// closed instead. Unlike [`HttpClientBuilder::connection_cache_ttl`],
+    /// which only applies to connections sitting idle, this limits the total
+    /// lifetime of busy connections too. This helps to spread load evenly when
+    /// a load balancer changes the backends behind it, and avoids keeping TLS
+    /// sessions open for very long.
+    ///
+    /// The lifetime has a granularity of whole seconds. By default
+    /// connections may be reused for as long as they remain open.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::HttpClient;
+    /// use std::time::Duration;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .connection_max_lifetime(Duration::from_secs(300))
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn connection_max_lifetime(mut self, lifetime: Duration) -> Self {
+        self.client_config.connection_max_lifetime = Some(lifetime);
+        self
+    }
+
+    /// Set the maximum number of requests that may be sent over a single
+    /// connection before it is closed.
+    ///
+    /// Once a connection has carried this many requests, it is closed after
+    /// the last one completes instead of being kept for reuse. Whether each
+    /// transfer reused a connection, and whether it retired one, is reported
+    /// by [`TransferStats`](crate::TransferStats).
+    ///
+    /// By default there is no limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::HttpClient;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .connection_max_requests(1000)
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn connection_max_requests(mut self, max: usize) -> Self {
+        self.client_config.connection_request_limit = Some(Arc::new(
+            crate::handler::ConnectionRequestLimit::new(max.max(1)),
+        ));
+        self
+    }
+
+    /// Set the cache of permanent redirects to use, or `None` to disable
+    /// caching redirects.
+    ///
+    /// A cache can be shared between multiple clients by passing clones of
+    /// it. See [`RedirectCache`] for details on which redirects are cached.
+    ///
+    /// By default each client has its own cache, which only has an effect on
+    /// requests that follow redirects.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::HttpClient;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .redirect_cache(None)
+    ///     .build()?;
+    ///
+    /// assert!(client.redirect_cache().is_none());
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn redirect_cache(mut self, cache: Option<RedirectCache>) -> Self {
+        self.request_config.redirect_cache = cache;
+        self
+    }
+
+    /// Cache the byte ranges of resources received in `206 Partial Content`
+    /// responses in the given cache, and use them to answer later requests for
+    /// ranges of the same resources.
+    ///
+    /// See [`RangeCache`] for details. Clients do not cache ranges by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::{config::RangeCache, HttpClient};
+    ///
+    /// let client = HttpClient::builder()
+    ///     .range_cache(RangeCache::with_capacity(16 * 1024 * 1024))
+    ///     .build()?;
+    ///
+    /// assert!(client.range_cache().is_some());
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn range_cache(mut self, cache: RangeCache) -> Self {
+        self.request_config.range_cache = Some(cache);
+        self
+    }
+
+    /// Remember what servers are known to support, as learned from their
+    /// responses, in the given cache.
+    ///
+    /// See [`CapabilityCache`] for details. Clients do not have a capability
+    /// cache by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::{config::CapabilityCache, HttpClient};
+    ///
+    /// let client = HttpClient::builder()
+    ///     .capability_cache(CapabilityCache::new())
+    ///     .build()?;
+    ///
+    /// assert!(client.capability_cache().is_some());
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn capability_cache(mut self, cache: CapabilityCache) -> Self {
+        self.request_config.capability_cache = Some(cache);
+        self
+    }
+
+    /// Use the given environment instead of the process-wide state, such as
+    /// environment variables, that the client would otherwise consult.
+    ///
+    /// This keeps clients in the same process from affecting each other,
+    /// such as when running tests in parallel. See [`Environment`] for
+    /// details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::{config::Environment, HttpClient};
+    ///
+    /// let client = HttpClient::builder()
+    ///     .environment(Environment::isolated())
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn environment(mut self, environment: Environment) -> Self {
+        self.request_config.environment = Some(environment);
+        self
+    }
+
+    /// Remember host names that fail to resolve for the given duration, and
+    /// fail requests to those hosts immediately in the meantime.
+    ///
+    /// Without this, a burst of requests to a host that does not exist or
+    /// whose DNS servers are unresponsive each wait for the resolver to fail
+    /// on their own. With a negative cache, only the first request waits, and
+    /// subsequent requests within the TTL fail right away with the same kind
+    /// of error. Timeouts that occur before any connection was made are
+    /// treated as resolution failures. A debug-level tracing event is emitted
+    /// whenever an entry is added or a request fails because of one.
+    ///
+    /// Passing a zero duration disables negative caching, which is the
+    /// default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::HttpClient;
+    /// use std::time::Duration;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .dns_negative_cache(Duration::from_secs(5))
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn dns_negative_cache(mut self, ttl: Duration) -> Self {
+        self.request_config.dns_negative_cache = if ttl.is_zero() {
+            None
+        } else {
+            Some(crate::dns_negative_cache::DnsNegativeCache::new(ttl))
+        };
+        self
+    }
+
+    /// Limit the automatic retries sent by this client using a retry budget.
+    ///
+    /// Retries sent by helpers such as
+    /// [`ResumableUpload`](crate::upload::ResumableUpload) using this client
+    /// withdraw from the budget, and fail with
+    /// [`ErrorKind::RetryBudgetExhausted`](crate::error::ErrorKind::RetryBudgetExhausted)
+    /// once it is exhausted. See [`RetryBudget`] for details.
+    ///
+    /// No retry budget is used by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::{config::RetryBudget, HttpClient};
+    ///
+    /// let client = HttpClient::builder()
+    ///     .retry_budget(RetryBudget::new(0.2))
+    ///     .build()?;
+    ///
+    /// assert_eq!(client.retry_budget().unwrap().exhausted(), 0);
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn retry_budget(mut self, budget: RetryBudget) -> Self {
+        self.request_config.retry_budget = Some(budget);
+        self
+    }
+
+    /// Write an event for each stage of every request sent by this client to
+    /// the given writer, as one line of JSON per event.
+    ///
+    /// This is meant for feeding log pipelines that ingest structured records,
+    /// and does not depend on [`tracing`](https://docs.rs/tracing) or any
+    /// logger being configured. Each event is an object with the following
+    /// fields:
+    ///
+    /// - `event`: The kind of event, one of `request`, `response`, `error`,
+    ///   `body_complete`, `body_error`, or `body_dropped`.
+    /// - `id`: A number identifying the request the event belongs to, unique
+    ///   within this client. Each redirect followed is a separate request.
+    /// - `timestamp`: The time of the event in seconds since the Unix epoch.
+    ///
+    /// Depending on the kind of event, `method`, `uri`, `status`, `version`,
+    /// `kind`, `message`, `bytes`, and `elapsed_ms` fields are included as
+    /// well. Errors writing to the writer are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::HttpClient;
+    ///
+    /// let client = HttpClient::builder()
+    ///     .event_log(std::io::stderr())
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn event_log(mut self, writer: impl std::io::Write + Send + 'static) -> Self {
+        self.request_config.event_log = Some(crate::event_log::EventLog::new(writer));
+        self
     }
 
-    /// Add a request interceptor to the client.
+    /// Propagate distributed tracing context to servers in headers of the
+    /// given format, and record a client span for each request sent.
+    ///
+    /// See the [`trace_context`](crate::trace_context) module for details.
     ///
     /// # Availability
     ///
     /// This method is only available when the
-    /// [`unstable-interceptors`](index.html#unstable-interceptors) feature is
-    /// enabled.
-    #[cfg(feature = "unstable-interceptors")]
-    #[inline]
-    pub fn interceptor(self, interceptor: impl Interceptor + 'static) -> Self {
-        self.interceptor_impl(interceptor)
-    }
-
-    #[allow(unused)]
-    pub(crate) fn interceptor_impl(mut self, interceptor: impl Interceptor + 'static) -> Self {
-        self.interceptors.push(InterceptorObj::new(interceptor));
+    /// [`trace-context`](index.html#trace-context) feature is enabled.
+    #[cfg(feature = "trace-context")]
+    pub fn trace_propagation(mut self, propagation: crate::trace_context::Propagation) -> Self {
+        self.request_config.trace_propagation = Some(propagation);
         self
     }
 
-    /// Set a maximum number of simultaneous connections that this client is
-    /// allowed to keep open at one time.
+    /// Set the clock this client uses for its own time calculations, such as
+    /// quota rate limits, retry budgets, and cache expiration.
     ///
-    /// If set to a value greater than zero, no more than `max` connections will
-    /// be opened at one time. If executing a new request would require opening
-    /// a new connection, then the request will stay in a "pending" state until
-    /// an existing connection can be used or an active request completes and
-    /// can be closed, making room for a new connection.
+    /// This is mainly useful for tests, which can use a
+    /// [`TestClock`](crate::testing::TestClock) to fast-forward time instead
+    /// of sleeping. See [`Clock`] for details. The system clock is used by
+    /// default.
     ///
-    /// Setting this value to `0` disables the limit entirely.
+    /// # Examples
     ///
-    /// This is an effective way of limiting the number of sockets or file
-    /// descriptors that this client will open, though note that the client may
-    /// use file descriptors for purposes other than just HTTP connections.
+    /// ```
+    /// use isahc::{testing::TestClock, HttpClient};
     ///
-    /// By default this value is `0` and no limit is enforced.
+    /// let clock = TestClock::new();
     ///
-    /// To apply a limit per-host, see
-    /// [`HttpClientBuilder::max_connections_per_host`].
-    pub fn max_connections(mut self, max: usize) -> Self {
-        self.agent_builder = self.agent_builder.max_connections(max);
+    /// let client = HttpClient::builder()
+    ///     .clock(clock.clone())
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn clock(mut self, clock: impl Clock) -> Self {
+        let clock = clock::SharedClock::new(clock);
+        self.agent_builder = self.agent_builder.clock(clock.clone());
+        self.request_config.clock = Some(clock);
         self
     }
 
-    /// Set a maximum number of simultaneous connections that this client is
-    /// allowed to keep open to individual hosts at one time.
-    ///
-    /// If set to a value greater than zero, no more than `max` connections will
-    /// be opened to a single host at one time. If executing a new request would
-    /// require opening a new connection, then the request will stay in a
-    /// "pending" state until an existing connection can be used or an active
-    /// request completes and can be closed, making room for a new connection.
+    /// Restrict the hosts, addresses, and ports this client may send requests
+    /// to using a host policy.
     ///
-    /// Setting this value to `0` disables the limit entirely. By default this
-    /// value is `0` and no limit is enforced.
+    /// Requests that are not allowed by the policy fail with
+    /// [`ErrorKind::RequestBlocked`](crate::error::ErrorKind::RequestBlocked)
+    /// before any connection is made. This is checked for every request sent
+    /// while following redirects too. See [`HostPolicy`] for details.
     ///
-    /// To set a global limit across all hosts, see
-    /// [`HttpClientBuilder::max_connections`].
-    pub fn max_connections_per_host(mut self, max: usize) -> Self {
-        self.agent_builder = self.agent_builder.max_connections_per_host(max);
-        self
-    }
-
-    /// Set the size of the connection cache.
+    /// No host policy is used by default.
     ///
-    /// After requests are completed, if the underlying connection is reusable,
-    /// it is added to the connection cache to be reused to reduce latency for
-    /// future requests.
+    /// # Examples
     ///
-    /// Setting the size to `0` disables connection caching for all requests
-    /// using this client.
+    /// ```
+    /// use isahc::{config::HostPolicy, HttpClient};
     ///
-    /// By default this value is unspecified. A reasonable default size will be
-    /// chosen.
-    pub fn connection_cache_size(mut self, size: usize) -> Self {
-        self.agent_builder = self.agent_builder.connection_cache_size(size);
-        self.client_config.close_connections = size == 0;
+    /// let client = HttpClient::builder()
+    ///     .host_policy(HostPolicy::new().allow_ports([80, 443]))
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn host_policy(mut self, policy: HostPolicy) -> Self {
+        self.request_config.host_policy = Some(policy);
         self
     }
 
-    /// Set the maximum time-to-live (TTL) for connections to remain in the
-    /// connection cache.
+    /// Set how internationalized domain names in request URIs are handled.
     ///
-    /// After requests are completed, if the underlying connection is
-    /// reusable, it is added to the connection cache to be reused to reduce
-    /// latency for future requests. This option controls how long such
-    /// connections should be still considered valid before being discarded.
+    /// Requests to host names not allowed by the policy fail with
+    /// [`ErrorKind::InvalidRequest`](crate::error::ErrorKind::InvalidRequest)
+    /// before any connection is made. This is checked for every request sent
+    /// while following redirects too. See [`IdnaPolicy`] for details.
     ///
-    /// Old connections have a high risk of not working any more and thus
-    /// attempting to use them wastes time if the server has disconnected.
+    /// By default, any valid internationalized domain name is allowed.
     ///
-    /// The default TTL is 118 seconds.
-    pub fn connection_cache_ttl(mut self, ttl: Duration) -> Self {
-        self.client_config.connection_cache_ttl = Some(ttl);
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::{config::IdnaPolicy, HttpClient};
+    ///
+    /// let client = HttpClient::builder()
+    ///     .idna_policy(IdnaPolicy::new().strict(true))
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn idna_policy(mut self, policy: IdnaPolicy) -> Self {
+        self.request_config.idna_policy = Some(policy);
         self
     }
 
@@ -293,6 +1266,10 @@ impl HttpClientBuilder {
     /// resolver for host+port pairs.
     ///
     /// Note that DNS resolving is only performed when establishing a new
+    /// connection, so connections already open to a host are reused
+    /// regardless of the overrides given. Use
+    /// [`ResolveMap::add_all`](crate::config::ResolveMap::add_all) to map a
+    /// host to several addresses to fail over between.
     ///
     /// # Examples
     ///
@@ -432,15 +1409,39 @@ impl HttpClientBuilder {
         self
     }
 
-    /// Build an [`HttpClient`] using the configured options.
+    /// Build a [`Session`] using the configured options, for sending a
+    /// sequence of requests over a single connection.
+    ///
+    /// Any limits on the number of connections are replaced, since the
+    /// session only ever opens one connection.
     ///
     /// If the client fails to initialize, an error will be returned.
+    pub fn build_session(self) -> Result<Session, Error> {
+        self.max_connections(1)
+            .connection_cache_size(1)
+            .build()
+            .map(Session::new)
+    }
+
+    /// Build an [`HttpClient`] using the configured options.
+    ///
+    /// If the client fails to initialize, an error will be returned. This
+    /// includes configuring options that cannot work together, such as a
+    /// proxy along with a Unix socket dialer, or HTTP/3 without libcurl
+    /// support for it. All such problems are listed in a single error of kind
+    /// [`ErrorKind::ClientInitialization`].
     #[allow(unused_mut)]
     pub fn build(mut self) -> Result<HttpClient, Error> {
         if let Some(err) = self.error {
             return Err(err);
         }
 
+        // Reject options that cannot work together up front, instead of
+        // failing every request later.
+        ConfigConflicts::check(&self.request_config).map_err(|e| {
+            Error::with_context(ErrorKind::ClientInitialization, Some(e.to_string()), e)
+        })?;
+
         // Add cookie interceptor if enabled.
         #[cfg(feature = "cookies")]
         {
@@ -454,26 +1455,29 @@ impl HttpClientBuilder {
             self = self.interceptor_impl(DefaultHeadersInterceptor::from(default_headers));
         }
 
+        let agent = self
+            .agent_builder
+            .spawn_group()
+            .map_err(|e| Error::new(ErrorKind::ClientInitialization, e))?;
+
         #[cfg(not(feature = "cookies"))]
         let inner = Inner {
-            agent: self
-                .agent_builder
-                .spawn()
-                .map_err(|e| Error::new(ErrorKind::ClientInitialization, e))?,
+            agent: RwLock::new(Arc::new(agent)),
+            agent_builder: self.agent_builder,
             client_config: self.client_config,
             request_config: self.request_config,
             interceptors: self.interceptors,
+            shutdown_hooks: Mutex::new(Some(self.shutdown_hooks)),
         };
 
         #[cfg(feature = "cookies")]
         let inner = Inner {
-            agent: self
-                .agent_builder
-                .spawn()
-                .map_err(|e| Error::new(ErrorKind::ClientInitialization, e))?,
+            agent: RwLock::new(Arc::new(agent)),
+            agent_builder: self.agent_builder,
             client_config: self.client_config,
             request_config: self.request_config,
             interceptors: self.interceptors,
+            shutdown_hooks: Mutex::new(Some(self.shutdown_hooks)),
             cookie_jar: self.cookie_jar,
         };
 
@@ -519,7 +1523,7 @@ impl<K, V> HeaderPair<K, V> for (K, V) {
     }
 }
 
-impl<'a, K: Copy, V: Copy> HeaderPair<K, V> for &'a (K, V) {
+impl<K: Copy, V: Copy> HeaderPair<K, V> for &(K, V) {
     fn pair(self) -> (K, V) {
         (self.0, self.1)
     }
@@ -553,6 +1557,15 @@ impl<'a, K: Copy, V: Copy> HeaderPair<K, V> for &'a (K, V) {
 /// different servers. If you are creating an API client library, that might be
 /// a good place to maintain your own internal client.
 ///
+/// # Forking
+///
+/// On Unix, a client created before the process forks can continue to be used
+/// in the child process. Since the child does not inherit the client's
+/// background agent thread, the client will detect that it is being used in a
+/// new process and transparently start a new agent when the next request is
+/// sent. Connections and other state belonging to the parent's agent are left
+/// untouched and are not shared with the child.
+///
 /// # Examples
 ///
 /// ```no_run
@@ -600,8 +1613,12 @@ pub struct HttpClient {
 }
 
 struct Inner {
-    /// This is how we talk to our background agent thread.
-    agent: agent::Handle,
+    /// This is how we talk to our background agent threads.
+    agent: RwLock<Arc<agent::Group>>,
+
+    /// Configuration for the agents, kept so that new ones can be spawned if
+    /// the client is used in a forked child process.
+    agent_builder: AgentBuilder,
 
     /// Client-wide request configuration.
     client_config: ClientConfig,
@@ -612,11 +1629,37 @@ struct Inner {
     /// Registered interceptors that requests should pass through.
     interceptors: Vec<InterceptorObj>,
 
+    /// Functions to call on shutdown, or `None` once they have been called.
+    shutdown_hooks: Mutex<Option<Vec<ShutdownHook>>>,
+
     /// Configured cookie jar, if any.
     #[cfg(feature = "cookies")]
     cookie_jar: Option<crate::cookies::CookieJar>,
 }
 
+impl Inner {
+    /// Notify interceptors, instrumentation, and shutdown hooks that the
+    /// client has shut down, unless they have been already.
+    fn notify_shutdown(&self) {
+        let hooks = match self.shutdown_hooks.lock().unwrap().take() {
+            Some(hooks) => hooks,
+            None => return,
+        };
+
+        for interceptor in &self.interceptors {
+            interceptor.shutdown();
+        }
+
+        if let Some(instrumentation) = self.client_config.instrumentation.as_ref() {
+            instrumentation.shutdown();
+        }
+
+        for hook in hooks {
+            hook();
+        }
+    }
+}
+
 impl HttpClient {
     /// Create a new HTTP client using the default configuration.
     ///
@@ -625,6 +1668,38 @@ impl HttpClient {
         HttpClientBuilder::default().build()
     }
 
+    /// Create a new HTTP client that answers requests with the given handler,
+    /// instead of sending them over the network.
+    ///
+    /// This is meant for unit tests of code that uses a client, which can then
+    /// return canned responses deterministically without starting a server.
+    /// Requests still go through the client's configuration, so redirects,
+    /// cookies, default headers and the like behave as they would with a real
+    /// server. Configuration that only affects the network connection, such as
+    /// timeouts or TLS options, has no effect. To configure the client
+    /// further, use [`HttpClientBuilder::handler`] instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::{prelude::*, AsyncBody, HttpClient, Response};
+    ///
+    /// let client = HttpClient::with_handler(|request| {
+    ///     assert_eq!(request.uri().path(), "/hello");
+    ///     Response::new(AsyncBody::from("hello world"))
+    /// })?;
+    ///
+    /// let mut response = client.get("http://example.org/hello")?;
+    /// assert_eq!(response.text()?, "hello world");
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn with_handler<F>(handler: F) -> Result<Self, Error>
+    where
+        F: Fn(Request<AsyncBody>) -> Response<AsyncBody> + Send + Sync + 'static,
+    {
+        HttpClientBuilder::default().handler(handler).build()
+    }
+
     /// Get a reference to a global client instance.
     ///
     /// TODO: Stabilize.
@@ -651,6 +1726,181 @@ impl HttpClient {
         self.inner.cookie_jar.as_ref()
     }
 
+    /// Get the cache of permanent redirects used by this HTTP client, if
+    /// redirect caching is enabled.
+    pub fn redirect_cache(&self) -> Option<&RedirectCache> {
+        self.inner.request_config.redirect_cache.as_ref()
+    }
+
+    /// Get the cache of byte ranges used by this HTTP client, if range caching
+    /// is enabled.
+    pub fn range_cache(&self) -> Option<&RangeCache> {
+        self.inner.request_config.range_cache.as_ref()
+    }
+
+    /// Get the cache of server capabilities used by this HTTP client, if it
+    /// has one.
+    pub fn capability_cache(&self) -> Option<&CapabilityCache> {
+        self.inner.request_config.capability_cache.as_ref()
+    }
+
+    /// Export a snapshot of the cookies, permanent redirects, and host name
+    /// resolution failures this client has learned, so that they can be
+    /// restored into a new client later using
+    /// [`import_state`](HttpClient::import_state).
+    ///
+    /// # Availability
+    ///
+    /// This method is only available when the [`state`](index.html#state)
+    /// feature is enabled.
+    #[cfg(feature = "state")]
+    pub fn export_state(&self) -> crate::ClientState {
+        crate::ClientState::export(
+            #[cfg(feature = "cookies")]
+            self.cookie_jar(),
+            self.redirect_cache(),
+            self.inner.request_config.dns_negative_cache.as_ref(),
+            self.clock().now(),
+        )
+    }
+
+    /// Import state previously exported from a client using
+    /// [`export_state`](HttpClient::export_state).
+    ///
+    /// Imported entries are merged into the existing state of this client,
+    /// replacing any existing entries for the same cookie, redirect, or host.
+    /// Entries for features this client does not have enabled, such as
+    /// cookies when this client has no cookie jar, are ignored, as are
+    /// entries that have expired since they were exported.
+    ///
+    /// # Availability
+    ///
+    /// This method is only available when the [`state`](index.html#state)
+    /// feature is enabled.
+    #[cfg(feature = "state")]
+    pub fn import_state(&self, state: crate::ClientState) {
+        state.import(
+            #[cfg(feature = "cookies")]
+            self.cookie_jar(),
+            self.redirect_cache(),
+            self.inner.request_config.dns_negative_cache.as_ref(),
+            self.clock().now(),
+        )
+    }
+
+    /// Get the retry budget limiting automatic retries sent by this client, if
+    /// one is configured.
+    pub fn retry_budget(&self) -> Option<&RetryBudget> {
+        self.inner.request_config.retry_budget.as_ref()
+    }
+
+    /// Get the clock this client uses for its own time calculations.
+    #[cfg_attr(not(feature = "blocking"), allow(dead_code))]
+    pub(crate) fn clock(&self) -> clock::SharedClock {
+        self.inner.request_config.clock.clone().unwrap_or_default()
+    }
+
+    /// Get statistics about the event loop of this client's background agent
+    /// thread.
+    ///
+    /// If the client uses multiple agent threads, the statistics of all of
+    /// them are added together.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::HttpClient;
+    ///
+    /// let client = HttpClient::new()?;
+    /// client.get("https://example.org")?;
+    ///
+    /// let stats = client.agent_stats();
+    /// println!(
+    ///     "{} iterations, {:?} busy, {:?} waiting",
+    ///     stats.iterations(),
+    ///     stats.busy_time(),
+    ///     stats.wait_time(),
+    /// );
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn agent_stats(&self) -> AgentStats {
+        self.inner.agent.read().unwrap().stats()
+    }
+
+    /// Close all idle connections in the connection cache, and make sure that
+    /// every request sent after this returns opens a new connection instead
+    /// of reusing an existing one.
+    ///
+    /// This is useful for long-running services after a DNS or load balancer
+    /// change, when connections to the old endpoints should no longer be used.
+    /// Requests already in progress are not interrupted; their connections are
+    /// closed once they complete.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::HttpClient;
+    ///
+    /// let client = HttpClient::new()?;
+    /// client.get("https://example.org")?;
+    ///
+    /// // Connect to example.org again on the next request.
+    /// client.clear_connections()?;
+    /// client.get("https://example.org")?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn clear_connections(&self) -> Result<(), Error> {
+        self.agent()?.clear_connections()
+    }
+
+    /// Shut down the client gracefully, waiting up to the given amount of time
+    /// for requests in progress to complete.
+    ///
+    /// Once called, the client stops accepting new requests, and sending one
+    /// fails with an error of kind [`ErrorKind::ClientClosed`]. This applies
+    /// to all clones of the client. Requests already sent are given until the
+    /// timeout passes to complete, including receiving their response bodies.
+    /// Any that are still in progress after that are aborted and fail with an
+    /// error of kind [`ErrorKind::ClientClosed`].
+    ///
+    /// This blocks until the client's background agent threads have stopped,
+    /// so that it is safe to exit the process afterwards. Returns true if all
+    /// requests completed before the timeout, or false if any had to be
+    /// aborted.
+    ///
+    /// Once the agent threads have stopped, the shutdown is announced to the
+    /// client's interceptors, its [`Instrumentation`], and any hooks added
+    /// using [`HttpClientBuilder::on_shutdown`], so that they can flush what
+    /// they have buffered. This happens only the first time the client is shut
+    /// down.
+    ///
+    /// Calling this from a callback that runs on the client's own agent thread
+    /// begins the shutdown without waiting for it, and returns false.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::HttpClient;
+    /// use std::time::Duration;
+    ///
+    /// let client = HttpClient::new()?;
+    ///
+    /// // ...
+    ///
+    /// if !client.shutdown(Duration::from_secs(10)) {
+    ///     eprintln!("some requests did not complete in time");
+    /// }
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn shutdown(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let clean = self.inner.agent.read().unwrap().clone().shutdown(deadline);
+
+        self.inner.notify_shutdown();
+
+        clean
+    }
+
     /// Send a GET request to the given URI.
     ///
     /// To customize the request further, see [`HttpClient::send`]. To execute
@@ -667,6 +1917,7 @@ impl HttpClient {
     /// # Ok::<(), isahc::Error>(())
     /// ```
     #[inline]
+    #[cfg(feature = "blocking")]
     pub fn get<U>(&self, uri: U) -> Result<Response<Body>, Error>
     where
         http::Uri: TryFrom<U>,
@@ -709,6 +1960,7 @@ impl HttpClient {
     /// # Ok::<(), isahc::Error>(())
     /// ```
     #[inline]
+    #[cfg(feature = "blocking")]
     pub fn head<U>(&self, uri: U) -> Result<Response<Body>, Error>
     where
         http::Uri: TryFrom<U>,
@@ -720,19 +1972,123 @@ impl HttpClient {
         }
     }
 
-    /// Send a HEAD request to the given URI asynchronously.
+    /// Send a HEAD request to the given URI asynchronously.
+    ///
+    /// To customize the request further, see [`HttpClient::send_async`]. To
+    /// execute the request synchronously, see [`HttpClient::head`].
+    pub fn head_async<U>(&self, uri: U) -> ResponseFuture<'_>
+    where
+        http::Uri: TryFrom<U>,
+        <http::Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        match http::Request::head(uri).body(()) {
+            Ok(request) => self.send_async(request),
+            Err(e) => ResponseFuture::error(Error::from_any(e)),
+        }
+    }
+
+    /// Prepare a download of the resource at the given URI into a writer,
+    /// which can resume an earlier partial download.
+    ///
+    /// See [`Download`](crate::download::Download) for details.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::HttpClient;
+    /// use std::fs::File;
+    ///
+    /// let client = HttpClient::new()?;
+    /// let mut file = File::create("archive.tar.gz")?;
+    ///
+    /// client
+    ///     .download("https://example.org/archive.tar.gz")
+    ///     .write_to(&mut file)?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    #[cfg(feature = "blocking")]
+    pub fn download<U>(&self, uri: U) -> crate::download::Download
+    where
+        http::Uri: TryFrom<U>,
+        <http::Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        let uri = http::Uri::try_from(uri).map_err(|e| Error::from(e.into()));
+
+        crate::download::Download::new(self.clone(), uri)
+    }
+
+    /// Follow the resource at the given URI, which is only ever appended to,
+    /// returning the bytes appended to it as they show up.
+    ///
+    /// See [`Tail`](crate::tail::Tail) for details.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::{tail::TailEvent, HttpClient};
+    /// use std::time::Duration;
+    ///
+    /// let client = HttpClient::new()?;
+    ///
+    /// let tail = client
+    ///     .tail("https://example.org/app.log")
+    ///     .interval(Duration::from_secs(5));
+    ///
+    /// for event in tail {
+    ///     if let TailEvent::Appended(bytes) = event? {
+    ///         print!("{}", String::from_utf8_lossy(&bytes));
+    ///     }
+    /// }
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    #[cfg(feature = "blocking")]
+    pub fn tail<U>(&self, uri: U) -> crate::tail::Tail
+    where
+        http::Uri: TryFrom<U>,
+        <http::Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        let uri = http::Uri::try_from(uri).map_err(|e| Error::from(e.into()));
+
+        crate::tail::Tail::new(self.clone(), uri)
+    }
+
+    /// Check each step of reaching the server at the given URI separately,
+    /// and report which step fails and why.
     ///
-    /// To customize the request further, see [`HttpClient::send_async`]. To
-    /// execute the request synchronously, see [`HttpClient::head`].
-    pub fn head_async<U>(&self, uri: U) -> ResponseFuture<'_>
+    /// The host name is resolved, a TCP connection is opened to one of its
+    /// addresses, and finally a `HEAD` request is sent using this client,
+    /// which also checks the TLS handshake for `https` URIs. Checking stops at
+    /// the first step that fails. See [`Diagnosis`](crate::diagnose::Diagnosis)
+    /// for details on the report.
+    ///
+    /// The DNS and TCP probes use the system resolver and a plain socket
+    /// rather than this client's connection settings, and the TCP probe uses
+    /// the client's connect timeout, or 10 seconds if none is set.
+    ///
+    /// An error is only returned if the URI is not an absolute `http` or
+    /// `https` URI. Failures of the steps themselves are part of the report.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::HttpClient;
+    ///
+    /// let diagnosis = HttpClient::new()?.diagnose("https://example.org")?;
+    ///
+    /// if let Some(stage) = diagnosis.failed_stage() {
+    ///     println!("cannot reach example.org: {} failed", stage);
+    /// }
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    #[cfg(feature = "blocking")]
+    pub fn diagnose<U>(&self, uri: U) -> Result<crate::diagnose::Diagnosis, Error>
     where
         http::Uri: TryFrom<U>,
         <http::Uri as TryFrom<U>>::Error: Into<http::Error>,
     {
-        match http::Request::head(uri).body(()) {
-            Ok(request) => self.send_async(request),
-            Err(e) => ResponseFuture::error(Error::from_any(e)),
-        }
+        let uri = http::Uri::try_from(uri).map_err(|e| Error::from(e.into()))?;
+
+        crate::diagnose::diagnose(self, uri, self.inner.request_config.connect_timeout)
     }
 
     /// Send a POST request to the given URI with a given request body.
@@ -753,6 +2109,7 @@ impl HttpClient {
     /// }"#)?;
     /// # Ok::<(), isahc::Error>(())
     #[inline]
+    #[cfg(feature = "blocking")]
     pub fn post<U, B>(&self, uri: U, body: B) -> Result<Response<Body>, Error>
     where
         http::Uri: TryFrom<U>,
@@ -801,6 +2158,7 @@ impl HttpClient {
     /// # Ok::<(), isahc::Error>(())
     /// ```
     #[inline]
+    #[cfg(feature = "blocking")]
     pub fn put<U, B>(&self, uri: U, body: B) -> Result<Response<Body>, Error>
     where
         http::Uri: TryFrom<U>,
@@ -835,6 +2193,7 @@ impl HttpClient {
     /// To customize the request further, see [`HttpClient::send`]. To execute
     /// the request asynchronously, see [`HttpClient::delete_async`].
     #[inline]
+    #[cfg(feature = "blocking")]
     pub fn delete<U>(&self, uri: U) -> Result<Response<Body>, Error>
     where
         http::Uri: TryFrom<U>,
@@ -888,6 +2247,19 @@ impl HttpClient {
     ///
     /// To execute a request asynchronously, see [`HttpClient::send_async`].
     ///
+    /// Calling this from a callback that runs on the client's own agent
+    /// thread, such as one set with [`Configurable::on_stats`], fails with an
+    /// error of kind [`ErrorKind::InvalidRequest`] instead of blocking the
+    /// thread that would have to complete the request forever. Use
+    /// [`HttpClientBuilder::callback_executor`] to run such callbacks
+    /// elsewhere.
+    ///
+    /// This is the only misuse that is detected. In particular, calling this
+    /// from a thread that drives an async runtime blocks the runtime until the
+    /// response arrives, and deadlocks if completing the request depends on a
+    /// task of the same runtime, such as one feeding the request body. Use
+    /// [`HttpClient::send_async`] in asynchronous code instead.
+    ///
     /// # Examples
     ///
     /// ```no_run
@@ -906,16 +2278,30 @@ impl HttpClient {
     /// assert!(response.status().is_success());
     /// # Ok::<(), isahc::Error>(())
     /// ```
+    #[cfg(feature = "blocking")]
     pub fn send<B>(&self, request: Request<B>) -> Result<Response<Body>, Error>
     where
         B: Into<Body>,
     {
+        if self.inner.agent.read().unwrap().is_current_thread() {
+            return Err(Error::new(ErrorKind::InvalidRequest, SendOnAgentThread));
+        }
+
+        #[cfg(feature = "trace-context")]
+        let request = crate::trace_context::capture(request);
+
         let span = tracing::debug_span!(
             "send",
             method = ?request.method(),
             uri = ?request.uri(),
         );
 
+        let read_timeout = request
+            .extensions()
+            .get::<RequestConfig>()
+            .and_then(|config| config.read_timeout)
+            .or(self.inner.request_config.read_timeout);
+
         let mut writer_maybe = None;
 
         let request = request.map(|body| {
@@ -935,7 +2321,12 @@ impl HttpClient {
                     // ensures that it is polled first and thus the request is
                     // initiated before we attempt to write the request body.
                     let (response, _) = try_zip(self.send_async_inner(request), async move {
-                        writer.write().await.map_err(Error::from)
+                        match writer.write().await {
+                            // The transfer stopped reading the body, such as
+                            // when the server rejected the request early.
+                            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                            result => result.map_err(Error::from),
+                        }
                     })
                     .await?;
 
@@ -947,7 +2338,7 @@ impl HttpClient {
             .instrument(span),
         )?;
 
-        Ok(response.map(|body| body.into_sync()))
+        Ok(response.map(|body| body.into_sync(read_timeout)))
     }
 
     /// Send an HTTP request and return the HTTP response asynchronously.
@@ -1001,16 +2392,93 @@ impl HttpClient {
     where
         B: Into<AsyncBody>,
     {
+        #[cfg(feature = "trace-context")]
+        let request = crate::trace_context::capture(request);
+
         let span = tracing::debug_span!(
             "send_async",
             method = ?request.method(),
             uri = ?request.uri(),
         );
 
-        ResponseFuture::new(
-            self.send_async_inner(request.map(Into::into))
-                .instrument(span),
-        )
+        let mut request = request.map(Into::into);
+
+        // Make the request cancelable through the returned future.
+        let cancel = match request.extensions().get::<CancelHandle>() {
+            Some(cancel) => cancel.clone(),
+            None => {
+                let cancel = CancelHandle::new();
+                request.extensions_mut().insert(cancel.clone());
+                cancel
+            }
+        };
+
+        ResponseFuture::new(self.send_async_inner(request).instrument(span))
+            .with_cancel_handle(cancel)
+    }
+
+    /// Prepare a request exactly as if it were sent, without sending it, and
+    /// return the request that would have been sent.
+    ///
+    /// The request is merged with the client's configuration and passes
+    /// through the same steps as a request being sent, including default
+    /// headers, cookies, authentication and URL normalization, so the request
+    /// returned shows what a server would receive. This is useful for
+    /// debugging how the client's configuration affects requests, or for
+    /// testing request signing. The body of the request is returned as well,
+    /// unread.
+    ///
+    /// A few headers are added by curl on its own as the request is written
+    /// to the connection, such as `Host` and `Content-Length`, and are not
+    /// included.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::{prelude::*, HttpClient};
+    ///
+    /// let client = HttpClient::builder()
+    ///     .default_header("x-api-key", "secret")
+    ///     .build()?;
+    ///
+    /// let request = client.dry_run(isahc::Request::get("https://example.org").body(())?)?;
+    ///
+    /// assert_eq!(request.headers()["x-api-key"], "secret");
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    #[cfg(feature = "blocking")]
+    pub fn dry_run<B>(&self, request: Request<B>) -> Result<Request<AsyncBody>, Error>
+    where
+        B: Into<AsyncBody>,
+    {
+        block_on(self.dry_run_async(request))
+    }
+
+    /// Prepare a request exactly as if it were sent asynchronously, without
+    /// sending it, and return the request that would have been sent.
+    ///
+    /// See [`HttpClient::dry_run`] for details.
+    pub async fn dry_run_async<B>(&self, request: Request<B>) -> Result<Request<AsyncBody>, Error>
+    where
+        B: Into<AsyncBody>,
+    {
+        let dry_run = DryRun::default();
+        let mut request = request.map(Into::into);
+        request.extensions_mut().insert(dry_run.clone());
+
+        self.send_async_inner(request).await?;
+
+        let request = dry_run.0.lock().unwrap().take();
+
+        request.ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidRequest,
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "request was answered without being sent",
+                ),
+            )
+        })
     }
 
     /// Actually send the request. All the public methods go through here.
@@ -1028,6 +2496,17 @@ impl HttpClient {
                 .insert(self.inner.request_config.clone());
         }
 
+        // Keep the addresses checked by the host policy for the lifetime of
+        // this request, including any redirects.
+        let has_host_policy = matches!(
+            request.extensions().get::<RequestConfig>(),
+            Some(config) if config.host_policy.is_some()
+        );
+
+        if has_host_policy && request.extensions().get::<PinnedAddresses>().is_none() {
+            request.extensions_mut().insert(PinnedAddresses::default());
+        }
+
         let ctx = interceptor::Context {
             invoker: Arc::new(self),
             interceptors: &self.inner.interceptors,
@@ -1036,26 +2515,153 @@ impl HttpClient {
         ctx.send(request).await
     }
 
+    /// Get the client's agents.
+    ///
+    /// If the client was created before the current process was forked, then
+    /// the agent threads only exist in the parent process, so new agents are
+    /// spawned for this process to use.
+    fn agent(&self) -> Result<Arc<agent::Group>, Error> {
+        let agent = self.inner.agent.read().unwrap().clone();
+
+        if !agent.is_inherited() {
+            return Ok(agent);
+        }
+
+        let mut agent = self.inner.agent.write().unwrap();
+
+        // Another thread may have beaten us to it.
+        if agent.is_inherited() {
+            tracing::debug!("client used after fork, spawning new agents");
+
+            *agent = Arc::new(
+                self.inner
+                    .agent_builder
+                    .spawn_group()
+                    .map_err(|e| Error::new(ErrorKind::ClientInitialization, e))?,
+            );
+        }
+
+        Ok(agent.clone())
+    }
+
     fn create_easy_handle(
         &self,
+        agent: Option<&agent::Handle>,
         mut request: Request<AsyncBody>,
-    ) -> Result<
-        (
-            curl::easy::Easy2<RequestHandler>,
-            impl Future<Output = Result<Response<ResponseBodyReader>, Error>>,
-        ),
-        curl::Error,
-    > {
+    ) -> Result<(EasyHandle, impl Future<Output = ResponseResult>), curl::Error> {
+        let expect_continue = request
+            .extensions()
+            .get::<RequestConfig>()
+            .unwrap()
+            .expect_continue
+            .clone()
+            .unwrap_or_default();
+
+        // Use length given in Content-Length header, or the size defined by the
+        // body itself.
+        let content_length = request
+            .headers()
+            .get("Content-Length")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok());
+
+        // A request asking to switch protocols gets a request body that stays
+        // open, which becomes the writing half of the connection if the server
+        // agrees to switch. Any body the request already has is sent first,
+        // with its length given in the Content-Length header so that the
+        // server knows where it ends.
+        let is_upgrade = request.headers().contains_key(http::header::UPGRADE)
+            && (request.body().is_empty() || request.body().len().is_some());
+        let upgrade_writer = if is_upgrade {
+            let (reader, writer) = sluice::pipe::pipe();
+            let body = std::mem::take(request.body_mut());
+
+            if let Some(len) = body.len().filter(|_| content_length.is_none()) {
+                if len > 0 {
+                    request
+                        .headers_mut()
+                        .insert(http::header::CONTENT_LENGTH, len.into());
+                }
+            }
+
+            *request.body_mut() = AsyncBody::from_reader(body.chain(reader));
+            Some(writer)
+        } else {
+            None
+        };
+
         // Prepare the request plumbing.
         let body = std::mem::take(request.body_mut());
         let has_body = !body.is_empty();
-        let body_length = body.len();
-        let (handler, future) = RequestHandler::new(body);
 
-        let mut easy = curl::easy::Easy2::new(handler);
+        // The checksum of a streamed body is only known once it has been sent,
+        // so it goes in a trailer. Trailers can only follow a chunked body.
+        let trailer_checksum = request
+            .extensions()
+            .get::<RequestConfig>()
+            .unwrap()
+            .body_checksum
+            .filter(|checksum| {
+                has_body
+                    && !is_upgrade
+                    && body.as_bytes().is_none()
+                    && !request.headers().contains_key(checksum.header_name())
+            });
+
+        if let Some(checksum) = trailer_checksum {
+            request.headers_mut().remove(http::header::CONTENT_LENGTH);
+            request.headers_mut().insert(
+                http::header::TRAILER,
+                HeaderValue::from(checksum.header_name()),
+            );
+        }
+
+        let body_length = content_length
+            .or_else(|| body.len())
+            .filter(|_| !is_upgrade && trailer_checksum.is_none());
+
+        // Small POST bodies already in memory are handed to curl up front, so
+        // that they can be sent in the same write as the request headers.
+        let small_post_body = Some(&body)
+            .filter(|_| request.method() == http::Method::POST && content_length.is_none())
+            .and_then(|body| body.as_bytes())
+            .filter(|bytes| expect_continue.is_skipped_for(Some(bytes.len() as u64)))
+            .map(<[u8]>::to_vec);
+
+        let cancellation = request
+            .extensions()
+            .get::<CancelHandle>()
+            .map(|handle| handle.0.clone())
+            .unwrap_or_default();
+
+        let (mut handler, future) = RequestHandler::new(body, cancellation);
+
+        if let Some(agent) = agent {
+            handler.set_agent_liveness(agent.liveness().clone());
+        }
+
+        handler.set_upgrade_writer(upgrade_writer);
+        handler.set_request_body_checksum(trailer_checksum);
+
+        if let Some(budget) = self.inner.client_config.response_buffer_budget.as_ref() {
+            handler.set_buffer_budget(budget.clone());
+        }
+
+        handler.set_callback_executor(self.inner.client_config.callback_executor.clone());
+        handler.set_instrumentation(self.inner.client_config.instrumentation.clone());
+
+        // Reuse a handle from a previous request if one is available.
+        let mut easy = match agent.and_then(agent::Handle::take_idle_handle) {
+            Some(mut easy) => {
+                *easy.get_mut() = handler;
+                easy
+            }
+            None => curl::easy::Easy2::new(handler),
+        };
 
         // Set whether curl should generate verbose debug data for us to log.
-        easy.verbose(easy.get_ref().is_debug_enabled())?;
+        // Instrumentation relies on it to tell when a connection is ready.
+        easy.verbose(easy.get_ref().is_debug_enabled() || easy.get_ref().is_instrumented())?;
 
         // Disable connection reuse logs if connection cache is disabled.
         if self.inner.client_config.close_connections {
@@ -1072,15 +2678,107 @@ impl HttpClient {
         request_config.set_opt(&mut easy)?;
         self.inner.client_config.set_opt(&mut easy)?;
 
+        // Switching protocols only exists in HTTP/1.1, and the connection
+        // cannot be used for anything else afterwards.
+        if is_upgrade {
+            easy.http_version(curl::easy::HttpVersion::V11)?;
+            easy.forbid_reuse(true)?;
+        }
+
+        // Curl only sends trailers with chunked encoding, which only exists in
+        // HTTP/1.1.
+        if trailer_checksum.is_some() {
+            easy.http_version(curl::easy::HttpVersion::V11)?;
+        }
+
+        // Must come after the client's options, since it decides whether
+        // connections are kept for reuse by default.
+        if request_config.fresh_connection == Some(true) {
+            easy.fresh_connect(true)?;
+            easy.forbid_reuse(true)?;
+        }
+
+        // Look up the proxy in the client's own environment, and keep curl
+        // from reading the one of the process.
+        if let Some(environment) = request_config.environment.as_ref() {
+            if request_config.proxy.is_none() {
+                easy.proxy(&environment.proxy(request.uri()).unwrap_or_default())?;
+
+                if request_config.proxy_blacklist.is_none() {
+                    easy.noproxy(&environment.no_proxy().unwrap_or_default())?;
+                }
+            }
+        }
+
+        easy.get_mut()
+            .set_interrupt_flag(request_config.interrupt_flag.clone());
+        easy.get_mut()
+            .set_headers_timeout(request_config.headers_timeout);
+        easy.get_mut().set_connection_request_limit(
+            self.inner.client_config.connection_request_limit.clone(),
+        );
+
+        let on_stats = request_config.on_stats.clone();
+
+        // Export the statistics of every transfer.
+        #[cfg(feature = "metrics")]
+        let on_stats = Some(crate::metrics::exporting(request.uri(), on_stats));
+
+        easy.get_mut().set_on_stats(on_stats);
+        easy.get_mut()
+            .set_collect_metrics(request_config.enable_metrics == Some(true));
+        easy.get_mut()
+            .set_transfer_log(request_config.transfer_messages);
+
+        // Curl only reports messages if verbose output is enabled.
+        if easy.get_ref().has_transfer_log() {
+            easy.verbose(true)?;
+        }
+
+        easy.get_mut()
+            .set_on_progress(request_config.on_progress.clone());
+        easy.get_mut()
+            .set_on_body_chunk(request_config.on_body_chunk.clone());
+
+        easy.get_mut()
+            .set_sniff_content_type(request_config.sniff_content_type == Some(true));
+        easy.get_mut()
+            .set_quota(request.extensions().get::<QuotaTag>().cloned());
+        easy.get_mut()
+            .set_priority(request_config.priority.unwrap_or_default());
+        easy.get_mut().set_host_key(request.uri().host().map(|host| {
+            let port = request.uri().port_u16().unwrap_or(
+                if request.uri().scheme() == Some(&http::uri::Scheme::HTTPS) {
+                    443
+                } else {
+                    80
+                },
+            );
+
+            format!("{}:{}", host.to_ascii_lowercase(), port)
+        }));
+
+        // Curl only decodes response bodies if the client does not do it.
+        if request_config.is_automatic_decompression()
+            && !request_config.is_client_side_decompression()
+        {
+            easy.get_mut()
+                .set_decompression_limit(request_config.decompression_limit.clone());
+        }
+
         // Check if we need to disable the Expect header.
-        let disable_expect_header = request_config.expect_continue
-            .as_ref()
-            .map(|x| x.is_disabled())
-            .unwrap_or_default();
+        let disable_expect_header =
+            has_body && (is_upgrade || expect_continue.is_skipped_for(body_length));
+
+        // Curl only adds the Expect header on its own for bodies larger than
+        // 1 MiB, so request it explicitly if the threshold is lower.
+        let force_expect_header = has_body
+            && !disable_expect_header
+            && body_length.is_some()
+            && !request.headers().contains_key(http::header::EXPECT);
 
         // Set the HTTP method to use. Curl ties in behavior with the request
         // method, so we need to configure this carefully.
-        #[allow(indirect_structural_match)]
         match (request.method(), has_body) {
             // Normal GET request.
             (&http::Method::GET, false) => {
@@ -1105,28 +2803,43 @@ impl HttpClient {
             }
         }
 
-        easy.url(&uri_to_string(request.uri()))?;
+        // To use an overridden host for SNI, curl is given a URI with that
+        // host and told to connect to the original host instead, unless a
+        // dialer already decides where to connect.
+        match request_config
+            .host_header
+            .as_ref()
+            .and_then(|host_header| host_header.sni_target(request.uri()))
+        {
+            Some((uri, connect_to)) => {
+                easy.url(&uri_to_string(&uri))?;
+
+                if request_config.dial.is_none() {
+                    let mut list = curl::easy::List::new();
+                    list.append(&connect_to)?;
+                    easy.connect_to(list)?;
+                }
+            }
+            None => easy.url(&uri_to_string(request.uri()))?,
+        }
+
+        if let Some(target) = request.extensions().get::<RequestTarget>() {
+            target.set_opt(&mut easy)?;
+        }
 
         // If the request has a body, then we either need to tell curl how large
         // the body is if we know it, or tell curl to use chunked encoding. If
         // we do neither, curl will simply not send the body without warning.
         if has_body {
-            // Use length given in Content-Length header, or the size defined by
-            // the body itself.
-            let body_length = request
-                .headers()
-                .get("Content-Length")
-                .and_then(|value| value.to_str().ok())
-                .and_then(|value| value.parse().ok())
-                .or(body_length);
-
-            if let Some(len) = body_length {
+            if let Some(bytes) = small_post_body.as_ref() {
+                easy.post_fields_copy(bytes)?;
+            } else if let Some(len) = body_length {
                 if request.method() == http::Method::POST {
                     easy.post_field_size(len)?;
                 } else {
                     easy.in_filesize(len)?;
                 }
-            } else {
+            } else if !is_upgrade {
                 // Set the Transfer-Encoding header to instruct curl to use
                 // chunked encoding. Replaces any existing values that may be
                 // incorrect.
@@ -1140,19 +2853,36 @@ impl HttpClient {
         // Generate a header list for curl.
         let mut headers = curl::easy::List::new();
 
-        let title_case = request
-            .extensions()
-            .get::<RequestConfig>()
-            .unwrap()
-            .title_case_headers
-            .unwrap_or(false);
+        let config = request.extensions().get::<RequestConfig>().unwrap();
+        let title_case = config.title_case_headers.unwrap_or(false);
+
+        // Reuse a single buffer for formatting every header, since curl makes
+        // its own copy of each one anyway.
+        let mut header_buf = String::with_capacity(64);
 
         for (name, value) in request.headers().iter() {
-            headers.append(&header_to_curl_string(name, value, title_case))?;
+            write_curl_header(&mut header_buf, name, value, title_case);
+            headers.append(&header_buf)?;
+        }
+
+        // An empty header tells curl not to send a header it would otherwise
+        // add on its own.
+        for name in config.omitted_headers.iter().flatten() {
+            if !request.headers().contains_key(name) {
+                headers.append(&format!("{}:", name))?;
+            }
+        }
+
+        // Data written to an upgraded connection is sent as is, without any
+        // framing.
+        if is_upgrade {
+            headers.append("Transfer-Encoding:")?;
         }
 
         if disable_expect_header {
             headers.append("Expect:")?;
+        } else if force_expect_header {
+            headers.append("Expect: 100-continue")?;
         }
 
         easy.http_headers(headers)?;
@@ -1173,7 +2903,8 @@ impl crate::interceptor::Invoke for &HttpClient {
             request
                 .headers_mut()
                 .entry(http::header::USER_AGENT)
-                .or_insert(USER_AGENT.parse().unwrap());
+                .or_insert_with(|| USER_AGENT.clone());
+
 
             // Check if automatic decompression is enabled; we'll need to know
             // this later after the response is sent.
@@ -1181,37 +2912,278 @@ impl crate::interceptor::Invoke for &HttpClient {
                 .extensions()
                 .get::<RequestConfig>()
                 .unwrap()
-                .automatic_decompression
-                .unwrap_or(false);
+                .is_automatic_decompression();
+
+            // If only some content types are decompressed, or custom codings
+            // are supported, then we decode response bodies ourselves once we
+            // know their type and coding.
+            let is_client_side_decompression = is_automatic_decompression
+                && request
+                    .extensions()
+                    .get::<RequestConfig>()
+                    .unwrap()
+                    .is_client_side_decompression();
+            let content_type_filter = request
+                .extensions()
+                .get::<RequestConfig>()
+                .unwrap()
+                .decompress_content_types
+                .clone()
+                .filter(|_| is_automatic_decompression);
+            let content_codings = request
+                .extensions()
+                .get::<RequestConfig>()
+                .unwrap()
+                .content_codings
+                .clone();
+            let decompression_limit = request
+                .extensions()
+                .get::<RequestConfig>()
+                .unwrap()
+                .decompression_limit
+                .clone();
+
+            if is_client_side_decompression {
+                let value = match content_codings.as_ref() {
+                    Some(codings) => codings.accept_encoding().ok_or_else(|| {
+                        Error::new(
+                            ErrorKind::InvalidRequest,
+                            io::Error::new(
+                                io::ErrorKind::InvalidInput,
+                                "content coding names must be valid tokens",
+                            ),
+                        )
+                    })?,
+                    None => HeaderValue::from_static(decode::ACCEPT_ENCODING),
+                };
+
+                request
+                    .headers_mut()
+                    .entry(http::header::ACCEPT_ENCODING)
+                    .or_insert(value);
+            }
+
+            if let Some(keep_alive) = request
+                .extensions()
+                .get::<RequestConfig>()
+                .unwrap()
+                .http10_keep_alive
+            {
+                prepare_http10_request(&mut request, keep_alive)?;
+            }
+
+            if let Some(target) = request.extensions().get::<RequestTarget>() {
+                if !target.is_valid() {
+                    return Err(Error::new(
+                        ErrorKind::InvalidRequest,
+                        io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "request target must not contain CR, LF, or NUL bytes",
+                        ),
+                    ));
+                }
+            }
+
+            if let Some(host_header) = request
+                .extensions()
+                .get::<RequestConfig>()
+                .unwrap()
+                .host_header
+                .as_ref()
+            {
+                let value = host_header.header_value().ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::InvalidRequest,
+                        io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "host header override must be a valid host and optional port",
+                        ),
+                    )
+                })?;
+
+                request
+                    .headers_mut()
+                    .entry(http::header::HOST)
+                    .or_insert(value);
+            }
+
+            if let Some(accept_versions) = request
+                .extensions()
+                .get::<RequestConfig>()
+                .unwrap()
+                .accept_versions
+                .as_ref()
+            {
+                let value = accept_versions.header_value().ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::InvalidRequest,
+                        io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "accepted media type versions must be valid in a header",
+                        ),
+                    )
+                })?;
+
+                request
+                    .headers_mut()
+                    .entry(http::header::ACCEPT)
+                    .or_insert(value);
+            }
+
+            // Encode the request body if its Content-Encoding header names a
+            // registered coding. The encoded length is not known up front.
+            if let Some(coding) = content_codings.as_ref().and_then(|codings| {
+                request
+                    .headers()
+                    .get(http::header::CONTENT_ENCODING)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| codings.get(value.trim()))
+            }) {
+                if !request.body().is_empty() {
+                    let body = std::mem::take(request.body_mut());
+
+                    *request.body_mut() = AsyncBody::from_reader(decode::Decoder::with_codec(
+                        body,
+                        decode::Codec::from_stream(coding.encoder()),
+                        None,
+                    ));
+                    request.headers_mut().remove(http::header::CONTENT_LENGTH);
+                }
+            }
+
+            // The checksum of a body already in memory can be sent up front.
+            // Streamed bodies get theirs in a trailer once they have been sent.
+            if let Some(checksum) = request
+                .extensions()
+                .get::<RequestConfig>()
+                .unwrap()
+                .body_checksum
+            {
+                if let Some(bytes) = request.body().as_bytes() {
+                    let value = HeaderValue::try_from(checksum.header_value(bytes)).unwrap();
+
+                    request
+                        .headers_mut()
+                        .entry(checksum.header_name())
+                        .or_insert(value);
+                }
+            }
+
+            // Hand the request back instead of sending it if this is a dry
+            // run, answering it with an empty response.
+            if let Some(dry_run) = request.extensions_mut().remove::<DryRun>() {
+                *dry_run.0.lock().unwrap() = Some(request);
+                return Ok(Response::new(AsyncBody::empty()));
+            }
+
+            // Answer the request without touching the network if the client
+            // was constructed with a handler.
+            if let Some(handler) = self.inner.client_config.handler.as_ref() {
+                return Ok((handler.0)(request));
+            }
+
+            let transport = match request.extensions().get::<Transport>() {
+                Some(transport) => Some(transport.take().ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::InvalidRequest,
+                        io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "transport was already used by another request",
+                        ),
+                    )
+                })?),
+                None => None,
+            };
+
+            // Without any agents, the request is executed on this thread.
+            let agents = self.agent()?;
+            let agent = Some(&agents)
+                .filter(|agents| !agents.is_empty())
+                .map(|agents| agents.select(request.extensions().get::<QuotaTag>()));
 
             // Create and configure a curl easy handle to fulfil the request.
-            let (easy, future) = self.create_easy_handle(request).map_err(Error::from_any)?;
+            let (mut easy, future) = self
+                .create_easy_handle(agent.as_deref(), request)
+                .map_err(Error::from_any)?;
+
+            // Send the request over the caller's stream.
+            if let Some(stream) = transport {
+                let socket = Box::new(TransportSocket::new(stream));
+                socket.set_opt(&mut easy).map_err(Error::from_any)?;
+                easy.get_mut().set_transport(socket);
+            }
 
-            // Send the request to the agent to be executed.
-            self.inner.agent.submit_request(easy)?;
+            easy.get_mut().set_clock(self.clock());
+            easy.get_mut().set_submitted_at(self.clock().now());
 
-            // Await for the response headers.
-            let response = future.await?;
+            let (mut response, transfer) = match agent {
+                Some(agent) => {
+                    // Send the request to the agent to be executed.
+                    agent.submit_request(easy).await?;
 
-            // If a Content-Length header is present, include that information in
-            // the body as well.
-            let body_len = response.content_length().filter(|_| {
-                // If automatic decompression is enabled, and will likely be
-                // selected, then the value of Content-Length does not indicate
-                // the uncompressed body length and merely the compressed data
-                // length. If it looks like we are in this scenario then we
-                // ignore the Content-Length, since it can only cause confusion
-                // when included with the body.
-                if is_automatic_decompression {
-                    if let Some(value) = response.headers().get(http::header::CONTENT_ENCODING) {
-                        if value != "identity" {
-                            return false;
-                        }
-                    }
+                    // Await for the response headers.
+                    (future.await?, None)
                 }
+                None => {
+                    let transfer = InlineTransfer::start(easy, self.clock())?;
 
-                true
-            });
+                    (transfer.drive(future).await??, Some(transfer))
+                }
+            };
+
+            let encoding = response
+                .headers()
+                .get(http::header::CONTENT_ENCODING)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .filter(|value| !value.eq_ignore_ascii_case("identity"))
+                .map(str::to_owned);
+
+            let is_decoded = is_automatic_decompression
+                && encoding.is_some()
+                && match content_type_filter.as_ref() {
+                    Some(filter) => filter.matches(
+                        response
+                            .headers()
+                            .get(http::header::CONTENT_TYPE)
+                            .and_then(|value| value.to_str().ok()),
+                    ),
+                    None => true,
+                };
+
+            // Set up decoding of the body if curl is not doing it for us.
+            let codec = match encoding.as_deref() {
+                Some(encoding)
+                    if is_decoded && is_client_side_decompression && !is_head_request =>
+                {
+                    let codec = decode::Codec::with_codings(encoding, content_codings.as_ref());
+
+                    Some(codec.ok_or_else(|| {
+                        Error::new(
+                            ErrorKind::InvalidContentEncoding,
+                            io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                format!("unsupported content encoding: {}", encoding),
+                            ),
+                        )
+                    })?)
+                }
+                _ => None,
+            };
+
+            // Let the caller know if the response body is left encoded.
+            if !is_decoded {
+                if let Some(encoding) = encoding {
+                    response.extensions_mut().insert(ContentEncoding(encoding));
+                }
+            }
+
+            // If a Content-Length header is present, include that information in
+            // the body as well, unless the body is decoded. In that case the
+            // value of Content-Length merely indicates the compressed data
+            // length, and can only cause confusion when included with the body.
+            let body_len = response.content_length().filter(|_| !is_decoded);
 
             // Convert the reader into an opaque Body.
             Ok(response.map(|reader| {
@@ -1220,12 +3192,23 @@ impl crate::interceptor::Invoke for &HttpClient {
                 } else {
                     let body = ResponseBody {
                         inner: reader,
+                        transfer,
                         // Extend the lifetime of the agent by including a reference
-                        // to its handle in the response body.
-                        _client: (*self).clone(),
+                        // to its handle in the response body, unless the response
+                        // is meant to be aborted when the client goes away.
+                        _client: match self.inner.client_config.shutdown_policy {
+                            ShutdownPolicy::Drain => Some((*self).clone()),
+                            ShutdownPolicy::Abort => None,
+                        },
                     };
 
-                    if let Some(len) = body_len {
+                    if let Some(codec) = codec {
+                        AsyncBody::from_reader(decode::Decoder::with_codec(
+                            body,
+                            codec,
+                            decompression_limit,
+                        ))
+                    } else if let Some(len) = body_len {
                         AsyncBody::from_reader_sized(body, len)
                     } else {
                         AsyncBody::from_reader(body)
@@ -1242,28 +3225,91 @@ impl fmt::Debug for HttpClient {
     }
 }
 
+/// Request extension asking for the request to be stored in the given slot
+/// once it is ready to be sent, instead of sending it.
+#[derive(Clone, Default)]
+struct DryRun(Arc<Mutex<Option<Request<AsyncBody>>>>);
+
+/// Error returned when a request is sent synchronously from a callback running
+/// on the agent thread that would have to execute it.
+#[cfg(feature = "blocking")]
+#[derive(Debug)]
+struct SendOnAgentThread;
+
+#[cfg(feature = "blocking")]
+impl fmt::Display for SendOnAgentThread {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(
+            "cannot send a request synchronously from a callback on the client's agent \
+             thread, since the agent cannot complete it while blocked; use `send_async` or a \
+             callback executor instead",
+        )
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl std::error::Error for SendOnAgentThread {}
+
 /// A future for a request being executed.
+///
+/// Dropping the future before it completes cancels the request.
 #[must_use = "futures do nothing unless you `.await` or poll them"]
-pub struct ResponseFuture<'c>(Pin<Box<dyn Future<Output = <Self as Future>::Output> + 'c + Send>>);
+pub struct ResponseFuture<'c> {
+    inner: Pin<Box<dyn Future<Output = <Self as Future>::Output> + 'c + Send>>,
+    cancel: CancelHandle,
+}
 
 impl<'c> ResponseFuture<'c> {
-    fn new<F>(future: F) -> Self
+    pub(crate) fn new<F>(future: F) -> Self
     where
         F: Future<Output = <Self as Future>::Output> + Send + 'c,
     {
-        ResponseFuture(Box::pin(future))
+        ResponseFuture {
+            inner: Box::pin(future),
+            cancel: CancelHandle::new(),
+        }
+    }
+
+    pub(crate) fn with_cancel_handle(mut self, cancel: CancelHandle) -> Self {
+        self.cancel = cancel;
+        self
     }
 
     fn error(error: Error) -> Self {
         Self::new(async move { Err(error) })
     }
+
+    /// Get a handle that can be used to cancel the request from elsewhere,
+    /// even after the response headers have been received and while the
+    /// response body is being read.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::HttpClient;
+    ///
+    /// # async fn run() -> Result<(), isahc::Error> {
+    /// let client = HttpClient::new()?;
+    /// let future = client.get_async("https://example.org/slow");
+    /// let cancel = future.cancel_handle();
+    ///
+    /// // Elsewhere:
+    /// cancel.cancel();
+    ///
+    /// let error = future.await.unwrap_err();
+    /// assert_eq!(error.kind(), &isahc::error::ErrorKind::Interrupted);
+    /// # Ok(()) }
+    /// ```
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
 }
 
 impl Future for ResponseFuture<'_> {
     type Output = Result<Response<AsyncBody>, Error>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        self.0.as_mut().poll(cx)
+        self.inner.as_mut().poll(cx)
     }
 }
 
@@ -1277,7 +3323,12 @@ impl<'c> fmt::Debug for ResponseFuture<'c> {
 /// alive until at least this transfer is complete.
 struct ResponseBody {
     inner: ResponseBodyReader,
-    _client: HttpClient,
+
+    /// The transfer to drive while reading, if it is executed on the thread
+    /// reading the body.
+    transfer: Option<Arc<InlineTransfer>>,
+
+    _client: Option<HttpClient>,
 }
 
 impl AsyncRead for ResponseBody {
@@ -1286,15 +3337,62 @@ impl AsyncRead for ResponseBody {
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
-        let inner = Pin::new(&mut self.inner);
-        inner.poll_read(cx, buf)
+        let this = &mut *self;
+        let inner = Pin::new(&mut this.inner);
+
+        match this.transfer.as_ref() {
+            Some(transfer) => transfer.poll_read(inner, cx, buf),
+            None => inner.poll_read(cx, buf),
+        }
+    }
+}
+
+/// Check that a request can be sent using HTTP/1.0 and add the headers it
+/// needs.
+fn prepare_http10_request(request: &mut Request<AsyncBody>, keep_alive: bool) -> Result<(), Error> {
+    fn invalid(message: &'static str) -> Error {
+        Error::new(
+            ErrorKind::InvalidRequest,
+            io::Error::new(io::ErrorKind::InvalidInput, message),
+        )
+    }
+
+    if request
+        .headers()
+        .contains_key(http::header::TRANSFER_ENCODING)
+    {
+        return Err(invalid(
+            "HTTP/1.0 does not support the Transfer-Encoding header",
+        ));
+    }
+
+    if !request.body().is_empty()
+        && request.body().len().is_none()
+        && !request.headers().contains_key(http::header::CONTENT_LENGTH)
+    {
+        return Err(invalid(
+            "HTTP/1.0 requires the length of the request body to be known",
+        ));
     }
+
+    request
+        .headers_mut()
+        .entry(http::header::CONNECTION)
+        .or_insert_with(|| {
+            HeaderValue::from_static(if keep_alive { "keep-alive" } else { "close" })
+        });
+
+    Ok(())
 }
 
 /// Convert a URI to a string. This implementation is a bit faster than the
 /// `Display` implementation that avoids the `std::fmt` machinery.
 fn uri_to_string(uri: &http::Uri) -> String {
-    let mut s = String::new();
+    // Size the string up front to avoid reallocating as it is built.
+    let capacity = uri.scheme_str().map(|s| s.len() + 3).unwrap_or(0)
+        + uri.authority().map(|a| a.as_str().len()).unwrap_or(0)
+        + uri.path_and_query().map(|p| p.as_str().len()).unwrap_or(0);
+    let mut s = String::with_capacity(capacity);
 
     if let Some(scheme) = uri.scheme() {
         s.push_str(scheme.as_str());
@@ -1327,10 +3425,7 @@ mod tests {
         let client = HttpClientBuilder::new()
             .default_header("some-key", "some-value")
             .build();
-        match client {
-            Ok(_) => assert!(true),
-            Err(_) => assert!(false),
-        }
+        assert!(client.is_ok());
     }
 
     #[test]
@@ -0,0 +1,227 @@
+//! Conversions between Isahc's HTTP types and version 1 of the [`http`]
+//! crate.
+//!
+//! Isahc's API uses version 0.2 of the `http` crate, which is re-exported as
+//! [`isahc::http`](crate::http). Crates built on version 1 can use the
+//! functions here to send their requests with Isahc and get responses back as
+//! version 1 types, without waiting for the rest of their dependencies to
+//! upgrade.
+//!
+//! Extensions cannot be carried over between versions, since their types are
+//! unrelated. This means that request configuration must be set on the
+//! converted request, and methods of [`ResponseExt`](crate::ResponseExt) are
+//! only available before converting a response.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use isahc::{compat::http1 as compat, HttpClient};
+//!
+//! let client = HttpClient::new()?;
+//!
+//! // A request built with version 1 of the `http` crate.
+//! let request = http1::Request::get("https://example.org").body(())?;
+//! let response: http1::Response<_> = compat::send(&client, request)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::{
+    body::AsyncBody,
+    error::{Error, ErrorKind},
+    HttpClient,
+};
+use http::{HeaderMap, Method, Request, Response, StatusCode, Uri, Version};
+
+#[cfg(feature = "blocking")]
+use crate::body::Body;
+
+/// Convert a request from version 1 of the `http` crate.
+pub fn request_from_http1<B>(request: http1::Request<B>) -> Result<Request<B>, Error> {
+    let (parts, body) = request.into_parts();
+    let mut request = Request::new(body);
+
+    *request.method_mut() = Method::from_bytes(parts.method.as_str().as_bytes())
+        .map_err(|e| Error::new(ErrorKind::InvalidRequest, e))?;
+    *request.uri_mut() = parts
+        .uri
+        .to_string()
+        .parse::<Uri>()
+        .map_err(|e| Error::new(ErrorKind::InvalidRequest, e))?;
+    *request.version_mut() = version_from_http1(parts.version);
+    *request.headers_mut() = headers_from_http1(&parts.headers)
+        .map_err(|e| Error::new(ErrorKind::InvalidRequest, e))?;
+
+    Ok(request)
+}
+
+/// Convert a request into version 1 of the `http` crate.
+pub fn request_into_http1<B>(request: Request<B>) -> Result<http1::Request<B>, Error> {
+    let (parts, body) = request.into_parts();
+    let mut request = http1::Request::new(body);
+
+    *request.method_mut() = http1::Method::from_bytes(parts.method.as_str().as_bytes())
+        .map_err(|e| Error::new(ErrorKind::InvalidRequest, e))?;
+    *request.uri_mut() = parts
+        .uri
+        .to_string()
+        .parse::<http1::Uri>()
+        .map_err(|e| Error::new(ErrorKind::InvalidRequest, e))?;
+    *request.version_mut() = version_into_http1(parts.version);
+    *request.headers_mut() = headers_into_http1(&parts.headers)
+        .map_err(|e| Error::new(ErrorKind::InvalidRequest, e))?;
+
+    Ok(request)
+}
+
+/// Convert a response from version 1 of the `http` crate.
+pub fn response_from_http1<B>(response: http1::Response<B>) -> Result<Response<B>, Error> {
+    let (parts, body) = response.into_parts();
+    let mut response = Response::new(body);
+
+    *response.status_mut() = StatusCode::from_u16(parts.status.as_u16())
+        .map_err(|e| Error::new(ErrorKind::ProtocolViolation, e))?;
+    *response.version_mut() = version_from_http1(parts.version);
+    *response.headers_mut() = headers_from_http1(&parts.headers)
+        .map_err(|e| Error::new(ErrorKind::ProtocolViolation, e))?;
+
+    Ok(response)
+}
+
+/// Convert a response into version 1 of the `http` crate.
+pub fn response_into_http1<B>(response: Response<B>) -> Result<http1::Response<B>, Error> {
+    let (parts, body) = response.into_parts();
+    let mut response = http1::Response::new(body);
+
+    *response.status_mut() = http1::StatusCode::from_u16(parts.status.as_u16())
+        .map_err(|e| Error::new(ErrorKind::ProtocolViolation, e))?;
+    *response.version_mut() = version_into_http1(parts.version);
+    *response.headers_mut() = headers_into_http1(&parts.headers)
+        .map_err(|e| Error::new(ErrorKind::ProtocolViolation, e))?;
+
+    Ok(response)
+}
+
+/// Convert a header map from version 1 of the `http` crate.
+pub fn headers_from_http1(headers: &http1::HeaderMap) -> Result<HeaderMap, http::Error> {
+    let mut map = HeaderMap::with_capacity(headers.len());
+
+    for (name, value) in headers {
+        map.append(
+            http::header::HeaderName::from_bytes(name.as_str().as_bytes())?,
+            http::header::HeaderValue::from_bytes(value.as_bytes())?,
+        );
+    }
+
+    Ok(map)
+}
+
+/// Convert a header map into version 1 of the `http` crate.
+pub fn headers_into_http1(headers: &HeaderMap) -> Result<http1::HeaderMap, http1::Error> {
+    let mut map = http1::HeaderMap::with_capacity(headers.len());
+
+    for (name, value) in headers {
+        map.append(
+            http1::header::HeaderName::from_bytes(name.as_str().as_bytes())?,
+            http1::header::HeaderValue::from_bytes(value.as_bytes())?,
+        );
+    }
+
+    Ok(map)
+}
+
+/// Send a request made with version 1 of the `http` crate using the given
+/// client, and return the response as version 1 types.
+///
+/// See [`HttpClient::send`] for details.
+#[cfg(feature = "blocking")]
+pub fn send<B>(
+    client: &HttpClient,
+    request: http1::Request<B>,
+) -> Result<http1::Response<Body>, Error>
+where
+    B: Into<Body>,
+{
+    response_into_http1(client.send(request_from_http1(request)?)?)
+}
+
+/// Send a request made with version 1 of the `http` crate using the given
+/// client asynchronously, and return the response as version 1 types.
+///
+/// See [`HttpClient::send_async`] for details.
+pub async fn send_async<B>(
+    client: &HttpClient,
+    request: http1::Request<B>,
+) -> Result<http1::Response<AsyncBody>, Error>
+where
+    B: Into<AsyncBody>,
+{
+    let request = request_from_http1(request)?.map(Into::into);
+
+    response_into_http1(client.send_async(request).await?)
+}
+
+fn version_from_http1(version: http1::Version) -> Version {
+    match version {
+        http1::Version::HTTP_09 => Version::HTTP_09,
+        http1::Version::HTTP_10 => Version::HTTP_10,
+        http1::Version::HTTP_2 => Version::HTTP_2,
+        http1::Version::HTTP_3 => Version::HTTP_3,
+        _ => Version::HTTP_11,
+    }
+}
+
+fn version_into_http1(version: Version) -> http1::Version {
+    match version {
+        Version::HTTP_09 => http1::Version::HTTP_09,
+        Version::HTTP_10 => http1::Version::HTTP_10,
+        Version::HTTP_2 => http1::Version::HTTP_2,
+        Version::HTTP_3 => http1::Version::HTTP_3,
+        _ => http1::Version::HTTP_11,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_round_trips() {
+        let request = http1::Request::post("http://example.org/a?b=c")
+            .version(http1::Version::HTTP_2)
+            .header("x-one", "1")
+            .header("x-one", "2")
+            .body("hello")
+            .unwrap();
+
+        let request = request_from_http1(request).unwrap();
+
+        assert_eq!(request.method(), Method::POST);
+        assert_eq!(request.uri(), "http://example.org/a?b=c");
+        assert_eq!(request.version(), Version::HTTP_2);
+        assert_eq!(request.headers().get_all("x-one").iter().count(), 2);
+
+        let request = request_into_http1(request).unwrap();
+
+        assert_eq!(request.method(), http1::Method::POST);
+        assert_eq!(request.headers().get_all("x-one").iter().count(), 2);
+        assert_eq!(*request.body(), "hello");
+    }
+
+    #[test]
+    fn response_round_trips() {
+        let response = Response::builder()
+            .status(418)
+            .header("x-one", "1")
+            .body(())
+            .unwrap();
+
+        let response = response_into_http1(response).unwrap();
+
+        assert_eq!(response.status(), 418);
+        assert_eq!(response.headers()["x-one"], "1");
+
+        let response = response_from_http1(response).unwrap();
+
+        assert_eq!(response.status(), 418);
+    }
+}
@@ -0,0 +1,17 @@
+//! Compatibility layers for working with other parts of the HTTP ecosystem.
+//!
+//! These make it easier to use Isahc alongside crates built on different
+//! versions of common types, or to switch an existing code base over to Isahc
+//! one piece at a time without having to rewrite every call site at once.
+//!
+//! # Availability
+//!
+//! Each submodule is only available when its feature is enabled:
+//! [`http-1`](../index.html#http-1) for [`http1`], and
+//! [`reqwest-compat`](../index.html#reqwest-compat) for [`reqwest`].
+
+#[cfg(feature = "http-1")]
+pub mod http1;
+
+#[cfg(feature = "reqwest-compat")]
+pub mod reqwest;
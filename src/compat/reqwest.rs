@@ -0,0 +1,517 @@
+//! A facade mirroring the most commonly used parts of the
+//! [reqwest](https://docs.rs/reqwest) API.
+//!
+//! Code written against reqwest's asynchronous client can usually be moved
+//! over by replacing `reqwest::` with `isahc::compat::reqwest::` in imports.
+//! Requests are sent using an ordinary [`HttpClient`], so they behave like
+//! any other request sent with Isahc.
+//!
+//! Only a subset of reqwest is provided, and there are some differences:
+//!
+//! - URLs are given and returned as [`Uri`] instead of `Url`.
+//! - Errors are ordinary Isahc [`Error`]s.
+//! - Response bodies are returned as a `Vec<u8>` instead of `Bytes`.
+//! - Query strings and form bodies cannot be built from `serde` types.
+//!
+//! Like reqwest, redirects are followed by default, up to a limit of 10.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use isahc::compat::reqwest::Client;
+//!
+//! # async fn run() -> Result<(), isahc::Error> {
+//! let client = Client::new();
+//!
+//! let body = client
+//!     .get("https://example.org")
+//!     .header("Accept", "text/html")
+//!     .send()
+//!     .await?
+//!     .error_for_status()?
+//!     .text()
+//!     .await?;
+//! # Ok(()) }
+//! ```
+
+use crate::{
+    auth::{Authentication, Credentials},
+    body::AsyncBody,
+    config::{Configurable, RedirectPolicy},
+    error::{Error, ErrorKind},
+    response::{AsyncReadResponseExt, ResponseExt},
+    HttpClient,
+    HttpClientBuilder,
+};
+use http::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    request::Builder,
+    Method,
+    StatusCode,
+    Uri,
+    Version,
+};
+use std::{convert::TryFrom, fmt, net::SocketAddr, time::Duration};
+
+/// A `Result` alias where the error is an Isahc [`Error`].
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Maximum number of redirects followed by default, which is the same as
+/// reqwest's default.
+const DEFAULT_MAX_REDIRECTS: u32 = 10;
+
+/// Send a GET request to the given URL using a new client.
+///
+/// For sending more than one request, create a [`Client`] once and reuse it.
+pub async fn get<U>(url: U) -> Result<Response>
+where
+    Uri: TryFrom<U>,
+    <Uri as TryFrom<U>>::Error: Into<http::Error>,
+{
+    Client::builder().build()?.get(url).send().await
+}
+
+/// An asynchronous client for sending requests, mirroring `reqwest::Client`.
+///
+/// Cloning a client is cheap, and clones share the same connection pool.
+#[derive(Clone, Debug)]
+pub struct Client {
+    inner: HttpClient,
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Client {
+    /// Create a new client with the default configuration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the client fails to initialize. Use [`Client::builder`] to
+    /// handle the error instead.
+    pub fn new() -> Self {
+        Self::builder()
+            .build()
+            .expect("failed to initialize client")
+    }
+
+    /// Create a builder for configuring a new client.
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+
+    /// Get the underlying Isahc client.
+    pub fn as_http_client(&self) -> &HttpClient {
+        &self.inner
+    }
+
+    /// Start building a GET request.
+    pub fn get<U>(&self, url: U) -> RequestBuilder
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        self.request(Method::GET, url)
+    }
+
+    /// Start building a POST request.
+    pub fn post<U>(&self, url: U) -> RequestBuilder
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        self.request(Method::POST, url)
+    }
+
+    /// Start building a PUT request.
+    pub fn put<U>(&self, url: U) -> RequestBuilder
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        self.request(Method::PUT, url)
+    }
+
+    /// Start building a PATCH request.
+    pub fn patch<U>(&self, url: U) -> RequestBuilder
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        self.request(Method::PATCH, url)
+    }
+
+    /// Start building a DELETE request.
+    pub fn delete<U>(&self, url: U) -> RequestBuilder
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        self.request(Method::DELETE, url)
+    }
+
+    /// Start building a HEAD request.
+    pub fn head<U>(&self, url: U) -> RequestBuilder
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        self.request(Method::HEAD, url)
+    }
+
+    /// Start building a request with the given method and URL.
+    pub fn request<U>(&self, method: Method, url: U) -> RequestBuilder
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        RequestBuilder {
+            client: self.clone(),
+            builder: http::Request::builder().method(method).uri(url),
+            body: AsyncBody::empty(),
+            error: None,
+        }
+    }
+
+    /// Send a request built by hand.
+    pub async fn execute(&self, request: http::Request<AsyncBody>) -> Result<Response> {
+        let url = request.uri().clone();
+        let response = self.inner.send_async(request).await?;
+
+        Ok(Response {
+            inner: response,
+            url,
+        })
+    }
+}
+
+impl From<HttpClient> for Client {
+    fn from(client: HttpClient) -> Self {
+        Self {
+            inner: client,
+        }
+    }
+}
+
+/// A builder for configuring a [`Client`], mirroring
+/// `reqwest::ClientBuilder`.
+#[derive(Debug)]
+pub struct ClientBuilder {
+    inner: HttpClientBuilder,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClientBuilder {
+    /// Create a new builder with the default configuration.
+    pub fn new() -> Self {
+        Self {
+            inner: HttpClientBuilder::new()
+                .redirect_policy(RedirectPolicy::Limit(DEFAULT_MAX_REDIRECTS)),
+        }
+    }
+
+    /// Set a timeout for the entirety of each request.
+    #[must_use = "builders have no effect if unused"]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.inner = self.inner.timeout(timeout);
+        self
+    }
+
+    /// Set a timeout for connecting to servers.
+    #[must_use = "builders have no effect if unused"]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.inner = self.inner.connect_timeout(timeout);
+        self
+    }
+
+    /// Set the `User-Agent` header to send with every request.
+    #[must_use = "builders have no effect if unused"]
+    pub fn user_agent<V>(mut self, value: V) -> Self
+    where
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<http::Error>,
+    {
+        self.inner = self.inner.default_header(http::header::USER_AGENT, value);
+        self
+    }
+
+    /// Set headers to send with every request.
+    #[must_use = "builders have no effect if unused"]
+    pub fn default_headers(mut self, headers: HeaderMap) -> Self {
+        self.inner = self.inner.default_headers(&headers);
+        self
+    }
+
+    /// Set how many redirects to follow at most. Setting this to `0` disables
+    /// following redirects.
+    ///
+    /// This replaces reqwest's `redirect(Policy)`.
+    #[must_use = "builders have no effect if unused"]
+    pub fn max_redirects(mut self, max: u32) -> Self {
+        self.inner = self.inner.redirect_policy(match max {
+            0 => RedirectPolicy::None,
+            max => RedirectPolicy::Limit(max),
+        });
+        self
+    }
+
+    /// Set how many idle connections to keep open in the connection pool.
+    #[must_use = "builders have no effect if unused"]
+    pub fn pool_max_idle(mut self, max: usize) -> Self {
+        self.inner = self.inner.connection_cache_size(max);
+        self
+    }
+
+    /// Build a [`Client`] using the configured options.
+    pub fn build(self) -> Result<Client> {
+        self.inner.build().map(Client::from)
+    }
+}
+
+/// A builder for a request, mirroring `reqwest::RequestBuilder`.
+///
+/// Any error that occurs while building the request is returned when it is
+/// sent.
+#[derive(Debug)]
+pub struct RequestBuilder {
+    client: Client,
+    builder: Builder,
+    body: AsyncBody,
+    error: Option<Error>,
+}
+
+impl RequestBuilder {
+    /// Add a header to the request.
+    #[must_use = "builders have no effect if unused"]
+    pub fn header<K, V>(mut self, key: K, value: V) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<http::Error>,
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<http::Error>,
+    {
+        self.builder = self.builder.header(key, value);
+        self
+    }
+
+    /// Add all of the given headers to the request.
+    #[must_use = "builders have no effect if unused"]
+    pub fn headers(mut self, headers: HeaderMap) -> Self {
+        if let Some(map) = self.builder.headers_mut() {
+            for (name, value) in headers.iter() {
+                map.append(name, value.clone());
+            }
+        }
+
+        self
+    }
+
+    /// Set the request body.
+    #[must_use = "builders have no effect if unused"]
+    pub fn body(mut self, body: impl Into<AsyncBody>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Set the request body to the given value serialized as JSON, and set
+    /// the `Content-Type` header to `application/json` unless it is already
+    /// set.
+    ///
+    /// This method is only available when the [`json`](../../index.html#json)
+    /// feature is enabled.
+    #[cfg(feature = "json")]
+    #[must_use = "builders have no effect if unused"]
+    pub fn json<T>(mut self, value: &T) -> Self
+    where
+        T: serde::Serialize + ?Sized,
+    {
+        match serde_json::to_vec(value) {
+            Ok(body) => {
+                let has_content_type = matches!(
+                    self.builder.headers_ref(),
+                    Some(headers) if headers.contains_key(http::header::CONTENT_TYPE)
+                );
+
+                if !has_content_type {
+                    self.builder = self
+                        .builder
+                        .header(http::header::CONTENT_TYPE, "application/json");
+                }
+
+                self.body = AsyncBody::from(body);
+            }
+            Err(e) => self.error = Some(Error::new(ErrorKind::InvalidRequest, e)),
+        }
+
+        self
+    }
+
+    /// Send credentials using HTTP Basic authentication.
+    #[must_use = "builders have no effect if unused"]
+    pub fn basic_auth<U, P>(mut self, username: U, password: Option<P>) -> Self
+    where
+        U: fmt::Display,
+        P: fmt::Display,
+    {
+        let password = password.map(|p| p.to_string()).unwrap_or_default();
+
+        self.builder = self
+            .builder
+            .authentication(Authentication::basic())
+            .credentials(Credentials::new(username.to_string(), password));
+        self
+    }
+
+    /// Send a bearer token in the `Authorization` header.
+    #[must_use = "builders have no effect if unused"]
+    pub fn bearer_auth<T>(self, token: T) -> Self
+    where
+        T: fmt::Display,
+    {
+        self.header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+    }
+
+    /// Set a timeout for the entirety of this request.
+    #[must_use = "builders have no effect if unused"]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.builder = self.builder.timeout(timeout);
+        self
+    }
+
+    /// Set the HTTP version to use for this request.
+    #[must_use = "builders have no effect if unused"]
+    pub fn version(mut self, version: Version) -> Self {
+        self.builder = self.builder.version(version);
+        self
+    }
+
+    /// Build the request without sending it.
+    pub fn build(self) -> Result<http::Request<AsyncBody>> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+
+        Ok(self.builder.body(self.body)?)
+    }
+
+    /// Send the request and wait for the response headers.
+    pub async fn send(self) -> Result<Response> {
+        let client = self.client.clone();
+        let request = self.build()?;
+
+        client.execute(request).await
+    }
+}
+
+/// A response to a request, mirroring `reqwest::Response`.
+///
+/// The response body has not been read yet when a response is returned, and
+/// is read using methods like [`Response::text`] that consume the response.
+#[derive(Debug)]
+pub struct Response {
+    inner: http::Response<AsyncBody>,
+    url: Uri,
+}
+
+impl Response {
+    /// Get the status code of the response.
+    pub fn status(&self) -> StatusCode {
+        self.inner.status()
+    }
+
+    /// Get the HTTP version of the response.
+    pub fn version(&self) -> Version {
+        self.inner.version()
+    }
+
+    /// Get the response headers.
+    pub fn headers(&self) -> &HeaderMap {
+        self.inner.headers()
+    }
+
+    /// Get a mutable reference to the response headers.
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        self.inner.headers_mut()
+    }
+
+    /// Get the length of the response body, if known.
+    pub fn content_length(&self) -> Option<u64> {
+        self.inner.body().len()
+    }
+
+    /// Get the final URL of the response, after following any redirects.
+    pub fn url(&self) -> &Uri {
+        self.inner.effective_uri().unwrap_or(&self.url)
+    }
+
+    /// Get the address of the server the response was received from, if
+    /// known.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.inner.remote_addr()
+    }
+
+    /// Return an error of kind [`ErrorKind::BadStatus`] if the response has a
+    /// client or server error status code.
+    pub fn error_for_status(self) -> Result<Self> {
+        self.error_for_status_ref()?;
+        Ok(self)
+    }
+
+    /// Return an error of kind [`ErrorKind::BadStatus`] if the response has a
+    /// client or server error status code.
+    pub fn error_for_status_ref(&self) -> Result<&Self> {
+        if self.status().is_client_error() || self.status().is_server_error() {
+            Err(Error::with_response(ErrorKind::BadStatus, &self.inner))
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Read the entire response body into memory.
+    pub async fn bytes(mut self) -> Result<Vec<u8>> {
+        Ok(self.inner.bytes().await?)
+    }
+
+    /// Read the entire response body as text, decoded using the charset
+    /// given in the `Content-Type` header.
+    ///
+    /// This method is only available when the
+    /// [`text-decoding`](../../index.html#text-decoding) feature is enabled.
+    #[cfg(feature = "text-decoding")]
+    pub async fn text(mut self) -> Result<String> {
+        Ok(self.inner.text().await?)
+    }
+
+    /// Read the entire response body and deserialize it as JSON.
+    ///
+    /// This method is only available when the [`json`](../../index.html#json)
+    /// feature is enabled.
+    #[cfg(feature = "json")]
+    pub async fn json<T>(mut self) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let body = self.inner.bytes().await?;
+
+        serde_json::from_slice(&body).map_err(|e| Error::new(ErrorKind::ProtocolViolation, e))
+    }
+
+    /// Convert into the underlying [`http::Response`].
+    pub fn into_inner(self) -> http::Response<AsyncBody> {
+        self.inner
+    }
+}
+
+impl From<Response> for http::Response<AsyncBody> {
+    fn from(response: Response) -> Self {
+        response.inner
+    }
+}
@@ -0,0 +1,208 @@
+use crate::media_type_version::versioned;
+use http::HeaderValue;
+
+/// The versions of a media type to ask for in the `Accept` header of a
+/// request, in order of preference.
+///
+/// Each version is sent with a lower [quality
+/// value](https://tools.ietf.org/html/rfc7231#section-5.3.1) than the one
+/// before it, so that the server picks the most preferred version it supports.
+/// By default, versions are sent in the subtype of the media type, as in
+/// `application/vnd.example.v2+json`. Use [`AcceptVersions::parameter`] to
+/// send them in a `version` parameter instead, as in
+/// `application/vnd.example+json; version=2`.
+///
+/// Use with
+/// [`Configurable::accept_versions`](crate::config::Configurable::accept_versions).
+/// If the request already has an `Accept` header, the header is left alone.
+/// Sending a request with a media type or version that cannot be sent in a
+/// header fails with an error of kind
+/// [`ErrorKind::InvalidRequest`](crate::error::ErrorKind::InvalidRequest).
+///
+/// Which version the server responded with can be checked using
+/// [`ResponseExt::media_type_version`](crate::ResponseExt::media_type_version).
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{config::AcceptVersions, prelude::*, Request};
+///
+/// // Prefer version 3, but accept version 2 while servers are migrated.
+/// let response = Request::get("https://api.example.org/widgets")
+///     .accept_versions(AcceptVersions::new("application/vnd.example+json").version(3).version(2))
+///     .body(())?
+///     .send()?;
+///
+/// match response.media_type_version() {
+///     Some(v) if v.version() == "3" => { /* parse the new format */ }
+///     _ => { /* parse the old format */ }
+/// }
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AcceptVersions {
+    media_type: String,
+    versions: Vec<String>,
+    parameter: bool,
+}
+
+impl AcceptVersions {
+    /// Ask for versions of the given media type, such as
+    /// `application/vnd.example+json`.
+    ///
+    /// Without any versions, the media type is sent as is.
+    pub fn new(media_type: impl Into<String>) -> Self {
+        Self {
+            media_type: media_type.into(),
+            versions: Vec::new(),
+            parameter: false,
+        }
+    }
+
+    /// Add a version to ask for, less preferred than the versions added
+    /// before it.
+    #[must_use = "builders have no effect if unused"]
+    pub fn version(mut self, version: impl ToString) -> Self {
+        self.versions.push(version.to_string());
+        self
+    }
+
+    /// Send versions in a `version` parameter instead of the subtype of the
+    /// media type. Disabled by default.
+    #[must_use = "builders have no effect if unused"]
+    pub fn parameter(mut self, enable: bool) -> Self {
+        self.parameter = enable;
+        self
+    }
+
+    /// Get the media type versions are asked for of.
+    pub fn media_type(&self) -> &str {
+        &self.media_type
+    }
+
+    /// Get the versions asked for, in order of preference.
+    pub fn versions(&self) -> impl Iterator<Item = &str> {
+        self.versions.iter().map(String::as_str)
+    }
+
+    /// Get the value of the `Accept` header, or `None` if the media type or a
+    /// version cannot be sent in a header.
+    pub(crate) fn header_value(&self) -> Option<HeaderValue> {
+        if self.versions.iter().any(|v| !is_valid_version(v)) {
+            return None;
+        }
+
+        let value = if self.versions.is_empty() {
+            self.media_type.clone()
+        } else {
+            self.versions
+                .iter()
+                .enumerate()
+                .map(|(i, version)| {
+                    let mut item = if self.parameter {
+                        format!("{}; version={}", self.media_type, version)
+                    } else {
+                        versioned(&self.media_type, version)
+                    };
+
+                    if i > 0 {
+                        item.push_str(&format!("; q=0.{}", 10usize.saturating_sub(i).max(1)));
+                    }
+
+                    item
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let (type_, subtype) = self.media_type.split_once('/')?;
+
+        if type_.is_empty()
+            || subtype.is_empty()
+            || self.media_type.contains(&[';', ',', ' '][..])
+        {
+            return None;
+        }
+
+        HeaderValue::from_str(&value).ok()
+    }
+}
+
+/// Get whether a version can be sent in a media type without quoting.
+fn is_valid_version(version: &str) -> bool {
+    !version.is_empty()
+        && version
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'.' || b == b'-' || b == b'_')
+}
+
+impl From<&str> for AcceptVersions {
+    fn from(media_type: &str) -> Self {
+        Self::new(media_type)
+    }
+}
+
+impl From<String> for AcceptVersions {
+    fn from(media_type: String) -> Self {
+        Self::new(media_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_in_subtype() {
+        let accept = AcceptVersions::new("application/vnd.example+json")
+            .version(3)
+            .version(2)
+            .version("1");
+
+        assert_eq!(
+            accept.header_value().unwrap(),
+            "application/vnd.example.v3+json, application/vnd.example.v2+json; q=0.9, \
+             application/vnd.example.v1+json; q=0.8"
+        );
+    }
+
+    #[test]
+    fn versions_in_parameter() {
+        let accept = AcceptVersions::new("application/vnd.example+json")
+            .version("2.1")
+            .version("2.0")
+            .parameter(true);
+
+        assert_eq!(
+            accept.header_value().unwrap(),
+            "application/vnd.example+json; version=2.1, \
+             application/vnd.example+json; version=2.0; q=0.9"
+        );
+    }
+
+    #[test]
+    fn quality_does_not_drop_to_zero() {
+        let accept = (1..=12).fold(AcceptVersions::new("application/vnd.example"), |a, v| {
+            a.version(v)
+        });
+        let value = accept.header_value().unwrap();
+
+        assert!(value.to_str().unwrap().ends_with("application/vnd.example.v12; q=0.1"));
+    }
+
+    #[test]
+    fn without_versions() {
+        assert_eq!(
+            AcceptVersions::new("application/json").header_value().unwrap(),
+            "application/json"
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(AcceptVersions::new("json").version(1).header_value(), None);
+        assert_eq!(AcceptVersions::new("application/json, */*").header_value(), None);
+        assert_eq!(AcceptVersions::new("application/json").version("1, 2").header_value(), None);
+        assert_eq!(AcceptVersions::new("application/json").version("").header_value(), None);
+    }
+}
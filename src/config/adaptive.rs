@@ -0,0 +1,269 @@
+use std::time::{Duration, Instant};
+
+/// Configuration for adjusting the number of requests a client has in progress
+/// at once based on how the servers it talks to are coping.
+///
+/// The limit is adjusted using additive increase, multiplicative decrease
+/// (AIMD), the same scheme TCP uses for its congestion window. Every request
+/// that completes normally raises the limit by a fraction, so that it grows by
+/// about one request per full round of requests. A request that shows the
+/// server is overloaded lowers the limit by the
+/// [decrease factor](AdaptiveConcurrency::decrease_factor) instead. A request
+/// shows the server is overloaded if it:
+///
+/// - fails to connect, times out, or fails with an I/O error,
+/// - receives a `429 Too Many Requests`, `502 Bad Gateway`,
+///   `503 Service Unavailable` or `504 Gateway Timeout` response, or
+/// - receives its response header later than the
+///   [latency threshold](AdaptiveConcurrency::latency_threshold), if one is
+///   set.
+///
+/// The limit is lowered at most once per round of requests; requests that had
+/// already begun when the limit was lowered do not lower it again, since they
+/// were sent while the limit was still too high.
+///
+/// Requests above the limit wait to begin in order of their
+/// [priority](crate::config::Configurable::priority), like requests above the
+/// [in-flight limit](crate::HttpClientBuilder::max_in_flight). If both limits
+/// are set, the lower one applies. Like the in-flight limit, the limit is kept
+/// for each [agent thread](crate::HttpClientBuilder::agent_threads)
+/// separately. The current limit is reported by
+/// [`AgentStats::concurrency_limit`](crate::AgentStats::concurrency_limit).
+///
+/// Use with
+/// [`HttpClientBuilder::adaptive_concurrency`](crate::HttpClientBuilder::adaptive_concurrency).
+///
+/// # Examples
+///
+/// ```
+/// use isahc::{config::AdaptiveConcurrency, HttpClient};
+/// use std::time::Duration;
+///
+/// let client = HttpClient::builder()
+///     .adaptive_concurrency(
+///         AdaptiveConcurrency::new()
+///             .initial_limit(8)
+///             .max_limit(64)
+///             .latency_threshold(Duration::from_secs(2)),
+///     )
+///     .build()?;
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct AdaptiveConcurrency {
+    initial_limit: usize,
+    min_limit: usize,
+    max_limit: usize,
+    latency_threshold: Option<Duration>,
+    decrease_factor: f64,
+}
+
+impl Default for AdaptiveConcurrency {
+    fn default() -> Self {
+        Self {
+            initial_limit: 16,
+            min_limit: 1,
+            max_limit: 256,
+            latency_threshold: None,
+            decrease_factor: 0.5,
+        }
+    }
+}
+
+impl AdaptiveConcurrency {
+    /// Create a new configuration with the default settings.
+    ///
+    /// The limit starts at 16 requests and is kept between 1 and 256
+    /// requests, is halved when the server is overloaded, and slow responses
+    /// are not taken into account.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the limit to start with.
+    ///
+    /// The value is clamped to the minimum and maximum limits.
+    #[must_use = "builders have no effect if unused"]
+    pub fn initial_limit(mut self, limit: usize) -> Self {
+        self.initial_limit = limit;
+        self
+    }
+
+    /// Set the lowest value the limit may be lowered to. Values lower than
+    /// `1` are treated as `1`.
+    #[must_use = "builders have no effect if unused"]
+    pub fn min_limit(mut self, limit: usize) -> Self {
+        self.min_limit = limit.max(1);
+        self
+    }
+
+    /// Set the highest value the limit may be raised to.
+    ///
+    /// Values lower than the minimum limit are treated as the minimum limit.
+    #[must_use = "builders have no effect if unused"]
+    pub fn max_limit(mut self, limit: usize) -> Self {
+        self.max_limit = limit;
+        self
+    }
+
+    /// Treat responses whose header takes longer than the given time to
+    /// arrive as a sign that the server is overloaded.
+    ///
+    /// The time is measured from when the request begins, so time spent
+    /// waiting for the limit is not included.
+    #[must_use = "builders have no effect if unused"]
+    pub fn latency_threshold(mut self, threshold: Duration) -> Self {
+        self.latency_threshold = Some(threshold);
+        self
+    }
+
+    /// Set the factor the limit is multiplied by when the server is
+    /// overloaded.
+    ///
+    /// The factor is clamped to be between `0.1` and `0.9`. The default is
+    /// `0.5`.
+    #[must_use = "builders have no effect if unused"]
+    pub fn decrease_factor(mut self, factor: f64) -> Self {
+        self.decrease_factor = factor.clamp(0.1, 0.9);
+        self
+    }
+
+    fn max(&self) -> usize {
+        self.max_limit.max(self.min_limit)
+    }
+}
+
+/// How a completed request reflects on the load of the server.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Signal {
+    /// The request completed without signs of the server struggling.
+    Healthy,
+
+    /// The request showed that the server is overloaded.
+    Overloaded,
+}
+
+/// The current limit of a client's agent, adjusted as requests complete.
+#[derive(Debug)]
+pub(crate) struct AdaptiveLimit {
+    config: AdaptiveConcurrency,
+    limit: f64,
+
+    /// When the limit was last lowered, if it has been.
+    decreased_at: Option<Instant>,
+}
+
+impl AdaptiveLimit {
+    pub(crate) fn new(config: AdaptiveConcurrency) -> Self {
+        Self {
+            limit: config.initial_limit.clamp(config.min_limit, config.max()) as f64,
+            config,
+            decreased_at: None,
+        }
+    }
+
+    /// Get the number of requests that may be in progress at once.
+    pub(crate) fn get(&self) -> usize {
+        self.limit as usize
+    }
+
+    /// Get whether a response received after the given time is too slow.
+    pub(crate) fn is_slow(&self, latency: Duration) -> bool {
+        self.config
+            .latency_threshold
+            .map_or(false, |threshold| latency > threshold)
+    }
+
+    /// Adjust the limit for a request that began at the given time and has
+    /// now completed.
+    pub(crate) fn record(&mut self, signal: Signal, began_at: Instant, now: Instant) {
+        match signal {
+            Signal::Healthy => {
+                self.limit = (self.limit + 1.0 / self.limit).min(self.config.max() as f64);
+            }
+            Signal::Overloaded => {
+                if self.decreased_at.map_or(false, |at| began_at < at) {
+                    return;
+                }
+
+                self.limit = (self.limit * self.config.decrease_factor)
+                    .floor()
+                    .max(self.config.min_limit as f64);
+                self.decreased_at = Some(now);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limit_grows_by_one_per_round() {
+        let now = Instant::now();
+        let mut limit = AdaptiveLimit::new(AdaptiveConcurrency::new().initial_limit(4));
+
+        for _ in 0..4 {
+            limit.record(Signal::Healthy, now, now);
+        }
+
+        assert_eq!(limit.get(), 4);
+
+        limit.record(Signal::Healthy, now, now);
+        assert_eq!(limit.get(), 5);
+    }
+
+    #[test]
+    fn limit_is_lowered_once_per_round() {
+        let now = Instant::now();
+        let later = now + Duration::from_secs(1);
+        let mut limit = AdaptiveLimit::new(AdaptiveConcurrency::new().initial_limit(20));
+
+        limit.record(Signal::Overloaded, now, later);
+        assert_eq!(limit.get(), 10);
+
+        // Began before the limit was lowered.
+        limit.record(Signal::Overloaded, now, later);
+        assert_eq!(limit.get(), 10);
+
+        limit.record(Signal::Overloaded, later, later);
+        assert_eq!(limit.get(), 5);
+    }
+
+    #[test]
+    fn limit_stays_within_bounds() {
+        let now = Instant::now();
+        let config = AdaptiveConcurrency::new()
+            .initial_limit(100)
+            .min_limit(2)
+            .max_limit(3);
+        let mut limit = AdaptiveLimit::new(config);
+
+        assert_eq!(limit.get(), 3);
+
+        for _ in 0..10 {
+            limit.record(Signal::Healthy, now, now);
+        }
+
+        assert_eq!(limit.get(), 3);
+
+        for _ in 0..10 {
+            limit.record(Signal::Overloaded, now, now);
+        }
+
+        assert_eq!(limit.get(), 2);
+    }
+
+    #[test]
+    fn slow_responses() {
+        let limit = AdaptiveLimit::new(AdaptiveConcurrency::new());
+        assert!(!limit.is_slow(Duration::from_secs(60)));
+
+        let limit = AdaptiveLimit::new(
+            AdaptiveConcurrency::new().latency_threshold(Duration::from_secs(1)),
+        );
+        assert!(!limit.is_slow(Duration::from_millis(500)));
+        assert!(limit.is_slow(Duration::from_secs(2)));
+    }
+}
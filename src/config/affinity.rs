@@ -0,0 +1,47 @@
+use std::borrow::Cow;
+
+/// A request extension that asks for requests with the same key to be sent
+/// over the same connection when possible.
+///
+/// Load balancers often spread requests over several backend servers, and a
+/// host name may resolve to several addresses. Some backends keep state that
+/// is only valid on the server that created it, and rely on clients to keep
+/// talking to the same server without any cookies to route on. Requests
+/// tagged with the same affinity key connect to the same address as the last
+/// request with that key sent to the same host, and so reuse its connection
+/// if it is still open, either as a new HTTP/2 stream or as an idle HTTP/1.1
+/// connection from the connection cache.
+///
+/// Affinity is best effort. It does not apply to requests sent through a
+/// proxy or requests that are configured with a
+/// [`Dialer`](crate::config::Dialer), and if connecting to the remembered
+/// address fails, the key is forgotten so that the next request can connect
+/// to any address for the host.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{config::AffinityKey, prelude::*, Request};
+///
+/// for _ in 0..3 {
+///     let response = Request::get("https://api.example.org/cart")
+///         .extension(AffinityKey::new("customer-42"))
+///         .body(())?
+///         .send()?;
+/// }
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct AffinityKey(Cow<'static, str>);
+
+impl AffinityKey {
+    /// Create a new affinity key.
+    pub fn new(key: impl Into<Cow<'static, str>>) -> Self {
+        Self(key.into())
+    }
+
+    /// Get the affinity key as a string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
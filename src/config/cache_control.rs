@@ -0,0 +1,129 @@
+use http::HeaderValue;
+use std::time::Duration;
+
+/// Cache directives for a request, sent to the server in a `Cache-Control`
+/// header and also obeyed by the [redirect cache](super::RedirectCache) of the
+/// client.
+///
+/// Use with
+/// [`Configurable::cache_control`](crate::config::Configurable::cache_control).
+/// If the request already has a `Cache-Control` header, the header is left
+/// alone, but the redirect cache still obeys the directives given here.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{config::CacheControl, prelude::*, Request};
+/// use std::time::Duration;
+///
+/// let response = Request::get("https://example.org")
+///     .cache_control(CacheControl::new().max_age(Duration::from_secs(60)))
+///     .body(())?
+///     .send()?;
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CacheControl {
+    pub(crate) no_cache: bool,
+    pub(crate) no_store: bool,
+    pub(crate) max_age: Option<Duration>,
+    pub(crate) only_if_cached: bool,
+}
+
+impl CacheControl {
+    /// Create a new set of directives without any directives.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Do not use a cached response without checking with the server first.
+    ///
+    /// Cached redirects are not followed for the request, although new
+    /// redirects may still be cached.
+    #[must_use = "builders have no effect if unused"]
+    pub fn no_cache(mut self) -> Self {
+        self.no_cache = true;
+        self
+    }
+
+    /// Do not cache anything about the request or its response.
+    ///
+    /// Redirects received for the request are not cached.
+    #[must_use = "builders have no effect if unused"]
+    pub fn no_store(mut self) -> Self {
+        self.no_store = true;
+        self
+    }
+
+    /// Only accept cached responses that are at most the given age.
+    ///
+    /// Cached redirects that were cached longer ago are not followed for the
+    /// request. The age is sent to the server in whole seconds.
+    #[must_use = "builders have no effect if unused"]
+    pub fn max_age(mut self, age: Duration) -> Self {
+        self.max_age = Some(age);
+        self
+    }
+
+    /// Only accept a cached response, instead of contacting the origin
+    /// server.
+    ///
+    /// This directive is meant for caching proxies between the client and the
+    /// server. The redirect cache of the client only caches redirects, not the
+    /// responses they lead to, so it cannot satisfy a request by itself and
+    /// ignores this directive.
+    #[must_use = "builders have no effect if unused"]
+    pub fn only_if_cached(mut self) -> Self {
+        self.only_if_cached = true;
+        self
+    }
+
+    /// Get the value of the `Cache-Control` header for these directives, or
+    /// `None` if there are no directives.
+    pub(crate) fn header_value(&self) -> Option<HeaderValue> {
+        let mut directives = Vec::new();
+
+        if self.no_cache {
+            directives.push(String::from("no-cache"));
+        }
+
+        if self.no_store {
+            directives.push(String::from("no-store"));
+        }
+
+        if let Some(age) = self.max_age {
+            directives.push(format!("max-age={}", age.as_secs()));
+        }
+
+        if self.only_if_cached {
+            directives.push(String::from("only-if-cached"));
+        }
+
+        if directives.is_empty() {
+            None
+        } else {
+            // Directives only contain visible ASCII characters.
+            Some(HeaderValue::from_str(&directives.join(", ")).unwrap())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_value() {
+        assert_eq!(CacheControl::new().header_value(), None);
+        assert_eq!(
+            CacheControl::new()
+                .no_cache()
+                .no_store()
+                .max_age(Duration::from_millis(90_500))
+                .only_if_cached()
+                .header_value()
+                .unwrap(),
+            "no-cache, no-store, max-age=90, only-if-cached"
+        );
+    }
+}
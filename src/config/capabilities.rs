@@ -0,0 +1,417 @@
+use http::{header, HeaderMap, Method, StatusCode, Uri, Version};
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+/// The default maximum number of hosts remembered by a [`CapabilityCache`].
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// A cache of what remote servers are known to support, learned from their
+/// responses.
+///
+/// Every response received by a client with a capability cache is inspected
+/// for hints about the capabilities of the server that sent it, which are
+/// remembered per origin, that is per scheme, host and port:
+///
+/// - Whether byte ranges are supported, from the `Accept-Ranges` header or a
+///   `206 Partial Content` response.
+/// - The HTTP version used, and whether HTTP/2 or newer has been used at all.
+/// - The content codings the server has compressed response bodies with.
+/// - The methods the server allows, from the `Allow` header, as sent in
+///   response to an `OPTIONS` request or with a `405 Method Not Allowed`
+///   response.
+///
+/// Nothing is requested from servers just to fill the cache, so a capability
+/// is only known once a response has revealed it. To learn about a server up
+/// front, send it an `OPTIONS` request.
+///
+/// Capabilities are hints: servers may handle resources differently, and may
+/// change over time. Helpers that rely on them, such as ones splitting a
+/// download into ranges, should still handle servers that turn out not to
+/// support a capability after all.
+///
+/// The cache remembers a bounded number of origins, forgetting the least
+/// recently updated one when full. Caches are cheap to clone, and clones share
+/// the same entries.
+///
+/// Clients do not have a capability cache by default. One can be set using
+/// [`HttpClientBuilder::capability_cache`](crate::HttpClientBuilder::capability_cache).
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{config::CapabilityCache, prelude::*, HttpClient, Request};
+///
+/// let cache = CapabilityCache::new();
+/// let client = HttpClient::builder().capability_cache(cache.clone()).build()?;
+///
+/// client.send(Request::options("https://example.org/").body(())?)?;
+///
+/// let uri = "https://example.org/video.mp4".parse()?;
+///
+/// if let Some(capabilities) = cache.get(&uri) {
+///     if capabilities.accepts_ranges() == Some(true) {
+///         // Download the video in pieces.
+///     }
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone)]
+pub struct CapabilityCache {
+    inner: Arc<Mutex<CapabilityCacheInner>>,
+}
+
+struct CapabilityCacheInner {
+    capacity: usize,
+    entries: HashMap<String, ServerCapabilities>,
+}
+
+impl Default for CapabilityCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CapabilityCache {
+    /// Create a new empty capability cache that remembers up to 1024 origins.
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    /// Create a new empty capability cache that remembers at most the given
+    /// number of origins.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(CapabilityCacheInner {
+                capacity,
+                entries: HashMap::new(),
+            })),
+        }
+    }
+
+    /// Get what is known about the server of the given URI, if any response
+    /// from its origin has been seen.
+    pub fn get(&self, uri: &Uri) -> Option<ServerCapabilities> {
+        let key = origin(uri)?;
+
+        self.inner.lock().unwrap().entries.get(&key).cloned()
+    }
+
+    /// Get the number of origins with known capabilities.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    /// Returns true if no capabilities are known.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget what is known about the server of the given URI. Returns true if
+    /// anything was known.
+    pub fn remove(&self, uri: &Uri) -> bool {
+        match origin(uri) {
+            Some(key) => self.inner.lock().unwrap().entries.remove(&key).is_some(),
+            None => false,
+        }
+    }
+
+    /// Forget all known capabilities.
+    pub fn clear(&self) {
+        self.inner.lock().unwrap().entries.clear();
+    }
+
+    /// Learn what the response to a request for the given URI reveals about
+    /// its server.
+    pub(crate) fn record(
+        &self,
+        uri: &Uri,
+        status: StatusCode,
+        version: Version,
+        headers: &HeaderMap,
+        now: Instant,
+    ) {
+        let key = match origin(uri) {
+            Some(key) => key,
+            None => return,
+        };
+
+        let mut inner = self.inner.lock().unwrap();
+
+        if inner.capacity == 0 {
+            return;
+        }
+
+        if !inner.entries.contains_key(&key) && inner.entries.len() >= inner.capacity {
+            let oldest = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, capabilities)| capabilities.updated_at)
+                .map(|(key, _)| key.clone());
+
+            if let Some(oldest) = oldest {
+                inner.entries.remove(&oldest);
+            }
+        }
+
+        inner
+            .entries
+            .entry(key)
+            .or_insert_with(|| ServerCapabilities::new(now))
+            .update(status, version, headers, now);
+    }
+}
+
+impl fmt::Debug for CapabilityCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CapabilityCache")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// What is known about the capabilities of a server, as learned by a
+/// [`CapabilityCache`].
+#[derive(Clone, Debug)]
+pub struct ServerCapabilities {
+    accepts_ranges: Option<bool>,
+    http_version: Option<Version>,
+    http2: bool,
+    content_encodings: Vec<String>,
+    allowed_methods: Option<Vec<Method>>,
+    updated_at: Instant,
+}
+
+impl ServerCapabilities {
+    fn new(now: Instant) -> Self {
+        Self {
+            accepts_ranges: None,
+            http_version: None,
+            http2: false,
+            content_encodings: Vec::new(),
+            allowed_methods: None,
+            updated_at: now,
+        }
+    }
+
+    /// Whether the server accepts requests for byte ranges, or `None` if it
+    /// has not said.
+    ///
+    /// This is the most recent answer given for any resource of the server.
+    pub fn accepts_ranges(&self) -> Option<bool> {
+        self.accepts_ranges
+    }
+
+    /// Get the HTTP version of the most recent response from the server.
+    pub fn http_version(&self) -> Option<Version> {
+        self.http_version
+    }
+
+    /// Returns true if the server has responded using HTTP/2 or newer.
+    ///
+    /// A server that has only responded using HTTP/1.x may still support
+    /// HTTP/2 if it was not offered, such as over plain-text connections.
+    pub fn supports_http2(&self) -> bool {
+        self.http2
+    }
+
+    /// Get the content codings the server has sent response bodies in, such
+    /// as `gzip`, in lowercase and in the order they were first seen.
+    pub fn content_encodings(&self) -> &[String] {
+        &self.content_encodings
+    }
+
+    /// Get the methods the server most recently listed in an `Allow` header,
+    /// or `None` if it has not sent one.
+    pub fn allowed_methods(&self) -> Option<&[Method]> {
+        self.allowed_methods.as_deref()
+    }
+
+    /// Returns true if the server has listed the given method in an `Allow`
+    /// header, false if it has listed other methods only, or `None` if it has
+    /// not sent one.
+    pub fn allows_method(&self, method: &Method) -> Option<bool> {
+        self.allowed_methods
+            .as_ref()
+            .map(|methods| methods.contains(method))
+    }
+
+    fn update(&mut self, status: StatusCode, version: Version, headers: &HeaderMap, now: Instant) {
+        self.updated_at = now;
+        self.http_version = Some(version);
+        self.http2 |= version >= Version::HTTP_2;
+
+        if status == StatusCode::PARTIAL_CONTENT {
+            self.accepts_ranges = Some(true);
+        }
+
+        for value in headers.get_all(header::ACCEPT_RANGES) {
+            if let Ok(value) = value.to_str() {
+                let units = value.split(',').map(str::trim);
+
+                self.accepts_ranges = Some(
+                    units
+                        .filter(|unit| !unit.is_empty())
+                        .any(|unit| unit.eq_ignore_ascii_case("bytes")),
+                );
+            }
+        }
+
+        let encodings = headers
+            .get_all(header::CONTENT_ENCODING)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|encoding| encoding.trim().to_ascii_lowercase())
+            .filter(|encoding| !encoding.is_empty() && encoding != "identity");
+
+        for encoding in encodings {
+            if !self.content_encodings.contains(&encoding) {
+                self.content_encodings.push(encoding);
+            }
+        }
+
+        if headers.contains_key(header::ALLOW) {
+            self.allowed_methods = Some(
+                headers
+                    .get_all(header::ALLOW)
+                    .iter()
+                    .filter_map(|value| value.to_str().ok())
+                    .flat_map(|value| value.split(','))
+                    .map(str::trim)
+                    .filter(|method| !method.is_empty())
+                    .filter_map(|method| method.parse().ok())
+                    .collect(),
+            );
+        }
+    }
+}
+
+/// Get the key of the origin of a URI, or `None` if it has no host.
+fn origin(uri: &Uri) -> Option<String> {
+    let scheme = uri.scheme_str().unwrap_or("http").to_ascii_lowercase();
+    let port = uri
+        .port_u16()
+        .unwrap_or(if scheme == "https" { 443 } else { 80 });
+
+    Some(format!(
+        "{}://{}:{}",
+        scheme,
+        uri.host()?.to_ascii_lowercase(),
+        port
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| {
+                (
+                    header::HeaderName::from_static(name),
+                    value.parse().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn capabilities_are_learned_per_origin() {
+        let cache = CapabilityCache::new();
+        let now = Instant::now();
+
+        cache.record(
+            &"https://Example.org/a".parse().unwrap(),
+            StatusCode::OK,
+            Version::HTTP_2,
+            &headers(&[
+                ("accept-ranges", "bytes"),
+                ("content-encoding", "gzip"),
+                ("allow", "GET, HEAD, OPTIONS"),
+            ]),
+            now,
+        );
+        cache.record(
+            &"https://example.org:443/b".parse().unwrap(),
+            StatusCode::OK,
+            Version::HTTP_11,
+            &headers(&[("content-encoding", "br, gzip")]),
+            now,
+        );
+
+        let capabilities = cache.get(&"https://example.org/c".parse().unwrap()).unwrap();
+
+        assert_eq!(capabilities.accepts_ranges(), Some(true));
+        assert_eq!(capabilities.http_version(), Some(Version::HTTP_11));
+        assert!(capabilities.supports_http2());
+        assert_eq!(capabilities.content_encodings(), ["gzip", "br"]);
+        assert_eq!(
+            capabilities.allowed_methods().unwrap(),
+            [Method::GET, Method::HEAD, Method::OPTIONS]
+        );
+        assert_eq!(capabilities.allows_method(&Method::PUT), Some(false));
+
+        assert!(cache.get(&"http://example.org/".parse().unwrap()).is_none());
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn range_support() {
+        let cache = CapabilityCache::new();
+        let uri = "http://example.org/".parse().unwrap();
+        let now = Instant::now();
+
+        cache.record(&uri, StatusCode::OK, Version::HTTP_11, &HeaderMap::new(), now);
+        assert_eq!(cache.get(&uri).unwrap().accepts_ranges(), None);
+
+        cache.record(
+            &uri,
+            StatusCode::OK,
+            Version::HTTP_11,
+            &headers(&[("accept-ranges", "none")]),
+            now,
+        );
+        assert_eq!(cache.get(&uri).unwrap().accepts_ranges(), Some(false));
+
+        cache.record(
+            &uri,
+            StatusCode::PARTIAL_CONTENT,
+            Version::HTTP_11,
+            &HeaderMap::new(),
+            now,
+        );
+        assert_eq!(cache.get(&uri).unwrap().accepts_ranges(), Some(true));
+    }
+
+    #[test]
+    fn least_recently_updated_origin_is_forgotten() {
+        let cache = CapabilityCache::with_capacity(2);
+        let now = Instant::now();
+        let uris: Vec<Uri> = ["http://a/", "http://b/", "http://c/"]
+            .iter()
+            .map(|uri| uri.parse().unwrap())
+            .collect();
+
+        for (i, uri) in uris.iter().enumerate() {
+            cache.record(
+                uri,
+                StatusCode::OK,
+                Version::HTTP_11,
+                &HeaderMap::new(),
+                now + Duration::from_secs(i as u64),
+            );
+        }
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&uris[0]).is_none());
+        assert!(cache.remove(&uris[1]));
+        assert!(!cache.remove(&uris[1]));
+    }
+}
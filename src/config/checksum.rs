@@ -0,0 +1,96 @@
+use crate::hash::{base64_encode, md5::Md5, sha256::Sha256};
+use http::header::HeaderName;
+
+/// A checksum of the request body sent along with a request, so that the
+/// server can verify that the body arrived intact.
+///
+/// Several storage APIs require or accept a checksum of uploaded objects. The
+/// checksum of a body that is already in memory is sent in a header. A body
+/// that is streamed is not read twice to compute its checksum up front;
+/// instead, the checksum is computed while the body is being sent, and sent
+/// afterwards in a trailer. Trailers are only supported with chunked transfer
+/// encoding, so such requests are sent using HTTP/1.1.
+///
+/// If the request already has a header with the name of the checksum, it is
+/// sent as is.
+///
+/// Use with
+/// [`Configurable::body_checksum`](crate::config::Configurable::body_checksum).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum BodyChecksum {
+    /// The base64 encoded MD5 digest of the body in a `Content-MD5` header, as
+    /// defined in [RFC 1864](https://tools.ietf.org/html/rfc1864).
+    ContentMd5,
+
+    /// The base64 encoded SHA-256 digest of the body in a `Digest` header, as
+    /// defined in [RFC 3230](https://tools.ietf.org/html/rfc3230).
+    Sha256,
+}
+
+impl BodyChecksum {
+    /// Get the name of the header or trailer the checksum is sent in.
+    pub(crate) fn header_name(self) -> HeaderName {
+        match self {
+            Self::ContentMd5 => HeaderName::from_static("content-md5"),
+            Self::Sha256 => HeaderName::from_static("digest"),
+        }
+    }
+
+    /// Start computing the checksum of a body.
+    pub(crate) fn hasher(self) -> Hasher {
+        match self {
+            Self::ContentMd5 => Hasher::Md5(Md5::new()),
+            Self::Sha256 => Hasher::Sha256(Sha256::new()),
+        }
+    }
+
+    /// Compute the value of the header for a body stored in memory.
+    pub(crate) fn header_value(self, body: &[u8]) -> String {
+        let mut hasher = self.hasher();
+        hasher.update(body);
+        hasher.finish()
+    }
+}
+
+/// The checksum of a body being computed incrementally.
+#[derive(Clone)]
+pub(crate) enum Hasher {
+    Md5(Md5),
+    Sha256(Sha256),
+}
+
+impl Hasher {
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Md5(md5) => md5.update(data),
+            Self::Sha256(sha256) => sha256.update(data),
+        }
+    }
+
+    /// Finish computing the checksum, returning the value of the header it is
+    /// sent in.
+    pub(crate) fn finish(self) -> String {
+        match self {
+            Self::Md5(md5) => base64_encode(&md5.finish()),
+            Self::Sha256(sha256) => format!("SHA-256={}", base64_encode(&sha256.finish())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_values() {
+        assert_eq!(
+            BodyChecksum::ContentMd5.header_value(b"hello world"),
+            "XrY7u+Ae7tCTyyK7j1rNww=="
+        );
+        assert_eq!(
+            BodyChecksum::Sha256.header_value(b"hello world"),
+            "SHA-256=uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek="
+        );
+    }
+}
@@ -1,23 +1,71 @@
 use super::{
+    dial::ConnectorObj,
     dns::{DnsCache, ResolveMap},
+    instrument::SharedInstrumentation,
     request::SetOpt,
+    shutdown::ShutdownPolicy,
 };
-use std::time::Duration;
+use crate::{
+    handler::{BufferBudget, CallbackExecutor, ConnectionRequestLimit},
+    testing::MockHandler,
+};
+use std::{os::raw::c_long, sync::Arc, time::Duration};
+
+const CURLOPT_MAXLIFETIME_CONN: curl_sys::CURLoption = curl_sys::CURLOPTTYPE_LONG + 314;
+const CURLOPT_SUPPRESS_CONNECT_HEADERS: curl_sys::CURLoption = curl_sys::CURLOPTTYPE_LONG + 265;
 
 #[derive(Debug, Default)]
 pub(crate) struct ClientConfig {
     pub(crate) connection_cache_ttl: Option<Duration>,
+    pub(crate) connection_max_lifetime: Option<Duration>,
+    pub(crate) connection_request_limit: Option<Arc<ConnectionRequestLimit>>,
     pub(crate) close_connections: bool,
+    pub(crate) connector: Option<Arc<ConnectorObj>>,
     pub(crate) dns_cache: Option<DnsCache>,
     pub(crate) dns_resolve: Option<ResolveMap>,
+    pub(crate) response_buffer_budget: Option<Arc<BufferBudget>>,
+    pub(crate) callback_executor: Option<CallbackExecutor>,
+    pub(crate) instrumentation: Option<SharedInstrumentation>,
+    pub(crate) shutdown_policy: ShutdownPolicy,
+    pub(crate) handler: Option<MockHandler>,
 }
 
 impl SetOpt for ClientConfig {
+    #[allow(unsafe_code)]
     fn set_opt<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
         if let Some(ttl) = self.connection_cache_ttl {
             easy.maxage_conn(ttl)?;
         }
 
+        if let Some(lifetime) = self.connection_max_lifetime {
+            let value = lifetime.as_secs().max(1) as c_long;
+
+            // Not exposed by the safe API.
+            unsafe {
+                match curl_sys::curl_easy_setopt(easy.raw(), CURLOPT_MAXLIFETIME_CONN, value) {
+                    curl_sys::CURLE_OK => {}
+                    code => return Err(curl::Error::new(code)),
+                }
+            }
+        }
+
+        // The response to a proxy CONNECT request must not be mistaken for the
+        // final response, which is completed as soon as its headers arrive.
+        unsafe {
+            match curl_sys::curl_easy_setopt(
+                easy.raw(),
+                CURLOPT_SUPPRESS_CONNECT_HEADERS,
+                1 as c_long,
+            ) {
+                curl_sys::CURLE_OK => {}
+                code => return Err(curl::Error::new(code)),
+            }
+        }
+
+        if let Some(connector) = self.connector.as_ref() {
+            connector.set_opt(easy)?;
+        }
+
         if let Some(cache) = self.dns_cache.as_ref() {
             cache.set_opt(easy)?;
         }
@@ -0,0 +1,72 @@
+use std::{
+    fmt,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// A source of the current time for a client.
+///
+/// Calculations made by a client itself rather than by curl use its clock,
+/// including rate limits of [quotas](crate::config::Quota), refills of
+/// [retry budgets](crate::config::RetryBudget), expiration of cached DNS
+/// failures, well-known documents, and OAuth tokens, and the delays between
+/// retries of uploads. Replacing the clock, for example with a
+/// [`TestClock`](crate::testing::TestClock), lets tests of code using these
+/// features fast-forward time instead of sleeping.
+///
+/// Timeouts are enforced by curl using the system clock, and are not affected
+/// by the clock of a client.
+///
+/// The system clock is used by default.
+pub trait Clock: Send + Sync + 'static {
+    /// Get the current time.
+    fn now(&self) -> Instant;
+
+    /// Block the current thread for the given amount of time.
+    ///
+    /// The default implementation puts the thread to sleep.
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// The clock used by default, which reads the system clock.
+struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A shared handle to the clock of a client.
+#[derive(Clone)]
+pub(crate) struct SharedClock(Arc<dyn Clock>);
+
+impl SharedClock {
+    pub(crate) fn new(clock: impl Clock) -> Self {
+        Self(Arc::new(clock))
+    }
+
+    pub(crate) fn now(&self) -> Instant {
+        self.0.now()
+    }
+
+    #[cfg_attr(not(feature = "blocking"), allow(dead_code))]
+    pub(crate) fn sleep(&self, duration: Duration) {
+        self.0.sleep(duration)
+    }
+}
+
+impl Default for SharedClock {
+    fn default() -> Self {
+        Self::new(SystemClock)
+    }
+}
+
+impl fmt::Debug for SharedClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Clock")
+    }
+}
@@ -0,0 +1,134 @@
+//! Detection of client options that cannot work together.
+
+use super::{request::RequestConfig, IpVersion};
+use crate::info::is_http_version_supported;
+use std::{error::Error, fmt};
+
+/// Error listing every problem found in a client configuration, each with a
+/// suggestion on how to fix it.
+#[derive(Debug)]
+pub(crate) struct ConfigConflicts(Vec<String>);
+
+impl ConfigConflicts {
+    /// Check a client configuration for options that cannot work together, or
+    /// that cannot work in this build.
+    pub(crate) fn check(config: &RequestConfig) -> Result<(), Self> {
+        let mut problems = Vec::new();
+        let strict_version = config
+            .version_negotiation
+            .as_ref()
+            .and_then(|negotiation| negotiation.strict_version());
+
+        match strict_version {
+            Some(http::Version::HTTP_2) if !is_http_version_supported(http::Version::HTTP_2) => {
+                problems.push(
+                    "HTTP/2 is required by the version negotiation, but libcurl was built \
+                     without HTTP/2 support; enable the `http2` crate feature, or use \
+                     `VersionNegotiation::latest_compatible()`"
+                        .to_owned(),
+                );
+            }
+            Some(http::Version::HTTP_3) if !is_http_version_supported(http::Version::HTTP_3) => {
+                problems.push(
+                    "HTTP/3 is required by the version negotiation, but libcurl was built \
+                     without HTTP/3 support; use a libcurl with HTTP/3 support, or use \
+                     `VersionNegotiation::latest_compatible()`"
+                        .to_owned(),
+                );
+            }
+            _ => {}
+        }
+
+        if config.http10_keep_alive.is_some() {
+            if let Some(version) = strict_version.filter(|v| *v != http::Version::HTTP_10) {
+                problems.push(format!(
+                    "HTTP/1.0 compatibility is enabled, but the version negotiation only \
+                     allows {:?}; remove one of the two options",
+                    version
+                ));
+            }
+        }
+
+        if let Some(dialer) = config.dial.as_ref() {
+            if dialer.is_unix_socket() && matches!(config.proxy, Some(Some(_))) {
+                problems.push(
+                    "requests are sent over a Unix socket, so the proxy would never be used; \
+                     remove the proxy, or dial the proxy's address instead"
+                        .to_owned(),
+                );
+            }
+
+            if let Some(addr) = dialer.ip_socket_addr() {
+                let allowed = match config.ip_version {
+                    Some(IpVersion::V4) => addr.is_ipv4(),
+                    Some(IpVersion::V6) => addr.is_ipv6(),
+                    _ => true,
+                };
+
+                if !allowed {
+                    problems.push(format!(
+                        "the dialer connects to {}, which is not allowed by the IP version \
+                         restriction; dial an address of the allowed IP version, or use \
+                         `IpVersion::Any`",
+                        addr
+                    ));
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Self(problems))
+        }
+    }
+}
+
+impl fmt::Display for ConfigConflicts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.as_slice() {
+            [problem] => write!(f, "invalid configuration: {}", problem),
+            problems => {
+                write!(f, "invalid configuration, {} problems found:", problems.len())?;
+
+                for problem in problems {
+                    write!(f, "\n- {}", problem)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for ConfigConflicts {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Dialer, VersionNegotiation};
+    use std::net::Ipv6Addr;
+
+    #[test]
+    fn default_client_config_has_no_conflicts() {
+        assert!(ConfigConflicts::check(&RequestConfig::client_defaults()).is_ok());
+    }
+
+    #[test]
+    fn all_conflicts_are_reported() {
+        let config = RequestConfig {
+            version_negotiation: Some(VersionNegotiation::http11()),
+            http10_keep_alive: Some(true),
+            dial: Some(Dialer::ip_socket((Ipv6Addr::LOCALHOST, 8080))),
+            ip_version: Some(IpVersion::V4),
+            ..RequestConfig::client_defaults()
+        };
+
+        let error = ConfigConflicts::check(&config).unwrap_err();
+
+        assert_eq!(error.0.len(), 2);
+        assert!(error.to_string().starts_with("invalid configuration, 2 problems found:\n- "));
+        assert!(error.0[0].contains("HTTP/1.1"));
+        assert!(error.0[1].contains("[::1]:8080"));
+    }
+}
@@ -0,0 +1,219 @@
+use http::HeaderValue;
+use std::{fmt, io, sync::Arc};
+
+/// A content coding such as a compression algorithm, supplying the encoders
+/// and decoders for bodies in that coding.
+///
+/// Register content codings with [`ContentCodings`] to support encodings that
+/// are not built in.
+pub trait ContentCoding: Send + Sync + 'static {
+    /// Create a stream that decodes data in this coding, used for response
+    /// bodies sent with this coding in their `Content-Encoding` header.
+    fn decoder(&self) -> Box<dyn CodingStream>;
+
+    /// Create a stream that encodes data in this coding, used for request
+    /// bodies whose `Content-Encoding` header names this coding.
+    fn encoder(&self) -> Box<dyn CodingStream>;
+}
+
+/// One direction of a [`ContentCoding`], converting data incrementally as it
+/// is sent or received.
+pub trait CodingStream: Send + Sync {
+    /// Convert a chunk of input, appending any output produced so far to
+    /// `output`.
+    fn write(&mut self, input: &[u8], output: &mut Vec<u8>) -> io::Result<()>;
+
+    /// Signal the end of the input, appending any remaining output to
+    /// `output`.
+    fn finish(&mut self, output: &mut Vec<u8>) -> io::Result<()>;
+}
+
+/// A registry of custom content codings that the client supports in addition
+/// to its built-in ones.
+///
+/// When a registry is configured, the client decodes response bodies itself
+/// instead of letting curl do it, and advertises the registered codings along
+/// with `deflate` and `gzip` in the `Accept-Encoding` header unless the
+/// request sets one itself. A response body in a registered coding is then
+/// decoded transparently, just like one in a built-in coding.
+///
+/// The built-in `deflate` and `gzip` codings are only decoded by the client
+/// when the [`content-decoding`](../index.html#content-decoding) feature is
+/// enabled. Without it, only the registered codings are advertised and
+/// supported while a registry is configured.
+///
+/// A request body is encoded with a registered coding if the request has a
+/// `Content-Encoding` header naming it, in which case the body is sent using
+/// chunked transfer encoding since its encoded length is not known ahead of
+/// time.
+///
+/// Registered codings take precedence over the built-in ones of the same
+/// name. Names are compared case-insensitively.
+///
+/// Use with
+/// [`Configurable::content_codings`](crate::config::Configurable::content_codings).
+///
+/// # Examples
+///
+/// ```
+/// use isahc::{
+///     config::{CodingStream, ContentCoding, ContentCodings},
+///     prelude::*,
+///     HttpClient,
+/// };
+/// use std::io;
+///
+/// /// A coding that flips every bit.
+/// struct Invert;
+///
+/// impl CodingStream for Invert {
+///     fn write(&mut self, input: &[u8], output: &mut Vec<u8>) -> io::Result<()> {
+///         output.extend(input.iter().map(|byte| !byte));
+///         Ok(())
+///     }
+///
+///     fn finish(&mut self, _output: &mut Vec<u8>) -> io::Result<()> {
+///         Ok(())
+///     }
+/// }
+///
+/// impl ContentCoding for Invert {
+///     fn decoder(&self) -> Box<dyn CodingStream> {
+///         Box::new(Invert)
+///     }
+///
+///     fn encoder(&self) -> Box<dyn CodingStream> {
+///         Box::new(Invert)
+///     }
+/// }
+///
+/// let client = HttpClient::builder()
+///     .content_codings(ContentCodings::new().register("x-invert", Invert))
+///     .build()?;
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Clone, Default)]
+pub struct ContentCodings {
+    codings: Vec<(String, Arc<dyn ContentCoding>)>,
+}
+
+impl ContentCodings {
+    /// Create a new registry without any custom codings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a content coding under the given name, replacing any coding
+    /// previously registered under the same name.
+    ///
+    /// Names must be valid tokens, or requests fail with
+    /// [`ErrorKind::InvalidRequest`](crate::error::ErrorKind::InvalidRequest).
+    #[must_use = "builders have no effect if unused"]
+    pub fn register(mut self, name: impl Into<String>, coding: impl ContentCoding) -> Self {
+        let name = name.into().to_ascii_lowercase();
+
+        self.codings.retain(|(existing, _)| *existing != name);
+        self.codings.push((name, Arc::new(coding)));
+        self
+    }
+
+    /// Get the coding registered under the given name, if any.
+    pub(crate) fn get(&self, name: &str) -> Option<&dyn ContentCoding> {
+        self.codings
+            .iter()
+            .find(|(registered, _)| registered.eq_ignore_ascii_case(name))
+            .map(|(_, coding)| &**coding)
+    }
+
+    /// Get the value of the `Accept-Encoding` header listing the built-in
+    /// codings along with the registered ones, or `None` if a name is not a
+    /// valid token.
+    pub(crate) fn accept_encoding(&self) -> Option<HeaderValue> {
+        let mut value = String::from(crate::decode::ACCEPT_ENCODING);
+
+        for (name, _) in &self.codings {
+            if name.is_empty() || !name.bytes().all(is_token_char) {
+                return None;
+            }
+
+            // Skip codings that are built in and listed already.
+            if !value.split(", ").any(|listed| listed == name) {
+                if !value.is_empty() {
+                    value.push_str(", ");
+                }
+
+                value.push_str(name);
+            }
+        }
+
+        HeaderValue::from_str(&value).ok()
+    }
+}
+
+impl fmt::Debug for ContentCodings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.codings.iter().map(|(name, _)| name))
+            .finish()
+    }
+}
+
+fn is_token_char(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Identity;
+
+    impl CodingStream for Identity {
+        fn write(&mut self, input: &[u8], output: &mut Vec<u8>) -> io::Result<()> {
+            output.extend_from_slice(input);
+            Ok(())
+        }
+
+        fn finish(&mut self, _: &mut Vec<u8>) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl ContentCoding for Identity {
+        fn decoder(&self) -> Box<dyn CodingStream> {
+            Box::new(Identity)
+        }
+
+        fn encoder(&self) -> Box<dyn CodingStream> {
+            Box::new(Identity)
+        }
+    }
+
+    #[test]
+    fn lookup_is_case_insensitive() {
+        let codings = ContentCodings::new().register("X-Custom", Identity);
+
+        assert!(codings.get("x-custom").is_some());
+        assert!(codings.get("X-CUSTOM").is_some());
+        assert!(codings.get("gzip").is_none());
+    }
+
+    #[cfg(feature = "content-decoding")]
+    #[test]
+    fn accept_encoding_lists_registered_codings_once() {
+        let codings = ContentCodings::new()
+            .register("zstd", Identity)
+            .register("gzip", Identity)
+            .register("ZSTD", Identity);
+
+        assert_eq!(codings.accept_encoding().unwrap(), "deflate, gzip, zstd");
+        assert_eq!(format!("{:?}", codings), r#"["gzip", "zstd"]"#);
+    }
+
+    #[test]
+    fn invalid_name_is_rejected() {
+        let codings = ContentCodings::new().register("not a token", Identity);
+
+        assert!(codings.accept_encoding().is_none());
+    }
+}
@@ -0,0 +1,182 @@
+use std::{error::Error, fmt};
+
+/// Limits on how much a compressed response body may expand when it is
+/// automatically decompressed.
+///
+/// A small compressed response can decompress into an enormous body, which
+/// can be used to exhaust the memory or disk of clients that read whole
+/// responses. When a limit is configured and a response body that was sent
+/// with a `Content-Encoding` exceeds it, the transfer is aborted and reading
+/// the body fails with
+/// [`ErrorKind::DecompressionLimitExceeded`](crate::error::ErrorKind::DecompressionLimitExceeded).
+///
+/// Limits have no effect on responses that are not compressed, or when
+/// [automatic
+/// decompression](crate::config::Configurable::automatic_decompression) is
+/// disabled.
+///
+/// # Examples
+///
+/// ```
+/// use isahc::{config::DecompressionLimit, prelude::*, HttpClient};
+///
+/// let client = HttpClient::builder()
+///     .decompression_limit(
+///         DecompressionLimit::new()
+///             .max_size(64 * 1024 * 1024)
+///             .max_ratio(100),
+///     )
+///     .build()?;
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct DecompressionLimit {
+    max_size: Option<u64>,
+    max_ratio: Option<u64>,
+}
+
+impl DecompressionLimit {
+    /// Number of decompressed bytes that may be received before the ratio
+    /// limit is checked. Small bodies of repetitive data can legitimately
+    /// have very high compression ratios.
+    const RATIO_GRACE: u64 = 1024 * 1024;
+
+    /// Create a new limit that does not restrict anything yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum size of a decompressed response body in bytes.
+    #[must_use = "builders have no effect if unused"]
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Set the maximum ratio of decompressed bytes to compressed bytes
+    /// received.
+    ///
+    /// The ratio is only checked once more than 1 MiB has been decompressed,
+    /// since small bodies of repetitive data can legitimately compress very
+    /// well.
+    #[must_use = "builders have no effect if unused"]
+    pub fn max_ratio(mut self, ratio: u64) -> Self {
+        self.max_ratio = Some(ratio);
+        self
+    }
+
+    /// Check the amount of data received so far against this limit.
+    pub(crate) fn check(
+        &self,
+        compressed: u64,
+        decompressed: u64,
+    ) -> Result<(), DecompressionLimitExceeded> {
+        if let Some(max_size) = self.max_size {
+            if decompressed > max_size {
+                return Err(DecompressionLimitExceeded(format!(
+                    "decompressed response body is larger than {} bytes",
+                    max_size
+                )));
+            }
+        }
+
+        if let Some(max_ratio) = self.max_ratio {
+            if decompressed > Self::RATIO_GRACE
+                && decompressed > compressed.max(1).saturating_mul(max_ratio)
+            {
+                return Err(DecompressionLimitExceeded(format!(
+                    "decompressed response body is more than {} times its compressed size",
+                    max_ratio
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Media ranges of the response bodies to decompress automatically.
+#[derive(Clone, Debug)]
+pub(crate) struct ContentTypeFilter(Vec<String>);
+
+impl ContentTypeFilter {
+    #[cfg_attr(not(feature = "content-decoding"), allow(dead_code))]
+    pub(crate) fn new(ranges: Vec<String>) -> Self {
+        Self(ranges)
+    }
+
+    /// Check whether a `Content-Type` header value matches any of the media
+    /// ranges. A missing content type matches nothing.
+    pub(crate) fn matches(&self, content_type: Option<&str>) -> bool {
+        let essence = match content_type.and_then(|value| value.split(';').next()) {
+            Some(essence) if !essence.trim().is_empty() => essence.trim(),
+            _ => return false,
+        };
+
+        self.0.iter().map(|range| range.trim()).any(|range| {
+            match range.strip_suffix("/*") {
+                Some("*") => true,
+                Some(top_level) => matches!(
+                    essence.split_once('/'),
+                    Some((essence, _)) if essence.eq_ignore_ascii_case(top_level)
+                ),
+                None => essence.eq_ignore_ascii_case(range),
+            }
+        })
+    }
+}
+
+/// Source of the error returned when a response body exceeds a
+/// [`DecompressionLimit`].
+#[derive(Debug)]
+pub(crate) struct DecompressionLimitExceeded(String);
+
+impl fmt::Display for DecompressionLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for DecompressionLimitExceeded {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unlimited_by_default() {
+        assert!(DecompressionLimit::new().check(1, u64::MAX).is_ok());
+    }
+
+    #[test]
+    fn max_size() {
+        let limit = DecompressionLimit::new().max_size(100);
+
+        assert!(limit.check(10, 100).is_ok());
+        assert!(limit.check(10, 101).is_err());
+    }
+
+    #[test]
+    fn content_type_filter_matches_ranges() {
+        let filter = ContentTypeFilter::new(vec!["application/json".into(), "text/*".into()]);
+
+        assert!(filter.matches(Some("application/json")));
+        assert!(filter.matches(Some("Application/JSON; charset=utf-8")));
+        assert!(filter.matches(Some("text/html")));
+        assert!(!filter.matches(Some("application/gzip")));
+        assert!(!filter.matches(Some("textual/plain")));
+        assert!(!filter.matches(Some("")));
+        assert!(!filter.matches(None));
+
+        assert!(ContentTypeFilter::new(vec!["*/*".into()]).matches(Some("image/png")));
+    }
+
+    #[test]
+    fn max_ratio_is_checked_after_grace() {
+        let limit = DecompressionLimit::new().max_ratio(10);
+
+        assert!(limit.check(1, 1024 * 1024).is_ok());
+        assert!(limit.check(200_000, 2_000_000).is_ok());
+        assert!(limit.check(100_000, 2_000_000).is_err());
+    }
+}
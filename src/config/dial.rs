@@ -4,7 +4,28 @@
 use super::SetOpt;
 use curl::easy::{Easy2, List};
 use http::Uri;
-use std::{convert::TryFrom, fmt, net::SocketAddr, str::FromStr};
+use std::{
+    convert::TryFrom,
+    fmt,
+    io,
+    net::{SocketAddr, SocketAddrV6, TcpStream},
+    os::raw::{c_int, c_void},
+    panic::{self, AssertUnwindSafe},
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+/// Socket option callback result telling curl that a socket is already
+/// connected.
+const CURL_SOCKOPT_ALREADY_CONNECTED: c_int = 2;
+
+/// Protocol number of TCP, which is the same on all platforms.
+const IPPROTO_TCP: c_int = 6;
+
+/// Option for connecting to an abstract Unix socket, which curl-sys does not
+/// define in the versions we support.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const CURLOPT_ABSTRACT_UNIX_SOCKET: curl_sys::CURLoption = curl_sys::CURLOPTTYPE_OBJECTPOINT + 264;
 
 /// An error which can be returned when parsing a dial address.
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -52,6 +73,9 @@ enum Inner {
 
     #[cfg(unix)]
     UnixSocket(std::path::PathBuf),
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    AbstractUnixSocket(Vec<u8>),
 }
 
 impl Dialer {
@@ -102,6 +126,49 @@ impl Dialer {
     pub fn unix_socket(path: impl Into<std::path::PathBuf>) -> Self {
         Self(Inner::UnixSocket(path.into()))
     }
+
+    /// Connect to a Unix socket in the abstract namespace, which is identified
+    /// by a name instead of a file.
+    ///
+    /// The name is given without the leading null byte that marks an abstract
+    /// socket address.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::config::Dialer;
+    ///
+    /// # #[cfg(target_os = "linux")]
+    /// let dialer = Dialer::abstract_unix_socket("my-daemon");
+    /// ```
+    ///
+    /// # Availability
+    ///
+    /// This function is only available on Linux and Android.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn abstract_unix_socket(name: impl Into<Vec<u8>>) -> Self {
+        Self(Inner::AbstractUnixSocket(name.into()))
+    }
+
+    /// Get the IP socket address to connect to, if this is an IP socket
+    /// dialer.
+    pub(crate) fn ip_socket_addr(&self) -> Option<SocketAddr> {
+        match &self.0 {
+            Inner::IpSocket(addr) => addr.strip_prefix("::")?.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns true if this dialer connects to a Unix socket.
+    pub(crate) fn is_unix_socket(&self) -> bool {
+        match &self.0 {
+            #[cfg(unix)]
+            Inner::UnixSocket(_) => true,
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            Inner::AbstractUnixSocket(_) => true,
+            _ => false,
+        }
+    }
 }
 
 impl Default for Dialer {
@@ -134,7 +201,7 @@ impl FromStr for Dialer {
             if s.starts_with("unix:") {
                 // URI paths are always absolute.
                 let mut path = std::path::PathBuf::from("/");
-                path.push(&s[5..].trim_start_matches('/'));
+                path.push(s[5..].trim_start_matches('/'));
 
                 return Ok(Self(Inner::UnixSocket(path)));
             }
@@ -185,10 +252,413 @@ impl SetOpt for Dialer {
             _ => None,
         })?;
 
+        // Curl stores abstract socket names in place of the socket path, so
+        // this must be set after clearing the path above.
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            if let Inner::AbstractUnixSocket(name) = &self.0 {
+                let name = std::ffi::CString::new(name.as_slice())?;
+
+                // The safe wrapper for this option needs a newer curl crate
+                // than our minimum supported Rust version can build. Curl
+                // copies the string, so it need not outlive the call.
+                #[allow(unsafe_code)]
+                unsafe {
+                    match curl_sys::curl_easy_setopt(
+                        easy.raw(),
+                        CURLOPT_ABSTRACT_UNIX_SOCKET,
+                        name.as_ptr(),
+                    ) {
+                        curl_sys::CURLE_OK => {}
+                        code => return Err(curl::Error::new(code)),
+                    }
+                }
+            }
+        }
+
         Ok(())
     }
 }
 
+/// Opens connections on behalf of curl, in place of the sockets it would
+/// otherwise create and connect itself.
+///
+/// A connector makes it possible to supply sockets that were connected in some
+/// special way, such as through a VPN library or with socket options that
+/// Isahc does not expose, while curl still speaks HTTP and TLS over them. Set
+/// one with
+/// [`HttpClientBuilder::connector`](crate::HttpClientBuilder::connector).
+///
+/// Connectors are invoked on the thread driving all transfers of the client,
+/// so while one is connecting no other transfer makes progress. They should
+/// return quickly.
+///
+/// A connector is implemented for any function that takes the address to
+/// connect to and returns a connected [`TcpStream`].
+///
+/// # Examples
+///
+/// ```
+/// use isahc::HttpClient;
+/// use std::{net::TcpStream, time::Duration};
+///
+/// let client = HttpClient::builder()
+///     .connector(|addr| {
+///         let stream = TcpStream::connect_timeout(&addr, Duration::from_secs(5))?;
+///         stream.set_ttl(32)?;
+///         Ok(stream)
+///     })
+///     .build()?;
+/// # Ok::<(), isahc::Error>(())
+/// ```
+pub trait Connector: Send + Sync + 'static {
+    /// Open a TCP connection to the given address.
+    ///
+    /// This is called each time curl needs a new connection to an IP address,
+    /// after resolving the host name of a request. If an error is returned,
+    /// the request fails with
+    /// [`ErrorKind::ConnectionFailed`](crate::error::ErrorKind::ConnectionFailed)
+    /// unless curl has other addresses to try.
+    fn connect(&self, addr: SocketAddr) -> io::Result<TcpStream>;
+
+    /// Close a connection previously opened by this connector, once curl no
+    /// longer needs it.
+    ///
+    /// The default implementation simply drops the stream.
+    fn close(&self, stream: TcpStream) {
+        drop(stream);
+    }
+}
+
+impl<F> Connector for F
+where
+    F: Fn(SocketAddr) -> io::Result<TcpStream> + Send + Sync + 'static,
+{
+    fn connect(&self, addr: SocketAddr) -> io::Result<TcpStream> {
+        (self)(addr)
+    }
+}
+
+/// Type-erased connector with a stable address that can be handed to curl.
+///
+/// Curl keeps a pointer to this object in each connection it opens, which may
+/// outlive the request that opened it. The agent therefore holds on to a
+/// reference until its multi handle, and with it all connections, is gone.
+pub(crate) struct ConnectorObj(Box<dyn Connector>);
+
+impl ConnectorObj {
+    pub(crate) fn new(connector: impl Connector) -> Self {
+        Self(Box::new(connector))
+    }
+}
+
+impl fmt::Debug for ConnectorObj {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Connector")
+    }
+}
+
+impl SetOpt for ConnectorObj {
+    #[allow(unsafe_code)]
+    fn set_opt<H>(&self, easy: &mut Easy2<H>) -> Result<(), curl::Error> {
+        let data = self as *const Self as *mut c_void;
+        let open: extern "C" fn(
+            *mut c_void,
+            curl_sys::curlsocktype,
+            *mut curl_sys::curl_sockaddr,
+        ) -> curl_sys::curl_socket_t = open_socket;
+        let sockopt: extern "C" fn(*mut c_void, curl_sys::curl_socket_t, c_int) -> c_int =
+            socket_options;
+        let close: extern "C" fn(*mut c_void, curl_sys::curl_socket_t) -> c_int = close_socket;
+
+        // Not exposed by the safe API, which also does not give the address to
+        // connect to.
+        unsafe {
+            for (option, value) in [
+                (curl_sys::CURLOPT_OPENSOCKETFUNCTION, open as *const c_void),
+                (curl_sys::CURLOPT_OPENSOCKETDATA, data),
+                (curl_sys::CURLOPT_SOCKOPTFUNCTION, sockopt as *const c_void),
+                (curl_sys::CURLOPT_SOCKOPTDATA, data),
+                (curl_sys::CURLOPT_CLOSESOCKETFUNCTION, close as *const c_void),
+                (curl_sys::CURLOPT_CLOSESOCKETDATA, data),
+            ] {
+                match curl_sys::curl_easy_setopt(easy.raw(), option, value) {
+                    curl_sys::CURLE_OK => {}
+                    code => return Err(curl::Error::new(code)),
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A request extension that sends the request over a stream the caller has
+/// already connected, instead of having curl open a connection of its own.
+///
+/// This is meant for environments with custom tunneling, where curl cannot
+/// connect to the server directly but the caller can obtain a connected socket
+/// some other way. Curl speaks the protocol given by the request URI over the
+/// stream, so for an `https` URI curl negotiates TLS over it as usual, while
+/// for an `http` URI the request is sent as is. If the tunnel already provides
+/// encryption, use an `http` URI.
+///
+/// The host name in the request URI is not resolved and is only used for the
+/// `Host` header and for verifying the server certificate. Proxy and dialer
+/// settings are ignored for the request.
+///
+/// A stream can only carry a single request, and is closed once the request
+/// completes. Sending another request with the same transport fails with an
+/// error of kind
+/// [`ErrorKind::InvalidRequest`](crate::error::ErrorKind::InvalidRequest).
+/// Redirects and retries do not carry the transport, and are sent over new
+/// connections as usual.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{config::Transport, prelude::*, Request};
+/// use std::net::TcpStream;
+///
+/// let stream = TcpStream::connect("10.0.0.1:443")?;
+///
+/// let response = Request::get("https://example.org/")
+///     .extension(Transport::new(stream))
+///     .body(())?
+///     .send()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone)]
+pub struct Transport(Arc<Mutex<Option<TcpStream>>>);
+
+impl Transport {
+    /// Create a transport from a connected TCP stream.
+    pub fn new(stream: TcpStream) -> Self {
+        Self(Arc::new(Mutex::new(Some(stream))))
+    }
+
+    /// Create a transport from a connected Unix socket.
+    ///
+    /// # Availability
+    ///
+    /// This function is only available on Unix.
+    #[cfg(unix)]
+    #[allow(unsafe_code)]
+    pub fn unix(stream: std::os::unix::net::UnixStream) -> Self {
+        use std::os::unix::io::IntoRawFd;
+
+        // Curl only needs the descriptor, whatever kind of socket it is.
+        Self::new(unsafe { from_raw_socket(stream.into_raw_fd()) })
+    }
+
+    /// Take the stream out of the transport, if it has not been used yet.
+    pub(crate) fn take(&self) -> Option<TcpStream> {
+        self.0.lock().unwrap().take()
+    }
+}
+
+impl fmt::Debug for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transport")
+            .field("used", &self.0.lock().unwrap().is_none())
+            .finish()
+    }
+}
+
+/// A stream taken from a [`Transport`] for a single transfer, with a stable
+/// address that can be handed to curl.
+///
+/// It must be kept alive for as long as the transfer it is set on.
+pub(crate) struct TransportSocket(Mutex<Option<TcpStream>>);
+
+impl TransportSocket {
+    pub(crate) fn new(stream: TcpStream) -> Self {
+        Self(Mutex::new(Some(stream)))
+    }
+}
+
+impl SetOpt for TransportSocket {
+    #[allow(unsafe_code)]
+    fn set_opt<H>(&self, easy: &mut Easy2<H>) -> Result<(), curl::Error> {
+        let data = self as *const Self as *mut c_void;
+        let open: extern "C" fn(
+            *mut c_void,
+            curl_sys::curlsocktype,
+            *mut curl_sys::curl_sockaddr,
+        ) -> curl_sys::curl_socket_t = open_transport_socket;
+        let sockopt: extern "C" fn(*mut c_void, curl_sys::curl_socket_t, c_int) -> c_int =
+            socket_options;
+
+        // Never share the stream with other requests, in either direction.
+        easy.fresh_connect(true)?;
+        easy.forbid_reuse(true)?;
+
+        // Skip resolving the host name, which may not be resolvable from here.
+        let mut connect_to = List::new();
+        connect_to.append("::127.0.0.1:")?;
+        easy.connect_to(connect_to)?;
+        easy.noproxy("*")?;
+
+        #[cfg(unix)]
+        easy.unix_socket_path(None::<&str>)?;
+
+        unsafe {
+            for (option, value) in [
+                (curl_sys::CURLOPT_OPENSOCKETFUNCTION, open as *const c_void),
+                (curl_sys::CURLOPT_OPENSOCKETDATA, data),
+                (curl_sys::CURLOPT_SOCKOPTFUNCTION, sockopt as *const c_void),
+                (curl_sys::CURLOPT_SOCKOPTDATA, data),
+                // Let curl close the stream itself, even if the client has a
+                // connector.
+                (curl_sys::CURLOPT_CLOSESOCKETFUNCTION, std::ptr::null()),
+                (curl_sys::CURLOPT_CLOSESOCKETDATA, std::ptr::null_mut()),
+            ] {
+                match curl_sys::curl_easy_setopt(easy.raw(), option, value) {
+                    curl_sys::CURLE_OK => {}
+                    code => return Err(curl::Error::new(code)),
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[allow(unsafe_code)]
+extern "C" fn open_socket(
+    data: *mut c_void,
+    purpose: curl_sys::curlsocktype,
+    address: *mut curl_sys::curl_sockaddr,
+) -> curl_sys::curl_socket_t {
+    let connector = unsafe { &*(data as *const ConnectorObj) };
+    let address = unsafe { &*address };
+
+    if purpose != curl_sys::CURLSOCKTYPE_IPCXN || address.protocol != IPPROTO_TCP {
+        tracing::debug!("connector does not support this kind of socket");
+        return curl_sys::CURL_SOCKET_BAD;
+    }
+
+    let addr = match unsafe { to_socket_addr(address) } {
+        Some(addr) => addr,
+        None => return curl_sys::CURL_SOCKET_BAD,
+    };
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| connector.0.connect(addr)))
+        .unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::Other, "connector panicked")))
+        .and_then(|stream| {
+            stream.set_nonblocking(true)?;
+            Ok(stream)
+        });
+
+    match result {
+        Ok(stream) => into_raw_socket(stream),
+        Err(e) => {
+            tracing::debug!(%addr, "connector failed to connect: {}", e);
+            curl_sys::CURL_SOCKET_BAD
+        }
+    }
+}
+
+extern "C" fn socket_options(
+    _data: *mut c_void,
+    _socket: curl_sys::curl_socket_t,
+    _purpose: c_int,
+) -> c_int {
+    // Every socket opened by a connector is already connected.
+    CURL_SOCKOPT_ALREADY_CONNECTED
+}
+
+#[allow(unsafe_code)]
+extern "C" fn close_socket(data: *mut c_void, socket: curl_sys::curl_socket_t) -> c_int {
+    let connector = unsafe { &*(data as *const ConnectorObj) };
+    let stream = unsafe { from_raw_socket(socket) };
+
+    if panic::catch_unwind(AssertUnwindSafe(|| connector.0.close(stream))).is_err() {
+        tracing::error!("connector panicked while closing a connection");
+    }
+
+    0
+}
+
+#[allow(unsafe_code)]
+extern "C" fn open_transport_socket(
+    data: *mut c_void,
+    _purpose: curl_sys::curlsocktype,
+    _address: *mut curl_sys::curl_sockaddr,
+) -> curl_sys::curl_socket_t {
+    let socket = unsafe { &*(data as *const TransportSocket) };
+
+    // Curl may ask for more than one connection, such as when it tries the
+    // next address after a failure, but there is only one stream to give.
+    let stream = match socket.0.lock() {
+        Ok(mut stream) => stream.take(),
+        Err(_) => None,
+    };
+
+    match stream.map(|stream| stream.set_nonblocking(true).map(|_| stream)) {
+        Some(Ok(stream)) => into_raw_socket(stream),
+        Some(Err(e)) => {
+            tracing::debug!("failed to prepare transport stream: {}", e);
+            curl_sys::CURL_SOCKET_BAD
+        }
+        None => {
+            tracing::debug!("transport stream was already used");
+            curl_sys::CURL_SOCKET_BAD
+        }
+    }
+}
+
+/// Read an IPv4 or IPv6 address given by curl.
+///
+/// The layout of `sockaddr_in` and `sockaddr_in6` is the same on all supported
+/// platforms past the address family, so they are told apart by length.
+#[allow(unsafe_code)]
+unsafe fn to_socket_addr(address: &curl_sys::curl_sockaddr) -> Option<SocketAddr> {
+    let bytes = std::slice::from_raw_parts(
+        &address.addr as *const _ as *const u8,
+        address.addrlen as usize,
+    );
+    let port = u16::from_be_bytes([bytes.get(2).copied()?, bytes.get(3).copied()?]);
+
+    match bytes.len() {
+        16 => {
+            let ip = <[u8; 4]>::try_from(&bytes[4..8]).ok()?;
+
+            Some(SocketAddr::from((ip, port)))
+        }
+        28 => {
+            let flow_info = u32::from_be_bytes(<[u8; 4]>::try_from(&bytes[4..8]).ok()?);
+            let ip = <[u8; 16]>::try_from(&bytes[8..24]).ok()?;
+            let scope_id = u32::from_ne_bytes(<[u8; 4]>::try_from(&bytes[24..28]).ok()?);
+
+            Some(SocketAddrV6::new(ip.into(), port, flow_info, scope_id).into())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(unix)]
+fn into_raw_socket(stream: TcpStream) -> curl_sys::curl_socket_t {
+    std::os::unix::io::IntoRawFd::into_raw_fd(stream)
+}
+
+#[cfg(windows)]
+fn into_raw_socket(stream: TcpStream) -> curl_sys::curl_socket_t {
+    std::os::windows::io::IntoRawSocket::into_raw_socket(stream) as _
+}
+
+#[cfg(unix)]
+#[allow(unsafe_code)]
+unsafe fn from_raw_socket(socket: curl_sys::curl_socket_t) -> TcpStream {
+    std::os::unix::io::FromRawFd::from_raw_fd(socket)
+}
+
+#[cfg(windows)]
+#[allow(unsafe_code)]
+unsafe fn from_raw_socket(socket: curl_sys::curl_socket_t) -> TcpStream {
+    std::os::windows::io::FromRawSocket::from_raw_socket(socket as _)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
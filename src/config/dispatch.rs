@@ -0,0 +1,26 @@
+/// Describes how a client with multiple agent threads chooses the thread that
+/// executes each request.
+///
+/// The default is to take turns.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AgentDispatch {
+    /// Assign requests to each agent thread in turn.
+    ///
+    /// This is the default strategy.
+    RoundRobin,
+
+    /// Assign each request to the agent thread with the fewest requests in
+    /// progress or waiting to begin.
+    ///
+    /// This keeps threads evenly busy when some requests take much longer than
+    /// others, such as a mix of long downloads and short API calls.
+    LeastLoaded,
+}
+
+// Can't derive this on our MSRV.
+#[allow(clippy::derivable_impls)]
+impl Default for AgentDispatch {
+    fn default() -> Self {
+        AgentDispatch::RoundRobin
+    }
+}
@@ -72,13 +72,52 @@ impl ResolveMap {
 
     /// Add a DNS mapping for a given host and port pair.
     #[must_use = "builders have no effect if unused"]
-    pub fn add<H, A>(mut self, host: H, port: u16, addr: A) -> Self
+    pub fn add<H, A>(self, host: H, port: u16, addr: A) -> Self
     where
         H: AsRef<str>,
         A: Into<IpAddr>,
     {
-        self.0
-            .push(format!("{}:{}:{}", host.as_ref(), port, addr.into()));
+        self.add_all(host, port, std::iter::once(addr))
+    }
+
+    /// Add a DNS mapping for a given host and port pair to multiple IP
+    /// addresses.
+    ///
+    /// Addresses are tried in the given order when connecting, moving on to
+    /// the next one if connecting to an address fails. If no addresses are
+    /// given then no mapping is added.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::config::ResolveMap;
+    ///
+    /// // Send requests for example.org to either of two backends.
+    /// let map = ResolveMap::new()
+    ///     .add_all("example.org", 443, [[192, 0, 2, 10], [192, 0, 2, 11]]);
+    /// ```
+    #[must_use = "builders have no effect if unused"]
+    pub fn add_all<H, I, A>(mut self, host: H, port: u16, addrs: I) -> Self
+    where
+        H: AsRef<str>,
+        I: IntoIterator<Item = A>,
+        A: Into<IpAddr>,
+    {
+        let addrs = addrs
+            .into_iter()
+            .map(|addr| match addr.into() {
+                // Bracket IPv6 addresses so that they cannot be confused with
+                // the separators between the host, port, and addresses.
+                IpAddr::V6(addr) => format!("[{}]", addr),
+                addr => addr.to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        if !addrs.is_empty() {
+            self.0
+                .push(format!("{}:{}:{}", host.as_ref(), port, addrs.join(",")));
+        }
+
         self
     }
 }
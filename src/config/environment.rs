@@ -0,0 +1,180 @@
+use http::Uri;
+use std::{collections::HashMap, env};
+
+/// The process-wide state a client consults implicitly, such as environment
+/// variables, replaced with state of its own.
+///
+/// By default, clients read the proxy to use from the `http_proxy`,
+/// `https_proxy`, `all_proxy` and `no_proxy` environment variables of the
+/// process, and check cookie domains against a public suffix list shared by
+/// the whole process, which is refreshed from the Internet using a shared
+/// client. This is convenient, but means that clients in the same process
+/// cannot be configured independently, which gets in the way when running
+/// tests in parallel or when embedding clients into plugins of a host
+/// application.
+///
+/// A client with an environment of its own reads environment variables from
+/// the environment instead, and uses the list of public suffixes bundled with
+/// Isahc, without refreshing it, unless told to use the shared list. The
+/// proxy configured explicitly with
+/// [`Configurable::proxy`](crate::config::Configurable::proxy) and
+/// [`Configurable::proxy_blacklist`](crate::config::Configurable::proxy_blacklist)
+/// still takes precedence over the one found in the environment.
+///
+/// Use with
+/// [`HttpClientBuilder::environment`](crate::HttpClientBuilder::environment).
+///
+/// # Examples
+///
+/// ```
+/// use isahc::{config::Environment, HttpClient};
+///
+/// // Ignore any proxy configured for the process, except for this client.
+/// let client = HttpClient::builder()
+///     .environment(
+///         Environment::isolated()
+///             .var("https_proxy", "http://proxy.test:3128")
+///             .var("no_proxy", "localhost"),
+///     )
+///     .build()?;
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Environment {
+    /// Whether variables not set here are read from the process.
+    inherit: bool,
+    vars: HashMap<String, String>,
+    shared_public_suffix_list: bool,
+}
+
+impl Environment {
+    /// Create an environment that starts out with the environment variables
+    /// of the process and uses the shared public suffix list, which is how
+    /// clients behave without an environment of their own.
+    ///
+    /// Variables can still be overridden for the client using
+    /// [`Environment::var`].
+    pub fn process() -> Self {
+        Self {
+            inherit: true,
+            vars: HashMap::new(),
+            shared_public_suffix_list: true,
+        }
+    }
+
+    /// Create an empty environment, without any environment variables, that
+    /// uses the bundled public suffix list.
+    pub fn isolated() -> Self {
+        Self {
+            inherit: false,
+            vars: HashMap::new(),
+            shared_public_suffix_list: false,
+        }
+    }
+
+    /// Set an environment variable.
+    #[must_use = "builders have no effect if unused"]
+    pub fn var(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.vars.insert(name.into(), value.into());
+        self
+    }
+
+    /// Use the public suffix list shared by the whole process, which is
+    /// refreshed from the Internet once a day, to check cookie domains.
+    ///
+    /// If disabled, the list bundled with Isahc is used as is. This only has
+    /// an effect if the [`psl`](../index.html#psl) feature is enabled.
+    #[must_use = "builders have no effect if unused"]
+    pub fn shared_public_suffix_list(mut self, enable: bool) -> Self {
+        self.shared_public_suffix_list = enable;
+        self
+    }
+
+    /// Get the value of an environment variable, if it is set and not empty.
+    pub fn get(&self, name: &str) -> Option<String> {
+        match self.vars.get(name) {
+            Some(value) => Some(value.clone()),
+            None if self.inherit => env::var(name).ok(),
+            None => None,
+        }
+        .filter(|value| !value.is_empty())
+    }
+
+    /// Returns true if the shared public suffix list is used.
+    #[cfg_attr(not(feature = "psl"), allow(dead_code))]
+    pub(crate) fn uses_shared_public_suffix_list(&self) -> bool {
+        self.shared_public_suffix_list
+    }
+
+    /// Get the proxy to use for a request to the given URI, looked up the same
+    /// way curl does. Only the lowercase variable is used for plain HTTP,
+    /// since `HTTP_PROXY` may be set by web servers from request headers.
+    pub(crate) fn proxy(&self, uri: &Uri) -> Option<String> {
+        let scheme = uri.scheme_str().unwrap_or("http").to_ascii_lowercase();
+        let name = format!("{}_proxy", scheme);
+
+        self.get(&name)
+            .or_else(|| {
+                if scheme == "http" {
+                    None
+                } else {
+                    self.get(&name.to_ascii_uppercase())
+                }
+            })
+            .or_else(|| self.get("all_proxy"))
+            .or_else(|| self.get("ALL_PROXY"))
+    }
+
+    /// Get the list of hosts not to use a proxy for.
+    pub(crate) fn no_proxy(&self) -> Option<String> {
+        self.get("no_proxy").or_else(|| self.get("NO_PROXY"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn isolated_environment_has_no_proxy() {
+        let uri = "https://example.org".parse().unwrap();
+
+        assert_eq!(Environment::isolated().proxy(&uri), None);
+        assert_eq!(Environment::isolated().no_proxy(), None);
+    }
+
+    #[test]
+    fn proxy_is_chosen_by_scheme() {
+        let environment = Environment::isolated()
+            .var("HTTP_PROXY", "http://ignored:1")
+            .var("HTTPS_PROXY", "http://secure:2")
+            .var("ALL_PROXY", "http://all:3");
+
+        assert_eq!(
+            environment.proxy(&"https://example.org".parse().unwrap()),
+            Some("http://secure:2".into())
+        );
+        assert_eq!(
+            environment.proxy(&"http://example.org".parse().unwrap()),
+            Some("http://all:3".into())
+        );
+
+        let environment = environment.var("http_proxy", "http://plain:4");
+        assert_eq!(
+            environment.proxy(&"http://example.org".parse().unwrap()),
+            Some("http://plain:4".into())
+        );
+    }
+
+    #[test]
+    fn empty_variables_are_unset() {
+        let environment = Environment::isolated()
+            .var("https_proxy", "")
+            .var("all_proxy", "http://all:3");
+
+        assert_eq!(
+            environment.proxy(&"https://example.org".parse().unwrap()),
+            Some("http://all:3".into())
+        );
+    }
+}
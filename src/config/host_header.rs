@@ -0,0 +1,144 @@
+use http::{uri::Authority, HeaderValue, Uri};
+
+/// An override of the `Host` header sent with a request, without changing
+/// which server the request is sent to.
+///
+/// The client still connects to the host and port in the request URI, but
+/// tells the server it is talking to the given host instead. This is useful
+/// for testing virtual hosts before their DNS records exist, or for sending
+/// requests to a specific server behind a CDN.
+///
+/// By default, only the `Host` header is changed, and TLS connections still
+/// use the host in the request URI for SNI and for verifying the server's
+/// certificate. Use [`HostHeader::sni`] to use the given host for those too.
+///
+/// Use with
+/// [`Configurable::host_header`](crate::config::Configurable::host_header).
+/// If the request already has a `Host` header, the header is left alone.
+/// Redirects to a different host or port are sent without the override.
+/// Sending a request with a host that is not a valid URI authority fails with
+/// an error of kind
+/// [`ErrorKind::InvalidRequest`](crate::error::ErrorKind::InvalidRequest).
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{config::HostHeader, prelude::*, Request};
+///
+/// // Ask the server at 203.0.113.7 for the staging site.
+/// let response = Request::get("https://203.0.113.7/")
+///     .host_header(HostHeader::new("staging.example.org").sni(true))
+///     .body(())?
+///     .send()?;
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HostHeader {
+    host: String,
+    sni: bool,
+}
+
+impl HostHeader {
+    /// Send the given host, optionally followed by a port, in the `Host`
+    /// header.
+    pub fn new(host: impl Into<String>) -> Self {
+        Self {
+            host: host.into(),
+            sni: false,
+        }
+    }
+
+    /// Also use the host for SNI and for verifying the server's certificate
+    /// when connecting over TLS. Disabled by default.
+    #[must_use = "builders have no effect if unused"]
+    pub fn sni(mut self, enable: bool) -> Self {
+        self.sni = enable;
+        self
+    }
+
+    /// Get the host sent in the `Host` header.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Get the parsed host, or `None` if it is not a valid authority without
+    /// user information.
+    pub(crate) fn authority(&self) -> Option<Authority> {
+        self.host
+            .parse::<Authority>()
+            .ok()
+            .filter(|authority| !authority.as_str().contains('@'))
+    }
+
+    /// Get the value of the `Host` header, or `None` if the host is invalid.
+    pub(crate) fn header_value(&self) -> Option<HeaderValue> {
+        self.authority()
+            .and_then(|authority| HeaderValue::from_str(authority.as_str()).ok())
+    }
+
+    /// Get the URI to hand to curl and the `CURLOPT_CONNECT_TO` entry that
+    /// sends it to the original server, if the host is also used for SNI.
+    pub(crate) fn sni_target(&self, uri: &Uri) -> Option<(Uri, String)> {
+        if !self.sni {
+            return None;
+        }
+
+        let host = self.authority()?.host().to_owned();
+        let original_host = uri.host()?;
+        let port = uri.port_u16().unwrap_or(match uri.scheme_str() {
+            Some("https") => 443,
+            _ => 80,
+        });
+
+        let mut parts = uri.clone().into_parts();
+        parts.authority = Some(format!("{}:{}", host, port).parse().ok()?);
+
+        Some((
+            Uri::from_parts(parts).ok()?,
+            format!("{}:{}:{}:{}", host, port, original_host, port),
+        ))
+    }
+}
+
+impl From<&str> for HostHeader {
+    fn from(host: &str) -> Self {
+        Self::new(host)
+    }
+}
+
+impl From<String> for HostHeader {
+    fn from(host: String) -> Self {
+        Self::new(host)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_value() {
+        assert_eq!(
+            HostHeader::new("other.example:8080").header_value().unwrap(),
+            "other.example:8080"
+        );
+        assert_eq!(HostHeader::new("user@other.example").header_value(), None);
+        assert_eq!(HostHeader::new("other example").header_value(), None);
+        assert_eq!(HostHeader::new("").header_value(), None);
+    }
+
+    #[test]
+    fn sni_target() {
+        let uri = "https://203.0.113.7/a?b".parse::<Uri>().unwrap();
+
+        assert_eq!(HostHeader::new("other.example").sni_target(&uri), None);
+
+        let (target, connect_to) = HostHeader::new("other.example:8443")
+            .sni(true)
+            .sni_target(&uri)
+            .unwrap();
+
+        assert_eq!(target, "https://other.example:443/a?b");
+        assert_eq!(connect_to, "other.example:443:203.0.113.7:443");
+    }
+}
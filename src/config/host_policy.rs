@@ -0,0 +1,321 @@
+//! Configuration of which hosts requests may be sent to.
+
+use std::{
+    collections::HashSet,
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    sync::Arc,
+};
+
+/// A policy restricting which hosts, addresses, and ports a client may connect
+/// to.
+///
+/// Services that fetch URLs supplied by their users can use a host policy to
+/// protect themselves against server-side request forgery (SSRF), where a
+/// malicious URL is used to make the service send requests to internal
+/// systems that are not otherwise reachable.
+///
+/// A policy is checked before each request is sent, including each request
+/// sent while following redirects. For host names, the name is resolved first
+/// and every address it resolves to is checked, and the connection is then
+/// made to exactly the address that was checked, so that the name cannot be
+/// made to resolve to a different address in between. A host name is only
+/// resolved once for each request sent by the client, and any further requests
+/// sent to the same host for it, such as while following redirects, connect to
+/// the same address.
+///
+/// By default, a new policy blocks connections to private, loopback,
+/// link-local, and other addresses that are not publicly routable, and allows
+/// any host name and port.
+///
+/// # Proxies and dialers
+///
+/// When a request is sent through an explicitly configured proxy, or using a
+/// custom [`Dialer`](crate::config::Dialer), the host name of the request is
+/// not resolved by the client, and so only the host name and port are
+/// checked.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{config::HostPolicy, prelude::*, HttpClient};
+///
+/// let client = HttpClient::builder()
+///     .host_policy(HostPolicy::new()
+///         .block_host("*.internal.example.org")
+///         .allow_ports([80, 443]))
+///     .build()?;
+///
+/// // Fails without connecting to anything.
+/// assert!(client.get("http://169.254.169.254/latest/meta-data/").is_err());
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct HostPolicy(Arc<Inner>);
+
+#[derive(Clone)]
+struct Inner {
+    allowed_hosts: Vec<HostPattern>,
+    blocked_hosts: Vec<HostPattern>,
+    allowed_ports: Option<HashSet<u16>>,
+    block_private_addresses: bool,
+}
+
+/// A host name, or a wildcard matching all subdomains of a domain.
+#[derive(Clone, Debug)]
+enum HostPattern {
+    Exact(String),
+    Subdomains(String),
+}
+
+impl Default for HostPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HostPolicy {
+    /// Create a new host policy that blocks connections to addresses that are
+    /// not publicly routable.
+    pub fn new() -> Self {
+        Self(Arc::new(Inner {
+            allowed_hosts: Vec::new(),
+            blocked_hosts: Vec::new(),
+            allowed_ports: None,
+            block_private_addresses: true,
+        }))
+    }
+
+    /// Only allow requests to hosts matching the given pattern, or any other
+    /// pattern given to this method.
+    ///
+    /// A pattern is either a host name or IP address, which only matches
+    /// exactly that host, or a host name prefixed with `*.`, which matches all
+    /// of its subdomains but not the host itself. Host names are matched
+    /// case-insensitively.
+    ///
+    /// If this method is never called, any host that is not explicitly
+    /// blocked is allowed.
+    #[must_use = "builders have no effect if unused"]
+    pub fn allow_host(mut self, pattern: impl AsRef<str>) -> Self {
+        let pattern = HostPattern::new(pattern.as_ref());
+        Arc::make_mut(&mut self.0).allowed_hosts.push(pattern);
+        self
+    }
+
+    /// Block requests to hosts matching the given pattern. Blocked hosts take
+    /// precedence over allowed hosts.
+    ///
+    /// See [`allow_host`](HostPolicy::allow_host) for the pattern syntax.
+    #[must_use = "builders have no effect if unused"]
+    pub fn block_host(mut self, pattern: impl AsRef<str>) -> Self {
+        let pattern = HostPattern::new(pattern.as_ref());
+        Arc::make_mut(&mut self.0).blocked_hosts.push(pattern);
+        self
+    }
+
+    /// Only allow requests to the given ports.
+    ///
+    /// Ports implied by the scheme of a URI, such as port 443 for `https`
+    /// URIs, are checked too. By default, any port is allowed.
+    #[must_use = "builders have no effect if unused"]
+    pub fn allow_ports(mut self, ports: impl IntoIterator<Item = u16>) -> Self {
+        Arc::make_mut(&mut self.0)
+            .allowed_ports
+            .get_or_insert_with(HashSet::new)
+            .extend(ports);
+        self
+    }
+
+    /// Set whether connections to addresses that are not publicly routable
+    /// are blocked.
+    ///
+    /// This includes loopback, private, link-local, carrier-grade NAT,
+    /// multicast, and unspecified addresses, as well as IPv6 addresses that
+    /// embed such an IPv4 address. Enabled by default.
+    #[must_use = "builders have no effect if unused"]
+    pub fn block_private_addresses(mut self, block: bool) -> Self {
+        Arc::make_mut(&mut self.0).block_private_addresses = block;
+        self
+    }
+
+    /// Check whether a host name or IP address is allowed by this policy.
+    pub(crate) fn is_host_allowed(&self, host: &str) -> bool {
+        let host = normalize(host);
+
+        if self.0.blocked_hosts.iter().any(|p| p.matches(&host)) {
+            return false;
+        }
+
+        self.0.allowed_hosts.is_empty() || self.0.allowed_hosts.iter().any(|p| p.matches(&host))
+    }
+
+    pub(crate) fn is_port_allowed(&self, port: u16) -> bool {
+        match self.0.allowed_ports.as_ref() {
+            Some(ports) => ports.contains(&port),
+            None => true,
+        }
+    }
+
+    pub(crate) fn is_address_allowed(&self, addr: IpAddr) -> bool {
+        !self.0.block_private_addresses || is_public(addr)
+    }
+}
+
+impl fmt::Debug for HostPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HostPolicy")
+            .field("allowed_hosts", &self.0.allowed_hosts)
+            .field("blocked_hosts", &self.0.blocked_hosts)
+            .field("allowed_ports", &self.0.allowed_ports)
+            .field("block_private_addresses", &self.0.block_private_addresses)
+            .finish()
+    }
+}
+
+impl HostPattern {
+    fn new(pattern: &str) -> Self {
+        let pattern = normalize(pattern);
+
+        match pattern.strip_prefix("*.") {
+            Some(domain) => Self::Subdomains(domain.to_owned()),
+            None => Self::Exact(pattern),
+        }
+    }
+
+    fn matches(&self, host: &str) -> bool {
+        match self {
+            Self::Exact(name) => host == name,
+            Self::Subdomains(domain) => matches!(
+                host.strip_suffix(domain.as_str()),
+                Some(prefix) if prefix.len() > 1 && prefix.ends_with('.')
+            ),
+        }
+    }
+}
+
+/// Normalize a host name or bracketed IPv6 address for comparison.
+fn normalize(host: &str) -> String {
+    host.trim_start_matches('[')
+        .trim_end_matches(']')
+        .trim_end_matches('.')
+        .to_ascii_lowercase()
+}
+
+/// Returns true if the given address is publicly routable.
+fn is_public(addr: IpAddr) -> bool {
+    match addr {
+        IpAddr::V4(addr) => is_public_v4(addr),
+        IpAddr::V6(addr) => is_public_v6(addr),
+    }
+}
+
+fn is_public_v4(addr: Ipv4Addr) -> bool {
+    let [a, b, c, _] = addr.octets();
+
+    !(a == 0
+        || a == 10
+        || a == 127
+        || (a == 100 && (64..128).contains(&b))
+        || (a == 169 && b == 254)
+        || (a == 172 && (16..32).contains(&b))
+        || (a == 192 && b == 0 && c == 0)
+        || (a == 192 && b == 168)
+        || (a == 198 && (18..20).contains(&b))
+        || a >= 224)
+}
+
+fn is_public_v6(addr: Ipv6Addr) -> bool {
+    let segments = addr.segments();
+
+    // IPv4-mapped (::ffff:0:0/96), IPv4-compatible (::/96), and NAT64
+    // (64:ff9b::/96) addresses reach the embedded IPv4 address.
+    let embedded = match segments {
+        [0, 0, 0, 0, 0, 0xffff, ..] | [0, 0, 0, 0, 0, 0, ..] | [0x64, 0xff9b, 0, 0, 0, 0, ..] => {
+            let [.., a, b, c, d] = addr.octets();
+            Some(Ipv4Addr::new(a, b, c, d))
+        }
+        _ => None,
+    };
+
+    if let Some(addr) = embedded {
+        return is_public_v4(addr);
+    }
+
+    !((segments[0] & 0xfe00) == 0xfc00 // Unique local
+        || (segments[0] & 0xffc0) == 0xfe80 // Link-local
+        || (segments[0] & 0xffc0) == 0xfec0 // Site-local
+        || (segments[0] & 0xff00) == 0xff00) // Multicast
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn private_addresses_are_blocked_by_default() {
+        let policy = HostPolicy::new();
+
+        for addr in &[
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "224.0.0.1",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "64:ff9b::a00:1",
+        ] {
+            assert!(!policy.is_address_allowed(ip(addr)), "{}", addr);
+        }
+
+        for addr in &["93.184.216.34", "172.32.0.1", "2606:2800:220:1::1"] {
+            assert!(policy.is_address_allowed(ip(addr)), "{}", addr);
+        }
+
+        assert!(
+            HostPolicy::new()
+                .block_private_addresses(false)
+                .is_address_allowed(ip("127.0.0.1"))
+        );
+    }
+
+    #[test]
+    fn host_patterns() {
+        let policy = HostPolicy::new()
+            .allow_host("example.org")
+            .allow_host("*.Example.com")
+            .block_host("admin.example.com");
+
+        assert!(policy.is_host_allowed("EXAMPLE.org."));
+        assert!(!policy.is_host_allowed("www.example.org"));
+        assert!(policy.is_host_allowed("www.example.com"));
+        assert!(!policy.is_host_allowed("example.com"));
+        assert!(!policy.is_host_allowed("badexample.com"));
+        assert!(!policy.is_host_allowed("admin.example.com"));
+
+        let policy = HostPolicy::new().block_host("[::1]");
+        assert!(!policy.is_host_allowed("[::1]"));
+        assert!(policy.is_host_allowed("anything.example"));
+    }
+
+    #[test]
+    fn ports() {
+        assert!(HostPolicy::new().is_port_allowed(22));
+
+        let policy = HostPolicy::new().allow_ports([443]).allow_ports([80]);
+        assert!(policy.is_port_allowed(80));
+        assert!(policy.is_port_allowed(443));
+        assert!(!policy.is_port_allowed(22));
+    }
+}
@@ -0,0 +1,288 @@
+use crate::error::{Error, ErrorKind};
+use std::{error::Error as StdError, fmt};
+
+/// Controls how internationalized domain names in request URIs are handled.
+///
+/// Host names in URIs sent over the network must be ASCII, so names containing
+/// other characters are converted to their punycode form, such as
+/// `xn--bcher-kva.example` for `bücher.example`. This is always done for the
+/// targets of redirects, and [`IdnHost::parse`] can be used to convert a host
+/// name before building a request URI with it.
+///
+/// By default, any valid internationalized domain name is allowed. In strict
+/// mode, requests to host names that are commonly used for phishing are
+/// rejected with an error of kind
+/// [`ErrorKind::InvalidRequest`](crate::error::ErrorKind::InvalidRequest)
+/// before they are sent. This includes host names mixing characters from
+/// different scripts in one label, such as Latin and Cyrillic, and labels
+/// written entirely in Cyrillic or Greek letters that look like Latin ones,
+/// such as `аpple` spelled with a Cyrillic `а`.
+///
+/// The policy is checked for every request sent while following redirects
+/// too.
+///
+/// # Examples
+///
+/// ```
+/// use isahc::{config::IdnaPolicy, HttpClient};
+///
+/// let client = HttpClient::builder()
+///     .idna_policy(IdnaPolicy::new().strict(true))
+///     .build()?;
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct IdnaPolicy {
+    strict: bool,
+}
+
+impl IdnaPolicy {
+    /// Create a new policy that allows any valid internationalized domain
+    /// name.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable or disable strict mode, which rejects mixed-script and
+    /// confusable host names.
+    #[must_use = "builders have no effect if unused"]
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Check the given host against this policy.
+    pub(crate) fn check(&self, host: &IdnHost) -> Result<(), Error> {
+        if !self.strict {
+            return Ok(());
+        }
+
+        for label in host.unicode.split('.') {
+            if let Some(reason) = check_label(label) {
+                return Err(Error::new(
+                    ErrorKind::InvalidRequest,
+                    Rejected(format!("host `{}` {}", host.unicode, reason)),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A domain name in both its ASCII form, as sent over the network, and its
+/// Unicode form, as displayed to users.
+///
+/// The host of the final request sent for a response is available using
+/// [`ResponseExt::idn_host`](crate::ResponseExt::idn_host).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IdnHost {
+    ascii: String,
+    unicode: String,
+}
+
+impl IdnHost {
+    /// Parse a domain name given in either its Unicode or punycode form.
+    ///
+    /// Returns an error of kind
+    /// [`ErrorKind::InvalidRequest`](crate::error::ErrorKind::InvalidRequest)
+    /// if the name is not a valid internationalized domain name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::config::IdnHost;
+    ///
+    /// let host = IdnHost::parse("bücher.example")?;
+    ///
+    /// assert_eq!(host.ascii(), "xn--bcher-kva.example");
+    /// assert_eq!(host.unicode(), "bücher.example");
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn parse(host: &str) -> Result<Self, Error> {
+        let ascii = idna::domain_to_ascii(host)
+            .map_err(|e| Error::new(ErrorKind::InvalidRequest, Rejected(e.to_string())))?;
+
+        let (unicode, result) = idna::domain_to_unicode(&ascii);
+
+        result.map_err(|e| Error::new(ErrorKind::InvalidRequest, Rejected(e.to_string())))?;
+
+        Ok(Self { ascii, unicode })
+    }
+
+    /// Get the ASCII form of the name, with any non-ASCII labels converted to
+    /// punycode.
+    pub fn ascii(&self) -> &str {
+        &self.ascii
+    }
+
+    /// Get the Unicode form of the name.
+    pub fn unicode(&self) -> &str {
+        &self.unicode
+    }
+
+    /// Returns true if the name contains any non-ASCII characters in its
+    /// Unicode form.
+    pub fn is_internationalized(&self) -> bool {
+        self.ascii != self.unicode
+    }
+}
+
+impl fmt::Display for IdnHost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.unicode)
+    }
+}
+
+/// Source of errors for host names that are not valid or not allowed.
+#[derive(Debug)]
+struct Rejected(String);
+
+impl fmt::Display for Rejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl StdError for Rejected {}
+
+/// Scripts distinguished when checking labels. This is a coarse approximation
+/// of the Unicode script property covering the scripts most often involved in
+/// spoofing.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Armenian,
+    Hebrew,
+    Arabic,
+    Han,
+    Hiragana,
+    Katakana,
+    Hangul,
+    Bopomofo,
+    Other,
+}
+
+impl Script {
+    /// Get the script of a character, or `None` for characters such as digits
+    /// and hyphens that are shared by all scripts.
+    fn of(c: char) -> Option<Self> {
+        Some(match c {
+            '0'..='9' | '-' | '_' | '\u{30FC}' => return None,
+            'a'..='z' | 'A'..='Z' | '\u{00C0}'..='\u{024F}' | '\u{1E00}'..='\u{1EFF}' => {
+                Self::Latin
+            }
+            '\u{0370}'..='\u{03FF}' | '\u{1F00}'..='\u{1FFF}' => Self::Greek,
+            '\u{0400}'..='\u{052F}' => Self::Cyrillic,
+            '\u{0530}'..='\u{058F}' => Self::Armenian,
+            '\u{0590}'..='\u{05FF}' => Self::Hebrew,
+            '\u{0600}'..='\u{06FF}' | '\u{0750}'..='\u{077F}' => Self::Arabic,
+            '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' => Self::Han,
+            '\u{3040}'..='\u{309F}' => Self::Hiragana,
+            '\u{30A0}'..='\u{30FF}' => Self::Katakana,
+            '\u{1100}'..='\u{11FF}' | '\u{AC00}'..='\u{D7AF}' => Self::Hangul,
+            '\u{3100}'..='\u{312F}' => Self::Bopomofo,
+            _ => Self::Other,
+        })
+    }
+}
+
+/// Combinations of scripts that are commonly written together and so are
+/// allowed in one label, following the "highly restrictive" profile of
+/// [UTS #39](https://www.unicode.org/reports/tr39/#Restriction_Level_Detection).
+const ALLOWED_SCRIPT_SETS: &[&[Script]] = &[
+    &[
+        Script::Latin,
+        Script::Han,
+        Script::Hiragana,
+        Script::Katakana,
+    ],
+    &[Script::Latin, Script::Han, Script::Bopomofo],
+    &[Script::Latin, Script::Han, Script::Hangul],
+];
+
+/// Lowercase Cyrillic and Greek letters that look like Latin letters. Labels
+/// are always lowercase after conversion.
+const LATIN_LOOKALIKES: &str = "аеорсухіјѕԁԛԝһӏѵανικορυ";
+
+/// Check a single label in Unicode form, returning why it is not allowed, if
+/// it isn't.
+fn check_label(label: &str) -> Option<&'static str> {
+    let mut scripts = Vec::new();
+
+    for script in label.chars().filter_map(Script::of) {
+        if !scripts.contains(&script) {
+            scripts.push(script);
+        }
+    }
+
+    if scripts.len() > 1
+        && !ALLOWED_SCRIPT_SETS
+            .iter()
+            .any(|set| scripts.iter().all(|script| set.contains(script)))
+    {
+        return Some("mixes characters from different scripts");
+    }
+
+    if matches!(scripts.as_slice(), [Script::Cyrillic] | [Script::Greek])
+        && label
+            .chars()
+            .filter(|c| Script::of(*c).is_some())
+            .all(|c| LATIN_LOOKALIKES.contains(c))
+    {
+        return Some("could be confused with a Latin host name");
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unicode_and_punycode_forms_are_equivalent() {
+        let a = IdnHost::parse("bücher.example").unwrap();
+        let b = IdnHost::parse("xn--bcher-kva.example").unwrap();
+
+        assert_eq!(a, b);
+        assert!(a.is_internationalized());
+        assert!(
+            !IdnHost::parse("example.org")
+                .unwrap()
+                .is_internationalized()
+        );
+    }
+
+    #[test]
+    fn mixed_scripts_are_rejected_in_strict_mode() {
+        // Latin "pple" with a Cyrillic "а".
+        let host = IdnHost::parse("\u{0430}pple.com").unwrap();
+
+        assert!(IdnaPolicy::new().check(&host).is_ok());
+        assert!(IdnaPolicy::new().strict(true).check(&host).is_err());
+    }
+
+    #[test]
+    fn whole_script_confusables_are_rejected_in_strict_mode() {
+        // "ерос" entirely in Cyrillic.
+        let host = IdnHost::parse("\u{0435}\u{0440}\u{043e}\u{0441}.com").unwrap();
+
+        assert!(IdnaPolicy::new().strict(true).check(&host).is_err());
+    }
+
+    #[test]
+    fn single_script_names_are_allowed_in_strict_mode() {
+        let policy = IdnaPolicy::new().strict(true);
+
+        for host in &["bücher.example", "пример.рф", "例え.jp", "日本語abc.jp"] {
+            assert!(
+                policy.check(&IdnHost::parse(host).unwrap()).is_ok(),
+                "{}",
+                host
+            );
+        }
+    }
+}
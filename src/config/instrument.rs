@@ -0,0 +1,318 @@
+use crate::{error::ErrorKind, CancelReason};
+use std::{
+    fmt,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// Source of unique IDs for requests sent by instrumented clients.
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A receiver of events about the requests executed by a client and the
+/// agent threads executing them.
+///
+/// Events are delivered synchronously from the thread where they happen,
+/// which for everything but [`AgentEventKind::RequestEnqueued`] is an agent
+/// thread. Implementations should therefore return quickly, for example by
+/// recording the event into a histogram or handing it off to a channel.
+///
+/// Any function taking an [`AgentEvent`] reference can be used as
+/// instrumentation.
+///
+/// # Examples
+///
+/// ```
+/// use isahc::{config::{AgentEvent, AgentEventKind}, HttpClient};
+///
+/// let client = HttpClient::builder()
+///     .instrumentation(|event: &AgentEvent| {
+///         if let AgentEventKind::HeadersReceived { status } = event.kind() {
+///             println!(
+///                 "request {:?} got status {} after {:?}",
+///                 event.request_id(),
+///                 status,
+///                 event.elapsed(),
+///             );
+///         }
+///     })
+///     .build()?;
+/// # Ok::<(), isahc::Error>(())
+/// ```
+pub trait Instrumentation: Send + Sync + 'static {
+    /// Handle an event.
+    fn on_event(&self, event: &AgentEvent);
+
+    /// Called once when the client is shut down using
+    /// [`HttpClient::shutdown`](crate::HttpClient::shutdown), after the last
+    /// event about its requests has been delivered.
+    ///
+    /// Implementations that buffer events can flush them here. The default
+    /// implementation does nothing.
+    fn on_shutdown(&self) {}
+}
+
+impl<F> Instrumentation for F
+where
+    F: Fn(&AgentEvent) + Send + Sync + 'static,
+{
+    fn on_event(&self, event: &AgentEvent) {
+        self(event)
+    }
+}
+
+/// An event delivered to the [`Instrumentation`] of a client.
+#[derive(Clone, Debug)]
+pub struct AgentEvent {
+    kind: AgentEventKind,
+    request_id: Option<u64>,
+    timestamp: Instant,
+    elapsed: Option<Duration>,
+}
+
+impl AgentEvent {
+    /// Get what happened.
+    pub fn kind(&self) -> &AgentEventKind {
+        &self.kind
+    }
+
+    /// Get the ID of the request this event is about, if any.
+    ///
+    /// Each request is assigned a unique ID when it is enqueued. Every event
+    /// about the same request carries the same ID, which can be used to
+    /// correlate them.
+    pub fn request_id(&self) -> Option<u64> {
+        self.request_id
+    }
+
+    /// Get when the event happened.
+    pub fn timestamp(&self) -> Instant {
+        self.timestamp
+    }
+
+    /// Get how long after the request was enqueued the event happened, if it
+    /// is about a request.
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.elapsed
+    }
+}
+
+/// The kind of an [`AgentEvent`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum AgentEventKind {
+    /// The request was handed to an agent thread to execute.
+    RequestEnqueued,
+
+    /// An attempt to open a connection to one of the addresses of the server
+    /// finished.
+    ///
+    /// When the host name of the server resolves to both IPv6 and IPv4
+    /// addresses, attempts to connect to each family are made in parallel
+    /// ("happy eyeballs"), and attempts that are still in progress once one
+    /// of them succeeds are abandoned. Reporting every attempt shows why
+    /// connecting is slow on networks where one of the families is broken.
+    /// Requests that reuse a connection make no attempts.
+    ConnectAttempt {
+        /// The address the attempt connected to. Whether it is an IPv4 or
+        /// IPv6 address tells the address family of the attempt.
+        addr: SocketAddr,
+
+        /// How the attempt ended.
+        result: ConnectAttemptResult,
+
+        /// How long the attempt took until it ended.
+        duration: Duration,
+    },
+
+    /// A connection to the server is ready and the request is about to be
+    /// sent over it.
+    ConnectionEstablished {
+        /// Whether an existing connection from the connection cache was
+        /// reused, rather than a new connection opened.
+        reused: bool,
+    },
+
+    /// A response header was received. This may happen more than once per
+    /// request, such as for informational responses or redirects.
+    HeadersReceived {
+        /// The status code of the response.
+        status: u16,
+    },
+
+    /// A chunk of the request body was read to be sent to the server.
+    RequestBodyRead {
+        /// The length of the chunk in bytes.
+        len: usize,
+    },
+
+    /// A chunk of the response body was received and written to the response
+    /// buffer.
+    ResponseBodyWritten {
+        /// The length of the chunk in bytes.
+        len: usize,
+    },
+
+    /// The request completed successfully.
+    RequestCompleted,
+
+    /// The request was canceled, or aborted by a shutdown of the client.
+    /// Followed by [`AgentEventKind::RequestFailed`].
+    RequestCanceled {
+        /// Why the request was canceled.
+        reason: CancelReason,
+    },
+
+    /// The request failed.
+    RequestFailed {
+        /// The kind of error the request failed with.
+        error: ErrorKind,
+    },
+
+    /// An agent thread woke up to do work.
+    AgentWakeup {
+        /// What woke the agent up.
+        cause: WakeupCause,
+    },
+
+    /// Connecting to the server failed because a resource limit of the
+    /// process or operating system was hit. Only reported when
+    /// [retrying](crate::HttpClientBuilder::retry_on_resource_exhaustion)
+    /// such failures is enabled.
+    ResourceExhausted {
+        /// The resource that ran out.
+        limit: ResourceLimit,
+
+        /// How long until the request is retried, or `None` if the request
+        /// has waited too long already and fails instead.
+        retry_in: Option<Duration>,
+    },
+}
+
+/// How a connection attempt reported by [`AgentEventKind::ConnectAttempt`]
+/// ended.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum ConnectAttemptResult {
+    /// The attempt connected, and its connection is used for the request.
+    Connected,
+
+    /// The attempt failed, such as by being refused or timing out.
+    Failed,
+
+    /// The attempt was given up on, because an attempt made in parallel
+    /// connected first.
+    Abandoned,
+}
+
+/// The reason an agent thread woke up.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum WakeupCause {
+    /// Activity was detected on a socket.
+    Socket,
+
+    /// The agent was notified of a new request or message.
+    Notify,
+
+    /// A timeout requested by curl or the agent expired.
+    Timeout,
+}
+
+/// A resource limit that can cause connecting to a server to fail.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum ResourceLimit {
+    /// The process or the system has too many open files, such as when the
+    /// `RLIMIT_NOFILE` limit is reached (`EMFILE` or `ENFILE`).
+    FileDescriptors,
+
+    /// No local port is available to connect from, usually because too many
+    /// recently closed connections are still in the `TIME_WAIT` state
+    /// (`EADDRNOTAVAIL`).
+    EphemeralPorts,
+
+    /// The system ran out of memory for socket buffers (`ENOBUFS`).
+    SocketBuffers,
+}
+
+impl ResourceLimit {
+    /// Get the resource limit an OS error code from a failed connection
+    /// attempt indicates, if any.
+    pub(crate) fn from_os_error(code: i32) -> Option<Self> {
+        #[cfg(unix)]
+        match code {
+            libc::EMFILE | libc::ENFILE => Some(Self::FileDescriptors),
+            libc::EADDRNOTAVAIL => Some(Self::EphemeralPorts),
+            libc::ENOBUFS => Some(Self::SocketBuffers),
+            _ => None,
+        }
+
+        // Winsock error codes, which are not exposed by the standard library.
+        #[cfg(windows)]
+        match code {
+            10024 => Some(Self::FileDescriptors),
+            10049 => Some(Self::EphemeralPorts),
+            10055 => Some(Self::SocketBuffers),
+            _ => None,
+        }
+
+        #[cfg(not(any(unix, windows)))]
+        {
+            let _ = code;
+            None
+        }
+    }
+}
+
+impl fmt::Display for ResourceLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::FileDescriptors => "too many open files",
+            Self::EphemeralPorts => "no local ports available",
+            Self::SocketBuffers => "out of socket buffer space",
+        })
+    }
+}
+
+/// Shared handle to the instrumentation of a client.
+#[derive(Clone)]
+pub(crate) struct SharedInstrumentation(Arc<dyn Instrumentation>);
+
+impl SharedInstrumentation {
+    pub(crate) fn new(instrumentation: impl Instrumentation) -> Self {
+        Self(Arc::new(instrumentation))
+    }
+
+    /// Assign a new unique request ID.
+    pub(crate) fn next_request_id() -> u64 {
+        NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Deliver an event, optionally about the request with the given ID which
+    /// was enqueued at the given time.
+    pub(crate) fn emit(&self, kind: AgentEventKind, request: Option<(u64, Instant)>) {
+        let timestamp = Instant::now();
+
+        self.0.on_event(&AgentEvent {
+            kind,
+            request_id: request.map(|(id, _)| id),
+            timestamp,
+            elapsed: request.map(|(_, enqueued)| timestamp.saturating_duration_since(enqueued)),
+        });
+    }
+
+    /// Notify the instrumentation that the client has shut down.
+    pub(crate) fn shutdown(&self) {
+        self.0.on_shutdown();
+    }
+}
+
+impl fmt::Debug for SharedInstrumentation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Instrumentation")
+    }
+}
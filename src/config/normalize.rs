@@ -0,0 +1,183 @@
+/// Controls how request URLs are normalized before they are sent.
+///
+/// Some servers are sensitive to the exact form of a URL, such as whether a
+/// character in the path is percent-encoded, so it can be necessary to control
+/// precisely what is sent. When a normalization policy is configured, the
+/// client applies it to every request URL, including the targets of
+/// redirects, and sends the result exactly as computed without curl
+/// normalizing it any further. The URL that was actually sent is available
+/// using [`ResponseExt::effective_uri`](crate::ResponseExt::effective_uri).
+///
+/// A new policy only removes dot segments like `/./` and `/../` from paths,
+/// which matches what happens when no policy is configured. The other
+/// transforms are opt-in, and some of them, like merging duplicate slashes,
+/// can change which resource a URL refers to on some servers. They are mostly
+/// useful for making URLs consistent, for example to use them as cache keys
+/// when crawling.
+///
+/// # Examples
+///
+/// ```
+/// use isahc::{config::UrlNormalization, prelude::*, HttpClient};
+///
+/// let client = HttpClient::builder()
+///     .url_normalization(
+///         UrlNormalization::new()
+///             .lowercase(true)
+///             .remove_default_port(true)
+///             .percent_encode_path("!'()*"),
+///     )
+///     .build()?;
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct UrlNormalization {
+    pub(crate) lowercase: bool,
+    pub(crate) remove_default_port: bool,
+    pub(crate) remove_dot_segments: bool,
+    pub(crate) decode_unreserved: bool,
+    pub(crate) path_chars: String,
+    pub(crate) query_chars: String,
+    pub(crate) merge_slashes: bool,
+    pub(crate) trailing_slash: TrailingSlash,
+    pub(crate) sort_query: bool,
+}
+
+/// What to do with a slash at the end of the path of URLs, as configured with
+/// [`UrlNormalization::trailing_slash`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TrailingSlash {
+    /// Leave the path as given.
+    Keep,
+
+    /// Append a slash to paths that do not end with one.
+    Add,
+
+    /// Remove the slash from the end of paths, unless the path is just `/`.
+    Remove,
+}
+
+// Can't derive this on our MSRV.
+#[allow(clippy::derivable_impls)]
+impl Default for TrailingSlash {
+    fn default() -> Self {
+        TrailingSlash::Keep
+    }
+}
+
+impl Default for UrlNormalization {
+    fn default() -> Self {
+        Self {
+            lowercase: false,
+            remove_default_port: false,
+            remove_dot_segments: true,
+            decode_unreserved: false,
+            path_chars: String::new(),
+            query_chars: String::new(),
+            merge_slashes: false,
+            trailing_slash: TrailingSlash::Keep,
+            sort_query: false,
+        }
+    }
+}
+
+impl UrlNormalization {
+    /// Create a new policy that only removes dot segments from paths.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Convert the scheme and host of URLs to lowercase.
+    ///
+    /// Disabled by default.
+    #[must_use = "builders have no effect if unused"]
+    pub fn lowercase(mut self, enable: bool) -> Self {
+        self.lowercase = enable;
+        self
+    }
+
+    /// Remove the port from URLs if it is the default port for the scheme,
+    /// such as port 443 for `https`.
+    ///
+    /// Disabled by default.
+    #[must_use = "builders have no effect if unused"]
+    pub fn remove_default_port(mut self, enable: bool) -> Self {
+        self.remove_default_port = enable;
+        self
+    }
+
+    /// Resolve dot segments like `/./` and `/../` in paths.
+    ///
+    /// Enabled by default. When disabled, paths are sent with any dot segments
+    /// as given.
+    #[must_use = "builders have no effect if unused"]
+    pub fn remove_dot_segments(mut self, enable: bool) -> Self {
+        self.remove_dot_segments = enable;
+        self
+    }
+
+    /// Collapse runs of consecutive slashes in paths into a single slash, so
+    /// that for example `/a//b` becomes `/a/b`.
+    ///
+    /// Disabled by default.
+    #[must_use = "builders have no effect if unused"]
+    pub fn merge_slashes(mut self, enable: bool) -> Self {
+        self.merge_slashes = enable;
+        self
+    }
+
+    /// Set whether to add or remove a slash at the end of paths.
+    ///
+    /// The default is [`TrailingSlash::Keep`].
+    #[must_use = "builders have no effect if unused"]
+    pub fn trailing_slash(mut self, trailing_slash: TrailingSlash) -> Self {
+        self.trailing_slash = trailing_slash;
+        self
+    }
+
+    /// Sort the parameters of query strings by name, so that URLs that only
+    /// differ in the order of their parameters become identical.
+    ///
+    /// Parameters with the same name keep their relative order, since some
+    /// servers treat repeated parameters as an ordered list.
+    ///
+    /// Disabled by default.
+    #[must_use = "builders have no effect if unused"]
+    pub fn sort_query(mut self, enable: bool) -> Self {
+        self.sort_query = enable;
+        self
+    }
+
+    /// Decode percent-encoded characters that never need to be encoded, such
+    /// as `%41` for `A`, and convert the hexadecimal digits of any other
+    /// percent-encoded characters to uppercase.
+    ///
+    /// Disabled by default.
+    #[must_use = "builders have no effect if unused"]
+    pub fn decode_unreserved(mut self, enable: bool) -> Self {
+        self.decode_unreserved = enable;
+        self
+    }
+
+    /// Set ASCII characters to percent-encode wherever they appear in the
+    /// path of URLs, in addition to the characters that URLs cannot contain
+    /// unencoded.
+    ///
+    /// No additional characters are encoded by default.
+    #[must_use = "builders have no effect if unused"]
+    pub fn percent_encode_path(mut self, chars: impl Into<String>) -> Self {
+        self.path_chars = chars.into();
+        self
+    }
+
+    /// Set ASCII characters to percent-encode wherever they appear in the
+    /// query string of URLs, in addition to the characters that URLs cannot
+    /// contain unencoded.
+    ///
+    /// No additional characters are encoded by default.
+    #[must_use = "builders have no effect if unused"]
+    pub fn percent_encode_query(mut self, chars: impl Into<String>) -> Self {
+        self.query_chars = chars.into();
+        self
+    }
+}
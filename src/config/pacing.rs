@@ -0,0 +1,136 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// Spacing enforced between the starts of requests sent by a client to the
+/// same host.
+///
+/// Some servers, and the web application firewalls in front of them, reject
+/// bursts of requests arriving at once even when the average rate is well
+/// within their limits. Pacing spreads such bursts out over time by starting
+/// at most one new request per host every interval, independently of any
+/// [quotas](crate::config::Quota) or limit on the
+/// [number of requests in progress](crate::HttpClientBuilder::max_in_flight).
+///
+/// Hosts are told apart by name and port, the same way curl does when
+/// limiting [connections per
+/// host](crate::HttpClientBuilder::max_connections_per_host). Requests waiting
+/// for their host to allow them to start do not hold up requests to other
+/// hosts, and time spent waiting does not count towards the request's
+/// [timeout](crate::config::Configurable::timeout).
+///
+/// Use with [`HttpClientBuilder::pacing`](crate::HttpClientBuilder::pacing).
+///
+/// # Examples
+///
+/// ```
+/// use isahc::{config::Pacing, HttpClient};
+/// use std::time::Duration;
+///
+/// // Start at most one request to each host every 50 milliseconds.
+/// let client = HttpClient::builder()
+///     .pacing(Pacing::per_host(Duration::from_millis(50)))
+///     .build()?;
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Pacing {
+    interval: Duration,
+}
+
+impl Pacing {
+    /// Start at most one new request to each host every `interval`.
+    pub fn per_host(interval: Duration) -> Self {
+        Self { interval }
+    }
+
+    /// Get the minimum amount of time between the starts of requests to the
+    /// same host.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+}
+
+/// When requests were last started to each host, tracked by an agent.
+#[derive(Debug)]
+pub(crate) struct PacingState {
+    interval: Duration,
+    started_at: HashMap<String, Instant>,
+}
+
+impl PacingState {
+    pub(crate) fn new(pacing: Pacing) -> Self {
+        Self {
+            interval: pacing.interval,
+            started_at: HashMap::new(),
+        }
+    }
+
+    /// Get how long until a request to the given host may be started, which is
+    /// zero if it may be started now. Requests without a host are not paced.
+    pub(crate) fn time_until_ready(&self, host: Option<&str>, now: Instant) -> Duration {
+        host.and_then(|host| self.started_at.get(host))
+            .map(|&started_at| (started_at + self.interval).saturating_duration_since(now))
+            .unwrap_or_default()
+    }
+
+    /// Record a request to the given host as started.
+    pub(crate) fn record(&mut self, host: Option<&str>, now: Instant) {
+        let interval = self.interval;
+
+        // Forget hosts that no longer hold anything up.
+        self.started_at
+            .retain(|_, started_at| now.saturating_duration_since(*started_at) < interval);
+
+        if let Some(host) = host {
+            self.started_at.insert(host.to_owned(), now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_to_same_host_are_spaced_out() {
+        let now = Instant::now();
+        let mut state = PacingState::new(Pacing::per_host(Duration::from_millis(100)));
+
+        assert_eq!(state.time_until_ready(Some("a:80"), now), Duration::ZERO);
+        state.record(Some("a:80"), now);
+
+        assert_eq!(
+            state.time_until_ready(Some("a:80"), now + Duration::from_millis(40)),
+            Duration::from_millis(60)
+        );
+        assert_eq!(
+            state.time_until_ready(Some("a:80"), now + Duration::from_millis(100)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn hosts_are_paced_separately() {
+        let now = Instant::now();
+        let mut state = PacingState::new(Pacing::per_host(Duration::from_millis(100)));
+
+        state.record(Some("a:80"), now);
+
+        assert_eq!(state.time_until_ready(Some("a:443"), now), Duration::ZERO);
+        assert_eq!(state.time_until_ready(Some("b:80"), now), Duration::ZERO);
+        assert_eq!(state.time_until_ready(None, now), Duration::ZERO);
+    }
+
+    #[test]
+    fn idle_hosts_are_forgotten() {
+        let now = Instant::now();
+        let mut state = PacingState::new(Pacing::per_host(Duration::from_millis(100)));
+
+        state.record(Some("a:80"), now);
+        state.record(Some("b:80"), now + Duration::from_millis(150));
+
+        assert_eq!(state.started_at.len(), 1);
+    }
+}
@@ -0,0 +1,80 @@
+use super::SetOpt;
+use curl::easy::Easy2;
+use std::{ffi::CString, net::IpAddr, os::raw::c_long};
+
+/// Option for `CURLOPT_HAPROXYPROTOCOL`, which is not exposed by curl-sys.
+const CURLOPT_HAPROXYPROTOCOL: curl_sys::CURLoption = curl_sys::CURLOPTTYPE_LONG + 274;
+
+/// Option for `CURLOPT_HAPROXY_CLIENT_IP`, which is not exposed by curl-sys.
+const CURLOPT_HAPROXY_CLIENT_IP: curl_sys::CURLoption = curl_sys::CURLOPTTYPE_OBJECTPOINT + 323;
+
+/// Configuration for sending a [PROXY protocol] header at the start of each
+/// new connection.
+///
+/// Servers that sit behind a load balancer such as HAProxy often expect every
+/// connection to begin with a PROXY protocol header, which tells them the
+/// address of the client that originally connected to the load balancer. When
+/// talking to such a server directly, for example in health checks or tests,
+/// the header has to be sent by the client instead.
+///
+/// Only version 1 of the protocol, the human-readable text format, is
+/// supported, since that is the version curl implements. The header is sent
+/// once per connection, so requests that reuse a connection do not send it
+/// again.
+///
+/// Use with
+/// [`Configurable::proxy_protocol`](crate::config::Configurable::proxy_protocol).
+///
+/// [PROXY protocol]: https://www.haproxy.org/download/1.8/doc/proxy-protocol.txt
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ProxyProtocol {
+    client_ip: Option<IpAddr>,
+}
+
+impl ProxyProtocol {
+    /// Send a PROXY protocol header containing the actual addresses of the
+    /// connection.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Report the given address as the source address of the connection,
+    /// instead of the actual local address.
+    ///
+    /// This is useful for testing how a server handles connections from
+    /// different clients. Setting the source address requires curl 8.2.0 or
+    /// newer; with older versions, sending a request fails.
+    #[must_use = "builders have no effect if unused"]
+    pub fn client_ip(mut self, ip: impl Into<IpAddr>) -> Self {
+        self.client_ip = Some(ip.into());
+        self
+    }
+}
+
+impl SetOpt for ProxyProtocol {
+    #[allow(unsafe_code)]
+    fn set_opt<H>(&self, easy: &mut Easy2<H>) -> Result<(), curl::Error> {
+        unsafe {
+            match curl_sys::curl_easy_setopt(easy.raw(), CURLOPT_HAPROXYPROTOCOL, 1 as c_long) {
+                curl_sys::CURLE_OK => {}
+                code => return Err(curl::Error::new(code)),
+            }
+        }
+
+        if let Some(ip) = self.client_ip {
+            // An address never contains a NUL byte.
+            let ip = CString::new(ip.to_string()).unwrap();
+
+            // Curl makes its own copy of the string.
+            let code = unsafe {
+                curl_sys::curl_easy_setopt(easy.raw(), CURLOPT_HAPROXY_CLIENT_IP, ip.as_ptr())
+            };
+
+            if code != curl_sys::CURLE_OK {
+                return Err(curl::Error::new(code));
+            }
+        }
+
+        Ok(())
+    }
+}
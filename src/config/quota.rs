@@ -0,0 +1,202 @@
+use std::{
+    borrow::Cow,
+    time::{Duration, Instant},
+};
+
+/// Limits on the requests sent by a client that are assigned to a named quota.
+///
+/// Quotas let parts of an application that share a client, such as different
+/// features or API keys, be limited separately, so that one of them sending a
+/// lot of requests cannot starve the others. Quotas are registered on a client
+/// using [`HttpClientBuilder::quota`](crate::HttpClientBuilder::quota), and
+/// requests are assigned to one by adding a [`QuotaTag`] to their extensions.
+///
+/// Requests that would exceed a quota wait in a queue until they can be
+/// started, in the order they were sent. Limits are enforced when a request is
+/// started, so time spent waiting for a quota does not count towards the
+/// request's [timeout](crate::config::Configurable::timeout).
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{config::{Quota, QuotaTag}, prelude::*, HttpClient, Request};
+///
+/// let client = HttpClient::builder()
+///     .quota("thumbnails", Quota::new().max_concurrent(4).max_per_second(10))
+///     .build()?;
+///
+/// let request = Request::get("https://example.org/thumbnail.png")
+///     .extension(QuotaTag::new("thumbnails"))
+///     .body(())?;
+///
+/// let response = client.send(request)?;
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Quota {
+    max_concurrent: usize,
+    max_per_second: u32,
+}
+
+impl Quota {
+    /// Create a new quota with no limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum number of requests assigned to this quota that may be
+    /// in progress at once.
+    ///
+    /// A request stays in progress until its response has been fully
+    /// received. Setting this value to `0` disables the limit, which is the
+    /// default.
+    #[must_use = "builders have no effect if unused"]
+    pub fn max_concurrent(mut self, max: usize) -> Self {
+        self.max_concurrent = max;
+        self
+    }
+
+    /// Set the maximum number of requests assigned to this quota that may be
+    /// started per second.
+    ///
+    /// Up to one second's worth of requests may be started at once after a
+    /// quiet period. Setting this value to `0` disables the limit, which is the
+    /// default.
+    #[must_use = "builders have no effect if unused"]
+    pub fn max_per_second(mut self, max: u32) -> Self {
+        self.max_per_second = max;
+        self
+    }
+}
+
+/// A request extension that assigns a request to a named [`Quota`].
+///
+/// Requests with a tag naming a quota that was not registered with the client
+/// are not limited.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct QuotaTag(Cow<'static, str>);
+
+impl QuotaTag {
+    /// Create a tag assigning requests to the quota with the given name.
+    pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
+        Self(name.into())
+    }
+
+    /// Get the name of the quota.
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Usage of a quota tracked by an agent.
+#[derive(Debug)]
+pub(crate) struct QuotaState {
+    quota: Quota,
+
+    /// Number of requests assigned to the quota that are in progress.
+    active: usize,
+
+    /// Number of requests that may be started before the rate limit is
+    /// reached.
+    tokens: f64,
+
+    updated_at: Instant,
+}
+
+impl QuotaState {
+    pub(crate) fn new(quota: Quota, now: Instant) -> Self {
+        Self {
+            tokens: quota.max_per_second.into(),
+            quota,
+            active: 0,
+            updated_at: now,
+        }
+    }
+
+    /// Try to start a request, returning true if the quota allows it.
+    pub(crate) fn try_acquire(&mut self, now: Instant) -> bool {
+        if self.quota.max_concurrent > 0 && self.active >= self.quota.max_concurrent {
+            return false;
+        }
+
+        if self.quota.max_per_second > 0 {
+            self.refill(now);
+
+            if self.tokens < 1.0 {
+                return false;
+            }
+
+            self.tokens -= 1.0;
+        }
+
+        self.active += 1;
+
+        true
+    }
+
+    /// Record a request that was started by `try_acquire` as completed.
+    pub(crate) fn release(&mut self) {
+        self.active = self.active.saturating_sub(1);
+    }
+
+    /// Get how long until the rate limit allows another request to be
+    /// started, or `None` if it is not the rate limit preventing one.
+    pub(crate) fn time_until_available(&mut self, now: Instant) -> Option<Duration> {
+        if self.quota.max_per_second == 0
+            || (self.quota.max_concurrent > 0 && self.active >= self.quota.max_concurrent)
+        {
+            return None;
+        }
+
+        self.refill(now);
+
+        let missing = (1.0 - self.tokens).max(0.0);
+
+        Some(Duration::from_secs_f64(
+            missing / f64::from(self.quota.max_per_second),
+        ))
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let rate = f64::from(self.quota.max_per_second);
+        let elapsed = now.saturating_duration_since(self.updated_at);
+
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * rate).min(rate);
+        self.updated_at = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrency_is_limited() {
+        let now = Instant::now();
+        let mut state = QuotaState::new(Quota::new().max_concurrent(2), now);
+
+        assert!(state.try_acquire(now));
+        assert!(state.try_acquire(now));
+        assert!(!state.try_acquire(now));
+        assert_eq!(state.time_until_available(now), None);
+
+        state.release();
+        assert!(state.try_acquire(now));
+    }
+
+    #[test]
+    fn rate_is_limited() {
+        let now = Instant::now();
+        let mut state = QuotaState::new(Quota::new().max_per_second(2), now);
+
+        assert!(state.try_acquire(now));
+        assert!(state.try_acquire(now));
+        assert!(!state.try_acquire(now));
+
+        let wait = state.time_until_available(now).unwrap();
+        assert!(wait > Duration::from_millis(400) && wait <= Duration::from_millis(500));
+
+        assert!(state.try_acquire(now + Duration::from_millis(500)));
+        assert!(!state.try_acquire(now + Duration::from_millis(500)));
+    }
+}
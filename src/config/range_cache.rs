@@ -0,0 +1,542 @@
+use crate::range_cache::ByteRange;
+use http::{
+    header::{self, HeaderName, HeaderValue},
+    HeaderMap,
+    Uri,
+};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt,
+    ops::RangeInclusive,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// The default maximum number of bytes held by a [`RangeCache`].
+const DEFAULT_CAPACITY: u64 = 64 * 1024 * 1024;
+
+/// An in-memory cache of byte ranges of responses.
+///
+/// When a `GET` request with a `Range` header receives a `206 Partial
+/// Content` response, the bytes of the response are remembered once its body
+/// has been read to the end. Future requests for ranges of the same URI are
+/// then answered from the cache as far as possible:
+///
+/// - If the whole range is cached, the response is made up from the cache
+///   without asking the server.
+/// - If the start or the end of the range is cached, only the bytes in
+///   between are requested from the server, with an `If-Range` header so that
+///   the server sends the whole resource instead if it has changed. The cached
+///   and received bytes are then stitched together into a single `206 Partial
+///   Content` response for the range that was asked for.
+///
+/// Ranges received by separate requests are joined together when they overlap
+/// or touch, so a resource downloaded in pieces, such as by a media player
+/// seeking back and forth or by a downloader resuming after an interruption,
+/// gradually fills up the cache.
+///
+/// Only responses with a strong `ETag` or a `Last-Modified` header are cached,
+/// since without them there is no way to tell whether ranges received at
+/// different times belong to the same version of the resource. Responses with
+/// a `Content-Encoding` or with `Cache-Control: no-store` are not cached
+/// either. Requests for several ranges at once, and requests with an
+/// `If-Range` header of their own, are passed through to the server.
+///
+/// The cached ranges of a URI are forgotten when a response shows that the
+/// resource has changed, or when a request with an unsafe method such as `PUT`
+/// or `DELETE` to it succeeds. The [`CacheControl`](super::CacheControl)
+/// directives of a request are obeyed: with `no_cache` cached ranges are not
+/// used, with `no_store` nothing is cached, and with `max_age` ranges of a
+/// resource first cached longer ago are not used.
+///
+/// The cache holds a bounded number of bytes, evicting the ranges of the least
+/// recently used URI when full. Caches are cheap to clone, and clones share
+/// the same entries.
+///
+/// Clients do not have a range cache by default. One can be set using
+/// [`HttpClientBuilder::range_cache`](crate::HttpClientBuilder::range_cache).
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{config::RangeCache, prelude::*, HttpClient, Request};
+///
+/// let cache = RangeCache::new();
+/// let client = HttpClient::builder().range_cache(cache.clone()).build()?;
+///
+/// // Fetch the first kilobyte of a video.
+/// client.send(Request::get("https://example.org/video.mp4")
+///     .header("range", "bytes=0-1023")
+///     .body(())?)?
+///     .bytes()?;
+///
+/// // Only the second kilobyte is requested from the server.
+/// client.send(Request::get("https://example.org/video.mp4")
+///     .header("range", "bytes=0-2047")
+///     .body(())?)?
+///     .bytes()?;
+///
+/// assert_eq!(cache.ranges(&"https://example.org/video.mp4".parse()?), [0..=2047]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone)]
+pub struct RangeCache {
+    inner: Arc<Mutex<RangeCacheInner>>,
+}
+
+struct RangeCacheInner {
+    capacity: u64,
+    /// Number of bytes cached across all entries.
+    size: u64,
+    /// Cached ranges by URI.
+    entries: HashMap<String, Entry>,
+    /// Cached URIs from least to most recently used.
+    order: VecDeque<String>,
+}
+
+struct Entry {
+    validator: Validator,
+    total_len: Option<u64>,
+    content_type: Option<HeaderValue>,
+    /// Cached bytes by their offset in the resource. Segments never overlap or
+    /// touch each other.
+    segments: BTreeMap<u64, Vec<u8>>,
+    cached_at: Instant,
+}
+
+impl Entry {
+    fn size(&self) -> u64 {
+        self.segments.values().map(|data| data.len() as u64).sum()
+    }
+
+    /// Get the cached bytes from the given offset up to at most the given
+    /// exclusive end offset.
+    fn bytes_from(&self, start: u64, end: u64) -> &[u8] {
+        match self.segments.range(..=start).next_back() {
+            Some((&offset, data)) if offset + data.len() as u64 > start => {
+                let end = end.min(offset + data.len() as u64);
+                &data[(start - offset) as usize..(end - offset) as usize]
+            }
+            _ => &[],
+        }
+    }
+
+    /// Add the given bytes at the given offset, joining them with any
+    /// segments they overlap or touch.
+    fn insert(&mut self, start: u64, data: Vec<u8>) {
+        let end = start + data.len() as u64;
+        let touching = self
+            .segments
+            .range(..=end)
+            .filter(|(&offset, data)| offset + data.len() as u64 >= start)
+            .map(|(&offset, _)| offset)
+            .collect::<Vec<_>>();
+
+        if touching.is_empty() {
+            self.segments.insert(start, data);
+            return;
+        }
+
+        let merged_start = touching[0].min(start);
+        let merged_end = touching
+            .iter()
+            .map(|offset| offset + self.segments[offset].len() as u64)
+            .max()
+            .unwrap_or(end)
+            .max(end);
+        let mut merged = vec![0; (merged_end - merged_start) as usize];
+
+        for offset in touching {
+            let existing = self.segments.remove(&offset).unwrap();
+            let at = (offset - merged_start) as usize;
+            merged[at..at + existing.len()].copy_from_slice(&existing);
+        }
+
+        let at = (start - merged_start) as usize;
+        merged[at..at + data.len()].copy_from_slice(&data);
+        self.segments.insert(merged_start, merged);
+    }
+}
+
+impl Default for RangeCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RangeCache {
+    /// Create a new empty range cache with the default capacity of 64 MiB.
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    /// Create a new empty range cache that holds at most the given number of
+    /// bytes.
+    pub fn with_capacity(capacity: u64) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(RangeCacheInner {
+                capacity,
+                size: 0,
+                entries: HashMap::new(),
+                order: VecDeque::new(),
+            })),
+        }
+    }
+
+    /// Get the ranges of a URI that are cached, in order of their offset.
+    pub fn ranges(&self, uri: &Uri) -> Vec<RangeInclusive<u64>> {
+        let inner = self.inner.lock().unwrap();
+
+        inner
+            .entries
+            .get(&uri.to_string())
+            .map(|entry| {
+                entry
+                    .segments
+                    .iter()
+                    .map(|(&offset, data)| offset..=offset + data.len() as u64 - 1)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Get the number of bytes cached across all URIs.
+    pub fn size(&self) -> u64 {
+        self.inner.lock().unwrap().size
+    }
+
+    /// Get the number of URIs with cached ranges.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    /// Returns true if no ranges are cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget the cached ranges of a URI. Returns true if any were cached.
+    pub fn remove(&self, uri: &Uri) -> bool {
+        self.inner.lock().unwrap().remove(&uri.to_string())
+    }
+
+    /// Forget all cached ranges.
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+
+        inner.entries.clear();
+        inner.order.clear();
+        inner.size = 0;
+    }
+
+    /// Get the cached bytes at the start and the end of a range of a URI,
+    /// optionally only if the URI was first cached at most the given time
+    /// ago. Returns `None` if none of the range is cached.
+    pub(crate) fn lookup(
+        &self,
+        uri: &Uri,
+        range: ByteRange,
+        max_age: Option<Duration>,
+    ) -> Option<Lookup> {
+        let key = uri.to_string();
+        let mut inner = self.inner.lock().unwrap();
+        let entry = inner
+            .entries
+            .get(&key)
+            .filter(|entry| max_age.map_or(true, |age| entry.cached_at.elapsed() <= age))?;
+        let (start, end) = range.resolve(entry.total_len)?;
+        let prefix = entry.bytes_from(start, end + 1).to_vec();
+        let prefix_end = start + prefix.len() as u64;
+        let mut suffix = Vec::new();
+
+        if prefix_end <= end {
+            if let Some((&offset, data)) = entry.segments.range(prefix_end..=end).next_back() {
+                if offset + data.len() as u64 > end {
+                    suffix = data[..(end + 1 - offset) as usize].to_vec();
+                }
+            }
+        }
+
+        if prefix.is_empty() && suffix.is_empty() {
+            return None;
+        }
+
+        let lookup = Lookup {
+            start,
+            end,
+            validator: entry.validator.clone(),
+            total_len: entry.total_len,
+            content_type: entry.content_type.clone(),
+            prefix,
+            suffix,
+        };
+
+        inner.touch(&key);
+
+        Some(lookup)
+    }
+
+    /// Cache bytes of a URI received at the given offset.
+    pub(crate) fn insert(&self, uri: &Uri, part: Part, start: u64, data: Vec<u8>) {
+        let key = uri.to_string();
+        let mut inner = self.inner.lock().unwrap();
+
+        if data.is_empty() || data.len() as u64 > inner.capacity {
+            return;
+        }
+
+        if inner
+            .entries
+            .get(&key)
+            .map_or(false, |entry| entry.validator != part.validator)
+        {
+            inner.remove(&key);
+        }
+
+        if !inner.entries.contains_key(&key) {
+            inner.entries.insert(
+                key.clone(),
+                Entry {
+                    validator: part.validator,
+                    total_len: None,
+                    content_type: None,
+                    segments: BTreeMap::new(),
+                    cached_at: Instant::now(),
+                },
+            );
+            inner.order.push_back(key.clone());
+        }
+
+        let entry = inner.entries.get_mut(&key).unwrap();
+        let old_size = entry.size();
+
+        entry.total_len = part.total_len.or(entry.total_len);
+        entry.content_type = part.content_type.or(entry.content_type.take());
+        entry.insert(start, data);
+
+        let new_size = entry.size();
+        inner.size = inner.size - old_size + new_size;
+        inner.touch(&key);
+
+        while inner.size > inner.capacity {
+            match inner.order.front().cloned() {
+                Some(evicted) => {
+                    inner.remove(&evicted);
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Forget the cached ranges of a URI if a response with the given headers
+    /// shows that the resource has changed since.
+    pub(crate) fn invalidate_changed(&self, uri: &Uri, headers: &HeaderMap) {
+        let key = uri.to_string();
+        let mut inner = self.inner.lock().unwrap();
+
+        let validator = Validator::from_headers(headers);
+
+        if inner
+            .entries
+            .get(&key)
+            .map_or(false, |entry| Some(&entry.validator) != validator.as_ref())
+        {
+            inner.remove(&key);
+        }
+    }
+
+    /// Get the number of bytes that may be cached at most.
+    pub(crate) fn capacity(&self) -> u64 {
+        self.inner.lock().unwrap().capacity
+    }
+}
+
+impl RangeCacheInner {
+    /// Mark the given key as most recently used.
+    fn touch(&mut self, key: &str) {
+        if let Some(index) = self.order.iter().position(|k| k == key) {
+            if let Some(key) = self.order.remove(index) {
+                self.order.push_back(key);
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &str) -> bool {
+        match self.entries.remove(key) {
+            Some(entry) => {
+                self.size -= entry.size();
+                self.order.retain(|k| k != key);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl fmt::Debug for RangeCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RangeCache")
+            .field("len", &self.len())
+            .field("size", &self.size())
+            .finish()
+    }
+}
+
+/// A value identifying a version of a resource, used to tell whether ranges
+/// received at different times can be stitched together.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Validator {
+    ETag(HeaderValue),
+    LastModified(HeaderValue),
+}
+
+impl Validator {
+    /// Get the validator of a response with the given headers, preferring a
+    /// strong entity tag. Weak entity tags cannot be used for ranges.
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        match headers.get(header::ETAG) {
+            Some(etag) if !etag.as_bytes().starts_with(b"W/") => Some(Self::ETag(etag.clone())),
+            _ => headers
+                .get(header::LAST_MODIFIED)
+                .cloned()
+                .map(Self::LastModified),
+        }
+    }
+
+    /// Get the name of the response header the validator came from.
+    pub(crate) fn header_name(&self) -> HeaderName {
+        match self {
+            Self::ETag(_) => header::ETAG,
+            Self::LastModified(_) => header::LAST_MODIFIED,
+        }
+    }
+
+    /// Get the value to send in an `If-Range` header.
+    pub(crate) fn value(&self) -> &HeaderValue {
+        match self {
+            Self::ETag(value) | Self::LastModified(value) => value,
+        }
+    }
+}
+
+/// Details of a response whose bytes are cached.
+pub(crate) struct Part {
+    pub(crate) validator: Validator,
+    pub(crate) total_len: Option<u64>,
+    pub(crate) content_type: Option<HeaderValue>,
+}
+
+/// The cached bytes of a requested range.
+pub(crate) struct Lookup {
+    /// Offset of the first byte of the range.
+    pub(crate) start: u64,
+    /// Offset of the last byte of the range.
+    pub(crate) end: u64,
+    pub(crate) validator: Validator,
+    pub(crate) total_len: Option<u64>,
+    pub(crate) content_type: Option<HeaderValue>,
+    /// Cached bytes at the start of the range.
+    pub(crate) prefix: Vec<u8>,
+    /// Cached bytes at the end of the range, not overlapping the prefix.
+    pub(crate) suffix: Vec<u8>,
+}
+
+impl Lookup {
+    /// Get the range of bytes that are not cached, if any. Cached bytes in
+    /// the middle of the range are not used.
+    pub(crate) fn missing(&self) -> Option<(u64, u64)> {
+        let start = self.start + self.prefix.len() as u64;
+        let end = self.end - self.suffix.len() as u64;
+
+        if start > end {
+            None
+        } else {
+            Some((start, end))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn part(etag: &'static str, total_len: u64) -> Part {
+        Part {
+            validator: Validator::ETag(HeaderValue::from_static(etag)),
+            total_len: Some(total_len),
+            content_type: None,
+        }
+    }
+
+    fn uri() -> Uri {
+        "http://example.org/file".parse().unwrap()
+    }
+
+    #[test]
+    fn segments_are_joined() {
+        let cache = RangeCache::new();
+
+        cache.insert(&uri(), part("\"a\"", 100), 10, vec![1; 10]);
+        cache.insert(&uri(), part("\"a\"", 100), 30, vec![3; 10]);
+        assert_eq!(cache.ranges(&uri()), [10..=19, 30..=39]);
+
+        cache.insert(&uri(), part("\"a\"", 100), 20, vec![2; 10]);
+        assert_eq!(cache.ranges(&uri()), [10..=39]);
+        assert_eq!(cache.size(), 30);
+
+        cache.insert(&uri(), part("\"a\"", 100), 5, vec![0; 40]);
+        assert_eq!(cache.ranges(&uri()), [5..=44]);
+        assert_eq!(cache.size(), 40);
+    }
+
+    #[test]
+    fn lookup_returns_prefix_and_suffix() {
+        let cache = RangeCache::new();
+
+        cache.insert(&uri(), part("\"a\"", 100), 0, (0..10).collect());
+        cache.insert(&uri(), part("\"a\"", 100), 20, (20..30).collect());
+
+        let lookup = cache.lookup(&uri(), ByteRange::Bounded(5, 24), None).unwrap();
+        assert_eq!(lookup.prefix, [5, 6, 7, 8, 9]);
+        assert_eq!(lookup.suffix, [20, 21, 22, 23, 24]);
+        assert_eq!(lookup.missing(), Some((10, 19)));
+
+        let lookup = cache.lookup(&uri(), ByteRange::Bounded(2, 8), None).unwrap();
+        assert_eq!(lookup.prefix, [2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(lookup.missing(), None);
+
+        assert!(cache.lookup(&uri(), ByteRange::Bounded(12, 18), None).is_none());
+        assert!(cache.lookup(&uri(), ByteRange::Last(5), None).is_none());
+    }
+
+    #[test]
+    fn changed_resource_replaces_ranges() {
+        let cache = RangeCache::new();
+
+        cache.insert(&uri(), part("\"a\"", 100), 0, vec![0; 10]);
+        cache.insert(&uri(), part("\"b\"", 100), 50, vec![0; 10]);
+        assert_eq!(cache.ranges(&uri()), [50..=59]);
+        assert_eq!(cache.size(), 10);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ETAG, HeaderValue::from_static("\"c\""));
+        cache.invalidate_changed(&uri(), &headers);
+        assert!(cache.is_empty());
+        assert_eq!(cache.size(), 0);
+    }
+
+    #[test]
+    fn least_recently_used_is_evicted() {
+        let cache = RangeCache::with_capacity(20);
+        let other = "http://example.org/other".parse().unwrap();
+
+        cache.insert(&uri(), part("\"a\"", 100), 0, vec![0; 10]);
+        cache.insert(&other, part("\"a\"", 100), 0, vec![0; 10]);
+        cache.lookup(&uri(), ByteRange::Bounded(0, 1), None);
+        cache.insert(&other, part("\"a\"", 100), 10, vec![0; 5]);
+
+        assert_eq!(cache.ranges(&uri()), []);
+        assert_eq!(cache.ranges(&other), [0..=14]);
+        assert_eq!(cache.size(), 15);
+    }
+}
@@ -1,3 +1,15 @@
+use http::{
+    header::{HeaderName, HeaderValue},
+    HeaderMap,
+    Uri,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
 /// Describes a policy for handling server redirects.
 ///
 /// The default is to not follow redirects.
@@ -16,8 +28,644 @@ pub enum RedirectPolicy {
     Limit(u32),
 }
 
+// Can't derive this on our MSRV.
+#[allow(clippy::derivable_impls)]
 impl Default for RedirectPolicy {
     fn default() -> Self {
         RedirectPolicy::None
     }
 }
+
+/// Function computing the key a redirect is cached under.
+type KeyFn = Arc<dyn Fn(&Uri) -> String + Send + Sync>;
+
+/// The maximum number of variants remembered for a single redirect.
+const MAX_VARIANTS: usize = 16;
+
+/// The default maximum number of redirects remembered by a [`RedirectCache`].
+const DEFAULT_CAPACITY: usize = 256;
+
+/// An in-memory cache of permanent redirects.
+///
+/// When a `GET` or `HEAD` request is redirected with the status `301 Moved
+/// Permanently` or `308 Permanent Redirect` while following redirects, the
+/// target of the redirect is remembered. Future `GET` and `HEAD` requests to
+/// the same URI that follow redirects are then sent directly to the target,
+/// skipping a round trip. Redirects sent with `Cache-Control: no-store` or
+/// `Vary: *` are not remembered.
+///
+/// If a redirect response has a `Vary` header, the redirect is only followed
+/// by future requests with the same values of the request headers it names,
+/// such as `Accept-Language`. A redirect is cached separately for each
+/// combination of values, and the cached variants can be inspected using
+/// [`RedirectCache::variants`].
+///
+/// Cached redirects of a URI are forgotten automatically when a request with
+/// an unsafe method such as `POST` or `DELETE` to it succeeds, as are those of
+/// the `Location` and `Content-Location` of the response if they are on the
+/// same host. They can also be forgotten explicitly using
+/// [`RedirectCache::remove`] and [`RedirectCache::invalidate_prefix`].
+///
+/// The cache holds a bounded number of redirects, evicting the least recently
+/// used when full. Caches are cheap to clone, and clones share the same
+/// entries.
+///
+/// Redirects are cached by their full source URI by default. A custom key
+/// function can be set using [`RedirectCache::cache_key`] to share cached
+/// redirects between URIs that should be treated as the same.
+///
+/// Every [`HttpClient`](crate::HttpClient) has its own redirect cache by
+/// default, which can be replaced or disabled using
+/// [`HttpClientBuilder::redirect_cache`](crate::HttpClientBuilder::redirect_cache)
+/// and inspected using
+/// [`HttpClient::redirect_cache`](crate::HttpClient::redirect_cache).
+#[derive(Clone)]
+pub struct RedirectCache {
+    inner: Arc<Mutex<RedirectCacheInner>>,
+    key: Option<KeyFn>,
+}
+
+struct RedirectCacheInner {
+    capacity: usize,
+    /// Cached redirects by key.
+    entries: HashMap<String, Entry>,
+    /// Cached keys from least to most recently used.
+    order: VecDeque<String>,
+    /// Number of responses with each cache status.
+    counts: [u64; 5],
+}
+
+struct Entry {
+    /// The source URI of the redirect that was cached last.
+    source: Uri,
+    /// Variants of the redirect from least to most recently cached.
+    variants: Vec<RedirectVariant>,
+}
+
+impl Default for RedirectCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RedirectCache {
+    /// Create a new empty redirect cache with the default capacity.
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    /// Create a new empty redirect cache that remembers at most the given
+    /// number of redirects.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(RedirectCacheInner {
+                capacity,
+                entries: HashMap::new(),
+                order: VecDeque::new(),
+                counts: [0; 5],
+            })),
+            key: None,
+        }
+    }
+
+    /// Set a function that computes the key a redirect is cached under from
+    /// its source URI, instead of using the full URI.
+    ///
+    /// URIs with the same key share a cached redirect. This can be used for
+    /// example to ignore volatile tracking parameters in query strings, to
+    /// compare host names case-insensitively, or to keep the redirects of
+    /// different tenants apart in a shared cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::{
+    ///     config::{RedirectCache, RedirectPolicy},
+    ///     prelude::*,
+    ///     HttpClient,
+    /// };
+    ///
+    /// // Ignore query strings entirely.
+    /// let cache = RedirectCache::new().cache_key(|uri| {
+    ///     let mut key = uri.clone().into_parts();
+    ///     key.path_and_query = Some(uri.path().parse().unwrap());
+    ///     http::Uri::from_parts(key).unwrap().to_string()
+    /// });
+    ///
+    /// let client = HttpClient::builder()
+    ///     .redirect_policy(RedirectPolicy::Follow)
+    ///     .redirect_cache(Some(cache))
+    ///     .build()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    #[must_use = "builders have no effect if unused"]
+    pub fn cache_key<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Uri) -> String + Send + Sync + 'static,
+    {
+        self.key = Some(Arc::new(f));
+        self
+    }
+
+    fn key(&self, uri: &Uri) -> String {
+        match self.key.as_ref() {
+            Some(f) => f(uri),
+            None => uri.to_string(),
+        }
+    }
+
+    /// Get the cached redirect target of a URI, if any.
+    ///
+    /// Only a single redirect is looked up; the target may itself have a
+    /// cached redirect. If the redirect varies by request headers, the variant
+    /// for a request without any of those headers is returned.
+    pub fn get(&self, uri: &Uri) -> Option<Uri> {
+        self.lookup(uri, &HeaderMap::new(), None)
+    }
+
+    /// Get the variants of the cached redirect of a URI, from least to most
+    /// recently cached.
+    ///
+    /// A redirect whose response had a `Vary` header is cached separately for
+    /// each combination of values of the request headers it names, and each
+    /// of those is a variant. A redirect without a `Vary` header has a single
+    /// variant. This is mostly useful for debugging.
+    pub fn variants(&self, uri: &Uri) -> Vec<RedirectVariant> {
+        let key = self.key(uri);
+        let inner = self.inner.lock().unwrap();
+
+        inner
+            .entries
+            .get(&key)
+            .map(|entry| entry.variants.clone())
+            .unwrap_or_default()
+    }
+
+    /// Get all cached redirects as pairs of source and target URIs, from
+    /// least to most recently used. Each variant of a redirect is included
+    /// as a separate pair.
+    ///
+    /// If several source URIs share a key, the source of the redirect that was
+    /// cached last is returned.
+    pub fn entries(&self) -> Vec<(Uri, Uri)> {
+        self.variant_entries()
+            .into_iter()
+            .map(|(source, variant)| (source, variant.target))
+            .collect()
+    }
+
+    /// Get all cached variants of redirects along with their source URIs,
+    /// from least to most recently used.
+    pub(crate) fn variant_entries(&self) -> Vec<(Uri, RedirectVariant)> {
+        let inner = self.inner.lock().unwrap();
+
+        inner
+            .order
+            .iter()
+            .filter_map(|key| inner.entries.get(key))
+            .flat_map(|entry| {
+                entry
+                    .variants
+                    .iter()
+                    .map(move |variant| (entry.source.clone(), variant.clone()))
+            })
+            .collect()
+    }
+
+    /// Get the number of URIs with cached redirects.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    /// Returns true if no redirects are cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget the cached redirect of a URI, including all of its variants.
+    /// Returns the target of the most recently cached variant if present.
+    pub fn remove(&self, uri: &Uri) -> Option<Uri> {
+        let key = self.key(uri);
+        let mut inner = self.inner.lock().unwrap();
+        let mut entry = inner.entries.remove(&key)?;
+
+        inner.order.retain(|k| k != &key);
+
+        entry.variants.pop().map(|variant| variant.target)
+    }
+
+    /// Forget the cached redirects of all URIs starting with the given
+    /// prefix, such as `https://example.org/api/`. Returns the number of URIs
+    /// whose redirects were forgotten.
+    ///
+    /// The prefix is compared with the source URI of each cached redirect as
+    /// a string, not with its key.
+    pub fn invalidate_prefix(&self, prefix: &str) -> usize {
+        let mut inner = self.inner.lock().unwrap();
+        let len = inner.entries.len();
+
+        inner
+            .entries
+            .retain(|_, entry| !entry.source.to_string().starts_with(prefix));
+
+        let RedirectCacheInner {
+            entries,
+            order,
+            ..
+        } = &mut *inner;
+        order.retain(|key| entries.contains_key(key));
+
+        len - entries.len()
+    }
+
+    /// Forget all cached redirects.
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+
+        inner.entries.clear();
+        inner.order.clear();
+    }
+
+    /// Get how many requests using this cache had the given status.
+    ///
+    /// Comparing the number of hits to the number of misses shows how
+    /// effective the cache is. Counts are kept for the lifetime of the cache
+    /// and are not reset by [`RedirectCache::clear`].
+    pub fn count(&self, status: CacheStatus) -> u64 {
+        self.inner.lock().unwrap().counts[status.index()]
+    }
+
+    /// Count a request that used this cache.
+    pub(crate) fn record(&self, status: CacheStatus) {
+        self.inner.lock().unwrap().counts[status.index()] += 1;
+    }
+
+    /// Get the cached redirect target of a URI for a request with the given
+    /// headers, optionally only if it was cached at most the given time ago.
+    pub(crate) fn lookup(
+        &self,
+        uri: &Uri,
+        headers: &HeaderMap,
+        max_age: Option<Duration>,
+    ) -> Option<Uri> {
+        let key = self.key(uri);
+        let mut inner = self.inner.lock().unwrap();
+        let target = inner
+            .entries
+            .get(&key)?
+            .variants
+            .iter()
+            .rev()
+            .find(|variant| variant.matches(headers))
+            .filter(|variant| match max_age {
+                Some(age) => variant.cached_at.elapsed() <= age,
+                None => true,
+            })?
+            .target
+            .clone();
+
+        inner.touch(&key);
+
+        Some(target)
+    }
+
+    /// Cache a redirect of a request with the given headers, which varies by
+    /// the values of the request headers with the given names.
+    pub(crate) fn insert(
+        &self,
+        source: &Uri,
+        headers: &HeaderMap,
+        vary: &[HeaderName],
+        target: Uri,
+    ) {
+        let key = self.key(source);
+        let mut inner = self.inner.lock().unwrap();
+
+        if inner.capacity == 0 {
+            return;
+        }
+
+        let variant = RedirectVariant {
+            values: vary.iter().map(|name| select(headers, name)).collect(),
+            vary: vary.to_vec(),
+            target,
+            cached_at: Instant::now(),
+        };
+
+        if let Some(entry) = inner.entries.get_mut(&key) {
+            entry.source = source.clone();
+            entry
+                .variants
+                .retain(|v| v.vary != variant.vary || v.values != variant.values);
+            entry.variants.push(variant);
+
+            if entry.variants.len() > MAX_VARIANTS {
+                entry.variants.remove(0);
+            }
+
+            inner.touch(&key);
+            return;
+        }
+
+        inner.entries.insert(
+            key.clone(),
+            Entry {
+                source: source.clone(),
+                variants: vec![variant],
+            },
+        );
+        inner.order.push_back(key);
+
+        while inner.order.len() > inner.capacity {
+            if let Some(evicted) = inner.order.pop_front() {
+                inner.entries.remove(&evicted);
+            }
+        }
+    }
+}
+
+/// How a [`RedirectCache`] was used for a request.
+///
+/// The status of a response can be retrieved with
+/// [`ResponseExt::cache_status`](crate::ResponseExt::cache_status), and the
+/// number of responses with each status with [`RedirectCache::count`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum CacheStatus {
+    /// At least one cached redirect was followed without asking the server.
+    Hit,
+
+    /// No cached redirect was found, so the server was asked.
+    Miss,
+
+    /// A cached redirect was confirmed by the server to still be valid.
+    ///
+    /// Cached redirects are permanent, so this status is not currently
+    /// produced.
+    Revalidated,
+
+    /// A cached redirect was followed even though it might be out of date.
+    ///
+    /// Cached redirects are permanent, so this status is not currently
+    /// produced.
+    Stale,
+
+    /// The cache was not consulted for the request, because its method is not
+    /// cacheable, redirects were not followed, or the request had a
+    /// [`no_cache`](super::CacheControl::no_cache) directive.
+    Bypass,
+}
+
+impl CacheStatus {
+    fn index(self) -> usize {
+        match self {
+            Self::Hit => 0,
+            Self::Miss => 1,
+            Self::Revalidated => 2,
+            Self::Stale => 3,
+            Self::Bypass => 4,
+        }
+    }
+}
+
+/// A variant of a cached redirect, which is followed only by requests with
+/// certain values of the request headers named in the `Vary` header of the
+/// redirect response.
+///
+/// Variants are returned by [`RedirectCache::variants`].
+#[derive(Clone, Debug)]
+pub struct RedirectVariant {
+    vary: Vec<HeaderName>,
+    values: Vec<Option<HeaderValue>>,
+    target: Uri,
+    cached_at: Instant,
+}
+
+impl RedirectVariant {
+    /// Get the names of the request headers this variant is selected by.
+    pub fn vary(&self) -> &[HeaderName] {
+        &self.vary
+    }
+
+    /// Get the value of a request header this variant is selected by, or
+    /// `None` if it is selected by the header being absent or it does not
+    /// vary by the header at all.
+    ///
+    /// Multiple values of the same header are combined into one value
+    /// separated by commas.
+    pub fn request_header(&self, name: &HeaderName) -> Option<&HeaderValue> {
+        self.vary
+            .iter()
+            .position(|n| n == name)
+            .and_then(|i| self.values[i].as_ref())
+    }
+
+    /// Get the target of the redirect.
+    pub fn target(&self) -> &Uri {
+        &self.target
+    }
+
+    fn matches(&self, headers: &HeaderMap) -> bool {
+        self.vary
+            .iter()
+            .zip(&self.values)
+            .all(|(name, value)| select(headers, name).as_ref() == value.as_ref())
+    }
+}
+
+/// Get the value of a request header a variant is selected by, combining
+/// multiple values into one.
+fn select(headers: &HeaderMap, name: &HeaderName) -> Option<HeaderValue> {
+    let mut values = headers.get_all(name).iter();
+    let first = values.next()?;
+    let mut combined = first.as_bytes().to_vec();
+
+    for value in values {
+        combined.extend_from_slice(b", ");
+        combined.extend_from_slice(value.as_bytes());
+    }
+
+    HeaderValue::from_bytes(&combined).ok()
+}
+
+impl RedirectCacheInner {
+    /// Mark a cached URI as most recently used.
+    fn touch(&mut self, key: &str) {
+        if let Some(index) = self.order.iter().position(|k| k == key) {
+            if let Some(key) = self.order.remove(index) {
+                self.order.push_back(key);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for RedirectCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedirectCache")
+            .field("len", &self.len())
+            .field("custom_key", &self.key.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uri(s: &str) -> Uri {
+        s.parse().unwrap()
+    }
+
+    fn insert(cache: &RedirectCache, source: &Uri, target: Uri) {
+        cache.insert(source, &HeaderMap::new(), &[], target);
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = RedirectCache::with_capacity(2);
+
+        insert(&cache, &uri("http://a/1"), uri("http://b/1"));
+        insert(&cache, &uri("http://a/2"), uri("http://b/2"));
+        assert_eq!(cache.get(&uri("http://a/1")), Some(uri("http://b/1")));
+
+        insert(&cache, &uri("http://a/3"), uri("http://b/3"));
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&uri("http://a/2")), None);
+        assert_eq!(
+            cache.entries(),
+            vec![
+                (uri("http://a/1"), uri("http://b/1")),
+                (uri("http://a/3"), uri("http://b/3")),
+            ]
+        );
+    }
+
+    #[test]
+    fn custom_key_shares_redirects() {
+        let cache = RedirectCache::new().cache_key(|uri| uri.path().to_owned());
+
+        insert(&cache, &uri("http://a/1?utm_source=x"), uri("http://b/1"));
+
+        assert_eq!(
+            cache.get(&uri("http://a/1?utm_source=y")),
+            Some(uri("http://b/1"))
+        );
+        assert_eq!(cache.get(&uri("http://a/2")), None);
+        assert_eq!(
+            cache.entries(),
+            vec![(uri("http://a/1?utm_source=x"), uri("http://b/1"))]
+        );
+        assert_eq!(cache.remove(&uri("http://a/1")), Some(uri("http://b/1")));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn variants_are_selected_by_vary_headers() {
+        let cache = RedirectCache::new();
+        let source = uri("http://a/");
+        let vary = [http::header::ACCEPT_LANGUAGE];
+        let mut en = HeaderMap::new();
+        let mut de = HeaderMap::new();
+
+        en.insert(http::header::ACCEPT_LANGUAGE, HeaderValue::from_static("en"));
+        de.insert(http::header::ACCEPT_LANGUAGE, HeaderValue::from_static("de"));
+
+        cache.insert(&source, &en, &vary, uri("http://a/en"));
+        cache.insert(&source, &de, &vary, uri("http://a/de"));
+        cache.insert(&source, &HeaderMap::new(), &vary, uri("http://a/any"));
+
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.lookup(&source, &en, None), Some(uri("http://a/en")));
+        assert_eq!(cache.lookup(&source, &de, None), Some(uri("http://a/de")));
+        assert_eq!(cache.get(&source), Some(uri("http://a/any")));
+
+        let mut fr = HeaderMap::new();
+        fr.insert(http::header::ACCEPT_LANGUAGE, HeaderValue::from_static("fr"));
+        assert_eq!(cache.lookup(&source, &fr, None), None);
+
+        // Caching the same variant again replaces it.
+        cache.insert(&source, &en, &vary, uri("http://a/en-us"));
+
+        let variants = cache.variants(&source);
+        assert_eq!(variants.len(), 3);
+        assert_eq!(variants[2].vary(), &vary);
+        assert_eq!(
+            variants[2].request_header(&http::header::ACCEPT_LANGUAGE),
+            Some(&HeaderValue::from_static("en"))
+        );
+        assert_eq!(variants[2].target(), &uri("http://a/en-us"));
+        assert_eq!(variants[1].request_header(&http::header::ACCEPT_LANGUAGE), None);
+        assert_eq!(cache.entries().len(), 3);
+
+        assert_eq!(cache.remove(&source), Some(uri("http://a/en-us")));
+        assert!(cache.variants(&source).is_empty());
+    }
+
+    #[test]
+    fn multiple_header_values_are_combined() {
+        let cache = RedirectCache::new();
+        let source = uri("http://a/");
+        let vary = [http::header::ACCEPT_ENCODING];
+        let mut headers = HeaderMap::new();
+
+        headers.append(http::header::ACCEPT_ENCODING, HeaderValue::from_static("gzip"));
+        headers.append(http::header::ACCEPT_ENCODING, HeaderValue::from_static("br"));
+        cache.insert(&source, &headers, &vary, uri("http://b/"));
+
+        let mut combined = HeaderMap::new();
+        combined.insert(http::header::ACCEPT_ENCODING, HeaderValue::from_static("gzip, br"));
+
+        assert_eq!(cache.lookup(&source, &combined, None), Some(uri("http://b/")));
+        assert_eq!(cache.get(&source), None);
+    }
+
+    #[test]
+    fn variants_are_bounded() {
+        let cache = RedirectCache::new();
+        let source = uri("http://a/");
+        let vary = [HeaderName::from_static("x-variant")];
+
+        for i in 0..MAX_VARIANTS + 1 {
+            let mut headers = HeaderMap::new();
+            headers.insert("x-variant", HeaderValue::from(i));
+            cache.insert(&source, &headers, &vary, uri(&format!("http://b/{}", i)));
+        }
+
+        let variants = cache.variants(&source);
+        assert_eq!(variants.len(), MAX_VARIANTS);
+        assert_eq!(variants[0].target(), &uri("http://b/1"));
+    }
+
+    #[test]
+    fn invalidate_prefix() {
+        let cache = RedirectCache::new();
+
+        insert(&cache, &uri("http://a/api/1"), uri("http://b/1"));
+        insert(&cache, &uri("http://a/api/2"), uri("http://b/2"));
+        insert(&cache, &uri("http://a/other"), uri("http://b/3"));
+
+        assert_eq!(cache.invalidate_prefix("http://a/api/"), 2);
+        assert_eq!(cache.invalidate_prefix("http://a/api/"), 0);
+        assert_eq!(
+            cache.entries(),
+            vec![(uri("http://a/other"), uri("http://b/3"))]
+        );
+    }
+
+    #[test]
+    fn remove_and_clear() {
+        let cache = RedirectCache::new();
+
+        insert(&cache, &uri("http://a/1"), uri("http://b/1"));
+        insert(&cache, &uri("http://a/2"), uri("http://b/2"));
+
+        assert_eq!(cache.remove(&uri("http://a/1")), Some(uri("http://b/1")));
+        assert_eq!(cache.remove(&uri("http://a/1")), None);
+        assert_eq!(cache.len(), 1);
+
+        cache.clear();
+        assert!(cache.is_empty());
+        assert!(cache.entries().is_empty());
+    }
+}
@@ -19,7 +19,7 @@ pub(crate) trait SetOpt {
 
 // Define this struct inside a macro to reduce some boilerplate.
 macro_rules! define_request_config {
-    ($($field:ident: $t:ty,)*) => {
+    ($($(#[$attr:meta])* $field:ident: $t:ty,)*) => {
         /// Configuration for an HTTP request.
         ///
         /// This struct is not exposed directly, but rather is interacted with
@@ -27,6 +27,7 @@ macro_rules! define_request_config {
         #[derive(Clone, Debug, Default)]
         pub struct RequestConfig {
             $(
+                $(#[$attr])*
                 pub(crate) $field: $t,
             )*
         }
@@ -37,6 +38,7 @@ macro_rules! define_request_config {
             /// config.
             pub(crate) fn merge(&mut self, defaults: &Self) {
                 $(
+                    $(#[$attr])*
                     if self.$field.is_none() {
                         if let Some(value) = defaults.$field.as_ref() {
                             self.$field = Some(value.clone());
@@ -54,31 +56,79 @@ define_request_config! {
     connect_timeout: Option<Duration>,
     low_speed_timeout: Option<(u32, Duration)>,
     version_negotiation: Option<VersionNegotiation>,
+    http10_keep_alive: Option<bool>,
     automatic_decompression: Option<bool>,
+    content_encoding_passthrough: Option<bool>,
+    decompression_limit: Option<DecompressionLimit>,
+    decompress_content_types: Option<decompression::ContentTypeFilter>,
+    content_codings: Option<ContentCodings>,
     expect_continue: Option<ExpectContinue>,
     authentication: Option<Authentication>,
     credentials: Option<Credentials>,
+    aws_sigv4: Option<AwsSigV4>,
     tcp_keepalive: Option<Duration>,
     tcp_nodelay: Option<bool>,
+    fresh_connection: Option<bool>,
     interface: Option<NetworkInterface>,
+    local_port_range: Option<(u16, u16)>,
     ip_version: Option<IpVersion>,
     dial: Option<Dialer>,
+    host_header: Option<HostHeader>,
+    accept_versions: Option<AcceptVersions>,
+    body_checksum: Option<BodyChecksum>,
     proxy: Option<Option<http::Uri>>,
     proxy_blacklist: Option<proxy::Blacklist>,
     proxy_authentication: Option<Proxy<Authentication>>,
     proxy_credentials: Option<Proxy<Credentials>>,
+    proxy_protocol: Option<ProxyProtocol>,
     max_upload_speed: Option<u64>,
     max_download_speed: Option<u64>,
+    resume_from: Option<u64>,
     ssl_client_certificate: Option<ClientCertificate>,
     ssl_ca_certificate: Option<CaCertificate>,
     ssl_ciphers: Option<tls::Ciphers>,
+    ssl_min_version: Option<TlsVersion>,
     ssl_options: Option<SslOption>,
+    proxy_ssl_client_certificate: Option<Proxy<ClientCertificate>>,
+    proxy_ssl_ca_certificate: Option<Proxy<CaCertificate>>,
+    proxy_ssl_ciphers: Option<Proxy<tls::Ciphers>>,
+    proxy_ssl_min_version: Option<Proxy<TlsVersion>>,
+    proxy_ssl_options: Option<Proxy<SslOption>>,
     enable_metrics: Option<bool>,
+    transfer_messages: Option<usize>,
+    interrupt_flag: Option<Arc<AtomicBool>>,
+    headers_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    on_stats: Option<crate::metrics::StatsCallback>,
+    on_progress: Option<crate::metrics::ProgressCallback>,
+    on_body_chunk: Option<crate::handler::BodyChunkCallback>,
+    sniff_content_type: Option<bool>,
+    priority: Option<i32>,
 
     // Used by interceptors
     redirect_policy: Option<RedirectPolicy>,
+    redirect_cache: Option<RedirectCache>,
+    range_cache: Option<RangeCache>,
+    capability_cache: Option<CapabilityCache>,
+    cache_control: Option<CacheControl>,
+    dns_negative_cache: Option<crate::dns_negative_cache::DnsNegativeCache>,
+    event_log: Option<crate::event_log::EventLog>,
+    #[cfg(feature = "trace-context")]
+    trace_propagation: Option<crate::trace_context::Propagation>,
+    retry_budget: Option<RetryBudget>,
+    retry_policy: Option<RetryPolicy>,
+    host_policy: Option<HostPolicy>,
+    idna_policy: Option<IdnaPolicy>,
+    url_normalization: Option<UrlNormalization>,
+    strict_validation: Option<bool>,
     auto_referer: Option<bool>,
+    fail_with_body: Option<usize>,
+    token_provider: Option<TokenProviderObj>,
+    authenticator: Option<AuthenticatorObj>,
     title_case_headers: Option<bool>,
+    omitted_headers: Option<Vec<http::header::HeaderName>>,
+    clock: Option<clock::SharedClock>,
+    environment: Option<Environment>,
 }
 
 impl RequestConfig {
@@ -93,11 +143,29 @@ impl RequestConfig {
             authentication: Some(Authentication::default()),
             // Set native root certificates if available.
             ssl_ca_certificate: CaCertificate::native(),
+            proxy_ssl_ca_certificate: CaCertificate::native().map(Proxy),
+            // Give each client its own cache of permanent redirects.
+            redirect_cache: Some(RedirectCache::new()),
             ..Default::default()
         }
     }
 }
 
+impl RequestConfig {
+    /// Returns true if response bodies are decoded automatically, taking
+    /// pass-through mode into account.
+    pub(crate) fn is_automatic_decompression(&self) -> bool {
+        self.automatic_decompression == Some(true)
+            && self.content_encoding_passthrough != Some(true)
+    }
+
+    /// Returns true if response bodies are decoded by the client rather than
+    /// by curl, when they are decoded at all.
+    pub(crate) fn is_client_side_decompression(&self) -> bool {
+        self.decompress_content_types.is_some() || self.content_codings.is_some()
+    }
+}
+
 impl SetOpt for RequestConfig {
     fn set_opt<H>(&self, easy: &mut Easy2<H>) -> Result<(), curl::Error> {
         if let Some(timeout) = self.timeout {
@@ -117,9 +185,25 @@ impl SetOpt for RequestConfig {
             negotiation.set_opt(easy)?;
         }
 
+        if self.http10_keep_alive.is_some() {
+            VersionNegotiation::http10().set_opt(easy)?;
+        }
+
         #[allow(unsafe_code)]
         {
-            if let Some(enable) = self.automatic_decompression {
+            // Pass-through mode overrides whatever decompression setting was
+            // inherited from the client.
+            let automatic_decompression = match self.content_encoding_passthrough {
+                Some(true) => Some(false),
+                // Response bodies are decoded by the client instead, once it
+                // knows their content type or coding.
+                _ if self.is_client_side_decompression() => {
+                    self.automatic_decompression.map(|_| false)
+                }
+                _ => self.automatic_decompression,
+            };
+
+            if let Some(enable) = automatic_decompression {
                 if enable {
                     // Enable automatic decompression, and also populate the
                     // Accept-Encoding header with all supported encodings if not
@@ -153,6 +237,12 @@ impl SetOpt for RequestConfig {
             credentials.set_opt(easy)?;
         }
 
+        // Must come after the authentication methods, since enabling signing
+        // overrides them.
+        if let Some(signing) = self.aws_sigv4.as_ref() {
+            signing.set_opt(easy)?;
+        }
+
         if let Some(interval) = self.tcp_keepalive {
             easy.tcp_keepalive(true)?;
             easy.tcp_keepintvl(interval)?;
@@ -166,6 +256,12 @@ impl SetOpt for RequestConfig {
             interface.set_opt(easy)?;
         }
 
+        if let Some((start, end)) = self.local_port_range {
+            easy.set_local_port(start)?;
+            // Curl counts the first port as part of the range.
+            easy.local_port_range((end - start).saturating_add(1))?;
+        }
+
         if let Some(version) = self.ip_version.as_ref() {
             version.set_opt(easy)?;
         }
@@ -193,6 +289,10 @@ impl SetOpt for RequestConfig {
             credentials.set_opt(easy)?;
         }
 
+        if let Some(proxy_protocol) = self.proxy_protocol.as_ref() {
+            proxy_protocol.set_opt(easy)?;
+        }
+
         if let Some(max) = self.max_upload_speed {
             easy.max_send_speed(max)?;
         }
@@ -201,6 +301,10 @@ impl SetOpt for RequestConfig {
             easy.max_recv_speed(max)?;
         }
 
+        if let Some(offset) = self.resume_from {
+            easy.resume_from(offset)?;
+        }
+
         if let Some(cert) = self.ssl_client_certificate.as_ref() {
             cert.set_opt(easy)?;
         }
@@ -213,12 +317,42 @@ impl SetOpt for RequestConfig {
             ciphers.set_opt(easy)?;
         }
 
+        if let Some(version) = self.ssl_min_version.as_ref() {
+            version.set_opt(easy)?;
+        }
+
         if let Some(options) = self.ssl_options.as_ref() {
             options.set_opt(easy)?;
         }
 
-        if let Some(enable) = self.enable_metrics {
-            easy.progress(enable)?;
+        if let Some(cert) = self.proxy_ssl_client_certificate.as_ref() {
+            cert.set_opt(easy)?;
+        }
+
+        if let Some(cert) = self.proxy_ssl_ca_certificate.as_ref() {
+            cert.set_opt(easy)?;
+        }
+
+        if let Some(ciphers) = self.proxy_ssl_ciphers.as_ref() {
+            ciphers.set_opt(easy)?;
+        }
+
+        if let Some(version) = self.proxy_ssl_min_version.as_ref() {
+            version.set_opt(easy)?;
+        }
+
+        if let Some(options) = self.proxy_ssl_options.as_ref() {
+            options.set_opt(easy)?;
+        }
+
+        if self.enable_metrics.is_some() || self.on_progress.is_some() {
+            easy.progress(self.enable_metrics == Some(true) || self.on_progress.is_some())?;
+        }
+
+        // URLs have already been normalized as requested, so make sure curl
+        // sends them exactly as they are.
+        if self.url_normalization.is_some() {
+            easy.path_as_is(true)?;
         }
 
         Ok(())
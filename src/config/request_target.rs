@@ -0,0 +1,68 @@
+use super::SetOpt;
+use curl::easy::Easy2;
+use std::ffi::CString;
+
+/// Option for `CURLOPT_REQUEST_TARGET`, which is not exposed by curl-sys.
+const CURLOPT_REQUEST_TARGET: curl_sys::CURLoption = curl_sys::CURLOPTTYPE_OBJECTPOINT + 266;
+
+/// A request extension that sends the given request target in the request
+/// line exactly as given, instead of the path and query of the request URI.
+///
+/// The request URI is still used to decide which server to connect to, but
+/// the target is sent byte-for-byte without being parsed, normalized, or
+/// percent-encoded. This is an escape hatch for testing how servers parse
+/// URLs, or for talking to legacy systems that expect non-standard encodings.
+/// For HTTP/2 and newer, the target is sent as the `:path` pseudo-header.
+///
+/// The target only applies to the request it is attached to, and not to any
+/// requests sent while following redirects. Sending a request with a target
+/// containing CR, LF, or NUL bytes fails with an error of kind
+/// [`ErrorKind::InvalidRequest`](crate::error::ErrorKind::InvalidRequest),
+/// since those cannot be expressed in a request line.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{config::RequestTarget, prelude::*, Request};
+///
+/// let response = Request::get("http://legacy.example.org/")
+///     .extension(RequestTarget::new("/search?q=caf\u{e9} latte"))
+///     .body(())?
+///     .send()?;
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RequestTarget(Vec<u8>);
+
+impl RequestTarget {
+    /// Create a new request target from the given bytes.
+    pub fn new(target: impl Into<Vec<u8>>) -> Self {
+        Self(target.into())
+    }
+
+    /// Get the bytes of the request target.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Returns true if the target can be sent in a request line.
+    pub(crate) fn is_valid(&self) -> bool {
+        !self.0.iter().any(|&b| matches!(b, b'\r' | b'\n' | 0))
+    }
+}
+
+impl SetOpt for RequestTarget {
+    #[allow(unsafe_code)]
+    fn set_opt<H>(&self, easy: &mut Easy2<H>) -> Result<(), curl::Error> {
+        let target = CString::new(self.0.clone())
+            .map_err(|_| curl::Error::new(curl_sys::CURLE_BAD_FUNCTION_ARGUMENT))?;
+
+        // Curl makes its own copy of the string.
+        unsafe {
+            match curl_sys::curl_easy_setopt(easy.raw(), CURLOPT_REQUEST_TARGET, target.as_ptr()) {
+                curl_sys::CURLE_OK => Ok(()),
+                code => Err(curl::Error::new(code)),
+            }
+        }
+    }
+}
@@ -0,0 +1,437 @@
+use http::{Method, StatusCode, Uri};
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Number of hosts tracked before buckets that are full again are discarded.
+const PRUNE_THRESHOLD: usize = 256;
+
+/// A policy for automatically retrying requests that failed for reasons that
+/// are likely to be transient.
+///
+/// A request is retried when:
+///
+/// - A connection to the server could not be established. Since nothing was
+///   sent yet, requests of any method are retried.
+/// - The connection failed while the request was in flight, and the request
+///   method is idempotent.
+/// - The server responded with one of the retryable status codes, and the
+///   request method is idempotent. By default these are `429 Too Many
+///   Requests`, `500 Internal Server Error`, `502 Bad Gateway`, `503 Service
+///   Unavailable`, and `504 Gateway Timeout`.
+///
+/// Only requests whose bodies can be sent again are retried, which includes
+/// empty bodies, bodies in memory, and bodies created with
+/// [`AsyncBody::from_fn`](crate::AsyncBody::from_fn). Requests with streaming
+/// bodies are sent once.
+///
+/// Between attempts the client waits for an exponentially growing backoff,
+/// starting at the initial backoff and doubling after every attempt up to the
+/// maximum backoff. Random jitter is applied to the backoff by default, so
+/// that clients failing at the same time do not retry at the same time. If
+/// the response includes a `Retry-After` header, the client waits for as long
+/// as the server asks instead. If the server asks to wait longer than the
+/// maximum backoff, the response is returned without retrying.
+///
+/// If the client has a [`RetryBudget`], every retry must be allowed by the
+/// budget as well.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{config::RetryPolicy, prelude::*, HttpClient};
+/// use std::time::Duration;
+///
+/// let client = HttpClient::builder()
+///     .retry_policy(
+///         RetryPolicy::new(3).backoff(Duration::from_millis(200), Duration::from_secs(5)),
+///     )
+///     .build()?;
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    jitter: bool,
+    retry_after: bool,
+    statuses: Vec<StatusCode>,
+}
+
+impl RetryPolicy {
+    /// Create a new retry policy that sends a request at most the given
+    /// number of times in total, including the first attempt.
+    ///
+    /// By default the backoff starts at 100 milliseconds and grows to at most
+    /// 10 seconds.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            jitter: true,
+            retry_after: true,
+            statuses: vec![
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+        }
+    }
+
+    /// Set the backoff before the first retry, and the maximum backoff between
+    /// any two attempts.
+    #[must_use = "builders have no effect if unused"]
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Enable or disable random jitter of the backoff. Enabled by default.
+    ///
+    /// With jitter, the client waits for a random amount of time between half
+    /// of the backoff and the full backoff.
+    #[must_use = "builders have no effect if unused"]
+    pub fn jitter(mut self, enable: bool) -> Self {
+        self.jitter = enable;
+        self
+    }
+
+    /// Enable or disable honoring `Retry-After` headers of responses. Enabled
+    /// by default.
+    #[must_use = "builders have no effect if unused"]
+    pub fn retry_after(mut self, enable: bool) -> Self {
+        self.retry_after = enable;
+        self
+    }
+
+    /// Set the response status codes that cause a request to be retried,
+    /// replacing the defaults.
+    #[must_use = "builders have no effect if unused"]
+    pub fn statuses(mut self, statuses: impl IntoIterator<Item = StatusCode>) -> Self {
+        self.statuses = statuses.into_iter().collect();
+        self
+    }
+
+    pub(crate) fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    pub(crate) fn max_backoff(&self) -> Duration {
+        self.max_backoff
+    }
+
+    pub(crate) fn honors_retry_after(&self) -> bool {
+        self.retry_after
+    }
+
+    /// Check whether a response with the given status to a request with the
+    /// given method should be retried.
+    pub(crate) fn is_retryable_status(&self, method: &Method, status: StatusCode) -> bool {
+        method.is_idempotent() && self.statuses.contains(&status)
+    }
+
+    /// Get the backoff to wait for after the given attempt, numbered from 1,
+    /// given a random number between 0 and 1 for jitter.
+    pub(crate) fn backoff_after(&self, attempt: u32, random: f64) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .checked_mul(1 << attempt.saturating_sub(1).min(31))
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff);
+
+        if self.jitter {
+            backoff.mul_f64(0.5 + random.clamp(0.0, 1.0) / 2.0)
+        } else {
+            backoff
+        }
+    }
+}
+
+/// A budget that limits how many automatic retries a client may send, so that
+/// retries cannot amplify an outage into a retry storm.
+///
+/// The budget is a token bucket kept separately for each host. Every request
+/// sent to a host deposits a fraction of a token, given by the retry ratio,
+/// and every retry withdraws a whole token. When no tokens are left, retries
+/// are refused and the request fails with
+/// [`ErrorKind::RetryBudgetExhausted`](crate::error::ErrorKind::RetryBudgetExhausted)
+/// instead. With the default ratio of `0.2`, retries may not exceed 20% of the
+/// requests sent to a host.
+///
+/// So that clients sending few requests can still retry occasionally, each
+/// bucket also refills at a minimum rate regardless of traffic. Buckets start
+/// full and never hold more than the configured burst of tokens.
+///
+/// Retry budgets are cheap to clone, and clones share the same buckets and
+/// counters.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{config::RetryBudget, HttpClient};
+///
+/// let budget = RetryBudget::new(0.1).min_per_second(2).burst(20);
+///
+/// let client = HttpClient::builder()
+///     .retry_budget(budget.clone())
+///     .build()?;
+///
+/// // Later:
+/// println!("{} retries refused", budget.exhausted());
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct RetryBudget {
+    ratio: f64,
+    min_per_second: f64,
+    burst: f64,
+    state: Arc<State>,
+}
+
+#[derive(Default)]
+struct State {
+    buckets: Mutex<HashMap<String, Bucket>>,
+    retries: AtomicU64,
+    exhausted: AtomicU64,
+}
+
+struct Bucket {
+    balance: f64,
+    updated_at: Instant,
+}
+
+impl Default for RetryBudget {
+    fn default() -> Self {
+        Self::new(0.2)
+    }
+}
+
+impl RetryBudget {
+    /// Create a new retry budget allowing retries to make up at most the given
+    /// ratio of the requests sent to each host, for example `0.2` for 20%.
+    ///
+    /// By default each host may also retry once per second regardless of
+    /// traffic, and may retry at most 10 times in a burst.
+    pub fn new(ratio: f64) -> Self {
+        Self {
+            ratio: ratio.max(0.0),
+            min_per_second: 1.0,
+            burst: 10.0,
+            state: Default::default(),
+        }
+    }
+
+    /// Set the number of retries per second each host is allowed regardless
+    /// of how many requests are sent to it.
+    #[must_use = "builders have no effect if unused"]
+    pub fn min_per_second(mut self, retries: u32) -> Self {
+        self.min_per_second = retries.into();
+        self
+    }
+
+    /// Set the maximum number of retries that can be saved up for each host,
+    /// and therefore sent in a burst.
+    #[must_use = "builders have no effect if unused"]
+    pub fn burst(mut self, retries: u32) -> Self {
+        self.burst = retries.into();
+        self
+    }
+
+    /// Get the total number of retries this budget has allowed.
+    pub fn retries(&self) -> u64 {
+        self.state.retries.load(Ordering::Relaxed)
+    }
+
+    /// Get the total number of retries this budget has refused because it was
+    /// exhausted.
+    pub fn exhausted(&self) -> u64 {
+        self.state.exhausted.load(Ordering::Relaxed)
+    }
+
+    /// Record a request being sent to the host of a URI.
+    pub(crate) fn deposit(&self, uri: &Uri, now: Instant) {
+        self.update(uri, now, |bucket| bucket.balance += self.ratio);
+    }
+
+    /// Try to withdraw a token for retrying a request to the host of a URI,
+    /// returning whether the retry is allowed.
+    #[cfg_attr(not(feature = "blocking"), allow(dead_code))]
+    pub(crate) fn try_withdraw(&self, uri: &Uri, now: Instant) -> bool {
+        let allowed = self.update(uri, now, |bucket| {
+            if bucket.balance >= 1.0 {
+                bucket.balance -= 1.0;
+                true
+            } else {
+                false
+            }
+        });
+
+        if allowed {
+            self.state.retries.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.state.exhausted.fetch_add(1, Ordering::Relaxed);
+
+            tracing::debug!(
+                host = uri.host().unwrap_or_default(),
+                "retry budget exhausted, not retrying request"
+            );
+        }
+
+        allowed
+    }
+
+    fn update<T>(&self, uri: &Uri, now: Instant, f: impl FnOnce(&mut Bucket) -> T) -> T {
+        let host = uri.host().unwrap_or_default().to_ascii_lowercase();
+        let mut buckets = self.state.buckets.lock().unwrap();
+
+        // A full bucket behaves the same as a new one, so they can be
+        // discarded without changing anything.
+        if buckets.len() >= PRUNE_THRESHOLD && !buckets.contains_key(&host) {
+            buckets.retain(|_, bucket| {
+                bucket.refill(now, self.min_per_second, self.burst);
+                bucket.balance < self.burst
+            });
+        }
+
+        let bucket = buckets.entry(host).or_insert_with(|| Bucket {
+            balance: self.burst,
+            updated_at: now,
+        });
+
+        bucket.refill(now, self.min_per_second, self.burst);
+        let result = f(bucket);
+        bucket.balance = bucket.balance.min(self.burst);
+
+        result
+    }
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant, per_second: f64, max: f64) {
+        let elapsed = now.saturating_duration_since(self.updated_at);
+
+        self.balance = (self.balance + elapsed.as_secs_f64() * per_second).min(max);
+        self.updated_at = now;
+    }
+}
+
+impl fmt::Debug for RetryBudget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryBudget")
+            .field("ratio", &self.ratio)
+            .field("min_per_second", &self.min_per_second)
+            .field("burst", &self.burst)
+            .field("retries", &self.retries())
+            .field("exhausted", &self.exhausted())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn uri(s: &str) -> Uri {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_up_to_max() {
+        let policy = RetryPolicy::new(10)
+            .backoff(Duration::from_millis(100), Duration::from_secs(1))
+            .jitter(false);
+
+        assert_eq!(policy.backoff_after(1, 0.0), Duration::from_millis(100));
+        assert_eq!(policy.backoff_after(2, 0.0), Duration::from_millis(200));
+        assert_eq!(policy.backoff_after(4, 0.0), Duration::from_millis(800));
+        assert_eq!(policy.backoff_after(5, 0.0), Duration::from_secs(1));
+        assert_eq!(policy.backoff_after(100, 0.0), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn jitter_is_between_half_and_full_backoff() {
+        let policy = RetryPolicy::new(10).backoff(Duration::from_secs(1), Duration::from_secs(1));
+
+        assert_eq!(policy.backoff_after(1, 0.0), Duration::from_millis(500));
+        assert_eq!(policy.backoff_after(1, 0.5), Duration::from_millis(750));
+        assert_eq!(policy.backoff_after(1, 1.0), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn only_idempotent_requests_are_retried_on_status() {
+        let policy = RetryPolicy::new(2);
+
+        assert!(policy.is_retryable_status(&Method::GET, StatusCode::SERVICE_UNAVAILABLE));
+        assert!(policy.is_retryable_status(&Method::PUT, StatusCode::TOO_MANY_REQUESTS));
+        assert!(!policy.is_retryable_status(&Method::POST, StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!policy.is_retryable_status(&Method::GET, StatusCode::NOT_FOUND));
+    }
+
+    #[test]
+    fn retries_are_limited_by_burst_and_ratio() {
+        let now = Instant::now();
+        let budget = RetryBudget::new(0.5).min_per_second(0).burst(2);
+        let a = uri("http://a.example/");
+
+        assert!(budget.try_withdraw(&a, now));
+        assert!(budget.try_withdraw(&a, now));
+        assert!(!budget.try_withdraw(&a, now));
+
+        // Two requests earn one retry.
+        budget.deposit(&a, now);
+        assert!(!budget.try_withdraw(&a, now));
+        budget.deposit(&a, now);
+        assert!(budget.try_withdraw(&a, now));
+
+        assert_eq!(budget.retries(), 3);
+        assert_eq!(budget.exhausted(), 2);
+    }
+
+    #[test]
+    fn hosts_have_separate_buckets() {
+        let now = Instant::now();
+        let budget = RetryBudget::new(0.2).min_per_second(0).burst(1);
+
+        assert!(budget.try_withdraw(&uri("http://a.example/"), now));
+        assert!(!budget.try_withdraw(&uri("http://A.example/other"), now));
+        assert!(budget.try_withdraw(&uri("http://b.example/"), now));
+    }
+
+    #[test]
+    fn deposits_do_not_exceed_burst() {
+        let now = Instant::now();
+        let budget = RetryBudget::new(1.0).min_per_second(0).burst(1);
+        let a = uri("http://a.example/");
+
+        for _ in 0..10 {
+            budget.deposit(&a, now);
+        }
+
+        assert!(budget.try_withdraw(&a, now));
+        assert!(!budget.try_withdraw(&a, now));
+    }
+
+    #[test]
+    fn buckets_refill_over_time() {
+        let now = Instant::now();
+        let budget = RetryBudget::new(0.0).min_per_second(1).burst(1);
+        let a = uri("http://a.example/");
+
+        assert!(budget.try_withdraw(&a, now));
+        assert!(!budget.try_withdraw(&a, now + Duration::from_millis(500)));
+        assert!(budget.try_withdraw(&a, now + Duration::from_secs(1)));
+    }
+}
@@ -0,0 +1,30 @@
+/// Describes what happens to responses that are still being received when the
+/// last handle to the client that sent them is dropped.
+///
+/// The default is to let them finish.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ShutdownPolicy {
+    /// Each response keeps the client's background agent alive until its
+    /// body has been fully received or the response is dropped, so responses
+    /// can continue to be read normally after the client is dropped.
+    ///
+    /// This is the default policy.
+    Drain,
+
+    /// Abort all transfers in progress as soon as the last handle to the client
+    /// is dropped. Reading the body of an affected response will then fail
+    /// with an error of kind
+    /// [`ErrorKind::ClientClosed`](crate::error::ErrorKind::ClientClosed).
+    ///
+    /// Dropping the client blocks briefly until its background agent has
+    /// stopped.
+    Abort,
+}
+
+// Can't derive this on our MSRV.
+#[allow(clippy::derivable_impls)]
+impl Default for ShutdownPolicy {
+    fn default() -> Self {
+        ShutdownPolicy::Drain
+    }
+}
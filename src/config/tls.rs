@@ -1,12 +1,12 @@
 //! Configuration options related to SSL/TLS.
 
-use super::SetOpt;
-use curl::easy::{Easy2, SslOpt};
+use super::{proxy::Proxy, SetOpt};
+use curl::easy::{Easy2, SslOpt, SslVersion};
 use once_cell::sync::Lazy;
 use std::{
     iter::FromIterator,
     ops::{BitOr, BitOrAssign},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 #[derive(Clone, Debug)]
@@ -182,6 +182,27 @@ impl SetOpt for ClientCertificate {
     }
 }
 
+impl SetOpt for Proxy<ClientCertificate> {
+    fn set_opt<H>(&self, easy: &mut Easy2<H>) -> Result<(), curl::Error> {
+        easy.proxy_sslcert_type(self.0.format)?;
+
+        match &self.0.data {
+            PathOrBlob::Path(path) => easy.proxy_sslcert(path_to_str(path)?),
+            PathOrBlob::Blob(bytes) => easy.proxy_sslcert_blob(bytes.as_slice()),
+        }?;
+
+        if let Some(key) = self.0.private_key.as_ref() {
+            Proxy(key.clone()).set_opt(easy)?;
+        }
+
+        if let Some(password) = self.0.password.as_ref() {
+            easy.proxy_key_password(password)?;
+        }
+
+        Ok(())
+    }
+}
+
 /// A private key file.
 #[derive(Clone, Debug)]
 pub struct PrivateKey {
@@ -282,6 +303,23 @@ impl SetOpt for PrivateKey {
     }
 }
 
+impl SetOpt for Proxy<PrivateKey> {
+    fn set_opt<H>(&self, easy: &mut Easy2<H>) -> Result<(), curl::Error> {
+        easy.proxy_sslkey_type(self.0.format)?;
+
+        match &self.0.data {
+            PathOrBlob::Path(path) => easy.proxy_sslkey(path_to_str(path)?),
+            PathOrBlob::Blob(bytes) => easy.proxy_sslkey_blob(bytes.as_slice()),
+        }?;
+
+        if let Some(password) = self.0.password.as_ref() {
+            easy.proxy_key_password(password)?;
+        }
+
+        Ok(())
+    }
+}
+
 /// A public CA certificate bundle file.
 #[derive(Clone, Debug)]
 pub struct CaCertificate {
@@ -385,6 +423,15 @@ impl SetOpt for CaCertificate {
     }
 }
 
+impl SetOpt for Proxy<CaCertificate> {
+    fn set_opt<H>(&self, easy: &mut Easy2<H>) -> Result<(), curl::Error> {
+        match &self.0.data {
+            PathOrBlob::Path(path) => easy.proxy_cainfo(path_to_str(path)?),
+            PathOrBlob::Blob(bytes) => easy.proxy_ssl_cainfo_blob(bytes.as_slice()),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub(crate) struct Ciphers(String);
 
@@ -400,6 +447,56 @@ impl SetOpt for Ciphers {
     }
 }
 
+impl SetOpt for Proxy<Ciphers> {
+    fn set_opt<H>(&self, easy: &mut Easy2<H>) -> Result<(), curl::Error> {
+        easy.proxy_ssl_cipher_list(&(self.0).0)
+    }
+}
+
+/// A version of the TLS protocol.
+///
+/// Used with
+/// [`Configurable::ssl_min_version`](crate::config::Configurable::ssl_min_version)
+/// to refuse connections using older versions.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[non_exhaustive]
+pub enum TlsVersion {
+    /// TLS 1.0.
+    Tls10,
+    /// TLS 1.1.
+    Tls11,
+    /// TLS 1.2.
+    Tls12,
+    /// TLS 1.3.
+    Tls13,
+}
+
+impl SetOpt for TlsVersion {
+    fn set_opt<H>(&self, easy: &mut Easy2<H>) -> Result<(), curl::Error> {
+        let min_version = match self {
+            Self::Tls10 => SslVersion::Tlsv10,
+            Self::Tls11 => SslVersion::Tlsv11,
+            Self::Tls12 => SslVersion::Tlsv12,
+            Self::Tls13 => SslVersion::Tlsv13,
+        };
+
+        easy.ssl_min_max_version(min_version, SslVersion::Default)
+    }
+}
+
+impl SetOpt for Proxy<TlsVersion> {
+    fn set_opt<H>(&self, easy: &mut Easy2<H>) -> Result<(), curl::Error> {
+        let min_version = match self.0 {
+            TlsVersion::Tls10 => SslVersion::Tlsv10,
+            TlsVersion::Tls11 => SslVersion::Tlsv11,
+            TlsVersion::Tls12 => SslVersion::Tlsv12,
+            TlsVersion::Tls13 => SslVersion::Tlsv13,
+        };
+
+        easy.proxy_ssl_min_max_version(min_version, SslVersion::Default)
+    }
+}
+
 /// A flag that can be used to alter the behavior of SSL/TLS connections.
 ///
 /// Most options are for disabling security checks that introduce security
@@ -475,6 +572,23 @@ impl SetOpt for SslOption {
     }
 }
 
+impl SetOpt for Proxy<SslOption> {
+    fn set_opt<H>(&self, easy: &mut Easy2<H>) -> Result<(), curl::Error> {
+        let mut opt = SslOpt::new();
+        opt.no_revoke(self.0.contains(SslOption::DANGER_ACCEPT_REVOKED_CERTS));
+
+        easy.proxy_ssl_options(&opt)?;
+        easy.proxy_ssl_verify_peer(!self.0.contains(SslOption::DANGER_ACCEPT_INVALID_CERTS))?;
+        easy.proxy_ssl_verify_host(!self.0.contains(SslOption::DANGER_ACCEPT_INVALID_HOSTS))
+    }
+}
+
+/// Curl only accepts paths for some of the proxy options as strings.
+fn path_to_str(path: &Path) -> Result<&str, curl::Error> {
+    path.to_str()
+        .ok_or_else(|| curl::Error::new(curl_sys::CURLE_BAD_FUNCTION_ARGUMENT))
+}
+
 #[cfg(test)]
 mod tests {
     use super::SslOption;
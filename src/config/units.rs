@@ -0,0 +1,419 @@
+//! Parsing of human-friendly durations, sizes, and speeds.
+
+use std::{convert::TryFrom, fmt, str::FromStr, time::Duration};
+
+/// An error which can be returned when parsing a duration, size, or speed
+/// from a string.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnitParseError {
+    what: &'static str,
+    input: String,
+    reason: &'static str,
+}
+
+impl UnitParseError {
+    fn new(what: &'static str, input: &str, reason: &'static str) -> Self {
+        Self {
+            what,
+            input: input.to_owned(),
+            reason,
+        }
+    }
+}
+
+impl fmt::Display for UnitParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid {} {:?}: {}", self.what, self.input, self.reason)
+    }
+}
+
+impl std::error::Error for UnitParseError {}
+
+/// A duration parsed from a human-friendly string, such as `30s`, `1.5h`, or
+/// `1m 30s`.
+///
+/// A duration is one or more numbers, each followed by a unit. The supported
+/// units are `ns`, `us` (or `µs`), `ms`, `s`, `m` (or `min`), `h`, and `d`.
+/// Numbers may have a fractional part, and any precision beyond nanoseconds
+/// is discarded. A number without a unit is rejected, since it would be
+/// ambiguous.
+///
+/// This is useful for reading options such as
+/// [`Configurable::timeout`](super::Configurable::timeout) from configuration
+/// files or environment variables.
+///
+/// # Examples
+///
+/// ```
+/// use isahc::config::HumanDuration;
+/// use std::time::Duration;
+///
+/// let timeout: HumanDuration = "1m 30s".parse()?;
+/// assert_eq!(Duration::from(timeout), Duration::from_secs(90));
+///
+/// assert!("30".parse::<HumanDuration>().is_err());
+/// # Ok::<(), isahc::config::UnitParseError>(())
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct HumanDuration(Duration);
+
+impl HumanDuration {
+    /// Get the parsed duration.
+    pub fn as_duration(&self) -> Duration {
+        self.0
+    }
+}
+
+impl From<HumanDuration> for Duration {
+    fn from(duration: HumanDuration) -> Self {
+        duration.0
+    }
+}
+
+impl FromStr for HumanDuration {
+    type Err = UnitParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |reason| UnitParseError::new("duration", s, reason);
+        let mut rest = s.trim();
+        let mut nanos = 0u128;
+
+        if rest.is_empty() {
+            return Err(error("expected a number"));
+        }
+
+        while !rest.is_empty() {
+            let (number, tail) = split_number(rest).ok_or_else(|| error("expected a number"))?;
+            let tail = tail.trim_start();
+            let unit_len = tail
+                .find(|c: char| c.is_ascii_digit() || c.is_whitespace())
+                .unwrap_or(tail.len());
+
+            let unit_nanos: u128 = match &tail[..unit_len] {
+                "ns" => 1,
+                "us" | "µs" => 1_000,
+                "ms" => 1_000_000,
+                "s" => 1_000_000_000,
+                "m" | "min" => 60 * 1_000_000_000,
+                "h" => 60 * 60 * 1_000_000_000,
+                "d" => 24 * 60 * 60 * 1_000_000_000,
+                "" => return Err(error("missing unit")),
+                _ => return Err(error("unknown unit")),
+            };
+
+            nanos = number
+                .scale(unit_nanos)
+                .and_then(|n| nanos.checked_add(n))
+                .ok_or_else(|| error("value is too large"))?;
+            rest = tail[unit_len..].trim_start();
+        }
+
+        let secs = u64::try_from(nanos / 1_000_000_000)
+            .map_err(|_| error("value is too large"))?;
+
+        Ok(Self(Duration::new(secs, (nanos % 1_000_000_000) as u32)))
+    }
+}
+
+impl TryFrom<&'_ str> for HumanDuration {
+    type Error = UnitParseError;
+
+    fn try_from(str: &str) -> Result<Self, Self::Error> {
+        str.parse()
+    }
+}
+
+impl TryFrom<String> for HumanDuration {
+    type Error = UnitParseError;
+
+    fn try_from(string: String) -> Result<Self, Self::Error> {
+        string.parse()
+    }
+}
+
+/// A number of bytes parsed from a human-friendly string, such as `512`,
+/// `10 MB`, or `1.5GiB`.
+///
+/// A size is a number optionally followed by a unit, with or without a space
+/// in between. The supported units are `B`, the decimal units `kB`, `MB`,
+/// `GB`, and `TB`, which are powers of 1000, and the binary units `KiB`,
+/// `MiB`, `GiB`, and `TiB`, which are powers of 1024. Units are not case
+/// sensitive. A number without a unit is a number of bytes. Numbers may have
+/// a fractional part, and any fraction of a byte is discarded.
+///
+/// This is useful for reading options such as
+/// [`DecompressionLimit::max_size`](super::DecompressionLimit::max_size) from
+/// configuration files or environment variables.
+///
+/// # Examples
+///
+/// ```
+/// use isahc::config::ByteSize;
+///
+/// assert_eq!("10 MB".parse::<ByteSize>()?.as_u64(), 10_000_000);
+/// assert_eq!("1.5KiB".parse::<ByteSize>()?.as_u64(), 1536);
+/// assert_eq!("512".parse::<ByteSize>()?.as_u64(), 512);
+/// # Ok::<(), isahc::config::UnitParseError>(())
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ByteSize(u64);
+
+impl ByteSize {
+    /// Get the parsed number of bytes.
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+
+    fn parse(s: &str, what: &'static str) -> Result<Self, UnitParseError> {
+        let error = |reason| UnitParseError::new(what, s, reason);
+        let (number, unit) =
+            split_number(s.trim()).ok_or_else(|| error("expected a number"))?;
+
+        let multiplier: u128 = match unit.trim_start().to_ascii_lowercase().as_str() {
+            "" | "b" => 1,
+            "kb" => 1_000,
+            "mb" => 1_000_000,
+            "gb" => 1_000_000_000,
+            "tb" => 1_000_000_000_000,
+            "kib" => 1 << 10,
+            "mib" => 1 << 20,
+            "gib" => 1 << 30,
+            "tib" => 1 << 40,
+            _ => return Err(error("unknown unit")),
+        };
+
+        number
+            .scale(multiplier)
+            .and_then(|bytes| u64::try_from(bytes).ok())
+            .map(Self)
+            .ok_or_else(|| error("value is too large"))
+    }
+}
+
+impl From<ByteSize> for u64 {
+    fn from(size: ByteSize) -> Self {
+        size.0
+    }
+}
+
+impl FromStr for ByteSize {
+    type Err = UnitParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s, "size")
+    }
+}
+
+impl TryFrom<&'_ str> for ByteSize {
+    type Error = UnitParseError;
+
+    fn try_from(str: &str) -> Result<Self, Self::Error> {
+        str.parse()
+    }
+}
+
+impl TryFrom<String> for ByteSize {
+    type Error = UnitParseError;
+
+    fn try_from(string: String) -> Result<Self, Self::Error> {
+        string.parse()
+    }
+}
+
+/// A transfer speed in bytes per second parsed from a human-friendly string,
+/// such as `5MiB/s` or `100 kB/s`.
+///
+/// A speed is a size as accepted by [`ByteSize`] followed by `/s`.
+///
+/// This is useful for reading options such as
+/// [`Configurable::max_download_speed`](super::Configurable::max_download_speed)
+/// from configuration files or environment variables.
+///
+/// # Examples
+///
+/// ```
+/// use isahc::{config::{ByteRate, Configurable}, HttpClient};
+///
+/// let speed: ByteRate = "5MiB/s".parse()?;
+/// assert_eq!(speed.bytes_per_second(), 5 * 1024 * 1024);
+///
+/// let client = HttpClient::builder()
+///     .max_download_speed(speed.into())
+///     .build()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ByteRate(u64);
+
+impl ByteRate {
+    /// Get the parsed number of bytes per second.
+    pub fn bytes_per_second(&self) -> u64 {
+        self.0
+    }
+}
+
+impl From<ByteRate> for u64 {
+    fn from(rate: ByteRate) -> Self {
+        rate.0
+    }
+}
+
+impl FromStr for ByteRate {
+    type Err = UnitParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim_end().strip_suffix("/s") {
+            Some(size) => ByteSize::parse(size, "speed").map(|size| Self(size.0)),
+            None => Err(UnitParseError::new("speed", s, "expected a unit ending in `/s`")),
+        }
+    }
+}
+
+impl TryFrom<&'_ str> for ByteRate {
+    type Error = UnitParseError;
+
+    fn try_from(str: &str) -> Result<Self, Self::Error> {
+        str.parse()
+    }
+}
+
+impl TryFrom<String> for ByteRate {
+    type Error = UnitParseError;
+
+    fn try_from(string: String) -> Result<Self, Self::Error> {
+        string.parse()
+    }
+}
+
+/// A non-negative decimal number, kept exact so that multiplying it by a unit
+/// does not lose precision.
+struct Number<'a> {
+    integer: &'a str,
+    fraction: &'a str,
+}
+
+impl Number<'_> {
+    /// Multiply by the given unit, discarding any fractional part of the
+    /// result. Returns `None` on overflow.
+    fn scale(&self, unit: u128) -> Option<u128> {
+        let integer = parse_digits(self.integer)?.checked_mul(unit)?;
+
+        // Digits beyond what a u128 can hold cannot affect the result for
+        // any unit we support.
+        let fraction = &self.fraction[..self.fraction.len().min(30)];
+        let fraction = parse_digits(fraction)?
+            .checked_mul(unit)?
+            .checked_div(10u128.checked_pow(fraction.len() as u32)?)?;
+
+        integer.checked_add(fraction)
+    }
+}
+
+fn parse_digits(digits: &str) -> Option<u128> {
+    if digits.is_empty() {
+        Some(0)
+    } else {
+        digits.parse().ok()
+    }
+}
+
+/// Split a leading decimal number off of the given string, returning the
+/// number and whatever follows it.
+fn split_number(s: &str) -> Option<(Number<'_>, &str)> {
+    let integer_len = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (integer, rest) = s.split_at(integer_len);
+
+    let (fraction, rest) = match rest.strip_prefix('.') {
+        Some(rest) => {
+            let fraction_len = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+
+            // A decimal point must be followed by at least one digit.
+            if fraction_len == 0 {
+                return None;
+            }
+
+            rest.split_at(fraction_len)
+        }
+        None => ("", rest),
+    };
+
+    if integer.is_empty() && fraction.is_empty() {
+        return None;
+    }
+
+    Some((Number { integer, fraction }, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("30s", Duration::from_secs(30))]
+    #[test_case("500ms", Duration::from_millis(500))]
+    #[test_case("1.5h", Duration::from_secs(5400))]
+    #[test_case("1m 30s", Duration::from_secs(90))]
+    #[test_case("2min", Duration::from_secs(120))]
+    #[test_case(" 1d ", Duration::from_secs(86400))]
+    #[test_case("30 s", Duration::from_secs(30))]
+    #[test_case("1h30m15s", Duration::from_secs(5415))]
+    #[test_case("100us", Duration::from_micros(100))]
+    #[test_case("0.5ns", Duration::from_nanos(0))]
+    #[test_case(".25s", Duration::from_millis(250))]
+    fn parse_duration(input: &str, expected: Duration) {
+        assert_eq!(input.parse::<HumanDuration>().unwrap().as_duration(), expected);
+    }
+
+    #[test_case("", "expected a number")]
+    #[test_case("30", "missing unit")]
+    #[test_case("1m 30", "missing unit")]
+    #[test_case("30sec", "unknown unit")]
+    #[test_case("-1s", "expected a number")]
+    #[test_case("1.s", "expected a number")]
+    #[test_case("s", "expected a number")]
+    #[test_case("99999999999999999999999d", "value is too large")]
+    fn parse_invalid_duration(input: &str, reason: &str) {
+        let error = input.parse::<HumanDuration>().unwrap_err();
+
+        assert_eq!(error.reason, reason);
+        assert!(error.to_string().starts_with("invalid duration"));
+    }
+
+    #[test_case("0", 0)]
+    #[test_case("512", 512)]
+    #[test_case("512B", 512)]
+    #[test_case("10 MB", 10_000_000)]
+    #[test_case("10mb", 10_000_000)]
+    #[test_case("1.5KiB", 1536)]
+    #[test_case("2 GiB", 2 << 30)]
+    #[test_case("1.0000001 kB", 1000)]
+    fn parse_size(input: &str, expected: u64) {
+        assert_eq!(input.parse::<ByteSize>().unwrap().as_u64(), expected);
+    }
+
+    #[test_case("", "expected a number")]
+    #[test_case("MB", "expected a number")]
+    #[test_case("10 M", "unknown unit")]
+    #[test_case("10 MB extra", "unknown unit")]
+    #[test_case("20000000 TiB", "value is too large")]
+    fn parse_invalid_size(input: &str, reason: &str) {
+        assert_eq!(input.parse::<ByteSize>().unwrap_err().reason, reason);
+    }
+
+    #[test_case("5MiB/s", 5 << 20)]
+    #[test_case("100 kB/s", 100_000)]
+    #[test_case("64/s", 64)]
+    fn parse_rate(input: &str, expected: u64) {
+        assert_eq!(input.parse::<ByteRate>().unwrap().bytes_per_second(), expected);
+    }
+
+    #[test]
+    fn rate_requires_per_second_suffix() {
+        let error = "5MiB".parse::<ByteRate>().unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "invalid speed \"5MiB\": expected a unit ending in `/s`"
+        );
+    }
+}
@@ -0,0 +1,214 @@
+//! Tracking of the individual connection attempts curl makes for a transfer.
+//!
+//! When a host name resolves to more than one address, curl tries them using
+//! the "happy eyeballs" algorithm (RFC 8305): an IPv6 address is tried first,
+//! and if it does not connect quickly an IPv4 address is tried in parallel,
+//! with whichever connects first being used. Curl does not report attempts
+//! through its API, only through its verbose messages, so the attempts are
+//! reconstructed from those.
+
+use crate::config::{AgentEventKind, ConnectAttemptResult};
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Instant,
+};
+
+/// Connection attempts of a transfer that have begun but not finished.
+#[derive(Debug, Default)]
+pub(crate) struct ConnectAttempts {
+    pending: Vec<(SocketAddr, Instant)>,
+}
+
+impl ConnectAttempts {
+    /// Update the attempts from an informational message curl reported at the
+    /// given time, returning events for the attempts it finished.
+    pub(crate) fn on_message(&mut self, message: &str, now: Instant) -> Vec<AgentEventKind> {
+        let message = message.trim();
+
+        if let Some(addr) = parse_trying(message) {
+            self.pending.push((addr, now));
+            return Vec::new();
+        }
+
+        if let Some(addr) = parse_connected(message) {
+            // The other attempts made in parallel are given up on.
+            let mut events = self.finish_where(now, |a| a == addr, ConnectAttemptResult::Connected);
+            events.extend(self.finish_all(now, ConnectAttemptResult::Abandoned));
+            return events;
+        }
+
+        if let Some(addr) = parse_failed(message) {
+            return self.finish_where(now, |a| a == addr, ConnectAttemptResult::Failed);
+        }
+
+        // Reported once connecting has failed for good, without saying which
+        // attempts were still in progress.
+        if message.starts_with("Failed to connect to ") {
+            return self.finish_all(now, ConnectAttemptResult::Failed);
+        }
+
+        Vec::new()
+    }
+
+    /// Finish the attempts still in progress when the transfer completed,
+    /// successfully or not.
+    pub(crate) fn on_complete(&mut self, success: bool, now: Instant) -> Vec<AgentEventKind> {
+        self.finish_all(
+            now,
+            if success {
+                ConnectAttemptResult::Abandoned
+            } else {
+                ConnectAttemptResult::Failed
+            },
+        )
+    }
+
+    fn finish_where(
+        &mut self,
+        now: Instant,
+        mut predicate: impl FnMut(SocketAddr) -> bool,
+        result: ConnectAttemptResult,
+    ) -> Vec<AgentEventKind> {
+        let mut events = Vec::new();
+
+        self.pending.retain(|&(addr, started)| {
+            if predicate(addr) {
+                events.push(AgentEventKind::ConnectAttempt {
+                    addr,
+                    result,
+                    duration: now.saturating_duration_since(started),
+                });
+                false
+            } else {
+                true
+            }
+        });
+
+        events
+    }
+
+    fn finish_all(&mut self, now: Instant, result: ConnectAttemptResult) -> Vec<AgentEventKind> {
+        self.finish_where(now, |_| true, result)
+    }
+}
+
+/// Parse a message like `Trying [::1]:8080...`.
+fn parse_trying(message: &str) -> Option<SocketAddr> {
+    message
+        .strip_prefix("Trying ")?
+        .trim_end_matches('.')
+        .parse()
+        .ok()
+}
+
+/// Parse a message like `Connected to localhost (127.0.0.1) port 8080 (#0)`.
+fn parse_connected(message: &str) -> Option<SocketAddr> {
+    let rest = message.strip_prefix("Connected to ")?;
+    let (_, rest) = rest.split_once('(')?;
+    let (ip, rest) = rest.split_once(')')?;
+
+    Some(SocketAddr::new(ip.parse().ok()?, parse_port(rest)?))
+}
+
+/// Parse a message like `connect to ::1 port 8080 failed: Connection refused`,
+/// which newer versions of curl write as `connect to ::1 port 8080 from ::1
+/// port 40000 failed: Connection refused`.
+fn parse_failed(message: &str) -> Option<SocketAddr> {
+    let rest = message.strip_prefix("connect to ")?;
+
+    if !rest.contains(" failed") {
+        return None;
+    }
+
+    let (ip, rest) = rest.split_once(' ')?;
+    let ip = ip.parse::<IpAddr>().ok()?;
+
+    Some(SocketAddr::new(ip, parse_port(rest)?))
+}
+
+/// Parse the port number from text starting like ` port 8080`.
+fn parse_port(text: &str) -> Option<u16> {
+    text.trim_start()
+        .strip_prefix("port ")?
+        .split(|c: char| !c.is_ascii_digit())
+        .next()?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn attempt(addr: &str, result: ConnectAttemptResult, millis: u64) -> AgentEventKind {
+        AgentEventKind::ConnectAttempt {
+            addr: addr.parse().unwrap(),
+            result,
+            duration: Duration::from_millis(millis),
+        }
+    }
+
+    #[test]
+    fn parallel_attempt_is_abandoned() {
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        let mut attempts = ConnectAttempts::default();
+
+        assert!(attempts.on_message("  Trying [2001:db8::1]:443...\n", at(0)).is_empty());
+        assert!(attempts.on_message("  Trying 192.0.2.1:443...\n", at(200)).is_empty());
+
+        assert_eq!(
+            attempts.on_message("Connected to example.org (192.0.2.1) port 443 (#0)\n", at(250)),
+            [
+                attempt("192.0.2.1:443", ConnectAttemptResult::Connected, 50),
+                attempt("[2001:db8::1]:443", ConnectAttemptResult::Abandoned, 250),
+            ]
+        );
+        assert!(attempts.on_complete(true, at(300)).is_empty());
+    }
+
+    #[test]
+    fn failed_attempts() {
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        let mut attempts = ConnectAttempts::default();
+
+        attempts.on_message("Trying [::1]:8080...", at(0));
+        assert_eq!(
+            attempts.on_message(
+                "connect to ::1 port 8080 from ::1 port 40000 failed: Connection refused",
+                at(1),
+            ),
+            [attempt("[::1]:8080", ConnectAttemptResult::Failed, 1)]
+        );
+
+        attempts.on_message("Trying 127.0.0.1:8080...", at(1));
+        assert_eq!(
+            attempts.on_message("connect to 127.0.0.1 port 8080 failed: Connection refused", at(3)),
+            [attempt("127.0.0.1:8080", ConnectAttemptResult::Failed, 2)]
+        );
+    }
+
+    #[test]
+    fn attempts_in_progress_fail_with_transfer() {
+        let start = Instant::now();
+        let mut attempts = ConnectAttempts::default();
+
+        attempts.on_message("Trying 192.0.2.1:80...", start);
+
+        assert_eq!(
+            attempts.on_complete(false, start + Duration::from_secs(1)),
+            [attempt("192.0.2.1:80", ConnectAttemptResult::Failed, 1000)]
+        );
+    }
+
+    #[test]
+    fn unrelated_messages_are_ignored() {
+        let mut attempts = ConnectAttempts::default();
+        let message = "Connection #0 to host example.org left intact";
+
+        assert!(attempts.on_message(message, Instant::now()).is_empty());
+        assert!(attempts.pending.is_empty());
+    }
+}
@@ -0,0 +1,318 @@
+//! Parsing of the `Content-Disposition` response header.
+
+/// Maximum length of a sanitized file name in bytes, which is the limit on
+/// most common file systems.
+const MAX_FILENAME_LEN: usize = 255;
+
+/// Device names reserved by Windows, which cannot be used as file names even
+/// with an extension.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// A parsed `Content-Disposition` header, as described in [RFC
+/// 6266](https://tools.ietf.org/html/rfc6266).
+///
+/// Servers use this header to indicate whether a response should be
+/// displayed inline or downloaded as an attachment, and to suggest a file name
+/// to save it as. Both plain `filename` parameters and [RFC
+/// 5987](https://tools.ietf.org/html/rfc5987) encoded `filename*` parameters
+/// are supported, with the latter taking precedence if both are present.
+///
+/// The suggested file name comes from the server and should not be trusted, so
+/// [`filename`](ContentDisposition::filename) returns a sanitized version that
+/// is safe to use as the name of a file in a directory of your choosing.
+///
+/// # Examples
+///
+/// ```
+/// use isahc::ContentDisposition;
+///
+/// let disposition =
+///     ContentDisposition::parse("attachment; filename*=UTF-8''na%C3%AFve%20file.txt").unwrap();
+///
+/// assert!(disposition.is_attachment());
+/// assert_eq!(disposition.filename(), Some("naïve file.txt"));
+///
+/// // Attempts to escape the download directory are defused.
+/// let disposition =
+///     ContentDisposition::parse(r#"attachment; filename="../../.bashrc""#).unwrap();
+///
+/// assert_eq!(disposition.filename(), Some(".bashrc"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContentDisposition {
+    disposition_type: String,
+    filename: Option<String>,
+}
+
+impl ContentDisposition {
+    /// Parse the value of a `Content-Disposition` header.
+    ///
+    /// Returns `None` if the value does not start with a valid disposition
+    /// type. Malformed parameters are ignored.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = split_params(value);
+
+        let disposition_type = parts
+            .next()
+            .map(str::trim)
+            .filter(|t| !t.is_empty() && t.bytes().all(is_token_byte))?
+            .to_ascii_lowercase();
+
+        let mut filename = None;
+        let mut extended_filename = None;
+
+        for param in parts {
+            let (name, value) = match param.split_once('=') {
+                Some((name, value)) => (name.trim(), value.trim()),
+                None => continue,
+            };
+
+            if name.eq_ignore_ascii_case("filename") {
+                filename = Some(unquote(value));
+            } else if name.eq_ignore_ascii_case("filename*") {
+                extended_filename = decode_extended_value(value);
+            }
+        }
+
+        Some(Self {
+            disposition_type,
+            filename: extended_filename
+                .or(filename)
+                .and_then(|filename| sanitize_filename(&filename)),
+        })
+    }
+
+    /// Get the disposition type, such as `inline` or `attachment`. The type
+    /// is always returned in lowercase.
+    pub fn disposition_type(&self) -> &str {
+        &self.disposition_type
+    }
+
+    /// Returns true if the server asked for the response to be downloaded
+    /// and saved locally rather than being displayed.
+    pub fn is_attachment(&self) -> bool {
+        self.disposition_type == "attachment"
+    }
+
+    /// Get the sanitized file name suggested by the server, if any.
+    ///
+    /// The name is reduced to its final path component, characters that are
+    /// not allowed in file names on common platforms are replaced with `_`,
+    /// and names that would refer to the current or parent directory or to a
+    /// reserved device name are rejected or altered. Very long names are
+    /// truncated.
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+}
+
+fn is_token_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&b)
+}
+
+/// Split a header value on semicolons, ignoring any inside quoted strings.
+fn split_params(s: &str) -> impl Iterator<Item = &str> {
+    let mut rest = Some(s);
+
+    std::iter::from_fn(move || {
+        let s = rest?;
+        let mut quoted = false;
+        let mut escaped = false;
+
+        for (i, c) in s.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' if quoted => escaped = true,
+                '"' => quoted = !quoted,
+                ';' if !quoted => {
+                    rest = Some(&s[i + 1..]);
+                    return Some(&s[..i]);
+                }
+                _ => {}
+            }
+        }
+
+        rest = None;
+        Some(s)
+    })
+}
+
+/// Remove quotes and escapes from a parameter value if it is a quoted string.
+fn unquote(value: &str) -> String {
+    let inner = match value.strip_prefix('"') {
+        Some(inner) => inner.strip_suffix('"').unwrap_or(inner),
+        None => return value.to_owned(),
+    };
+
+    let mut unquoted = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.extend(chars.next()),
+            c => unquoted.push(c),
+        }
+    }
+
+    unquoted
+}
+
+/// Decode an extended parameter value of the form `charset'language'value`,
+/// as described in RFC 5987.
+fn decode_extended_value(value: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '\'');
+    let charset = parts.next()?;
+    let _language = parts.next()?;
+    let bytes = percent_decode(parts.next()?)?;
+
+    if charset.eq_ignore_ascii_case("utf-8") {
+        String::from_utf8(bytes).ok()
+    } else if charset.eq_ignore_ascii_case("iso-8859-1") {
+        // Every ISO-8859-1 byte has the same value as its Unicode code point.
+        Some(bytes.into_iter().map(char::from).collect())
+    } else {
+        None
+    }
+}
+
+fn percent_decode(s: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(s.len());
+    let mut bytes = s.bytes();
+
+    while let Some(b) = bytes.next() {
+        if b == b'%' {
+            let hex = [bytes.next()?, bytes.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            decoded.push(b);
+        }
+    }
+
+    Some(decoded)
+}
+
+/// Make a file name suggested by a server safe to use as a local file name.
+fn sanitize_filename(filename: &str) -> Option<String> {
+    // Only keep the last path component, using both Unix and Windows
+    // separators.
+    let name = filename.rsplit(&['/', '\\'][..]).next()?;
+
+    let mut name: String = name
+        .chars()
+        .map(|c| match c {
+            c if c.is_control() => '_',
+            '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+            c => c,
+        })
+        .collect();
+
+    // Windows silently strips trailing dots and spaces.
+    let trimmed_len = name.trim_end_matches(&['.', ' '][..]).len();
+    name.truncate(trimmed_len);
+
+    let name = name.trim_start();
+
+    if name.is_empty() || name == "." || name == ".." {
+        return None;
+    }
+
+    let stem = name.split('.').next().unwrap_or(name);
+
+    let mut name = if RESERVED_NAMES
+        .iter()
+        .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+    {
+        format!("_{}", name)
+    } else {
+        name.to_owned()
+    };
+
+    if name.len() > MAX_FILENAME_LEN {
+        let mut len = MAX_FILENAME_LEN;
+
+        while !name.is_char_boundary(len) {
+            len -= 1;
+        }
+
+        name.truncate(len);
+    }
+
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn filename(header: &str) -> Option<String> {
+        ContentDisposition::parse(header)
+            .unwrap()
+            .filename()
+            .map(String::from)
+    }
+
+    #[test]
+    fn parse_disposition_type() {
+        let disposition = ContentDisposition::parse("Attachment").unwrap();
+
+        assert_eq!(disposition.disposition_type(), "attachment");
+        assert!(disposition.is_attachment());
+        assert_eq!(disposition.filename(), None);
+
+        let disposition = ContentDisposition::parse("inline; filename=foo.html").unwrap();
+
+        assert!(!disposition.is_attachment());
+        assert_eq!(disposition.filename(), Some("foo.html"));
+    }
+
+    #[test_case("")]
+    #[test_case("; filename=foo")]
+    #[test_case("attach ment")]
+    fn parse_invalid(header: &str) {
+        assert_eq!(ContentDisposition::parse(header), None);
+    }
+
+    #[test_case(r#"attachment; filename="foo.txt""#, "foo.txt")]
+    #[test_case(r#"attachment; filename="semi;colon.txt"; size=3"#, "semi;colon.txt")]
+    #[test_case(r#"attachment; filename="say \"hi\".txt""#, "say _hi_.txt")]
+    #[test_case("attachment; FILENAME=plain.txt", "plain.txt")]
+    #[test_case("attachment; filename*=UTF-8''%E2%82%AC%20rates.pdf", "€ rates.pdf")]
+    #[test_case("attachment; filename*=iso-8859-1'en'%A3%20rates.pdf", "£ rates.pdf")]
+    #[test_case(
+        r#"attachment; filename*=UTF-8''fancy.txt; filename="fallback.txt""#,
+        "fancy.txt"
+    )]
+    #[test_case(
+        r#"attachment; filename="fallback.txt"; filename*=unknown''fancy.txt"#,
+        "fallback.txt"
+    )]
+    fn parse_filename(header: &str, expected: &str) {
+        assert_eq!(filename(header).as_deref(), Some(expected));
+    }
+
+    #[test_case(r#"attachment; filename="../../etc/passwd""#, Some("passwd"))]
+    #[test_case(r#"attachment; filename="..\\..\\boot.ini""#, Some("boot.ini"))]
+    #[test_case("attachment; filename*=UTF-8''..%2F..%2Fevil.sh", Some("evil.sh"))]
+    #[test_case(r#"attachment; filename="..""#, None)]
+    #[test_case(r#"attachment; filename="dir/""#, None)]
+    #[test_case(r#"attachment; filename="  trailing. . ""#, Some("trailing"))]
+    #[test_case("attachment; filename*=UTF-8''bell%07.txt", Some("bell_.txt"))]
+    #[test_case("attachment; filename=con.txt", Some("_con.txt"))]
+    fn sanitize(header: &str, expected: Option<&str>) {
+        assert_eq!(filename(header).as_deref(), expected);
+    }
+
+    #[test]
+    fn long_filenames_are_truncated() {
+        let header = format!("attachment; filename={}", "é".repeat(200));
+        let name = filename(&header).unwrap();
+
+        assert_eq!(name.len(), 254);
+        assert!(name.chars().all(|c| c == 'é'));
+    }
+}
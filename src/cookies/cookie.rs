@@ -259,6 +259,11 @@ impl Cookie {
         self.secure
     }
 
+    /// Get the time when this cookie expires, if it is not a session cookie.
+    pub(crate) fn expiration(&self) -> Option<SystemTime> {
+        self.expiration
+    }
+
     /// Get whether this cookie should be persisted across sessions.
     #[inline]
     #[allow(unused)]
@@ -4,6 +4,7 @@
 use super::{Cookie, CookieJar};
 use crate::{
     body::AsyncBody,
+    config::request::RequestConfig,
     error::Error,
     interceptor::{Context, Interceptor, InterceptorFuture},
     response::ResponseExt,
@@ -70,6 +71,11 @@ impl Interceptor for CookieInterceptor {
             }
 
             let request_uri = request.uri().clone();
+            let shared_public_suffix_list = request
+                .extensions()
+                .get::<RequestConfig>()
+                .and_then(|config| config.environment.as_ref())
+                .map_or(true, |environment| environment.uses_shared_public_suffix_list());
             let mut response = ctx.send(request).await?;
 
             if let Some(jar) = jar {
@@ -95,7 +101,7 @@ impl Interceptor for CookieInterceptor {
                         });
 
                     for cookie in cookies {
-                        let _ = jar.set(cookie, request_uri);
+                        let _ = jar.set_checked(cookie, request_uri, shared_public_suffix_list);
                     }
                 }
 
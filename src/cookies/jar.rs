@@ -119,6 +119,32 @@ impl CookieJar {
         self.cookies.write().unwrap().clear();
     }
 
+    /// Get a copy of all unexpired cookies in the jar, along with their
+    /// domain-value and path-value.
+    pub(crate) fn entries(&self) -> Vec<(String, String, Cookie)> {
+        self.cookies
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|cookie| !cookie.cookie.is_expired())
+            .map(|c| (c.domain_value.clone(), c.path_value.clone(), c.cookie.clone()))
+            .collect()
+    }
+
+    /// Insert a cookie that was previously accepted by a jar, without
+    /// validating it against a request URI again.
+    pub(crate) fn insert_entry(&self, domain_value: String, path_value: String, cookie: Cookie) {
+        if cookie.is_expired() {
+            return;
+        }
+
+        self.cookies.write().unwrap().replace(CookieWithContext {
+            domain_value,
+            path_value,
+            cookie,
+        });
+    }
+
     /// Set a cookie for the given absolute request URI.
     ///
     /// If the cookie was set successfully, returns the cookie that previously existed for
@@ -130,6 +156,18 @@ impl CookieJar {
         &self,
         cookie: Cookie,
         request_uri: &Uri,
+    ) -> Result<Option<Cookie>, CookieRejectedError> {
+        self.set_checked(cookie, request_uri, true)
+    }
+
+    /// Set a cookie for the given absolute request URI, checking its domain
+    /// against either the shared or the bundled public suffix list.
+    #[cfg_attr(not(feature = "psl"), allow(unused_variables))]
+    pub(crate) fn set_checked(
+        &self,
+        cookie: Cookie,
+        request_uri: &Uri,
+        shared_public_suffix_list: bool,
     ) -> Result<Option<Cookie>, CookieRejectedError> {
         let request_host = if let Some(host) = request_uri.host() {
             host
@@ -178,7 +216,13 @@ impl CookieJar {
             // https://tools.ietf.org/html/rfc6265#section-5.3.5
             #[cfg(feature = "psl")]
             {
-                if super::psl::is_public_suffix(domain) {
+                let is_public_suffix = if shared_public_suffix_list {
+                    super::psl::is_public_suffix(domain)
+                } else {
+                    super::psl::is_bundled_public_suffix(domain)
+                };
+
+                if is_public_suffix {
                     tracing::warn!(
                         "cookie '{}' dropped, setting cookies for domain '{}' is not allowed",
                         cookie.name(),
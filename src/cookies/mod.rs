@@ -19,6 +19,12 @@
 //!
 //! The global default client instance does not have an assigned cookie jar.
 //!
+//! # Persistence
+//!
+//! Cookie jars can be saved to and loaded from files in the Netscape cookie
+//! file format used by curl and many browsers with
+//! [`CookieJar::write_netscape`] and [`CookieJar::read_netscape`].
+//!
 //! # Availability
 //!
 //! This module is only available when the [`cookies`](index.html#cookies)
@@ -27,6 +33,7 @@
 mod cookie;
 pub(crate) mod interceptor;
 mod jar;
+mod netscape;
 
 #[cfg(feature = "psl")]
 mod psl;
@@ -0,0 +1,207 @@
+//! Reading and writing cookie jars in the Netscape cookie file format, which
+//! is also used by curl and many browsers.
+//!
+//! Each line describes one cookie with seven tab-separated fields: domain,
+//! whether subdomains are included, path, whether the cookie is secure,
+//! expiration time in seconds since the Unix epoch (zero for session
+//! cookies), name, and value. Lines starting with `#` are comments, except
+//! for the `#HttpOnly_` prefix curl adds to the domain of HTTP-only cookies.
+
+use super::{Cookie, CookieJar};
+use std::{
+    io::{self, BufRead, Write},
+    time::{Duration, UNIX_EPOCH},
+};
+
+const HEADER: &str = "# Netscape HTTP Cookie File\n";
+const HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+impl CookieJar {
+    /// Add cookies read from a file in the Netscape cookie file format to this
+    /// jar, such as one written by curl's `--cookie-jar` option.
+    ///
+    /// Expired cookies and lines that are not valid cookies are skipped.
+    /// Cookies in the file replace any cookies in the jar with the same
+    /// domain, path, and name.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::cookies::CookieJar;
+    /// use std::{fs::File, io::BufReader};
+    ///
+    /// let jar = CookieJar::new();
+    /// jar.read_netscape(BufReader::new(File::open("cookies.txt")?))?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn read_netscape<R: BufRead>(&self, reader: R) -> io::Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+
+            if let Some((domain, path, cookie)) = parse_line(&line) {
+                self.insert_entry(domain, path, cookie);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write all unexpired cookies in this jar in the Netscape cookie file
+    /// format, which can be read back with [`CookieJar::read_netscape`] or by
+    /// curl's `--cookie` option.
+    ///
+    /// Session cookies are included with an expiration time of zero, like
+    /// curl does.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::cookies::CookieJar;
+    /// use std::fs::File;
+    ///
+    /// let jar = CookieJar::new();
+    /// jar.write_netscape(File::create("cookies.txt")?)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn write_netscape<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut entries = self.entries();
+
+        // Write cookies in a stable order so that saved files are easy to
+        // compare.
+        entries.sort_by(|a, b| (&a.0, &a.1, a.2.name()).cmp(&(&b.0, &b.1, b.2.name())));
+
+        writer.write_all(HEADER.as_bytes())?;
+
+        for (domain, path, cookie) in entries {
+            let expires = cookie
+                .expiration()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |duration| duration.as_secs());
+
+            let (domain, include_subdomains) = if cookie.domain().is_some() {
+                (format!(".{}", domain), "TRUE")
+            } else {
+                (domain, "FALSE")
+            };
+
+            writeln!(
+                writer,
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                domain,
+                include_subdomains,
+                path,
+                if cookie.is_secure() { "TRUE" } else { "FALSE" },
+                expires,
+                cookie.name(),
+                cookie.value(),
+            )?;
+        }
+
+        writer.flush()
+    }
+}
+
+/// Parse one line of a cookie file into the domain-value, path-value, and
+/// cookie it describes.
+fn parse_line(line: &str) -> Option<(String, String, Cookie)> {
+    let line = line.strip_prefix(HTTP_ONLY_PREFIX).unwrap_or(line);
+
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let mut fields = line.split('\t');
+    let domain = fields.next()?;
+    let include_subdomains = fields.next()?;
+    let path = fields.next()?;
+    let secure = fields.next()?;
+    let expires = fields.next()?.parse::<u64>().ok()?;
+    let name = fields.next()?;
+    let value = fields.next().unwrap_or("");
+
+    let domain = domain.trim_start_matches('.').to_lowercase();
+
+    if domain.is_empty() {
+        return None;
+    }
+
+    let mut builder = Cookie::builder(name, value)
+        .path(path)
+        .secure(secure.eq_ignore_ascii_case("TRUE"));
+
+    if include_subdomains.eq_ignore_ascii_case("TRUE") {
+        builder = builder.domain(domain.clone());
+    }
+
+    if expires > 0 {
+        builder = builder.expiration(UNIX_EPOCH + Duration::from_secs(expires));
+    }
+
+    let cookie = builder.build().ok()?;
+
+    if cookie.is_expired() {
+        return None;
+    }
+
+    Some((domain, path.to_owned(), cookie))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let jar = CookieJar::new();
+        let uri = "https://www.example.org/app/page".parse().unwrap();
+
+        jar.set(Cookie::parse("session=abc").unwrap(), &uri).unwrap();
+        jar.set(
+            Cookie::parse("id=42; Domain=example.org; Path=/; Secure; Max-Age=3600").unwrap(),
+            &uri,
+        )
+        .unwrap();
+
+        let mut file = Vec::new();
+        jar.write_netscape(&mut file).unwrap();
+
+        let loaded = CookieJar::new();
+        loaded.read_netscape(file.as_slice()).unwrap();
+
+        let uri = "https://www.example.org/app/other".parse().unwrap();
+        assert_eq!(loaded.get_by_name(&uri, "session").unwrap(), "abc");
+        assert_eq!(loaded.get_by_name(&uri, "id").unwrap(), "42");
+
+        // Only the domain cookie applies to other hosts.
+        let uri = "https://api.example.org/".parse().unwrap();
+        assert!(loaded.get_by_name(&uri, "session").is_none());
+        assert_eq!(loaded.get_by_name(&uri, "id").unwrap(), "42");
+
+        // Secure cookies are not sent over HTTP.
+        let uri = "http://api.example.org/".parse().unwrap();
+        assert!(loaded.get_by_name(&uri, "id").is_none());
+    }
+
+    #[test]
+    fn reads_file_written_by_curl() {
+        let file = "# Netscape HTTP Cookie File\n\
+                    # https://curl.se/docs/http-cookies.html\n\
+                    \n\
+                    .example.org\tTRUE\t/\tFALSE\t0\tdomain\tone\n\
+                    #HttpOnly_example.org\tFALSE\t/\tFALSE\t4102444800\thost\ttwo\n\
+                    example.org\tFALSE\t/\tFALSE\t1\texpired\tthree\n\
+                    not a cookie\n";
+
+        let jar = CookieJar::new();
+        jar.read_netscape(file.as_bytes()).unwrap();
+
+        let uri = "http://example.org/".parse().unwrap();
+        assert_eq!(jar.get_by_name(&uri, "domain").unwrap(), "one");
+        assert_eq!(jar.get_by_name(&uri, "host").unwrap(), "two");
+        assert!(jar.get_by_name(&uri, "expired").is_none());
+
+        let uri = "http://www.example.org/".parse().unwrap();
+        assert_eq!(jar.get_by_name(&uri, "domain").unwrap(), "one");
+        assert!(jar.get_by_name(&uri, "host").is_none());
+    }
+}
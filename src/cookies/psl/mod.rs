@@ -31,6 +31,16 @@ static TTL: Lazy<Duration> = Lazy::new(|| Duration::from_secs(24 * 60 * 60));
 /// Global in-memory PSL cache.
 static CACHE: Lazy<RwLock<ListCache>> = Lazy::new(Default::default);
 
+/// The bundled list, for clients that do not use the global cache.
+static BUNDLED: Lazy<List> = Lazy::new(bundled_list);
+
+/// Parse the bundled version of the list.
+fn bundled_list() -> List {
+    include_str!("list/public_suffix_list.dat")
+        .parse()
+        .expect("could not parse bundled public suffix list")
+}
+
 struct ListCache {
     list: List,
     last_refreshed: Option<SystemTime>,
@@ -45,9 +55,7 @@ impl Default for ListCache {
             // build, because that would force you to have an active Internet
             // connection in order to compile. And that would be really
             // annoying, especially if you are on a slow connection.
-            list: include_str!("list/public_suffix_list.dat")
-                .parse()
-                .expect("could not parse bundled public suffix list"),
+            list: bundled_list(),
 
             // Refresh the list right away.
             last_refreshed: None,
@@ -112,18 +120,24 @@ impl ListCache {
 /// If the current list information is stale, a background refresh will be
 /// triggered. The current data will be used to respond to this query.
 pub(crate) fn is_public_suffix(domain: impl AsRef<str>) -> bool {
-    let domain = domain.as_ref().as_bytes();
-
-    with_cache(|cache| {
-        // Check if the given domain is a public suffix.
-        cache
-            .list
-            .suffix(domain)
-            // We don't want to block unknown hosts like `localhost`
-            .filter(publicsuffix::Suffix::is_known)
-            .filter(|suffix| suffix == &domain)
-            .is_some()
-    })
+    with_cache(|cache| is_listed(&cache.list, domain.as_ref()))
+}
+
+/// Determine if the given domain is a public suffix according to the bundled
+/// list, without refreshing it.
+pub(crate) fn is_bundled_public_suffix(domain: impl AsRef<str>) -> bool {
+    is_listed(&BUNDLED, domain.as_ref())
+}
+
+fn is_listed(list: &List, domain: &str) -> bool {
+    let domain = domain.as_bytes();
+
+    // Check if the given domain is a public suffix.
+    list.suffix(domain)
+        // We don't want to block unknown hosts like `localhost`
+        .filter(publicsuffix::Suffix::is_known)
+        .filter(|suffix| suffix == &domain)
+        .is_some()
 }
 
 /// Execute a given closure with a reference to the list cache. If the list is
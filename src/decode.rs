@@ -0,0 +1,668 @@
+//! Adapters for decoding response bodies obtained outside of a client.
+//!
+//! Response bodies are normally decompressed by the client, and can be decoded
+//! into text with methods such as
+//! [`ReadResponseExt::text`](crate::ReadResponseExt::text). The adapters in
+//! this module expose the same steps over any [`Read`](std::io::Read) or
+//! [`AsyncRead`], so that bodies obtained elsewhere, such as from a cache or
+//! a file, can be processed consistently:
+//!
+//! - [`Decoder`] decompresses data in a content encoding such as `gzip`, if
+//!   the [`content-decoding`](../index.html#content-decoding) feature is
+//!   enabled.
+//! - [`Transcoder`] converts text in a character encoding such as
+//!   `iso-8859-1` to UTF-8.
+//!
+//! Adapters can be composed by wrapping one in the other.
+//!
+//! # Examples
+//!
+//! ```
+//! use isahc::decode::{Decoder, Transcoder};
+//! use std::io::Read;
+//!
+//! // A body in ISO-8859-1, stored without compression.
+//! let stored: &[u8] = b"caf\xe9";
+//!
+//! let mut text = String::new();
+//! Transcoder::for_content_type(
+//!     Decoder::new(stored, "identity").unwrap(),
+//!     Some("text/plain; charset=iso-8859-1"),
+//! )
+//! .read_to_string(&mut text)?;
+//!
+//! assert_eq!(text, "caf\u{e9}");
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use crate::{
+    config::{
+        decompression::DecompressionLimitExceeded,
+        CodingStream,
+        ContentCodings,
+        DecompressionLimit,
+    },
+    error::{Error, ErrorKind},
+};
+#[cfg(feature = "content-decoding")]
+use flate2::write::{DeflateDecoder, GzDecoder, ZlibDecoder};
+#[cfg(feature = "content-decoding")]
+use std::io::Write;
+use futures_lite::{io::AsyncRead, ready};
+use http::{header::CONTENT_ENCODING, HeaderMap};
+use std::{
+    fmt,
+    io::{self, Read},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Content encodings that can be decoded, as advertised in the
+/// `Accept-Encoding` header.
+#[cfg(feature = "content-decoding")]
+pub(crate) const ACCEPT_ENCODING: &str = "deflate, gzip";
+
+/// Content encodings that can be decoded, as advertised in the
+/// `Accept-Encoding` header.
+#[cfg(not(feature = "content-decoding"))]
+pub(crate) const ACCEPT_ENCODING: &str = "";
+
+/// Size of the buffer encoded data is read into.
+const BUFFER_SIZE: usize = 16 * 1024;
+
+/// A decoder for a single content encoding.
+pub(crate) enum Codec {
+    #[cfg(feature = "content-decoding")]
+    Gzip(GzDecoder<Vec<u8>>),
+    #[cfg(feature = "content-decoding")]
+    Zlib(ZlibDecoder<Vec<u8>>),
+    #[cfg(feature = "content-decoding")]
+    Deflate(DeflateDecoder<Vec<u8>>),
+
+    /// The `deflate` encoding, before any data has been seen.
+    #[cfg(feature = "content-decoding")]
+    UnknownDeflate,
+
+    /// The `identity` encoding, which leaves data as is.
+    Identity(Vec<u8>),
+
+    /// A registered custom coding, along with its output so far.
+    Custom(Box<dyn CodingStream>, Vec<u8>),
+}
+
+impl Codec {
+    /// Get a decoder for the given content encoding, or `None` if it is not
+    /// supported.
+    pub(crate) fn new(encoding: &str) -> Option<Self> {
+        #[cfg(feature = "content-decoding")]
+        {
+            if encoding.eq_ignore_ascii_case("gzip") || encoding.eq_ignore_ascii_case("x-gzip") {
+                return Some(Codec::Gzip(GzDecoder::new(Vec::new())));
+            }
+
+            if encoding.eq_ignore_ascii_case("deflate") {
+                return Some(Codec::UnknownDeflate);
+            }
+        }
+
+        if encoding.eq_ignore_ascii_case("identity") {
+            Some(Codec::Identity(Vec::new()))
+        } else {
+            None
+        }
+    }
+
+    /// Get a decoder for the given content encoding, preferring codings in
+    /// the given registry over the built-in ones.
+    pub(crate) fn with_codings(encoding: &str, codings: Option<&ContentCodings>) -> Option<Self> {
+        match codings.and_then(|codings| codings.get(encoding)) {
+            Some(coding) => Some(Codec::Custom(coding.decoder(), Vec::new())),
+            None => Self::new(encoding),
+        }
+    }
+
+    /// Wrap a stream that converts data in some other way, such as an
+    /// encoder.
+    pub(crate) fn from_stream(stream: Box<dyn CodingStream>) -> Self {
+        Codec::Custom(stream, Vec::new())
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        match self {
+            #[cfg(feature = "content-decoding")]
+            Codec::Gzip(decoder) => decoder.write_all(data),
+            #[cfg(feature = "content-decoding")]
+            Codec::Zlib(decoder) => decoder.write_all(data),
+            #[cfg(feature = "content-decoding")]
+            Codec::Deflate(decoder) => decoder.write_all(data),
+            Codec::Identity(output) => {
+                output.extend_from_slice(data);
+                Ok(())
+            }
+            Codec::Custom(stream, output) => stream.write(data, output),
+            #[cfg(feature = "content-decoding")]
+            Codec::UnknownDeflate => {
+                // The deflate encoding is supposed to be wrapped in the zlib
+                // format, but some servers send raw deflate data instead, which
+                // curl accepts as well. A zlib header always has 8 in its low
+                // nibble, which for raw data would mean a stored block with
+                // nonzero padding.
+                *self = match data.first() {
+                    Some(byte) if byte & 0x0f == 8 => Codec::Zlib(ZlibDecoder::new(Vec::new())),
+                    Some(_) => Codec::Deflate(DeflateDecoder::new(Vec::new())),
+                    None => return Ok(()),
+                };
+
+                self.write(data)
+            }
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self {
+            #[cfg(feature = "content-decoding")]
+            Codec::Gzip(decoder) => decoder.try_finish(),
+            #[cfg(feature = "content-decoding")]
+            Codec::Zlib(decoder) => decoder.try_finish(),
+            #[cfg(feature = "content-decoding")]
+            Codec::Deflate(decoder) => decoder.try_finish(),
+            Codec::Custom(stream, output) => stream.finish(output),
+            #[cfg(feature = "content-decoding")]
+            Codec::UnknownDeflate => Ok(()),
+            Codec::Identity(_) => Ok(()),
+        }
+    }
+
+    /// Replace the contents of `buf` with the data decoded so far.
+    fn take_output(&mut self, buf: &mut Vec<u8>) {
+        buf.clear();
+
+        match self {
+            #[cfg(feature = "content-decoding")]
+            Codec::Gzip(decoder) => std::mem::swap(buf, decoder.get_mut()),
+            #[cfg(feature = "content-decoding")]
+            Codec::Zlib(decoder) => std::mem::swap(buf, decoder.get_mut()),
+            #[cfg(feature = "content-decoding")]
+            Codec::Deflate(decoder) => std::mem::swap(buf, decoder.get_mut()),
+            Codec::Identity(output) | Codec::Custom(_, output) => std::mem::swap(buf, output),
+            #[cfg(feature = "content-decoding")]
+            Codec::UnknownDeflate => {}
+        }
+    }
+}
+
+/// A reader adapter that decompresses data in a content encoding.
+///
+/// The `gzip`, `deflate` and `identity` encodings are supported, which are
+/// the encodings the client itself decodes when it decides whether to decode
+/// a body based on its content type. Works with both [`Read`] and
+/// [`AsyncRead`] readers.
+///
+/// # Availability
+///
+/// The `gzip` and `deflate` encodings are only supported when the
+/// [`content-decoding`](../index.html#content-decoding) feature is enabled.
+/// Otherwise only `identity` is.
+pub struct Decoder<R> {
+    inner: R,
+    codec: Codec,
+    limit: Option<DecompressionLimit>,
+    buffer: Box<[u8]>,
+    output: Vec<u8>,
+    position: usize,
+    encoded_bytes: u64,
+    decoded_bytes: u64,
+    eof: bool,
+}
+
+impl<R> Decoder<R> {
+    /// Create a decoder for data in the given content encoding, such as
+    /// `gzip`, or `None` if the encoding is not supported.
+    pub fn new(inner: R, content_encoding: &str) -> Option<Self> {
+        Codec::new(content_encoding.trim()).map(|codec| Self::with_codec(inner, codec, None))
+    }
+
+    /// Create a decoder for a body with the given headers, using the encoding
+    /// in its `Content-Encoding` header, or `identity` if there is none.
+    /// Returns `None` if the encoding is not supported.
+    pub fn for_headers(inner: R, headers: &HeaderMap) -> Option<Self> {
+        match headers.get(CONTENT_ENCODING) {
+            Some(value) => Self::new(inner, value.to_str().ok()?),
+            None => Self::new(inner, "identity"),
+        }
+    }
+
+    /// Fail with an error of kind
+    /// [`ErrorKind::DecompressionLimitExceeded`] if the decoded data exceeds
+    /// the given limit, as a guard against decompression bombs.
+    #[must_use = "builders have no effect if unused"]
+    pub fn limit(mut self, limit: DecompressionLimit) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Get a reference to the reader of the encoded data.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Unwrap the reader of the encoded data.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    pub(crate) fn with_codec(inner: R, codec: Codec, limit: Option<DecompressionLimit>) -> Self {
+        Self {
+            inner,
+            codec,
+            limit,
+            buffer: vec![0; BUFFER_SIZE].into_boxed_slice(),
+            output: Vec::new(),
+            position: 0,
+            encoded_bytes: 0,
+            decoded_bytes: 0,
+            eof: false,
+        }
+    }
+
+    /// Copy decoded data into the given buffer, if there is any left or the
+    /// end has been reached.
+    fn copy_output(&mut self, buf: &mut [u8]) -> Option<usize> {
+        if self.position < self.output.len() {
+            let len = buf.len().min(self.output.len() - self.position);
+            buf[..len].copy_from_slice(&self.output[self.position..self.position + len]);
+            self.position += len;
+
+            Some(len)
+        } else if self.eof {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    /// Decode the given number of bytes read into the buffer, where zero
+    /// marks the end of the data.
+    fn decode(&mut self, len: usize) -> io::Result<()> {
+        let result = if len == 0 {
+            self.eof = true;
+
+            // Bodyless responses may still declare an encoding.
+            if self.encoded_bytes > 0 {
+                self.codec.finish()
+            } else {
+                Ok(())
+            }
+        } else {
+            self.encoded_bytes += len as u64;
+            self.codec.write(&self.buffer[..len])
+        };
+
+        result.map_err(|e| Error::new(ErrorKind::InvalidContentEncoding, e))?;
+
+        self.codec.take_output(&mut self.output);
+        self.position = 0;
+        self.decoded_bytes += self.output.len() as u64;
+
+        if let Some(limit) = self.limit.as_ref() {
+            limit
+                .check(self.encoded_bytes, self.decoded_bytes)
+                .map_err(|e: DecompressionLimitExceeded| {
+                    Error::new(ErrorKind::DecompressionLimitExceeded, e)
+                })?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if let Some(len) = self.copy_output(buf) {
+                return Ok(len);
+            }
+
+            let len = self.inner.read(&mut self.buffer)?;
+            self.decode(len)?;
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for Decoder<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+
+        loop {
+            if let Some(len) = this.copy_output(buf) {
+                return Poll::Ready(Ok(len));
+            }
+
+            let len = ready!(Pin::new(&mut this.inner).poll_read(cx, &mut this.buffer))?;
+            this.decode(len)?;
+        }
+    }
+}
+
+impl<R> fmt::Debug for Decoder<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Decoder")
+            .field("encoded_bytes", &self.encoded_bytes)
+            .field("decoded_bytes", &self.decoded_bytes)
+            .finish()
+    }
+}
+
+/// A reader adapter that converts text in a character encoding to UTF-8.
+///
+/// Character encodings are looked up by the labels defined by the [Encoding
+/// Standard](https://encoding.spec.whatwg.org), the same way the charset of a
+/// response is when decoding it as text. Malformed sequences are replaced with
+/// the replacement character. Works with both [`Read`] and [`AsyncRead`]
+/// readers.
+///
+/// # Availability
+///
+/// This type is only available when the
+/// [`text-decoding`](../index.html#text-decoding) feature is enabled.
+#[cfg(feature = "text-decoding")]
+pub struct Transcoder<R> {
+    inner: R,
+    decoder: encoding_rs::Decoder,
+    buffer: Box<[u8]>,
+    output: Vec<u8>,
+    position: usize,
+    eof: bool,
+}
+
+#[cfg(feature = "text-decoding")]
+impl<R> Transcoder<R> {
+    /// Create a transcoder for text in the character encoding with the given
+    /// label, such as `utf-8` or `iso-8859-1`, or `None` if the encoding is
+    /// not known.
+    pub fn for_label(inner: R, label: &str) -> Option<Self> {
+        encoding_rs::Encoding::for_label(label.trim().as_bytes())
+            .map(|encoding| Self::new(inner, encoding))
+    }
+
+    /// Create a transcoder for text with the given content type, using the
+    /// character encoding in its `charset` parameter.
+    ///
+    /// Like decoding a response as text, falls back to UTF-8 if there is no
+    /// content type, it has no `charset` parameter, or the encoding is not
+    /// known.
+    pub fn for_content_type(inner: R, content_type: Option<&str>) -> Self {
+        let charset = content_type
+            .and_then(|content_type| content_type.parse::<mime::Mime>().ok())
+            .and_then(|content_type| {
+                content_type
+                    .get_param(mime::CHARSET)
+                    .map(|charset| charset.to_string())
+            });
+
+        if let Some(charset) = charset {
+            match encoding_rs::Encoding::for_label(charset.as_bytes()) {
+                Some(encoding) => return Self::new(inner, encoding),
+                None => tracing::warn!("unknown encoding '{}', falling back to UTF-8", charset),
+            }
+        }
+
+        Self::new(inner, encoding_rs::UTF_8)
+    }
+
+    /// Get a reference to the reader of the original text.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Unwrap the reader of the original text.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn new(inner: R, encoding: &'static encoding_rs::Encoding) -> Self {
+        Self {
+            inner,
+            decoder: encoding.new_decoder(),
+            buffer: vec![0; BUFFER_SIZE].into_boxed_slice(),
+            output: Vec::new(),
+            position: 0,
+            eof: false,
+        }
+    }
+
+    /// Copy converted text into the given buffer, if there is any left or the
+    /// end has been reached.
+    fn copy_output(&mut self, buf: &mut [u8]) -> Option<usize> {
+        if self.position < self.output.len() {
+            let len = buf.len().min(self.output.len() - self.position);
+            buf[..len].copy_from_slice(&self.output[self.position..self.position + len]);
+            self.position += len;
+
+            Some(len)
+        } else if self.eof {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    /// Convert the given number of bytes read into the buffer, where zero
+    /// marks the end of the text.
+    fn transcode(&mut self, len: usize) {
+        let last = len == 0;
+        self.eof = last;
+
+        // Partial characters at the end of the input are kept by the decoder
+        // until the next call.
+        let max_len = self
+            .decoder
+            .max_utf8_buffer_length(len)
+            .expect("buffer length overflow");
+        self.output.resize(max_len, 0);
+
+        let (_, _, written, _) =
+            self.decoder
+                .decode_to_utf8(&self.buffer[..len], &mut self.output, last);
+
+        self.output.truncate(written);
+        self.position = 0;
+    }
+}
+
+#[cfg(feature = "text-decoding")]
+impl<R: Read> Read for Transcoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if let Some(len) = self.copy_output(buf) {
+                return Ok(len);
+            }
+
+            let len = self.inner.read(&mut self.buffer)?;
+            self.transcode(len);
+        }
+    }
+}
+
+#[cfg(feature = "text-decoding")]
+impl<R: AsyncRead + Unpin> AsyncRead for Transcoder<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+
+        loop {
+            if let Some(len) = this.copy_output(buf) {
+                return Poll::Ready(Ok(len));
+            }
+
+            let len = ready!(Pin::new(&mut this.inner).poll_read(cx, &mut this.buffer))?;
+            this.transcode(len);
+        }
+    }
+}
+
+#[cfg(feature = "text-decoding")]
+impl<R> fmt::Debug for Transcoder<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transcoder")
+            .field("encoding", &self.decoder.encoding().name())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "content-decoding")]
+    use flate2::{write::GzEncoder, Compression};
+    #[cfg(feature = "content-decoding")]
+    use futures_lite::future::block_on;
+
+    /// Reader that returns at most one byte at a time.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(self.0.len()).min(1);
+            buf[..len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+            Ok(len)
+        }
+    }
+
+    #[cfg(feature = "content-decoding")]
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[cfg(feature = "content-decoding")]
+    #[test]
+    fn decode_gzip_blocking() {
+        let encoded = gzip(b"hello world");
+        let mut decoded = Vec::new();
+
+        Decoder::new(encoded.as_slice(), "gzip")
+            .unwrap()
+            .read_to_end(&mut decoded)
+            .unwrap();
+
+        assert_eq!(decoded, b"hello world");
+    }
+
+    #[cfg(feature = "content-decoding")]
+    #[test]
+    fn decode_gzip_async() {
+        let encoded = gzip(b"hello world");
+        let mut decoded = Vec::new();
+
+        let mut decoder = Decoder::new(futures_lite::io::Cursor::new(encoded), "GZIP").unwrap();
+
+        block_on(futures_lite::AsyncReadExt::read_to_end(&mut decoder, &mut decoded)).unwrap();
+
+        assert_eq!(decoded, b"hello world");
+    }
+
+    #[test]
+    fn decoder_for_headers() {
+        let mut headers = HeaderMap::new();
+        let mut decoded = Vec::new();
+
+        Decoder::for_headers(&b"plain"[..], &headers)
+            .unwrap()
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, b"plain");
+
+        headers.insert(CONTENT_ENCODING, "br".parse().unwrap());
+        assert!(Decoder::for_headers(&b""[..], &headers).is_none());
+    }
+
+    #[cfg(feature = "content-decoding")]
+    #[test]
+    fn decode_invalid_data() {
+        let error = Decoder::new(&b"not gzip"[..], "gzip")
+            .unwrap()
+            .read_to_end(&mut Vec::new())
+            .unwrap_err();
+
+        assert_eq!(
+            Error::from(error).kind(),
+            &ErrorKind::InvalidContentEncoding
+        );
+    }
+
+    #[cfg(feature = "content-decoding")]
+    #[test]
+    fn decode_over_limit() {
+        let encoded = gzip(&[0; 64 * 1024]);
+        let error = Decoder::new(encoded.as_slice(), "gzip")
+            .unwrap()
+            .limit(DecompressionLimit::new().max_size(1024))
+            .read_to_end(&mut Vec::new())
+            .unwrap_err();
+
+        assert_eq!(
+            Error::from(error).kind(),
+            &ErrorKind::DecompressionLimitExceeded
+        );
+    }
+
+    #[cfg(feature = "text-decoding")]
+    #[test]
+    fn transcode_split_characters() {
+        // "né" in UTF-16LE, read one byte at a time.
+        let mut text = String::new();
+
+        Transcoder::for_label(Trickle(&[0x6e, 0x00, 0xe9, 0x00]), "utf-16le")
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+
+        assert_eq!(text, "n\u{e9}");
+    }
+
+    #[cfg(feature = "text-decoding")]
+    #[test]
+    fn transcode_content_type() {
+        let mut text = String::new();
+
+        Transcoder::for_content_type(&b"\xe9"[..], Some("text/plain; charset=latin1"))
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "\u{e9}");
+
+        text.clear();
+        Transcoder::for_content_type(&b"\xe9"[..], Some("text/plain; charset=bogus"))
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "\u{fffd}");
+
+        assert!(Transcoder::for_label(&b""[..], "bogus").is_none());
+    }
+
+    #[cfg(all(feature = "content-decoding", feature = "text-decoding"))]
+    #[test]
+    fn transcode_decoded_body_async() {
+        let encoded = gzip(b"caf\xe9");
+        let mut text = String::new();
+
+        let mut transcoder = Transcoder::for_label(
+            Decoder::new(futures_lite::io::Cursor::new(encoded), "gzip").unwrap(),
+            "windows-1252",
+        )
+        .unwrap();
+
+        block_on(futures_lite::AsyncReadExt::read_to_string(&mut transcoder, &mut text)).unwrap();
+
+        assert_eq!(text, "caf\u{e9}");
+    }
+}
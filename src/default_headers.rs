@@ -1,5 +1,6 @@
 use crate::{
     body::AsyncBody,
+    config::request::RequestConfig,
     error::Error,
     interceptor::{Context, Interceptor, InterceptorFuture},
 };
@@ -31,8 +32,14 @@ impl Interceptor for DefaultHeadersInterceptor {
             // We are checking here if header already contains the key, simply
             // ignore it. In case the key wasn't present in parts.headers ensure
             // that we have all the headers from default headers.
+            let omitted = request
+                .extensions()
+                .get::<RequestConfig>()
+                .and_then(|config| config.omitted_headers.clone())
+                .unwrap_or_default();
+
             for name in self.headers.keys() {
-                if !request.headers().contains_key(name) {
+                if !request.headers().contains_key(name) && !omitted.contains(name) {
                     for v in self.headers.get_all(name).iter() {
                         request.headers_mut().append(name, v.clone());
                     }
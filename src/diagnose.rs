@@ -0,0 +1,293 @@
+//! Troubleshooting connections to a server step by step.
+//!
+//! When a request fails, the error usually describes the last thing that went
+//! wrong, which is not always helpful for telling a user what to fix. The
+//! [`HttpClient::diagnose`](crate::HttpClient::diagnose) method instead checks
+//! each step of reaching a server separately, and reports which one failed and
+//! why. This is meant for building "connection troubleshooter" features into
+//! applications.
+//!
+//! # Availability
+//!
+//! This module is only available when the [`blocking`](../index.html#blocking)
+//! feature is enabled.
+
+use crate::{
+    config::Configurable,
+    error::{Error, ErrorKind},
+    HttpClient,
+    ResponseExt,
+};
+use http::{Request, StatusCode, Uri};
+use std::{
+    fmt,
+    io,
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+/// How long to wait for the TCP probe to connect if the client does not have a
+/// connect timeout configured.
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A step of reaching a server that is checked by a [`Diagnosis`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum Stage {
+    /// Resolving the host name of the server to one or more addresses.
+    Dns,
+
+    /// Opening a TCP connection to one of the resolved addresses.
+    Tcp,
+
+    /// Establishing a TLS session with the server. Only checked for `https`
+    /// URIs.
+    Tls,
+
+    /// Sending an HTTP request and receiving a response.
+    Http,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Dns => "DNS",
+            Self::Tcp => "TCP",
+            Self::Tls => "TLS",
+            Self::Http => "HTTP",
+        })
+    }
+}
+
+/// The result of checking a single [`Stage`].
+#[derive(Debug)]
+pub struct Probe {
+    stage: Stage,
+    elapsed: Duration,
+    error: Option<Error>,
+}
+
+impl Probe {
+    /// Get the stage that was checked.
+    pub fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    /// Get how long the check took.
+    ///
+    /// For a successful TLS stage this is the duration of the handshake as
+    /// measured by curl, which is zero if it is unknown.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns true if the stage succeeded.
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Get the reason the stage failed, if it did.
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+}
+
+/// A report of which steps of reaching a server succeeded, returned by
+/// [`HttpClient::diagnose`](crate::HttpClient::diagnose).
+///
+/// The stages are checked in order, and checking stops at the first stage that
+/// fails, since the later stages depend on it. The report therefore contains a
+/// probe for every stage up to and including the first failed one.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::HttpClient;
+///
+/// let diagnosis = HttpClient::new()?.diagnose("https://example.org")?;
+///
+/// for probe in diagnosis.probes() {
+///     match probe.error() {
+///         None => println!("{}: ok ({:?})", probe.stage(), probe.elapsed()),
+///         Some(e) => println!("{}: failed: {}", probe.stage(), e),
+///     }
+/// }
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct Diagnosis {
+    probes: Vec<Probe>,
+    addrs: Vec<SocketAddr>,
+    status: Option<StatusCode>,
+}
+
+impl Diagnosis {
+    /// Get the probes of the stages that were checked, in order.
+    pub fn probes(&self) -> &[Probe] {
+        &self.probes
+    }
+
+    /// Get the probe of the given stage, if it was checked.
+    pub fn probe(&self, stage: Stage) -> Option<&Probe> {
+        self.probes.iter().find(|probe| probe.stage == stage)
+    }
+
+    /// Get the stage that failed, or `None` if every stage succeeded.
+    pub fn failed_stage(&self) -> Option<Stage> {
+        self.probes
+            .iter()
+            .find(|probe| !probe.is_ok())
+            .map(Probe::stage)
+    }
+
+    /// Returns true if every stage succeeded.
+    pub fn is_ok(&self) -> bool {
+        self.failed_stage().is_none()
+    }
+
+    /// Get the addresses the host name resolved to.
+    pub fn addrs(&self) -> &[SocketAddr] {
+        &self.addrs
+    }
+
+    /// Get the status code of the response received by the HTTP probe, if it
+    /// succeeded.
+    ///
+    /// Any response counts as a success for the HTTP stage, including error
+    /// responses, since they show that the server can be reached.
+    pub fn status(&self) -> Option<StatusCode> {
+        self.status
+    }
+
+    fn push(&mut self, stage: Stage, started: Instant, error: Option<Error>) -> bool {
+        let ok = error.is_none();
+
+        self.probes.push(Probe {
+            stage,
+            elapsed: started.elapsed(),
+            error,
+        });
+
+        ok
+    }
+}
+
+pub(crate) fn diagnose(
+    client: &HttpClient,
+    uri: Uri,
+    connect_timeout: Option<Duration>,
+) -> Result<Diagnosis, Error> {
+    let invalid_uri = || {
+        Error::new(
+            ErrorKind::InvalidRequest,
+            io::Error::new(io::ErrorKind::InvalidInput, "URI must be an absolute HTTP(S) URI"),
+        )
+    };
+
+    let is_https = match uri.scheme_str() {
+        Some("https") => true,
+        Some("http") => false,
+        _ => return Err(invalid_uri()),
+    };
+    let host = uri.host().ok_or_else(invalid_uri)?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let port = uri.port_u16().unwrap_or(if is_https { 443 } else { 80 });
+
+    let mut diagnosis = Diagnosis {
+        probes: Vec::new(),
+        addrs: Vec::new(),
+        status: None,
+    };
+
+    let started = Instant::now();
+    let error = match (host, port).to_socket_addrs() {
+        Ok(addrs) => {
+            diagnosis.addrs = addrs.collect();
+
+            if diagnosis.addrs.is_empty() {
+                Some(Error::new(
+                    ErrorKind::NameResolution,
+                    io::Error::new(io::ErrorKind::NotFound, "host name has no addresses"),
+                ))
+            } else {
+                None
+            }
+        }
+        Err(e) => Some(Error::new(ErrorKind::NameResolution, e)),
+    };
+
+    if !diagnosis.push(Stage::Dns, started, error) {
+        return Ok(diagnosis);
+    }
+
+    let connect_timeout = connect_timeout.unwrap_or(DEFAULT_CONNECT_TIMEOUT);
+
+    let started = Instant::now();
+    let mut error = None;
+
+    for addr in &diagnosis.addrs {
+        match TcpStream::connect_timeout(addr, connect_timeout) {
+            Ok(_) => {
+                error = None;
+                break;
+            }
+            Err(e) => {
+                let kind = if e.kind() == io::ErrorKind::TimedOut {
+                    ErrorKind::Timeout
+                } else {
+                    ErrorKind::ConnectionFailed
+                };
+
+                error = Some(Error::new(kind, e).with_remote_addr(*addr));
+            }
+        }
+    }
+
+    if !diagnosis.push(Stage::Tcp, started, error) {
+        return Ok(diagnosis);
+    }
+
+    // TLS is checked as part of sending a real request, since curl does not
+    // offer a way to only perform the handshake.
+    let started = Instant::now();
+    let request = Request::head(uri).metrics(true).body(())?;
+
+    match client.send(request) {
+        Ok(response) => {
+            let metrics = response.metrics();
+
+            if is_https {
+                diagnosis.probes.push(Probe {
+                    stage: Stage::Tls,
+                    elapsed: metrics.map(|m| m.secure_connect_time()).unwrap_or_default(),
+                    error: None,
+                });
+            }
+
+            diagnosis.probes.push(Probe {
+                stage: Stage::Http,
+                elapsed: metrics
+                    .map(|m| m.total_time())
+                    .unwrap_or_else(|| started.elapsed()),
+                error: None,
+            });
+            diagnosis.status = Some(response.status());
+        }
+        Err(e) if is_https && (e.is_tls() || e.tls_error_kind().is_some()) => {
+            diagnosis.push(Stage::Tls, started, Some(e));
+        }
+        Err(e) => {
+            if is_https {
+                diagnosis.probes.push(Probe {
+                    stage: Stage::Tls,
+                    elapsed: Duration::default(),
+                    error: None,
+                });
+            }
+
+            diagnosis.push(Stage::Http, started, Some(e));
+        }
+    }
+
+    Ok(diagnosis)
+}
@@ -0,0 +1,217 @@
+//! Negative caching of host name resolution failures.
+
+use crate::{
+    body::AsyncBody,
+    config::request::RequestConfig,
+    error::{Error, ErrorKind},
+    interceptor::{Context, Interceptor, InterceptorFuture},
+};
+use http::Request;
+use std::{
+    collections::HashMap,
+    error::Error as StdError,
+    fmt,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// A shared cache of host names that recently failed to resolve.
+#[derive(Clone)]
+pub(crate) struct DnsNegativeCache {
+    ttl: Duration,
+    /// Cached failures and the time at which they expire.
+    entries: Arc<Mutex<HashMap<String, (Instant, ErrorKind)>>>,
+}
+
+impl DnsNegativeCache {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Default::default(),
+        }
+    }
+
+    /// Get the kind of error a host failed with, if it failed recently.
+    fn get(&self, host: &str, now: Instant) -> Option<(ErrorKind, Duration)> {
+        let mut entries = self.entries.lock().unwrap();
+        let (expires_at, kind) = entries.get(host)?.clone();
+        let remaining = expires_at.saturating_duration_since(now);
+
+        if remaining.is_zero() {
+            entries.remove(host);
+            return None;
+        }
+
+        Some((kind, remaining))
+    }
+
+    fn insert(&self, host: String, kind: ErrorKind, now: Instant) {
+        self.insert_for(host, kind, self.ttl, now);
+    }
+
+    /// Cache a failure for the given amount of time, capped to the TTL of
+    /// this cache.
+    pub(crate) fn insert_for(
+        &self,
+        host: String,
+        kind: ErrorKind,
+        remaining: Duration,
+        now: Instant,
+    ) {
+        let mut entries = self.entries.lock().unwrap();
+
+        entries.retain(|_, (expires_at, _)| *expires_at > now);
+        entries.insert(host, (now + remaining.min(self.ttl), kind));
+    }
+
+    /// Get all unexpired failures along with the time remaining until they
+    /// expire.
+    #[cfg(feature = "state")]
+    pub(crate) fn entries(&self, now: Instant) -> Vec<(String, ErrorKind, Duration)> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, (expires_at, _))| *expires_at > now)
+            .map(|(host, (expires_at, kind))| {
+                (host.clone(), kind.clone(), expires_at.duration_since(now))
+            })
+            .collect()
+    }
+}
+
+impl fmt::Debug for DnsNegativeCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DnsNegativeCache")
+            .field("ttl", &self.ttl)
+            .finish()
+    }
+}
+
+/// Source of errors for requests that fail due to a negative cache entry.
+#[derive(Debug)]
+struct CachedFailure {
+    host: String,
+    remaining: Duration,
+}
+
+impl fmt::Display for CachedFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "resolving `{}` failed recently, not retrying for another {:?}",
+            self.host, self.remaining
+        )
+    }
+}
+
+impl StdError for CachedFailure {}
+
+/// Interceptor that fails requests immediately when the host recently failed
+/// to resolve, and remembers hosts that fail to resolve.
+pub(crate) struct DnsNegativeCacheInterceptor;
+
+impl Interceptor for DnsNegativeCacheInterceptor {
+    type Err = Error;
+
+    fn intercept<'a>(
+        &'a self,
+        request: Request<AsyncBody>,
+        ctx: Context<'a>,
+    ) -> InterceptorFuture<'a, Self::Err> {
+        Box::pin(async move {
+            let config = request.extensions().get::<RequestConfig>();
+            let cache = config.and_then(|config| config.dns_negative_cache.clone());
+            let clock = config
+                .and_then(|config| config.clock.clone())
+                .unwrap_or_default();
+
+            // IP addresses are never resolved and so are never cached.
+            let host = request
+                .uri()
+                .host()
+                .map(|host| host.trim_start_matches('[').trim_end_matches(']'))
+                .filter(|host| host.parse::<IpAddr>().is_err())
+                .map(str::to_ascii_lowercase);
+
+            let (cache, host) = match (cache, host) {
+                (Some(cache), Some(host)) => (cache, host),
+                _ => return ctx.send(request).await,
+            };
+
+            if let Some((kind, remaining)) = cache.get(&host, clock.now()) {
+                tracing::debug!(
+                    host = host.as_str(),
+                    ?remaining,
+                    "failing request using negative DNS cache entry"
+                );
+
+                return Err(Error::new(kind, CachedFailure { host, remaining }));
+            }
+
+            let result = ctx.send(request).await;
+
+            if let Err(e) = result.as_ref() {
+                // A timeout before any address was connected to most likely
+                // happened while waiting for the resolver.
+                let resolve_failed = match e.kind() {
+                    ErrorKind::NameResolution => true,
+                    ErrorKind::Timeout => e.remote_addr().is_none(),
+                    _ => false,
+                };
+
+                if resolve_failed {
+                    tracing::debug!(
+                        host = host.as_str(),
+                        ttl = ?cache.ttl,
+                        "adding negative DNS cache entry"
+                    );
+
+                    cache.insert(host, e.kind().clone(), clock.now());
+                }
+            }
+
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_expire() {
+        let cache = DnsNegativeCache::new(Duration::from_millis(50));
+        let now = Instant::now();
+
+        cache.insert("example.invalid".into(), ErrorKind::NameResolution, now);
+
+        let (kind, remaining) = cache.get("example.invalid", now).unwrap();
+        assert_eq!(kind, ErrorKind::NameResolution);
+        assert_eq!(remaining, Duration::from_millis(50));
+        assert!(cache.get("other.invalid", now).is_none());
+
+        assert!(cache
+            .get("example.invalid", now + Duration::from_millis(50))
+            .is_none());
+    }
+
+    #[test]
+    fn insert_for_is_capped_to_ttl() {
+        let cache = DnsNegativeCache::new(Duration::from_secs(5));
+        let now = Instant::now();
+
+        cache.insert_for(
+            "example.invalid".into(),
+            ErrorKind::Timeout,
+            Duration::from_secs(60),
+            now,
+        );
+
+        let (kind, remaining) = cache.get("example.invalid", now).unwrap();
+        assert_eq!(kind, ErrorKind::Timeout);
+        assert_eq!(remaining, Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,456 @@
+//! Helpers for talking to the [Docker Engine
+//! API](https://docs.docker.com/engine/api/).
+//!
+//! The Docker daemon usually listens on a Unix socket, and some of its
+//! endpoints, such as attaching to a container or starting an exec instance,
+//! take over the connection to stream the standard input and output of a
+//! process. A [`Docker`] connects to the daemon, prefixes request paths with
+//! the API version, and switches protocols for these endpoints. [`Frames`]
+//! splits the output streamed by them into standard output and standard
+//! error.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use isahc::{docker::{Docker, Frames, StreamType}, prelude::*};
+//! use std::io::Write;
+//!
+//! let docker = Docker::new()?.version("1.43");
+//!
+//! println!("{}", docker.get("/containers/json")?.text()?);
+//!
+//! let mut connection = docker.attach("my-container")?;
+//! connection.write_all(b"echo hello\n")?;
+//!
+//! for frame in Frames::new(&mut connection) {
+//!     let frame = frame?;
+//!
+//!     if frame.stream() == StreamType::Stdout {
+//!         std::io::stdout().write_all(frame.data())?;
+//!     }
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! # Availability
+//!
+//! This module is only available when the [`docker`](../index.html#docker)
+//! feature is enabled.
+
+use crate::{
+    config::Environment,
+    error::{Error, ErrorKind},
+    Body,
+    HttpClient,
+    ReadResponseExt,
+    ResponseExt,
+    Upgraded,
+};
+use http::{
+    header::{CONNECTION, CONTENT_TYPE, UPGRADE},
+    Request,
+    Response,
+    Uri,
+};
+use std::{
+    convert::TryFrom,
+    fmt,
+    io::{self, Read},
+};
+
+/// Path of the Unix socket the Docker daemon listens on by default.
+pub const DEFAULT_SOCKET: &str = "/var/run/docker.sock";
+
+/// A client for the Docker Engine API.
+///
+/// Request paths given to a `Docker` are relative to the API root, such as
+/// `/containers/json`, and are prefixed with the API version if one is set
+/// with [`Docker::version`]. Cloning a `Docker` is cheap and shares the
+/// underlying [`HttpClient`].
+#[derive(Clone)]
+pub struct Docker {
+    client: HttpClient,
+    base_uri: String,
+    version: Option<String>,
+}
+
+impl Docker {
+    /// Connect to the daemon given by the `DOCKER_HOST` environment variable,
+    /// or the one listening on [`DEFAULT_SOCKET`] if it is not set.
+    ///
+    /// See [`Docker::from_host`] for the supported values of `DOCKER_HOST`.
+    pub fn new() -> Result<Self, Error> {
+        Self::from_environment(&Environment::process())
+    }
+
+    /// Connect to the daemon given by the `DOCKER_HOST` variable of the given
+    /// environment, or the one listening on [`DEFAULT_SOCKET`] if it is not
+    /// set.
+    pub fn from_environment(environment: &Environment) -> Result<Self, Error> {
+        match environment.get("DOCKER_HOST") {
+            Some(host) => Self::from_host(&host),
+            None => Self::from_host(&format!("unix://{}", DEFAULT_SOCKET)),
+        }
+    }
+
+    /// Connect to the daemon at the given address, in the format used by
+    /// `DOCKER_HOST`.
+    ///
+    /// Supported addresses are `unix:///path/to/socket` for a Unix socket, and
+    /// `tcp://host:port` for a daemon listening on a TCP port without TLS.
+    /// Returns an error of kind
+    /// [`ClientInitialization`](ErrorKind::ClientInitialization) for any other
+    /// address.
+    pub fn from_host(host: &str) -> Result<Self, Error> {
+        #[cfg(unix)]
+        {
+            if let Some(path) = host.strip_prefix("unix://") {
+                return Self::unix_socket(path);
+            }
+        }
+
+        if let Some(addr) = host.strip_prefix("tcp://") {
+            let base_uri = format!("http://{}", addr.trim_end_matches('/'));
+            return Ok(Self::with_client(HttpClient::new()?, base_uri));
+        }
+
+        Err(Error::new(
+            ErrorKind::ClientInitialization,
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported docker host: {}", host),
+            ),
+        ))
+    }
+
+    /// Connect to the daemon listening on the given Unix socket.
+    ///
+    /// # Availability
+    ///
+    /// This function is only available on Unix.
+    #[cfg(unix)]
+    pub fn unix_socket(path: impl Into<std::path::PathBuf>) -> Result<Self, Error> {
+        use crate::config::{Configurable, Dialer};
+
+        let client = HttpClient::builder()
+            .dial(Dialer::unix_socket(path))
+            .build()?;
+
+        Ok(Self::with_client(client, "http://localhost"))
+    }
+
+    /// Talk to the daemon using the given client, with request paths resolved
+    /// against the given base URI.
+    ///
+    /// This is useful for daemons that need additional configuration to
+    /// connect to, such as TLS client certificates.
+    pub fn with_client(client: HttpClient, base_uri: impl Into<String>) -> Self {
+        Self {
+            client,
+            base_uri: base_uri.into().trim_end_matches('/').to_owned(),
+            version: None,
+        }
+    }
+
+    /// Set the version of the API to use, such as `1.43`.
+    ///
+    /// If not set, the daemon uses the latest version it supports.
+    #[must_use = "builders have no effect if unused"]
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        let version = version.into();
+        self.version = Some(version.trim_start_matches('v').to_owned());
+        self
+    }
+
+    /// Get the HTTP client used to talk to the daemon.
+    pub fn client(&self) -> &HttpClient {
+        &self.client
+    }
+
+    /// Get the full URI for the given API path.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use isahc::docker::Docker;
+    ///
+    /// let docker = Docker::with_client(isahc::HttpClient::new()?, "http://localhost")
+    ///     .version("1.43");
+    ///
+    /// assert_eq!(
+    ///     docker.uri("/containers/json?all=1"),
+    ///     "http://localhost/v1.43/containers/json?all=1",
+    /// );
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn uri(&self, path: &str) -> String {
+        let path = path.trim_start_matches('/');
+
+        match self.version.as_ref() {
+            Some(version) => format!("{}/v{}/{}", self.base_uri, version, path),
+            None => format!("{}/{}", self.base_uri, path),
+        }
+    }
+
+    /// Send a `GET` request to the given API path.
+    pub fn get(&self, path: &str) -> Result<Response<Body>, Error> {
+        self.send(Request::get(path).body(())?)
+    }
+
+    /// Send a request to the API.
+    ///
+    /// Only the path and query of the request URI are used, and resolved
+    /// against the daemon's address and API version.
+    pub fn send<B: Into<Body>>(&self, request: Request<B>) -> Result<Response<Body>, Error> {
+        self.client.send(self.resolve(request)?)
+    }
+
+    /// Attach to the standard input, output, and error of a running container.
+    ///
+    /// Writing to the returned connection writes to the container's standard
+    /// input, and reading from it reads its output. Unless the container was
+    /// created with a TTY, the output is multiplexed and can be read using
+    /// [`Frames`].
+    pub fn attach(&self, container: &str) -> Result<Upgraded<Body>, Error> {
+        let path = format!(
+            "/containers/{}/attach?stream=1&stdin=1&stdout=1&stderr=1",
+            container
+        );
+
+        self.hijack(Request::post(path).body(Body::empty())?)
+    }
+
+    /// Start an exec instance created with `POST /containers/{id}/exec`, and
+    /// attach to its standard input, output, and error.
+    ///
+    /// `tty` must match how the exec instance was created. If it is `false`,
+    /// the output is multiplexed and can be read using [`Frames`].
+    pub fn exec_start(&self, exec: &str, tty: bool) -> Result<Upgraded<Body>, Error> {
+        let body = format!(r#"{{"Detach":false,"Tty":{}}}"#, tty);
+        let request = Request::post(format!("/exec/{}/start", exec))
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))?;
+
+        self.hijack(request)
+    }
+
+    /// Send a request to an endpoint that takes over the connection, and
+    /// return the connection.
+    fn hijack(&self, mut request: Request<Body>) -> Result<Upgraded<Body>, Error> {
+        request
+            .headers_mut()
+            .insert(CONNECTION, http::HeaderValue::from_static("Upgrade"));
+        request
+            .headers_mut()
+            .insert(UPGRADE, http::HeaderValue::from_static("tcp"));
+
+        let mut response = match self.send(request)?.upgrade() {
+            Ok(connection) => return Ok(connection),
+            Err(response) => response,
+        };
+
+        if response.status().is_client_error() || response.status().is_server_error() {
+            let error = Error::with_response(ErrorKind::BadStatus, &response);
+            let body = response.bytes()?;
+
+            return Err(error.with_response_body(response.map(|_| body)));
+        }
+
+        Err(Error::with_response(ErrorKind::ProtocolViolation, &response))
+    }
+
+    fn resolve<B: Into<Body>>(&self, request: Request<B>) -> Result<Request<Body>, Error> {
+        let (mut parts, body) = request.into_parts();
+        let path = parts
+            .uri
+            .path_and_query()
+            .map_or("/", |path| path.as_str());
+
+        parts.uri = Uri::try_from(self.uri(path)).map_err(|e| Error::from(http::Error::from(e)))?;
+
+        Ok(Request::from_parts(parts, body.into()))
+    }
+}
+
+impl fmt::Debug for Docker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Docker")
+            .field("base_uri", &self.base_uri)
+            .field("version", &self.version)
+            .finish()
+    }
+}
+
+/// A standard stream of a container's process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StreamType {
+    /// Standard input.
+    Stdin,
+
+    /// Standard output.
+    Stdout,
+
+    /// Standard error.
+    Stderr,
+}
+
+/// A chunk of output written by a container's process to one of its
+/// standard streams.
+#[derive(Clone, Debug)]
+pub struct Frame {
+    stream: StreamType,
+    data: Vec<u8>,
+}
+
+impl Frame {
+    /// Get the stream the data was written to.
+    pub fn stream(&self) -> StreamType {
+        self.stream
+    }
+
+    /// Get the data written.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Take the data written.
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+/// An iterator over the frames of multiplexed output, as streamed by
+/// containers and exec instances without a TTY.
+///
+/// Each frame starts with an 8 byte header giving the stream and length of
+/// the frame, followed by the data itself. Iteration ends when the underlying
+/// reader reaches the end of its data.
+pub struct Frames<R> {
+    reader: R,
+}
+
+impl<R: Read> Frames<R> {
+    /// Read frames from the given reader.
+    pub fn new(reader: R) -> Self {
+        Self { reader }
+    }
+
+    /// Get back the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn read_frame(&mut self) -> io::Result<Option<Frame>> {
+        let mut header = [0; 8];
+        let mut filled = 0;
+
+        while filled < header.len() {
+            match self.reader.read(&mut header[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(len) => filled += len,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let stream = match header[0] {
+            0 => StreamType::Stdin,
+            1 => StreamType::Stdout,
+            2 => StreamType::Stderr,
+            other => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unknown stream type in frame header: {}", other),
+                ));
+            }
+        };
+
+        let len = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+        let mut data = vec![0; len as usize];
+        self.reader.read_exact(&mut data)?;
+
+        Ok(Some(Frame { stream, data }))
+    }
+}
+
+impl<R: Read> Iterator for Frames<R> {
+    type Item = io::Result<Frame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_frame().transpose()
+    }
+}
+
+impl<R> fmt::Debug for Frames<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Frames").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn docker() -> Docker {
+        Docker::with_client(HttpClient::new().unwrap(), "http://localhost/")
+    }
+
+    #[test]
+    fn uri_without_version() {
+        assert_eq!(docker().uri("/info"), "http://localhost/info");
+        assert_eq!(docker().uri("info"), "http://localhost/info");
+    }
+
+    #[test]
+    fn uri_with_version() {
+        assert_eq!(
+            docker().version("v1.41").uri("/images/json?all=1"),
+            "http://localhost/v1.41/images/json?all=1"
+        );
+    }
+
+    #[test]
+    fn tcp_host() {
+        let docker = Docker::from_host("tcp://127.0.0.1:2375/").unwrap();
+
+        assert_eq!(docker.uri("/info"), "http://127.0.0.1:2375/info");
+    }
+
+    #[test]
+    fn unsupported_host() {
+        let error = Docker::from_host("ssh://user@host").unwrap_err();
+
+        assert_eq!(error.kind(), &ErrorKind::ClientInitialization);
+    }
+
+    #[test]
+    fn read_frames() {
+        let mut stream = Vec::new();
+        stream.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 5]);
+        stream.extend_from_slice(b"hello");
+        stream.extend_from_slice(&[2, 0, 0, 0, 0, 0, 0, 4]);
+        stream.extend_from_slice(b"oops");
+
+        let frames = Frames::new(stream.as_slice())
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].stream(), StreamType::Stdout);
+        assert_eq!(frames[0].data(), b"hello");
+        assert_eq!(frames[1].stream(), StreamType::Stderr);
+        assert_eq!(frames[1].data(), b"oops");
+    }
+
+    #[test]
+    fn truncated_frame_is_an_error() {
+        let stream = [1, 0, 0, 0, 0, 0, 0, 5, b'h', b'i'];
+        let mut frames = Frames::new(&stream[..]);
+
+        assert_eq!(
+            frames.next().unwrap().unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+}
@@ -0,0 +1,1079 @@
+//! Helpers for downloading resources into a writer, resuming interrupted
+//! downloads where possible.
+//!
+//! A [`Download`] is created with [`HttpClient::download`]. If part of the
+//! resource was already downloaded by an earlier attempt, the download can be
+//! resumed from where it stopped with [`Download::resume_from`], and only the
+//! remainder is requested from the server using a `Range` header.
+//!
+//! A local copy of a resource can also be kept up to date with
+//! [`Download::update_file`], which only downloads the resource if it has
+//! changed, or just the new tail of it if it only grows, like a log file.
+//!
+//! The body of any response can be saved with
+//! [`ReadResponseExt::save_to`](crate::ReadResponseExt::save_to), which
+//! combines reporting progress, limiting the size, and verifying a digest
+//! according to [`SaveOptions`]. It can also be spooled into a temporary file
+//! with [`ReadResponseExt::spool`](crate::ReadResponseExt::spool), and where
+//! and how temporary files are created is configured with [`TempFileOptions`].
+//!
+//! # Availability
+//!
+//! This module is only available when the [`blocking`](../index.html#blocking)
+//! feature is enabled.
+
+use crate::{
+    config::request::WithRequestConfig,
+    error::{Error, ErrorKind},
+    Body,
+    HttpClient,
+};
+use http::{
+    header::{
+        HeaderName,
+        HeaderValue,
+        CONTENT_LENGTH,
+        CONTENT_RANGE,
+        ETAG,
+        IF_RANGE,
+        LAST_MODIFIED,
+    },
+    HeaderMap,
+    Method,
+    Request,
+    Response,
+    StatusCode,
+    Uri,
+};
+use std::{
+    collections::hash_map::RandomState,
+    env,
+    error::Error as _,
+    fmt,
+    fs::{self, File, Metadata, OpenOptions},
+    hash::{BuildHasher, Hasher},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// A download of a resource into a writer, optionally resuming an earlier
+/// partial download.
+///
+/// When resuming, the server is asked for the remainder of the resource only.
+/// A `206 Partial Content` response is checked to start at the requested
+/// offset before anything is written. If the server does not support range
+/// requests and sends the entire resource instead, the bytes that were already
+/// downloaded are skipped.
+///
+/// Responses with an unsuccessful status code are returned as an error of kind
+/// [`ErrorKind::BadStatus`], so that error pages never end up in the writer.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::HttpClient;
+/// use std::fs::OpenOptions;
+///
+/// let client = HttpClient::new()?;
+/// let mut file = OpenOptions::new()
+///     .create(true)
+///     .append(true)
+///     .open("image.iso")?;
+/// let offset = file.metadata()?.len();
+///
+/// let info = client
+///     .download("https://example.org/image.iso")
+///     .resume_from(offset)
+///     .write_to(&mut file)?;
+///
+/// println!("downloaded {} bytes", info.len());
+/// # Ok::<(), isahc::Error>(())
+/// ```
+pub struct Download {
+    client: HttpClient,
+    uri: Result<Uri, Error>,
+    offset: u64,
+    validator: Option<HeaderValue>,
+    append_only: bool,
+    headers: HeaderMap,
+    temp_file: Option<TempFileOptions>,
+}
+
+/// The outcome of updating a local file with [`Download::update_file`].
+#[derive(Clone, Debug)]
+pub enum FileUpdate {
+    /// The local file was already up to date, and nothing was downloaded.
+    Unchanged,
+
+    /// The resource was downloaded, replacing the local file.
+    Downloaded(DownloadInfo),
+
+    /// The new tail of an append-only resource was downloaded and appended to
+    /// the local file.
+    Appended(DownloadInfo),
+}
+
+/// Information about a completed [`Download`].
+#[derive(Clone, Debug)]
+pub struct DownloadInfo {
+    offset: u64,
+    len: u64,
+    total_len: Option<u64>,
+    validator: Option<HeaderValue>,
+}
+
+impl DownloadInfo {
+    /// Get the offset in the resource that the written bytes started at. This
+    /// is the same as the offset the download was resumed from.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Get the number of bytes written to the writer.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns true if nothing was written to the writer, because the
+    /// resource was already completely downloaded.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the total length of the resource in bytes, if known.
+    pub fn total_len(&self) -> Option<u64> {
+        self.total_len
+    }
+
+    /// Get the validator of the downloaded resource, if the server sent one.
+    ///
+    /// This is the strong `ETag` of the resource if it has one, and its
+    /// `Last-Modified` date otherwise. Passing it to [`Download::if_range`]
+    /// when resuming a later download ensures that the resource has not
+    /// changed in between.
+    pub fn validator(&self) -> Option<&HeaderValue> {
+        self.validator.as_ref()
+    }
+}
+
+impl Download {
+    pub(crate) fn new(client: HttpClient, uri: Result<Uri, Error>) -> Self {
+        Self {
+            client,
+            uri,
+            offset: 0,
+            validator: None,
+            append_only: false,
+            headers: HeaderMap::new(),
+            temp_file: None,
+        }
+    }
+
+    /// Set the number of bytes already downloaded by an earlier attempt, to
+    /// resume the download from.
+    ///
+    /// The writer given to [`Download::write_to`] is expected to already
+    /// contain those bytes, positioned so that the remainder is written after
+    /// them.
+    #[must_use = "builders have no effect if unused"]
+    pub fn resume_from(mut self, offset: u64) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Only resume the download if the resource still matches the given
+    /// validator, which is an `ETag` or a `Last-Modified` date of the resource
+    /// from when the earlier attempt was made.
+    ///
+    /// If the resource has changed in the meantime, the download fails with an
+    /// error of kind [`ErrorKind::ResourceChanged`] and nothing is written.
+    #[must_use = "builders have no effect if unused"]
+    pub fn if_range(mut self, validator: HeaderValue) -> Self {
+        self.validator = Some(validator);
+        self
+    }
+
+    /// Treat the resource as append-only, such as a log file, when updating a
+    /// local copy of it with [`Download::update_file`].
+    ///
+    /// If the resource has grown since the local copy was downloaded, only
+    /// the bytes that were added are requested and appended to the local copy.
+    /// If the resource has shrunk, it is assumed to have been replaced, such
+    /// as by log rotation, and is downloaded again.
+    #[must_use = "builders have no effect if unused"]
+    pub fn append_only(mut self, append_only: bool) -> Self {
+        self.append_only = append_only;
+        self
+    }
+
+    /// Download the resource into a temporary file created with the given
+    /// options when replacing a local file with [`Download::update_file`],
+    /// and move it into place once the download has completed.
+    ///
+    /// This way the local file is never left partially written, even if the
+    /// download fails. The temporary file is created in the directory of the
+    /// local file unless [another directory](TempFileOptions::dir) is given,
+    /// which has to be on the same file system. Tails of append-only
+    /// resources are still appended to the local file directly.
+    #[must_use = "builders have no effect if unused"]
+    pub fn temp_file(mut self, options: TempFileOptions) -> Self {
+        self.temp_file = Some(options);
+        self
+    }
+
+    /// Add a header to include in the download requests.
+    #[must_use = "builders have no effect if unused"]
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    /// Perform the download, streaming the response body into the given
+    /// writer and blocking until it completes or fails.
+    pub fn write_to<W: Write>(self, writer: &mut W) -> Result<DownloadInfo, Error> {
+        let uri = self.uri.clone()?;
+
+        if self.offset == 0 {
+            let response = self.send(uri, None)?;
+
+            if !response.status().is_success() {
+                return Err(Error::with_response(ErrorKind::BadStatus, &response));
+            }
+
+            let total_len = content_length(&response);
+
+            return finish(response, writer, 0, total_len);
+        }
+
+        let response = match self.send(uri.clone(), Some(self.offset)) {
+            Ok(response) => response,
+
+            // Curl refuses to read a body that does not start at the offset
+            // it was asked to resume from, which may fail the request before
+            // the response is returned.
+            Err(e) if is_range_error(&e) => return self.write_full_to(uri, writer),
+
+            Err(e) => return Err(e),
+        };
+
+        match response.status() {
+            StatusCode::PARTIAL_CONTENT => {
+                let (start, total_len) = response
+                    .headers()
+                    .get(CONTENT_RANGE)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(parse_content_range)
+                    .ok_or_else(|| Error::with_response(ErrorKind::ProtocolViolation, &response))?;
+
+                if start != Some(self.offset) {
+                    tracing::debug!(?start, "server returned a range other than the one requested");
+                    return Err(Error::with_response(ErrorKind::ProtocolViolation, &response));
+                }
+
+                finish(response, writer, self.offset, total_len)
+            }
+
+            // There is nothing left to download if the offset is exactly the
+            // length of the resource.
+            StatusCode::RANGE_NOT_SATISFIABLE => {
+                let total_len = response
+                    .headers()
+                    .get(CONTENT_RANGE)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(parse_content_range)
+                    .and_then(|(_, total_len)| total_len);
+
+                if total_len == Some(self.offset) {
+                    Ok(DownloadInfo {
+                        offset: self.offset,
+                        len: 0,
+                        total_len,
+                        validator: validator(&response),
+                    })
+                } else {
+                    Err(Error::with_response(ErrorKind::ResourceChanged, &response))
+                }
+            }
+
+            status if status.is_success() => {
+                drop(response);
+                self.write_full_to(uri, writer)
+            }
+
+            _ => Err(Error::with_response(ErrorKind::BadStatus, &response)),
+        }
+    }
+
+    /// Finish a resumed download after the server responded with the entire
+    /// resource instead of the requested range.
+    fn write_full_to<W: Write>(&self, uri: Uri, writer: &mut W) -> Result<DownloadInfo, Error> {
+        // A validator that does not match means that the bytes downloaded so
+        // far belong to a different version of the resource.
+        if self.validator.is_some() {
+            return Err(Error::from(ErrorKind::ResourceChanged));
+        }
+
+        // Otherwise the server does not support ranges, so request the whole
+        // resource again and skip what we already have.
+        tracing::debug!("server does not support range requests, skipping downloaded bytes");
+
+        let mut response = self.send(uri, None)?;
+
+        if response.status() != StatusCode::OK {
+            let kind = if response.status().is_success() {
+                ErrorKind::ProtocolViolation
+            } else {
+                ErrorKind::BadStatus
+            };
+
+            return Err(Error::with_response(kind, &response));
+        }
+
+        let total_len = content_length(&response);
+        let skipped = io::copy(&mut response.body_mut().take(self.offset), &mut io::sink())?;
+
+        if skipped < self.offset {
+            return Err(Error::with_response(ErrorKind::ResourceChanged, &response));
+        }
+
+        finish(response, writer, self.offset, total_len)
+    }
+
+    /// Update a local copy of the resource at the given path, downloading it
+    /// only if it has changed.
+    ///
+    /// A `HEAD` request is sent first to compare the resource with the local
+    /// file. The file is considered up to date if it has the same size as the
+    /// resource, and either the validator given to [`Download::if_range`]
+    /// still matches the resource, or the file was modified after the
+    /// resource's `Last-Modified` date. Otherwise the file is replaced with a
+    /// fresh download of the resource.
+    ///
+    /// If the resource is [append-only](Download::append_only) and has grown,
+    /// only the new tail is downloaded and appended to the file instead.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::{download::FileUpdate, HttpClient};
+    ///
+    /// let client = HttpClient::new()?;
+    ///
+    /// let update = client
+    ///     .download("https://example.org/app.log")
+    ///     .append_only(true)
+    ///     .update_file("app.log")?;
+    ///
+    /// match update {
+    ///     FileUpdate::Unchanged => println!("no new log lines"),
+    ///     FileUpdate::Appended(info) => println!("{} new bytes of log lines", info.len()),
+    ///     FileUpdate::Downloaded(info) => println!("log was rotated, {} bytes", info.len()),
+    /// }
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    pub fn update_file(self, path: impl AsRef<Path>) -> Result<FileUpdate, Error> {
+        let path = path.as_ref();
+        let uri = self.uri.clone()?;
+        let head = self.client.send(self.request(Method::HEAD, uri).body(())?)?;
+
+        if !head.status().is_success() {
+            return Err(Error::with_response(ErrorKind::BadStatus, &head));
+        }
+
+        let local = match fs::metadata(path) {
+            Ok(metadata) => Some(metadata),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        if let Some(local) = local {
+            let remote_len = content_length(&head);
+
+            if remote_len == Some(local.len())
+                && (self.append_only || self.is_unchanged(&head, &local))
+            {
+                return Ok(FileUpdate::Unchanged);
+            }
+
+            if self.append_only && remote_len.map_or(false, |len| len > local.len()) {
+                let mut file = OpenOptions::new().append(true).open(path)?;
+
+                // The validator of a growing resource changes with every
+                // append, so it cannot be used to check the range.
+                let info = Self {
+                    offset: local.len(),
+                    validator: None,
+                    ..self
+                }
+                .write_to(&mut file)?;
+
+                return Ok(FileUpdate::Appended(info));
+            }
+        }
+
+        let info = match self.temp_file.clone() {
+            Some(temp_file) => {
+                let (mut file, temp_path) = temp_file.create_named(parent_dir(path))?;
+                let result = Self { offset: 0, ..self }
+                    .write_to(&mut file)
+                    .and_then(|info| {
+                        fs::rename(&temp_path, path)
+                            .map(|_| info)
+                            .map_err(Error::from)
+                    });
+
+                if result.is_err() {
+                    let _ = fs::remove_file(&temp_path);
+                }
+
+                result?
+            }
+            None => Self { offset: 0, ..self }.write_to(&mut File::create(path)?)?,
+        };
+
+        Ok(FileUpdate::Downloaded(info))
+    }
+
+    /// Returns true if a resource of the same size as a local file, described
+    /// by the given `HEAD` response, is the same as the local file.
+    fn is_unchanged(&self, head: &Response<Body>, local: &Metadata) -> bool {
+        if let Some(expected) = self.validator.as_ref() {
+            return validator(head).as_ref() == Some(expected);
+        }
+
+        let last_modified = head
+            .headers()
+            .get(LAST_MODIFIED)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| httpdate::parse_http_date(value).ok());
+
+        match (last_modified, local.modified()) {
+            (Some(last_modified), Ok(modified)) => last_modified <= modified,
+            _ => false,
+        }
+    }
+
+    fn send(&self, uri: Uri, resume_from: Option<u64>) -> Result<Response<Body>, Error> {
+        let mut builder = self.request(Method::GET, uri);
+
+        if let Some(offset) = resume_from {
+            builder = builder.with_config(|config| {
+                config.resume_from = Some(offset);
+            });
+
+            if let Some(validator) = self.validator.clone() {
+                builder = builder.header(IF_RANGE, validator);
+            }
+        }
+
+        self.client.send(builder.body(())?)
+    }
+
+    fn request(&self, method: Method, uri: Uri) -> http::request::Builder {
+        let mut builder = Request::builder().method(method).uri(uri);
+
+        if let Some(headers) = builder.headers_mut() {
+            headers.extend(self.headers.clone());
+        }
+
+        builder
+    }
+}
+
+impl fmt::Debug for Download {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Download")
+            .field("uri", &self.uri)
+            .field("offset", &self.offset)
+            .field("validator", &self.validator)
+            .field("append_only", &self.append_only)
+            .field("temp_file", &self.temp_file)
+            .finish()
+    }
+}
+
+/// A hash function for verifying the integrity of a response body saved with
+/// [`ReadResponseExt::save_to`](crate::ReadResponseExt::save_to).
+///
+/// Isahc does not include any hash functions itself, but implementing this
+/// trait for one from a crate such as `sha2` only takes a few lines.
+///
+/// # Examples
+///
+/// A digest using the 64-bit FNV-1a hash:
+///
+/// ```
+/// use isahc::download::Digest;
+///
+/// struct Fnv1a(u64);
+///
+/// impl Digest for Fnv1a {
+///     fn update(&mut self, data: &[u8]) {
+///         for &byte in data {
+///             self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x100000001b3);
+///         }
+///     }
+///
+///     fn finish(&mut self) -> Vec<u8> {
+///         self.0.to_be_bytes().to_vec()
+///     }
+/// }
+/// ```
+pub trait Digest {
+    /// Feed the next chunk of the body into the hash.
+    fn update(&mut self, data: &[u8]);
+
+    /// Get the hash of all the data fed in.
+    fn finish(&mut self) -> Vec<u8>;
+}
+
+/// The progress of saving a response body, reported to the callback set with
+/// [`SaveOptions::on_progress`].
+#[derive(Clone, Copy, Debug)]
+pub struct SaveProgress {
+    written: u64,
+    total_len: Option<u64>,
+}
+
+impl SaveProgress {
+    /// Get the number of bytes written so far.
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// Get the length of the body in bytes, if the server declared it.
+    pub fn total_len(&self) -> Option<u64> {
+        self.total_len
+    }
+}
+
+type ProgressCallback<'a> = Box<dyn FnMut(&SaveProgress) + 'a>;
+
+/// Options for saving a response body with
+/// [`ReadResponseExt::save_to`](crate::ReadResponseExt::save_to).
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{download::SaveOptions, prelude::*};
+///
+/// let options = SaveOptions::new()
+///     .max_size(100 * 1024 * 1024)
+///     .on_progress(|progress| {
+///         println!("{} of {:?} bytes", progress.written(), progress.total_len());
+///     });
+///
+/// let summary = isahc::get("https://example.org/image.iso")?
+///     .save_to_file("image.iso", options)?;
+///
+/// println!("saved {} bytes in {:?}", summary.len(), summary.elapsed());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Default)]
+pub struct SaveOptions<'a> {
+    max_size: Option<u64>,
+    on_progress: Option<ProgressCallback<'a>>,
+    digest: Option<(Box<dyn Digest + 'a>, Vec<u8>)>,
+    temp_file: Option<TempFileOptions>,
+}
+
+impl<'a> SaveOptions<'a> {
+    /// Create options for saving the body as is, without any limit.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail if the body is larger than the given number of bytes.
+    ///
+    /// If the server declares a larger `Content-Length`, nothing is written at
+    /// all. Otherwise the body is written until it exceeds the limit.
+    #[must_use = "builders have no effect if unused"]
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Call the given function every time a chunk of the body was written.
+    #[must_use = "builders have no effect if unused"]
+    pub fn on_progress(mut self, f: impl FnMut(&SaveProgress) + 'a) -> Self {
+        self.on_progress = Some(Box::new(f));
+        self
+    }
+
+    /// Hash the body with the given digest, and fail if the hash does not
+    /// equal the expected one once the entire body was written.
+    #[must_use = "builders have no effect if unused"]
+    pub fn verify(mut self, digest: impl Digest + 'a, expected: impl Into<Vec<u8>>) -> Self {
+        self.digest = Some((Box::new(digest), expected.into()));
+        self
+    }
+
+    /// Write the body into a temporary file created with the given options.
+    ///
+    /// When saving into a file with
+    /// [`ReadResponseExt::save_to_file`](crate::ReadResponseExt::save_to_file),
+    /// the temporary file is moved into place once the entire body was
+    /// written, so that the file is never left partially written. It is
+    /// created in the directory of the file unless [another
+    /// directory](TempFileOptions::dir) is given, which has to be on the same
+    /// file system, and is never [anonymous](TempFileOptions::anonymous).
+    ///
+    /// When spooling with [`ReadResponseExt::spool`](crate::ReadResponseExt::spool),
+    /// these options are used for the file spooled into.
+    #[must_use = "builders have no effect if unused"]
+    pub fn temp_file(mut self, options: TempFileOptions) -> Self {
+        self.temp_file = Some(options);
+        self
+    }
+}
+
+impl fmt::Debug for SaveOptions<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SaveOptions")
+            .field("max_size", &self.max_size)
+            .field("on_progress", &self.on_progress.is_some())
+            .field("expected_digest", &self.digest.as_ref().map(|(_, expected)| expected))
+            .field("temp_file", &self.temp_file)
+            .finish()
+    }
+}
+
+/// A summary of a response body saved with
+/// [`ReadResponseExt::save_to`](crate::ReadResponseExt::save_to).
+#[derive(Clone, Debug)]
+pub struct SaveSummary {
+    len: u64,
+    elapsed: Duration,
+    digest: Option<Vec<u8>>,
+}
+
+impl SaveSummary {
+    /// Get the number of bytes written.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns true if the body was empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get how long it took to receive and write the body.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Get the verified hash of the body, if a digest was given.
+    pub fn digest(&self) -> Option<&[u8]> {
+        self.digest.as_deref()
+    }
+}
+
+/// Options for creating the temporary files that response bodies are written
+/// into, for environments where what ends up on disk and who can read it
+/// matters, such as when handling secrets.
+///
+/// Temporary files are used when [spooling](crate::ReadResponseExt::spool) a
+/// response body, and optionally when saving a body into a file with
+/// [`SaveOptions::temp_file`] or downloading one with
+/// [`Download::temp_file`]. Each file gets a random name starting with
+/// `.isahc-`, and is only readable and writable by its owner by default.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{download::{SaveOptions, TempFileOptions}, prelude::*};
+/// use std::io::Read;
+///
+/// // Spool a large secret into a file without a name on a RAM disk.
+/// let options = TempFileOptions::new().dir("/dev/shm").anonymous(true);
+///
+/// let mut spooled = isahc::get("https://vault.example.org/secret")?
+///     .spool(SaveOptions::new().temp_file(options))?;
+///
+/// let mut secret = Vec::new();
+/// spooled.read_to_end(&mut secret)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TempFileOptions {
+    dir: Option<PathBuf>,
+    mode: Option<u32>,
+    anonymous: bool,
+}
+
+impl TempFileOptions {
+    /// Create options for temporary files with the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create temporary files in the given directory.
+    ///
+    /// By default, spooled files are created in the directory returned by
+    /// [`std::env::temp_dir`], and files that are moved into place once
+    /// written are created in the directory they are moved to.
+    #[must_use = "builders have no effect if unused"]
+    pub fn dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = Some(dir.into());
+        self
+    }
+
+    /// Set the permission bits of temporary files, such as `0o640`.
+    ///
+    /// The default is `0o600`. The bits are set exactly as given, regardless
+    /// of the umask of the process. This has no effect on platforms other than
+    /// Unix.
+    #[must_use = "builders have no effect if unused"]
+    pub fn mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Remove spooled files from their directory as soon as they are created,
+    /// so that they cannot be opened by anyone else and are freed by the
+    /// operating system once closed, even if the process crashes. Disabled by
+    /// default.
+    ///
+    /// This has no effect on platforms other than Unix, where spooled files
+    /// are always removed once dropped.
+    #[must_use = "builders have no effect if unused"]
+    pub fn anonymous(mut self, enable: bool) -> Self {
+        self.anonymous = enable;
+        self
+    }
+
+    /// Create a temporary file, in the given directory unless another one was
+    /// configured. The path of the file is returned unless it is anonymous.
+    pub(crate) fn create(&self, default_dir: &Path) -> io::Result<(File, Option<PathBuf>)> {
+        let (file, path) = self.create_named(default_dir)?;
+
+        if cfg!(unix) && self.anonymous {
+            fs::remove_file(&path)?;
+            return Ok((file, None));
+        }
+
+        Ok((file, Some(path)))
+    }
+
+    /// Create a temporary file that is never anonymous.
+    pub(crate) fn create_named(&self, default_dir: &Path) -> io::Result<(File, PathBuf)> {
+        let dir = self.dir.as_deref().unwrap_or(default_dir);
+
+        loop {
+            let path = dir.join(format!(".isahc-{:016x}.tmp", random()));
+            let mut options = OpenOptions::new();
+
+            options.read(true).write(true).create_new(true);
+
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::OpenOptionsExt;
+
+                options.mode(self.mode.unwrap_or(0o600));
+            }
+
+            let file = match options.open(&path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            };
+
+            // The mode given when creating the file is restricted by the umask.
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+
+                if let Some(mode) = self.mode {
+                    if let Err(e) = file.set_permissions(fs::Permissions::from_mode(mode)) {
+                        let _ = fs::remove_file(&path);
+                        return Err(e);
+                    }
+                }
+            }
+
+            return Ok((file, path));
+        }
+    }
+}
+
+/// A response body spooled into a temporary file with
+/// [`ReadResponseExt::spool`](crate::ReadResponseExt::spool).
+///
+/// The file is read from its start, and can be read again after seeking back.
+/// It is removed once the spooled file is dropped, unless it was already
+/// removed by being [anonymous](TempFileOptions::anonymous).
+#[derive(Debug)]
+pub struct SpooledFile {
+    file: File,
+    path: Option<PathBuf>,
+    summary: SaveSummary,
+}
+
+impl SpooledFile {
+    /// Get the path of the file, or `None` if it is anonymous.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Get a summary of the body written into the file.
+    pub fn summary(&self) -> &SaveSummary {
+        &self.summary
+    }
+}
+
+impl Read for SpooledFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Seek for SpooledFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+impl Drop for SpooledFile {
+    fn drop(&mut self) {
+        if let Some(path) = self.path.take() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Get a random number for naming temporary files.
+fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// Save a response body into a writer as configured by the given options.
+pub(crate) fn save<R: Read, W: Write>(
+    response: &mut Response<R>,
+    mut writer: W,
+    mut options: SaveOptions<'_>,
+) -> io::Result<SaveSummary> {
+    let started = Instant::now();
+    let total_len = content_length(response);
+
+    if let (Some(max_size), Some(total_len)) = (options.max_size, total_len) {
+        if total_len > max_size {
+            return Err(too_large(max_size));
+        }
+    }
+
+    let mut buf = vec![0; 16 * 1024];
+    let mut written = 0;
+
+    loop {
+        let len = match response.body_mut().read(&mut buf) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        written += len as u64;
+
+        if let Some(max_size) = options.max_size {
+            if written > max_size {
+                return Err(too_large(max_size));
+            }
+        }
+
+        writer.write_all(&buf[..len])?;
+
+        if let Some((digest, _)) = options.digest.as_mut() {
+            digest.update(&buf[..len]);
+        }
+
+        if let Some(f) = options.on_progress.as_mut() {
+            f(&SaveProgress { written, total_len });
+        }
+    }
+
+    writer.flush()?;
+
+    let digest = match options.digest.as_mut() {
+        Some((digest, expected)) => {
+            let actual = digest.finish();
+
+            if actual != *expected {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "response body does not match the expected digest",
+                ));
+            }
+
+            Some(actual)
+        }
+        None => None,
+    };
+
+    Ok(SaveSummary {
+        len: written,
+        elapsed: started.elapsed(),
+        digest,
+    })
+}
+
+/// Save a response body into a file, removing the file again if saving fails.
+pub(crate) fn save_to_file<R: Read>(
+    response: &mut Response<R>,
+    path: &Path,
+    mut options: SaveOptions<'_>,
+) -> io::Result<SaveSummary> {
+    let (file, temp_path) = match options.temp_file.take() {
+        Some(temp_file) => {
+            let (file, temp_path) = temp_file.create_named(parent_dir(path))?;
+            (file, Some(temp_path))
+        }
+        None => (File::create(path)?, None),
+    };
+    let written_path = temp_path.as_deref().unwrap_or(path);
+
+    let result = save(response, io::BufWriter::new(file), options).and_then(|summary| {
+        match temp_path.as_ref() {
+            Some(temp_path) => fs::rename(temp_path, path).map(|_| summary),
+            None => Ok(summary),
+        }
+    });
+
+    if result.is_err() {
+        let _ = fs::remove_file(written_path);
+    }
+
+    result
+}
+
+/// Save a response body into a temporary file, positioned at its start.
+pub(crate) fn spool<R: Read>(
+    response: &mut Response<R>,
+    mut options: SaveOptions<'_>,
+) -> io::Result<SpooledFile> {
+    let temp_file = options.temp_file.take().unwrap_or_default();
+    let (file, path) = temp_file.create(&env::temp_dir())?;
+    let result = save(response, io::BufWriter::new(&file), options)
+        .and_then(|summary| (&file).seek(SeekFrom::Start(0)).map(|_| summary));
+
+    match result {
+        Ok(summary) => Ok(SpooledFile {
+            file,
+            path,
+            summary,
+        }),
+        Err(e) => {
+            if let Some(path) = path {
+                let _ = fs::remove_file(path);
+            }
+
+            Err(e)
+        }
+    }
+}
+
+/// Get the directory a file is in, for creating temporary files next to it.
+fn parent_dir(path: &Path) -> &Path {
+    path.parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."))
+}
+
+fn too_large(max_size: u64) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("response body is larger than the limit of {} bytes", max_size),
+    )
+}
+
+/// Stream the rest of a response body into a writer.
+fn finish<W: Write>(
+    mut response: Response<Body>,
+    writer: &mut W,
+    offset: u64,
+    total_len: Option<u64>,
+) -> Result<DownloadInfo, Error> {
+    let validator = validator(&response);
+    let len = io::copy(response.body_mut(), writer)?;
+
+    Ok(DownloadInfo {
+        offset,
+        len,
+        total_len,
+        validator,
+    })
+}
+
+/// Returns true if a request failed because the server did not respond with
+/// the range that was asked for.
+fn is_range_error(error: &Error) -> bool {
+    error
+        .source()
+        .and_then(|source| source.downcast_ref::<curl::Error>())
+        .map_or(false, curl::Error::is_range_error)
+}
+
+/// Get the length of the entire resource from a full response.
+fn content_length<T>(response: &Response<T>) -> Option<u64> {
+    response
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+}
+
+/// Get a validator that can be used in an `If-Range` header from a response.
+/// Weak entity tags cannot be used for ranges.
+fn validator<T>(response: &Response<T>) -> Option<HeaderValue> {
+    response
+        .headers()
+        .get(ETAG)
+        .filter(|etag| !etag.as_bytes().starts_with(b"W/"))
+        .or_else(|| response.headers().get(LAST_MODIFIED))
+        .cloned()
+}
+
+/// Parse a `Content-Range` header value into the first byte position of the
+/// range and the complete length of the resource, either of which may be
+/// unknown.
+pub(crate) fn parse_content_range(value: &str) -> Option<(Option<u64>, Option<u64>)> {
+    let (range, total_len) = value.trim().strip_prefix("bytes ")?.split_once('/')?;
+
+    let total_len = match total_len.trim() {
+        "*" => None,
+        total_len => Some(total_len.parse().ok()?),
+    };
+
+    let start = match range.trim() {
+        "*" => None,
+        range => {
+            let (start, end) = range.split_once('-')?;
+            let start = start.parse::<u64>().ok()?;
+
+            if end.parse::<u64>().ok()? < start {
+                return None;
+            }
+
+            Some(start)
+        }
+    };
+
+    Some((start, total_len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("bytes 0-499/1234", Some((Some(0), Some(1234))))]
+    #[test_case("bytes 500-999/*", Some((Some(500), None)))]
+    #[test_case("bytes */1234", Some((None, Some(1234))))]
+    #[test_case("bytes 500-499/1234", None)]
+    #[test_case("bytes 500/1234", None)]
+    #[test_case("items 0-1/2", None)]
+    fn parse_content_range_values(value: &str, expected: Option<(Option<u64>, Option<u64>)>) {
+        assert_eq!(parse_content_range(value), expected);
+    }
+}
@@ -5,7 +5,9 @@ use std::{error::Error as StdError, fmt, io, net::SocketAddr, sync::Arc};
 use http::Response;
 use once_cell::sync::OnceCell;
 
-use crate::ResponseExt;
+use crate::{CancelReason, ResponseExt};
+
+pub use crate::transfer_log::{TransferMessage, TransferMessageKind};
 
 /// A non-exhaustive list of error types that can occur while sending an HTTP
 /// request or receiving an HTTP response.
@@ -17,12 +19,39 @@ use crate::ResponseExt;
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[non_exhaustive]
 pub enum ErrorKind {
+    /// The background agent thread responsible for executing the request
+    /// crashed or stopped responding before the request could be completed.
+    ///
+    /// A crash indicates a bug in Isahc or one of its dependencies. Subsequent
+    /// requests using the same client are likely to fail too, so a new client
+    /// should be created. An agent that only stopped responding for a while,
+    /// for example because a callback ran for a long time, is used again once
+    /// it makes progress.
+    AgentCrashed,
+
     /// A problem occurred with the local certificate.
     BadClientCertificate,
 
     /// The server certificate could not be validated.
     BadServerCertificate,
 
+    /// The server responded with an error status code, and the request was
+    /// configured to treat that as an error using
+    /// [`Configurable::fail_with_body`](crate::config::Configurable::fail_with_body).
+    ///
+    /// The response is available using [`Error::response`].
+    BadStatus,
+
+    /// The client that sent the request was dropped or shut down before the
+    /// response was completely received.
+    ///
+    /// This is only returned when the client is configured to abort
+    /// outstanding transfers on shutdown, see
+    /// [`ShutdownPolicy`](crate::config::ShutdownPolicy) for details, or when
+    /// the client was shut down using
+    /// [`HttpClient::shutdown`](crate::HttpClient::shutdown).
+    ClientClosed,
+
     /// The HTTP client failed to initialize.
     ///
     /// This error can occur when trying to create a client with invalid
@@ -35,6 +64,18 @@ pub enum ErrorKind {
     /// the request on the specified port.
     ConnectionFailed,
 
+    /// A compressed response body expanded beyond the configured
+    /// [`DecompressionLimit`](crate::config::DecompressionLimit) when
+    /// decompressed, and the transfer was aborted.
+    DecompressionLimitExceeded,
+
+    /// The request was interrupted by setting its interrupt flag, or canceled
+    /// using a [`CancelHandle`](crate::CancelHandle).
+    ///
+    /// See [`Configurable::interrupt_flag`](crate::config::Configurable::interrupt_flag)
+    /// for details.
+    Interrupted,
+
     /// The server either returned a response using an unknown or unsupported
     /// encoding format, or the response encoding was malformed.
     InvalidContentEncoding,
@@ -73,6 +114,14 @@ pub enum ErrorKind {
     /// likely to produce the same error.
     ProtocolViolation,
 
+    /// The request was not sent because the client's
+    /// [`HostPolicy`](crate::config::HostPolicy) does not allow requests to
+    /// its host, port, or resolved address.
+    ///
+    /// The reason the request was blocked is available using
+    /// [`Error::source`][std::error::Error::source].
+    RequestBlocked,
+
     /// Request processing could not continue because the client needed to
     /// re-send the request body, but was unable to rewind the body stream to
     /// the beginning in order to do so.
@@ -84,6 +133,28 @@ pub enum ErrorKind {
     /// [`AsyncBody::from_bytes_static`][crate::AsyncBody::from_bytes_static].
     RequestBodyNotRewindable,
 
+    /// The server stopped sending the response body for longer than the
+    /// configured
+    /// [`Configurable::read_timeout`](crate::config::Configurable::read_timeout)
+    /// while it was being read.
+    ///
+    /// Unlike [`ErrorKind::Timeout`], this means that the server did respond,
+    /// but stalled in the middle of the response body.
+    ReadTimeout,
+
+    /// A [`Download`](crate::download::Download) could not be resumed because
+    /// the resource has changed since the bytes downloaded so far were
+    /// received. The download must be restarted from the beginning.
+    ResourceChanged,
+
+    /// A request failed and could have been retried automatically, but the
+    /// client's [`RetryBudget`](crate::config::RetryBudget) for the host was
+    /// exhausted.
+    ///
+    /// The error that caused the last attempt to fail is available using
+    /// [`Error::source`][std::error::Error::source].
+    RetryBudgetExhausted,
+
     /// A request or operation took longer than the configured timeout time.
     Timeout,
 
@@ -106,10 +177,19 @@ impl ErrorKind {
     #[inline]
     fn description(&self) -> Option<&str> {
         match self {
+            Self::AgentCrashed => Some("the agent thread executing the request crashed"),
             Self::BadClientCertificate => Some("a problem occurred with the local certificate"),
             Self::BadServerCertificate => Some("the server certificate could not be validated"),
+            Self::BadStatus => Some("the server responded with an error status code"),
+            Self::ClientClosed => {
+                Some("the client was closed before the response was completely received")
+            }
             Self::ClientInitialization => Some("failed to initialize client"),
             Self::ConnectionFailed => Some("failed to connect to the server"),
+            Self::DecompressionLimitExceeded => {
+                Some("response body exceeded the decompression limit")
+            }
+            Self::Interrupted => Some("the request was interrupted"),
             Self::InvalidContentEncoding => Some(
                 "the server either returned a response using an unknown or unsupported encoding format, or the response encoding was malformed",
             ),
@@ -121,9 +201,15 @@ impl ErrorKind {
             Self::ProtocolViolation => {
                 Some("the server made an unrecoverable HTTP protocol violation")
             }
+            Self::ReadTimeout => Some("the server stopped sending the response body"),
+            Self::RequestBlocked => Some("request was blocked by the host policy"),
             Self::RequestBodyNotRewindable => {
                 Some("request body could not be re-sent because it is not rewindable")
             }
+            Self::ResourceChanged => Some("the resource changed since it was partially downloaded"),
+            Self::RetryBudgetExhausted => {
+                Some("request was not retried because the retry budget is exhausted")
+            }
             Self::Timeout => {
                 Some("request or operation took longer than the configured timeout time")
             }
@@ -147,6 +233,39 @@ impl PartialEq<ErrorKind> for &'_ ErrorKind {
     }
 }
 
+/// A more specific classification of why a TLS connection failed.
+///
+/// TLS failures are reported with a general [`ErrorKind`] such as
+/// [`ErrorKind::BadServerCertificate`] or [`ErrorKind::ConnectionFailed`].
+/// Where the error details reported by curl and the TLS backend allow it,
+/// [`Error::tls_error_kind`] additionally tells apart the common reasons for
+/// the failure, for example to present an accurate message to the user, or to
+/// decide whether offering the user to override the failure is reasonable.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum TlsErrorKind {
+    /// The server's certificate has expired, or is not valid yet.
+    CertificateExpired,
+
+    /// The server's certificate was not issued by a trusted certificate
+    /// authority, for example because it is self-signed or the server did not
+    /// send the full certificate chain.
+    UntrustedIssuer,
+
+    /// The server's certificate is not valid for the host name the request
+    /// was sent to.
+    HostnameMismatch,
+
+    /// The TLS handshake failed for a reason unrelated to certificates, for
+    /// example because the client and server have no protocol version or
+    /// cipher suite in common, or the server does not speak TLS at all.
+    Handshake,
+
+    /// The server rejected the client certificate, or required a client
+    /// certificate and none was provided.
+    ClientCertificateRejected,
+}
+
 /// An error encountered while sending an HTTP request or receiving an HTTP
 /// response.
 ///
@@ -163,6 +282,10 @@ impl PartialEq<ErrorKind> for &'_ ErrorKind {
 /// If you need to get more specific details about the reason for the error, you
 /// can use the [`source`][std::error::Error::source] method. We do not provide
 /// any stability guarantees about what error sources are returned.
+///
+/// When an error is reported by curl, the detailed message curl gives about
+/// the failure, such as the exact reason a TLS handshake failed, is included
+/// both when displaying the error and in its source.
 #[derive(Clone)]
 pub struct Error(Arc<Inner>);
 
@@ -172,6 +295,9 @@ struct Inner {
     source: Option<Box<dyn SourceError>>,
     local_addr: OnceCell<SocketAddr>,
     remote_addr: OnceCell<SocketAddr>,
+    response: OnceCell<Response<Vec<u8>>>,
+    transfer_messages: OnceCell<Vec<TransferMessage>>,
+    cancel_reason: OnceCell<CancelReason>,
 }
 
 impl Error {
@@ -195,6 +321,9 @@ impl Error {
             source: Some(Box::new(source)),
             local_addr: OnceCell::new(),
             remote_addr: OnceCell::new(),
+            response: OnceCell::new(),
+            transfer_messages: OnceCell::new(),
+            cancel_reason: OnceCell::new(),
         }))
     }
 
@@ -313,11 +442,16 @@ impl Error {
     /// probably should not be retried without first fixing the request
     /// parameters.
     pub fn is_client(&self) -> bool {
+        if let Some(response) = self.response() {
+            return response.status().is_client_error();
+        }
+
         match self.kind() {
             ErrorKind::BadClientCertificate
             | ErrorKind::ClientInitialization
             | ErrorKind::InvalidCredentials
             | ErrorKind::InvalidRequest
+            | ErrorKind::RequestBlocked
             | ErrorKind::RequestBodyNotRewindable
             | ErrorKind::TlsEngine => true,
             _ => false,
@@ -337,6 +471,10 @@ impl Error {
 
     /// Returns true if this error was likely the fault of the server.
     pub fn is_server(&self) -> bool {
+        if let Some(response) = self.response() {
+            return response.status().is_server_error();
+        }
+
         match self.kind() {
             ErrorKind::BadServerCertificate
             | ErrorKind::ProtocolViolation
@@ -354,11 +492,12 @@ impl Error {
     ///   request or replying in a timely manner.
     /// - The server received the request but is taking a long time to fulfill
     ///   the request.
+    /// - The server stalled while sending the response body.
     ///
     /// Sometimes retrying the request once or twice is enough to resolve the
     /// error.
     pub fn is_timeout(&self) -> bool {
-        self.kind() == ErrorKind::Timeout
+        matches!(self.kind(), ErrorKind::Timeout | ErrorKind::ReadTimeout)
     }
 
     /// Returns true if this error is related to SSL/TLS.
@@ -371,6 +510,18 @@ impl Error {
         }
     }
 
+    /// Get a more specific classification of this error if it is a TLS
+    /// failure whose reason could be determined.
+    ///
+    /// The reason is determined from the error details reported by curl and
+    /// the TLS backend, which vary between backends and versions, so `None`
+    /// may be returned even for TLS errors.
+    pub fn tls_error_kind(&self) -> Option<TlsErrorKind> {
+        let error = self.source()?.downcast_ref::<curl::Error>()?;
+
+        classify_tls_error(error.code(), error.extra_description())
+    }
+
     /// Get the local socket address of the last-used connection involved in
     /// this error, if known.
     ///
@@ -389,6 +540,50 @@ impl Error {
         self.0.remote_addr.get().cloned()
     }
 
+    /// Get the response that caused this error, if any.
+    ///
+    /// This is only available for errors of kind
+    /// [`ErrorKind::BadStatus`]. The body contains as much of the response
+    /// body as was configured to be kept using
+    /// [`Configurable::fail_with_body`](crate::config::Configurable::fail_with_body).
+    pub fn response(&self) -> Option<&Response<Vec<u8>>> {
+        self.0.response.get()
+    }
+
+    /// Get the last informational messages curl reported about the transfer
+    /// that failed with this error, oldest first.
+    ///
+    /// Messages are only kept for requests configured to do so using
+    /// [`Configurable::transfer_messages`](crate::config::Configurable::transfer_messages),
+    /// and this is empty otherwise. This makes the protocol messages leading
+    /// up to a failure available without enabling verbose logging for every
+    /// request.
+    pub fn transfer_messages(&self) -> &[TransferMessage] {
+        self.0
+            .transfer_messages
+            .get()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Get the reason the request was canceled for, if it failed because it
+    /// was canceled.
+    ///
+    /// Requests canceled using a [`CancelHandle`](crate::CancelHandle) fail
+    /// with an error of kind [`ErrorKind::Interrupted`] that carries the
+    /// reason given when canceling. Requests aborted by
+    /// [`HttpClient::shutdown`](crate::HttpClient::shutdown) fail with an
+    /// error of kind [`ErrorKind::ClientClosed`] that carries
+    /// [`CancelReason::Shutdown`].
+    pub fn cancel_reason(&self) -> Option<CancelReason> {
+        self.0.cancel_reason.get().copied()
+    }
+
+    pub(crate) fn with_response_body(self, response: Response<Vec<u8>>) -> Self {
+        let _ = self.0.response.set(response);
+        self
+    }
+
     pub(crate) fn with_local_addr(self, addr: SocketAddr) -> Self {
         let _ = self.0.local_addr.set(addr);
         self
@@ -398,6 +593,16 @@ impl Error {
         let _ = self.0.remote_addr.set(addr);
         self
     }
+
+    pub(crate) fn with_transfer_messages(self, messages: Vec<TransferMessage>) -> Self {
+        let _ = self.0.transfer_messages.set(messages);
+        self
+    }
+
+    pub(crate) fn with_cancel_reason(self, reason: CancelReason) -> Self {
+        let _ = self.0.cancel_reason.set(reason);
+        self
+    }
 }
 
 impl StdError for Error {
@@ -424,6 +629,8 @@ impl fmt::Debug for Error {
             )
             .field("local_addr", &self.0.local_addr.get())
             .field("remote_addr", &self.0.remote_addr.get())
+            .field("status", &self.response().map(Response::status))
+            .field("cancel_reason", &self.cancel_reason())
             .finish()
     }
 }
@@ -446,6 +653,9 @@ impl From<ErrorKind> for Error {
             source: None,
             local_addr: OnceCell::new(),
             remote_addr: OnceCell::new(),
+            response: OnceCell::new(),
+            transfer_messages: OnceCell::new(),
+            cancel_reason: OnceCell::new(),
         }))
     }
 }
@@ -474,7 +684,10 @@ impl From<Error> for io::Error {
     fn from(error: Error) -> Self {
         let kind = match error.kind() {
             ErrorKind::ConnectionFailed => io::ErrorKind::ConnectionRefused,
-            ErrorKind::Timeout => io::ErrorKind::TimedOut,
+            ErrorKind::DecompressionLimitExceeded => io::ErrorKind::InvalidData,
+            ErrorKind::Timeout | ErrorKind::ReadTimeout => io::ErrorKind::TimedOut,
+            // Not mapped to `io::ErrorKind::Interrupted` on purpose, since
+            // readers are expected to simply retry on that.
             _ => io::ErrorKind::Other,
         };
 
@@ -502,6 +715,71 @@ impl From<http::Error> for Error {
 
 /// Internal trait object for source errors. This is used to capture additional
 /// methods about the source error value in the vtable.
+/// Classify a curl error as a specific kind of TLS failure using its code and
+/// the detailed message from the TLS backend.
+fn classify_tls_error(code: curl_sys::CURLcode, detail: Option<&str>) -> Option<TlsErrorKind> {
+    let tls_code = match code {
+        curl_sys::CURLE_SSL_CONNECT_ERROR
+        | curl_sys::CURLE_PEER_FAILED_VERIFICATION
+        | curl_sys::CURLE_SSL_ISSUER_ERROR => true,
+        // A rejected client certificate can be reported after the handshake
+        // when using TLS 1.3.
+        curl_sys::CURLE_RECV_ERROR | curl_sys::CURLE_SEND_ERROR => false,
+        _ => return None,
+    };
+
+    let detail = detail.unwrap_or_default().to_ascii_lowercase();
+    let contains = |patterns: &[&str]| patterns.iter().any(|p| detail.contains(p));
+
+    // Alerts sent by the server about the certificate we presented.
+    if contains(&[
+        "alert bad certificate",
+        "alert certificate required",
+        "alert certificate unknown",
+        "alert certificate revoked",
+        "alert unknown ca",
+    ]) {
+        return Some(TlsErrorKind::ClientCertificateRejected);
+    }
+
+    if !tls_code {
+        return None;
+    }
+
+    if contains(&[
+        "does not match target host",
+        "no alternative certificate subject name",
+        "hostname mismatch",
+        "notvalidforname",
+        "cn_no_match",
+    ]) {
+        return Some(TlsErrorKind::HostnameMismatch);
+    }
+
+    if contains(&["expired", "not yet valid", "notvalidyet"]) {
+        return Some(TlsErrorKind::CertificateExpired);
+    }
+
+    if contains(&[
+        "unable to get local issuer",
+        "unable to get issuer",
+        "unable to verify the first certificate",
+        "self signed",
+        "self-signed",
+        "unknownissuer",
+        "untrusted",
+        "unknown ca",
+    ]) {
+        return Some(TlsErrorKind::UntrustedIssuer);
+    }
+
+    match code {
+        curl_sys::CURLE_SSL_CONNECT_ERROR => Some(TlsErrorKind::Handshake),
+        curl_sys::CURLE_SSL_ISSUER_ERROR => Some(TlsErrorKind::UntrustedIssuer),
+        _ => None,
+    }
+}
+
 trait SourceError: StdError + Send + Sync + 'static {
     /// Get the type name of the concrete error type when the parent error was
     /// created. Used for enriching the debug formatting.
@@ -526,4 +804,53 @@ mod tests {
     use super::*;
 
     static_assertions::assert_impl_all!(Error: Send, Sync);
+
+    #[test]
+    fn tls_errors_are_classified() {
+        let classify = |code, detail| classify_tls_error(code, Some(detail));
+
+        assert_eq!(
+            classify(
+                curl_sys::CURLE_PEER_FAILED_VERIFICATION,
+                "SSL certificate problem: certificate has expired"
+            ),
+            Some(TlsErrorKind::CertificateExpired)
+        );
+        assert_eq!(
+            classify(
+                curl_sys::CURLE_PEER_FAILED_VERIFICATION,
+                "SSL certificate problem: self-signed certificate"
+            ),
+            Some(TlsErrorKind::UntrustedIssuer)
+        );
+        assert_eq!(
+            classify(
+                curl_sys::CURLE_PEER_FAILED_VERIFICATION,
+                "SSL: no alternative certificate subject name matches target host name 'example.org'"
+            ),
+            Some(TlsErrorKind::HostnameMismatch)
+        );
+        assert_eq!(
+            classify(
+                curl_sys::CURLE_SSL_CONNECT_ERROR,
+                "error:0A00010B:SSL routines::wrong version number"
+            ),
+            Some(TlsErrorKind::Handshake)
+        );
+        assert_eq!(
+            classify(
+                curl_sys::CURLE_RECV_ERROR,
+                "OpenSSL SSL_read: error:0A00045C:SSL routines::tlsv13 alert certificate required"
+            ),
+            Some(TlsErrorKind::ClientCertificateRejected)
+        );
+        assert_eq!(
+            classify(curl_sys::CURLE_RECV_ERROR, "Connection reset by peer"),
+            None
+        );
+        assert_eq!(
+            classify(curl_sys::CURLE_COULDNT_CONNECT, "certificate has expired"),
+            None
+        );
+    }
 }
@@ -0,0 +1,287 @@
+//! Structured logging of request lifecycle events as JSON lines.
+
+use crate::{
+    body::AsyncBody,
+    config::request::RequestConfig,
+    error::Error,
+    interceptor::{Context, Interceptor, InterceptorFuture},
+};
+use futures_lite::io::AsyncRead;
+use http::{Request, Response};
+use std::{
+    fmt::{self, Write as _},
+    io::{self, Write},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+        Mutex,
+    },
+    task::{Context as TaskContext, Poll},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// A destination for request lifecycle events, shared by all requests sent by
+/// a client.
+#[derive(Clone)]
+pub(crate) struct EventLog {
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+/// A value of a field of an event.
+enum Value<'a> {
+    Str(&'a str),
+    Int(u64),
+    Millis(f64),
+}
+
+impl EventLog {
+    pub(crate) fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Arc::new(Mutex::new(Box::new(writer))),
+            next_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    /// Write an event with the given name and fields as a single line of JSON.
+    fn emit(&self, event: &str, id: u64, fields: &[(&str, Value<'_>)]) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |duration| duration.as_secs_f64());
+
+        let mut line = String::new();
+        line.push_str("{\"event\":");
+        write_str(&mut line, event);
+        let _ = write!(line, ",\"id\":{},\"timestamp\":{:.6}", id, timestamp);
+
+        for (name, value) in fields {
+            line.push(',');
+            write_str(&mut line, name);
+            line.push(':');
+
+            match value {
+                Value::Str(s) => write_str(&mut line, s),
+                Value::Int(n) => {
+                    let _ = write!(line, "{}", n);
+                }
+                Value::Millis(ms) => {
+                    let _ = write!(line, "{:.3}", ms);
+                }
+            }
+        }
+
+        line.push_str("}\n");
+
+        let mut writer = self.writer.lock().unwrap();
+
+        if let Err(e) = writer
+            .write_all(line.as_bytes())
+            .and_then(|_| writer.flush())
+        {
+            tracing::debug!("failed to write event log: {}", e);
+        }
+    }
+}
+
+impl fmt::Debug for EventLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EventLog")
+    }
+}
+
+/// Append a string to a line of JSON as a quoted and escaped string.
+fn write_str(line: &mut String, s: &str) {
+    line.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => line.push_str("\\\""),
+            '\\' => line.push_str("\\\\"),
+            '\n' => line.push_str("\\n"),
+            '\r' => line.push_str("\\r"),
+            '\t' => line.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(line, "\\u{:04x}", c as u32);
+            }
+            c => line.push(c),
+        }
+    }
+
+    line.push('"');
+}
+
+fn millis_since(start: Instant) -> Value<'static> {
+    Value::Millis(start.elapsed().as_secs_f64() * 1000.0)
+}
+
+/// Interceptor that writes lifecycle events of requests to the configured
+/// event log, if any.
+pub(crate) struct EventLogInterceptor;
+
+impl Interceptor for EventLogInterceptor {
+    type Err = Error;
+
+    fn intercept<'a>(
+        &'a self,
+        request: Request<AsyncBody>,
+        ctx: Context<'a>,
+    ) -> InterceptorFuture<'a, Self::Err> {
+        Box::pin(async move {
+            let log = match request
+                .extensions()
+                .get::<RequestConfig>()
+                .and_then(|config| config.event_log.clone())
+            {
+                Some(log) => log,
+                None => return ctx.send(request).await,
+            };
+
+            let id = log.next_id.fetch_add(1, Ordering::Relaxed);
+            let start = Instant::now();
+
+            log.emit(
+                "request",
+                id,
+                &[
+                    ("method", Value::Str(request.method().as_str())),
+                    ("uri", Value::Str(&request.uri().to_string())),
+                ],
+            );
+
+            let response = match ctx.send(request).await {
+                Ok(response) => response,
+                Err(e) => {
+                    let kind = format!("{:?}", e.kind());
+                    let message = e.to_string();
+                    let cancel_reason = e.cancel_reason().map(|reason| reason.to_string());
+                    let mut fields = vec![
+                        ("kind", Value::Str(&kind)),
+                        ("message", Value::Str(&message)),
+                        ("elapsed_ms", millis_since(start)),
+                    ];
+
+                    if let Some(reason) = cancel_reason.as_deref() {
+                        fields.push(("cancel_reason", Value::Str(reason)));
+                    }
+
+                    log.emit("error", id, &fields);
+
+                    return Err(e);
+                }
+            };
+
+            log.emit(
+                "response",
+                id,
+                &[
+                    ("status", Value::Int(response.status().as_u16().into())),
+                    ("version", Value::Str(&format!("{:?}", response.version()))),
+                    ("elapsed_ms", millis_since(start)),
+                ],
+            );
+
+            let (parts, body) = response.into_parts();
+
+            // Empty bodies are complete already, and must stay empty.
+            if body.is_empty() {
+                log.emit(
+                    "body_complete",
+                    id,
+                    &[("bytes", Value::Int(0)), ("elapsed_ms", millis_since(start))],
+                );
+
+                return Ok(Response::from_parts(parts, body));
+            }
+
+            let len = body.len();
+            let body = LoggedBody {
+                inner: body,
+                log,
+                id,
+                start,
+                bytes: 0,
+                done: false,
+            };
+
+            let body = match len {
+                Some(len) => AsyncBody::from_reader_sized(body, len),
+                None => AsyncBody::from_reader(body),
+            };
+
+            Ok(Response::from_parts(parts, body))
+        })
+    }
+}
+
+/// Response body wrapper that logs when the body has been read completely, or
+/// could not be.
+struct LoggedBody {
+    inner: AsyncBody,
+    log: EventLog,
+    id: u64,
+    start: Instant,
+    bytes: u64,
+    done: bool,
+}
+
+impl LoggedBody {
+    fn finish(&mut self, event: &str, error: Option<&io::Error>) {
+        if self.done {
+            return;
+        }
+
+        self.done = true;
+
+        let message = error.map(ToString::to_string);
+        let mut fields = vec![
+            ("bytes", Value::Int(self.bytes)),
+            ("elapsed_ms", millis_since(self.start)),
+        ];
+
+        if let Some(message) = message.as_deref() {
+            fields.push(("message", Value::Str(message)));
+        }
+
+        self.log.emit(event, self.id, &fields);
+    }
+}
+
+impl AsyncRead for LoggedBody {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let result = Pin::new(&mut self.inner).poll_read(cx, buf);
+
+        match &result {
+            Poll::Ready(Ok(0)) if !buf.is_empty() => self.finish("body_complete", None),
+            Poll::Ready(Ok(len)) => self.bytes += *len as u64,
+            Poll::Ready(Err(e)) => self.finish("body_error", Some(e)),
+            Poll::Pending => {}
+        }
+
+        result
+    }
+}
+
+impl Drop for LoggedBody {
+    fn drop(&mut self) {
+        self.finish("body_dropped", None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strings_are_escaped() {
+        let mut line = String::new();
+
+        write_str(&mut line, "a \"quoted\"\\path\n\u{1}");
+
+        assert_eq!(line, r#""a \"quoted\"\\path\n\u0001""#);
+    }
+}
@@ -0,0 +1,45 @@
+use crate::{
+    body::AsyncBody,
+    config::request::RequestConfig,
+    error::{Error, ErrorKind},
+    interceptor::{Context, Interceptor, InterceptorFuture},
+};
+use futures_lite::io::AsyncReadExt;
+use http::{Request, Response};
+
+/// Interceptor that turns responses with an error status code into errors when
+/// requested, capturing the beginning of the response body in the error.
+pub(crate) struct FailWithBodyInterceptor;
+
+impl Interceptor for FailWithBodyInterceptor {
+    type Err = Error;
+
+    fn intercept<'a>(
+        &'a self,
+        request: Request<AsyncBody>,
+        ctx: Context<'a>,
+    ) -> InterceptorFuture<'a, Self::Err> {
+        Box::pin(async move {
+            let limit = request
+                .extensions()
+                .get::<RequestConfig>()
+                .and_then(|config| config.fail_with_body);
+
+            let response = ctx.send(request).await?;
+
+            let limit = match limit {
+                Some(limit) if response.status().as_u16() >= 400 => limit,
+                _ => return Ok(response),
+            };
+
+            let (parts, body) = response.into_parts();
+            let mut buf = Vec::new();
+
+            body.take(limit as u64).read_to_end(&mut buf).await?;
+
+            let response = Response::from_parts(parts, buf);
+
+            Err(Error::with_response(ErrorKind::BadStatus, &response).with_response_body(response))
+        })
+    }
+}
@@ -0,0 +1,161 @@
+//! Serializing request bodies as HTML forms.
+
+use crate::error::{Error, ErrorKind};
+use serde::{
+    de::{Deserialize, Deserializer, MapAccess, Visitor},
+    Serialize,
+};
+use serde_json::Value;
+use std::{fmt, io};
+use url::form_urlencoded;
+
+/// The fields of a value serialized as JSON, in the order they were
+/// serialized in.
+struct Fields(Vec<(String, Value)>);
+
+impl<'de> Deserialize<'de> for Fields {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FieldsVisitor;
+
+        impl<'de> Visitor<'de> for FieldsVisitor {
+            type Value = Fields;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a struct or map")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Fields, A::Error> {
+                let mut fields = Vec::new();
+
+                while let Some(field) = map.next_entry()? {
+                    fields.push(field);
+                }
+
+                Ok(Fields(fields))
+            }
+        }
+
+        deserializer.deserialize_map(FieldsVisitor)
+    }
+}
+
+/// Serialize a struct or map as an `application/x-www-form-urlencoded` body.
+///
+/// Fields must be strings, numbers, or booleans, or sequences of them, which
+/// are sent as one field per element. Fields that are `None` are omitted.
+pub(crate) fn serialize<T>(value: &T) -> Result<String, Error>
+where
+    T: Serialize + ?Sized,
+{
+    // Go through JSON to reuse its serializer, parsing the result back in a
+    // way that keeps the fields in order.
+    let json = serde_json::to_string(value).map_err(|e| Error::new(ErrorKind::InvalidRequest, e))?;
+    let fields = serde_json::from_str::<Fields>(&json)
+        .map_err(|e| Error::new(ErrorKind::InvalidRequest, e))?;
+
+    let mut serializer = form_urlencoded::Serializer::new(String::new());
+
+    for (name, value) in fields.0 {
+        match value {
+            Value::Array(values) => {
+                for value in values {
+                    append_field(&mut serializer, &name, value)?;
+                }
+            }
+            value => append_field(&mut serializer, &name, value)?,
+        }
+    }
+
+    Ok(serializer.finish())
+}
+
+fn append_field(
+    serializer: &mut form_urlencoded::Serializer<'_, String>,
+    name: &str,
+    value: Value,
+) -> Result<(), Error> {
+    match value {
+        Value::Null => {}
+        Value::Bool(value) => {
+            serializer.append_pair(name, if value { "true" } else { "false" });
+        }
+        Value::Number(value) => {
+            serializer.append_pair(name, &value.to_string());
+        }
+        Value::String(value) => {
+            serializer.append_pair(name, &value);
+        }
+        Value::Array(_) | Value::Object(_) => {
+            return Err(Error::new(
+                ErrorKind::InvalidRequest,
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("form field `{}` cannot be nested", name),
+                ),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    struct Login {
+        user: &'static str,
+        password: &'static str,
+    }
+
+    impl Serialize for Login {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            use serde::ser::SerializeStruct;
+
+            let mut state = serializer.serialize_struct("Login", 2)?;
+            state.serialize_field("user", self.user)?;
+            state.serialize_field("password", self.password)?;
+            state.end()
+        }
+    }
+
+    #[test]
+    fn fields_keep_their_order() {
+        let login = Login {
+            user: "jane",
+            password: "hunter2",
+        };
+
+        assert_eq!(serialize(&login).unwrap(), "user=jane&password=hunter2");
+    }
+
+    #[test]
+    fn non_struct_values_are_rejected() {
+        assert_eq!(serialize(&["a", "b"]).unwrap_err().kind(), &ErrorKind::InvalidRequest);
+        assert_eq!(serialize("a=b").unwrap_err().kind(), &ErrorKind::InvalidRequest);
+    }
+
+    #[test]
+    fn scalars_and_sequences() {
+        let value = json!({
+            "name": "Jane Doe",
+            "age": 42,
+            "admin": false,
+            "nickname": null,
+            "tags": ["a&b", "c"],
+        });
+
+        assert_eq!(
+            serialize(&value).unwrap(),
+            "admin=false&age=42&name=Jane+Doe&tags=a%26b&tags=c"
+        );
+    }
+
+    #[test]
+    fn nested_fields_are_rejected() {
+        let value = json!({ "user": { "name": "Jane" } });
+
+        assert_eq!(serialize(&value).unwrap_err().kind(), &ErrorKind::InvalidRequest);
+    }
+}
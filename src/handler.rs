@@ -1,35 +1,81 @@
 #![allow(unsafe_code)]
 
 use crate::{
+    agent::Liveness,
     body::AsyncBody,
+    cancel::{CancelOnDrop, CancelReason, Cancellation},
+    connect_attempt::ConnectAttempts,
+    config::{
+        checksum::Hasher,
+        clock::SharedClock,
+        decompression::DecompressionLimitExceeded,
+        dial::TransportSocket,
+        instrument::SharedInstrumentation,
+        AgentEventKind,
+        BodyChecksum,
+        DecompressionLimit,
+        QuotaTag,
+    },
     error::{Error, ErrorKind},
-    metrics::Metrics,
+    headers::HasHeaders,
+    metrics::{
+        EarlyResponse,
+        Metrics,
+        Progress,
+        ProgressCallback,
+        StatsCallback,
+        Timestamps,
+        TransferStats,
+    },
     parsing::{parse_header, parse_status_line},
     response::{LocalAddr, RemoteAddr},
+    sniff::{self, SniffedContentType},
     trailer::TrailerWriter,
+    transfer_log::{TransferLog, TransferMessageKind},
+    upgrade::UpgradeWriter,
 };
 use async_channel::Sender;
+use bytes::Bytes;
 use curl::easy::{InfoType, ReadError, SeekResult, WriteError};
 use curl_sys::CURL;
-use futures_lite::io::{AsyncRead, AsyncWrite};
+use event_listener::EventListener;
+use futures_lite::{
+    future,
+    io::{AsyncRead, AsyncWrite},
+};
 use http::Response;
 use once_cell::sync::OnceCell;
 use sluice::pipe;
 use std::{
     ascii,
-    ffi::CStr,
+    collections::HashMap,
+    ffi::{c_void, CStr, CString},
     fmt,
     future::Future,
     io,
     mem,
     net::SocketAddr,
-    os::raw::{c_char, c_long},
+    os::raw::{c_char, c_int, c_long},
     pin::Pin,
     ptr,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+        Mutex,
+    },
     task::{Context, Poll, Waker},
+    time::{Duration, Instant},
 };
 
+/// Options for sending trailers after a chunked request body, which are not
+/// exposed by curl-sys.
+const CURLOPT_TRAILERFUNCTION: curl_sys::CURLoption = curl_sys::CURLOPTTYPE_FUNCTIONPOINT + 283;
+const CURLOPT_TRAILERDATA: curl_sys::CURLoption = curl_sys::CURLOPTTYPE_OBJECTPOINT + 284;
+
+/// Return values of a trailer callback.
+const CURL_TRAILERFUNC_OK: c_int = 0;
+const CURL_TRAILERFUNC_ABORT: c_int = 1;
+
 pub(crate) struct RequestBody(pub(crate) AsyncBody);
 
 /// Manages the state of a single request/response life cycle.
@@ -69,6 +115,23 @@ pub(crate) struct RequestHandler {
     /// an agent when the request is initialized.
     request_body_waker: Option<Waker>,
 
+    /// Number of bytes of the request body read by curl so far, if it has
+    /// started reading it.
+    request_body_read: Option<u64>,
+
+    /// Whether curl has read the request body to the end.
+    request_body_finished: bool,
+
+    /// Set if the server responded before receiving all of the request body.
+    early_response: Option<EarlyResponse>,
+
+    /// Whether the rest of the request body is withheld from curl.
+    request_body_stopped: bool,
+
+    /// Checksum of the request body to send in a trailer, computed as curl
+    /// reads the body.
+    request_body_checksum: Option<(BodyChecksum, Hasher)>,
+
     /// Status code of the response.
     response_status_code: Option<http::StatusCode>,
 
@@ -92,12 +155,135 @@ pub(crate) struct RequestHandler {
     /// Metrics object for publishing metrics data to. Lazily initialized.
     metrics: Option<Metrics>,
 
+    /// A user-provided flag that aborts the transfer once set.
+    interrupt_flag: Option<Arc<AtomicBool>>,
+
+    /// Cancellation state of the request, shared with its cancel handles and
+    /// response future.
+    cancellation: Arc<Cancellation>,
+
+    /// A user-provided callback to receive the final transfer statistics.
+    on_stats: Option<StatsCallback>,
+
+    /// Where to run user callbacks and complete the response future, if not
+    /// inline on the agent thread.
+    callback_executor: Option<CallbackExecutor>,
+
+    /// Name of the quota this transfer is assigned to, if any.
+    quota: Option<QuotaTag>,
+
+    /// Priority of this transfer while waiting to begin.
+    priority: i32,
+
+    /// Name and port of the host the transfer is sent to, for pacing request
+    /// starts.
+    host_key: Option<String>,
+
+    /// Whether to sniff the content type of the response body.
+    sniff_content_type: bool,
+
+    /// Content type sniffed from the first chunk of the response body, if any.
+    sniffed_content_type: Option<String>,
+
+    /// When the request was submitted to the agent.
+    submitted_at: Option<Instant>,
+
+    /// Where to report lifecycle events of this request, if anywhere.
+    instrumentation: Option<SharedInstrumentation>,
+
+    /// ID of this request and when it was enqueued according to the system
+    /// clock, as reported to the instrumentation.
+    instrumented_request: Option<(u64, Instant)>,
+
+    /// Whether the connection used by this transfer has been reported to the
+    /// instrumentation.
+    connection_reported: bool,
+
+    /// Connection attempts in progress, tracked if the transfer is
+    /// instrumented.
+    connect_attempts: ConnectAttempts,
+
+    /// When the agent began executing the request.
+    dequeued_at: Option<Instant>,
+
+    /// When connecting for the request first failed because a resource limit
+    /// was hit, if it has.
+    exhausted_since: Option<Instant>,
+
+    /// Recent messages curl reported about the transfer, if kept.
+    transfer_log: Option<TransferLog>,
+
+    /// When the header of the last non-informational response was received.
+    headers_received_at: Option<Instant>,
+
+    /// Clock of the client that sent the request, used for timestamps.
+    clock: SharedClock,
+
+    /// Maximum time to wait for the response header to begin.
+    headers_timeout: Option<Duration>,
+
+    /// Set once the first byte of the response header has been received.
+    headers_started: bool,
+
+    /// Whether metrics should be collected for this transfer.
+    collect_metrics: bool,
+
+    /// Callback to deliver progress updates to.
+    on_progress: Option<ProgressCallback>,
+
+    /// Progress last delivered to the progress callback.
+    last_progress: Progress,
+
+    /// Callback to deliver the response body to instead of the response body
+    /// reader.
+    on_body_chunk: Option<BodyChunkCallback>,
+
+    /// Limit on the number of requests sent over each connection.
+    connection_request_limit: Option<Arc<ConnectionRequestLimit>>,
+
+    /// Set if the connection will be closed after this transfer because it
+    /// reached its request limit.
+    connection_retired: bool,
+
+    /// Writing half of the request body of a request asking to switch
+    /// protocols. Handed over with the response if the server agrees to
+    /// switch, and dropped to end the request body otherwise.
+    upgrade_writer: Option<pipe::PipeWriter>,
+
+    /// Caller-provided stream to send this transfer over, if any. Curl holds a
+    /// pointer to it until the transfer completes.
+    transport: Option<Box<TransportSocket>>,
+
+    /// Limit on how much a compressed response body may expand, if any.
+    decompression_limit: Option<DecompressionLimit>,
+
+    /// Whether the response body has a content encoding that curl decodes.
+    is_response_encoded: bool,
+
+    /// Number of response body bytes received after decompression.
+    decompressed_bytes: u64,
+
+    /// Set once the response body exceeds the decompression limit, so that the
+    /// agent aborts the transfer.
+    decompression_limit_exceeded: bool,
+
     /// Raw pointer to the associated curl easy handle. The pointer is not owned
     /// by this struct, but the parent struct to this one, so we know it will be
     /// valid at least for the lifetime of this struct (assuming all other
     /// invariants are upheld).
     handle: *mut CURL,
 
+    /// Maximum number of response body bytes this transfer may receive during
+    /// a single turn of the agent event loop. Zero means no limit.
+    write_quantum: usize,
+
+    /// Number of response body bytes received so far during the current turn.
+    write_quantum_used: usize,
+
+    /// Set when the transfer has been paused because it used up its quantum
+    /// for the current turn.
+    write_quantum_exhausted: bool,
+
     /// If true, do not warn about prematurely closed responses.
     pub(crate) disable_connection_reuse_log: bool,
 }
@@ -114,12 +300,201 @@ struct Shared {
     /// to communicate an error while reading the response body if the handler
     /// suddenly aborts.
     result: OnceCell<Result<(), Error>>,
+
+    /// Number of response body bytes written into the response body buffer
+    /// that have not been read yet.
+    buffered: AtomicUsize,
+
+    /// Client-wide budget that buffered response body bytes count against, if
+    /// any.
+    budget: OnceCell<Arc<BufferBudget>>,
+
+    /// Liveness of the agent executing the request, if known.
+    agent: OnceCell<Arc<Liveness>>,
+}
+
+impl Shared {
+    fn is_agent_crashed(&self) -> bool {
+        self.agent
+            .get()
+            .map(|agent| agent.is_crashed())
+            .unwrap_or(false)
+    }
+
+    /// Check whether buffering the given number of additional bytes would go
+    /// over the buffer budget.
+    ///
+    /// A response with nothing buffered is always allowed to buffer more, even
+    /// if the budget is exceeded. Otherwise a consumer that waits on one
+    /// response before reading others could wait forever.
+    fn would_exceed_budget(&self, len: usize) -> bool {
+        match self.budget.get() {
+            Some(budget) => self.buffered.load(Ordering::SeqCst) > 0 && !budget.has_room(len),
+            None => false,
+        }
+    }
+
+    fn acquire_buffered(&self, len: usize) {
+        self.buffered.fetch_add(len, Ordering::SeqCst);
+
+        if let Some(budget) = self.budget.get() {
+            budget.acquire(len);
+        }
+    }
+
+    fn release_buffered(&self, len: usize) {
+        self.buffered.fetch_sub(len, Ordering::SeqCst);
+
+        if let Some(budget) = self.budget.get() {
+            budget.release(len);
+        }
+    }
+}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        // Both the handler and the reader are gone, so anything left in the
+        // buffer has been freed.
+        if let Some(budget) = self.budget.get() {
+            budget.release(*self.buffered.get_mut());
+        }
+    }
+}
+
+/// A user-provided function that runs callbacks and completes response
+/// futures somewhere other than on the agent thread.
+#[derive(Clone)]
+pub(crate) struct CallbackExecutor(pub(crate) Arc<dyn Fn(Task) + Send + Sync>);
+
+/// A unit of work given to a [`CallbackExecutor`].
+pub(crate) type Task = Box<dyn FnOnce() + Send>;
+
+impl CallbackExecutor {
+    fn execute(&self, f: impl FnOnce() + Send + 'static) {
+        (self.0)(Box::new(f));
+    }
+}
+
+impl fmt::Debug for CallbackExecutor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CallbackExecutor")
+    }
+}
+
+/// A callback receiving chunks of the response body as they arrive.
+#[derive(Clone)]
+pub(crate) struct BodyChunkCallback(pub(crate) Arc<dyn Fn(Bytes) + Send + Sync>);
+
+impl fmt::Debug for BodyChunkCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("BodyChunkCallback")
+    }
+}
+
+/// Limits how many requests may be sent over each connection.
+///
+/// Connections are identified by their local and remote address. A transfer
+/// that opened a new connection starts counting afresh, so an entry left
+/// behind by a connection closed for other reasons is simply reset once its
+/// address is taken by a new connection.
+#[derive(Debug)]
+pub(crate) struct ConnectionRequestLimit {
+    max: usize,
+    counts: Mutex<HashMap<(SocketAddr, SocketAddr), usize>>,
+}
+
+impl ConnectionRequestLimit {
+    pub(crate) fn new(max: usize) -> Self {
+        Self {
+            max,
+            counts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record a request sent over the given connection.
+    ///
+    /// Returns true if the connection has now carried the maximum number of
+    /// requests and must be closed once the transfer completes.
+    fn record(&self, connection: (SocketAddr, SocketAddr), is_new: bool) -> bool {
+        let mut counts = self.counts.lock().unwrap();
+        let count = counts.entry(connection).or_default();
+
+        if is_new {
+            *count = 0;
+        }
+
+        *count += 1;
+
+        if *count >= self.max {
+            counts.remove(&connection);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// A limit on the total number of response body bytes that may be buffered in
+/// memory at once across all responses sharing the budget, waiting to be read
+/// by consumers.
+///
+/// Transfers that would exceed the budget are paused until consumers catch up
+/// and free up room.
+#[derive(Debug)]
+pub(crate) struct BufferBudget {
+    limit: usize,
+    used: AtomicUsize,
+
+    /// Wakers for transfers paused waiting for room in the budget.
+    waiting: Mutex<Vec<Waker>>,
+}
+
+impl BufferBudget {
+    pub(crate) fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            used: AtomicUsize::new(0),
+            waiting: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn has_room(&self, len: usize) -> bool {
+        self.used.load(Ordering::SeqCst).saturating_add(len) <= self.limit
+    }
+
+    fn acquire(&self, len: usize) {
+        self.used.fetch_add(len, Ordering::SeqCst);
+    }
+
+    fn release(&self, len: usize) {
+        if len == 0 {
+            return;
+        }
+
+        self.used.fetch_sub(len, Ordering::SeqCst);
+
+        // Let all waiting transfers try again. Any that still do not fit will
+        // simply wait again.
+        let waiting = mem::take(&mut *self.waiting.lock().unwrap());
+
+        for waker in waiting {
+            waker.wake();
+        }
+    }
+
+    /// Register a waker to be woken when room is made in the budget.
+    fn wait(&self, waker: &Waker) {
+        self.waiting.lock().unwrap().push(waker.clone());
+    }
 }
 
 impl RequestHandler {
     /// Create a new request handler and an associated response future.
+    ///
+    /// Dropping the response future before it completes cancels the request.
     pub(crate) fn new(
         request_body: AsyncBody,
+        cancellation: Arc<Cancellation>,
     ) -> (
         Self,
         impl Future<Output = Result<Response<ResponseBodyReader>, Error>>,
@@ -134,6 +509,11 @@ impl RequestHandler {
             shared: shared.clone(),
             request_body,
             request_body_waker: None,
+            request_body_read: None,
+            request_body_finished: false,
+            early_response: None,
+            request_body_stopped: false,
+            request_body_checksum: None,
             response_status_code: None,
             response_version: None,
             response_headers: http::HeaderMap::new(),
@@ -141,21 +521,83 @@ impl RequestHandler {
             response_body_waker: None,
             response_trailer_writer: TrailerWriter::new(),
             metrics: None,
+            interrupt_flag: None,
+            cancellation: cancellation.clone(),
+            on_stats: None,
+            callback_executor: None,
+            quota: None,
+            priority: 0,
+            host_key: None,
+            sniff_content_type: false,
+            sniffed_content_type: None,
+            submitted_at: None,
+            instrumentation: None,
+            instrumented_request: None,
+            connection_reported: false,
+            connect_attempts: ConnectAttempts::default(),
+            dequeued_at: None,
+            exhausted_since: None,
+            transfer_log: None,
+            headers_received_at: None,
+            clock: SharedClock::default(),
+            headers_timeout: None,
+            headers_started: false,
+            collect_metrics: false,
+            on_progress: None,
+            last_progress: Progress::default(),
+            on_body_chunk: None,
+            connection_request_limit: None,
+            connection_retired: false,
+            upgrade_writer: None,
+            transport: None,
+            decompression_limit: None,
+            is_response_encoded: false,
+            decompressed_bytes: 0,
+            decompression_limit_exceeded: false,
             handle: ptr::null_mut(),
+            write_quantum: 0,
+            write_quantum_used: 0,
+            write_quantum_exhausted: false,
             disable_connection_reuse_log: false,
         };
 
+        let cancel_on_drop = CancelOnDrop::new(cancellation);
+
         // Create a future that resolves when the handler receives the response
         // headers.
         let future = async move {
-            let builder = receiver
-                .recv()
-                .await
-                .map_err(|e| Error::new(ErrorKind::Unknown, e))??;
+            let received = async {
+                receiver.recv().await.map_err(|e| {
+                    if shared.is_agent_crashed() {
+                        Error::new(ErrorKind::AgentCrashed, e)
+                    } else {
+                        Error::new(ErrorKind::Unknown, e)
+                    }
+                })
+            };
+
+            // Don't wait forever for a response that will never come if the
+            // agent stops responding.
+            let crashed = async {
+                match shared.agent.get() {
+                    Some(agent) => agent.crashed().await,
+                    None => future::pending().await,
+                }
+
+                Err(Error::from(ErrorKind::AgentCrashed))
+            };
+
+            let builder = future::or(received, crashed).await;
+
+            // The request is no longer canceled by dropping this future.
+            cancel_on_drop.disarm();
+
+            let builder = builder??;
 
             let reader = ResponseBodyReader {
                 inner: response_body_reader,
                 shared,
+                agent_listener: None,
             };
 
             builder
@@ -199,6 +641,7 @@ impl RequestHandler {
         handle: *mut CURL,
         request_waker: Waker,
         response_waker: Waker,
+        cancel_waker: Waker,
     ) {
         let _enter = self.span.enter();
 
@@ -206,10 +649,399 @@ impl RequestHandler {
         debug_assert!(self.request_body_waker.is_none());
         debug_assert!(self.response_body_waker.is_none());
 
-        self.span.record("id", &id);
+        self.span.record("id", id);
         self.handle = handle;
         self.request_body_waker = Some(request_waker);
         self.response_body_waker = Some(response_waker);
+        self.cancellation.set_waker(cancel_waker);
+
+        // Handles are reused, so the trailer callback is also cleared for
+        // requests that do not send a checksum.
+        let (callback, data) = if self.request_body_checksum.is_some() {
+            let callback: extern "C" fn(*mut *mut curl_sys::curl_slist, *mut c_void) -> c_int =
+                send_checksum_trailer;
+
+            (callback as *const c_void, self as *const Self as *mut c_void)
+        } else {
+            (ptr::null(), ptr::null_mut())
+        };
+
+        unsafe {
+            curl_sys::curl_easy_setopt(handle, CURLOPT_TRAILERFUNCTION, callback);
+            curl_sys::curl_easy_setopt(handle, CURLOPT_TRAILERDATA, data);
+        }
+    }
+
+    /// Undo initialization after the request was removed from the multi
+    /// handle without completing, so that it can begin again later.
+    pub(crate) fn deinit(&mut self) {
+        self.handle = ptr::null_mut();
+        self.request_body_waker = None;
+        self.response_body_waker = None;
+    }
+
+    /// Set the liveness tracker of the agent that will execute this request,
+    /// so that consumers of the response can be woken up if it crashes.
+    pub(crate) fn set_agent_liveness(&mut self, liveness: Arc<Liveness>) {
+        let _ = self.shared.agent.set(liveness);
+    }
+
+    /// Count response body bytes buffered by this transfer against the given
+    /// budget.
+    pub(crate) fn set_buffer_budget(&mut self, budget: Arc<BufferBudget>) {
+        let _ = self.shared.budget.set(budget);
+    }
+
+    /// Set a flag that, once set, indicates that the transfer should be
+    /// aborted.
+    pub(crate) fn set_interrupt_flag(&mut self, flag: Option<Arc<AtomicBool>>) {
+        self.interrupt_flag = flag;
+    }
+
+    /// Keep up to the given number of the most recent messages curl reports
+    /// about the transfer, to attach to an error if it fails.
+    pub(crate) fn set_transfer_log(&mut self, capacity: Option<usize>) {
+        self.transfer_log = capacity.filter(|&n| n > 0).map(TransferLog::new);
+    }
+
+    /// Returns true if messages curl reports about the transfer are kept.
+    pub(crate) fn has_transfer_log(&self) -> bool {
+        self.transfer_log.is_some()
+    }
+
+    /// Set a callback to deliver the final transfer statistics to.
+    pub(crate) fn set_on_stats(&mut self, callback: Option<StatsCallback>) {
+        self.on_stats = callback;
+    }
+
+    /// Set whether metrics should be collected for this transfer.
+    pub(crate) fn set_collect_metrics(&mut self, enable: bool) {
+        self.collect_metrics = enable;
+    }
+
+    /// Set a callback to deliver progress updates to.
+    pub(crate) fn set_on_progress(&mut self, callback: Option<ProgressCallback>) {
+        self.on_progress = callback;
+    }
+
+    /// Set a callback to deliver the response body to in chunks.
+    pub(crate) fn set_on_body_chunk(&mut self, callback: Option<BodyChunkCallback>) {
+        self.on_body_chunk = callback;
+    }
+
+    /// Set where to run user callbacks and complete the response future.
+    pub(crate) fn set_callback_executor(&mut self, executor: Option<CallbackExecutor>) {
+        self.callback_executor = executor;
+    }
+
+    /// Set where to report lifecycle events of this request.
+    pub(crate) fn set_instrumentation(&mut self, instrumentation: Option<SharedInstrumentation>) {
+        self.instrumentation = instrumentation;
+    }
+
+    /// Returns true if lifecycle events of this request are reported anywhere.
+    pub(crate) fn is_instrumented(&self) -> bool {
+        self.instrumentation.is_some()
+    }
+
+    /// Record when the request was submitted to the agent.
+    pub(crate) fn set_clock(&mut self, clock: SharedClock) {
+        self.clock = clock;
+    }
+
+    pub(crate) fn set_submitted_at(&mut self, now: Instant) {
+        self.submitted_at = Some(now);
+
+        if self.instrumentation.is_some() {
+            self.instrumented_request =
+                Some((SharedInstrumentation::next_request_id(), Instant::now()));
+            self.emit(AgentEventKind::RequestEnqueued);
+        }
+    }
+
+    /// Report a lifecycle event of this request to the instrumentation.
+    pub(crate) fn emit(&self, kind: AgentEventKind) {
+        if let Some(instrumentation) = self.instrumentation.as_ref() {
+            instrumentation.emit(kind, self.instrumented_request);
+        }
+    }
+
+    /// Record when the agent began executing the request.
+    pub(crate) fn set_dequeued_at(&mut self, now: Instant) {
+        self.dequeued_at = Some(now);
+    }
+
+    /// Get when the agent began executing the request, if it has.
+    pub(crate) fn dequeued_at(&self) -> Option<Instant> {
+        self.dequeued_at
+    }
+
+    /// Get the status code of the response received so far, if any.
+    pub(crate) fn response_status(&self) -> Option<http::StatusCode> {
+        self.response_status_code
+    }
+
+    /// Get how long it took from when the agent began executing the request
+    /// until the header of the final response was received, if it has been.
+    pub(crate) fn response_latency(&self) -> Option<Duration> {
+        Some(
+            self.headers_received_at?
+                .saturating_duration_since(self.dequeued_at?),
+        )
+    }
+
+    /// Record that connecting for the request failed because a resource limit
+    /// was hit, and get when that first happened.
+    pub(crate) fn set_exhausted_at(&mut self, now: Instant) -> Instant {
+        *self.exhausted_since.get_or_insert(now)
+    }
+
+    /// Set the maximum time to wait for the response header to begin.
+    pub(crate) fn set_headers_timeout(&mut self, timeout: Option<Duration>) {
+        self.headers_timeout = timeout;
+    }
+
+    /// Limit the number of requests sent over each connection.
+    pub(crate) fn set_connection_request_limit(
+        &mut self,
+        limit: Option<Arc<ConnectionRequestLimit>>,
+    ) {
+        self.connection_request_limit = limit;
+    }
+
+    /// Count this transfer against the request limit of its connection, and
+    /// forbid the connection from being reused if the limit has been reached.
+    ///
+    /// Curl decides whether to keep a connection only once the transfer is
+    /// done, so this takes effect even though the transfer is underway.
+    fn record_connection_use(&mut self) {
+        let limit = match self.connection_request_limit.clone() {
+            Some(limit) => limit,
+            None => return,
+        };
+
+        let connection = match (self.get_local_addr(), self.get_primary_addr()) {
+            (Some(local), Some(remote)) => (local, remote),
+            _ => return,
+        };
+
+        if limit.record(connection, self.get_num_connects() > 0) {
+            tracing::debug!(?connection, "connection reached its request limit");
+
+            unsafe {
+                curl_sys::curl_easy_setopt(
+                    self.handle,
+                    curl_sys::CURLOPT_FORBID_REUSE,
+                    1 as c_long,
+                );
+            }
+
+            self.connection_retired = true;
+        }
+    }
+
+    /// Stop sending the request body after the server responded before
+    /// receiving all of it.
+    ///
+    /// If curl has not started sending the body yet, it is still waiting to
+    /// find out whether to send it at all, such as when expecting a `100
+    /// Continue` response or negotiating authentication, and decides what to
+    /// do itself.
+    fn stop_sending_request_body(&mut self) {
+        if self.request_body.is_empty()
+            || self.request_body_finished
+            || self.early_response.is_some()
+        {
+            return;
+        }
+
+        self.early_response = Some(EarlyResponse {
+            bytes_sent: 0,
+            body_len: self.request_body.len(),
+        });
+
+        if self.request_body_read.is_none() {
+            return;
+        }
+
+        tracing::debug!("server responded before receiving the whole request body, stop sending");
+        self.request_body_stopped = true;
+
+        // The rest of a stream that cannot be sent again is of no use anymore,
+        // and dropping it lets whatever is writing into it know. Bodies that
+        // can be sent again are kept in case curl starts over.
+        if !self.request_body.is_repeatable() {
+            self.request_body = AsyncBody::from_reader(futures_lite::io::empty());
+        }
+
+        // The server cannot tell where the body it did not read ends, so the
+        // connection is out of step. Streams of an HTTP/2 connection are ended
+        // independently of the others.
+        if self.response_version.map_or(false, |version| version < http::Version::HTTP_2) {
+            unsafe {
+                curl_sys::curl_easy_setopt(
+                    self.handle,
+                    curl_sys::CURLOPT_FORBID_REUSE,
+                    1 as c_long,
+                );
+            }
+        }
+
+        // Curl does not ask for more of the body while waiting for it, so wake
+        // it up to find out that there is no more.
+        if let Some(waker) = self.request_body_waker.as_ref() {
+            waker.wake_by_ref();
+        }
+    }
+
+    /// Get the number of bytes of the request body curl has sent so far.
+    fn get_bytes_uploaded(&self) -> u64 {
+        let mut uploaded = 0f64;
+
+        if !self.handle.is_null() {
+            unsafe {
+                curl_sys::curl_easy_getinfo(
+                    self.handle,
+                    curl_sys::CURLINFO_SIZE_UPLOAD,
+                    &mut uploaded as *mut f64,
+                );
+            }
+        }
+
+        uploaded as u64
+    }
+
+    /// Get the point in time by which the response header must begin, if a
+    /// headers timeout applies and the server has not started responding yet.
+    pub(crate) fn headers_deadline(&self) -> Option<Instant> {
+        if self.headers_started {
+            return None;
+        }
+
+        Some(self.dequeued_at? + self.headers_timeout?)
+    }
+
+    /// Assign this transfer to a quota enforced by the agent.
+    pub(crate) fn set_quota(&mut self, quota: Option<QuotaTag>) {
+        self.quota = quota;
+    }
+
+    /// Set the writing half of the request body of a request asking to switch
+    /// protocols.
+    pub(crate) fn set_upgrade_writer(&mut self, writer: Option<pipe::PipeWriter>) {
+        self.upgrade_writer = writer;
+    }
+
+    /// Keep a stream that the transfer has been configured to use alive for as
+    /// long as the transfer.
+    pub(crate) fn set_transport(&mut self, transport: Box<TransportSocket>) {
+        self.transport = Some(transport);
+    }
+
+    /// Get the quota this transfer is assigned to, if any.
+    pub(crate) fn quota(&self) -> Option<&QuotaTag> {
+        self.quota.as_ref()
+    }
+
+    /// Set the priority of this transfer while it waits to begin.
+    pub(crate) fn set_priority(&mut self, priority: i32) {
+        self.priority = priority;
+    }
+
+    /// Get the priority of this transfer while it waits to begin.
+    pub(crate) fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    /// Set the name and port of the host the transfer is sent to.
+    pub(crate) fn set_host_key(&mut self, key: Option<String>) {
+        self.host_key = key;
+    }
+
+    /// Compute a checksum of the request body as it is sent, and send it in a
+    /// trailer afterwards.
+    pub(crate) fn set_request_body_checksum(&mut self, checksum: Option<BodyChecksum>) {
+        self.request_body_checksum = checksum.map(|checksum| (checksum, checksum.hasher()));
+    }
+
+    /// Get the name and port of the host the transfer is sent to, if known.
+    pub(crate) fn host_key(&self) -> Option<&str> {
+        self.host_key.as_deref()
+    }
+
+    /// Sniff the content type of the response body before returning the
+    /// response.
+    pub(crate) fn set_sniff_content_type(&mut self, enable: bool) {
+        self.sniff_content_type = enable;
+    }
+
+    /// Limit how much the response body may expand if it is decompressed.
+    pub(crate) fn set_decompression_limit(&mut self, limit: Option<DecompressionLimit>) {
+        self.decompression_limit = limit;
+    }
+
+    /// Returns true if the interrupt flag for this transfer has been set, if
+    /// the request has been canceled, or if the transfer must be aborted for
+    /// exceeding its decompression limit.
+    pub(crate) fn is_interrupted(&self) -> bool {
+        self.decompression_limit_exceeded
+            || self.cancellation.is_canceled()
+            || self
+                .interrupt_flag
+                .as_ref()
+                .map(|flag| flag.load(Ordering::SeqCst))
+                .unwrap_or(false)
+    }
+
+    /// Check whether receiving `len` more bytes of the response body would
+    /// exceed the decompression limit, if the body is being decompressed.
+    fn check_decompression_limit(&self, len: usize) -> Result<(), DecompressionLimitExceeded> {
+        let limit = match self.decompression_limit.as_ref() {
+            Some(limit) => limit,
+            None => return Ok(()),
+        };
+
+        if !self.is_response_encoded {
+            return Ok(());
+        }
+
+        // Curl counts the bytes it receives before decoding them.
+        let mut compressed = 0f64;
+
+        if !self.handle.is_null() {
+            unsafe {
+                curl_sys::curl_easy_getinfo(
+                    self.handle,
+                    curl_sys::CURLINFO_SIZE_DOWNLOAD,
+                    &mut compressed as *mut f64,
+                );
+            }
+        }
+
+        limit.check(compressed as u64, self.decompressed_bytes + len as u64)
+    }
+
+    /// Limit how many response body bytes this transfer may receive per turn of
+    /// the agent event loop. Once the limit is reached the transfer is paused
+    /// until the next turn, giving other transfers a chance to make progress.
+    pub(crate) fn set_write_quantum(&mut self, quantum: usize) {
+        self.write_quantum = quantum;
+    }
+
+    /// Returns true if the transfer is currently paused because it used up its
+    /// quantum for the current turn.
+    pub(crate) fn is_write_quantum_exhausted(&self) -> bool {
+        self.write_quantum_exhausted
+    }
+
+    /// Reset the quantum for a new turn of the agent event loop.
+    ///
+    /// Returns true if the transfer was paused because it exhausted its
+    /// quantum during the previous turn, in which case the caller is
+    /// responsible for unpausing it.
+    pub(crate) fn begin_turn(&mut self) -> bool {
+        self.write_quantum_used = 0;
+        mem::take(&mut self.write_quantum_exhausted)
     }
 
     /// Set the final result for this transfer.
@@ -223,11 +1055,60 @@ impl RequestHandler {
                 e = e.with_remote_addr(addr);
             }
 
+            if let Some(log) = self.transfer_log.as_mut() {
+                e = e.with_transfer_messages(log.take());
+            }
+
+            // Requests are only failed as closed while the client shuts down.
+            let cancel_reason = match e.kind() {
+                ErrorKind::Interrupted => self.cancellation.reason(),
+                ErrorKind::ClientClosed => Some(CancelReason::Shutdown),
+                _ => None,
+            };
+
+            if let Some(reason) = cancel_reason {
+                e = e.with_cancel_reason(reason);
+            }
+
             e
         });
 
+        // Deliver the final statistics before anyone can observe completion,
+        // unless they are delivered by an executor.
+        if let Some(callback) = self.on_stats.take() {
+            let stats = self.get_transfer_stats(result.as_ref().err().cloned());
+
+            match self.callback_executor.as_ref() {
+                Some(executor) => executor.execute(move || (callback.0)(stats)),
+                None => (callback.0)(stats),
+            }
+        }
+
+        if self.instrumentation.is_some() {
+            for event in self
+                .connect_attempts
+                .on_complete(result.is_ok(), Instant::now())
+            {
+                self.emit(event);
+            }
+        }
+
+        let kind = match result.as_ref() {
+            Ok(()) => AgentEventKind::RequestCompleted,
+            Err(e) => AgentEventKind::RequestFailed {
+                error: e.kind().clone(),
+            },
+        };
+        let cancel_reason = result.as_ref().err().and_then(Error::cancel_reason);
+
         if self.shared.result.set(result).is_err() {
             tracing::debug!("attempted to set error multiple times");
+        } else {
+            if let Some(reason) = cancel_reason {
+                self.emit(AgentEventKind::RequestCanceled { reason });
+            }
+
+            self.emit(kind);
         }
 
         // Flush the trailer, if we haven't already.
@@ -251,8 +1132,17 @@ impl RequestHandler {
                 Ok(self.build_response())
             };
 
-            if sender.try_send(result).is_err() {
-                tracing::debug!("request canceled by user");
+            // Sending the result wakes up the task waiting for the response,
+            // which may be expensive depending on the waker.
+            let complete = move || {
+                if sender.try_send(result).is_err() {
+                    tracing::debug!("request canceled by user");
+                }
+            };
+
+            match self.callback_executor.as_ref() {
+                Some(executor) => executor.execute(complete),
+                None => complete(),
             }
         }
     }
@@ -280,23 +1170,157 @@ impl RequestHandler {
             builder = builder.extension(RemoteAddr(addr));
         }
 
-        // Keep the request body around in case interceptors need access to
-        // it. Otherwise we're just going to drop it later.
-        builder = builder.extension(RequestBody(mem::take(&mut self.request_body)));
+        if let Some(mut early_response) = self.early_response {
+            early_response.bytes_sent = self.get_bytes_uploaded();
+            builder = builder.extension(early_response);
+        }
+
+        // If the server agreed to switch protocols, the request body is still
+        // being sent and is written to through the upgraded connection.
+        // Otherwise end the request body, so that the transfer can complete.
+        let upgrade_writer = self.upgrade_writer.take();
+
+        if let Some(writer) = upgrade_writer
+            .filter(|_| self.response_status_code == Some(http::StatusCode::SWITCHING_PROTOCOLS))
+        {
+            builder = builder.extension(UpgradeWriter::new(writer));
+        } else {
+            // Keep the request body around in case interceptors need access to
+            // it. Otherwise we're just going to drop it later. The rest of a
+            // stream that was not sent completely is dropped right away, so
+            // that whatever is writing into it knows it is not needed.
+            let body = mem::take(&mut self.request_body);
+
+            if self.request_body_finished || body.is_repeatable() {
+                builder = builder.extension(RequestBody(body));
+            }
+        }
 
         // Include a handle to the trailer headers. We won't know if there
         // are any until we reach the end of the response body.
         builder = builder.extension(self.response_trailer_writer.trailer());
 
+        if let Some(content_type) = self.sniffed_content_type.take() {
+            builder = builder.extension(SniffedContentType(content_type));
+        }
+
+        if let Some(timestamps) = self.get_timestamps() {
+            builder = builder.extension(timestamps);
+        }
+
         // Include metrics in response, but only if it was created. If
         // metrics are disabled then it won't have been created.
         if let Some(metrics) = self.metrics.clone() {
+            metrics.inner.remote_addr.store(self.get_primary_addr());
             builder = builder.extension(metrics);
         }
 
         builder
     }
 
+    /// Update the metrics of this transfer, if enabled, with the final values
+    /// reported by curl.
+    pub(crate) fn snapshot_metrics(&mut self) {
+        if self.handle.is_null() {
+            return;
+        }
+
+        let remote_addr = self.get_primary_addr();
+
+        if let Some(metrics) = self.metrics.as_ref() {
+            unsafe {
+                scrape_metrics(self.handle, metrics);
+                scrape_sizes(self.handle, metrics);
+            }
+
+            metrics.inner.remote_addr.store(remote_addr);
+        }
+    }
+
+    fn get_transfer_stats(&mut self, error: Option<Error>) -> TransferStats {
+        let metrics = Metrics::new();
+        let mut info = [0 as c_long; 4];
+
+        if !self.handle.is_null() {
+            unsafe {
+                scrape_metrics(self.handle, &metrics);
+                scrape_sizes(self.handle, &metrics);
+
+                let longs = [
+                    curl_sys::CURLINFO_HEADER_SIZE,
+                    curl_sys::CURLINFO_REQUEST_SIZE,
+                    curl_sys::CURLINFO_NUM_CONNECTS,
+                    curl_sys::CURLINFO_REDIRECT_COUNT,
+                ];
+
+                for (value, kind) in info.iter_mut().zip(longs.iter()) {
+                    curl_sys::curl_easy_getinfo(self.handle, *kind, value as *mut c_long);
+                }
+            }
+        }
+
+        TransferStats {
+            metrics,
+            status: self.response_status_code,
+            version: self.response_version,
+            local_addr: self.get_local_addr(),
+            remote_addr: self.get_primary_addr(),
+            header_bytes: info[0].max(0) as u64,
+            request_bytes: info[1].max(0) as u64,
+            connects: info[2].max(0) as u32,
+            redirects: info[3].max(0) as u32,
+            connection_retired: self.connection_retired,
+            timestamps: self.get_timestamps(),
+            error,
+        }
+    }
+
+    fn get_timestamps(&self) -> Option<Timestamps> {
+        let submitted = self.submitted_at?;
+        let dequeued = self.dequeued_at?;
+        let mut pretransfer_time = 0f64;
+
+        if !self.handle.is_null() {
+            unsafe {
+                curl_sys::curl_easy_getinfo(
+                    self.handle,
+                    curl_sys::CURLINFO_PRETRANSFER_TIME,
+                    &mut pretransfer_time as *mut f64,
+                );
+            }
+        }
+
+        // Curl measures times from the moment the transfer began, which is
+        // when the agent dequeued it.
+        let started = Some(pretransfer_time)
+            .filter(|&time| time > 0f64)
+            .map(|time| dequeued + Duration::from_secs_f64(time));
+
+        Some(Timestamps {
+            submitted,
+            dequeued,
+            started,
+            headers_received: self.headers_received_at,
+        })
+    }
+
+    /// Get the number of new connections curl had to open for this transfer.
+    fn get_num_connects(&self) -> c_long {
+        let mut connects: c_long = 0;
+
+        if !self.handle.is_null() {
+            unsafe {
+                curl_sys::curl_easy_getinfo(
+                    self.handle,
+                    curl_sys::CURLINFO_NUM_CONNECTS,
+                    &mut connects as *mut c_long,
+                );
+            }
+        }
+
+        connects
+    }
+
     fn get_primary_addr(&mut self) -> Option<SocketAddr> {
         let ip = self.get_primary_ip()?.parse().ok()?;
         let port = self.get_primary_port()?;
@@ -400,6 +1424,11 @@ impl curl::easy::Handler for RequestHandler {
             return false;
         }
 
+        if !self.headers_started {
+            self.headers_started = true;
+            self.record_connection_use();
+        }
+
         let span = tracing::trace_span!(parent: &self.span, "header");
         let _enter = span.enter();
 
@@ -428,25 +1457,68 @@ impl curl::easy::Handler for RequestHandler {
             // Also clear any pre-existing headers that might be left over from
             // a previous intermediate response.
             self.response_headers.clear();
+            self.is_response_encoded = false;
 
             return true;
         }
 
         // Is this a header line?
         if let Some((name, value)) = parse_header(data) {
+            if name == http::header::CONTENT_ENCODING && value != "identity" {
+                self.is_response_encoded = true;
+            }
+
             self.response_headers.append(name, value);
             return true;
         }
 
         // Is this the end of the response header?
         if data == b"\r\n" {
-            // We will acknowledge the end of the header, but we can't complete
-            // our response future yet. If curl decides to follow a redirect,
-            // then this current response is not the final response and not the
-            // one we should complete with.
+            if let Some(status) = self.response_status_code {
+                self.emit(AgentEventKind::HeadersReceived {
+                    status: status.as_u16(),
+                });
+
+                if !status.is_informational() {
+                    self.headers_received_at = Some(self.clock.now());
+                }
+
+                // A server rejecting the request is not going to read the rest
+                // of its body. Curl retries a failed expectation itself.
+                if status.as_u16() >= 400 && status != http::StatusCode::EXPECTATION_FAILED {
+                    self.stop_sending_request_body();
+                }
+            }
+
+            // Complete the response future as soon as we know that this is the
+            // final response, so that the caller can start consuming the body
+            // of long-lived streams before any of it arrives. Informational
+            // responses are followed by another response, and curl may answer
+            // an authentication challenge or a failed expectation with another
+            // request over the same transfer.
+            //
+            // Otherwise, we will complete the future when curl marks the
+            // transfer as complete, or when we start receiving a response body.
+            // Sniffing the content type also needs the first chunk of the body,
+            // and an encoded body might turn out to be impossible to decode.
             //
-            // Instead, we will complete the future when curl marks the transfer
-            // as complete, or when we start receiving a response body.
+            // A server that agreed to switch protocols may not send anything
+            // until the client does, so that response is completed right away.
+            let is_final = matches!(
+                self.response_status_code,
+                Some(status) if !status.is_informational()
+                    && status != http::StatusCode::UNAUTHORIZED
+                    && status != http::StatusCode::PROXY_AUTHENTICATION_REQUIRED
+                    && status != http::StatusCode::EXPECTATION_FAILED
+            );
+
+            if (is_final && !self.sniff_content_type && !self.is_response_encoded)
+                || (self.upgrade_writer.is_some()
+                    && self.response_status_code == Some(http::StatusCode::SWITCHING_PROTOCOLS))
+            {
+                self.complete_response_future();
+            }
+
             return true;
         }
 
@@ -461,6 +1533,15 @@ impl curl::easy::Handler for RequestHandler {
             return Err(ReadError::Abort);
         }
 
+        if self.request_body_stopped {
+            return Ok(0);
+        }
+
+        // Curl is sending the body after all, such as once authenticated.
+        if self.request_body_read.is_none() {
+            self.early_response = None;
+        }
+
         let span = tracing::trace_span!(parent: &self.span, "read");
         let _enter = span.enter();
 
@@ -471,7 +1552,23 @@ impl curl::easy::Handler for RequestHandler {
 
             match Pin::new(&mut self.request_body).poll_read(&mut context, data) {
                 Poll::Pending => Err(ReadError::Pause),
-                Poll::Ready(Ok(len)) => Ok(len),
+                Poll::Ready(Ok(len)) => {
+                    let read = self.request_body_read.unwrap_or_default() + len as u64;
+
+                    self.request_body_read = Some(read);
+                    self.request_body_finished |=
+                        len == 0 || self.request_body.len().map_or(false, |total| read >= total);
+
+                    if let Some((_, hasher)) = self.request_body_checksum.as_mut() {
+                        hasher.update(&data[..len]);
+                    }
+
+                    if len > 0 {
+                        self.emit(AgentEventKind::RequestBodyRead { len });
+                    }
+
+                    Ok(len)
+                }
                 Poll::Ready(Err(e)) => {
                     tracing::error!("error reading request body: {}", e);
 
@@ -506,6 +1603,17 @@ impl curl::easy::Handler for RequestHandler {
         // If curl wants to seek to the beginning, there's a chance that we
         // can do that.
         if whence == io::SeekFrom::Start(0) && self.request_body.reset() {
+            // The body is sent again from the start, such as to answer an
+            // authentication challenge.
+            self.request_body_read = None;
+            self.request_body_finished = false;
+            self.early_response = None;
+            self.request_body_stopped = false;
+
+            if let Some((checksum, hasher)) = self.request_body_checksum.as_mut() {
+                *hasher = checksum.hasher();
+            }
+
             SeekResult::Ok
         } else {
             tracing::warn!("seek requested for request body, but it is not supported");
@@ -520,19 +1628,95 @@ impl curl::easy::Handler for RequestHandler {
         let _enter = span.enter();
         tracing::trace!("received {} bytes of data", data.len());
 
+        // The first chunk of the body is the only chance to sniff its content
+        // type before the response is handed over.
+        if self.sniff_content_type && self.sender.is_some() {
+            self.sniffed_content_type =
+                sniff::sniff_response(self.response_headers.content_type(), data);
+        }
+
         // Now that we've started receiving the response body, we know no more
         // redirects can happen and we can complete the future safely.
         self.complete_response_future();
 
+        // If this transfer has already received its fair share of data this
+        // turn, pause it so that other transfers can make progress. Curl will
+        // hold onto this data and hand it to us again once we are unpaused.
+        if self.write_quantum > 0 && self.write_quantum_used >= self.write_quantum {
+            tracing::trace!("transfer exhausted its quantum for this turn, pausing");
+            self.write_quantum_exhausted = true;
+            return Err(WriteError::Pause);
+        }
+
+        if let Err(e) = self.check_decompression_limit(data.len()) {
+            tracing::warn!("aborting response: {}", e);
+
+            // Curl does not reliably fail a transfer when a write is refused
+            // while it is being unpaused, so pause it instead and let the agent
+            // abort it like an interrupted request. Set the error first so that
+            // the caller gets the reason instead of a generic interruption.
+            self.set_result(Err(Error::new(ErrorKind::DecompressionLimitExceeded, e)));
+            self.decompression_limit_exceeded = true;
+
+            return Err(WriteError::Pause);
+        }
+
+        // Hand the data to the body callback instead of the response body
+        // reader if there is one. There is no backpressure, so the transfer
+        // keeps going even if the response has been dropped.
+        if let Some(callback) = self.on_body_chunk.as_ref() {
+            let chunk = Bytes::copy_from_slice(data);
+            let callback = callback.clone();
+
+            match self.callback_executor.as_ref() {
+                Some(executor) => executor.execute(move || (callback.0)(chunk)),
+                None => (callback.0)(chunk),
+            }
+
+            self.write_quantum_used += data.len();
+            self.decompressed_bytes += data.len() as u64;
+            self.emit(AgentEventKind::ResponseBodyWritten { len: data.len() });
+
+            return Ok(data.len());
+        }
+
         // Create a task context using a waker provided by the agent so we can
         // do an asynchronous write.
         if let Some(waker) = self.response_body_waker.as_ref() {
+            // If buffering this data would exceed the client's buffer budget,
+            // wait until consumers have read enough data to make room. Check
+            // again after registering to avoid missing a wakeup.
+            if self.shared.would_exceed_budget(data.len()) {
+                self.shared.budget.get().unwrap().wait(waker);
+
+                if self.shared.would_exceed_budget(data.len()) {
+                    tracing::trace!("response buffer budget exceeded, pausing");
+                    return Err(WriteError::Pause);
+                }
+            }
+
             let mut context = Context::from_waker(waker);
 
+            // Count the data as buffered before writing it, since the reader
+            // may consume it as soon as it is written.
+            self.shared.acquire_buffered(data.len());
+
             match Pin::new(&mut self.response_body_writer).poll_write(&mut context, data) {
-                Poll::Pending => Err(WriteError::Pause),
-                Poll::Ready(Ok(len)) => Ok(len),
+                Poll::Pending => {
+                    self.shared.release_buffered(data.len());
+                    Err(WriteError::Pause)
+                }
+                Poll::Ready(Ok(len)) => {
+                    // The pipe either writes everything or nothing.
+                    debug_assert_eq!(len, data.len());
+                    self.write_quantum_used += len;
+                    self.decompressed_bytes += len as u64;
+                    self.emit(AgentEventKind::ResponseBodyWritten { len });
+                    Ok(len)
+                }
                 Poll::Ready(Err(e)) => {
+                    self.shared.release_buffered(data.len());
+
                     if e.kind() == io::ErrorKind::BrokenPipe {
                         // Only warn about connections closed for HTTP/1.x.
                         if !self.disable_connection_reuse_log
@@ -559,6 +1743,31 @@ impl curl::easy::Handler for RequestHandler {
 
     /// Capture transfer progress updates from curl.
     fn progress(&mut self, dltotal: f64, dlnow: f64, ultotal: f64, ulnow: f64) -> bool {
+        if let Some(callback) = self.on_progress.as_ref() {
+            let progress = Progress {
+                bytes_sent: ulnow as u64,
+                upload_total: ultotal as u64,
+                bytes_received: dlnow as u64,
+                download_total: dltotal as u64,
+            };
+
+            // Curl calls this function frequently even if nothing changed.
+            if progress != self.last_progress {
+                self.last_progress = progress;
+
+                let callback = callback.clone();
+
+                match self.callback_executor.as_ref() {
+                    Some(executor) => executor.execute(move || (callback.0)(progress)),
+                    None => (callback.0)(progress),
+                }
+            }
+        }
+
+        if !self.collect_metrics {
+            return true;
+        }
+
         // Initialize metrics if required.
         let metrics = self.metrics.get_or_insert_with(Metrics::new);
 
@@ -571,59 +1780,7 @@ impl curl::easy::Handler for RequestHandler {
         // Also scrape additional metrics.
         if !self.handle.is_null() {
             unsafe {
-                curl_sys::curl_easy_getinfo(
-                    self.handle,
-                    curl_sys::CURLINFO_SPEED_UPLOAD,
-                    metrics.inner.upload_speed.as_ptr(),
-                );
-
-                curl_sys::curl_easy_getinfo(
-                    self.handle,
-                    curl_sys::CURLINFO_SPEED_DOWNLOAD,
-                    metrics.inner.download_speed.as_ptr(),
-                );
-
-                curl_sys::curl_easy_getinfo(
-                    self.handle,
-                    curl_sys::CURLINFO_NAMELOOKUP_TIME,
-                    metrics.inner.namelookup_time.as_ptr(),
-                );
-
-                curl_sys::curl_easy_getinfo(
-                    self.handle,
-                    curl_sys::CURLINFO_CONNECT_TIME,
-                    metrics.inner.connect_time.as_ptr(),
-                );
-
-                curl_sys::curl_easy_getinfo(
-                    self.handle,
-                    curl_sys::CURLINFO_APPCONNECT_TIME,
-                    metrics.inner.appconnect_time.as_ptr(),
-                );
-
-                curl_sys::curl_easy_getinfo(
-                    self.handle,
-                    curl_sys::CURLINFO_PRETRANSFER_TIME,
-                    metrics.inner.pretransfer_time.as_ptr(),
-                );
-
-                curl_sys::curl_easy_getinfo(
-                    self.handle,
-                    curl_sys::CURLINFO_STARTTRANSFER_TIME,
-                    metrics.inner.starttransfer_time.as_ptr(),
-                );
-
-                curl_sys::curl_easy_getinfo(
-                    self.handle,
-                    curl_sys::CURLINFO_TOTAL_TIME,
-                    metrics.inner.total_time.as_ptr(),
-                );
-
-                curl_sys::curl_easy_getinfo(
-                    self.handle,
-                    curl_sys::CURLINFO_REDIRECT_TIME,
-                    metrics.inner.redirect_time.as_ptr(),
-                );
+                scrape_metrics(self.handle, metrics);
             }
         }
 
@@ -637,6 +1794,25 @@ impl curl::easy::Handler for RequestHandler {
     fn debug(&mut self, kind: InfoType, data: &[u8]) {
         let _enter = self.span.enter();
 
+        // The request header is only sent once a connection is ready.
+        if matches!(kind, InfoType::HeaderOut)
+            && self.instrumentation.is_some()
+            && !self.connection_reported
+        {
+            self.connection_reported = true;
+            self.emit(AgentEventKind::ConnectionEstablished {
+                reused: self.get_num_connects() == 0,
+            });
+        }
+
+        if matches!(kind, InfoType::Text) && self.instrumentation.is_some() {
+            let message = String::from_utf8_lossy(data);
+
+            for event in self.connect_attempts.on_message(&message, Instant::now()) {
+                self.emit(event);
+            }
+        }
+
         struct FormatAscii<T>(T);
 
         impl<T: AsRef<[u8]>> fmt::Display for FormatAscii<T> {
@@ -648,6 +1824,15 @@ impl curl::easy::Handler for RequestHandler {
             }
         }
 
+        if let Some(log) = self.transfer_log.as_mut() {
+            match kind {
+                InfoType::Text => log.push(TransferMessageKind::Info, data),
+                InfoType::HeaderOut => log.push(TransferMessageKind::HeaderOut, data),
+                InfoType::HeaderIn => log.push(TransferMessageKind::HeaderIn, data),
+                _ => (),
+            }
+        }
+
         match kind {
             InfoType::Text => {
                 tracing::debug!("{}", String::from_utf8_lossy(data).trim_end())
@@ -669,11 +1854,125 @@ impl fmt::Debug for RequestHandler {
     }
 }
 
+/// Gets called by curl once the request body has been sent, to add the
+/// checksum of the body to the trailers.
+extern "C" fn send_checksum_trailer(
+    list: *mut *mut curl_sys::curl_slist,
+    data: *mut c_void,
+) -> c_int {
+    let handler = unsafe { &*(data as *const RequestHandler) };
+
+    let (checksum, hasher) = match handler.request_body_checksum.as_ref() {
+        Some(checksum) => checksum,
+        None => return CURL_TRAILERFUNC_OK,
+    };
+
+    let trailer = format!("{}: {}", checksum.header_name(), hasher.clone().finish());
+    let trailer = match CString::new(trailer) {
+        Ok(trailer) => trailer,
+        Err(_) => return CURL_TRAILERFUNC_ABORT,
+    };
+
+    // Curl makes its own copy of the string.
+    let appended = unsafe { curl_sys::curl_slist_append(*list, trailer.as_ptr()) };
+
+    if appended.is_null() {
+        return CURL_TRAILERFUNC_ABORT;
+    }
+
+    unsafe {
+        *list = appended;
+    }
+
+    CURL_TRAILERFUNC_OK
+}
+
+/// Read the total number of bytes sent and received by a transfer into a
+/// metrics object.
+unsafe fn scrape_sizes(handle: *mut CURL, metrics: &Metrics) {
+    curl_sys::curl_easy_getinfo(
+        handle,
+        curl_sys::CURLINFO_SIZE_UPLOAD,
+        metrics.inner.upload_progress.as_ptr(),
+    );
+
+    curl_sys::curl_easy_getinfo(
+        handle,
+        curl_sys::CURLINFO_SIZE_DOWNLOAD,
+        metrics.inner.download_progress.as_ptr(),
+    );
+}
+
+/// Read the current timing and speed values of a transfer into a metrics
+/// object.
+///
+/// # Safety
+///
+/// The handle must be a valid curl easy handle.
+unsafe fn scrape_metrics(handle: *mut CURL, metrics: &Metrics) {
+    curl_sys::curl_easy_getinfo(
+        handle,
+        curl_sys::CURLINFO_SPEED_UPLOAD,
+        metrics.inner.upload_speed.as_ptr(),
+    );
+
+    curl_sys::curl_easy_getinfo(
+        handle,
+        curl_sys::CURLINFO_SPEED_DOWNLOAD,
+        metrics.inner.download_speed.as_ptr(),
+    );
+
+    curl_sys::curl_easy_getinfo(
+        handle,
+        curl_sys::CURLINFO_NAMELOOKUP_TIME,
+        metrics.inner.namelookup_time.as_ptr(),
+    );
+
+    curl_sys::curl_easy_getinfo(
+        handle,
+        curl_sys::CURLINFO_CONNECT_TIME,
+        metrics.inner.connect_time.as_ptr(),
+    );
+
+    curl_sys::curl_easy_getinfo(
+        handle,
+        curl_sys::CURLINFO_APPCONNECT_TIME,
+        metrics.inner.appconnect_time.as_ptr(),
+    );
+
+    curl_sys::curl_easy_getinfo(
+        handle,
+        curl_sys::CURLINFO_PRETRANSFER_TIME,
+        metrics.inner.pretransfer_time.as_ptr(),
+    );
+
+    curl_sys::curl_easy_getinfo(
+        handle,
+        curl_sys::CURLINFO_STARTTRANSFER_TIME,
+        metrics.inner.starttransfer_time.as_ptr(),
+    );
+
+    curl_sys::curl_easy_getinfo(
+        handle,
+        curl_sys::CURLINFO_TOTAL_TIME,
+        metrics.inner.total_time.as_ptr(),
+    );
+
+    curl_sys::curl_easy_getinfo(
+        handle,
+        curl_sys::CURLINFO_REDIRECT_TIME,
+        metrics.inner.redirect_time.as_ptr(),
+    );
+}
+
 /// Wrapper around a pipe reader that returns an error that tracks transfer
 /// cancellation.
 pub(crate) struct ResponseBodyReader {
     inner: pipe::PipeReader,
     shared: Arc<Shared>,
+
+    /// Used to wake up a pending read if the agent crashes.
+    agent_listener: Option<EventListener>,
 }
 
 impl AsyncRead for ResponseBodyReader {
@@ -682,21 +1981,43 @@ impl AsyncRead for ResponseBodyReader {
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
-        let inner = Pin::new(&mut self.inner);
+        let this = &mut *self;
 
-        match inner.poll_read(cx, buf) {
+        match Pin::new(&mut this.inner).poll_read(cx, buf) {
             // On EOF, check to see if the transfer was cancelled, and if so,
             // return an error.
-            Poll::Ready(Ok(0)) => match self.shared.result.get() {
+            Poll::Ready(Ok(0)) => match this.shared.result.get() {
                 // The transfer did finish successfully, so return EOF.
                 Some(Ok(())) => Poll::Ready(Ok(0)),
 
                 // The transfer finished with an error, so return the error.
                 Some(Err(e)) => Poll::Ready(Err(io::Error::from(e.clone()))),
 
+                // The transfer was dropped because the agent crashed.
+                None if this.shared.is_agent_crashed() => {
+                    Poll::Ready(Err(Error::from(ErrorKind::AgentCrashed).into()))
+                }
+
                 // The transfer did not finish properly at all, so return an error.
                 None => Poll::Ready(Err(io::ErrorKind::ConnectionAborted.into())),
             },
+            Poll::Ready(Ok(len)) => {
+                this.shared.release_buffered(len);
+                Poll::Ready(Ok(len))
+            }
+
+            // No data is available yet. If the agent has stopped responding
+            // then no data ever will be, so return an error instead of waiting
+            // forever.
+            Poll::Pending => match this.shared.agent.get() {
+                Some(agent) => match agent.poll_crashed(&mut this.agent_listener, cx) {
+                    Poll::Ready(()) => {
+                        Poll::Ready(Err(Error::from(ErrorKind::AgentCrashed).into()))
+                    }
+                    Poll::Pending => Poll::Pending,
+                },
+                None => Poll::Pending,
+            },
             poll => poll,
         }
     }
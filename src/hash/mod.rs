@@ -0,0 +1,84 @@
+//! Minimal implementations of the message digests and encodings needed by HTTP
+//! authentication and request body checksums.
+
+pub(crate) mod md5;
+pub(crate) mod sha256;
+
+/// Input to a digest algorithm that processes 64-byte blocks, buffered until a
+/// whole block is available.
+#[derive(Clone)]
+pub(crate) struct Blocks {
+    buffer: [u8; 64],
+    buffered: usize,
+    len: u64,
+}
+
+impl Blocks {
+    pub(crate) fn new() -> Self {
+        Self {
+            buffer: [0; 64],
+            buffered: 0,
+            len: 0,
+        }
+    }
+
+    /// Add input, calling `compress` for each block that is completed.
+    pub(crate) fn update(&mut self, mut input: &[u8], mut compress: impl FnMut(&[u8; 64])) {
+        self.len = self.len.wrapping_add(input.len() as u64);
+
+        while !input.is_empty() {
+            let take = (64 - self.buffered).min(input.len());
+
+            self.buffer[self.buffered..self.buffered + take].copy_from_slice(&input[..take]);
+            self.buffered += take;
+            input = &input[take..];
+
+            if self.buffered == 64 {
+                compress(&self.buffer);
+                self.buffered = 0;
+            }
+        }
+    }
+
+    /// Pad the input with the given encoding of its length in bits, calling
+    /// `compress` for the final blocks.
+    pub(crate) fn finish(
+        mut self,
+        encode_len: fn(u64) -> [u8; 8],
+        mut compress: impl FnMut(&[u8; 64]),
+    ) {
+        let bits = encode_len(self.len.wrapping_mul(8));
+        let padding = if self.buffered < 56 { 56 } else { 120 } - self.buffered;
+        let mut tail = [0; 64];
+        tail[0] = 0x80;
+
+        self.update(&tail[..padding], &mut compress);
+        self.update(&bits, &mut compress);
+    }
+}
+
+/// Encode bytes as standard base64 with padding.
+pub(crate) fn base64_encode(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut output = String::with_capacity((input.len() + 2) / 3 * 4);
+
+    for chunk in input.chunks(3) {
+        let bytes = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = u32::from(bytes[0]) << 16 | u32::from(bytes[1]) << 8 | u32::from(bytes[2]);
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+
+    output
+}
@@ -15,6 +15,7 @@ pub(crate) trait HasHeaders {
             .and_then(|v| v.parse().ok())
     }
 
+    #[cfg_attr(not(feature = "text-decoding"), allow(dead_code))]
     fn content_type(&self) -> Option<&str> {
         self.headers()
             .get(http::header::CONTENT_TYPE)
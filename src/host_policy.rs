@@ -0,0 +1,209 @@
+//! Enforcement of host policies before requests are sent.
+
+use crate::{
+    body::AsyncBody,
+    config::{request::RequestConfig, Dialer, IpVersion},
+    error::{Error, ErrorKind},
+    interceptor::{Context, Interceptor, InterceptorFuture},
+};
+use http::Request;
+use std::{
+    collections::HashMap,
+    error::Error as StdError,
+    fmt,
+    net::{IpAddr, SocketAddr, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+/// Source of errors for requests blocked by a host policy.
+#[derive(Debug)]
+struct Blocked(String);
+
+impl fmt::Display for Blocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} by the host policy", self.0)
+    }
+}
+
+impl StdError for Blocked {}
+
+fn blocked(reason: String) -> Error {
+    tracing::debug!("request blocked: {}", reason);
+    Error::new(ErrorKind::RequestBlocked, Blocked(reason))
+}
+
+/// The addresses host names were resolved to while sending a request.
+///
+/// This is created once for each request sent by a client with a host policy,
+/// and shared by every request sent for it, such as when following redirects
+/// or retrying, so that a host name is only resolved and checked once. This
+/// prevents DNS rebinding, where a host name first resolves to an allowed
+/// address for the check, and then to a blocked address for a later request.
+#[derive(Clone, Default)]
+pub(crate) struct PinnedAddresses(Arc<Mutex<HashMap<String, IpAddr>>>);
+
+impl PinnedAddresses {
+    fn get(&self, host: &str) -> Option<IpAddr> {
+        self.0
+            .lock()
+            .unwrap()
+            .get(&host.to_ascii_lowercase())
+            .copied()
+    }
+
+    fn insert(&self, host: &str, addr: IpAddr) {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(host.to_ascii_lowercase(), addr);
+    }
+}
+
+/// Interceptor that checks each request against the configured host policy,
+/// if any, and pins the connection to the checked address.
+///
+/// This must be placed after the redirect interceptor so that every hop is
+/// checked, and requests must have [`PinnedAddresses`] inserted before the
+/// redirect interceptor for host names to stay pinned across hops.
+pub(crate) struct HostPolicyInterceptor;
+
+impl Interceptor for HostPolicyInterceptor {
+    type Err = Error;
+
+    fn intercept<'a>(
+        &'a self,
+        mut request: Request<AsyncBody>,
+        ctx: Context<'a>,
+    ) -> InterceptorFuture<'a, Self::Err> {
+        Box::pin(async move {
+            let config = match request.extensions().get::<RequestConfig>() {
+                Some(config) if config.host_policy.is_some() => config,
+                _ => return ctx.send(request).await,
+            };
+            let policy = config.host_policy.clone().unwrap();
+
+            let host = request
+                .uri()
+                .host()
+                .ok_or(ErrorKind::InvalidRequest)?
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .to_owned();
+            let port = request
+                .uri()
+                .port_u16()
+                .or_else(|| match request.uri().scheme_str() {
+                    Some("http") => Some(80),
+                    Some("https") => Some(443),
+                    _ => None,
+                })
+                .ok_or(ErrorKind::InvalidRequest)?;
+
+            if !policy.is_host_allowed(&host) {
+                return Err(blocked(format!("host `{}` is not allowed", host)));
+            }
+
+            if !policy.is_port_allowed(port) {
+                return Err(blocked(format!("port {} is not allowed", port)));
+            }
+
+            // The connection is not made by curl to the host of the request,
+            // so there is no address for us to check.
+            if config.dial.is_some() || matches!(config.proxy, Some(Some(_))) {
+                return ctx.send(request).await;
+            }
+
+            let ip_version = config.ip_version.clone().unwrap_or(IpVersion::Any);
+
+            if let Ok(addr) = host.parse::<IpAddr>() {
+                if !policy.is_address_allowed(addr) {
+                    return Err(blocked(format!("address {} is not allowed", addr)));
+                }
+
+                return ctx.send(request).await;
+            }
+
+            let pinned = request.extensions().get::<PinnedAddresses>().cloned();
+
+            let addr = match pinned.as_ref().and_then(|pinned| pinned.get(&host)) {
+                // The host was already resolved for an earlier request sent for
+                // this one, so connect to the same address instead of resolving
+                // it again, which might give a different answer.
+                Some(addr) => {
+                    if !policy.is_address_allowed(addr) {
+                        return Err(blocked(format!("address {} is not allowed", addr)));
+                    }
+
+                    addr
+                }
+                None => {
+                    let addrs = resolve(host.clone(), port).await?;
+
+                    if let Some(addr) =
+                        addrs.iter().find(|addr| !policy.is_address_allowed(**addr))
+                    {
+                        return Err(blocked(format!(
+                            "host `{}` resolves to address {}, which is not allowed",
+                            host, addr
+                        )));
+                    }
+
+                    let addr = addrs
+                        .into_iter()
+                        .find(|addr| match ip_version {
+                            IpVersion::V4 => addr.is_ipv4(),
+                            IpVersion::V6 => addr.is_ipv6(),
+                            IpVersion::Any => true,
+                        })
+                        .ok_or(ErrorKind::NameResolution)?;
+
+                    if let Some(pinned) = pinned.as_ref() {
+                        pinned.insert(&host, addr);
+                    }
+
+                    addr
+                }
+            };
+
+            tracing::debug!("connecting to checked address {} for host `{}`", addr, host);
+
+            // Connect to exactly the address we checked, rather than letting
+            // curl resolve the host again.
+            if let Some(config) = request.extensions_mut().get_mut::<RequestConfig>() {
+                config.dial = Some(Dialer::ip_socket(SocketAddr::new(addr, port)));
+            }
+
+            ctx.send(request).await
+        })
+    }
+}
+
+/// Resolve a host name on a separate thread, since the system resolver
+/// blocks.
+async fn resolve(host: String, port: u16) -> Result<Vec<IpAddr>, Error> {
+    let (sender, receiver) = async_channel::bounded(1);
+
+    thread::Builder::new()
+        .name(String::from("isahc-resolver"))
+        .spawn(move || {
+            let result = (host.as_str(), port)
+                .to_socket_addrs()
+                .map(|addrs| addrs.map(|addr| addr.ip()).collect::<Vec<_>>());
+
+            let _ = sender.try_send(result);
+        })
+        .map_err(|e| Error::new(ErrorKind::NameResolution, e))?;
+
+    let addrs = receiver
+        .recv()
+        .await
+        .map_err(|_| Error::from(ErrorKind::NameResolution))?
+        .map_err(|e| Error::new(ErrorKind::NameResolution, e))?;
+
+    if addrs.is_empty() {
+        return Err(ErrorKind::NameResolution.into());
+    }
+
+    Ok(addrs)
+}
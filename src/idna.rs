@@ -0,0 +1,60 @@
+//! Enforcement of internationalized domain name policies.
+
+use crate::{
+    body::AsyncBody,
+    config::{request::RequestConfig, IdnHost},
+    error::Error,
+    interceptor::{Context, Interceptor, InterceptorFuture},
+};
+use http::Request;
+use std::net::IpAddr;
+
+/// Interceptor that checks the host of each request against the configured
+/// IDNA policy, if any, and records the host in both its forms on the
+/// response.
+///
+/// This is placed after the redirect interceptor so that every hop is checked.
+pub(crate) struct IdnaInterceptor;
+
+impl Interceptor for IdnaInterceptor {
+    type Err = Error;
+
+    fn intercept<'a>(
+        &'a self,
+        request: Request<AsyncBody>,
+        ctx: Context<'a>,
+    ) -> InterceptorFuture<'a, Self::Err> {
+        Box::pin(async move {
+            let host = match request.uri().host() {
+                Some(host) if !is_ip_literal(host) => host,
+                _ => return ctx.send(request).await,
+            };
+
+            let policy = request
+                .extensions()
+                .get::<RequestConfig>()
+                .and_then(|config| config.idna_policy.as_ref());
+
+            let host = match (IdnHost::parse(host), policy) {
+                (Ok(host), Some(policy)) => {
+                    policy.check(&host)?;
+                    host
+                }
+                (Ok(host), None) => host,
+                (Err(e), Some(_)) => return Err(e),
+                // Leave it to curl to decide what to do with host names that
+                // are not valid domain names without a policy asking us to.
+                (Err(_), None) => return ctx.send(request).await,
+            };
+
+            let mut response = ctx.send(request).await?;
+            response.extensions_mut().insert(host);
+
+            Ok(response)
+        })
+    }
+}
+
+fn is_ip_literal(host: &str) -> bool {
+    host.starts_with('[') || host.parse::<IpAddr>().is_ok()
+}
@@ -51,6 +51,15 @@ pub fn is_http_version_supported(version: http::Version) -> bool {
     }
 }
 
+/// Check if runtime support is available for
+/// [NTLM authentication](crate::auth::Authentication::ntlm).
+///
+/// NTLM is only available if libcurl was built with the cryptographic
+/// functions it requires, which depends on how libcurl was compiled.
+pub fn is_ntlm_supported() -> bool {
+    CURL_VERSION.feature_ntlm()
+}
+
 fn curl_version() -> (u8, u8, u8) {
     let bits = CURL_VERSION.version_num();
 
@@ -16,10 +16,10 @@
 //!   error and also records the type of the interceptor that created the error
 //!   for visibility. But we can't add a new variant right now without a BC
 //!   break. See [#182](https://github.com/sagebind/isahc/issues/182).
-//! - Automatic redirect following currently bypasses interceptors for
-//!   subsequent requests. This will be fixed when redirect handling is
-//!   rewritten as an interceptor itself. See
-//!   [#232](https://github.com/sagebind/isahc/issues/232).
+//!
+//! Interceptors registered with a client are invoked for every request sent,
+//! synchronously or asynchronously, in the order they were registered. Each
+//! redirect followed is sent through the interceptors as a separate request.
 ///
 /// # Availability
 ///
@@ -27,6 +27,7 @@
 /// [`unstable-interceptors`](../index.html#unstable-interceptors) feature is
 /// enabled.
 use crate::body::AsyncBody;
+use futures_lite::io::AsyncRead;
 use http::{Request, Response};
 use std::{error::Error, fmt, future::Future, pin::Pin};
 
@@ -74,6 +75,15 @@ pub trait Interceptor: Send + Sync {
         request: Request<AsyncBody>,
         ctx: Context<'a>,
     ) -> InterceptorFuture<'a, Self::Err>;
+
+    /// Called once when the client this interceptor is registered with is
+    /// shut down using [`HttpClient::shutdown`](crate::HttpClient::shutdown),
+    /// after its requests have completed or been aborted.
+    ///
+    /// This gives interceptors that buffer data, such as recordings of
+    /// requests or metrics, a chance to flush it deterministically before the
+    /// process exits. The default implementation does nothing.
+    fn shutdown(&self) {}
 }
 
 /// The type of future returned by an interceptor.
@@ -91,6 +101,28 @@ where
     InterceptorFn(f)
 }
 
+/// Replace the body of a response with a stream derived from the original body.
+///
+/// This allows an interceptor to transform a response body before application
+/// code sees it, such as to decrypt, validate, or rewrite its contents. The
+/// given function receives the original body and returns a reader producing
+/// the new body, which is read lazily as the application reads the response.
+///
+/// Since the transformed body may have a different length than the original,
+/// the new body has an unknown length and any `Content-Length` header is
+/// removed from the response.
+pub fn map_body<F, R>(response: Response<AsyncBody>, f: F) -> Response<AsyncBody>
+where
+    F: FnOnce(AsyncBody) -> R,
+    R: AsyncRead + Send + Sync + 'static,
+{
+    let (mut parts, body) = response.into_parts();
+
+    parts.headers.remove(http::header::CONTENT_LENGTH);
+
+    Response::from_parts(parts, AsyncBody::from_reader(f(body)))
+}
+
 /// An interceptor created from an arbitrary closure or function. See
 /// [`from_fn`] for details.
 pub struct InterceptorFn<F>(F);
@@ -121,7 +153,9 @@ impl<F: fmt::Debug> fmt::Debug for InterceptorFn<F> {
 }
 
 // Workaround for https://github.com/rust-lang/rust/issues/51004
-#[allow(unreachable_pub)]
+//
+// Not every arity generated here is actually used.
+#[allow(dead_code, unreachable_pub)]
 mod private {
     use std::future::Future;
 
@@ -21,6 +21,10 @@ impl Interceptor for InterceptorObj {
     ) -> InterceptorFuture<'a, Self::Err> {
         self.0.dyn_intercept(request, cx)
     }
+
+    fn shutdown(&self) {
+        self.0.dyn_shutdown();
+    }
 }
 
 /// Object-safe version of the interceptor used for type erasure. Implementation
@@ -31,6 +35,8 @@ trait DynInterceptor: Send + Sync {
         request: Request<AsyncBody>,
         cx: Context<'a>,
     ) -> InterceptorFuture<'a, Error>;
+
+    fn dyn_shutdown(&self);
 }
 
 impl<I: Interceptor> DynInterceptor for I {
@@ -41,4 +47,8 @@ impl<I: Interceptor> DynInterceptor for I {
     ) -> InterceptorFuture<'a, Error> {
         Box::pin(async move { self.intercept(request, cx).await.map_err(Error::from_any) })
     }
+
+    fn dyn_shutdown(&self) {
+        self.shutdown();
+    }
 }
@@ -134,6 +134,49 @@
 //! [`text`](AsyncReadResponseExt::text)) will also automatically be selected by
 //! the compiler.
 //!
+//! ### Async runtimes
+//!
+//! The asynchronous API does not depend on any particular async runtime. The
+//! futures returned are driven entirely by Isahc's own background agent
+//! thread, which wakes up any waiting tasks as network activity occurs, so
+//! they can be awaited from within any executor without any additional
+//! integration.
+//!
+//! Response bodies implement the [`AsyncRead`](futures_lite::io::AsyncRead)
+//! trait from the `futures` ecosystem. Runtimes that use the same I/O traits,
+//! such as [async-std], can use them directly:
+//!
+//! ```ignore
+//! let mut response = isahc::get_async("https://httpbin.org/get").await?;
+//! async_std::io::copy(response.body_mut(), &mut async_std::io::stdout()).await?;
+//! ```
+//!
+//! [Tokio] uses its own I/O traits, which can be bridged to using the
+//! compatibility wrapper provided by [tokio-util]:
+//!
+//! ```ignore
+//! use tokio_util::compat::FuturesAsyncReadCompatExt;
+//!
+//! let mut response = isahc::get_async("https://httpbin.org/get").await?;
+//! tokio::io::copy(&mut response.body_mut().compat(), &mut tokio::io::stdout()).await?;
+//! ```
+//!
+//! All futures returned by Isahc are standard library
+//! [`Future`](std::future::Future)s. Applications that still use the older
+//! `futures` 0.1 model, such as those built on Tokio 0.1, can adapt them using
+//! the [compatibility layer][futures-compat] provided by `futures` 0.3:
+//!
+//! ```ignore
+//! use futures::{FutureExt, TryFutureExt};
+//!
+//! let future01 = isahc::get_async("https://httpbin.org/get").boxed().compat();
+//! ```
+//!
+//! [async-std]: https://docs.rs/async-std
+//! [futures-compat]: https://docs.rs/futures/0.3/futures/compat/index.html
+//! [Tokio]: https://docs.rs/tokio
+//! [tokio-util]: https://docs.rs/tokio-util/*/tokio_util/compat/index.html
+//!
 //! # Feature flags
 //!
 //! Isahc is designed to be as "pay-as-you-need" as possible using Cargo feature
@@ -147,12 +190,64 @@
 //! features = ["psl"]
 //! ```
 //!
+//! Every feature may be enabled on its own, though a few of them also enable
+//! the features they build upon: `docker`, `psl`, `tus` and `watch` enable
+//! `blocking`, `oauth`, `openapi` and `watch` enable `json`, and
+//! `rustls-tls-native-certs` enables `rustls-tls`. For size-sensitive
+//! deployments, disabling the default features produces a minimal build of just
+//! the asynchronous client core, linked against a libcurl without TLS or HTTP/2
+//! support. Features can then be added back one at a time as needed:
+//!
+//! ```toml
+//! [dependencies.isahc]
+//! version = "1.7"
+//! default-features = false
+//! features = ["blocking", "native-tls"]
+//! ```
+//!
+//! Note that libcurl always depends on zlib, so decompressing responses
+//! compressed with `gzip` or `deflate` is available even in a minimal build.
+//!
 //! Below is a list of all available feature flags and their meanings.
 //!
+//! ## `blocking`
+//!
+//! Enable the synchronous API, including the free-standing request functions
+//! such as [`get`] and [`send`], the synchronous methods on [`HttpClient`],
+//! [`Body`], and [`ReadResponseExt`]. Applications that only use the
+//! asynchronous API can disable this feature to skip compiling it. Enabled by
+//! default.
+//!
+//! ## `content-decoding`
+//!
+//! Decode `gzip` and `deflate` response bodies in Rust instead of in libcurl
+//! where the client needs to decide how to decode them itself. This is
+//! required by `Configurable::decompress_content_types`, and lets
+//! [`decode::Decoder`] and [`ContentCodings`](config::ContentCodings) support
+//! these encodings. Pulls in [flate2](https://docs.rs/flate2). Disabled by
+//! default.
+//!
 //! ## `cookies`
 //!
 //! Enable persistent HTTP cookie support. Disabled by default.
 //!
+//! ## `docker`
+//!
+//! Enable the [`docker`] module, a preset for talking to the Docker Engine
+//! API over its Unix socket, including attaching to containers. Implies
+//! `blocking`, disabled by default.
+//!
+//! ## `form`
+//!
+//! Serialize request bodies as HTML forms via [serde](https://serde.rs), using
+//! [`RequestBuilderExt::form`]. Disabled by default.
+//!
+//! ## `http-1`
+//!
+//! Enable the [`compat::http1`] module, which converts between the types of
+//! version 1 of the [`http`] crate and the version used by Isahc. Disabled by
+//! default.
+//!
 //! ## `http2`
 //!
 //! Enable compile-time support for HTTP/2 in libcurl via libnghttp2. This does
@@ -168,12 +263,68 @@
 //! ## `json`
 //!
 //! Additional serialization and deserialization of JSON bodies via
-//! [serde](https://serde.rs). Disabled by default.
+//! [serde](https://serde.rs), using [`RequestBuilderExt::json`] and
+//! [`ReadResponseExt::json`]. Disabled by default.
+//!
+//! ## `keychain`
+//!
+//! Allow [`CredentialStore`](auth::CredentialStore) to look up credentials in
+//! the operating system keychain. Disabled by default.
+//!
+//! ## `metrics`
+//!
+//! Export statistics of every transfer through the
+//! [metrics](https://docs.rs/metrics) facade, so that any metrics backend
+//! installed as a recorder receives them. Disabled by default.
+//!
+//! The following metrics are recorded, all labeled with the `host` of the
+//! request:
+//!
+//! - `isahc_requests_total`: Counter of completed transfers, also labeled with
+//!   the `status_class` of the response (`1xx` through `5xx`, or `error` if no
+//!   response was received) and its HTTP `version` (`1.0`, `1.1`, `2`, `3`,
+//!   or `none`).
+//! - `isahc_request_duration_seconds`: Histogram of the total time of
+//!   transfers, with the same labels as `isahc_requests_total`.
+//! - `isahc_time_to_first_byte_seconds`: Histogram of the time until the
+//!   first byte of transfers was sent or received.
+//! - `isahc_connections_opened_total`: Counter of new connections created.
+//! - `isahc_bytes_sent_total`: Counter of bytes of request headers and bodies
+//!   sent.
+//! - `isahc_bytes_received_total`: Counter of bytes of response headers and
+//!   bodies received.
+//!
+//! Each redirect followed according to a
+//! [`RedirectPolicy`](config::RedirectPolicy) and each retry is recorded as a
+//! separate transfer.
+//!
+//! ## `oauth`
+//!
+//! Enable the [`oauth`] module, which provides token providers for acquiring
+//! access tokens from OAuth 2.0 authorization servers. Implies `json`,
+//! disabled by default.
+//!
+//! ## `openapi`
+//!
+//! Enable the [`openapi`] module, for contract testing of responses against
+//! an OpenAPI document. Implies `json`, disabled by default.
+//!
+//! ## `progress`
+//!
+//! Enable the [`progress`] module, which renders transfer progress and metrics
+//! in the same human-readable style as the curl command-line tool. Disabled by
+//! default.
 //!
 //! ## `psl`
 //!
 //! Enable use of the Public Suffix List to filter out potentially malicious
-//! cross-domain cookies. Implies `cookies`, disabled by default.
+//! cross-domain cookies. Implies `cookies` and `blocking`, disabled by default.
+//!
+//! ## `reqwest-compat`
+//!
+//! Enable the [`compat::reqwest`] module, which mirrors the most commonly used
+//! parts of the [reqwest](https://docs.rs/reqwest) API to ease migrating from
+//! it. Disabled by default.
 //!
 //! ## `spnego`
 //!
@@ -184,6 +335,12 @@
 //! Kerberos](https://web.mit.edu/kerberos/) headers must be pre-installed at
 //! compile time.
 //!
+//! ## `state`
+//!
+//! Enable exporting and importing the state a client has learned, such as
+//! cookies and permanent redirects, using [`HttpClient::export_state`] and
+//! [`HttpClient::import_state`]. Disabled by default.
+//!
 //! ## `static-curl`
 //!
 //! Use a bundled libcurl version and statically link to it. Enabled by default.
@@ -193,6 +350,24 @@
 //! Enable support for decoding text-based responses in various charsets into
 //! strings. Enabled by default.
 //!
+//! ## `trace-context`
+//!
+//! Enable the [`trace_context`] module, which propagates distributed tracing
+//! context to servers in W3C Trace Context or B3 headers and records a client
+//! span for each request. Disabled by default.
+//!
+//! ## `tus`
+//!
+//! Enable the [`upload::tus`] module, a client for the
+//! [tus](https://tus.io) resumable upload protocol. Implies `blocking`,
+//! disabled by default.
+//!
+//! ## `watch`
+//!
+//! Enable the [`watch`] module, for consuming Kubernetes-style watch streams
+//! of JSON events that resume where they left off when reconnected. Implies
+//! `blocking` and `json`, disabled by default.
+//!
 //! ## Unstable APIs
 //!
 //! There are also some features that enable new incubating APIs that do not
@@ -240,7 +415,13 @@
     clippy::all
 )]
 // These lints suggest to use features not available in our MSRV.
-#![allow(clippy::manual_strip, clippy::match_like_matches_macro)]
+#![allow(
+    clippy::io_other_error,
+    clippy::manual_div_ceil,
+    clippy::manual_strip,
+    clippy::match_like_matches_macro,
+    clippy::unnecessary_map_or
+)]
 
 use std::convert::TryFrom;
 
@@ -250,47 +431,133 @@ mod macros;
 #[cfg(feature = "cookies")]
 pub mod cookies;
 
+mod affinity;
 mod agent;
 mod body;
+mod cancel;
+mod capabilities;
 mod client;
+mod connect_attempt;
+mod content_disposition;
 mod default_headers;
+mod dns_negative_cache;
+mod event_log;
+mod fail_with_body;
+#[cfg(feature = "form")]
+mod form;
 mod handler;
+mod hash;
 mod headers;
+mod host_policy;
+mod idna;
 mod info;
+mod media_type_version;
 mod metrics;
+mod normalize;
 mod parsing;
+mod query;
+mod range_cache;
 mod redirect;
 mod request;
 mod response;
+mod retry;
+mod session;
+mod sniff;
+#[cfg(feature = "state")]
+mod state;
 mod task;
 mod text;
 mod trailer;
+mod transfer_log;
+mod upgrade;
+mod validation;
 
 pub mod auth;
 pub mod config;
+pub mod decode;
 pub mod error;
+pub mod multipart;
+pub mod testing;
+
+#[cfg(any(feature = "http-1", feature = "reqwest-compat"))]
+pub mod compat;
+
+#[cfg(feature = "docker")]
+pub mod docker;
+
+#[cfg(feature = "blocking")]
+pub mod diagnose;
+
+#[cfg(feature = "blocking")]
+pub mod download;
+
+#[cfg(feature = "blocking")]
+pub mod tail;
+
+#[cfg(feature = "blocking")]
+pub mod transaction;
+
+#[cfg(feature = "blocking")]
+pub mod upload;
+
+#[cfg(feature = "watch")]
+pub mod watch;
+
+#[cfg(feature = "blocking")]
+pub mod well_known;
 
 #[cfg(feature = "unstable-interceptors")]
 pub mod interceptor;
+
+#[cfg(feature = "oauth")]
+pub mod oauth;
+
+#[cfg(feature = "openapi")]
+pub mod openapi;
+
+#[cfg(feature = "progress")]
+pub mod progress;
+
+#[cfg(feature = "trace-context")]
+pub mod trace_context;
 #[cfg(not(feature = "unstable-interceptors"))]
 #[allow(unreachable_pub, unused)]
 pub(crate) mod interceptor;
 
 pub use crate::{
-    body::{AsyncBody, Body},
+    agent::AgentStats,
+    body::AsyncBody,
+    cancel::{CancelHandle, CancelReason},
     client::{HttpClient, HttpClientBuilder, ResponseFuture},
+    content_disposition::ContentDisposition,
     error::Error,
     http::{request::Request, response::Response},
     info::*,
-    metrics::Metrics,
+    media_type_version::MediaTypeVersion,
+    metrics::{EarlyResponse, Metrics, Progress, Timestamps, TransferStats},
+    query::QueryMut,
     request::RequestExt,
-    response::{AsyncReadResponseExt, ReadResponseExt, ResponseExt},
+    response::{AsyncReadResponseExt, ResponseExt},
+    session::Session,
     trailer::Trailer,
+    upgrade::Upgraded,
 };
 
+#[cfg(feature = "blocking")]
+pub use crate::{body::Body, response::ReadResponseExt};
+
+#[cfg(any(feature = "json", feature = "form"))]
+pub use crate::request::RequestBuilderExt;
+
+#[cfg(feature = "state")]
+pub use crate::state::ClientState;
+
 /// Re-export of HTTP types.
 pub use http;
 
+/// Re-export of the byte buffer type used for response body chunks.
+pub use bytes::Bytes;
+
 /// A "prelude" for importing commonly used Isahc types and traits.
 ///
 /// The prelude re-exports most commonly used traits and macros from this crate.
@@ -304,19 +571,22 @@ pub use http;
 /// ```
 pub mod prelude {
     #[doc(no_inline)]
-    pub use crate::{
-        config::Configurable,
-        AsyncReadResponseExt,
-        ReadResponseExt,
-        RequestExt,
-        ResponseExt,
-    };
+    pub use crate::{config::Configurable, AsyncReadResponseExt, RequestExt, ResponseExt};
+
+    #[cfg(feature = "blocking")]
+    #[doc(no_inline)]
+    pub use crate::ReadResponseExt;
+
+    #[cfg(any(feature = "json", feature = "form"))]
+    #[doc(no_inline)]
+    pub use crate::RequestBuilderExt;
 }
 
 /// Send a GET request to the given URI.
 ///
 /// The request is executed using a shared [`HttpClient`] instance. See
 /// [`HttpClient::get`] for details.
+#[cfg(feature = "blocking")]
 pub fn get<U>(uri: U) -> Result<Response<Body>, Error>
 where
     http::Uri: TryFrom<U>,
@@ -341,6 +611,7 @@ where
 ///
 /// The request is executed using a shared [`HttpClient`] instance. See
 /// [`HttpClient::head`] for details.
+#[cfg(feature = "blocking")]
 pub fn head<U>(uri: U) -> Result<Response<Body>, Error>
 where
     http::Uri: TryFrom<U>,
@@ -365,6 +636,7 @@ where
 ///
 /// The request is executed using a shared [`HttpClient`] instance. See
 /// [`HttpClient::post`] for details.
+#[cfg(feature = "blocking")]
 pub fn post<U, B>(uri: U, body: B) -> Result<Response<Body>, Error>
 where
     http::Uri: TryFrom<U>,
@@ -411,6 +683,7 @@ where
 ///
 /// The request is executed using a shared [`HttpClient`] instance. See
 /// [`HttpClient::put`] for details.
+#[cfg(feature = "blocking")]
 pub fn put<U, B>(uri: U, body: B) -> Result<Response<Body>, Error>
 where
     http::Uri: TryFrom<U>,
@@ -438,6 +711,7 @@ where
 ///
 /// The request is executed using a shared [`HttpClient`] instance. See
 /// [`HttpClient::delete`] for details.
+#[cfg(feature = "blocking")]
 pub fn delete<U>(uri: U) -> Result<Response<Body>, Error>
 where
     http::Uri: TryFrom<U>,
@@ -462,6 +736,7 @@ where
 ///
 /// The request is executed using a shared [`HttpClient`] instance. See
 /// [`HttpClient::send`] for details.
+#[cfg(feature = "blocking")]
 pub fn send<B: Into<Body>>(request: Request<B>) -> Result<Response<Body>, Error> {
     HttpClient::shared().send(request)
 }
@@ -0,0 +1,185 @@
+//! Parsing of versioned media types in the `Content-Type` response header.
+
+/// A media type that carries an API version, such as
+/// `application/vnd.example.v2+json`.
+///
+/// APIs that are versioned through content negotiation put the version either
+/// at the end of a vendor or personal subtype, as in
+/// `application/vnd.example.v2+json`, or in a `version` parameter, as in
+/// `application/vnd.example+json; version=2`. Both forms are recognized, with
+/// the subtype taking precedence if both are present. Media types are compared
+/// case-insensitively, so the media type returned is always in lowercase.
+///
+/// To ask for specific versions of a media type, use
+/// [`Configurable::accept_versions`](crate::config::Configurable::accept_versions).
+/// The version the server chose can then be read from the response using
+/// [`ResponseExt::media_type_version`](crate::ResponseExt::media_type_version).
+///
+/// # Examples
+///
+/// ```
+/// use isahc::MediaTypeVersion;
+///
+/// let version = MediaTypeVersion::parse("application/vnd.example.v2+json").unwrap();
+/// assert_eq!(version.media_type(), "application/vnd.example+json");
+/// assert_eq!(version.version(), "2");
+///
+/// let version = MediaTypeVersion::parse("application/vnd.example+json; version=2.1").unwrap();
+/// assert_eq!(version.media_type(), "application/vnd.example+json");
+/// assert_eq!(version.version(), "2.1");
+///
+/// assert_eq!(MediaTypeVersion::parse("application/json"), None);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MediaTypeVersion {
+    media_type: String,
+    version: String,
+}
+
+impl MediaTypeVersion {
+    /// Parse the value of a `Content-Type` header, returning `None` if it is
+    /// not a valid media type or does not carry a version.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.split(';');
+        let essence = parts.next()?.trim().to_ascii_lowercase();
+        let (type_, subtype) = essence.split_once('/')?;
+
+        if type_.is_empty() || subtype.is_empty() {
+            return None;
+        }
+
+        let (name, suffix) = match subtype.split_once('+') {
+            Some((name, suffix)) => (name, Some(suffix)),
+            None => (subtype, None),
+        };
+
+        if let Some((name, version)) = split_version(name) {
+            let mut media_type = format!("{}/{}", type_, name);
+
+            if let Some(suffix) = suffix {
+                media_type.push('+');
+                media_type.push_str(suffix);
+            }
+
+            return Some(Self {
+                media_type,
+                version: version.to_owned(),
+            });
+        }
+
+        for parameter in parts {
+            if let Some((key, value)) = parameter.split_once('=') {
+                if key.trim().eq_ignore_ascii_case("version") {
+                    let version = value.trim().trim_matches('"');
+
+                    if !version.is_empty() {
+                        return Some(Self {
+                            version: version.to_owned(),
+                            media_type: essence,
+                        });
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Get the media type without its version, such as
+    /// `application/vnd.example+json`.
+    pub fn media_type(&self) -> &str {
+        &self.media_type
+    }
+
+    /// Get the version of the media type, such as `2`. The leading `v` of a
+    /// version in the subtype is not included.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+}
+
+/// Split a subtype like `vnd.example.v2` into its name and version.
+fn split_version(subtype: &str) -> Option<(&str, &str)> {
+    let (name, version) = subtype.rsplit_once('.')?;
+    let version = version.strip_prefix('v')?;
+
+    if name.is_empty() || version.is_empty() || !version.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    Some((name, version))
+}
+
+/// Insert a version into the subtype of a media type, turning
+/// `application/vnd.example+json` into `application/vnd.example.v2+json`.
+pub(crate) fn versioned(media_type: &str, version: &str) -> String {
+    match media_type.split_once('+') {
+        Some((name, suffix)) => format!("{}.v{}+{}", name, version, suffix),
+        None => format!("{}.v{}", media_type, version),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(value: &str) -> Option<(String, String)> {
+        MediaTypeVersion::parse(value).map(|v| (v.media_type, v.version))
+    }
+
+    fn pair(media_type: &str, version: &str) -> Option<(String, String)> {
+        Some((media_type.to_owned(), version.to_owned()))
+    }
+
+    #[test]
+    fn version_in_subtype() {
+        assert_eq!(
+            parse("application/vnd.example.v2+json"),
+            pair("application/vnd.example+json", "2")
+        );
+        assert_eq!(
+            parse("Application/VND.Example.V10+JSON; charset=utf-8"),
+            pair("application/vnd.example+json", "10")
+        );
+        assert_eq!(parse("application/vnd.example.v3"), pair("application/vnd.example", "3"));
+    }
+
+    #[test]
+    fn version_parameter() {
+        assert_eq!(
+            parse("application/vnd.example+json; charset=utf-8; version=2"),
+            pair("application/vnd.example+json", "2")
+        );
+        assert_eq!(
+            parse("application/json;Version=\"1.5\""),
+            pair("application/json", "1.5")
+        );
+    }
+
+    #[test]
+    fn subtype_takes_precedence() {
+        assert_eq!(
+            parse("application/vnd.example.v2+json; version=3"),
+            pair("application/vnd.example+json", "2")
+        );
+    }
+
+    #[test]
+    fn unversioned() {
+        assert_eq!(parse("application/json"), None);
+        assert_eq!(parse("application/vnd.example.vnext+json"), None);
+        assert_eq!(parse("application/vnd.example+json; version="), None);
+        assert_eq!(parse("application/v2"), None);
+        assert_eq!(parse("/v2"), None);
+        assert_eq!(parse(""), None);
+    }
+
+    #[test]
+    fn insert_version() {
+        assert_eq!(
+            versioned("application/vnd.example+json", "2"),
+            "application/vnd.example.v2+json"
+        );
+        assert_eq!(versioned("application/vnd.example", "2"), "application/vnd.example.v2");
+    }
+}
@@ -1,7 +1,14 @@
 //! Request and response metrics tracking.
 
+use crate::error::Error;
 use crossbeam_utils::atomic::AtomicCell;
-use std::{fmt, sync::Arc, time::Duration};
+use http::{StatusCode, Version};
+use std::{
+    fmt,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 /// An object that holds status updates and progress statistics on a particular
 /// request. A [`Metrics`] can be shared between threads, which allows an agent
@@ -45,6 +52,8 @@ pub(crate) struct Inner {
     pub(crate) starttransfer_time: AtomicCell<f64>,
     pub(crate) total_time: AtomicCell<f64>,
     pub(crate) redirect_time: AtomicCell<f64>,
+
+    pub(crate) remote_addr: AtomicCell<Option<SocketAddr>>,
 }
 
 impl Metrics {
@@ -149,6 +158,14 @@ impl Metrics {
     pub fn redirect_time(&self) -> Duration {
         Duration::from_secs_f64(self.inner.redirect_time.load())
     }
+
+    /// Get the address of the server the transfer was made with, once a
+    /// connection has been established.
+    ///
+    /// When a redirect is followed, this is the address of the last server.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.inner.remote_addr.load()
+    }
 }
 
 impl fmt::Debug for Metrics {
@@ -165,6 +182,429 @@ impl fmt::Debug for Metrics {
             .field("transfer_time", &self.transfer_time())
             .field("total_time", &self.total_time())
             .field("redirect_time", &self.redirect_time())
+            .field("remote_addr", &self.remote_addr())
+            .finish()
+    }
+}
+
+/// Final statistics of a completed transfer, as reported by curl.
+///
+/// Delivered to the callback set with
+/// [`Configurable::on_stats`](crate::config::Configurable::on_stats) right
+/// before the transfer completes.
+#[derive(Clone)]
+pub struct TransferStats {
+    pub(crate) metrics: Metrics,
+    pub(crate) status: Option<StatusCode>,
+    pub(crate) version: Option<Version>,
+    pub(crate) local_addr: Option<SocketAddr>,
+    pub(crate) remote_addr: Option<SocketAddr>,
+    pub(crate) header_bytes: u64,
+    pub(crate) request_bytes: u64,
+    pub(crate) connects: u32,
+    pub(crate) redirects: u32,
+    pub(crate) connection_retired: bool,
+    pub(crate) timestamps: Option<Timestamps>,
+    pub(crate) error: Option<Error>,
+}
+
+impl TransferStats {
+    /// Get the status code of the response, if one was received.
+    pub fn status(&self) -> Option<StatusCode> {
+        self.status
+    }
+
+    /// Get the HTTP version of the response, if one was received.
+    pub fn version(&self) -> Option<Version> {
+        self.version
+    }
+
+    /// Get the local socket address of the last connection used.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    /// Get the remote socket address of the last connection used.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+
+    /// Get the points in time at which the transfer passed through each stage
+    /// on the client side, if it was executed by an agent.
+    pub fn timestamps(&self) -> Option<Timestamps> {
+        self.timestamps
+    }
+
+    /// Get the error the transfer failed with, if it failed.
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+
+    /// Number of bytes uploaded in the request body.
+    pub fn bytes_uploaded(&self) -> u64 {
+        self.metrics.upload_progress().0
+    }
+
+    /// Number of bytes downloaded in the response body.
+    pub fn bytes_downloaded(&self) -> u64 {
+        self.metrics.download_progress().0
+    }
+
+    /// Number of bytes of response headers received.
+    pub fn header_bytes(&self) -> u64 {
+        self.header_bytes
+    }
+
+    /// Number of bytes of request headers and body sent.
+    pub fn request_bytes(&self) -> u64 {
+        self.request_bytes
+    }
+
+    /// Number of new connections that had to be created for the transfer.
+    /// This is zero if an existing connection was reused.
+    pub fn connects(&self) -> u32 {
+        self.connects
+    }
+
+    /// Returns true if the transfer was sent over an existing connection
+    /// instead of opening a new one.
+    pub fn connection_reused(&self) -> bool {
+        self.connects == 0 && self.remote_addr.is_some()
+    }
+
+    /// Returns true if the connection used by the transfer was closed
+    /// afterwards instead of being kept for reuse, because it reached the
+    /// limit set with
+    /// [`HttpClientBuilder::connection_max_requests`](crate::HttpClientBuilder::connection_max_requests).
+    pub fn connection_retired(&self) -> bool {
+        self.connection_retired
+    }
+
+    /// Number of redirects followed by curl itself.
+    ///
+    /// Redirects followed according to a
+    /// [`RedirectPolicy`](crate::config::RedirectPolicy) are separate
+    /// transfers, each of which reports its own statistics.
+    pub fn redirects(&self) -> u32 {
+        self.redirects
+    }
+
+    /// Average upload speed in bytes/second.
+    pub fn upload_speed(&self) -> f64 {
+        self.metrics.upload_speed()
+    }
+
+    /// Average download speed in bytes/second.
+    pub fn download_speed(&self) -> f64 {
+        self.metrics.download_speed()
+    }
+
+    /// Time from the start of the transfer until DNS name resolving was
+    /// completed.
+    pub fn name_lookup_time(&self) -> Duration {
+        self.metrics.name_lookup_time()
+    }
+
+    /// Time taken to establish a connection to the server, not including TLS
+    /// connection time.
+    pub fn connect_time(&self) -> Duration {
+        self.metrics.connect_time()
+    }
+
+    /// Time spent on TLS handshakes.
+    pub fn secure_connect_time(&self) -> Duration {
+        self.metrics.secure_connect_time()
+    }
+
+    /// Time from the start of the transfer until the first byte was either
+    /// sent or received.
+    pub fn transfer_start_time(&self) -> Duration {
+        self.metrics.transfer_start_time()
+    }
+
+    /// Time spent sending the request and receiving the response.
+    pub fn transfer_time(&self) -> Duration {
+        self.metrics.transfer_time()
+    }
+
+    /// Total time of the transfer.
+    pub fn total_time(&self) -> Duration {
+        self.metrics.total_time()
+    }
+
+    /// Time spent on redirects followed by curl itself.
+    pub fn redirect_time(&self) -> Duration {
+        self.metrics.redirect_time()
+    }
+}
+
+impl fmt::Debug for TransferStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransferStats")
+            .field("status", &self.status)
+            .field("version", &self.version)
+            .field("local_addr", &self.local_addr)
+            .field("remote_addr", &self.remote_addr)
+            .field("error", &self.error)
+            .field("bytes_uploaded", &self.bytes_uploaded())
+            .field("bytes_downloaded", &self.bytes_downloaded())
+            .field("header_bytes", &self.header_bytes)
+            .field("request_bytes", &self.request_bytes)
+            .field("connects", &self.connects)
+            .field("redirects", &self.redirects)
+            .field("connection_retired", &self.connection_retired)
+            .field("timestamps", &self.timestamps)
+            .field("upload_speed", &self.upload_speed())
+            .field("download_speed", &self.download_speed())
+            .field("name_lookup_time", &self.name_lookup_time())
+            .field("connect_time", &self.connect_time())
+            .field("secure_connect_time", &self.secure_connect_time())
+            .field("transfer_start_time", &self.transfer_start_time())
+            .field("transfer_time", &self.transfer_time())
+            .field("total_time", &self.total_time())
+            .field("redirect_time", &self.redirect_time())
             .finish()
     }
 }
+
+/// Points in time at which a transfer passed through each stage on the client
+/// side.
+///
+/// Comparing these timestamps tells apart time spent waiting on the client,
+/// such as for room in the agent's request queue or for a
+/// [`Quota`](crate::config::Quota), from time spent on the network and
+/// waiting for the server. This is useful for example for excluding
+/// client-side queueing from latency measured against a service level
+/// objective.
+///
+/// Timestamps are taken from the [`Clock`](crate::config::Clock) of the
+/// client. They are available using
+/// [`ResponseExt::timestamps`](crate::ResponseExt::timestamps) and
+/// [`TransferStats::timestamps`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Timestamps {
+    pub(crate) submitted: Instant,
+    pub(crate) dequeued: Instant,
+    pub(crate) started: Option<Instant>,
+    pub(crate) headers_received: Option<Instant>,
+}
+
+impl Timestamps {
+    /// Get when the request was submitted to the agent for execution, after
+    /// passing through any interceptors.
+    pub fn submitted(&self) -> Instant {
+        self.submitted
+    }
+
+    /// Get when the agent began executing the request, after any time spent
+    /// waiting in its queue or for a quota.
+    pub fn dequeued(&self) -> Instant {
+        self.dequeued
+    }
+
+    /// Get when the request started to be sent to the server, after any name
+    /// resolution, connecting, and TLS handshake. This is `None` if the
+    /// transfer failed before the request could be sent.
+    pub fn started(&self) -> Option<Instant> {
+        self.started
+    }
+
+    /// Get when the header of the final response was completely received,
+    /// before any of the response body. This is `None` if no response was
+    /// received.
+    ///
+    /// Responses are returned as soon as their header is received, so this is
+    /// usually just before the response was returned to the caller.
+    pub fn headers_received(&self) -> Option<Instant> {
+        self.headers_received
+    }
+
+    /// Get the amount of time the request spent waiting on the client before
+    /// the agent began executing it.
+    pub fn queue_time(&self) -> Duration {
+        self.dequeued.saturating_duration_since(self.submitted)
+    }
+}
+
+/// Describes a response that the server sent before it had received the whole
+/// request body.
+///
+/// Servers often reject a request as soon as they have seen its header, for
+/// example with `413 Payload Too Large` or `401 Unauthorized`, without reading
+/// the body that follows. When a final response with an error status arrives
+/// while the request body is still being sent, the rest of the body is not
+/// sent, and the response is returned to the caller as usual. An HTTP/1.x
+/// connection that a request body was cut short on is not reused.
+///
+/// Available using
+/// [`ResponseExt::early_response`](crate::ResponseExt::early_response).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct EarlyResponse {
+    pub(crate) bytes_sent: u64,
+    pub(crate) body_len: Option<u64>,
+}
+
+impl EarlyResponse {
+    /// Number of bytes of the request body that had been sent when the
+    /// response arrived.
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent
+    }
+
+    /// Total size of the request body that was not completely sent, if known.
+    pub fn body_len(&self) -> Option<u64> {
+        self.body_len
+    }
+}
+
+/// A snapshot of how far a transfer has progressed, delivered to the callback
+/// set with [`Configurable::on_progress`](crate::config::Configurable::on_progress).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Progress {
+    pub(crate) bytes_sent: u64,
+    pub(crate) upload_total: u64,
+    pub(crate) bytes_received: u64,
+    pub(crate) download_total: u64,
+}
+
+impl Progress {
+    /// Number of bytes of the request body sent so far.
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent
+    }
+
+    /// Total size of the request body, if known.
+    pub fn upload_total(&self) -> Option<u64> {
+        Some(self.upload_total).filter(|&total| total > 0)
+    }
+
+    /// Number of bytes of the response body received so far.
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received
+    }
+
+    /// Total size of the response body, if known.
+    pub fn download_total(&self) -> Option<u64> {
+        Some(self.download_total).filter(|&total| total > 0)
+    }
+}
+
+/// A callback receiving progress updates of each transfer.
+#[derive(Clone)]
+pub(crate) struct ProgressCallback(pub(crate) Arc<dyn Fn(Progress) + Send + Sync>);
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+/// A callback receiving the statistics of each transfer.
+#[derive(Clone)]
+pub(crate) struct StatsCallback(pub(crate) Arc<dyn Fn(TransferStats) + Send + Sync>);
+
+impl fmt::Debug for StatsCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StatsCallback")
+    }
+}
+
+/// Wrap a statistics callback so that the statistics of each transfer to the
+/// given URI are also exported through the [`metrics`](::metrics) facade.
+#[cfg(feature = "metrics")]
+pub(crate) fn exporting(uri: &http::Uri, callback: Option<StatsCallback>) -> StatsCallback {
+    let host = uri.host().unwrap_or_default().to_ascii_lowercase();
+
+    StatsCallback(Arc::new(move |stats| {
+        export(&host, &stats);
+
+        if let Some(callback) = callback.as_ref() {
+            (callback.0)(stats);
+        }
+    }))
+}
+
+#[cfg(feature = "metrics")]
+fn export(host: &str, stats: &TransferStats) {
+    static DESCRIBE: std::sync::Once = std::sync::Once::new();
+
+    DESCRIBE.call_once(|| {
+        use ::metrics::{describe_counter, describe_histogram, Unit};
+
+        describe_counter!("isahc_requests_total", "Completed HTTP transfers.");
+        describe_histogram!(
+            "isahc_request_duration_seconds",
+            Unit::Seconds,
+            "Total time of HTTP transfers."
+        );
+        describe_histogram!(
+            "isahc_time_to_first_byte_seconds",
+            Unit::Seconds,
+            "Time until the first byte of HTTP transfers was sent or received."
+        );
+        describe_counter!(
+            "isahc_connections_opened_total",
+            "New connections created for HTTP transfers."
+        );
+        describe_counter!(
+            "isahc_connections_reused_total",
+            "HTTP transfers sent over an existing connection."
+        );
+        describe_counter!(
+            "isahc_connections_retired_total",
+            "Connections closed after reaching their request limit."
+        );
+        describe_counter!(
+            "isahc_bytes_sent_total",
+            Unit::Bytes,
+            "Bytes of HTTP request headers and bodies sent."
+        );
+        describe_counter!(
+            "isahc_bytes_received_total",
+            Unit::Bytes,
+            "Bytes of HTTP response headers and bodies received."
+        );
+    });
+
+    let status_class = match stats.status.map(|status| status.as_u16() / 100) {
+        Some(1) => "1xx",
+        Some(2) => "2xx",
+        Some(3) => "3xx",
+        Some(4) => "4xx",
+        Some(5) => "5xx",
+        _ => "error",
+    };
+
+    let version = match stats.version {
+        Some(Version::HTTP_09) => "0.9",
+        Some(Version::HTTP_10) => "1.0",
+        Some(Version::HTTP_11) => "1.1",
+        Some(Version::HTTP_2) => "2",
+        Some(Version::HTTP_3) => "3",
+        _ => "none",
+    };
+
+    let labels = [
+        ("host", host.to_owned()),
+        ("status_class", status_class.to_owned()),
+        ("version", version.to_owned()),
+    ];
+
+    ::metrics::register_counter!("isahc_requests_total", &labels).increment(1);
+    ::metrics::register_histogram!("isahc_request_duration_seconds", &labels)
+        .record(stats.total_time().as_secs_f64());
+
+    let labels = [("host", host.to_owned())];
+
+    ::metrics::register_histogram!("isahc_time_to_first_byte_seconds", &labels)
+        .record(stats.transfer_start_time().as_secs_f64());
+    ::metrics::register_counter!("isahc_connections_opened_total", &labels)
+        .increment(stats.connects.into());
+    ::metrics::register_counter!("isahc_connections_reused_total", &labels)
+        .increment(stats.connection_reused().into());
+    ::metrics::register_counter!("isahc_connections_retired_total", &labels)
+        .increment(stats.connection_retired.into());
+    ::metrics::register_counter!("isahc_bytes_sent_total", &labels).increment(stats.request_bytes);
+    ::metrics::register_counter!("isahc_bytes_received_total", &labels)
+        .increment(stats.header_bytes + stats.bytes_downloaded());
+}
@@ -0,0 +1,471 @@
+//! Building `multipart/form-data` request bodies.
+//!
+//! A [`Form`] is made up of named parts, each of which can be a text field, a
+//! byte buffer, a file, or a stream. The form is serialized with a random
+//! boundary into a request body, and
+//! [`content_type`](Form::content_type) gives the matching `Content-Type`
+//! header to send along with it, as described in [RFC
+//! 7578](https://tools.ietf.org/html/rfc7578).
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use isahc::{multipart::{Form, Part}, prelude::*, Request};
+//!
+//! let form = Form::new()
+//!     .text("title", "Holiday photos")
+//!     .part(
+//!         "photo",
+//!         Part::file("beach.jpg")?.content_type("image/jpeg"),
+//!     );
+//!
+//! let response = Request::post("https://example.org/upload")
+//!     .header("Content-Type", form.content_type())
+//!     .body(form)?
+//!     .send()?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::AsyncBody;
+use futures_lite::io::AsyncRead;
+use std::{
+    collections::{hash_map::RandomState, VecDeque},
+    fmt,
+    fs::File,
+    hash::{BuildHasher, Hasher},
+    io::{self, Cursor, Read},
+    path::Path,
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Context, Poll},
+};
+
+/// A `multipart/form-data` body made up of named parts.
+///
+/// Parts are sent in the order they are added. See the [module
+/// documentation](self) for an example.
+pub struct Form {
+    boundary: String,
+    parts: Vec<(String, Part)>,
+}
+
+impl Form {
+    /// Create a new empty form with a random boundary.
+    pub fn new() -> Self {
+        Self {
+            boundary: format!("{:016x}{:016x}", random(), random()),
+            parts: Vec::new(),
+        }
+    }
+
+    /// Get the boundary that separates the parts of this form.
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// Get the value of the `Content-Type` header to send with this form,
+    /// including its boundary.
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// Add a text field to the form.
+    pub fn text(self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.part(name, Part::text(value))
+    }
+
+    /// Add a part to the form with the given field name.
+    pub fn part(mut self, name: impl Into<String>, part: Part) -> Self {
+        self.parts.push((name.into(), part));
+        self
+    }
+
+    /// Get the total length of the serialized form, if the length of every
+    /// part is known.
+    pub fn len(&self) -> Option<u64> {
+        let mut len = self.closing().len() as u64;
+
+        for (name, part) in &self.parts {
+            len += self.part_header(name, part).len() as u64;
+            len += part.body.len()?;
+            len += 2;
+        }
+
+        Some(len)
+    }
+
+    /// Returns true if the form has no parts.
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    /// Format the delimiter and headers that precede the body of a part.
+    fn part_header(&self, name: &str, part: &Part) -> String {
+        let mut header = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"",
+            self.boundary,
+            escape(name)
+        );
+
+        if let Some(file_name) = &part.file_name {
+            header.push_str("; filename=\"");
+            header.push_str(&escape(file_name));
+            header.push('"');
+        }
+
+        if let Some(content_type) = &part.content_type {
+            header.push_str("\r\nContent-Type: ");
+            header.push_str(content_type);
+        }
+
+        header.push_str("\r\n\r\n");
+        header
+    }
+
+    /// Format the delimiter that ends the form.
+    fn closing(&self) -> String {
+        format!("--{}--\r\n", self.boundary)
+    }
+
+    /// Serialize the form into a sequence of segments to be read in order.
+    fn into_reader(self) -> FormReader {
+        let mut segments = VecDeque::with_capacity(self.parts.len() * 3 + 1);
+        let headers = self
+            .parts
+            .iter()
+            .map(|(name, part)| self.part_header(name, part))
+            .collect::<Vec<_>>();
+        let closing = self.closing();
+
+        for (header, (_, part)) in headers.into_iter().zip(self.parts) {
+            segments.push_back(Segment::Bytes(Cursor::new(header.into_bytes())));
+            segments.push_back(match part.body {
+                PartBody::Bytes(bytes) => Segment::Bytes(Cursor::new(bytes)),
+                PartBody::File(file, _) => Segment::Reader(Box::pin(FileReader(file))),
+                PartBody::Reader(reader, _) => Segment::Reader(reader),
+            });
+            segments.push_back(Segment::Bytes(Cursor::new(b"\r\n".to_vec())));
+        }
+
+        segments.push_back(Segment::Bytes(Cursor::new(closing.into_bytes())));
+
+        FormReader { segments }
+    }
+
+    /// Serialize the form into a single buffer, if all of its parts are
+    /// stored in memory.
+    fn to_bytes(&self) -> Option<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(self.len()? as usize);
+
+        for (name, part) in &self.parts {
+            match &part.body {
+                PartBody::Bytes(body) => {
+                    bytes.extend_from_slice(self.part_header(name, part).as_bytes());
+                    bytes.extend_from_slice(body);
+                    bytes.extend_from_slice(b"\r\n");
+                }
+                _ => return None,
+            }
+        }
+
+        bytes.extend_from_slice(self.closing().as_bytes());
+
+        Some(bytes)
+    }
+}
+
+impl Default for Form {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Form {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Form")
+            .field("boundary", &self.boundary)
+            .field("parts", &self.parts)
+            .finish()
+    }
+}
+
+impl From<Form> for AsyncBody {
+    fn from(form: Form) -> Self {
+        // Forms kept entirely in memory can be sent again if the request is
+        // redirected or retried.
+        if let Some(bytes) = form.to_bytes() {
+            return bytes.into();
+        }
+
+        match form.len() {
+            Some(len) => AsyncBody::from_reader_sized(form.into_reader(), len),
+            None => AsyncBody::from_reader(form.into_reader()),
+        }
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl From<Form> for crate::Body {
+    fn from(form: Form) -> Self {
+        use crate::Body;
+        use futures_lite::io::BlockOn;
+
+        if let Some(bytes) = form.to_bytes() {
+            return bytes.into();
+        }
+
+        match form.len() {
+            Some(len) => Body::from_reader_sized(BlockOn::new(form.into_reader()), len),
+            None => Body::from_reader(BlockOn::new(form.into_reader())),
+        }
+    }
+}
+
+/// A single part of a [`Form`].
+///
+/// Every part can be given a file name and a content type. Parts created with
+/// [`Part::file`] get both by default.
+pub struct Part {
+    body: PartBody,
+    file_name: Option<String>,
+    content_type: Option<String>,
+}
+
+enum PartBody {
+    Bytes(Vec<u8>),
+    File(File, u64),
+    Reader(Pin<Box<dyn AsyncRead + Send + Sync>>, Option<u64>),
+}
+
+impl PartBody {
+    fn len(&self) -> Option<u64> {
+        match self {
+            Self::Bytes(bytes) => Some(bytes.len() as u64),
+            Self::File(_, len) => Some(*len),
+            Self::Reader(_, len) => *len,
+        }
+    }
+}
+
+impl Part {
+    fn new(body: PartBody) -> Self {
+        Self {
+            body,
+            file_name: None,
+            content_type: None,
+        }
+    }
+
+    /// Create a part containing the given text.
+    pub fn text(value: impl Into<String>) -> Self {
+        Self::new(PartBody::Bytes(value.into().into_bytes()))
+    }
+
+    /// Create a part containing the given bytes.
+    pub fn bytes(bytes: impl Into<Vec<u8>>) -> Self {
+        Self::new(PartBody::Bytes(bytes.into()))
+    }
+
+    /// Create a part containing the contents of the file at the given path.
+    ///
+    /// The file is opened right away, but is read only as the form is sent.
+    /// The part gets the name of the file as its file name and a content type
+    /// of `application/octet-stream`, both of which can be changed.
+    ///
+    /// Like curl, the file is read on the thread that drives the transfer. For
+    /// files on slow or remote file systems, consider using
+    /// [`Part::reader_sized`] with an asynchronous file reader instead.
+    pub fn file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let mut part = Self::new(PartBody::File(file, len))
+            .content_type("application/octet-stream");
+
+        if let Some(file_name) = path.file_name() {
+            part = part.file_name(file_name.to_string_lossy());
+        }
+
+        Ok(part)
+    }
+
+    /// Create a part that streams its contents from the given reader.
+    ///
+    /// Since the length of the part is unknown, a form containing it will
+    /// have an unknown length as well.
+    pub fn reader<R>(reader: R) -> Self
+    where
+        R: AsyncRead + Send + Sync + 'static,
+    {
+        Self::new(PartBody::Reader(Box::pin(reader), None))
+    }
+
+    /// Create a part that streams its contents from the given reader, which
+    /// produces exactly `length` bytes.
+    pub fn reader_sized<R>(reader: R, length: u64) -> Self
+    where
+        R: AsyncRead + Send + Sync + 'static,
+    {
+        Self::new(PartBody::Reader(Box::pin(reader), Some(length)))
+    }
+
+    /// Set the file name of this part.
+    pub fn file_name(mut self, file_name: impl Into<String>) -> Self {
+        self.file_name = Some(file_name.into());
+        self
+    }
+
+    /// Set the content type of this part.
+    pub fn content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+}
+
+impl fmt::Debug for Part {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Part")
+            .field("file_name", &self.file_name)
+            .field("content_type", &self.content_type)
+            .field("len", &self.body.len())
+            .finish()
+    }
+}
+
+/// Reads a serialized form one segment at a time.
+struct FormReader {
+    segments: VecDeque<Segment>,
+}
+
+enum Segment {
+    Bytes(Cursor<Vec<u8>>),
+    Reader(Pin<Box<dyn AsyncRead + Send + Sync>>),
+}
+
+impl AsyncRead for FormReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        while let Some(segment) = self.segments.front_mut() {
+            let len = match segment {
+                Segment::Bytes(cursor) => cursor.read(buf)?,
+                Segment::Reader(reader) => match reader.as_mut().poll_read(cx, buf) {
+                    Poll::Ready(result) => result?,
+                    Poll::Pending => return Poll::Pending,
+                },
+            };
+
+            if len > 0 {
+                return Poll::Ready(Ok(len));
+            }
+
+            self.segments.pop_front();
+        }
+
+        Poll::Ready(Ok(0))
+    }
+}
+
+/// Reads a file synchronously, the same way curl reads file parts.
+struct FileReader(File);
+
+impl AsyncRead for FileReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(self.0.read(buf))
+    }
+}
+
+/// Escape a field name or file name for use in a quoted parameter, the same
+/// way browsers do.
+fn escape(value: &str) -> String {
+    value
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Generate a random number for use in a boundary.
+fn random() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_lite::{future::block_on, io::AsyncReadExt};
+
+    fn read(form: Form) -> String {
+        let mut body = AsyncBody::from(form);
+        let mut string = String::new();
+        block_on(body.read_to_string(&mut string)).unwrap();
+        string
+    }
+
+    #[test]
+    fn serialize_form() {
+        let mut form = Form::new()
+            .text("name", "value")
+            .part(
+                "file",
+                Part::bytes("hello").file_name("a.txt").content_type("text/plain"),
+            );
+        form.boundary = "XYZ".into();
+
+        let expected = "--XYZ\r\n\
+             Content-Disposition: form-data; name=\"name\"\r\n\r\n\
+             value\r\n\
+             --XYZ\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+             Content-Type: text/plain\r\n\r\n\
+             hello\r\n\
+             --XYZ--\r\n";
+
+        assert_eq!(form.len(), Some(expected.len() as u64));
+        assert_eq!(read(form), expected);
+    }
+
+    #[test]
+    fn streamed_parts_have_same_serialization() {
+        let mut form = Form::new()
+            .text("a", "1")
+            .part("b", Part::reader(futures_lite::io::Cursor::new(b"22".to_vec())));
+        form.boundary = "XYZ".into();
+
+        assert_eq!(form.len(), None);
+        assert_eq!(
+            read(form),
+            "--XYZ\r\n\
+             Content-Disposition: form-data; name=\"a\"\r\n\r\n\
+             1\r\n\
+             --XYZ\r\n\
+             Content-Disposition: form-data; name=\"b\"\r\n\r\n\
+             22\r\n\
+             --XYZ--\r\n"
+        );
+    }
+
+    #[test]
+    fn names_are_escaped() {
+        assert_eq!(escape("a\"b\r\nc"), "a%22b%0D%0Ac");
+    }
+
+    #[test]
+    fn boundaries_are_unique() {
+        assert_ne!(Form::new().boundary(), Form::new().boundary());
+    }
+}
@@ -0,0 +1,340 @@
+//! Normalization of request URLs before they are sent.
+
+use crate::{
+    body::AsyncBody,
+    config::{request::RequestConfig, TrailingSlash, UrlNormalization},
+    error::{Error, ErrorKind},
+    interceptor::{Context, Interceptor, InterceptorFuture},
+    redirect::EffectiveUri,
+};
+use http::{Request, Uri};
+use std::fmt::Write;
+
+/// Interceptor that normalizes the URL of each request according to the
+/// configured policy, if any, and records the URL that was sent on the
+/// response.
+///
+/// This is placed after the redirect interceptor so that every hop is
+/// normalized.
+pub(crate) struct NormalizeInterceptor;
+
+impl Interceptor for NormalizeInterceptor {
+    type Err = Error;
+
+    fn intercept<'a>(
+        &'a self,
+        mut request: Request<AsyncBody>,
+        ctx: Context<'a>,
+    ) -> InterceptorFuture<'a, Self::Err> {
+        Box::pin(async move {
+            let policy = match request
+                .extensions()
+                .get::<RequestConfig>()
+                .and_then(|config| config.url_normalization.as_ref())
+            {
+                Some(policy) => policy,
+                None => return ctx.send(request).await,
+            };
+
+            let uri = normalize(request.uri(), policy)?;
+
+            tracing::trace!("normalized request URL to {}", uri);
+            *request.uri_mut() = uri.clone();
+
+            let mut response = ctx.send(request).await?;
+            response.extensions_mut().insert(EffectiveUri(uri));
+
+            Ok(response)
+        })
+    }
+}
+
+fn normalize(uri: &Uri, policy: &UrlNormalization) -> Result<Uri, Error> {
+    let mut s = String::new();
+
+    if let Some(scheme) = uri.scheme_str() {
+        if policy.lowercase {
+            s.push_str(&scheme.to_ascii_lowercase());
+        } else {
+            s.push_str(scheme);
+        }
+        s.push_str("://");
+    }
+
+    if let Some(authority) = uri.authority() {
+        let authority = authority.as_str();
+
+        // Keep any user information as given.
+        let (userinfo, host_port) = match authority.rfind('@') {
+            Some(i) => authority.split_at(i + 1),
+            None => ("", authority),
+        };
+
+        s.push_str(userinfo);
+
+        let host_port = if policy.remove_default_port {
+            match (uri.port_u16(), uri.scheme_str()) {
+                (Some(80), Some(scheme)) if scheme.eq_ignore_ascii_case("http") => {
+                    strip_port(host_port)
+                }
+                (Some(443), Some(scheme)) if scheme.eq_ignore_ascii_case("https") => {
+                    strip_port(host_port)
+                }
+                _ => host_port,
+            }
+        } else {
+            host_port
+        };
+
+        if policy.lowercase {
+            s.push_str(&host_port.to_ascii_lowercase());
+        } else {
+            s.push_str(host_port);
+        }
+    }
+
+    let mut path = uri.path().to_owned();
+
+    if policy.decode_unreserved {
+        path = decode_unreserved(&path);
+    }
+
+    if policy.remove_dot_segments {
+        path = remove_dot_segments(&path);
+    }
+
+    if policy.merge_slashes {
+        path = merge_slashes(&path);
+    }
+
+    match policy.trailing_slash {
+        TrailingSlash::Keep => {}
+        TrailingSlash::Add => {
+            if !path.ends_with('/') {
+                path.push('/');
+            }
+        }
+        TrailingSlash::Remove => {
+            while path.len() > 1 && path.ends_with('/') {
+                path.pop();
+            }
+        }
+    }
+
+    encode_chars(&mut s, &path, &policy.path_chars);
+
+    if let Some(query) = uri.query() {
+        s.push('?');
+
+        let mut query = if policy.decode_unreserved {
+            decode_unreserved(query)
+        } else {
+            query.to_owned()
+        };
+
+        if policy.sort_query {
+            query = sort_query(&query);
+        }
+
+        encode_chars(&mut s, &query, &policy.query_chars);
+    }
+
+    s.parse()
+        .map_err(|e| Error::new(ErrorKind::InvalidRequest, e))
+}
+
+fn strip_port(host_port: &str) -> &str {
+    match host_port.rfind(':') {
+        // Don't mistake the colons of an IPv6 address for a port.
+        Some(i) if !host_port[i..].contains(']') => &host_port[..i],
+        _ => host_port,
+    }
+}
+
+/// Decode percent-encoded unreserved characters and uppercase the hex digits
+/// of other percent-encoded characters, as described in [RFC 3986, section
+/// 6.2.2](https://tools.ietf.org/html/rfc3986#section-6.2.2).
+fn decode_unreserved(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = String::with_capacity(s.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && i + 2 < bytes.len()
+            && bytes[i + 1].is_ascii_hexdigit()
+            && bytes[i + 2].is_ascii_hexdigit()
+        {
+            let hex = &s[i + 1..i + 3];
+            let byte = u8::from_str_radix(hex, 16).unwrap();
+
+            if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+                out.push(byte as char);
+            } else {
+                out.push('%');
+                out.push_str(&hex.to_ascii_uppercase());
+            }
+
+            i += 3;
+            continue;
+        }
+
+        let c = s[i..].chars().next().unwrap();
+        out.push(c);
+        i += c.len_utf8();
+    }
+
+    out
+}
+
+/// Resolve dot segments in an absolute path, as described in [RFC 3986,
+/// section 5.2.4](https://tools.ietf.org/html/rfc3986#section-5.2.4).
+fn remove_dot_segments(path: &str) -> String {
+    if !path.starts_with('/') {
+        return path.to_owned();
+    }
+
+    let segments = path[1..].split('/').collect::<Vec<_>>();
+    let mut output = Vec::with_capacity(segments.len());
+
+    for (i, segment) in segments.iter().enumerate() {
+        let is_last = i == segments.len() - 1;
+
+        match *segment {
+            "." => {}
+            ".." => {
+                output.pop();
+            }
+            segment => {
+                output.push(segment);
+                continue;
+            }
+        }
+
+        // A trailing dot segment refers to a directory.
+        if is_last {
+            output.push("");
+        }
+    }
+
+    format!("/{}", output.join("/"))
+}
+
+/// Collapse consecutive slashes in a path into one.
+fn merge_slashes(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+
+    for c in path.chars() {
+        if c != '/' || !out.ends_with('/') {
+            out.push(c);
+        }
+    }
+
+    out
+}
+
+/// Sort the parameters of a query string by name, keeping parameters with the
+/// same name in order.
+fn sort_query(query: &str) -> String {
+    let mut params = query.split('&').collect::<Vec<_>>();
+
+    params.sort_by_key(|param| param.split('=').next().unwrap_or(param));
+    params.join("&")
+}
+
+/// Append a string, percent-encoding any of the given characters.
+fn encode_chars(out: &mut String, s: &str, chars: &str) {
+    for c in s.chars() {
+        if c.is_ascii() && chars.contains(c) {
+            write!(out, "%{:02X}", c as u8).unwrap();
+        } else {
+            out.push(c);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("/a/b/c/./../../g", "/a/g")]
+    #[test_case("/a/./", "/a/")]
+    #[test_case("/a/..", "/")]
+    #[test_case("/..", "/")]
+    #[test_case("/a//b", "/a//b")]
+    fn dot_segments_are_removed(path: &str, expected: &str) {
+        assert_eq!(remove_dot_segments(path), expected);
+    }
+
+    #[test_case("/a//b///c", "/a/b/c")]
+    #[test_case("//", "/")]
+    #[test_case("/a/b", "/a/b")]
+    fn duplicate_slashes_are_merged(path: &str, expected: &str) {
+        assert_eq!(merge_slashes(path), expected);
+    }
+
+    #[test_case("b=2&a=1&c", "a=1&b=2&c")]
+    #[test_case("b=2&a=3&b=1", "a=3&b=2&b=1")]
+    #[test_case("", "")]
+    fn query_is_sorted_by_name(query: &str, expected: &str) {
+        assert_eq!(sort_query(query), expected);
+    }
+
+    #[test_case(TrailingSlash::Keep, "/a/", "/a/"; "keep")]
+    #[test_case(TrailingSlash::Add, "/a", "/a/"; "add")]
+    #[test_case(TrailingSlash::Add, "/a/", "/a/"; "add existing")]
+    #[test_case(TrailingSlash::Remove, "/a//", "/a"; "remove")]
+    #[test_case(TrailingSlash::Remove, "/", "/"; "remove root")]
+    fn trailing_slash_is_applied(trailing_slash: TrailingSlash, path: &str, expected: &str) {
+        let uri = format!("http://example.org{}", path).parse().unwrap();
+        let policy = UrlNormalization::new().trailing_slash(trailing_slash);
+
+        assert_eq!(
+            normalize(&uri, &policy).unwrap(),
+            format!("http://example.org{}", expected).as_str()
+        );
+    }
+
+    #[test]
+    fn unreserved_characters_are_decoded() {
+        assert_eq!(decode_unreserved("/%41%7e%2f%2F%"), "/A~%2F%2F%");
+    }
+
+    #[test]
+    fn scheme_host_and_port_are_normalized() {
+        let uri = "HTTPS://User@Example.ORG:443/A/%62/./c?Q=%7E"
+            .parse()
+            .unwrap();
+        let policy = UrlNormalization::new()
+            .lowercase(true)
+            .remove_default_port(true)
+            .decode_unreserved(true);
+
+        assert_eq!(
+            normalize(&uri, &policy).unwrap(),
+            "https://User@example.org/A/b/c?Q=~"
+        );
+    }
+
+    #[test]
+    fn ipv6_address_without_port_is_kept() {
+        let uri = "http://[::1]/".parse().unwrap();
+        let policy = UrlNormalization::new().remove_default_port(true);
+
+        assert_eq!(normalize(&uri, &policy).unwrap(), "http://[::1]/");
+    }
+
+    #[test]
+    fn extra_characters_are_encoded() {
+        let uri = "http://example.org/a(b)?c=d,e".parse().unwrap();
+        let policy = UrlNormalization::new()
+            .percent_encode_path("()")
+            .percent_encode_query(",");
+
+        assert_eq!(
+            normalize(&uri, &policy).unwrap(),
+            "http://example.org/a%28b%29?c=d%2Ce"
+        );
+    }
+}
@@ -0,0 +1,593 @@
+//! Acquiring access tokens from OAuth 2.0 and OpenID Connect authorization
+//! servers.
+//!
+//! The token providers in this module implement the [client
+//! credentials](https://tools.ietf.org/html/rfc6749#section-4.4) and [refresh
+//! token](https://tools.ietf.org/html/rfc6749#section-6) grants. They request
+//! access tokens from a token endpoint as needed, cache them until shortly
+//! before they expire, and can be used directly as a
+//! [`TokenProvider`](crate::auth::TokenProvider) to authenticate requests.
+//!
+//! The token endpoint of a server can be discovered using
+//! [`WellKnown::oauth_authorization_server`](crate::well_known::WellKnown::oauth_authorization_server)
+//! or
+//! [`WellKnown::openid_configuration`](crate::well_known::WellKnown::openid_configuration).
+//!
+//! # Availability
+//!
+//! This module is only available when the [`oauth`](../index.html#oauth)
+//! feature is enabled.
+
+use crate::{
+    auth::{Authentication, Credentials, TokenFuture, TokenProvider},
+    config::Configurable,
+    error::{Error, ErrorKind},
+    AsyncReadResponseExt,
+    HttpClient,
+};
+use http::{
+    header::{ACCEPT, CONTENT_TYPE},
+    Request,
+    Uri,
+};
+use std::{
+    convert::TryFrom,
+    error::Error as StdError,
+    fmt,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Default amount of time before a token expires that a new token is
+/// requested.
+const DEFAULT_REFRESH_MARGIN: Duration = Duration::from_secs(30);
+
+/// An access token issued by an authorization server.
+#[derive(Clone)]
+pub struct Token {
+    access_token: String,
+    token_type: String,
+    expires_at: Option<Instant>,
+    refresh_token: Option<String>,
+    scope: Option<String>,
+}
+
+impl Token {
+    /// Get the access token itself.
+    pub fn access_token(&self) -> &str {
+        &self.access_token
+    }
+
+    /// Get the type of the token, which is usually `Bearer`.
+    pub fn token_type(&self) -> &str {
+        &self.token_type
+    }
+
+    /// Get the time at which the token expires, if the server said when.
+    pub fn expires_at(&self) -> Option<Instant> {
+        self.expires_at
+    }
+
+    /// Get the refresh token issued along with the access token, if any.
+    pub fn refresh_token(&self) -> Option<&str> {
+        self.refresh_token.as_deref()
+    }
+
+    /// Get the scope of the token, if it differs from the scope requested.
+    pub fn scope(&self) -> Option<&str> {
+        self.scope.as_deref()
+    }
+
+    /// Returns true if the token has expired, or will expire within the given
+    /// margin of time.
+    pub fn expires_within(&self, margin: Duration) -> bool {
+        self.expires_within_at(margin, Instant::now())
+    }
+
+    fn expires_within_at(&self, margin: Duration, now: Instant) -> bool {
+        matches!(self.expires_at, Some(expires_at) if expires_at <= now + margin)
+    }
+
+    /// Parse a successful token response received at the given time.
+    fn from_json(json: &serde_json::Value, now: Instant) -> Option<Self> {
+        Some(Self {
+            access_token: json["access_token"].as_str()?.to_owned(),
+            token_type: json["token_type"].as_str().unwrap_or("Bearer").to_owned(),
+            expires_at: json["expires_in"]
+                .as_u64()
+                .or_else(|| json["expires_in"].as_str()?.parse().ok())
+                .map(|secs| now + Duration::from_secs(secs)),
+            refresh_token: json["refresh_token"].as_str().map(String::from),
+            scope: json["scope"].as_str().map(String::from),
+        })
+    }
+}
+
+// Tokens are secrets, so don't print them even on accident.
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Token")
+            .field("access_token", &"*****")
+            .field("token_type", &self.token_type)
+            .field("expires_at", &self.expires_at)
+            .field("refresh_token", &self.refresh_token.as_ref().map(|_| "*****"))
+            .field("scope", &self.scope)
+            .finish()
+    }
+}
+
+/// An error response returned by a token endpoint.
+///
+/// Errors of this type are returned as the source of an [`Error`] of kind
+/// [`ErrorKind::InvalidCredentials`].
+#[derive(Clone, Debug)]
+pub struct OAuthError {
+    error: String,
+    description: Option<String>,
+    uri: Option<String>,
+}
+
+impl OAuthError {
+    /// Get the error code, such as `invalid_client` or `invalid_grant`.
+    pub fn error(&self) -> &str {
+        &self.error
+    }
+
+    /// Get the human-readable description of the error, if any.
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Get the URI of a page describing the error, if any.
+    pub fn uri(&self) -> Option<&str> {
+        self.uri.as_deref()
+    }
+}
+
+impl fmt::Display for OAuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.error)?;
+
+        if let Some(description) = self.description.as_ref() {
+            write!(f, ": {}", description)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl StdError for OAuthError {}
+
+/// How a client authenticates itself to the token endpoint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ClientAuthentication {
+    /// Send the client ID and secret using HTTP Basic authentication
+    /// (`client_secret_basic`). This is the default.
+    Basic,
+
+    /// Send the client ID and secret in the request body
+    /// (`client_secret_post`).
+    Post,
+}
+
+// Can't derive this on our MSRV.
+#[allow(clippy::derivable_impls)]
+impl Default for ClientAuthentication {
+    fn default() -> Self {
+        Self::Basic
+    }
+}
+
+/// Shared implementation of requests to a token endpoint.
+struct TokenEndpoint {
+    uri: Uri,
+    client_id: String,
+    client_secret: Option<String>,
+    authentication: ClientAuthentication,
+    params: Vec<(String, String)>,
+    client: Option<HttpClient>,
+    refresh_margin: Duration,
+    token: Mutex<Option<Token>>,
+}
+
+impl TokenEndpoint {
+    fn new<U>(uri: U, client_id: String) -> Result<Self, Error>
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        Ok(Self {
+            uri: Uri::try_from(uri).map_err(|e| Error::from(e.into()))?,
+            client_id,
+            client_secret: None,
+            authentication: ClientAuthentication::default(),
+            params: Vec::new(),
+            client: None,
+            refresh_margin: DEFAULT_REFRESH_MARGIN,
+            token: Mutex::new(None),
+        })
+    }
+
+    fn http_client(&self) -> &HttpClient {
+        self.client.as_ref().unwrap_or_else(|| HttpClient::shared())
+    }
+
+    /// Get the cached token if it is still fresh.
+    fn cached(&self) -> Option<Token> {
+        let now = self.http_client().clock().now();
+
+        self.token
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|token| !token.expires_within_at(self.refresh_margin, now))
+            .cloned()
+    }
+
+    async fn request(&self, grant: &[(&str, &str)]) -> Result<Token, Error> {
+        let mut request = Request::post(self.uri.clone())
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .header(ACCEPT, "application/json");
+
+        // The serializer is not `Send`, so make sure it is gone before
+        // awaiting anything.
+        let form = {
+            let mut form = url::form_urlencoded::Serializer::new(String::new());
+            form.extend_pairs(grant);
+            form.extend_pairs(&self.params);
+
+            match (self.authentication, self.client_secret.as_ref()) {
+                (ClientAuthentication::Basic, Some(secret)) => {
+                    // The client ID and secret must be form-encoded before
+                    // being used as Basic credentials.
+                    request = request
+                        .authentication(Authentication::basic())
+                        .credentials(Credentials::new(
+                            form_encode(&self.client_id),
+                            form_encode(secret),
+                        ));
+                }
+                (_, secret) => {
+                    form.append_pair("client_id", &self.client_id);
+
+                    if let Some(secret) = secret {
+                        form.append_pair("client_secret", secret);
+                    }
+                }
+            }
+
+            form.finish()
+        };
+
+        let client = self.http_client();
+        let mut response = client.send_async(request.body(form)?).await?;
+        let body = response.bytes().await?;
+
+        let json = serde_json::from_slice::<serde_json::Value>(&body).map_err(|e| {
+            tracing::debug!("token endpoint returned invalid JSON: {}", e);
+            Error::with_response(ErrorKind::ProtocolViolation, &response)
+        })?;
+
+        if !response.status().is_success() {
+            return Err(match json["error"].as_str() {
+                Some(error) => {
+                    let error = OAuthError {
+                        error: error.to_owned(),
+                        description: json["error_description"].as_str().map(String::from),
+                        uri: json["error_uri"].as_str().map(String::from),
+                    };
+
+                    tracing::debug!("token request was rejected: {}", error);
+
+                    Error::new(ErrorKind::InvalidCredentials, error)
+                }
+                None => Error::with_response(ErrorKind::BadStatus, &response),
+            });
+        }
+
+        let token = Token::from_json(&json, client.clock().now()).ok_or_else(|| {
+            tracing::debug!("token endpoint response is missing an access token");
+            Error::with_response(ErrorKind::ProtocolViolation, &response)
+        })?;
+
+        *self.token.lock().unwrap() = Some(token.clone());
+
+        Ok(token)
+    }
+}
+
+impl fmt::Debug for TokenEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenEndpoint")
+            .field("uri", &self.uri)
+            .field("client_id", &self.client_id)
+            .field("authentication", &self.authentication)
+            .field("refresh_margin", &self.refresh_margin)
+            .finish()
+    }
+}
+
+fn form_encode(value: &str) -> String {
+    url::form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
+
+macro_rules! impl_token_endpoint_builders {
+    ($t:ty) => {
+        impl $t {
+            /// Set how the client authenticates itself to the token endpoint.
+            #[must_use = "builders have no effect if unused"]
+            pub fn client_authentication(mut self, authentication: ClientAuthentication) -> Self {
+                self.endpoint.authentication = authentication;
+                self
+            }
+
+            /// Add an extra parameter to send to the token endpoint, such as
+            /// `audience` or `resource`.
+            #[must_use = "builders have no effect if unused"]
+            pub fn param(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+                self.endpoint.params.push((name.into(), value.into()));
+                self
+            }
+
+            /// Set the client to send token requests with. If not set, the
+            /// same default client used by [`isahc::send`](crate::send) is
+            /// used.
+            ///
+            /// This should not be a client that uses this provider itself.
+            #[must_use = "builders have no effect if unused"]
+            pub fn client(mut self, client: HttpClient) -> Self {
+                self.endpoint.client = Some(client);
+                self
+            }
+
+            /// Set how long before a token expires to request a new one. The
+            /// default is 30 seconds.
+            #[must_use = "builders have no effect if unused"]
+            pub fn refresh_margin(mut self, margin: Duration) -> Self {
+                self.endpoint.refresh_margin = margin;
+                self
+            }
+
+            /// Get the most recently issued token, if any, even if it has
+            /// expired.
+            pub fn current_token(&self) -> Option<Token> {
+                self.endpoint.token.lock().unwrap().clone()
+            }
+        }
+    };
+}
+
+/// A token provider using the client credentials grant, for services that
+/// authenticate as themselves rather than on behalf of a user.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{oauth::ClientCredentials, prelude::*, HttpClient};
+///
+/// let client = HttpClient::builder()
+///     .token_provider(
+///         ClientCredentials::new("https://auth.example.org/token", "my-service", "secret")?
+///             .scope("orders:read"),
+///     )
+///     .build()?;
+///
+/// let response = client.get("https://api.example.org/orders")?;
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct ClientCredentials {
+    endpoint: TokenEndpoint,
+    scopes: Vec<String>,
+}
+
+impl ClientCredentials {
+    /// Create a new provider that requests tokens from the given token
+    /// endpoint using the given client ID and secret.
+    pub fn new<U>(
+        token_endpoint: U,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+    ) -> Result<Self, Error>
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        let mut endpoint = TokenEndpoint::new(token_endpoint, client_id.into())?;
+        endpoint.client_secret = Some(client_secret.into());
+
+        Ok(Self {
+            endpoint,
+            scopes: Vec::new(),
+        })
+    }
+
+    /// Add a scope to request.
+    #[must_use = "builders have no effect if unused"]
+    pub fn scope(mut self, scope: impl Into<String>) -> Self {
+        self.scopes.push(scope.into());
+        self
+    }
+
+    /// Request a new token from the token endpoint, even if the current token
+    /// has not yet expired.
+    pub async fn fetch_token(&self) -> Result<Token, Error> {
+        let scope = self.scopes.join(" ");
+        let mut grant = vec![("grant_type", "client_credentials")];
+
+        if !scope.is_empty() {
+            grant.push(("scope", &scope));
+        }
+
+        self.endpoint.request(&grant).await
+    }
+}
+
+impl_token_endpoint_builders!(ClientCredentials);
+
+impl TokenProvider for ClientCredentials {
+    fn token(&self) -> TokenFuture<'_> {
+        Box::pin(async move {
+            match self.endpoint.cached() {
+                Some(token) => Ok(token.access_token),
+                None => Ok(self.fetch_token().await?.access_token),
+            }
+        })
+    }
+
+    fn invalidate(&self) {
+        self.endpoint.token.lock().unwrap().take();
+    }
+}
+
+/// A token provider using the refresh token grant, for acting on behalf of a
+/// user who previously authorized the application.
+///
+/// If the server issues a new refresh token along with an access token, it
+/// replaces the old refresh token. Use [`RefreshToken::refresh_token`] to
+/// persist the latest refresh token.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{oauth::RefreshToken, prelude::*, HttpClient};
+///
+/// let provider = RefreshToken::new(
+///     "https://auth.example.org/token",
+///     "my-app",
+///     std::fs::read_to_string("refresh-token.txt")?,
+/// )?;
+///
+/// let client = HttpClient::builder().token_provider(provider).build()?;
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct RefreshToken {
+    endpoint: TokenEndpoint,
+    refresh_token: Mutex<String>,
+}
+
+impl RefreshToken {
+    /// Create a new provider that requests tokens from the given token
+    /// endpoint for a public client with the given ID, using the given
+    /// refresh token.
+    pub fn new<U>(
+        token_endpoint: U,
+        client_id: impl Into<String>,
+        refresh_token: impl Into<String>,
+    ) -> Result<Self, Error>
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        Ok(Self {
+            endpoint: TokenEndpoint::new(token_endpoint, client_id.into())?,
+            refresh_token: Mutex::new(refresh_token.into().trim().to_owned()),
+        })
+    }
+
+    /// Set the client secret, for confidential clients.
+    #[must_use = "builders have no effect if unused"]
+    pub fn client_secret(mut self, secret: impl Into<String>) -> Self {
+        self.endpoint.client_secret = Some(secret.into());
+        self
+    }
+
+    /// Get the current refresh token.
+    pub fn refresh_token(&self) -> String {
+        self.refresh_token.lock().unwrap().clone()
+    }
+
+    /// Request a new token from the token endpoint, even if the current token
+    /// has not yet expired.
+    pub async fn fetch_token(&self) -> Result<Token, Error> {
+        let refresh_token = self.refresh_token();
+        let grant = [
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token.as_str()),
+        ];
+
+        let token = self.endpoint.request(&grant).await?;
+
+        if let Some(rotated) = token.refresh_token() {
+            *self.refresh_token.lock().unwrap() = rotated.to_owned();
+        }
+
+        Ok(token)
+    }
+}
+
+impl_token_endpoint_builders!(RefreshToken);
+
+impl TokenProvider for RefreshToken {
+    fn token(&self) -> TokenFuture<'_> {
+        Box::pin(async move {
+            match self.endpoint.cached() {
+                Some(token) => Ok(token.access_token),
+                None => Ok(self.fetch_token().await?.access_token),
+            }
+        })
+    }
+
+    fn invalidate(&self) {
+        self.endpoint.token.lock().unwrap().take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse_token_response() {
+        let now = Instant::now();
+        let token = Token::from_json(
+            &json!({
+                "access_token": "abc",
+                "token_type": "bearer",
+                "expires_in": 3600,
+                "refresh_token": "def",
+            }),
+            now,
+        )
+        .unwrap();
+
+        assert_eq!(token.access_token(), "abc");
+        assert_eq!(token.token_type(), "bearer");
+        assert_eq!(token.refresh_token(), Some("def"));
+        assert!(!token.expires_within_at(Duration::from_secs(3599), now));
+        assert!(token.expires_within_at(Duration::from_secs(3600), now));
+    }
+
+    #[test]
+    fn token_without_expiry_never_expires() {
+        let token = Token::from_json(&json!({"access_token": "abc"}), Instant::now()).unwrap();
+
+        assert_eq!(token.token_type(), "Bearer");
+        assert!(!token.expires_within(Duration::from_secs(u32::MAX as u64)));
+    }
+
+    #[test]
+    fn token_response_without_access_token_is_invalid() {
+        assert!(Token::from_json(&json!({"token_type": "bearer"}), Instant::now()).is_none());
+    }
+
+    #[test]
+    fn debug_hides_secrets() {
+        let token = Token::from_json(
+            &json!({
+                "access_token": "secret-access",
+                "refresh_token": "secret-refresh",
+            }),
+            Instant::now(),
+        )
+        .unwrap();
+
+        let debug = format!("{:?}", token);
+
+        assert!(!debug.contains("secret-access"));
+        assert!(!debug.contains("secret-refresh"));
+    }
+}
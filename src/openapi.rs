@@ -0,0 +1,582 @@
+//! Contract testing of responses against an [OpenAPI](https://www.openapis.org)
+//! document.
+//!
+//! Client authors can use an [`OpenApi`] document describing a server to
+//! check that the responses it sends still match what the client was written
+//! against, catching drift between the server and its documentation early.
+//! Responses are checked for a documented status code and content type, and
+//! JSON bodies are validated against the schema of the response.
+//!
+//! Only the parts of the OpenAPI 3 format needed for checking responses are
+//! understood. Schemas are validated by their `type`, `nullable`, `enum`,
+//! `properties`, `required`, `additionalProperties`, `items`, `allOf`,
+//! `anyOf` and `oneOf` keywords, and local `$ref` references are followed.
+//! Other keywords, such as `format` or `minimum`, are ignored.
+//!
+//! # Availability
+//!
+//! This module is only available when the [`openapi`](../index.html#openapi)
+//! feature is enabled.
+
+use crate::error::{Error, ErrorKind};
+use http::{header::CONTENT_TYPE, Method, Response, Uri};
+use serde_json::Value;
+use std::{error::Error as StdError, fmt};
+
+/// How many `$ref` references are followed in a row before giving up, to
+/// guard against reference cycles.
+const MAX_REF_DEPTH: usize = 32;
+
+/// An OpenAPI document to check responses against.
+///
+/// # Examples
+///
+/// ```
+/// use isahc::{
+///     http::{Method, Response, Uri},
+///     openapi::OpenApi,
+/// };
+///
+/// let api = OpenApi::from_slice(br##"{
+///     "openapi": "3.0.3",
+///     "paths": {
+///         "/users/{id}": {
+///             "get": {
+///                 "responses": {
+///                     "200": {
+///                         "content": {
+///                             "application/json": {
+///                                 "schema": {
+///                                     "type": "object",
+///                                     "required": ["name"],
+///                                     "properties": {"name": {"type": "string"}}
+///                                 }
+///                             }
+///                         }
+///                     }
+///                 }
+///             }
+///         }
+///     }
+/// }"##)?;
+///
+/// let response = Response::builder()
+///     .header("content-type", "application/json")
+///     .body(r#"{"name": 42}"#)?;
+///
+/// let uri = Uri::from_static("https://example.org/users/1");
+/// let violations = api.validate(&Method::GET, &uri, &response);
+///
+/// assert_eq!(violations[0].to_string(), "body at `$.name`: expected string, got number");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct OpenApi {
+    document: Value,
+}
+
+impl OpenApi {
+    /// Parse an OpenAPI document in JSON format.
+    ///
+    /// Returns an error of kind [`ErrorKind::ProtocolViolation`] if the
+    /// document is not valid JSON or does not have any paths.
+    pub fn from_slice(json: &[u8]) -> Result<Self, Error> {
+        let document = serde_json::from_slice(json)
+            .map_err(|e| Error::new(ErrorKind::ProtocolViolation, e))?;
+
+        Self::from_value(document)
+    }
+
+    /// Use an already parsed OpenAPI document.
+    ///
+    /// Returns an error of kind [`ErrorKind::ProtocolViolation`] if the
+    /// document does not have any paths.
+    pub fn from_value(document: Value) -> Result<Self, Error> {
+        if !document["paths"].is_object() {
+            tracing::debug!("OpenAPI document is missing paths");
+            return Err(Error::from(ErrorKind::ProtocolViolation));
+        }
+
+        Ok(Self {
+            document,
+        })
+    }
+
+    /// Check a response to a request with the given method and URI against
+    /// the document, returning every way it violates the document.
+    ///
+    /// The path of the URI is matched against the paths of the document, after
+    /// removing the path of any server listed in the document. If no
+    /// operation is documented for the request, checking stops there.
+    pub fn validate<T>(&self, method: &Method, uri: &Uri, response: &Response<T>) -> Vec<Violation>
+    where
+        T: AsRef<[u8]>,
+    {
+        let mut violations = Vec::new();
+
+        let operation = match self.operation(method, uri.path()) {
+            Some(operation) => operation,
+            None => {
+                violations.push(Violation::UndocumentedOperation {
+                    method: method.to_string(),
+                    path: uri.path().to_owned(),
+                });
+                return violations;
+            }
+        };
+
+        let status = response.status();
+        let responses = &operation["responses"];
+        let documented = responses
+            .get(status.as_str())
+            .or_else(|| responses.get(format!("{}XX", status.as_u16() / 100)))
+            .or_else(|| responses.get(format!("{}xx", status.as_u16() / 100)))
+            .or_else(|| responses.get("default"))
+            .map(|documented| self.resolve(documented));
+
+        let documented = match documented {
+            Some(documented) => documented,
+            None => {
+                violations.push(Violation::UndocumentedStatus {
+                    status: status.as_u16(),
+                });
+                return violations;
+            }
+        };
+
+        let content = match documented["content"].as_object() {
+            Some(content) if !content.is_empty() => content,
+
+            // Nothing is documented about the body, so anything goes.
+            _ => return violations,
+        };
+
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.split(';').next().unwrap_or_default().trim().to_ascii_lowercase());
+
+        let media_type = content_type.as_deref().and_then(|content_type| {
+            let (kind, _) = content_type.split_once('/').unwrap_or((content_type, ""));
+
+            content
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(content_type))
+                .or_else(|| {
+                    content.iter().find(|(key, _)| {
+                        key.strip_suffix("/*")
+                            .map_or(false, |prefix| prefix.eq_ignore_ascii_case(kind))
+                    })
+                })
+                .or_else(|| content.iter().find(|(key, _)| *key == "*/*"))
+                .map(|(_, media_type)| media_type)
+        });
+
+        let media_type = match media_type {
+            Some(media_type) => media_type,
+            None => {
+                // An empty response without a content type does not claim to
+                // have any particular content.
+                if content_type.is_some() || !response.body().as_ref().is_empty() {
+                    violations.push(Violation::UndocumentedContentType {
+                        content_type,
+                    });
+                }
+
+                return violations;
+            }
+        };
+
+        let is_json = content_type.as_deref().map_or(false, |content_type| {
+            content_type == "application/json" || content_type.ends_with("+json")
+        });
+
+        if let (true, Some(schema)) = (is_json, media_type.get("schema")) {
+            match serde_json::from_slice::<Value>(response.body().as_ref()) {
+                Ok(body) => self.validate_schema(schema, &body, "$", 0, &mut violations),
+                Err(e) => violations.push(Violation::InvalidJson {
+                    message: e.to_string(),
+                }),
+            }
+        }
+
+        violations
+    }
+
+    /// Check a response like [`OpenApi::validate`], returning an error listing
+    /// every violation if there are any.
+    ///
+    /// Each violation is also logged as a warning, so that drift can be
+    /// noticed even where the error is ignored.
+    pub fn check<T>(
+        &self,
+        method: &Method,
+        uri: &Uri,
+        response: &Response<T>,
+    ) -> Result<(), ContractError>
+    where
+        T: AsRef<[u8]>,
+    {
+        let violations = self.validate(method, uri, response);
+
+        if violations.is_empty() {
+            return Ok(());
+        }
+
+        for violation in &violations {
+            tracing::warn!("response to {} {} violates contract: {}", method, uri, violation);
+        }
+
+        Err(ContractError {
+            violations,
+        })
+    }
+
+    /// Find the operation documented for a method and path.
+    fn operation(&self, method: &Method, path: &str) -> Option<&Value> {
+        let paths = self.document["paths"].as_object()?;
+        let method = method.as_str().to_ascii_lowercase();
+
+        let mut candidates = vec![path];
+
+        for server in self.document["servers"].as_array().into_iter().flatten() {
+            if let Some(base) = server["url"].as_str().map(server_path) {
+                if let Some(rest) = path.strip_prefix(base.trim_end_matches('/')) {
+                    if rest.starts_with('/') {
+                        candidates.push(rest);
+                    }
+                }
+            }
+        }
+
+        // Prefer literal segments over templated ones, so that `/users/me`
+        // wins over `/users/{id}`.
+        candidates
+            .iter()
+            .flat_map(|path| {
+                paths
+                    .iter()
+                    .filter_map(move |(template, item)| {
+                        template_matches(template, path).map(|templated| (templated, item))
+                    })
+            })
+            .min_by_key(|(templated, _)| *templated)
+            .and_then(|(_, item)| self.resolve(item).get(&method))
+    }
+
+    /// Follow a local `$ref` reference, if the value is one.
+    fn resolve<'a>(&'a self, mut value: &'a Value) -> &'a Value {
+        for _ in 0..MAX_REF_DEPTH {
+            match value["$ref"].as_str().and_then(|r| r.strip_prefix('#')) {
+                Some(pointer) => match self.document.pointer(pointer) {
+                    Some(target) => value = target,
+                    None => return &Value::Null,
+                },
+                None => break,
+            }
+        }
+
+        value
+    }
+
+    fn validate_schema(
+        &self,
+        schema: &Value,
+        value: &Value,
+        path: &str,
+        depth: usize,
+        violations: &mut Vec<Violation>,
+    ) {
+        if depth > MAX_REF_DEPTH {
+            return;
+        }
+
+        let schema = self.resolve(schema);
+        let mut mismatch = |message: String| {
+            violations.push(Violation::Schema {
+                path: path.to_owned(),
+                message,
+            })
+        };
+
+        if value.is_null() && schema["nullable"].as_bool() == Some(true) {
+            return;
+        }
+
+        let types = match &schema["type"] {
+            Value::String(kind) => vec![kind.as_str()],
+            Value::Array(kinds) => kinds.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+
+        if !types.is_empty() && !types.iter().any(|kind| has_type(value, kind)) {
+            mismatch(format!("expected {}, got {}", types.join(" or "), type_name(value)));
+            return;
+        }
+
+        if let Some(values) = schema["enum"].as_array() {
+            if !values.contains(value) {
+                mismatch(format!("{} is not one of the allowed values", value));
+            }
+        }
+
+        for schema in schema["allOf"].as_array().into_iter().flatten() {
+            self.validate_schema(schema, value, path, depth + 1, violations);
+        }
+
+        for (keyword, exactly_one) in &[("anyOf", false), ("oneOf", true)] {
+            if let Some(schemas) = schema[*keyword].as_array() {
+                let matching = schemas
+                    .iter()
+                    .filter(|schema| {
+                        let mut nested = Vec::new();
+                        self.validate_schema(schema, value, path, depth + 1, &mut nested);
+                        nested.is_empty()
+                    })
+                    .count();
+
+                if matching == 0 || (*exactly_one && matching > 1) {
+                    violations.push(Violation::Schema {
+                        path: path.to_owned(),
+                        message: format!("{} schemas of `{}` match", matching, keyword),
+                    });
+                }
+            }
+        }
+
+        if let Value::Object(object) = value {
+            for name in schema["required"].as_array().into_iter().flatten() {
+                if let Some(name) = name.as_str().filter(|name| !object.contains_key(*name)) {
+                    violations.push(Violation::Schema {
+                        path: path.to_owned(),
+                        message: format!("missing required property `{}`", name),
+                    });
+                }
+            }
+
+            let properties = schema["properties"].as_object();
+
+            for (name, value) in object {
+                let path = property_path(path, name);
+
+                match properties.and_then(|properties| properties.get(name)) {
+                    Some(schema) => {
+                        self.validate_schema(schema, value, &path, depth + 1, violations)
+                    }
+                    None => match &schema["additionalProperties"] {
+                        Value::Bool(false) => violations.push(Violation::Schema {
+                            path,
+                            message: String::from("property is not allowed"),
+                        }),
+                        schema @ Value::Object(_) => {
+                            self.validate_schema(schema, value, &path, depth + 1, violations)
+                        }
+                        _ => {}
+                    },
+                }
+            }
+        }
+
+        if let (Value::Array(items), Some(schema)) = (value, schema.get("items")) {
+            for (i, item) in items.iter().enumerate() {
+                let path = format!("{}[{}]", path, i);
+
+                self.validate_schema(schema, item, &path, depth + 1, violations);
+            }
+        }
+    }
+}
+
+/// A way a response violates an [`OpenApi`] document.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Violation {
+    /// No operation is documented for the method and path of the request.
+    UndocumentedOperation {
+        /// The request method.
+        method: String,
+        /// The path of the request URI.
+        path: String,
+    },
+
+    /// The status code of the response is not documented for the operation.
+    UndocumentedStatus {
+        /// The status code of the response.
+        status: u16,
+    },
+
+    /// The content type of the response is not documented for its status.
+    UndocumentedContentType {
+        /// The content type of the response without any parameters, if it
+        /// has one.
+        content_type: Option<String>,
+    },
+
+    /// The response body is not valid JSON.
+    InvalidJson {
+        /// A description of the syntax error.
+        message: String,
+    },
+
+    /// A value in the response body does not match the documented schema.
+    Schema {
+        /// The path of the value, such as `$.users[0].name`.
+        path: String,
+        /// A description of the mismatch.
+        message: String,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UndocumentedOperation { method, path } => {
+                write!(f, "operation {} {} is not documented", method, path)
+            }
+            Self::UndocumentedStatus { status } => {
+                write!(f, "status {} is not documented", status)
+            }
+            Self::UndocumentedContentType { content_type } => write!(
+                f,
+                "content type {} is not documented",
+                content_type.as_deref().unwrap_or("(none)")
+            ),
+            Self::InvalidJson { message } => write!(f, "body is not valid JSON: {}", message),
+            Self::Schema { path, message } => write!(f, "body at `{}`: {}", path, message),
+        }
+    }
+}
+
+/// An error returned by [`OpenApi::check`] when a response violates the
+/// document.
+#[derive(Clone, Debug)]
+pub struct ContractError {
+    violations: Vec<Violation>,
+}
+
+impl ContractError {
+    /// Get every way the response violates the document.
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+}
+
+impl fmt::Display for ContractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("response violates API contract")?;
+
+        for violation in &self.violations {
+            write!(f, "\n- {}", violation)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl StdError for ContractError {}
+
+impl From<ContractError> for Error {
+    fn from(error: ContractError) -> Self {
+        Error::new(ErrorKind::ProtocolViolation, error)
+    }
+}
+
+/// Get the path of a server URL, which may be absolute or relative.
+fn server_path(url: &str) -> &str {
+    match url.find("://") {
+        Some(index) => {
+            let rest = &url[index + 3..];
+            rest.find('/').map_or("", |index| &rest[index..])
+        }
+        None => url,
+    }
+}
+
+/// Check whether a path matches a path template such as `/users/{id}`,
+/// returning the number of templated segments if it does.
+fn template_matches(template: &str, path: &str) -> Option<usize> {
+    let mut templated = 0;
+    let mut template_segments = template.split('/');
+    let mut path_segments = path.split('/');
+
+    loop {
+        match (template_segments.next(), path_segments.next()) {
+            (None, None) => return Some(templated),
+            (Some(template), Some(segment)) => match template.find('{') {
+                None if template == segment => {}
+                None => return None,
+                Some(start) => {
+                    let end = template.rfind('}')?;
+                    let (prefix, suffix) = (&template[..start], &template[end + 1..]);
+
+                    if segment.len() <= prefix.len() + suffix.len()
+                        || !segment.starts_with(prefix)
+                        || !segment.ends_with(suffix)
+                    {
+                        return None;
+                    }
+
+                    templated += 1;
+                }
+            },
+            _ => return None,
+        }
+    }
+}
+
+fn has_type(value: &Value, kind: &str) -> bool {
+    match kind {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Append a property name to a JSON path.
+fn property_path(path: &str, name: &str) -> String {
+    if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        format!("{}.{}", path, name)
+    } else {
+        format!("{}[{:?}]", path, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("/users", "/users", Some(0))]
+    #[test_case("/users/{id}", "/users/42", Some(1))]
+    #[test_case("/users/{id}", "/users/", None)]
+    #[test_case("/users/{id}.json", "/users/42.json", Some(1))]
+    #[test_case("/users/{id}.json", "/users/42.xml", None)]
+    #[test_case("/users/{id}", "/users/42/posts", None)]
+    fn template(template: &str, path: &str, expected: Option<usize>) {
+        assert_eq!(template_matches(template, path), expected);
+    }
+
+    #[test_case("https://api.example.org/v1", "/v1")]
+    #[test_case("https://api.example.org", "")]
+    #[test_case("/v2", "/v2")]
+    fn server(url: &str, expected: &str) {
+        assert_eq!(server_path(url), expected);
+    }
+}
@@ -52,16 +52,20 @@ pub(crate) fn parse_header(line: &[u8]) -> Option<(HeaderName, HeaderValue)> {
     Some((name, value))
 }
 
-pub(crate) fn header_to_curl_string(
+/// Format a header in the syntax expected by curl, writing the result into the
+/// given buffer. Any existing contents of the buffer are overwritten, which
+/// allows one buffer to be reused for formatting many headers.
+pub(crate) fn write_curl_header(
+    string: &mut String,
     name: &HeaderName,
     value: &HeaderValue,
     title_case: bool,
-) -> String {
+) {
     let header_value = value
         .to_str()
         .expect("request header value is not valid UTF-8!");
 
-    let mut string = String::new();
+    string.clear();
 
     if title_case {
         let name_bytes: &[u8] = name.as_ref();
@@ -88,8 +92,6 @@ pub(crate) fn header_to_curl_string(
         string.push_str(": ");
         string.push_str(header_value);
     }
-
-    string
 }
 
 #[cfg(test)]
@@ -161,6 +163,23 @@ mod tests {
         );
     }
 
+    fn header_to_curl_string(name: &HeaderName, value: &HeaderValue, title_case: bool) -> String {
+        let mut string = String::new();
+        write_curl_header(&mut string, name, value, title_case);
+        string
+    }
+
+    #[test]
+    fn header_buffer_is_overwritten() {
+        let mut string = String::from("some previous header: value");
+        let name = "foo".parse().unwrap();
+        let value = "bar".parse().unwrap();
+
+        write_curl_header(&mut string, &name, &value, false);
+
+        assert_eq!(string, "foo: bar");
+    }
+
     #[test]
     fn parse_invalid_headers() {
         assert_eq!(parse_header(b""), None);
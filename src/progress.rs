@@ -0,0 +1,427 @@
+//! Human-readable rendering of transfer progress and statistics.
+//!
+//! These utilities are intended for command-line programs that want to display
+//! the progress of a download or upload in the same style as the `curl`
+//! command-line tool. They only format data found in [`Metrics`], so metrics
+//! must be enabled on the request using
+//! [`Configurable::metrics`](crate::config::Configurable::metrics).
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use isahc::{prelude::*, progress::{ProgressMeter, TransferSummary}, Request};
+//! use std::io::Read;
+//!
+//! let mut response = Request::get("https://example.org")
+//!     .metrics(true)
+//!     .body(())?
+//!     .send()?;
+//!
+//! let metrics = response.metrics().unwrap().clone();
+//! let mut meter = ProgressMeter::new();
+//! let mut buf = [0; 8192];
+//!
+//! eprintln!("{}", ProgressMeter::HEADER);
+//!
+//! while response.body_mut().read(&mut buf)? > 0 {
+//!     eprint!("\r{}", meter.update(&metrics));
+//! }
+//!
+//! eprintln!("\r{}", meter.update(&metrics));
+//! eprintln!("{}", TransferSummary::new(&metrics));
+//! # Ok::<(), isahc::Error>(())
+//! ```
+//!
+//! # Availability
+//!
+//! This module is only available when the [`progress`](index.html#progress)
+//! feature is enabled.
+
+use crate::Metrics;
+use std::{fmt, time::Duration};
+
+/// Format a number of bytes in at most five characters, using the same binary
+/// unit suffixes as curl (`k`, `M`, `G`, `T`, and `P`).
+///
+/// Values are right-aligned when a width is given, like numbers.
+///
+/// # Examples
+///
+/// ```
+/// use isahc::progress::format_size;
+///
+/// assert_eq!(format_size(512).to_string(), "512");
+/// assert_eq!(format_size(3 * 1024 * 1024).to_string(), "3072k");
+/// assert_eq!(format_size(150 * 1024 * 1024).to_string(), "150M");
+/// ```
+pub fn format_size(bytes: u64) -> impl fmt::Display {
+    Size(bytes)
+}
+
+/// Format a duration in `HH:MM:SS` form like curl does, or as `--:--:--` if
+/// the duration is not known.
+///
+/// Durations of more than 99 hours are shown in days, and durations of more
+/// than 999 days in years.
+///
+/// # Examples
+///
+/// ```
+/// use isahc::progress::format_duration;
+/// use std::time::Duration;
+///
+/// assert_eq!(format_duration(Some(Duration::from_secs(3725))).to_string(), " 1:02:05");
+/// assert_eq!(format_duration(None).to_string(), "--:--:--");
+/// ```
+pub fn format_duration(duration: Option<Duration>) -> impl fmt::Display {
+    Time(duration)
+}
+
+struct Size(u64);
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const K: u64 = 1024;
+        const M: u64 = K * 1024;
+        const G: u64 = M * 1024;
+        const T: u64 = G * 1024;
+        const P: u64 = T * 1024;
+
+        let bytes = self.0;
+
+        let s = if bytes < 100_000 {
+            bytes.to_string()
+        } else if bytes < 10_000 * K {
+            format!("{}k", bytes / K)
+        } else if bytes < 100 * M {
+            format!("{}.{}M", bytes / M, tenths(bytes % M, M))
+        } else if bytes < 10_000 * M {
+            format!("{}M", bytes / M)
+        } else if bytes < 100 * G {
+            format!("{}.{}G", bytes / G, tenths(bytes % G, G))
+        } else if bytes < 10_000 * G {
+            format!("{}G", bytes / G)
+        } else if bytes < 10_000 * T {
+            format!("{}T", bytes / T)
+        } else {
+            format!("{}P", bytes / P)
+        };
+
+        f.pad_integral(true, "", &s)
+    }
+}
+
+/// Get the first decimal digit of `remainder / unit`, rounded down.
+fn tenths(remainder: u64, unit: u64) -> u64 {
+    (remainder / (unit / 10)).min(9)
+}
+
+struct Time(Option<Duration>);
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = match self.0 {
+            Some(duration) => duration.as_secs(),
+            None => return f.write_str("--:--:--"),
+        };
+
+        let hours = secs / 3600;
+
+        if hours <= 99 {
+            write!(f, "{:2}:{:02}:{:02}", hours, (secs / 60) % 60, secs % 60)
+        } else if hours / 24 <= 999 {
+            write!(f, "{:3}d {:02}h", hours / 24, hours % 24)
+        } else {
+            write!(f, "{:7}y", hours / 24 / 365)
+        }
+    }
+}
+
+/// Calculate a percentage, if the total is known.
+fn percent(now: u64, total: u64) -> u64 {
+    if total == 0 {
+        0
+    } else {
+        (now.min(total) as f64 / total as f64 * 100.0) as u64
+    }
+}
+
+/// Renders the progress of a transfer as a table row in the same format used
+/// by the curl command-line tool.
+///
+/// The meter keeps track of the previous update in order to report the
+/// current speed of the transfer, so the same meter should be updated
+/// repeatedly over the course of a single transfer.
+#[derive(Debug, Default)]
+pub struct ProgressMeter {
+    /// Elapsed time and total bytes transferred at the previous update.
+    last_sample: Option<(Duration, u64)>,
+
+    /// Most recently calculated current speed in bytes per second.
+    current_speed: u64,
+}
+
+impl ProgressMeter {
+    /// Column headings to print above the lines rendered by a progress meter.
+    pub const HEADER: &'static str = "  % Total    % Received % Xferd  Average Speed   Time    Time     Time  Current\n                                 Dload  Upload   Total   Spent    Left  Speed";
+
+    /// Create a new progress meter for a transfer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take a new sample of the given metrics and render the current progress
+    /// of the transfer as a single line matching [`HEADER`](Self::HEADER).
+    ///
+    /// The returned line does not include a line ending, which allows
+    /// interactive programs to overwrite the same line repeatedly using a
+    /// carriage return.
+    pub fn update(&mut self, metrics: &Metrics) -> ProgressLine {
+        let (uploaded, upload_total) = metrics.upload_progress();
+        let (downloaded, download_total) = metrics.download_progress();
+        let spent = metrics.total_time();
+        let transferred = uploaded + downloaded;
+
+        if let Some((last_spent, last_transferred)) = self.last_sample {
+            let elapsed = spent.saturating_sub(last_spent).as_secs_f64();
+
+            if elapsed > 0.0 {
+                self.current_speed =
+                    (transferred.saturating_sub(last_transferred) as f64 / elapsed) as u64;
+            }
+        } else {
+            self.current_speed =
+                (metrics.download_speed() + metrics.upload_speed()) as u64;
+        }
+
+        self.last_sample = Some((spent, transferred));
+
+        let total = upload_total + download_total;
+        let average_speed = metrics.download_speed() + metrics.upload_speed();
+
+        // The total time can only be estimated if the size of the transfer is
+        // known in advance.
+        let estimated_total = if total > 0 && average_speed > 0.0 {
+            Some(Duration::from_secs_f64(total as f64 / average_speed).max(spent))
+        } else {
+            None
+        };
+
+        ProgressLine {
+            total,
+            transferred,
+            downloaded,
+            download_total,
+            uploaded,
+            upload_total,
+            download_speed: metrics.download_speed() as u64,
+            upload_speed: metrics.upload_speed() as u64,
+            estimated_total,
+            spent,
+            current_speed: self.current_speed,
+        }
+    }
+}
+
+/// A single line of progress output rendered by a [`ProgressMeter`].
+#[derive(Clone, Debug)]
+pub struct ProgressLine {
+    total: u64,
+    transferred: u64,
+    downloaded: u64,
+    download_total: u64,
+    uploaded: u64,
+    upload_total: u64,
+    download_speed: u64,
+    upload_speed: u64,
+    estimated_total: Option<Duration>,
+    spent: Duration,
+    current_speed: u64,
+}
+
+impl fmt::Display for ProgressLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:3} {:>5}  {:3} {:>5}  {:3} {:>5}  {:>5}  {:>5} {} {} {} {:>5}",
+            percent(self.transferred, self.total),
+            Size(self.total),
+            percent(self.downloaded, self.download_total),
+            Size(self.downloaded),
+            percent(self.uploaded, self.upload_total),
+            Size(self.uploaded),
+            Size(self.download_speed),
+            Size(self.upload_speed),
+            Time(self.estimated_total),
+            Time(Some(self.spent)),
+            Time(
+                self.estimated_total
+                    .map(|total| total.saturating_sub(self.spent))
+            ),
+            Size(self.current_speed),
+        )
+    }
+}
+
+/// A summary of a completed transfer, including a breakdown of where time was
+/// spent.
+///
+/// The summary is rendered as a multi-line table when displayed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{prelude::*, progress::TransferSummary, Request};
+///
+/// let mut response = Request::get("https://example.org")
+///     .metrics(true)
+///     .body(())?
+///     .send()?;
+///
+/// response.consume()?;
+///
+/// eprintln!("{}", TransferSummary::new(response.metrics().unwrap()));
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct TransferSummary {
+    name_lookup_time: Duration,
+    connect_time: Duration,
+    secure_connect_time: Duration,
+    transfer_start_time: Duration,
+    transfer_time: Duration,
+    redirect_time: Duration,
+    total_time: Duration,
+    downloaded: u64,
+    download_speed: u64,
+    uploaded: u64,
+    upload_speed: u64,
+}
+
+impl TransferSummary {
+    /// Create a summary from the current values of the given metrics.
+    pub fn new(metrics: &Metrics) -> Self {
+        Self {
+            name_lookup_time: metrics.name_lookup_time(),
+            connect_time: metrics.connect_time(),
+            secure_connect_time: metrics.secure_connect_time(),
+            transfer_start_time: metrics.transfer_start_time(),
+            transfer_time: metrics.transfer_time(),
+            redirect_time: metrics.redirect_time(),
+            total_time: metrics.total_time(),
+            downloaded: metrics.download_progress().0,
+            download_speed: metrics.download_speed() as u64,
+            uploaded: metrics.upload_progress().0,
+            upload_speed: metrics.upload_speed() as u64,
+        }
+    }
+}
+
+impl fmt::Display for TransferSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = [
+            ("DNS lookup", self.name_lookup_time),
+            ("TCP connect", self.connect_time),
+            ("TLS handshake", self.secure_connect_time),
+            ("Start transfer", self.transfer_start_time),
+            ("Transfer", self.transfer_time),
+            ("Redirects", self.redirect_time),
+            ("Total", self.total_time),
+        ];
+
+        for (label, duration) in rows {
+            writeln!(f, "{:>14}: {:9.6}s", label, duration.as_secs_f64())?;
+        }
+
+        writeln!(
+            f,
+            "{:>14}: {:>5}B at {:>5}B/s",
+            "Downloaded",
+            Size(self.downloaded),
+            Size(self.download_speed)
+        )?;
+
+        write!(
+            f,
+            "{:>14}: {:>5}B at {:>5}B/s",
+            "Uploaded",
+            Size(self.uploaded),
+            Size(self.upload_speed)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_are_formatted_like_curl() {
+        const M: u64 = 1024 * 1024;
+
+        for &(bytes, expected) in &[
+            (0, "0"),
+            (99_999, "99999"),
+            (100_000, "97k"),
+            (10_000 * 1024 - 1, "9999k"),
+            (10_000 * 1024, "9.7M"),
+            (100 * M - 1, "99.9M"),
+            (100 * M, "100M"),
+            (10_000 * M, "9.7G"),
+            (u64::MAX, "16383P"),
+        ] {
+            assert_eq!(format_size(bytes).to_string(), expected);
+        }
+
+        assert_eq!(format!("{:>5}", format_size(42)), "   42");
+    }
+
+    #[test]
+    fn durations_are_formatted_like_curl() {
+        for &(secs, expected) in &[
+            (0, " 0:00:00"),
+            (59, " 0:00:59"),
+            (3600 * 99 + 59 * 60 + 59, "99:59:59"),
+            (3600 * 100, "  4d 04h"),
+            (3600 * 24 * 2000, "      5y"),
+        ] {
+            assert_eq!(
+                format_duration(Some(Duration::from_secs(secs))).to_string(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn progress_line_is_formatted_like_curl() {
+        let metrics = Metrics::new();
+        metrics.inner.download_total.store(200_000.0);
+        metrics.inner.download_progress.store(50_000.0);
+        metrics.inner.download_speed.store(25_000.0);
+        metrics.inner.total_time.store(2.0);
+
+        let line = ProgressMeter::new().update(&metrics).to_string();
+
+        assert_eq!(
+            line,
+            " 25  195k   25 50000    0     0  25000      0  0:00:08  0:00:02  0:00:06 25000"
+        );
+    }
+
+    #[test]
+    fn current_speed_is_measured_between_updates() {
+        let metrics = Metrics::new();
+        let mut meter = ProgressMeter::new();
+        meter.update(&metrics);
+
+        metrics.inner.download_progress.store(3000.0);
+        metrics.inner.total_time.store(1.0);
+        meter.update(&metrics);
+        assert_eq!(meter.current_speed, 3000);
+
+        metrics.inner.download_progress.store(4000.0);
+        metrics.inner.total_time.store(3.0);
+        meter.update(&metrics);
+        assert_eq!(meter.current_speed, 500);
+    }
+}
@@ -0,0 +1,223 @@
+//! Structured access to the query string of a request URI.
+
+use http::{
+    uri::{PathAndQuery, Uri},
+    Request,
+};
+use std::fmt;
+use url::form_urlencoded;
+
+/// Mutable view of the query parameters of a request, returned by
+/// [`RequestExt::query_mut`](crate::RequestExt::query_mut).
+///
+/// Parameters are decoded when the view is created. If any changes are made,
+/// the query string of the request URI is encoded again from the parameters
+/// when the view is dropped. Otherwise the original query string is left
+/// exactly as it was.
+///
+/// # Examples
+///
+/// ```
+/// use isahc::{prelude::*, Request};
+///
+/// let mut request = Request::get("https://example.org/search?q=cats").body(())?;
+///
+/// request.query_mut().append("api_key", "secret");
+///
+/// assert_eq!(
+///     request.uri(),
+///     "https://example.org/search?q=cats&api_key=secret"
+/// );
+/// # Ok::<(), isahc::http::Error>(())
+/// ```
+pub struct QueryMut<'a> {
+    uri: &'a mut Uri,
+    params: Vec<(String, String)>,
+    modified: bool,
+}
+
+impl<'a> QueryMut<'a> {
+    pub(crate) fn new<T>(request: &'a mut Request<T>) -> Self {
+        let params = request
+            .uri()
+            .query()
+            .map(|query| {
+                form_urlencoded::parse(query.as_bytes())
+                    .into_owned()
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            uri: request.uri_mut(),
+            params,
+            modified: false,
+        }
+    }
+
+    /// Get the first value of the parameter with the given name, if any.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get all values of the parameter with the given name, in order.
+    pub fn get_all<'b>(&'b self, name: &'b str) -> impl Iterator<Item = &'b str> {
+        self.params
+            .iter()
+            .filter(move |(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Iterate over the names and values of all parameters, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.params
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Report whether a parameter with the given name is present.
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Add a parameter after any existing ones, keeping other parameters with
+    /// the same name.
+    pub fn append(&mut self, name: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.params.push((name.into(), value.into()));
+        self.modified = true;
+        self
+    }
+
+    /// Set a parameter, replacing all existing parameters with the same name.
+    ///
+    /// The new value takes the place of the first existing parameter, or is
+    /// added to the end if there was none.
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        let name = name.into();
+        let value = value.into();
+
+        // Keep only the first parameter with this name, then replace its value.
+        let mut found = false;
+        self.params.retain(|(n, _)| *n != name || !std::mem::replace(&mut found, true));
+
+        match self.params.iter_mut().find(|(n, _)| *n == name) {
+            Some(param) => param.1 = value,
+            None => self.params.push((name, value)),
+        }
+
+        self.modified = true;
+        self
+    }
+
+    /// Remove all parameters with the given name.
+    pub fn remove(&mut self, name: &str) -> &mut Self {
+        let len = self.params.len();
+
+        self.params.retain(|(n, _)| n != name);
+        self.modified |= self.params.len() != len;
+        self
+    }
+
+    /// Remove all parameters.
+    pub fn clear(&mut self) -> &mut Self {
+        self.modified |= !self.params.is_empty();
+        self.params.clear();
+        self
+    }
+
+    fn write_back(&mut self) {
+        let path = self.uri.path();
+        let mut path_and_query = String::from(if path.is_empty() { "/" } else { path });
+
+        if !self.params.is_empty() {
+            path_and_query.push('?');
+            path_and_query.push_str(
+                &form_urlencoded::Serializer::new(String::new())
+                    .extend_pairs(&self.params)
+                    .finish(),
+            );
+        }
+
+        let mut parts = self.uri.clone().into_parts();
+
+        // Both of these are valid by construction, since the path came from a
+        // valid URI and the query is fully percent-encoded.
+        parts.path_and_query = Some(PathAndQuery::from_maybe_shared(path_and_query).unwrap());
+        *self.uri = Uri::from_parts(parts).unwrap();
+    }
+}
+
+impl Drop for QueryMut<'_> {
+    fn drop(&mut self) {
+        if self.modified {
+            self.write_back();
+        }
+    }
+}
+
+impl fmt::Debug for QueryMut<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RequestExt;
+
+    fn request(uri: &str) -> Request<()> {
+        Request::get(uri).body(()).unwrap()
+    }
+
+    #[test]
+    fn parameters_are_decoded() {
+        let mut request = request("http://example.org/?a=1&b=hello%20world&a=2&c");
+        let query = request.query_mut();
+
+        assert_eq!(query.get("a"), Some("1"));
+        assert_eq!(query.get_all("a").collect::<Vec<_>>(), ["1", "2"]);
+        assert_eq!(query.get("b"), Some("hello world"));
+        assert_eq!(query.get("c"), Some(""));
+        assert!(!query.contains("d"));
+    }
+
+    #[test]
+    fn unmodified_query_is_left_alone() {
+        let mut request = request("http://example.org/path?b=%7e&a");
+
+        request.query_mut();
+
+        assert_eq!(request.uri(), "http://example.org/path?b=%7e&a");
+    }
+
+    #[test]
+    fn set_replaces_all_values() {
+        let mut request = request("http://example.org/?a=1&b=2&a=3");
+
+        request.query_mut().set("a", "x y");
+
+        assert_eq!(request.uri(), "http://example.org/?a=x+y&b=2");
+    }
+
+    #[test]
+    fn removing_last_parameter_removes_query() {
+        let mut request = request("http://example.org/path?a=1");
+
+        request.query_mut().remove("a");
+
+        assert_eq!(request.uri(), "http://example.org/path");
+    }
+
+    #[test]
+    fn parameters_are_added_to_uri_without_query() {
+        let mut request = request("http://example.org");
+
+        request.query_mut().append("key", "a&b");
+
+        assert_eq!(request.uri(), "http://example.org/?key=a%26b");
+    }
+}
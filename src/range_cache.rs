@@ -0,0 +1,484 @@
+//! Serving requests for byte ranges from a [`RangeCache`], and stitching
+//! cached ranges together with ranges received from the server.
+
+use crate::{
+    body::AsyncBody,
+    config::{
+        range_cache::{Lookup, Part, Validator},
+        request::RequestConfig,
+        RangeCache,
+    },
+    error::Error,
+    interceptor::{Context, Interceptor, InterceptorFuture},
+    request::RequestExt,
+};
+use futures_lite::{
+    io::{AsyncRead, AsyncReadExt, Cursor},
+    ready,
+};
+use http::{header, HeaderMap, HeaderValue, Method, Request, Response, StatusCode, Uri};
+use std::{
+    io,
+    pin::Pin,
+    task::{Context as TaskContext, Poll},
+};
+
+/// A single range of bytes in a `Range` request header.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum ByteRange {
+    /// The bytes between two offsets, inclusive.
+    Bounded(u64, u64),
+
+    /// The bytes from an offset to the end.
+    From(u64),
+
+    /// The given number of bytes at the end.
+    Last(u64),
+}
+
+impl ByteRange {
+    /// Parse the value of a `Range` header, returning `None` if it is invalid
+    /// or asks for more than one range.
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        let spec = value.trim().strip_prefix("bytes=")?.trim();
+
+        if spec.contains(',') {
+            return None;
+        }
+
+        let (start, end) = spec.split_once('-')?;
+        let (start, end) = (start.trim(), end.trim());
+
+        match (start.is_empty(), end.is_empty()) {
+            (false, false) => {
+                let (start, end) = (start.parse().ok()?, end.parse().ok()?);
+
+                if start <= end {
+                    Some(Self::Bounded(start, end))
+                } else {
+                    None
+                }
+            }
+            (false, true) => Some(Self::From(start.parse().ok()?)),
+            (true, false) => Some(Self::Last(end.parse().ok()?)).filter(|r| r != &Self::Last(0)),
+            (true, true) => None,
+        }
+    }
+
+    /// Get the first and last offset of the range in a resource of the given
+    /// length, if known. Returns `None` if the range cannot be resolved
+    /// without knowing the length, or is outside of the resource.
+    pub(crate) fn resolve(self, total_len: Option<u64>) -> Option<(u64, u64)> {
+        let (start, end) = match (self, total_len) {
+            (Self::Bounded(start, end), Some(len)) => (start, end.min(len.checked_sub(1)?)),
+            (Self::Bounded(start, end), None) => (start, end),
+            (Self::From(start), Some(len)) => (start, len.checked_sub(1)?),
+            (Self::Last(n), Some(len)) => (len.saturating_sub(n), len.checked_sub(1)?),
+            (_, None) => return None,
+        };
+
+        if start <= end {
+            Some((start, end))
+        } else {
+            None
+        }
+    }
+}
+
+/// Parse the value of a `Content-Range` response header into the first and
+/// last offset of the range and the length of the resource, if known.
+fn parse_content_range(value: &str) -> Option<(u64, u64, Option<u64>)> {
+    let (range, total_len) = value.trim().strip_prefix("bytes ")?.split_once('/')?;
+    let (start, end) = range.trim().split_once('-')?;
+    let (start, end) = (start.parse().ok()?, end.parse().ok()?);
+    let total_len = match total_len.trim() {
+        "*" => None,
+        len => Some(len.parse().ok()?),
+    };
+
+    if start <= end && total_len.map_or(true, |len| end < len) {
+        Some((start, end, total_len))
+    } else {
+        None
+    }
+}
+
+fn content_range(start: u64, end: u64, total_len: Option<u64>) -> HeaderValue {
+    let value = match total_len {
+        Some(len) => format!("bytes {}-{}/{}", start, end, len),
+        None => format!("bytes {}-{}/*", start, end),
+    };
+
+    HeaderValue::from_str(&value).unwrap()
+}
+
+/// Interceptor that answers requests for byte ranges from the range cache of
+/// the client, if it has one.
+pub(crate) struct RangeCacheInterceptor;
+
+impl Interceptor for RangeCacheInterceptor {
+    type Err = Error;
+
+    fn intercept<'a>(
+        &'a self,
+        mut request: Request<AsyncBody>,
+        ctx: Context<'a>,
+    ) -> InterceptorFuture<'a, Self::Err> {
+        Box::pin(async move {
+            let config = request.extensions().get::<RequestConfig>();
+            let cache = match config.and_then(|config| config.range_cache.clone()) {
+                Some(cache) => cache,
+                None => return ctx.send(request).await,
+            };
+            let cache_control = config
+                .and_then(|config| config.cache_control.clone())
+                .unwrap_or_default();
+
+            if cache_control.no_store {
+                return ctx.send(request).await;
+            }
+
+            let uri = request.uri().clone();
+
+            if request.method() != Method::GET {
+                let is_safe = matches!(*request.method(), Method::HEAD | Method::OPTIONS);
+                let response = ctx.send(request).await?;
+
+                if !is_safe && response.status().is_success() {
+                    cache.remove(&uri);
+                }
+
+                return Ok(response);
+            }
+
+            let range = request
+                .headers()
+                .get(header::RANGE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(ByteRange::parse);
+
+            let lookup = range
+                .filter(|_| !cache_control.no_cache)
+                .filter(|_| !request.headers().contains_key(header::IF_RANGE))
+                .and_then(|range| cache.lookup(&uri, range, cache_control.max_age));
+
+            let lookup = match lookup {
+                Some(lookup) => lookup,
+                None => {
+                    let response = ctx.send(request).await?;
+                    return Ok(record(&cache, &uri, response));
+                }
+            };
+
+            let (missing_start, missing_end) = match lookup.missing() {
+                Some(missing) => missing,
+                None => {
+                    tracing::debug!(start = lookup.start, end = lookup.end, "range cache hit");
+                    return Ok(cached_response(lookup));
+                }
+            };
+
+            tracing::debug!(
+                start = missing_start,
+                end = missing_end,
+                "range partially cached, requesting the rest",
+            );
+
+            // Keep a copy of the request to send again without the cache, in
+            // case the server answers with a range that cannot be stitched.
+            let fallback = request
+                .body()
+                .try_clone()
+                .and_then(|body| request.to_builder().body(body).ok());
+
+            request.headers_mut().insert(
+                header::RANGE,
+                HeaderValue::from_str(&format!("bytes={}-{}", missing_start, missing_end))
+                    .unwrap(),
+            );
+            request
+                .headers_mut()
+                .insert(header::IF_RANGE, lookup.validator.value().clone());
+
+            let response = ctx.send(request).await?;
+
+            if is_stitchable(&response, &lookup, missing_start, missing_end) {
+                return Ok(stitch(&cache, &uri, lookup, response));
+            }
+
+            // The resource has changed, so the cached ranges are of no use.
+            // A full response is still a valid answer to the original request.
+            cache.remove(&uri);
+
+            if response.status() == StatusCode::PARTIAL_CONTENT {
+                if let Some(fallback) = fallback {
+                    drop(response);
+                    let response = ctx.send(fallback).await?;
+                    return Ok(record(&cache, &uri, response));
+                }
+            }
+
+            Ok(record(&cache, &uri, response))
+        })
+    }
+}
+
+/// Make up a response for a range that is cached completely.
+fn cached_response(lookup: Lookup) -> Response<AsyncBody> {
+    let len = lookup.prefix.len() as u64;
+    let mut builder = Response::builder()
+        .status(StatusCode::PARTIAL_CONTENT)
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(
+            header::CONTENT_RANGE,
+            content_range(lookup.start, lookup.end, lookup.total_len),
+        )
+        .header(header::CONTENT_LENGTH, len)
+        .header(lookup.validator.header_name(), lookup.validator.value().clone());
+
+    if let Some(content_type) = lookup.content_type {
+        builder = builder.header(header::CONTENT_TYPE, content_type);
+    }
+
+    builder.body(AsyncBody::from(lookup.prefix)).unwrap()
+}
+
+/// Check whether a response to a request for the missing part of a range
+/// contains exactly that part of the same version of the resource.
+fn is_stitchable(response: &Response<AsyncBody>, lookup: &Lookup, start: u64, end: u64) -> bool {
+    if response.status() != StatusCode::PARTIAL_CONTENT || is_encoded(response.headers()) {
+        return false;
+    }
+
+    if Validator::from_headers(response.headers()).as_ref() != Some(&lookup.validator) {
+        return false;
+    }
+
+    match response
+        .headers()
+        .get(header::CONTENT_RANGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_content_range)
+    {
+        Some((s, e, total_len)) => {
+            s == start
+                && e == end
+                && match (total_len, lookup.total_len) {
+                    (Some(a), Some(b)) => a == b,
+                    _ => true,
+                }
+        }
+        None => false,
+    }
+}
+
+/// Combine the cached bytes of a range with the missing bytes received from
+/// the server into a response for the whole range.
+fn stitch(
+    cache: &RangeCache,
+    uri: &Uri,
+    lookup: Lookup,
+    response: Response<AsyncBody>,
+) -> Response<AsyncBody> {
+    let total_len = lookup.total_len.or_else(|| {
+        response
+            .headers()
+            .get(header::CONTENT_RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_content_range)
+            .and_then(|(_, _, total_len)| total_len)
+    });
+    let len = lookup.end - lookup.start + 1;
+    let missing_start = lookup.start + lookup.prefix.len() as u64;
+    let missing_len = len - lookup.prefix.len() as u64 - lookup.suffix.len() as u64;
+    let (mut parts, body) = response.into_parts();
+    let recorder = Recorder::new(
+        body,
+        cache.clone(),
+        uri.clone(),
+        Part {
+            validator: lookup.validator,
+            total_len,
+            content_type: parts.headers.get(header::CONTENT_TYPE).cloned(),
+        },
+        missing_start,
+        missing_len,
+    );
+
+    parts.headers.insert(
+        header::CONTENT_RANGE,
+        content_range(lookup.start, lookup.end, total_len),
+    );
+    parts.headers.insert(header::CONTENT_LENGTH, len.into());
+
+    let body = Cursor::new(lookup.prefix)
+        .chain(recorder)
+        .chain(Cursor::new(lookup.suffix));
+
+    Response::from_parts(parts, AsyncBody::from_reader_sized(body, len))
+}
+
+/// Arrange for the body of a response to be cached once it has been read, if
+/// it is a single range of bytes that can be cached.
+fn record(cache: &RangeCache, uri: &Uri, response: Response<AsyncBody>) -> Response<AsyncBody> {
+    match response.status() {
+        StatusCode::OK => {
+            cache.invalidate_changed(uri, response.headers());
+            return response;
+        }
+        StatusCode::PARTIAL_CONTENT => {}
+        _ => return response,
+    }
+
+    let headers = response.headers();
+
+    if is_encoded(headers) || is_no_store(headers) {
+        return response;
+    }
+
+    let validator = match Validator::from_headers(headers) {
+        Some(validator) => validator,
+        None => return response,
+    };
+
+    let (start, end, total_len) = match headers
+        .get(header::CONTENT_RANGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_content_range)
+    {
+        Some(range) => range,
+        None => return response,
+    };
+
+    let len = end - start + 1;
+
+    if len > cache.capacity() {
+        return response;
+    }
+
+    let part = Part {
+        validator,
+        total_len,
+        content_type: headers.get(header::CONTENT_TYPE).cloned(),
+    };
+    let (parts, body) = response.into_parts();
+    let recorder = Recorder::new(body, cache.clone(), uri.clone(), part, start, len);
+
+    Response::from_parts(parts, AsyncBody::from_reader_sized(recorder, len))
+}
+
+fn is_encoded(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_ENCODING)
+        .map_or(false, |value| value != "identity")
+}
+
+fn is_no_store(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|directive| directive.trim().eq_ignore_ascii_case("no-store"))
+}
+
+/// A response body that caches the bytes read from it once all of them have
+/// been read.
+struct Recorder {
+    body: AsyncBody,
+    cache: RangeCache,
+    uri: Uri,
+    /// Details of the response, until the bytes are cached or given up on.
+    part: Option<Part>,
+    start: u64,
+    len: u64,
+    data: Vec<u8>,
+}
+
+impl Recorder {
+    fn new(body: AsyncBody, cache: RangeCache, uri: Uri, part: Part, start: u64, len: u64) -> Self {
+        Self {
+            body,
+            cache,
+            uri,
+            part: Some(part),
+            start,
+            len,
+            data: Vec::new(),
+        }
+    }
+}
+
+impl AsyncRead for Recorder {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let result = ready!(Pin::new(&mut this.body).poll_read(cx, buf));
+
+        match &result {
+            Ok(0) => {
+                if let Some(part) = this.part.take() {
+                    if this.data.len() as u64 == this.len {
+                        let data = std::mem::take(&mut this.data);
+                        this.cache.insert(&this.uri, part, this.start, data);
+                    }
+                }
+            }
+            Ok(n) => {
+                if this.part.is_some() {
+                    this.data.extend_from_slice(&buf[..*n]);
+
+                    // More bytes than the range holds, so something is off.
+                    if this.data.len() as u64 > this.len {
+                        this.part = None;
+                        this.data = Vec::new();
+                    }
+                }
+            }
+            Err(_) => {
+                this.part = None;
+                this.data = Vec::new();
+            }
+        }
+
+        Poll::Ready(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_range() {
+        assert_eq!(ByteRange::parse("bytes=0-499"), Some(ByteRange::Bounded(0, 499)));
+        assert_eq!(ByteRange::parse("bytes=9500-"), Some(ByteRange::From(9500)));
+        assert_eq!(ByteRange::parse("bytes=-500"), Some(ByteRange::Last(500)));
+        assert_eq!(ByteRange::parse("bytes=0-0,-1"), None);
+        assert_eq!(ByteRange::parse("bytes=500-400"), None);
+        assert_eq!(ByteRange::parse("bytes=-0"), None);
+        assert_eq!(ByteRange::parse("items=0-5"), None);
+    }
+
+    #[test]
+    fn resolve_range() {
+        assert_eq!(ByteRange::Bounded(0, 499).resolve(None), Some((0, 499)));
+        assert_eq!(ByteRange::Bounded(0, 499).resolve(Some(100)), Some((0, 99)));
+        assert_eq!(ByteRange::Bounded(100, 499).resolve(Some(100)), None);
+        assert_eq!(ByteRange::From(10).resolve(None), None);
+        assert_eq!(ByteRange::From(10).resolve(Some(100)), Some((10, 99)));
+        assert_eq!(ByteRange::Last(10).resolve(Some(100)), Some((90, 99)));
+        assert_eq!(ByteRange::Last(500).resolve(Some(100)), Some((0, 99)));
+    }
+
+    #[test]
+    fn parse_content_ranges() {
+        assert_eq!(parse_content_range("bytes 0-499/1234"), Some((0, 499, Some(1234))));
+        assert_eq!(parse_content_range("bytes 0-499/*"), Some((0, 499, None)));
+        assert_eq!(parse_content_range("bytes */1234"), None);
+        assert_eq!(parse_content_range("bytes 0-499/400"), None);
+    }
+}
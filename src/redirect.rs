@@ -1,12 +1,19 @@
 use crate::{
     body::AsyncBody,
-    config::{request::RequestConfig, RedirectPolicy},
+    config::{request::RequestConfig, CacheStatus, RedirectCache, RedirectPolicy},
     error::{Error, ErrorKind},
     handler::RequestBody,
     interceptor::{Context, Interceptor, InterceptorFuture},
     request::RequestExt,
 };
-use http::{header::ToStrError, HeaderValue, Request, Response, Uri};
+use http::{
+    header::{HeaderName, ToStrError},
+    HeaderValue,
+    Method,
+    Request,
+    Response,
+    Uri,
+};
 use std::{borrow::Cow, convert::TryFrom, str};
 use url::Url;
 
@@ -19,6 +26,20 @@ const DEFAULT_REDIRECT_LIMIT: u32 = 1024;
 /// following any redirects.
 pub(crate) struct EffectiveUri(pub(crate) Uri);
 
+/// Record the effective URI of a response, unless the URI that was actually
+/// sent was already recorded, such as after URL normalization.
+fn set_effective_uri<T>(response: &mut Response<T>, uri: Uri) {
+    if response.extensions().get::<EffectiveUri>().is_none() {
+        response.extensions_mut().insert(EffectiveUri(uri));
+    }
+}
+
+/// Record how the redirect cache was used for a response.
+fn set_cache_status<T>(response: &mut Response<T>, cache: &RedirectCache, status: CacheStatus) {
+    cache.record(status);
+    response.extensions_mut().insert(status);
+}
+
 /// Interceptor that implements automatic following of HTTP redirects.
 pub(crate) struct RedirectInterceptor;
 
@@ -42,12 +63,35 @@ impl Interceptor for RedirectInterceptor {
                 .cloned()
                 .unwrap_or_default();
 
+            let cache = request
+                .extensions()
+                .get::<RequestConfig>()
+                .and_then(|config| config.redirect_cache.clone());
+
+            let cache_control = request
+                .extensions()
+                .get::<RequestConfig>()
+                .and_then(|config| config.cache_control.clone())
+                .unwrap_or_default();
+
+            if let Some(value) = cache_control.header_value() {
+                request
+                    .headers_mut()
+                    .entry(http::header::CACHE_CONTROL)
+                    .or_insert(value);
+            }
+
             // No redirect handling, just proceed normally.
             if policy == RedirectPolicy::None {
+                let method = request.method().clone();
                 let mut response = ctx.send(request).await?;
-                response
-                    .extensions_mut()
-                    .insert(EffectiveUri(effective_uri));
+
+                if let Some(cache) = cache.as_ref() {
+                    invalidate(cache, &method, &effective_uri, &response);
+                    set_cache_status(&mut response, cache, CacheStatus::Bypass);
+                }
+
+                set_effective_uri(&mut response, effective_uri);
 
                 return Ok(response);
             }
@@ -63,6 +107,41 @@ impl Interceptor for RedirectInterceptor {
                 _ => DEFAULT_REDIRECT_LIMIT,
             };
 
+            let mut cache_status = CacheStatus::Bypass;
+
+            // Skip straight to the target of any permanent redirects we
+            // remember. Limiting the number of hops guards against cycles.
+            if let Some(cache) = cache
+                .as_ref()
+                .filter(|_| is_cacheable(request.method()) && !cache_control.no_cache)
+            {
+                let mut hops = 0;
+
+                while hops < limit {
+                    let max_age = cache_control.max_age;
+
+                    match cache.lookup(request.uri(), request.headers(), max_age) {
+                        Some(target) => {
+                            tracing::debug!(
+                                "using cached redirect from {} to {}",
+                                request.uri(),
+                                target
+                            );
+                            *request.uri_mut() = target;
+                            hops += 1;
+                        }
+                        None => break,
+                    }
+                }
+
+                effective_uri = request.uri().clone();
+                cache_status = if hops > 0 {
+                    CacheStatus::Hit
+                } else {
+                    CacheStatus::Miss
+                };
+            }
+
             // Keep track of how many redirects we've done.
             let mut redirect_count: u32 = 0;
 
@@ -73,6 +152,13 @@ impl Interceptor for RedirectInterceptor {
                 // Send the request to get the ball rolling.
                 let mut response = ctx.send(request).await?;
 
+                // Forget cached redirects of anything the request changed.
+                if let Some(cache) = cache.as_ref() {
+                    if let Some(method) = request_builder.method_ref() {
+                        invalidate(cache, method, &effective_uri, &response);
+                    }
+                }
+
                 // Check for a redirect.
                 if let Some(location) = get_redirect_location(&effective_uri, &response) {
                     // If we've reached the limit, return an error as requested.
@@ -80,6 +166,21 @@ impl Interceptor for RedirectInterceptor {
                         return Err(Error::with_response(ErrorKind::TooManyRedirects, &response));
                     }
 
+                    // Remember permanent redirects for next time.
+                    if let Some(cache) = cache.as_ref() {
+                        if (response.status() == 301 || response.status() == 308)
+                            && matches!(request_builder.method_ref(), Some(m) if is_cacheable(m))
+                            && !is_no_store(&response)
+                            && !cache_control.no_store
+                        {
+                            if let (Some(vary), Some(headers)) =
+                                (get_vary(&response), request_builder.headers_ref())
+                            {
+                                cache.insert(&effective_uri, headers, &vary, location.clone());
+                            }
+                        }
+                    }
+
                     // Set referer header.
                     if auto_referer {
                         let referer = request_builder.uri_ref().unwrap().to_string();
@@ -119,6 +220,17 @@ impl Interceptor for RedirectInterceptor {
                         ));
                     }
 
+                    // The host header override only applies to the server
+                    // the request was originally sent to.
+                    if location.authority() != effective_uri.authority() {
+                        if let Some(config) = request_builder
+                            .extensions_mut()
+                            .and_then(|extensions| extensions.get_mut::<RequestConfig>())
+                        {
+                            config.host_header = None;
+                        }
+                    }
+
                     // Update the request to point to the new URI.
                     effective_uri = location.clone();
                     request = request_builder
@@ -129,9 +241,11 @@ impl Interceptor for RedirectInterceptor {
                 }
                 // No more redirects; set the effective URI we finally settled on and return.
                 else {
-                    response
-                        .extensions_mut()
-                        .insert(EffectiveUri(effective_uri));
+                    set_effective_uri(&mut response, effective_uri);
+
+                    if let Some(cache) = cache.as_ref() {
+                        set_cache_status(&mut response, cache, cache_status);
+                    }
 
                     return Ok(response);
                 }
@@ -140,6 +254,81 @@ impl Interceptor for RedirectInterceptor {
     }
 }
 
+/// Only redirects of safe requests are cached, since following a cached
+/// redirect for other methods would change which resource is modified.
+fn is_cacheable(method: &Method) -> bool {
+    method == Method::GET || method == Method::HEAD
+}
+
+fn is_no_store<T>(response: &Response<T>) -> bool {
+    response
+        .headers()
+        .get_all(http::header::CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|directive| directive.trim().eq_ignore_ascii_case("no-store"))
+}
+
+/// Forget the cached redirects of the resources changed by a successful
+/// request with an unsafe method, as described in [RFC 7234, Section
+/// 4.4](https://tools.ietf.org/html/rfc7234#section-4.4).
+fn invalidate<T>(
+    cache: &RedirectCache,
+    method: &Method,
+    request_uri: &Uri,
+    response: &Response<T>,
+) {
+    let status = response.status();
+
+    if method.is_safe() || !(status.is_success() || status.is_redirection()) {
+        return;
+    }
+
+    cache.remove(request_uri);
+
+    for name in &[http::header::LOCATION, http::header::CONTENT_LOCATION] {
+        let uri = response
+            .headers()
+            .get(name)
+            .and_then(|value| parse_location(value).ok())
+            .and_then(|location| resolve(request_uri, location.as_ref()).ok());
+
+        // Only URIs on the same host may be invalidated, to prevent a server
+        // from evicting the redirects of another.
+        if let Some(uri) = uri.filter(|uri| uri.authority() == request_uri.authority()) {
+            cache.remove(&uri);
+        }
+    }
+}
+
+/// Get the names of the request headers listed in the `Vary` header of a
+/// response, or `None` if the response varies by something other than request
+/// headers and should not be cached.
+fn get_vary<T>(response: &Response<T>) -> Option<Vec<HeaderName>> {
+    let mut names = Vec::new();
+
+    for value in response.headers().get_all(http::header::VARY) {
+        for name in value.to_str().ok()?.split(',').map(str::trim) {
+            if name.is_empty() {
+                continue;
+            }
+
+            if name == "*" {
+                return None;
+            }
+
+            let name = HeaderName::from_bytes(name.as_bytes()).ok()?;
+
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+
+    Some(names)
+}
+
 fn get_redirect_location<T>(request_uri: &Uri, response: &Response<T>) -> Option<Uri> {
     if response.status().is_redirection() {
         let location = response.headers().get(http::header::LOCATION)?;
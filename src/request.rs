@@ -1,13 +1,20 @@
 use crate::{
-    body::{AsyncBody, Body},
+    body::AsyncBody,
+    cancel::CancelHandle,
     client::ResponseFuture,
+    query::QueryMut,
     config::{
         request::{RequestConfig, WithRequestConfig},
         Configurable,
     },
-    error::Error,
 };
-use http::{Request, Response};
+#[cfg(feature = "blocking")]
+use crate::body::Body;
+#[cfg(any(feature = "blocking", feature = "json", feature = "form"))]
+use crate::error::Error;
+use http::Request;
+#[cfg(feature = "blocking")]
+use http::Response;
 
 /// Extension methods on an HTTP request.
 pub trait RequestExt<T> {
@@ -17,6 +24,15 @@ pub trait RequestExt<T> {
     /// Note that third-party extensions are not cloned.
     fn to_builder(&self) -> http::request::Builder;
 
+    /// Get a mutable view of the query parameters of this request's URI.
+    ///
+    /// Changes made through the view are written back to the URI when it is
+    /// dropped. This is especially useful in
+    /// [interceptors](crate::interceptor) that need to add parameters such as
+    /// API keys or signatures to outgoing requests. See [`QueryMut`] for
+    /// details.
+    fn query_mut(&mut self) -> QueryMut<'_>;
+
     /// Send the HTTP request synchronously using the default client.
     ///
     /// This is a convenience method that is equivalent to
@@ -36,6 +52,7 @@ pub trait RequestExt<T> {
     ///     .send()?;
     /// # Ok::<(), isahc::Error>(())
     /// ```
+    #[cfg(feature = "blocking")]
     fn send(self) -> Result<Response<Body>, Error>
     where
         T: Into<Body>;
@@ -62,6 +79,12 @@ impl<T> RequestExt<T> for Request<T> {
             builder = builder.extension(config.clone());
         }
 
+        // Follow-up requests, such as redirects, are canceled along with the
+        // original request.
+        if let Some(cancel) = self.extensions().get::<CancelHandle>() {
+            builder = builder.extension(cancel.clone());
+        }
+
         #[cfg(feature = "cookies")]
         {
             if let Some(cookie_jar) = self.extensions().get::<crate::cookies::CookieJar>() {
@@ -69,9 +92,21 @@ impl<T> RequestExt<T> for Request<T> {
             }
         }
 
+        #[cfg(feature = "trace-context")]
+        {
+            if let Some(context) = self.extensions().get::<crate::trace_context::TraceContext>() {
+                builder = builder.extension(context.clone());
+            }
+        }
+
         builder
     }
 
+    fn query_mut(&mut self) -> QueryMut<'_> {
+        QueryMut::new(self)
+    }
+
+    #[cfg(feature = "blocking")]
     fn send(self) -> Result<Response<Body>, Error>
     where
         T: Into<Body>,
@@ -87,6 +122,123 @@ impl<T> RequestExt<T> for Request<T> {
     }
 }
 
+/// Extension methods on an HTTP request builder for setting typed bodies.
+///
+/// # Availability
+///
+/// This trait is only available when the [`json`](../index.html#json) or
+/// [`form`](../index.html#form) feature is enabled.
+#[cfg(any(feature = "json", feature = "form"))]
+pub trait RequestBuilderExt {
+    /// Finish building the request with the given value serialized as JSON as
+    /// its body.
+    ///
+    /// The `Content-Type` header is set to `application/json`, unless it was
+    /// already set.
+    ///
+    /// # Availability
+    ///
+    /// This method is only available when the [`json`](../index.html#json)
+    /// feature is enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::{prelude::*, Request};
+    /// use serde_json::json;
+    ///
+    /// let response = Request::post("https://httpbin.org/post")
+    ///     .json(&json!({
+    ///         "speed": "fast",
+    ///         "cool_name": true,
+    ///     }))?
+    ///     .send()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    #[cfg(feature = "json")]
+    fn json<T>(self, value: &T) -> Result<Request<Vec<u8>>, Error>
+    where
+        T: serde::Serialize + ?Sized;
+
+    /// Finish building the request with the given value serialized as an HTML
+    /// form as its body.
+    ///
+    /// The value must serialize as a struct or map whose fields are strings,
+    /// numbers, or booleans, or sequences of them, which are sent as one field
+    /// per element. Fields that are `None` are omitted. The `Content-Type`
+    /// header is set to `application/x-www-form-urlencoded`, unless it was
+    /// already set.
+    ///
+    /// # Availability
+    ///
+    /// This method is only available when the [`form`](../index.html#form)
+    /// feature is enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::{prelude::*, Request};
+    /// use std::collections::HashMap;
+    ///
+    /// let mut form = HashMap::new();
+    /// form.insert("username", "jane");
+    /// form.insert("password", "hunter2");
+    ///
+    /// let response = Request::post("https://example.org/login")
+    ///     .form(&form)?
+    ///     .send()?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    #[cfg(feature = "form")]
+    fn form<T>(self, value: &T) -> Result<Request<Vec<u8>>, Error>
+    where
+        T: serde::Serialize + ?Sized;
+}
+
+#[cfg(any(feature = "json", feature = "form"))]
+impl RequestBuilderExt for http::request::Builder {
+    #[cfg(feature = "json")]
+    fn json<T>(self, value: &T) -> Result<Request<Vec<u8>>, Error>
+    where
+        T: serde::Serialize + ?Sized,
+    {
+        let body = serde_json::to_vec(value)
+            .map_err(|e| Error::new(crate::error::ErrorKind::InvalidRequest, e))?;
+
+        with_body(self, "application/json", body)
+    }
+
+    #[cfg(feature = "form")]
+    fn form<T>(self, value: &T) -> Result<Request<Vec<u8>>, Error>
+    where
+        T: serde::Serialize + ?Sized,
+    {
+        let body = crate::form::serialize(value)?;
+
+        with_body(self, "application/x-www-form-urlencoded", body.into_bytes())
+    }
+}
+
+/// Finish building a request with the given body, setting its content type
+/// unless it was already set.
+#[cfg(any(feature = "json", feature = "form"))]
+fn with_body(
+    mut builder: http::request::Builder,
+    content_type: &'static str,
+    body: Vec<u8>,
+) -> Result<Request<Vec<u8>>, Error> {
+    let has_content_type = matches!(
+        builder.headers_ref(),
+        Some(headers) if headers.contains_key(http::header::CONTENT_TYPE)
+    );
+
+    if !has_content_type {
+        builder = builder.header(http::header::CONTENT_TYPE, content_type);
+    }
+
+    Ok(builder.body(body)?)
+}
+
 impl Configurable for http::request::Builder {
     #[cfg(feature = "cookies")]
     fn cookie_jar(self, cookie_jar: crate::cookies::CookieJar) -> Self {
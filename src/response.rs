@@ -1,10 +1,20 @@
-use crate::{metrics::Metrics, redirect::EffectiveUri, trailer::Trailer};
+use crate::{
+    config::{CacheStatus, IdnHost},
+    content_disposition::ContentDisposition,
+    media_type_version::MediaTypeVersion,
+    metrics::{EarlyResponse, Metrics, Timestamps},
+    redirect::EffectiveUri,
+    sniff::SniffedContentType,
+    trailer::Trailer,
+    upgrade::Upgraded,
+};
 use futures_lite::io::{copy as copy_async, AsyncRead, AsyncWrite};
 use http::{Response, Uri};
+use std::{io, net::SocketAddr, time::Instant};
+#[cfg(feature = "blocking")]
 use std::{
     fs::File,
-    io::{self, Read, Write},
-    net::SocketAddr,
+    io::{Read, Write},
     path::Path,
 };
 
@@ -37,12 +47,22 @@ pub trait ResponseExt<T> {
 
     /// Get the effective URI of this response. This value differs from the
     /// original URI provided when making the request if at least one redirect
-    /// was followed.
+    /// was followed, or if the URI was changed by
+    /// [URL normalization](crate::config::Configurable::url_normalization), in
+    /// which case this is the URI that was actually sent.
     ///
     /// This information is only available if populated by the HTTP client that
     /// produced the response.
     fn effective_uri(&self) -> Option<&Uri>;
 
+    /// Get how the [redirect cache](crate::config::RedirectCache) of the
+    /// client was used for this request, if it has one and redirects were
+    /// handled by the client.
+    ///
+    /// A response with [`CacheStatus::Hit`] was reached by following at least
+    /// one cached redirect without asking the server.
+    fn cache_status(&self) -> Option<CacheStatus>;
+
     /// Get the local socket address of the last-used connection involved in
     /// this request, if known.
     ///
@@ -90,6 +110,165 @@ pub trait ResponseExt<T> {
     /// metrics you can use
     /// [`Configurable::metrics`](crate::config::Configurable::metrics).
     fn metrics(&self) -> Option<&Metrics>;
+
+    /// Get the points in time at which the request for this response passed
+    /// through each stage on the client side, such as when it was submitted
+    /// and when it started to be sent. See [`Timestamps`] for details.
+    ///
+    /// Returns `None` for responses not received by a client.
+    fn timestamps(&self) -> Option<Timestamps>;
+
+    /// Get when the header of this response was completely received.
+    ///
+    /// Responses are returned as soon as their header arrives, while the body
+    /// may still be streaming in, so latency-sensitive code can use this to
+    /// measure or act on the time to the header independently from the time
+    /// taken by the body. This is a shorthand for
+    /// [`Timestamps::headers_received`].
+    ///
+    /// Returns `None` for responses not received by a client.
+    fn headers_received_at(&self) -> Option<Instant>;
+
+    /// If the server sent this response before it had received the whole
+    /// request body, get how much of the body was sent. The rest of the body
+    /// is not sent. See [`EarlyResponse`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::prelude::*;
+    ///
+    /// let response = isahc::put("https://example.org/upload", vec![0; 1 << 30])?;
+    ///
+    /// if let Some(early) = response.early_response() {
+    ///     println!("rejected after sending {} bytes", early.bytes_sent());
+    /// }
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    fn early_response(&self) -> Option<EarlyResponse>;
+
+    /// Get the content type of the response body as detected by inspecting
+    /// the body itself, if content type sniffing is enabled and the
+    /// `Content-Type` header returned by the server was missing or wrong.
+    ///
+    /// To enable sniffing you can use
+    /// [`Configurable::sniff_content_type`](crate::config::Configurable::sniff_content_type).
+    fn sniffed_content_type(&self) -> Option<&str>;
+
+    /// Get the encoding the response body is still in, as given by the
+    /// `Content-Encoding` header, if it was not decoded automatically.
+    ///
+    /// Returns `None` if the response body was not encoded, or if it was
+    /// decoded because
+    /// [`Configurable::automatic_decompression`](crate::config::Configurable::automatic_decompression)
+    /// is enabled. If this returns an encoding, reading the body returns the
+    /// encoded bytes as sent by the server, and decoding them is up to the
+    /// caller.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::{prelude::*, Request};
+    ///
+    /// let response = Request::get("https://example.org")
+    ///     .header("Accept-Encoding", "gzip")
+    ///     .automatic_decompression(false)
+    ///     .body(())?
+    ///     .send()?;
+    ///
+    /// if response.content_encoding() == Some("gzip") {
+    ///     // Decode the body yourself.
+    /// }
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    fn content_encoding(&self) -> Option<&str>;
+
+    /// Get the host name the final request for this response was sent to, in
+    /// both its ASCII and Unicode forms.
+    ///
+    /// This is useful for displaying an internationalized domain name to users
+    /// as it was written, rather than in its punycode form. Returns `None` if
+    /// the request was sent to an IP address.
+    fn idn_host(&self) -> Option<&IdnHost>;
+
+    /// Parse the `Content-Disposition` header of the response, if present.
+    ///
+    /// This is useful for saving a downloaded file using the name suggested by
+    /// the server. See [`ContentDisposition`] for details on how the suggested
+    /// file name is sanitized.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::prelude::*;
+    ///
+    /// let mut response = isahc::get("https://example.org/download")?;
+    ///
+    /// let filename = response
+    ///     .content_disposition()
+    ///     .and_then(|disposition| disposition.filename().map(String::from))
+    ///     .unwrap_or_else(|| String::from("download"));
+    ///
+    /// response.copy_to_file(filename)?;
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    fn content_disposition(&self) -> Option<ContentDisposition>;
+
+    /// Parse the version of the media type in the `Content-Type` header of the
+    /// response, if it carries one.
+    ///
+    /// This is useful for checking which version of an API the server
+    /// responded with when asking for several using
+    /// [`Configurable::accept_versions`](crate::config::Configurable::accept_versions).
+    /// See [`MediaTypeVersion`] for the forms of versioned media types that
+    /// are recognized.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::prelude::*;
+    ///
+    /// let response = isahc::get("https://api.example.org/widgets")?;
+    ///
+    /// if let Some(version) = response.media_type_version() {
+    ///     println!("server responded with version {}", version.version());
+    /// }
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    fn media_type_version(&self) -> Option<MediaTypeVersion>;
+
+    /// Take the underlying connection out of a `101 Switching Protocols`
+    /// response, for speaking the protocol the server switched to.
+    ///
+    /// A request is treated as asking to switch protocols if it has an
+    /// `Upgrade` header and either no body or a body of known length, which is
+    /// sent before anything written to the connection. Such requests are
+    /// always sent using HTTP/1.1, and their connections are never reused for
+    /// other requests. See [`Upgraded`] for details on using the connection.
+    ///
+    /// If the server did not switch protocols, the response is returned back
+    /// unchanged as an error.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::{prelude::*, Request};
+    /// use std::io::{Read, Write};
+    ///
+    /// let response = Request::get("http://example.org/echo")
+    ///     .header("Connection", "Upgrade")
+    ///     .header("Upgrade", "echo")
+    ///     .body(())?
+    ///     .send()?;
+    ///
+    /// let mut connection = response.upgrade().expect("server refused to upgrade");
+    /// connection.write_all(b"hello")?;
+    ///
+    /// let mut buf = [0; 5];
+    /// connection.read_exact(&mut buf)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    fn upgrade(self) -> Result<Upgraded<T>, Response<T>>;
 }
 
 impl<T> ResponseExt<T> for Response<T> {
@@ -105,6 +284,10 @@ impl<T> ResponseExt<T> for Response<T> {
         self.extensions().get::<EffectiveUri>().map(|v| &v.0)
     }
 
+    fn cache_status(&self) -> Option<CacheStatus> {
+        self.extensions().get().copied()
+    }
+
     fn local_addr(&self) -> Option<SocketAddr> {
         self.extensions().get::<LocalAddr>().map(|v| v.0)
     }
@@ -121,9 +304,56 @@ impl<T> ResponseExt<T> for Response<T> {
     fn metrics(&self) -> Option<&Metrics> {
         self.extensions().get()
     }
+
+    fn timestamps(&self) -> Option<Timestamps> {
+        self.extensions().get().copied()
+    }
+
+    fn headers_received_at(&self) -> Option<Instant> {
+        self.timestamps()?.headers_received()
+    }
+
+    fn early_response(&self) -> Option<EarlyResponse> {
+        self.extensions().get().copied()
+    }
+
+    fn content_disposition(&self) -> Option<ContentDisposition> {
+        self.headers()
+            .get(http::header::CONTENT_DISPOSITION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(ContentDisposition::parse)
+    }
+
+    fn media_type_version(&self) -> Option<MediaTypeVersion> {
+        self.headers()
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(MediaTypeVersion::parse)
+    }
+
+    fn sniffed_content_type(&self) -> Option<&str> {
+        self.extensions()
+            .get::<SniffedContentType>()
+            .map(|v| v.0.as_str())
+    }
+
+    fn content_encoding(&self) -> Option<&str> {
+        self.extensions()
+            .get::<ContentEncoding>()
+            .map(|v| v.0.as_str())
+    }
+
+    fn idn_host(&self) -> Option<&IdnHost> {
+        self.extensions().get()
+    }
+
+    fn upgrade(self) -> Result<Upgraded<T>, Response<T>> {
+        Upgraded::from_response(self)
+    }
 }
 
 /// Provides extension methods for consuming HTTP response streams.
+#[cfg(feature = "blocking")]
 pub trait ReadResponseExt<R: Read> {
     /// Read any remaining bytes from the response body stream and discard them
     /// until the end of the stream is reached. It is usually a good idea to
@@ -211,6 +441,72 @@ pub trait ReadResponseExt<R: Read> {
         File::create(path).and_then(|f| self.copy_to(f))
     }
 
+    /// Save the response body into a writer, reporting progress, limiting its
+    /// size, and verifying its digest as configured by the given options.
+    ///
+    /// Returns a summary of the saved body. If the body is larger than the
+    /// limit or does not match the expected digest, an error of kind
+    /// [`io::ErrorKind::InvalidData`] is returned, although part or all of
+    /// the body may have been written already.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::{download::SaveOptions, prelude::*};
+    ///
+    /// let mut buf = Vec::new();
+    /// let summary = isahc::get("https://example.org")?
+    ///     .save_to(&mut buf, SaveOptions::new().max_size(1024 * 1024))?;
+    ///
+    /// println!("saved {} bytes in {:?}", summary.len(), summary.elapsed());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    fn save_to<W: Write>(
+        &mut self,
+        writer: W,
+        options: crate::download::SaveOptions<'_>,
+    ) -> io::Result<crate::download::SaveSummary>;
+
+    /// Save the response body into a file, like
+    /// [`save_to`](ReadResponseExt::save_to).
+    ///
+    /// If saving fails, the partially written file is removed again.
+    fn save_to_file<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: crate::download::SaveOptions<'_>,
+    ) -> io::Result<crate::download::SaveSummary>;
+
+    /// Save the response body into a temporary file, like
+    /// [`save_to`](ReadResponseExt::save_to), and return the file for reading
+    /// the body back from its start.
+    ///
+    /// This is useful for bodies too large to keep in memory that have to be
+    /// read more than once, or only after the connection has been released.
+    /// The file is created as configured by the
+    /// [temporary file options](crate::download::SaveOptions::temp_file), and
+    /// removed again when the returned file is dropped or if saving fails.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::{download::SaveOptions, prelude::*};
+    /// use std::io::Read;
+    ///
+    /// let mut spooled = isahc::get("https://example.org/large.csv")?
+    ///     .spool(SaveOptions::new())?;
+    ///
+    /// println!("spooled {} bytes", spooled.summary().len());
+    ///
+    /// let mut contents = String::new();
+    /// spooled.read_to_string(&mut contents)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    fn spool(
+        &mut self,
+        options: crate::download::SaveOptions<'_>,
+    ) -> io::Result<crate::download::SpooledFile>;
+
     /// Read the entire response body into memory.
     ///
     /// # Examples
@@ -257,6 +553,40 @@ pub trait ReadResponseExt<R: Read> {
     #[cfg(feature = "text-decoding")]
     fn text(&mut self) -> io::Result<String>;
 
+    /// Read the response body as a string, decoding it using the given
+    /// character encoding instead of the one declared by the server.
+    ///
+    /// This is useful for servers that declare the wrong character set, or
+    /// none at all. Any [label](https://encoding.spec.whatwg.org/#names-and-labels)
+    /// of an encoding supported by web browsers can be given, such as `utf-8`
+    /// or `iso-8859-1`. A byte order mark at the beginning of the body still
+    /// takes precedence, as with [`text`](ReadResponseExt::text).
+    ///
+    /// Returns an error of kind [`io::ErrorKind::InvalidInput`] if the given
+    /// encoding is unknown.
+    ///
+    /// This method consumes the entire response body stream and can only be
+    /// called once.
+    ///
+    /// # Availability
+    ///
+    /// This method is only available when the
+    /// [`text-decoding`](index.html#text-decoding) feature is enabled, which it
+    /// is by default.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use isahc::prelude::*;
+    ///
+    /// let text = isahc::get("https://example.org/legacy.txt")?
+    ///     .text_with_charset("windows-1252")?;
+    /// println!("{}", text);
+    /// # Ok::<(), isahc::Error>(())
+    /// ```
+    #[cfg(feature = "text-decoding")]
+    fn text_with_charset(&mut self, charset: &str) -> io::Result<String>;
+
     /// Deserialize the response body as JSON into a given type.
     ///
     /// # Availability
@@ -280,11 +610,35 @@ pub trait ReadResponseExt<R: Read> {
         T: serde::de::DeserializeOwned;
 }
 
+#[cfg(feature = "blocking")]
 impl<R: Read> ReadResponseExt<R> for Response<R> {
     fn copy_to<W: Write>(&mut self, mut writer: W) -> io::Result<u64> {
         io::copy(self.body_mut(), &mut writer)
     }
 
+    fn save_to<W: Write>(
+        &mut self,
+        writer: W,
+        options: crate::download::SaveOptions<'_>,
+    ) -> io::Result<crate::download::SaveSummary> {
+        crate::download::save(self, writer, options)
+    }
+
+    fn save_to_file<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: crate::download::SaveOptions<'_>,
+    ) -> io::Result<crate::download::SaveSummary> {
+        crate::download::save_to_file(self, path.as_ref(), options)
+    }
+
+    fn spool(
+        &mut self,
+        options: crate::download::SaveOptions<'_>,
+    ) -> io::Result<crate::download::SpooledFile> {
+        crate::download::spool(self, options)
+    }
+
     fn bytes(&mut self) -> io::Result<Vec<u8>> {
         let mut buf = allocate_buffer(self);
 
@@ -298,6 +652,11 @@ impl<R: Read> ReadResponseExt<R> for Response<R> {
         crate::text::Decoder::for_response(self).decode_reader(self.body_mut())
     }
 
+    #[cfg(feature = "text-decoding")]
+    fn text_with_charset(&mut self, charset: &str) -> io::Result<String> {
+        crate::text::Decoder::for_label(charset)?.decode_reader(self.body_mut())
+    }
+
     #[cfg(feature = "json")]
     fn json<D>(&mut self) -> Result<D, serde_json::Error>
     where
@@ -417,6 +776,19 @@ pub trait AsyncReadResponseExt<R: AsyncRead + Unpin> {
     #[cfg(feature = "text-decoding")]
     fn text(&mut self) -> crate::text::TextFuture<'_, &mut R>;
 
+    /// Read the response body as a string asynchronously, decoding it using
+    /// the given character encoding instead of the one declared by the server.
+    ///
+    /// See [`ReadResponseExt::text_with_charset`] for details.
+    ///
+    /// # Availability
+    ///
+    /// This method is only available when the
+    /// [`text-decoding`](index.html#text-decoding) feature is enabled, which it
+    /// is by default.
+    #[cfg(feature = "text-decoding")]
+    fn text_with_charset(&mut self, charset: &str) -> crate::text::TextFuture<'_, &mut R>;
+
     /// Deserialize the response body as JSON into a given type.
     ///
     /// # Caveats
@@ -480,6 +852,14 @@ impl<R: AsyncRead + Unpin> AsyncReadResponseExt<R> for Response<R> {
         crate::text::Decoder::for_response(self).decode_reader_async(self.body_mut())
     }
 
+    #[cfg(feature = "text-decoding")]
+    fn text_with_charset(&mut self, charset: &str) -> crate::text::TextFuture<'_, &mut R> {
+        match crate::text::Decoder::for_label(charset) {
+            Ok(decoder) => decoder.decode_reader_async(self.body_mut()),
+            Err(e) => crate::text::TextFuture::new(async move { Err(e) }),
+        }
+    }
+
     #[cfg(feature = "json")]
     fn json<T>(&mut self) -> JsonFuture<'_, R, T>
     where
@@ -494,7 +874,7 @@ impl<R: AsyncRead + Unpin> AsyncReadResponseExt<R> for Response<R> {
             if let Err(e) = copy_async(self.body_mut(), &mut buf).await {
                 struct ErrorReader(Option<io::Error>);
 
-                impl Read for ErrorReader {
+                impl std::io::Read for ErrorReader {
                     fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
                         Err(self.0.take().unwrap())
                     }
@@ -548,6 +928,9 @@ pub(crate) struct LocalAddr(pub(crate) SocketAddr);
 
 pub(crate) struct RemoteAddr(pub(crate) SocketAddr);
 
+/// Encoding of a response body that was not decoded automatically.
+pub(crate) struct ContentEncoding(pub(crate) String);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,245 @@
+//! Support for automatically retrying requests, and for limiting retries.
+
+use crate::{
+    body::AsyncBody,
+    config::{clock::SharedClock, request::RequestConfig, RetryPolicy},
+    error::{Error, ErrorKind},
+    interceptor::{Context, Interceptor, InterceptorFuture},
+    request::RequestExt,
+    HttpClient,
+};
+use http::{header::RETRY_AFTER, HeaderMap, Request, Uri};
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// Check whether a request to the given URI that failed with the given error
+/// may be retried according to the retry budget of a client, returning the
+/// error to fail with instead if not.
+#[cfg_attr(not(feature = "blocking"), allow(dead_code))]
+pub(crate) fn allow_retry(client: &HttpClient, uri: &Uri, error: &Error) -> Result<(), Error> {
+    match client.retry_budget() {
+        Some(budget) if !budget.try_withdraw(uri, client.clock().now()) => {
+            Err(Error::new(ErrorKind::RetryBudgetExhausted, error.clone()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Interceptor that deposits every request sent into the configured retry
+/// budget, if any.
+pub(crate) struct RetryBudgetInterceptor;
+
+impl Interceptor for RetryBudgetInterceptor {
+    type Err = Error;
+
+    fn intercept<'a>(
+        &'a self,
+        request: Request<AsyncBody>,
+        ctx: Context<'a>,
+    ) -> InterceptorFuture<'a, Self::Err> {
+        Box::pin(async move {
+            if let Some(config) = request.extensions().get::<RequestConfig>() {
+                if let Some(budget) = config.retry_budget.as_ref() {
+                    let clock = config.clock.clone().unwrap_or_default();
+                    budget.deposit(request.uri(), clock.now());
+                }
+            }
+
+            ctx.send(request).await
+        })
+    }
+}
+
+/// Interceptor that retries requests according to the configured retry
+/// policy, if any.
+pub(crate) struct RetryInterceptor;
+
+impl Interceptor for RetryInterceptor {
+    type Err = Error;
+
+    fn intercept<'a>(
+        &'a self,
+        mut request: Request<AsyncBody>,
+        ctx: Context<'a>,
+    ) -> InterceptorFuture<'a, Self::Err> {
+        Box::pin(async move {
+            let config = request.extensions().get::<RequestConfig>();
+            let policy = match config.and_then(|config| config.retry_policy.clone()) {
+                Some(policy) => policy,
+                None => return ctx.send(request).await,
+            };
+            let budget = config.and_then(|config| config.retry_budget.clone());
+            let clock = config
+                .and_then(|config| config.clock.clone())
+                .unwrap_or_default();
+
+            let mut attempt = 1;
+
+            loop {
+                // Keep a copy of the request to send again, as long as more
+                // attempts are allowed and the body can be sent again.
+                let next = if attempt < policy.max_attempts() {
+                    request
+                        .body()
+                        .try_clone()
+                        .and_then(|body| request.to_builder().body(body).ok())
+                } else {
+                    None
+                };
+
+                let method = request.method().clone();
+                let uri = request.uri().clone();
+                let result = ctx.send(request).await;
+
+                let next = match next {
+                    Some(next) => next,
+                    None => return result,
+                };
+
+                let delay = match &result {
+                    Ok(response) if policy.is_retryable_status(&method, response.status()) => {
+                        match retry_after(&policy, response.headers()) {
+                            Some(delay) if delay > policy.max_backoff() => {
+                                tracing::debug!(
+                                    ?delay,
+                                    "server asked to retry later than the maximum backoff, not retrying"
+                                );
+                                return result;
+                            }
+                            Some(delay) => delay,
+                            None => policy.backoff_after(attempt, random()),
+                        }
+                    }
+                    Err(e) if is_retryable_error(&method, e) => policy.backoff_after(attempt, random()),
+                    _ => return result,
+                };
+
+                if let Some(budget) = budget.as_ref() {
+                    if !budget.try_withdraw(&uri, clock.now()) {
+                        return match result {
+                            Err(e) => Err(Error::new(ErrorKind::RetryBudgetExhausted, e)),
+                            response => response,
+                        };
+                    }
+                }
+
+                tracing::debug!(attempt, ?delay, "retrying request");
+
+                // Close the connection of a response we are not going to read
+                // before waiting.
+                drop(result);
+                sleep(&clock, delay).await;
+
+                request = next;
+                attempt += 1;
+            }
+        })
+    }
+}
+
+/// Check whether a request with the given method that failed with the given
+/// error should be retried.
+fn is_retryable_error(method: &http::Method, error: &Error) -> bool {
+    match error.kind() {
+        // The request was never sent, so it is always safe to send again.
+        ErrorKind::ConnectionFailed => true,
+        ErrorKind::Io => method.is_idempotent(),
+        _ => false,
+    }
+}
+
+/// Get the delay requested by the `Retry-After` header of a response, if any
+/// and if the policy honors it.
+fn retry_after(policy: &RetryPolicy, headers: &HeaderMap) -> Option<Duration> {
+    if !policy.honors_retry_after() {
+        return None;
+    }
+
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = httpdate::parse_http_date(value).ok()?;
+
+    Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+/// Wait for the given amount of time without blocking the current task.
+async fn sleep(clock: &SharedClock, duration: Duration) {
+    let (sender, receiver) = async_channel::bounded::<()>(1);
+    let clock = clock.clone();
+
+    thread::spawn(move || {
+        clock.sleep(duration);
+        drop(sender);
+    });
+
+    let _ = receiver.recv().await;
+}
+
+/// Get a random number between 0 and 1 for jittering backoffs.
+fn random() -> f64 {
+    let hasher = RandomState::new().build_hasher();
+
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_after_seconds() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, "120".parse().unwrap());
+
+        assert_eq!(
+            retry_after(&RetryPolicy::new(2), &headers),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            retry_after(&RetryPolicy::new(2).retry_after(false), &headers),
+            None
+        );
+    }
+
+    #[test]
+    fn retry_after_date() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            RETRY_AFTER,
+            httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(60))
+                .parse()
+                .unwrap(),
+        );
+
+        let delay = retry_after(&RetryPolicy::new(2), &headers).unwrap();
+
+        assert!(delay > Duration::from_secs(50) && delay <= Duration::from_secs(60));
+    }
+
+    #[test]
+    fn retry_after_date_in_past() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap());
+
+        assert_eq!(
+            retry_after(&RetryPolicy::new(2), &headers),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn random_is_in_range() {
+        for _ in 0..100 {
+            let n = random();
+            assert!((0.0..1.0).contains(&n));
+        }
+    }
+}
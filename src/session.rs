@@ -0,0 +1,177 @@
+//! Sequences of requests that share a single connection.
+
+use crate::{
+    body::AsyncBody,
+    client::{HttpClient, ResponseFuture},
+    error::{Error, ErrorKind},
+    response::ResponseExt,
+};
+use http::{Request, Response};
+use std::{fmt, net::SocketAddr};
+
+#[cfg(feature = "blocking")]
+use crate::body::Body;
+
+/// A sequence of requests that are all sent over the same connection.
+///
+/// Some stateful servers associate state with the connection a client is
+/// using rather than with cookies or headers, and so need every request in a
+/// conversation to arrive over the same connection. Benchmarks that measure
+/// the performance of a single connection have the same requirement.
+///
+/// A session owns a client of its own that never opens more than one
+/// connection at a time. The connection opened by the first request is kept
+/// open and reused for each following request, which must be to the same
+/// host. Requests are sent one at a time, and the response body of each
+/// request should be read to the end before sending the next request so that
+/// the connection can be reused.
+///
+/// If the connection is closed, such as by the server, a new connection would
+/// be needed for the next request. When this happens the request fails with
+/// an error of kind [`ErrorKind::ConnectionFailed`], and so will every request
+/// sent using the session afterwards. Start a new session to continue.
+///
+/// A session is created using
+/// [`HttpClientBuilder::build_session`](crate::HttpClientBuilder::build_session).
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{prelude::*, HttpClient, Request};
+///
+/// let mut session = HttpClient::builder().build_session()?;
+///
+/// let mut response = session.send(
+///     Request::post("http://legacy.example.org/login").body("user=jane")?,
+/// )?;
+/// response.consume()?;
+///
+/// let mut response = session.send(
+///     Request::get("http://legacy.example.org/account").body(())?,
+/// )?;
+/// println!("{}", response.text()?);
+/// # Ok::<(), isahc::Error>(())
+/// ```
+pub struct Session {
+    client: HttpClient,
+    connection: Option<Connection>,
+    lost: bool,
+}
+
+/// Identifies a connection by the addresses of its ends.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Connection {
+    local: SocketAddr,
+    remote: SocketAddr,
+}
+
+impl Session {
+    pub(crate) fn new(client: HttpClient) -> Self {
+        Self {
+            client,
+            connection: None,
+            lost: false,
+        }
+    }
+
+    /// Returns true if the connection of this session has been lost, in
+    /// which case any further requests will fail.
+    pub fn is_lost(&self) -> bool {
+        self.lost
+    }
+
+    /// Send an HTTP request over the session connection and return the HTTP
+    /// response.
+    ///
+    /// See [`HttpClient::send`] for details.
+    #[cfg(feature = "blocking")]
+    pub fn send<B>(&mut self, request: Request<B>) -> Result<Response<Body>, Error>
+    where
+        B: Into<Body>,
+    {
+        if self.lost {
+            return Err(lost_error());
+        }
+
+        let response = self.client.send(request)?;
+        self.check_connection(&response)?;
+
+        Ok(response)
+    }
+
+    /// Send an HTTP request over the session connection and return the HTTP
+    /// response asynchronously.
+    ///
+    /// See [`HttpClient::send_async`] for details.
+    pub fn send_async<B>(&mut self, request: Request<B>) -> ResponseFuture<'_>
+    where
+        B: Into<AsyncBody>,
+    {
+        let request = request.map(Into::into);
+
+        ResponseFuture::new(async move {
+            if self.lost {
+                return Err(lost_error());
+            }
+
+            let response = self.client.send_async(request).await?;
+            self.check_connection(&response)?;
+
+            Ok(response)
+        })
+    }
+
+    /// Make sure that a response was received over the session connection.
+    fn check_connection<T>(&mut self, response: &Response<T>) -> Result<(), Error> {
+        // Transports without socket addresses, such as Unix sockets, cannot
+        // be told apart.
+        let connection = match (response.local_addr(), response.remote_addr()) {
+            (Some(local), Some(remote)) => Connection {
+                local,
+                remote,
+            },
+            _ => return Ok(()),
+        };
+
+        match self.connection {
+            None => {
+                self.connection = Some(connection);
+                Ok(())
+            }
+            Some(expected) if expected == connection => Ok(()),
+            Some(expected) => {
+                tracing::debug!(
+                    ?expected,
+                    actual = ?connection,
+                    "session connection was lost",
+                );
+                self.lost = true;
+                Err(lost_error())
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Session")
+            .field("connection", &self.connection)
+            .field("lost", &self.lost)
+            .finish()
+    }
+}
+
+fn lost_error() -> Error {
+    Error::new(ErrorKind::ConnectionFailed, ConnectionLost)
+}
+
+#[derive(Debug)]
+struct ConnectionLost;
+
+impl fmt::Display for ConnectionLost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the connection of the session was lost")
+    }
+}
+
+impl std::error::Error for ConnectionLost {}
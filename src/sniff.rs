@@ -0,0 +1,292 @@
+//! Content type sniffing for responses with a missing or unreliable
+//! `Content-Type` header.
+//!
+//! The rules used here are a simplified version of the [MIME Sniffing
+//! Standard](https://mimesniff.spec.whatwg.org) used by web browsers. Only the
+//! first chunk of the response body is examined, so sniffing never delays
+//! delivery of the response.
+
+/// Response extension holding the sniffed content type of the response body.
+pub(crate) struct SniffedContentType(pub(crate) String);
+
+/// Maximum number of bytes examined at the start of the body.
+const SNIFF_LEN: usize = 1445;
+
+/// Signatures of well-known binary formats. A `None` byte in a pattern matches
+/// any byte.
+const SIGNATURES: &[(&[Option<u8>], &str)] = &[
+    (&bytes(b"GIF87a"), "image/gif"),
+    (&bytes(b"GIF89a"), "image/gif"),
+    (&bytes(b"\x89PNG\r\n\x1a\n"), "image/png"),
+    (&bytes(b"\xff\xd8\xff"), "image/jpeg"),
+    (
+        &[
+            Some(b'R'),
+            Some(b'I'),
+            Some(b'F'),
+            Some(b'F'),
+            None,
+            None,
+            None,
+            None,
+            Some(b'W'),
+            Some(b'E'),
+            Some(b'B'),
+            Some(b'P'),
+            Some(b'V'),
+            Some(b'P'),
+        ],
+        "image/webp",
+    ),
+    (&bytes(b"BM"), "image/bmp"),
+    (&bytes(b"\x00\x00\x01\x00"), "image/x-icon"),
+    (&bytes(b"%PDF-"), "application/pdf"),
+    (&bytes(b"%!PS-Adobe-"), "application/postscript"),
+    (&bytes(b"\x1f\x8b\x08"), "application/x-gzip"),
+    (&bytes(b"PK\x03\x04"), "application/zip"),
+    (&bytes(b"Rar!\x1a\x07\x00"), "application/x-rar-compressed"),
+    (&bytes(b"\x00asm"), "application/wasm"),
+    (&bytes(b"OggS\x00"), "application/ogg"),
+    (&bytes(b"ID3"), "audio/mpeg"),
+];
+
+/// Tags that indicate the start of an HTML document, when followed by a space
+/// or `>`.
+const HTML_TAGS: &[&[u8]] = &[
+    b"<!DOCTYPE HTML",
+    b"<HTML",
+    b"<HEAD",
+    b"<SCRIPT",
+    b"<IFRAME",
+    b"<H1",
+    b"<DIV",
+    b"<FONT",
+    b"<TABLE",
+    b"<A",
+    b"<STYLE",
+    b"<TITLE",
+    b"<B",
+    b"<BODY",
+    b"<BR",
+    b"<P",
+];
+
+/// Turn a literal byte string into an exact signature pattern.
+const fn bytes<const N: usize>(literal: &[u8; N]) -> [Option<u8>; N] {
+    let mut pattern = [None; N];
+    let mut i = 0;
+
+    while i < N {
+        pattern[i] = Some(literal[i]);
+        i += 1;
+    }
+
+    pattern
+}
+
+/// Determine the content type of a response body from the type declared by
+/// the server and the first chunk of the body.
+///
+/// Returns `None` if the declared type should be trusted.
+pub(crate) fn sniff_response(declared: Option<&str>, data: &[u8]) -> Option<String> {
+    let data = &data[..data.len().min(SNIFF_LEN)];
+
+    let essence = declared
+        .and_then(|declared| declared.split(';').next())
+        .map(|essence| essence.trim().to_ascii_lowercase())
+        .filter(|essence| essence.contains('/'));
+
+    match essence.as_deref() {
+        // No usable content type was given at all.
+        None | Some("unknown/unknown") | Some("application/unknown") | Some("*/*") => {
+            Some(sniff(data))
+        }
+
+        // Generic binary content may be something more specific.
+        Some("application/octet-stream") => sniff_signature(data).map(String::from),
+
+        // Servers often label all sorts of files as text. Only second-guess
+        // them if the content clearly is not text.
+        Some(essence) if is_textual(essence) && is_binary(data) => Some(
+            sniff_signature(data)
+                .unwrap_or("application/octet-stream")
+                .to_owned(),
+        ),
+
+        Some(_) => None,
+    }
+}
+
+/// Sniff the content type of data with no known type.
+fn sniff(data: &[u8]) -> String {
+    // A byte order mark is a strong indicator of text.
+    for &(bom, charset) in &[
+        (&b"\xef\xbb\xbf"[..], "utf-8"),
+        (&b"\xfe\xff"[..], "utf-16be"),
+        (&b"\xff\xfe"[..], "utf-16le"),
+    ] {
+        if data.starts_with(bom) {
+            return format!("text/plain; charset={}", charset);
+        }
+    }
+
+    let trimmed = trim_start(data);
+
+    if is_html(trimmed) {
+        return match find_meta_charset(data) {
+            Some(charset) => format!("text/html; charset={}", charset),
+            None => String::from("text/html"),
+        };
+    }
+
+    if trimmed.starts_with(b"<?xml") {
+        return String::from("text/xml");
+    }
+
+    if let Some(content_type) = sniff_signature(data) {
+        return String::from(content_type);
+    }
+
+    if is_binary(data) {
+        String::from("application/octet-stream")
+    } else if trimmed.starts_with(b"{") || trimmed.starts_with(b"[") {
+        String::from("application/json")
+    } else {
+        String::from("text/plain")
+    }
+}
+
+/// Match the data against signatures of known binary formats.
+fn sniff_signature(data: &[u8]) -> Option<&'static str> {
+    SIGNATURES
+        .iter()
+        .find(|(pattern, _)| {
+            data.len() >= pattern.len()
+                && pattern
+                    .iter()
+                    .zip(data)
+                    .all(|(expected, actual)| expected.is_none() || *expected == Some(*actual))
+        })
+        .map(|&(_, content_type)| content_type)
+}
+
+/// Returns true if the given MIME type essence describes text.
+fn is_textual(essence: &str) -> bool {
+    essence.starts_with("text/")
+        || essence.ends_with("/json")
+        || essence.ends_with("+json")
+        || essence.ends_with("/xml")
+        || essence.ends_with("+xml")
+        || essence == "application/javascript"
+}
+
+/// Returns true if the data contains bytes that never appear in text.
+fn is_binary(data: &[u8]) -> bool {
+    data.iter()
+        .any(|&b| matches!(b, 0x00..=0x08 | 0x0b | 0x0e..=0x1a | 0x1c..=0x1f))
+}
+
+fn is_html(data: &[u8]) -> bool {
+    if data.starts_with(b"<!--") {
+        return true;
+    }
+
+    HTML_TAGS.iter().any(|tag| {
+        data.len() > tag.len()
+            && data[..tag.len()].eq_ignore_ascii_case(tag)
+            && matches!(data[tag.len()], b' ' | b'>')
+    })
+}
+
+/// Look for a charset declared in a `<meta>` tag of an HTML document.
+fn find_meta_charset(data: &[u8]) -> Option<&str> {
+    const NEEDLE: &[u8] = b"charset=";
+
+    let start = data
+        .windows(NEEDLE.len())
+        .position(|window| window.eq_ignore_ascii_case(NEEDLE))?
+        + NEEDLE.len();
+
+    let value = &data[start..];
+    let value = match value.first() {
+        Some(b'"') | Some(b'\'') => &value[1..],
+        _ => value,
+    };
+
+    let len = value
+        .iter()
+        .position(|&b| !(b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b':' | b'.')))
+        .unwrap_or(value.len());
+
+    match std::str::from_utf8(&value[..len]) {
+        Ok(charset) if !charset.is_empty() => Some(charset),
+        _ => None,
+    }
+}
+
+fn trim_start(data: &[u8]) -> &[u8] {
+    let start = data
+        .iter()
+        .position(|&b| !matches!(b, b'\t' | b'\n' | b'\x0c' | b'\r' | b' '))
+        .unwrap_or(data.len());
+
+    &data[start..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR", "image/png")]
+    #[test_case(b"GIF89a\x01\x00\x01\x00", "image/gif")]
+    #[test_case(b"RIFF\x24\x00\x00\x00WEBPVP8 ", "image/webp")]
+    #[test_case(b"%PDF-1.7\n", "application/pdf")]
+    #[test_case(b"PK\x03\x04\x14\x00", "application/zip")]
+    #[test_case(b"  <!DOCTYPE html><html>", "text/html")]
+    #[test_case(b"<html><head><meta charset=\"windows-1252\">", "text/html; charset=windows-1252")]
+    #[test_case(b"<?xml version=\"1.0\"?>", "text/xml")]
+    #[test_case(b"\xef\xbb\xbfhello", "text/plain; charset=utf-8")]
+    #[test_case(b"\n{\"hello\": \"world\"}", "application/json")]
+    #[test_case(b"hello world", "text/plain")]
+    #[test_case(b"\x00\x01\x02\x03", "application/octet-stream")]
+    fn sniff_without_content_type(data: &[u8], expected: &str) {
+        assert_eq!(sniff_response(None, data).as_deref(), Some(expected));
+    }
+
+    #[test]
+    fn html_tag_must_be_terminated() {
+        assert_eq!(sniff(b"<Android>"), "text/plain");
+        assert_eq!(sniff(b"<a href=\"/\">"), "text/html");
+    }
+
+    #[test]
+    fn declared_content_type_is_trusted_when_plausible() {
+        assert_eq!(sniff_response(Some("text/css"), b"body {}"), None);
+        assert_eq!(sniff_response(Some("image/png"), b"not a png"), None);
+        assert_eq!(
+            sniff_response(Some("application/octet-stream"), b"hello"),
+            None
+        );
+    }
+
+    #[test]
+    fn declared_content_type_is_corrected_when_wrong() {
+        assert_eq!(
+            sniff_response(Some("text/plain; charset=utf-8"), b"\x89PNG\r\n\x1a\n\x00"),
+            Some(String::from("image/png"))
+        );
+        assert_eq!(
+            sniff_response(Some("application/json"), b"\x00\x00\x00\x07"),
+            Some(String::from("application/octet-stream"))
+        );
+        assert_eq!(
+            sniff_response(Some("application/octet-stream"), b"%PDF-1.4"),
+            Some(String::from("application/pdf"))
+        );
+        assert_eq!(
+            sniff_response(Some("garbage"), b"hello"),
+            Some(String::from("text/plain"))
+        );
+    }
+}
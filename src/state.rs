@@ -0,0 +1,292 @@
+//! Exporting and importing the knowledge a client accumulates over time.
+
+use crate::{config::RedirectCache, dns_negative_cache::DnsNegativeCache, error::ErrorKind};
+use http::{
+    header::{HeaderName, HeaderValue},
+    HeaderMap,
+};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant, SystemTime};
+
+/// A snapshot of the state an [`HttpClient`](crate::HttpClient) has learned
+/// while sending requests, which can be persisted and restored into another
+/// client, for example across restarts of a long-running process.
+///
+/// A client state includes:
+///
+/// - Unexpired cookies in the client's cookie jar, if the
+///   [`cookies`](index.html#cookies) feature is enabled and the client has a
+///   cookie jar.
+/// - Permanent redirects in the client's
+///   [`RedirectCache`](crate::config::RedirectCache).
+/// - Hosts in the client's negative DNS cache, if one is enabled using
+///   [`HttpClientBuilder::dns_negative_cache`](crate::HttpClientBuilder::dns_negative_cache).
+///
+/// Note that libcurl's own HSTS and Alt-Svc caches are not exposed by the
+/// underlying curl bindings, and are not part of the state.
+///
+/// The state implements [`Serialize`] and [`Deserialize`] so that it can be
+/// stored in any format supported by serde. Expiration times are stored as
+/// wall clock times, so entries that expire while the state is stored are
+/// discarded when it is imported.
+///
+/// # Availability
+///
+/// This type is only available when the [`state`](index.html#state) feature
+/// is enabled.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{ClientState, HttpClient};
+///
+/// let client = HttpClient::new()?;
+/// // Send some requests...
+///
+/// let saved = serde_json::to_string(&client.export_state())?;
+///
+/// // Later, perhaps in a new process:
+/// let client = HttpClient::new()?;
+/// client.import_state(serde_json::from_str::<ClientState>(&saved)?);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ClientState {
+    #[serde(default)]
+    cookies: Vec<CookieEntry>,
+
+    #[serde(default)]
+    redirects: Vec<RedirectEntry>,
+
+    #[serde(default)]
+    dns_failures: Vec<DnsFailureEntry>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct CookieEntry {
+    name: String,
+    value: String,
+    domain: String,
+    host_only: bool,
+    path: String,
+    secure: bool,
+    expires: Option<SystemTime>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct RedirectEntry {
+    source: String,
+    target: String,
+
+    /// The request headers the redirect varies by, along with the values it
+    /// was cached for.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    vary: Vec<VaryEntry>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct VaryEntry {
+    name: String,
+    value: Option<String>,
+}
+
+impl RedirectEntry {
+    /// Get request headers selecting this redirect and the names of the
+    /// headers it varies by, or `None` if any of them are invalid.
+    fn vary_headers(&self) -> Option<(HeaderMap, Vec<HeaderName>)> {
+        let mut headers = HeaderMap::new();
+        let mut names = Vec::with_capacity(self.vary.len());
+
+        for entry in &self.vary {
+            let name = HeaderName::from_bytes(entry.name.as_bytes()).ok()?;
+
+            if let Some(value) = entry.value.as_ref() {
+                headers.append(name.clone(), HeaderValue::from_str(value).ok()?);
+            }
+
+            names.push(name);
+        }
+
+        Some((headers, names))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct DnsFailureEntry {
+    host: String,
+    kind: DnsFailureKind,
+    expires: SystemTime,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum DnsFailureKind {
+    NameResolution,
+    Timeout,
+}
+
+impl ClientState {
+    /// Returns true if the state contains nothing to import.
+    pub fn is_empty(&self) -> bool {
+        self.cookies.is_empty() && self.redirects.is_empty() && self.dns_failures.is_empty()
+    }
+
+    pub(crate) fn export(
+        #[cfg(feature = "cookies")] cookie_jar: Option<&crate::cookies::CookieJar>,
+        redirect_cache: Option<&RedirectCache>,
+        dns_negative_cache: Option<&DnsNegativeCache>,
+        now: Instant,
+    ) -> Self {
+        let mut state = Self::default();
+
+        #[cfg(feature = "cookies")]
+        {
+            if let Some(jar) = cookie_jar {
+                state.cookies = jar
+                    .entries()
+                    .into_iter()
+                    .map(|(domain, path, cookie)| CookieEntry {
+                        name: cookie.name().to_owned(),
+                        value: cookie.value().to_owned(),
+                        domain,
+                        host_only: cookie.domain().is_none(),
+                        path,
+                        secure: cookie.is_secure(),
+                        expires: cookie.expiration(),
+                    })
+                    .collect();
+            }
+        }
+
+        if let Some(cache) = redirect_cache {
+            state.redirects = cache
+                .variant_entries()
+                .into_iter()
+                .filter_map(|(source, variant)| {
+                    // Variants selected by header values that are not valid
+                    // strings cannot be stored, and are skipped.
+                    let vary = variant
+                        .vary()
+                        .iter()
+                        .map(|name| {
+                            let value = match variant.request_header(name) {
+                                Some(value) => Some(value.to_str().ok()?.to_owned()),
+                                None => None,
+                            };
+
+                            Some(VaryEntry {
+                                name: name.as_str().to_owned(),
+                                value,
+                            })
+                        })
+                        .collect::<Option<Vec<_>>>()?;
+
+                    Some(RedirectEntry {
+                        source: source.to_string(),
+                        target: variant.target().to_string(),
+                        vary,
+                    })
+                })
+                .collect();
+        }
+
+        if let Some(cache) = dns_negative_cache {
+            let system_now = SystemTime::now();
+
+            state.dns_failures = cache
+                .entries(now)
+                .into_iter()
+                .filter_map(|(host, kind, remaining)| {
+                    let kind = match kind {
+                        ErrorKind::NameResolution => DnsFailureKind::NameResolution,
+                        ErrorKind::Timeout => DnsFailureKind::Timeout,
+                        _ => return None,
+                    };
+
+                    Some(DnsFailureEntry {
+                        host,
+                        kind,
+                        expires: system_now + remaining,
+                    })
+                })
+                .collect();
+        }
+
+        state
+    }
+
+    pub(crate) fn import(
+        self,
+        #[cfg(feature = "cookies")] cookie_jar: Option<&crate::cookies::CookieJar>,
+        redirect_cache: Option<&RedirectCache>,
+        dns_negative_cache: Option<&DnsNegativeCache>,
+        now: Instant,
+    ) {
+        #[cfg(feature = "cookies")]
+        {
+            if let Some(jar) = cookie_jar {
+                for entry in self.cookies {
+                    let mut builder = crate::cookies::Cookie::builder(entry.name, entry.value)
+                        .path(entry.path.clone())
+                        .secure(entry.secure);
+
+                    if !entry.host_only {
+                        builder = builder.domain(entry.domain.clone());
+                    }
+
+                    if let Some(expires) = entry.expires {
+                        builder = builder.expiration(expires);
+                    }
+
+                    match builder.build() {
+                        Ok(cookie) => jar.insert_entry(entry.domain, entry.path, cookie),
+                        Err(e) => tracing::warn!("skipping invalid cookie in client state: {}", e),
+                    }
+                }
+            }
+        }
+
+        if let Some(cache) = redirect_cache {
+            // Entries are stored from least to most recently used, so
+            // inserting them in order preserves their recency.
+            for entry in self.redirects {
+                match (
+                    entry.source.parse(),
+                    entry.target.parse(),
+                    entry.vary_headers(),
+                ) {
+                    (Ok(source), Ok(target), Some((headers, vary))) => {
+                        cache.insert(&source, &headers, &vary, target)
+                    }
+                    _ => tracing::warn!(
+                        "skipping invalid redirect in client state: {} -> {}",
+                        entry.source,
+                        entry.target
+                    ),
+                }
+            }
+        }
+
+        if let Some(cache) = dns_negative_cache {
+            let system_now = SystemTime::now();
+
+            for entry in self.dns_failures {
+                let remaining = entry
+                    .expires
+                    .duration_since(system_now)
+                    .unwrap_or(Duration::ZERO);
+
+                if remaining.is_zero() {
+                    continue;
+                }
+
+                let kind = match entry.kind {
+                    DnsFailureKind::NameResolution => ErrorKind::NameResolution,
+                    DnsFailureKind::Timeout => ErrorKind::Timeout,
+                };
+
+                cache.insert_for(entry.host, kind, remaining, now);
+            }
+        }
+    }
+}
@@ -0,0 +1,277 @@
+//! Helpers for following a remote resource that is only ever appended to,
+//! such as a log file, over HTTP.
+//!
+//! A [`Tail`] is created with [`HttpClient::tail`]. It periodically asks the
+//! server for everything after the last byte it has seen using a `Range`
+//! header, and returns the newly appended bytes as they show up.
+//!
+//! # Availability
+//!
+//! This module is only available when the [`blocking`](../index.html#blocking)
+//! feature is enabled.
+
+use crate::{
+    download::parse_content_range,
+    error::{Error, ErrorKind},
+    HttpClient,
+};
+use bytes::Bytes;
+use http::{
+    header::{HeaderName, HeaderValue, CONTENT_RANGE, ETAG, RANGE},
+    HeaderMap,
+    Request,
+    Response,
+    StatusCode,
+    Uri,
+};
+use std::{fmt, io::Read, thread, time::Duration};
+
+/// Default amount of time to wait between polling for new bytes.
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A follower of a remote append-only resource, returning the bytes appended
+/// to it as they show up.
+///
+/// `Tail` is an iterator that never ends. Each call to `next` blocks until
+/// new bytes have been appended to the resource, polling the server at the
+/// configured [interval](Tail::interval) in the meantime.
+///
+/// The resource is assumed to have been rotated, such as by a log rotation
+/// job, when it becomes shorter than the bytes seen so far, or when it is
+/// replaced by a resource of the same length with a different `ETag`. A
+/// [`TailEvent::Rotated`] event is then returned, and following starts over
+/// from the beginning of the new resource.
+///
+/// Errors, including responses with an unsuccessful status code, are returned
+/// from the iterator as well. Calling `next` again afterwards retries after
+/// waiting for the interval, so it is up to the caller to decide which errors
+/// are worth retrying.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{tail::TailEvent, HttpClient};
+/// use std::io::{stdout, Write};
+///
+/// let client = HttpClient::new()?;
+///
+/// for event in client.tail("https://example.org/app.log") {
+///     match event? {
+///         TailEvent::Appended(bytes) => stdout().write_all(&bytes)?,
+///         TailEvent::Rotated => eprintln!("log was rotated"),
+///         _ => {}
+///     }
+/// }
+/// # Ok::<(), isahc::Error>(())
+/// ```
+pub struct Tail {
+    client: HttpClient,
+    uri: Result<Uri, Error>,
+    offset: u64,
+    etag: Option<HeaderValue>,
+    interval: Duration,
+    headers: HeaderMap,
+    wait: bool,
+}
+
+/// A change to a resource followed by a [`Tail`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum TailEvent {
+    /// Bytes were appended to the resource.
+    Appended(Bytes),
+
+    /// The resource was rotated. Any bytes returned afterwards are from the
+    /// beginning of the new resource.
+    Rotated,
+}
+
+impl Tail {
+    pub(crate) fn new(client: HttpClient, uri: Result<Uri, Error>) -> Self {
+        Self {
+            client,
+            uri,
+            offset: 0,
+            etag: None,
+            interval: DEFAULT_INTERVAL,
+            headers: HeaderMap::new(),
+            wait: false,
+        }
+    }
+
+    /// Start following from the given offset in the resource, skipping the
+    /// bytes before it. By default the resource is followed from the
+    /// beginning.
+    ///
+    /// This can be used to continue following where an earlier `Tail` left
+    /// off, as given by [`Tail::offset`].
+    #[must_use = "builders have no effect if unused"]
+    pub fn resume_from(mut self, offset: u64) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Set how long to wait between polling the server for new bytes. The
+    /// default is one second.
+    #[must_use = "builders have no effect if unused"]
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Add a header to include in the polling requests.
+    #[must_use = "builders have no effect if unused"]
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    /// Get the offset in the resource that the next bytes returned will start
+    /// at, which is the number of bytes of the current resource seen so far.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Poll the server once for bytes appended after the current offset.
+    fn poll(&mut self) -> Result<Option<TailEvent>, Error> {
+        let mut builder = Request::get(self.uri.clone()?)
+            .header(RANGE, format!("bytes={}-", self.offset));
+
+        if let Some(headers) = builder.headers_mut() {
+            headers.extend(self.headers.clone());
+        }
+
+        let mut response = self.client.send(builder.body(())?)?;
+        let etag = strong_etag(&response);
+
+        match response.status() {
+            StatusCode::PARTIAL_CONTENT => {
+                let start = response
+                    .headers()
+                    .get(CONTENT_RANGE)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(parse_content_range)
+                    .and_then(|(start, _)| start);
+
+                if start != Some(self.offset) {
+                    return Err(Error::with_response(ErrorKind::ProtocolViolation, &response));
+                }
+
+                let mut bytes = Vec::new();
+                response.body_mut().read_to_end(&mut bytes)?;
+
+                self.etag = etag;
+                Ok(self.advance(bytes))
+            }
+
+            // Nothing was appended if the offset is exactly the length of the
+            // resource, unless it was replaced by a different resource of the
+            // same length.
+            StatusCode::RANGE_NOT_SATISFIABLE => {
+                let total_len = response
+                    .headers()
+                    .get(CONTENT_RANGE)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(parse_content_range)
+                    .and_then(|(_, total_len)| total_len)
+                    .ok_or_else(|| Error::with_response(ErrorKind::ProtocolViolation, &response))?;
+
+                let replaced = match (&self.etag, &etag) {
+                    (Some(previous), Some(current)) => previous != current,
+                    _ => false,
+                };
+
+                if total_len < self.offset || (total_len == self.offset && replaced) {
+                    Ok(Some(self.rotate()))
+                } else if total_len == self.offset {
+                    if etag.is_some() {
+                        self.etag = etag;
+                    }
+
+                    Ok(None)
+                } else {
+                    Err(Error::with_response(ErrorKind::ProtocolViolation, &response))
+                }
+            }
+
+            // The server does not support ranges and sent the entire
+            // resource, so skip the bytes we already have.
+            StatusCode::OK => {
+                let mut bytes = Vec::new();
+                response.body_mut().read_to_end(&mut bytes)?;
+
+                if (bytes.len() as u64) < self.offset {
+                    return Ok(Some(self.rotate()));
+                }
+
+                bytes.drain(..self.offset as usize);
+                self.etag = etag;
+                Ok(self.advance(bytes))
+            }
+
+            _ => Err(Error::with_response(ErrorKind::BadStatus, &response)),
+        }
+    }
+
+    fn advance(&mut self, bytes: Vec<u8>) -> Option<TailEvent> {
+        if bytes.is_empty() {
+            return None;
+        }
+
+        self.offset += bytes.len() as u64;
+
+        Some(TailEvent::Appended(bytes.into()))
+    }
+
+    fn rotate(&mut self) -> TailEvent {
+        tracing::debug!(offset = self.offset, "followed resource was rotated");
+
+        self.offset = 0;
+        self.etag = None;
+
+        TailEvent::Rotated
+    }
+}
+
+impl Iterator for Tail {
+    type Item = Result<TailEvent, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.wait {
+                thread::sleep(self.interval);
+            }
+
+            let result = self.poll().transpose();
+
+            // Start over right away after a rotation, since the new resource
+            // may already have bytes in it.
+            self.wait = !matches!(result, Some(Ok(TailEvent::Rotated)));
+
+            if result.is_some() {
+                return result;
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Tail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tail")
+            .field("uri", &self.uri)
+            .field("offset", &self.offset)
+            .field("etag", &self.etag)
+            .field("interval", &self.interval)
+            .finish()
+    }
+}
+
+/// Get the strong `ETag` of a response, if any. Weak entity tags may stay the
+/// same when the resource changes, so they cannot be used to detect rotation.
+fn strong_etag<T>(response: &Response<T>) -> Option<HeaderValue> {
+    response
+        .headers()
+        .get(ETAG)
+        .filter(|etag| !etag.as_bytes().starts_with(b"W/"))
+        .cloned()
+}
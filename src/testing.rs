@@ -0,0 +1,1282 @@
+//! Utilities for testing code that uses Isahc.
+
+use crate::{
+    config::{Clock, Dialer},
+    AsyncBody,
+};
+use http::{Request, Response};
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// A [`Clock`] that only moves forward when told to, for tests of time-based
+/// behavior that should not have to wait in real time.
+///
+/// Sleeping on a test clock advances it by the amount of time slept and
+/// returns immediately. Cloning a test clock produces a handle to the same
+/// clock, so a test can keep a clone to advance time after giving one to a
+/// client.
+///
+/// # Examples
+///
+/// ```
+/// use isahc::{testing::TestClock, HttpClient};
+/// use std::time::Duration;
+///
+/// let clock = TestClock::new();
+///
+/// let client = HttpClient::builder()
+///     .clock(clock.clone())
+///     .dns_negative_cache(Duration::from_secs(30))
+///     .build()?;
+///
+/// // Cached DNS failures expire without the test waiting 30 seconds.
+/// clock.advance(Duration::from_secs(30));
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct TestClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl Default for TestClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestClock {
+    /// Create a new test clock starting at the current time.
+    pub fn new() -> Self {
+        Self {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Move the clock forward by the given amount of time.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl Clock for TestClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+/// Records the exact requests sent to it, for snapshot tests of code that
+/// builds HTTP requests, such as API clients.
+///
+/// A recorder listens on a local port and answers every request with a
+/// canned response, `200 OK` with an empty body unless configured otherwise.
+/// Point a client at it with [`RequestRecorder::dialer`], and requests to any
+/// URI are sent to the recorder instead, so that the `Host` header and request
+/// target stay the same as in production.
+///
+/// Each request received is captured as a [`RecordedRequest`], which can be
+/// compared against a snapshot of its expected serialized form.
+///
+/// # Examples
+///
+/// ```
+/// use isahc::{prelude::*, testing::RequestRecorder, HttpClient, Request};
+///
+/// let recorder = RequestRecorder::new()?;
+///
+/// let client = HttpClient::builder()
+///     .dial(recorder.dialer())
+///     .build()?;
+///
+/// client.send(
+///     Request::post("http://api.example.org/users")
+///         .header("content-type", "application/json")
+///         .body(r#"{"name":"jane"}"#)?,
+/// )?;
+///
+/// recorder.last_request().unwrap().assert_snapshot(r#"
+///     POST /users HTTP/1.1
+///     Host: api.example.org
+///     Accept: */*
+///     Accept-Encoding: deflate, gzip
+///     content-type: application/json
+///     Content-Length: 15
+///
+///     {"name":"jane"}
+/// "#);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct RequestRecorder {
+    addr: SocketAddr,
+    shared: Arc<RecorderShared>,
+}
+
+#[derive(Debug)]
+struct RecorderShared {
+    requests: Mutex<Vec<RecordedRequest>>,
+    response: Mutex<(u16, Vec<u8>)>,
+    upstream: Mutex<Option<SocketAddr>>,
+    ignored_headers: Mutex<Vec<String>>,
+    started: Instant,
+    closed: AtomicBool,
+}
+
+impl RequestRecorder {
+    /// Start a new recorder listening on a local port.
+    pub fn new() -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let shared = Arc::new(RecorderShared {
+            requests: Mutex::new(Vec::new()),
+            response: Mutex::new((200, Vec::new())),
+            upstream: Mutex::new(None),
+            ignored_headers: Mutex::new(vec!["user-agent".into()]),
+            started: Instant::now(),
+            closed: AtomicBool::new(false),
+        });
+
+        thread::Builder::new()
+            .name("isahc-request-recorder".into())
+            .spawn({
+                let shared = shared.clone();
+
+                move || {
+                    for stream in listener.incoming() {
+                        if shared.closed.load(Ordering::SeqCst) {
+                            break;
+                        }
+
+                        if let Ok(stream) = stream {
+                            let shared = shared.clone();
+
+                            thread::spawn(move || {
+                                if let Err(e) = shared.serve(stream) {
+                                    tracing::debug!("request recorder connection failed: {}", e);
+                                }
+                            });
+                        }
+                    }
+                }
+            })?;
+
+        Ok(Self { addr, shared })
+    }
+
+    /// Set the response to answer requests with from now on.
+    ///
+    /// The response always has a `Content-Length` header and no other
+    /// headers.
+    pub fn respond_with(&self, status: u16, body: impl Into<Vec<u8>>) {
+        *self.shared.response.lock().unwrap() = (status, body.into());
+    }
+
+    /// Forward requests received from now on to a live server at the given
+    /// address, and answer them with its responses instead of a canned
+    /// response.
+    ///
+    /// Requests are forwarded byte for byte over plain TCP, and each response
+    /// is recorded along with its request. This can be used to record a
+    /// session against a real server, to later [`Replay`] it against another
+    /// deployment of the same server.
+    pub fn forward_to(&self, upstream: SocketAddr) {
+        *self.shared.upstream.lock().unwrap() = Some(upstream);
+    }
+
+    /// Leave the given header out of snapshots of requests recorded from now
+    /// on.
+    ///
+    /// By default, only `User-Agent` is left out, since it contains the
+    /// versions of Isahc and curl. Header names are not case sensitive.
+    pub fn ignore_header(&self, name: impl Into<String>) {
+        self.shared
+            .ignored_headers
+            .lock()
+            .unwrap()
+            .push(name.into().to_ascii_lowercase());
+    }
+
+    /// Get a dialer that connects to this recorder, for use with
+    /// [`Configurable::dial`](crate::config::Configurable::dial).
+    pub fn dialer(&self) -> Dialer {
+        Dialer::ip_socket(self.addr)
+    }
+
+    /// Get the address the recorder is listening on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Get all requests recorded so far, in the order they were received.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.shared.requests.lock().unwrap().clone()
+    }
+
+    /// Get the most recent request recorded, if any.
+    pub fn last_request(&self) -> Option<RecordedRequest> {
+        self.shared.requests.lock().unwrap().last().cloned()
+    }
+}
+
+impl Drop for RequestRecorder {
+    fn drop(&mut self) {
+        self.shared.closed.store(true, Ordering::SeqCst);
+
+        // Wake up the listener thread so that it notices.
+        let _ = TcpStream::connect(self.addr);
+    }
+}
+
+impl RecorderShared {
+    fn serve(&self, stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = stream;
+
+        while let Some(mut request) = read_request_head(&mut reader)? {
+            request.offset = self.started.elapsed();
+
+            let expect_continue = request
+                .header("expect")
+                .map_or(false, |value| value.eq_ignore_ascii_case("100-continue"));
+
+            if expect_continue {
+                writer.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+            }
+
+            read_request_body(&mut reader, &mut request)?;
+            request.ignored_headers = self.ignored_headers.lock().unwrap().clone();
+
+            let upstream = *self.upstream.lock().unwrap();
+            let (response, raw) = match upstream {
+                Some(upstream) => send_raw(upstream, &request)?,
+                None => {
+                    let (status, body) = self.response.lock().unwrap().clone();
+
+                    canned_response(status, body)
+                }
+            };
+
+            request.response = Some(response);
+            self.requests.lock().unwrap().push(request);
+
+            writer.write_all(&raw)?;
+            writer.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A request captured by a [`RequestRecorder`], exactly as it was sent.
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    method: String,
+    target: String,
+    version: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    raw: Vec<u8>,
+    offset: Duration,
+    response: Option<RecordedResponse>,
+    ignored_headers: Vec<String>,
+}
+
+impl RecordedRequest {
+    /// Get the request method.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// Get the request target, usually the path and query string.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Get the headers of the request, in the order they were sent and with
+    /// the names spelled as they were sent.
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// Get the value of the first header with the given name, if any. Header
+    /// names are not case sensitive.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get the request body. If the body was sent using chunked encoding, the
+    /// chunks are joined together.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Get the request exactly as it was received, including the framing of
+    /// the body.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.raw
+    }
+
+    /// Get when the request was received, relative to when the recorder was
+    /// started.
+    pub fn offset(&self) -> Duration {
+        self.offset
+    }
+
+    /// Get the response the recorder answered the request with.
+    pub fn response(&self) -> Option<&RecordedResponse> {
+        self.response.as_ref()
+    }
+
+    /// Render the request as text for comparing against a snapshot.
+    ///
+    /// The snapshot is the request line, followed by each header that is not
+    /// ignored in the order sent, a blank line, and the body. A body that is
+    /// not valid UTF-8 is written with non-printable bytes escaped.
+    pub fn snapshot(&self) -> String {
+        let mut snapshot = format!("{} {} {}\n", self.method, self.target, self.version);
+
+        for (name, value) in &self.headers {
+            if !self.ignored_headers.contains(&name.to_ascii_lowercase()) {
+                snapshot.push_str(&format!("{}: {}\n", name, value));
+            }
+        }
+
+        snapshot.push('\n');
+
+        match std::str::from_utf8(&self.body) {
+            Ok(body) => snapshot.push_str(body),
+            Err(_) => snapshot.extend(
+                self.body
+                    .iter()
+                    .flat_map(|&byte| std::ascii::escape_default(byte))
+                    .map(char::from),
+            ),
+        }
+
+        snapshot
+    }
+
+    /// Assert that the request matches the given snapshot, panicking with
+    /// both versions if it does not.
+    ///
+    /// To allow snapshots to be written as indented multi-line string
+    /// literals, leading and trailing blank lines and the indentation common
+    /// to all lines of the expected snapshot are removed before comparing.
+    #[track_caller]
+    pub fn assert_snapshot(&self, expected: &str) {
+        let actual = self.snapshot();
+        let expected = dedent(expected);
+
+        if actual.trim_end() != expected {
+            panic!(
+                "request does not match snapshot\n\nexpected:\n{}\n\nactual:\n{}\n",
+                expected,
+                actual.trim_end()
+            );
+        }
+    }
+}
+
+impl fmt::Display for RecordedRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.snapshot())
+    }
+}
+
+/// A response sent for a [`RecordedRequest`], or received when replaying it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecordedResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl RecordedResponse {
+    /// Get the status code of the response.
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// Get the headers of the response, in the order they were sent and with
+    /// the names spelled as they were sent.
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// Get the value of the first header with the given name, if any. Header
+    /// names are not case sensitive.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get the response body. If the body was sent using chunked encoding,
+    /// the chunks are joined together.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+}
+
+/// Compares two responses and describes how they differ, for regression and
+/// contract tests.
+///
+/// Responses are compared by status code, headers and body. Headers are
+/// compared by name regardless of order between different names, but values
+/// of the same header must appear in the same order. The `Date` header is
+/// ignored by default, since it almost never matches.
+///
+/// If both bodies are valid JSON and the [`json`](../index.html#json) feature
+/// is enabled, the bodies are compared as JSON values, ignoring formatting and
+/// the order of object keys, and each differing value is reported with its
+/// path. Other bodies are compared byte for byte.
+///
+/// # Examples
+///
+/// ```
+/// use isahc::{http::Response, testing::ResponseDiff};
+///
+/// let expected = Response::builder().status(200).header("etag", "1").body("hello")?;
+/// let actual = Response::builder().status(200).header("etag", "2").body("hello")?;
+///
+/// let diff = ResponseDiff::new().compare(&expected, &actual);
+/// assert_eq!(diff.to_string(), "header `etag`: expected [\"1\"], got [\"2\"]\n");
+///
+/// ResponseDiff::new()
+///     .ignore_header("etag")
+///     .compare(&expected, &actual)
+///     .assert_empty();
+/// # Ok::<(), isahc::http::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct ResponseDiff {
+    ignored_headers: Vec<String>,
+}
+
+impl Default for ResponseDiff {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResponseDiff {
+    /// Create a new comparison that only ignores the `Date` header.
+    pub fn new() -> Self {
+        Self {
+            ignored_headers: vec!["date".into()],
+        }
+    }
+
+    /// Ignore the given header when comparing responses, such as a header
+    /// containing a request ID. Header names are not case sensitive.
+    #[must_use = "builders have no effect if unused"]
+    pub fn ignore_header(mut self, name: impl Into<String>) -> Self {
+        self.ignored_headers.push(name.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Compare an actual response against the expected one.
+    pub fn compare<A, B>(&self, expected: &Response<A>, actual: &Response<B>) -> Diff
+    where
+        A: AsRef<[u8]>,
+        B: AsRef<[u8]>,
+    {
+        self.compare_parts(Parts::from_response(expected), Parts::from_response(actual))
+    }
+
+    fn compare_parts(&self, expected: Parts<'_>, actual: Parts<'_>) -> Diff {
+        let mut differences = Vec::new();
+
+        if expected.status != actual.status {
+            differences.push(Difference::Status {
+                expected: expected.status,
+                actual: actual.status,
+            });
+        }
+
+        let mut names = expected
+            .headers
+            .iter()
+            .chain(&actual.headers)
+            .map(|(name, _)| name.clone())
+            .filter(|name| !self.ignored_headers.contains(name))
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+
+        for name in names {
+            let expected = expected.header_values(&name);
+            let actual = actual.header_values(&name);
+
+            if expected != actual {
+                differences.push(Difference::Header {
+                    name,
+                    expected,
+                    actual,
+                });
+            }
+        }
+
+        if expected.body != actual.body
+            && !diff_json(expected.body, actual.body, &mut differences)
+        {
+            differences.push(Difference::Body {
+                offset: expected
+                    .body
+                    .iter()
+                    .zip(actual.body)
+                    .position(|(a, b)| a != b)
+                    .unwrap_or_else(|| expected.body.len().min(actual.body.len())),
+                expected_len: expected.body.len(),
+                actual_len: actual.body.len(),
+            });
+        }
+
+        Diff {
+            differences,
+        }
+    }
+}
+
+/// The parts of a response that are compared, with lowercase header names.
+struct Parts<'a> {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: &'a [u8],
+}
+
+impl<'a> Parts<'a> {
+    fn from_response<T: AsRef<[u8]>>(response: &'a Response<T>) -> Self {
+        Self {
+            status: response.status().as_u16(),
+            headers: response
+                .headers()
+                .iter()
+                .map(|(name, value)| {
+                    (name.as_str().to_owned(), String::from_utf8_lossy(value.as_bytes()).into())
+                })
+                .collect(),
+            body: response.body().as_ref(),
+        }
+    }
+
+    fn from_recorded(response: &'a RecordedResponse) -> Self {
+        Self {
+            status: response.status,
+            headers: response
+                .headers
+                .iter()
+                .map(|(name, value)| (name.to_ascii_lowercase(), value.clone()))
+                .collect(),
+            body: &response.body,
+        }
+    }
+
+    fn header_values(&self, name: &str) -> Vec<String> {
+        self.headers
+            .iter()
+            .filter(|(header, _)| header == name)
+            .map(|(_, value)| value.clone())
+            .collect()
+    }
+}
+
+/// Compare two bodies as JSON if both are valid JSON, adding a difference for
+/// each differing value. Returns false if they are not both JSON.
+#[cfg(feature = "json")]
+fn diff_json(expected: &[u8], actual: &[u8], differences: &mut Vec<Difference>) -> bool {
+    fn diff(
+        path: &str,
+        expected: Option<&serde_json::Value>,
+        actual: Option<&serde_json::Value>,
+        differences: &mut Vec<Difference>,
+    ) {
+        use serde_json::Value;
+
+        match (expected, actual) {
+            (Some(Value::Object(expected)), Some(Value::Object(actual))) => {
+                let mut keys = expected.keys().chain(actual.keys()).collect::<Vec<_>>();
+                keys.sort();
+                keys.dedup();
+
+                for key in keys {
+                    let path = if !key.is_empty()
+                        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                    {
+                        format!("{}.{}", path, key)
+                    } else {
+                        format!("{}[{:?}]", path, key)
+                    };
+
+                    diff(&path, expected.get(key), actual.get(key), differences);
+                }
+            }
+            (Some(Value::Array(expected)), Some(Value::Array(actual))) => {
+                for i in 0..expected.len().max(actual.len()) {
+                    let path = format!("{}[{}]", path, i);
+
+                    diff(&path, expected.get(i), actual.get(i), differences);
+                }
+            }
+            _ if expected != actual => differences.push(Difference::Json {
+                path: path.to_owned(),
+                expected: expected.map(|value| value.to_string()),
+                actual: actual.map(|value| value.to_string()),
+            }),
+            _ => {}
+        }
+    }
+
+    match (
+        serde_json::from_slice::<serde_json::Value>(expected),
+        serde_json::from_slice::<serde_json::Value>(actual),
+    ) {
+        (Ok(expected), Ok(actual)) => {
+            diff("$", Some(&expected), Some(&actual), differences);
+            true
+        }
+        _ => false,
+    }
+}
+
+#[cfg(not(feature = "json"))]
+fn diff_json(_: &[u8], _: &[u8], _: &mut Vec<Difference>) -> bool {
+    false
+}
+
+/// The differences between two responses found by a [`ResponseDiff`].
+///
+/// Displaying a diff lists each difference on its own line.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Diff {
+    differences: Vec<Difference>,
+}
+
+impl Diff {
+    /// Returns true if the responses are the same.
+    pub fn is_empty(&self) -> bool {
+        self.differences.is_empty()
+    }
+
+    /// Get each way the responses differ.
+    pub fn differences(&self) -> &[Difference] {
+        &self.differences
+    }
+
+    /// Assert that the responses are the same, panicking with the differences
+    /// if they are not.
+    #[track_caller]
+    pub fn assert_empty(&self) {
+        if !self.is_empty() {
+            panic!("responses differ:\n{}", self);
+        }
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for difference in &self.differences {
+            writeln!(f, "{}", difference)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A single way two responses differ.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Difference {
+    /// The status codes differ.
+    Status {
+        /// The expected status code.
+        expected: u16,
+        /// The actual status code.
+        actual: u16,
+    },
+
+    /// The values of a header differ. A missing header has no values.
+    Header {
+        /// The lowercase name of the header.
+        name: String,
+        /// The expected values of the header, in order.
+        expected: Vec<String>,
+        /// The actual values of the header, in order.
+        actual: Vec<String>,
+    },
+
+    /// A value in JSON bodies differs. A missing value is `None`.
+    Json {
+        /// The path of the value, such as `$.users[0].name`.
+        path: String,
+        /// The expected value, serialized as JSON.
+        expected: Option<String>,
+        /// The actual value, serialized as JSON.
+        actual: Option<String>,
+    },
+
+    /// The bodies differ, and are not both JSON.
+    Body {
+        /// The offset of the first byte that differs.
+        offset: usize,
+        /// The length of the expected body.
+        expected_len: usize,
+        /// The length of the actual body.
+        actual_len: usize,
+    },
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Status { expected, actual } => {
+                write!(f, "status: expected {}, got {}", expected, actual)
+            }
+            Self::Header {
+                name,
+                expected,
+                actual,
+            } => write!(f, "header `{}`: expected {:?}, got {:?}", name, expected, actual),
+            Self::Json {
+                path,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "body at `{}`: expected {}, got {}",
+                path,
+                expected.as_deref().unwrap_or("nothing"),
+                actual.as_deref().unwrap_or("nothing")
+            ),
+            Self::Body {
+                offset,
+                expected_len,
+                actual_len,
+            } => write!(
+                f,
+                "body: differs at byte {} (expected {} bytes, got {} bytes)",
+                offset, expected_len, actual_len
+            ),
+        }
+    }
+}
+
+/// Re-sends recorded requests byte for byte to a live server, and compares
+/// the responses against the recorded ones.
+///
+/// This is useful for regression testing an API after a deployment: record a
+/// session against a known good deployment using
+/// [`RequestRecorder::forward_to`], then replay it against the new one and
+/// check that nothing changed.
+///
+/// Requests are sent over plain TCP in the order given, each on a new
+/// connection. Responses are compared as described for [`ResponseDiff`],
+/// additionally ignoring any headers passed to [`Replay::ignore_header`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::testing::{RecordedRequest, Replay};
+///
+/// # let recording: Vec<RecordedRequest> = Vec::new();
+/// let results = Replay::new("127.0.0.1:8080".parse()?)
+///     .ignore_header("etag")
+///     .run(&recording)?;
+///
+/// for result in results.iter().filter(|result| !result.matches()) {
+///     println!("{} {} changed:", result.request().method(), result.request().target());
+///
+///     print!("{}", result.diff());
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Replay {
+    addr: SocketAddr,
+    preserve_timing: bool,
+    diff: ResponseDiff,
+}
+
+impl Replay {
+    /// Create a replay against the server at the given address.
+    pub fn new(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            preserve_timing: false,
+            diff: ResponseDiff::new(),
+        }
+    }
+
+    /// Wait between requests for as long as passed between them when they
+    /// were recorded, instead of sending each request as soon as the
+    /// previous one is answered. Disabled by default.
+    #[must_use = "builders have no effect if unused"]
+    pub fn preserve_timing(mut self, preserve: bool) -> Self {
+        self.preserve_timing = preserve;
+        self
+    }
+
+    /// Ignore the given response header when comparing responses, such as a
+    /// header containing a request ID. Header names are not case sensitive.
+    #[must_use = "builders have no effect if unused"]
+    pub fn ignore_header(mut self, name: impl Into<String>) -> Self {
+        self.diff = self.diff.ignore_header(name);
+        self
+    }
+
+    /// Send the given requests in order and compare their responses.
+    ///
+    /// Returns an error if a request cannot be sent or its response cannot be
+    /// read. Requests that were recorded without a response are still sent,
+    /// and any response counts as a match for them.
+    pub fn run(&self, requests: &[RecordedRequest]) -> io::Result<Vec<ReplayedRequest>> {
+        let started = Instant::now();
+        let first_offset = requests.first().map(RecordedRequest::offset).unwrap_or_default();
+        let mut results = Vec::with_capacity(requests.len());
+
+        for request in requests {
+            if self.preserve_timing {
+                let due = started + request.offset.saturating_sub(first_offset);
+                let now = Instant::now();
+
+                if due > now {
+                    thread::sleep(due - now);
+                }
+            }
+
+            let (response, _) = send_raw(self.addr, request)?;
+            let diff = match request.response.as_ref() {
+                Some(expected) => self
+                    .diff
+                    .compare_parts(Parts::from_recorded(expected), Parts::from_recorded(&response)),
+                None => Diff::default(),
+            };
+
+            results.push(ReplayedRequest {
+                request: request.clone(),
+                response,
+                diff,
+            });
+        }
+
+        Ok(results)
+    }
+}
+
+/// The result of replaying a single [`RecordedRequest`] with a [`Replay`].
+#[derive(Clone, Debug)]
+pub struct ReplayedRequest {
+    request: RecordedRequest,
+    response: RecordedResponse,
+    diff: Diff,
+}
+
+impl ReplayedRequest {
+    /// Get the request that was replayed.
+    pub fn request(&self) -> &RecordedRequest {
+        &self.request
+    }
+
+    /// Get the response received when replaying the request.
+    pub fn response(&self) -> &RecordedResponse {
+        &self.response
+    }
+
+    /// Returns true if the response matches the recorded one.
+    pub fn matches(&self) -> bool {
+        self.diff.is_empty()
+    }
+
+    /// Get how the response differs from the recorded one.
+    pub fn diff(&self) -> &Diff {
+        &self.diff
+    }
+}
+
+/// Send a recorded request exactly as it was received to a server on a new
+/// connection, returning the response and its raw bytes.
+fn send_raw(
+    addr: SocketAddr,
+    request: &RecordedRequest,
+) -> io::Result<(RecordedResponse, Vec<u8>)> {
+    let mut stream = TcpStream::connect(addr)?;
+    stream.write_all(&request.raw)?;
+    stream.flush()?;
+
+    read_response(&mut BufReader::new(stream), &request.method)
+}
+
+/// Build a response with the given status and body and only a
+/// `Content-Length` header, returning it and its raw bytes.
+fn canned_response(status: u16, body: Vec<u8>) -> (RecordedResponse, Vec<u8>) {
+    let mut raw = format!(
+        "HTTP/1.1 {} {}\r\nContent-Length: {}\r\n\r\n",
+        status,
+        http::StatusCode::from_u16(status)
+            .ok()
+            .and_then(|status| status.canonical_reason())
+            .unwrap_or_default(),
+        body.len()
+    )
+    .into_bytes();
+    raw.extend_from_slice(&body);
+
+    let response = RecordedResponse {
+        status,
+        headers: vec![("Content-Length".into(), body.len().to_string())],
+        body,
+    };
+
+    (response, raw)
+}
+
+/// Read a line into the given buffer, also appending it to the raw bytes read
+/// so far. Returns the number of bytes read.
+fn read_raw_line(
+    reader: &mut impl BufRead,
+    line: &mut String,
+    raw: &mut Vec<u8>,
+) -> io::Result<usize> {
+    line.clear();
+
+    let len = reader.read_line(line)?;
+    raw.extend_from_slice(line.as_bytes());
+
+    Ok(len)
+}
+
+/// Read header lines up to and including the blank line ending them.
+fn read_headers(
+    reader: &mut impl BufRead,
+    raw: &mut Vec<u8>,
+) -> io::Result<Vec<(String, String)>> {
+    let mut headers = Vec::new();
+    let mut line = String::new();
+
+    loop {
+        read_raw_line(reader, &mut line, raw)?;
+
+        let header = line.trim_end_matches(&['\r', '\n'][..]);
+
+        if header.is_empty() {
+            return Ok(headers);
+        }
+
+        match header.split_once(':') {
+            Some((name, value)) => headers.push((name.to_owned(), value.trim().to_owned())),
+            None => return Err(io::Error::new(io::ErrorKind::InvalidData, "malformed header")),
+        }
+    }
+}
+
+/// Read the request line and headers of the next request on a connection,
+/// or `None` if the connection was closed.
+fn read_request_head(reader: &mut impl BufRead) -> io::Result<Option<RecordedRequest>> {
+    let mut line = String::new();
+    let mut raw = Vec::new();
+
+    if read_raw_line(reader, &mut line, &mut raw)? == 0 {
+        return Ok(None);
+    }
+
+    let mut parts = line.trim_end().splitn(3, ' ');
+    let (method, target, version) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(method), Some(target), Some(version)) => (method, target, version),
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "malformed request line")),
+    };
+
+    let mut request = RecordedRequest {
+        method: method.to_owned(),
+        target: target.to_owned(),
+        version: version.to_owned(),
+        headers: Vec::new(),
+        body: Vec::new(),
+        raw: Vec::new(),
+        offset: Duration::default(),
+        response: None,
+        ignored_headers: Vec::new(),
+    };
+
+    request.headers = read_headers(reader, &mut raw)?;
+    request.raw = raw;
+
+    Ok(Some(request))
+}
+
+/// Read the body of a request whose head has already been read.
+fn read_request_body(reader: &mut impl BufRead, request: &mut RecordedRequest) -> io::Result<()> {
+    request.body = read_body(reader, &request.headers, false, &mut request.raw)?;
+
+    Ok(())
+}
+
+/// Read the status line, headers and body of a response to a request with the
+/// given method, skipping any informational responses. Returns the response
+/// and the raw bytes of the final response.
+fn read_response(
+    reader: &mut impl BufRead,
+    method: &str,
+) -> io::Result<(RecordedResponse, Vec<u8>)> {
+    loop {
+        let mut line = String::new();
+        let mut raw = Vec::new();
+
+        if read_raw_line(reader, &mut line, &mut raw)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let status = line
+            .split(' ')
+            .nth(1)
+            .and_then(|status| status.parse::<u16>().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed status line"))?;
+        let headers = read_headers(reader, &mut raw)?;
+
+        if (100..200).contains(&status) {
+            continue;
+        }
+
+        let no_body = method.eq_ignore_ascii_case("HEAD") || status == 204 || status == 304;
+        let body = if no_body {
+            Vec::new()
+        } else {
+            read_body(reader, &headers, true, &mut raw)?
+        };
+
+        return Ok((
+            RecordedResponse {
+                status,
+                headers,
+                body,
+            },
+            raw,
+        ));
+    }
+}
+
+/// Read a message body framed as described by the given headers, appending
+/// the raw bytes read. If there is no framing, a response body extends to the
+/// end of the connection while a request has no body.
+fn read_body(
+    reader: &mut impl BufRead,
+    headers: &[(String, String)],
+    is_response: bool,
+    raw: &mut Vec<u8>,
+) -> io::Result<Vec<u8>> {
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    };
+
+    let mut body = Vec::new();
+
+    let chunked = header("transfer-encoding")
+        .map_or(false, |value| value.eq_ignore_ascii_case("chunked"));
+
+    if chunked {
+        let mut line = String::new();
+
+        loop {
+            read_raw_line(reader, &mut line, raw)?;
+
+            let size = line.trim().split(';').next().unwrap_or_default();
+            let size = usize::from_str_radix(size, 16)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "malformed chunk size"))?;
+
+            if size == 0 {
+                // Skip any trailers.
+                loop {
+                    if read_raw_line(reader, &mut line, raw)? == 0 || line.trim().is_empty() {
+                        return Ok(body);
+                    }
+                }
+            }
+
+            let start = body.len();
+            reader.take(size as u64).read_to_end(&mut body)?;
+            raw.extend_from_slice(&body[start..]);
+
+            // Skip the line ending after the chunk.
+            read_raw_line(reader, &mut line, raw)?;
+        }
+    }
+
+    if let Some(len) = header("content-length") {
+        let len = len
+            .parse()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "malformed content length"))?;
+
+        reader.take(len).read_to_end(&mut body)?;
+    } else if is_response {
+        reader.read_to_end(&mut body)?;
+    }
+
+    raw.extend_from_slice(&body);
+
+    Ok(body)
+}
+
+/// Remove leading and trailing blank lines and the indentation common to all
+/// non-blank lines.
+fn dedent(s: &str) -> String {
+    let lines = s
+        .lines()
+        .skip_while(|line| line.trim().is_empty())
+        .collect::<Vec<_>>();
+
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_end()
+        .to_owned()
+}
+
+/// A function answering the requests of a client constructed with
+/// [`HttpClient::with_handler`](crate::HttpClient::with_handler) instead of
+/// sending them over the network.
+#[derive(Clone)]
+pub(crate) struct MockHandler(
+    pub(crate) Arc<dyn Fn(Request<AsyncBody>) -> Response<AsyncBody> + Send + Sync>,
+);
+
+impl fmt::Debug for MockHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MockHandler")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_only_passes_when_advanced() {
+        let clock = TestClock::new();
+        let start = clock.now();
+
+        assert_eq!(clock.now(), start);
+
+        clock.advance(Duration::from_secs(5));
+        clock.clone().sleep(Duration::from_secs(10));
+
+        assert_eq!(clock.now() - start, Duration::from_secs(15));
+    }
+
+    #[test]
+    fn dedent_snapshot() {
+        let snapshot = "
+            GET / HTTP/1.1
+              Indented: yes
+
+            body
+        ";
+
+        assert_eq!(dedent(snapshot), "GET / HTTP/1.1\n  Indented: yes\n\nbody");
+    }
+
+    #[test]
+    fn read_chunked_request() {
+        let bytes = &b"POST /upload HTTP/1.1\r\n\
+            Transfer-Encoding: chunked\r\n\
+            \r\n\
+            5\r\nhello\r\n\
+            6;ext=1\r\n world\r\n\
+            0\r\n\
+            Trailer: x\r\n\
+            \r\n"[..];
+        let mut input = bytes;
+
+        let mut request = read_request_head(&mut input).unwrap().unwrap();
+        read_request_body(&mut input, &mut request).unwrap();
+
+        assert_eq!(request.method(), "POST");
+        assert_eq!(request.target(), "/upload");
+        assert_eq!(request.header("TRANSFER-ENCODING"), Some("chunked"));
+        assert_eq!(request.body(), b"hello world");
+        assert_eq!(request.as_bytes(), bytes);
+        assert!(read_request_head(&mut input).unwrap().is_none());
+    }
+
+    #[test]
+    fn read_response_skips_informational() {
+        let mut input = &b"HTTP/1.1 100 Continue\r\n\
+            \r\n\
+            HTTP/1.1 200 OK\r\n\
+            Transfer-Encoding: chunked\r\n\
+            \r\n\
+            2\r\nok\r\n\
+            0\r\n\
+            \r\n"[..];
+
+        let (response, raw) = read_response(&mut input, "GET").unwrap();
+
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.header("transfer-encoding"), Some("chunked"));
+        assert_eq!(response.body(), b"ok");
+        assert_eq!(
+            raw,
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nok\r\n0\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn diff_bodies() {
+        let response = |body: &'static str| Response::new(body);
+        let diff = ResponseDiff::new().compare(&response("hello"), &response("help!"));
+
+        assert_eq!(
+            diff.differences(),
+            [Difference::Body {
+                offset: 3,
+                expected_len: 5,
+                actual_len: 5,
+            }]
+        );
+        assert!(ResponseDiff::new()
+            .compare(&response("same"), &response("same"))
+            .is_empty());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn diff_json_bodies() {
+        let expected = Response::new(r#"{"users": [{"name": "jane"}], "total": 1, "a-b": 0}"#);
+        let actual = Response::new(r#"{"total":1,"users":[{"name":"john"},{"name":"jim"}]}"#);
+        let diff = ResponseDiff::new().compare(&expected, &actual);
+
+        assert_eq!(
+            diff.to_string(),
+            "body at `$[\"a-b\"]`: expected 0, got nothing\n\
+             body at `$.users[0].name`: expected \"jane\", got \"john\"\n\
+             body at `$.users[1]`: expected nothing, got {\"name\":\"jim\"}\n"
+        );
+    }
+}
@@ -2,7 +2,7 @@
 
 #![cfg(feature = "text-decoding")]
 
-use crate::headers::HasHeaders;
+use crate::{headers::HasHeaders, ResponseExt};
 use encoding_rs::{CoderResult, Encoding};
 use futures_lite::io::{AsyncRead, AsyncReadExt};
 use http::Response;
@@ -55,20 +55,41 @@ impl Decoder {
         }
     }
 
+    /// Create a new decoder for the encoding with the given label, such as
+    /// `utf-8` or `iso-8859-1`.
+    pub(crate) fn for_label(label: &str) -> io::Result<Self> {
+        Encoding::for_label(label.trim().as_bytes())
+            .map(Self::new)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown character encoding: {}", label),
+                )
+            })
+    }
+
     /// Create a new encoder suitable for decoding the given response.
+    ///
+    /// A content type sniffed from the body takes precedence over the one
+    /// declared by the server, since it is only present if the declared one is
+    /// missing or wrong.
     pub(crate) fn for_response<T>(response: &Response<T>) -> Self {
-        if let Some(content_type) = response
-            .content_type()
-            .and_then(|header| header.parse::<mime::Mime>().ok())
-        {
-            if let Some(charset) = content_type.get_param(mime::CHARSET) {
-                if let Some(encoding) =
-                    encoding_rs::Encoding::for_label(charset.as_ref().as_bytes())
-                {
-                    return Self::new(encoding);
-                } else {
-                    tracing::warn!("unknown encoding '{}', falling back to UTF-8", charset);
-                }
+        let charset = response
+            .sniffed_content_type()
+            .into_iter()
+            .chain(response.content_type())
+            .filter_map(|content_type| content_type.parse::<mime::Mime>().ok())
+            .find_map(|content_type| {
+                content_type
+                    .get_param(mime::CHARSET)
+                    .map(|charset| charset.to_string())
+            });
+
+        if let Some(charset) = charset {
+            if let Some(encoding) = encoding_rs::Encoding::for_label(charset.as_bytes()) {
+                return Self::new(encoding);
+            } else {
+                tracing::warn!("unknown encoding '{}', falling back to UTF-8", charset);
             }
         }
 
@@ -76,6 +97,7 @@ impl Decoder {
     }
 
     /// Consume this decoder to decode text from a given synchronous reader.
+    #[cfg(feature = "blocking")]
     pub(crate) fn decode_reader(self, mut reader: impl io::Read) -> io::Result<String> {
         decode_reader!(self, buf, reader.read(buf))
     }
@@ -115,7 +137,7 @@ impl Decoder {
         }
 
         // If last is true, buf should always be fully consumed.
-        if cfg!(debug) && last {
+        if cfg!(debug_assertions) && last {
             assert_eq!(buf.len(), 0);
         }
 
@@ -126,9 +148,9 @@ impl Decoder {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::body::Body;
 
-    static_assertions::assert_impl_all!(TextFuture<'_, &mut Body>: Send);
+    #[cfg(feature = "blocking")]
+    static_assertions::assert_impl_all!(TextFuture<'_, &mut crate::body::Body>: Send);
 
     #[test]
     fn utf8_decode() {
@@ -150,4 +172,19 @@ mod tests {
 
         assert_eq!(decoder.finish(&[]), "hello world!");
     }
+
+    #[test]
+    fn decode_with_label() {
+        let decoder = Decoder::for_label(" ISO-8859-1 ").unwrap();
+
+        assert_eq!(decoder.finish(b"caf\xe9"), "café");
+    }
+
+    #[test]
+    fn unknown_label_is_rejected() {
+        match Decoder::for_label("klingon") {
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::InvalidInput),
+            Ok(_) => panic!("expected an error"),
+        }
+    }
 }
@@ -0,0 +1,487 @@
+//! Propagation of distributed tracing context to servers.
+//!
+//! When a client is configured with a [`Propagation`] format using
+//! [`HttpClientBuilder::trace_propagation`](crate::HttpClientBuilder::trace_propagation),
+//! every request it sends becomes a span in a distributed trace:
+//!
+//! - The parent of the span is the [`TraceContext`] attached to the request as
+//!   an extension, if any, or else the context that is
+//!   [current](TraceContext::current) on the thread sending the request. If
+//!   there is neither, the request starts a new trace.
+//! - The identifiers of the span are sent to the server in `traceparent` and
+//!   `tracestate` headers as specified by [W3C Trace
+//!   Context](https://www.w3.org/TR/trace-context/), in a `b3` header as
+//!   specified by [B3](https://github.com/openzipkin/b3-propagation), or both.
+//!   Headers already present in a request are left alone.
+//! - A client span named `http.client` is recorded with
+//!   [`tracing`](https://docs.rs/tracing), including the trace and span IDs,
+//!   so that a subscriber can export it to a tracing backend.
+//! - The context of the span is attached to the response as an extension.
+//!
+//! Each redirect followed is a separate span with the same parent.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use isahc::{
+//!     prelude::*,
+//!     trace_context::{Propagation, TraceContext},
+//!     HttpClient,
+//! };
+//!
+//! let client = HttpClient::builder()
+//!     .trace_propagation(Propagation::W3C)
+//!     .build()?;
+//!
+//! // Continue the trace of an incoming request.
+//! let parent = TraceContext::from_w3c(
+//!     "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+//!     None,
+//! ).unwrap();
+//! let _guard = parent.clone().attach();
+//!
+//! let response = client.get("https://example.org")?;
+//! let context = response.extensions().get::<TraceContext>().unwrap();
+//!
+//! assert_eq!(context.trace_id(), parent.trace_id());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::{
+    body::AsyncBody,
+    config::request::RequestConfig,
+    error::Error,
+    interceptor::{Context, Interceptor, InterceptorFuture},
+};
+use http::{header::HeaderName, HeaderValue, Request};
+use std::{
+    cell::RefCell,
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasher, Hasher},
+    marker::PhantomData,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use tracing_futures::Instrument;
+
+const TRACEPARENT: HeaderName = HeaderName::from_static("traceparent");
+const TRACESTATE: HeaderName = HeaderName::from_static("tracestate");
+const B3: HeaderName = HeaderName::from_static("b3");
+
+thread_local! {
+    /// Context attached to the current thread, if any.
+    #[allow(clippy::missing_const_for_thread_local)]
+    static CURRENT: RefCell<Option<TraceContext>> = RefCell::new(None);
+}
+
+/// Formats of headers used to propagate trace context to servers.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Propagation {
+    /// The `traceparent` and `tracestate` headers of [W3C Trace
+    /// Context](https://www.w3.org/TR/trace-context/).
+    W3C,
+
+    /// The single `b3` header of [B3](https://github.com/openzipkin/b3-propagation),
+    /// as used by Zipkin.
+    B3,
+
+    /// Both the W3C and the B3 headers.
+    W3CAndB3,
+}
+
+impl Propagation {
+    fn w3c(self) -> bool {
+        matches!(self, Self::W3C | Self::W3CAndB3)
+    }
+
+    fn b3(self) -> bool {
+        matches!(self, Self::B3 | Self::W3CAndB3)
+    }
+}
+
+/// The identity of a span within a distributed trace.
+#[derive(Clone, Eq, PartialEq)]
+pub struct TraceContext {
+    trace_id: u128,
+    span_id: u64,
+    parent_span_id: Option<u64>,
+    sampled: bool,
+    trace_state: Option<String>,
+}
+
+impl TraceContext {
+    /// Create a context for the root span of a new trace with random IDs.
+    ///
+    /// The trace is sampled.
+    pub fn new_root() -> Self {
+        Self {
+            trace_id: (u128::from(random_id()) << 64) | u128::from(random_id()),
+            span_id: random_id(),
+            parent_span_id: None,
+            sampled: true,
+            trace_state: None,
+        }
+    }
+
+    /// Parse a context from the values of W3C `traceparent` and `tracestate`
+    /// headers, such as those of an incoming request.
+    ///
+    /// Returns `None` if the `traceparent` value is not valid.
+    pub fn from_w3c(traceparent: &str, tracestate: Option<&str>) -> Option<Self> {
+        let mut parts = traceparent.trim().splitn(5, '-');
+
+        let version = parse_hex(parts.next()?, 2)? as u8;
+        let trace_id = parse_hex(parts.next()?, 32)?;
+        let span_id = parse_hex(parts.next()?, 16)? as u64;
+        let flags = parse_hex(parts.next()?, 2)? as u8;
+
+        // Version 255 is invalid, and version 0 has no further fields. Later
+        // versions may add fields, which are ignored.
+        if version == 0xff || (version == 0 && parts.next().is_some()) {
+            return None;
+        }
+
+        if trace_id == 0 || span_id == 0 {
+            return None;
+        }
+
+        Some(Self {
+            trace_id,
+            span_id,
+            parent_span_id: None,
+            sampled: flags & 1 == 1,
+            trace_state: tracestate
+                .map(str::trim)
+                .filter(|state| !state.is_empty())
+                .map(String::from),
+        })
+    }
+
+    /// Get the ID of the trace.
+    pub fn trace_id(&self) -> u128 {
+        self.trace_id
+    }
+
+    /// Get the ID of the span.
+    pub fn span_id(&self) -> u64 {
+        self.span_id
+    }
+
+    /// Get the ID of the parent of the span, if known.
+    pub fn parent_span_id(&self) -> Option<u64> {
+        self.parent_span_id
+    }
+
+    /// Check whether the trace is sampled, meaning that its spans are
+    /// expected to be recorded.
+    pub fn is_sampled(&self) -> bool {
+        self.sampled
+    }
+
+    /// Get the vendor-specific `tracestate` of the trace, if any.
+    pub fn trace_state(&self) -> Option<&str> {
+        self.trace_state.as_deref()
+    }
+
+    /// Set whether the trace is sampled.
+    pub fn with_sampled(mut self, sampled: bool) -> Self {
+        self.sampled = sampled;
+        self
+    }
+
+    /// Set the vendor-specific `tracestate` of the trace.
+    pub fn with_trace_state(mut self, trace_state: impl Into<String>) -> Self {
+        self.trace_state = Some(trace_state.into());
+        self
+    }
+
+    /// Get the value of a W3C `traceparent` header for this context.
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{:032x}-{:016x}-{:02x}",
+            self.trace_id,
+            self.span_id,
+            u8::from(self.sampled)
+        )
+    }
+
+    /// Get the value of a single B3 `b3` header for this context.
+    pub fn b3(&self) -> String {
+        let mut value = format!(
+            "{:032x}-{:016x}-{}",
+            self.trace_id,
+            self.span_id,
+            u8::from(self.sampled)
+        );
+
+        if let Some(parent_span_id) = self.parent_span_id {
+            value.push_str(&format!("-{:016x}", parent_span_id));
+        }
+
+        value
+    }
+
+    /// Get the context that is current on this thread, if any.
+    pub fn current() -> Option<Self> {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+
+    /// Make this context the current context on this thread until the
+    /// returned guard is dropped.
+    ///
+    /// Requests sent on this thread in the meantime become children of this
+    /// context, unless they have a context of their own attached. Attaching a
+    /// context while another is attached replaces it, and dropping the guard
+    /// restores the previous context.
+    ///
+    /// For asynchronous requests the current context is captured when calling
+    /// [`HttpClient::send_async`](crate::HttpClient::send_async), not when the
+    /// returned future is polled.
+    pub fn attach(self) -> ContextGuard {
+        let previous = CURRENT.with(|current| current.borrow_mut().replace(self));
+
+        ContextGuard {
+            previous,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Create a context for a new child span of this context.
+    fn child(&self) -> Self {
+        Self {
+            trace_id: self.trace_id,
+            span_id: random_id(),
+            parent_span_id: Some(self.span_id),
+            sampled: self.sampled,
+            trace_state: self.trace_state.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TraceContext")
+            .field("trace_id", &format_args!("{:032x}", self.trace_id))
+            .field("span_id", &format_args!("{:016x}", self.span_id))
+            .field("sampled", &self.sampled)
+            .field("trace_state", &self.trace_state)
+            .finish()
+    }
+}
+
+/// Guard returned by [`TraceContext::attach`] that restores the previous
+/// current context when dropped.
+#[must_use = "the context is detached again when the guard is dropped"]
+pub struct ContextGuard {
+    previous: Option<TraceContext>,
+    // The guard must be dropped on the thread it was created on.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+impl fmt::Debug for ContextGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ContextGuard")
+    }
+}
+
+/// Attach the current context of this thread to a request about to be sent,
+/// unless it has one already.
+pub(crate) fn capture<T>(mut request: Request<T>) -> Request<T> {
+    if request.extensions().get::<TraceContext>().is_none() {
+        if let Some(context) = TraceContext::current() {
+            request.extensions_mut().insert(context);
+        }
+    }
+
+    request
+}
+
+/// Generate a random, non-zero ID.
+fn random_id() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+
+    hasher.finish().max(1)
+}
+
+/// Parse a string of lowercase hexadecimal digits of an exact length.
+fn parse_hex(s: &str, len: usize) -> Option<u128> {
+    if s.len() != len || !s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+        return None;
+    }
+
+    u128::from_str_radix(s, 16).ok()
+}
+
+/// Interceptor that propagates trace context to servers and records client
+/// spans, if a request asks for it.
+pub(crate) struct TraceContextInterceptor;
+
+impl Interceptor for TraceContextInterceptor {
+    type Err = Error;
+
+    fn intercept<'a>(
+        &'a self,
+        mut request: Request<AsyncBody>,
+        ctx: Context<'a>,
+    ) -> InterceptorFuture<'a, Self::Err> {
+        Box::pin(async move {
+            let propagation = match request
+                .extensions()
+                .get::<RequestConfig>()
+                .and_then(|config| config.trace_propagation)
+            {
+                Some(propagation) => propagation,
+                None => return ctx.send(request).await,
+            };
+
+            let context = match request.extensions().get::<TraceContext>() {
+                Some(parent) => parent.child(),
+                None => TraceContext::new_root(),
+            };
+
+            let headers = request.headers_mut();
+
+            if propagation.w3c() && !headers.contains_key(TRACEPARENT) {
+                headers.insert(TRACEPARENT, header_value(context.traceparent()));
+
+                if let Some(state) = context.trace_state() {
+                    if let Ok(value) = HeaderValue::from_str(state) {
+                        headers.insert(TRACESTATE, value);
+                    }
+                }
+            }
+
+            if propagation.b3() && !headers.contains_key(B3) {
+                headers.insert(B3, header_value(context.b3()));
+            }
+
+            let span = tracing::debug_span!(
+                "http.client",
+                otel.kind = "client",
+                trace_id = %format_args!("{:032x}", context.trace_id),
+                span_id = %format_args!("{:016x}", context.span_id),
+                parent_span_id = ?context.parent_span_id.map(|id| format!("{:016x}", id)),
+                http.method = %request.method(),
+                http.url = %request.uri(),
+                http.status_code = tracing::field::Empty,
+                error = tracing::field::Empty,
+            );
+
+            let result = ctx.send(request).instrument(span.clone()).await;
+
+            match result {
+                Ok(mut response) => {
+                    span.record("http.status_code", response.status().as_u16());
+                    response.extensions_mut().insert(context);
+
+                    Ok(response)
+                }
+                Err(e) => {
+                    span.record("error", tracing::field::display(&e));
+
+                    Err(e)
+                }
+            }
+        })
+    }
+}
+
+fn header_value(value: String) -> HeaderValue {
+    // Formatted IDs are always valid header values.
+    HeaderValue::from_str(&value).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_traceparent() {
+        let context = TraceContext::from_w3c(
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            Some("congo=t61rcWkgMzE"),
+        )
+        .unwrap();
+
+        assert_eq!(context.trace_id(), 0x4bf92f3577b34da6a3ce929d0e0e4736);
+        assert_eq!(context.span_id(), 0x00f067aa0ba902b7);
+        assert!(context.is_sampled());
+        assert_eq!(context.trace_state(), Some("congo=t61rcWkgMzE"));
+        assert_eq!(
+            context.traceparent(),
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+        );
+    }
+
+    #[test]
+    fn invalid_traceparent() {
+        for value in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+        ] {
+            assert_eq!(TraceContext::from_w3c(value, None), None, "{}", value);
+        }
+
+        assert!(TraceContext::from_w3c(
+            "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            None
+        )
+        .is_some());
+    }
+
+    #[test]
+    fn child_keeps_trace_id() {
+        let parent = TraceContext::new_root().with_sampled(false);
+        let child = parent.child();
+
+        assert_eq!(child.trace_id(), parent.trace_id());
+        assert_ne!(child.span_id(), parent.span_id());
+        assert_eq!(child.parent_span_id(), Some(parent.span_id()));
+        assert!(!child.is_sampled());
+        assert_eq!(
+            child.b3(),
+            format!(
+                "{:032x}-{:016x}-0-{:016x}",
+                parent.trace_id(),
+                child.span_id(),
+                parent.span_id()
+            )
+        );
+    }
+
+    #[test]
+    fn attached_context_is_restored() {
+        let outer = TraceContext::new_root();
+        let inner = TraceContext::new_root();
+
+        assert_eq!(TraceContext::current(), None);
+
+        let outer_guard = outer.clone().attach();
+        assert_eq!(TraceContext::current(), Some(outer.clone()));
+
+        let inner_guard = inner.clone().attach();
+        assert_eq!(TraceContext::current(), Some(inner));
+
+        drop(inner_guard);
+        assert_eq!(TraceContext::current(), Some(outer));
+
+        drop(outer_guard);
+        assert_eq!(TraceContext::current(), None);
+    }
+}
@@ -0,0 +1,217 @@
+//! Helpers for executing a series of requests as a unit, undoing the effects
+//! of the requests that succeeded if a later one fails.
+//!
+//! HTTP has no transactions that span multiple requests, so workflows that
+//! need several calls to succeed together, such as creating a resource and
+//! then attaching it to another one, are usually made best-effort by
+//! compensating for the calls that already succeeded when a later call fails.
+//! A [`Transaction`] takes care of the bookkeeping for this.
+//!
+//! # Availability
+//!
+//! This module is only available when the [`blocking`](../index.html#blocking)
+//! feature is enabled.
+
+use crate::{
+    error::{Error, ErrorKind},
+    Body,
+    HttpClient,
+};
+use http::{Request, Response};
+use std::fmt;
+
+type Action<'a> =
+    Box<dyn FnOnce(&HttpClient, &mut [Response<Body>]) -> Result<Response<Body>, Error> + 'a>;
+
+type Compensation<'a> =
+    Box<dyn FnOnce(&HttpClient, &mut Response<Body>) -> Result<(), Error> + 'a>;
+
+/// A series of requests that are executed one after the other, where each
+/// request may have a compensation that undoes its effects.
+///
+/// The steps of a transaction are executed in the order they were added, and
+/// each step is given the responses of the steps before it so that it can
+/// build on their results. A step fails if it returns an error or a response
+/// with an unsuccessful status code. When a step fails, the remaining steps
+/// are skipped and the compensations of the steps that already succeeded are
+/// executed in reverse order.
+///
+/// Compensating is best-effort: if a compensation fails, the failure is
+/// logged and the remaining compensations are still executed. The error that
+/// failed the transaction is returned either way.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::{prelude::*, transaction::{Step, Transaction}};
+///
+/// let responses = Transaction::new()
+///     .step(
+///         Step::new(|client, _| client.put("https://example.org/users/alice", "{}"))
+///             .compensate(|client, _| {
+///                 client.delete("https://example.org/users/alice")?;
+///                 Ok(())
+///             }),
+///     )
+///     .step(Step::new(|client, responses| {
+///         let user = responses[0].text()?;
+///
+///         client.post("https://example.org/teams/admins/members", user)
+///     }))
+///     .execute()?;
+///
+/// assert_eq!(responses.len(), 2);
+/// # Ok::<(), isahc::Error>(())
+/// ```
+pub struct Transaction<'a> {
+    client: Option<HttpClient>,
+    steps: Vec<Step<'a>>,
+}
+
+/// A single step of a [`Transaction`].
+pub struct Step<'a> {
+    action: Action<'a>,
+    compensation: Option<Compensation<'a>>,
+}
+
+impl<'a> Step<'a> {
+    /// Create a step that executes the given action.
+    ///
+    /// The action is called with the client of the transaction and the
+    /// responses of the steps executed before it, and returns the response of
+    /// this step.
+    pub fn new<F>(action: F) -> Self
+    where
+        F: FnOnce(&HttpClient, &mut [Response<Body>]) -> Result<Response<Body>, Error> + 'a,
+    {
+        Self {
+            action: Box::new(action),
+            compensation: None,
+        }
+    }
+
+    /// Create a step that sends the given request.
+    pub fn request<B>(request: Request<B>) -> Self
+    where
+        B: Into<Body> + 'a,
+    {
+        Self::new(move |client, _| client.send(request))
+    }
+
+    /// Set a compensation that undoes the effects of this step, which is
+    /// executed if a later step of the transaction fails.
+    ///
+    /// The compensation is called with the client of the transaction and the
+    /// response of this step.
+    #[must_use = "builders have no effect if unused"]
+    pub fn compensate<F>(mut self, compensation: F) -> Self
+    where
+        F: FnOnce(&HttpClient, &mut Response<Body>) -> Result<(), Error> + 'a,
+    {
+        self.compensation = Some(Box::new(compensation));
+        self
+    }
+}
+
+impl fmt::Debug for Step<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Step")
+            .field("compensated", &self.compensation.is_some())
+            .finish()
+    }
+}
+
+impl<'a> Transaction<'a> {
+    /// Create a new empty transaction.
+    pub fn new() -> Self {
+        Self {
+            client: None,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Set the client to execute the transaction with. If not set, the same
+    /// default client used by [`isahc::send`](crate::send) is used.
+    #[must_use = "builders have no effect if unused"]
+    pub fn client(mut self, client: HttpClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Add a step to the end of the transaction.
+    #[must_use = "builders have no effect if unused"]
+    pub fn step(mut self, step: Step<'a>) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Execute the transaction, blocking until every step has succeeded or
+    /// the transaction has been rolled back.
+    ///
+    /// Returns the responses of every step in order. If a step fails, its
+    /// error is returned instead after the steps before it are compensated
+    /// for. A response with an unsuccessful status code is returned as an
+    /// error of kind [`ErrorKind::BadStatus`].
+    pub fn execute(self) -> Result<Vec<Response<Body>>, Error> {
+        let client = self.client.unwrap_or_else(|| HttpClient::shared().clone());
+        let mut responses = Vec::with_capacity(self.steps.len());
+        let mut compensations = Vec::with_capacity(self.steps.len());
+
+        for (index, step) in self.steps.into_iter().enumerate() {
+            let result = (step.action)(&client, &mut responses).and_then(|response| {
+                if response.status().is_success() {
+                    Ok(response)
+                } else {
+                    Err(Error::with_response(ErrorKind::BadStatus, &response))
+                }
+            });
+
+            match result {
+                Ok(response) => {
+                    responses.push(response);
+                    compensations.push(step.compensation);
+                }
+                Err(e) => {
+                    tracing::debug!(step = index, "transaction step failed, rolling back: {}", e);
+                    rollback(&client, responses, compensations);
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(responses)
+    }
+}
+
+impl Default for Transaction<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Transaction<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transaction")
+            .field("client", &self.client)
+            .field("steps", &self.steps)
+            .finish()
+    }
+}
+
+/// Execute the compensations of the steps that succeeded, from the last to the
+/// first.
+fn rollback(
+    client: &HttpClient,
+    responses: Vec<Response<Body>>,
+    compensations: Vec<Option<Compensation<'_>>>,
+) {
+    let steps = responses.into_iter().zip(compensations).enumerate().rev();
+
+    for (index, (mut response, compensation)) in steps {
+        if let Some(compensation) = compensation {
+            if let Err(e) = compensation(client, &mut response) {
+                tracing::warn!(step = index, "failed to compensate transaction step: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! Bounded log of the informational messages curl reports about a transfer.
+
+use std::{collections::VecDeque, fmt, time::Instant};
+
+/// Request headers whose values are not kept in the log, since errors are
+/// often logged or reported elsewhere.
+const REDACTED_HEADERS: &[&str] = &["authorization", "cookie", "proxy-authorization"];
+
+/// The kind of a [`TransferMessage`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum TransferMessageKind {
+    /// An informational message from curl, such as about resolving a host
+    /// name, connecting, or negotiating TLS.
+    Info,
+
+    /// A header line sent to the server.
+    HeaderOut,
+
+    /// A header line received from the server.
+    HeaderIn,
+}
+
+/// An informational message curl reported about a transfer.
+///
+/// The last messages of a failed transfer are available using
+/// [`Error::transfer_messages`](crate::Error::transfer_messages) when enabled
+/// with
+/// [`Configurable::transfer_messages`](crate::config::Configurable::transfer_messages).
+#[derive(Clone, Debug)]
+pub struct TransferMessage {
+    kind: TransferMessageKind,
+    text: String,
+    timestamp: Instant,
+}
+
+impl TransferMessage {
+    /// Get the kind of message.
+    pub fn kind(&self) -> TransferMessageKind {
+        self.kind
+    }
+
+    /// Get the text of the message, without a trailing line break.
+    ///
+    /// The values of headers containing credentials, such as `Authorization`
+    /// and `Cookie`, are replaced with `<redacted>`.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Get when curl reported the message.
+    pub fn timestamp(&self) -> Instant {
+        self.timestamp
+    }
+}
+
+/// Formats the message the way curl's verbose output does, prefixed with `*`,
+/// `>` or `<` depending on its kind.
+impl fmt::Display for TransferMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prefix = match self.kind {
+            TransferMessageKind::Info => '*',
+            TransferMessageKind::HeaderOut => '>',
+            TransferMessageKind::HeaderIn => '<',
+        };
+
+        write!(f, "{} {}", prefix, self.text)
+    }
+}
+
+/// Ring buffer of the most recent messages of a transfer.
+#[derive(Debug)]
+pub(crate) struct TransferLog {
+    capacity: usize,
+    messages: VecDeque<TransferMessage>,
+}
+
+impl TransferLog {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            messages: VecDeque::with_capacity(capacity.min(64)),
+        }
+    }
+
+    /// Record the data curl reported, which may contain multiple lines.
+    pub(crate) fn push(&mut self, kind: TransferMessageKind, data: &[u8]) {
+        let timestamp = Instant::now();
+
+        for line in String::from_utf8_lossy(data).lines() {
+            let line = line.trim_end();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let text = match (kind, line.split_once(':')) {
+                (TransferMessageKind::HeaderOut, Some((name, _)))
+                    if REDACTED_HEADERS
+                        .iter()
+                        .any(|redacted| name.trim().eq_ignore_ascii_case(redacted)) =>
+                {
+                    format!("{}: <redacted>", name)
+                }
+                _ => line.to_owned(),
+            };
+
+            if self.messages.len() == self.capacity {
+                self.messages.pop_front();
+            }
+
+            self.messages.push_back(TransferMessage {
+                kind,
+                text,
+                timestamp,
+            });
+        }
+    }
+
+    /// Take the messages recorded so far, oldest first.
+    pub(crate) fn take(&mut self) -> Vec<TransferMessage> {
+        self.messages.drain(..).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_most_recent_messages() {
+        let mut log = TransferLog::new(3);
+
+        log.push(TransferMessageKind::Info, b"Trying 127.0.0.1:80...\n");
+        log.push(
+            TransferMessageKind::HeaderOut,
+            b"GET / HTTP/1.1\r\nHost: example.org\r\nAuthorization: Bearer secret\r\n\r\n",
+        );
+
+        let messages = log.take();
+
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].to_string(), "> GET / HTTP/1.1");
+        assert_eq!(messages[1].to_string(), "> Host: example.org");
+        assert_eq!(messages[2].to_string(), "> Authorization: <redacted>");
+        assert!(log.take().is_empty());
+    }
+
+    #[test]
+    fn received_headers_are_kept_as_is() {
+        let mut log = TransferLog::new(8);
+
+        log.push(TransferMessageKind::HeaderIn, b"Set-Cookie: a=b\r\n");
+
+        assert_eq!(log.take()[0].text(), "Set-Cookie: a=b");
+    }
+}
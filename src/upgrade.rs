@@ -0,0 +1,114 @@
+//! Connections switched to another protocol with `101 Switching Protocols`.
+
+use futures_lite::io::{AsyncRead, AsyncWrite};
+use http::{Response, StatusCode};
+use sluice::pipe::PipeWriter;
+use std::{
+    fmt, io,
+    pin::Pin,
+    sync::Mutex,
+    task::{Context, Poll},
+};
+
+/// Writing half of an upgraded connection, attached to a `101` response.
+pub(crate) struct UpgradeWriter(Mutex<Option<PipeWriter>>);
+
+impl UpgradeWriter {
+    pub(crate) fn new(writer: PipeWriter) -> Self {
+        Self(Mutex::new(Some(writer)))
+    }
+}
+
+/// A connection that the server switched to another protocol, such as
+/// WebSocket, in response to a request with an `Upgrade` header.
+///
+/// Reading from the connection reads what the server sends, and writing to it
+/// sends to the server as is. Closing the connection for writing only stops
+/// sending; the server is not notified and reading continues until the server
+/// closes the connection. An `Upgraded` is created by [`ResponseExt::upgrade`](crate::ResponseExt::upgrade).
+///
+/// The connection implements [`AsyncRead`] and [`AsyncWrite`], and if the
+/// response body it was created from is synchronous, also
+/// [`Read`](std::io::Read) and [`Write`](std::io::Write).
+pub struct Upgraded<T> {
+    reader: T,
+    writer: PipeWriter,
+}
+
+impl<T> Upgraded<T> {
+    /// Take the upgraded connection out of a response, if the server agreed to
+    /// switch protocols.
+    pub(crate) fn from_response(mut response: Response<T>) -> Result<Self, Response<T>> {
+        if response.status() != StatusCode::SWITCHING_PROTOCOLS {
+            return Err(response);
+        }
+
+        let writer = response
+            .extensions_mut()
+            .remove::<UpgradeWriter>()
+            .and_then(|writer| writer.0.into_inner().unwrap());
+
+        match writer {
+            Some(writer) => Ok(Self {
+                reader: response.into_body(),
+                writer,
+            }),
+            None => Err(response),
+        }
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for Upgraded<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.reader).poll_read(cx, buf)
+    }
+}
+
+impl<T: Unpin> AsyncWrite for Upgraded<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.writer).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.writer).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.writer).poll_close(cx)
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<T: io::Read> io::Read for Upgraded<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<T: io::Read> io::Write for Upgraded<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        futures_lite::future::block_on(futures_lite::io::AsyncWriteExt::write(
+            &mut self.writer,
+            buf,
+        ))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        futures_lite::future::block_on(futures_lite::io::AsyncWriteExt::flush(&mut self.writer))
+    }
+}
+
+impl<T> fmt::Debug for Upgraded<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Upgraded").finish()
+    }
+}
@@ -0,0 +1,402 @@
+//! Helpers for reliably uploading large bodies over unreliable networks.
+//!
+//! Some servers allow an interrupted upload to be resumed from where it left
+//! off, rather than starting over from the beginning. A [`ResumableUpload`]
+//! takes care of asking the server how much of the body it has received after
+//! a failure, and re-sending only the remainder.
+//!
+//! # Availability
+//!
+//! This module is only available when the [`blocking`](../index.html#blocking)
+//! feature is enabled.
+
+use crate::{
+    error::{Error, ErrorKind},
+    Body,
+    HttpClient,
+};
+use http::{
+    header::{HeaderName, HeaderValue, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, RANGE},
+    HeaderMap,
+    Method,
+    Request,
+    Response,
+    StatusCode,
+    Uri,
+};
+use std::{
+    convert::TryFrom,
+    fmt,
+    io::{self, Read, Seek, SeekFrom},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+pub mod s3;
+
+#[cfg(feature = "tus")]
+pub mod tus;
+
+/// Default number of times an upload request may fail before giving up.
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+
+/// Default amount of time to wait after a failed attempt before resuming.
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Header used by the offset protocol to communicate the upload offset.
+const UPLOAD_OFFSET: &str = "upload-offset";
+
+/// The protocol used to negotiate where to resume an upload from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ResumeProtocol {
+    /// Upload using `PUT` requests with a `Content-Range` header.
+    ///
+    /// The offset to resume from is queried by sending an empty `PUT` request
+    /// with a `Content-Range: bytes */<length>` header. The server responds
+    /// with `308 Resume Incomplete` and a `Range` header indicating the bytes
+    /// received so far, or with a success status if the upload is already
+    /// complete. This is the protocol used by Google Cloud Storage and
+    /// several other services.
+    ContentRange,
+
+    /// Upload using `PATCH` requests with an `Upload-Offset` header.
+    ///
+    /// The offset to resume from is queried by sending a `HEAD` request and
+    /// reading the `Upload-Offset` response header. Each `PATCH` request
+    /// includes the offset its body starts at, and the server responds with
+    /// the new offset. This is the core of the
+    /// [tus](https://tus.io/protocols/resumable-upload.html) protocol.
+    UploadOffset,
+}
+
+/// An upload of a seekable body to a URI that supports resuming interrupted
+/// uploads.
+///
+/// If an upload request fails because of a network error, the server is asked
+/// how much of the body it has already received, and the upload continues from
+/// that offset after a short delay. Responses with an unexpected status code
+/// are returned to the caller as-is, so the final status should still be
+/// checked.
+///
+/// The upload location must already exist; how it is created differs between
+/// services.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::upload::{ResumableUpload, ResumeProtocol};
+/// use std::fs::File;
+///
+/// let file = File::open("large-video.mp4")?;
+///
+/// let response = ResumableUpload::new("https://example.org/uploads/1234", file)?
+///     .protocol(ResumeProtocol::ContentRange)
+///     .max_attempts(10)
+///     .send()?;
+///
+/// assert!(response.status().is_success());
+/// # Ok::<(), isahc::Error>(())
+/// ```
+pub struct ResumableUpload<R> {
+    client: Option<HttpClient>,
+    uri: Uri,
+    protocol: ResumeProtocol,
+    source: Arc<Mutex<R>>,
+    len: u64,
+    offset: Option<u64>,
+    headers: HeaderMap,
+    max_attempts: u32,
+    retry_delay: Duration,
+}
+
+/// Result of sending a single request of an upload.
+enum Progress {
+    /// The server has received this many bytes so far.
+    Offset(u64),
+
+    /// The upload is finished, or cannot continue. Either way, this is the
+    /// response to return to the caller.
+    Done(Response<Body>),
+}
+
+impl<R: Read + Seek + Send + 'static> ResumableUpload<R> {
+    /// Create a new upload of the given source to the given URI.
+    ///
+    /// The length of the upload is determined by seeking to the end of the
+    /// source.
+    pub fn new<U>(uri: U, mut source: R) -> Result<Self, Error>
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        let uri = Uri::try_from(uri).map_err(|e| Error::from(e.into()))?;
+        let len = source.seek(SeekFrom::End(0))?;
+
+        Ok(Self {
+            client: None,
+            uri,
+            protocol: ResumeProtocol::ContentRange,
+            source: Arc::new(Mutex::new(source)),
+            len,
+            offset: None,
+            headers: HeaderMap::new(),
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            retry_delay: DEFAULT_RETRY_DELAY,
+        })
+    }
+
+    /// Set the client to send upload requests with. If not set, the same
+    /// default client used by [`isahc::send`](crate::send) is used.
+    #[must_use = "builders have no effect if unused"]
+    pub fn client(mut self, client: HttpClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Set the protocol to use for resuming the upload. The default is
+    /// [`ResumeProtocol::ContentRange`].
+    #[must_use = "builders have no effect if unused"]
+    pub fn protocol(mut self, protocol: ResumeProtocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    /// Set the offset to start uploading from, if it is already known.
+    ///
+    /// By default the server is asked for the current offset before anything
+    /// is uploaded, which allows an upload started by a previous process to be
+    /// continued. If the upload is known to be new, setting the offset to zero
+    /// saves a round trip.
+    #[must_use = "builders have no effect if unused"]
+    pub fn start_offset(mut self, offset: u64) -> Self {
+        self.offset = Some(offset.min(self.len));
+        self
+    }
+
+    /// Add a header to include in every request sent for this upload.
+    #[must_use = "builders have no effect if unused"]
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    /// Set how many times uploading may fail before giving up and returning
+    /// the last error. The default is 5.
+    #[must_use = "builders have no effect if unused"]
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Set how long to wait after a failed attempt before resuming. The
+    /// default is one second.
+    #[must_use = "builders have no effect if unused"]
+    pub fn retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Get the total length of the upload in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns true if the body to upload is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Perform the upload, blocking until it completes or fails.
+    ///
+    /// Returns the final response from the server.
+    pub fn send(mut self) -> Result<Response<Body>, Error> {
+        let mut failures = 0;
+
+        loop {
+            let result = match self.offset {
+                Some(offset) => self.upload_from(offset),
+                None => self.query_offset(),
+            };
+
+            match result {
+                Ok(Progress::Done(response)) => return Ok(response),
+                Ok(Progress::Offset(offset)) => {
+                    // The server accepted only part of the body, which is fine
+                    // as long as the upload keeps moving forward.
+                    if matches!(self.offset, Some(previous) if offset <= previous) {
+                        failures += 1;
+
+                        if failures >= self.max_attempts {
+                            tracing::debug!("server is not accepting any more of the upload");
+                            return Err(Error::from(ErrorKind::ProtocolViolation));
+                        }
+                    }
+
+                    self.offset = Some(offset.min(self.len));
+                }
+                Err(e) if is_retryable(&e) && failures + 1 < self.max_attempts => {
+                    crate::retry::allow_retry(self.http_client(), &self.uri, &e)?;
+                    failures += 1;
+
+                    tracing::debug!(
+                        attempt = failures,
+                        "upload request failed, resuming after {:?}: {}",
+                        self.retry_delay,
+                        e
+                    );
+
+                    self.http_client().clock().sleep(self.retry_delay);
+
+                    // We don't know how much the server received before the
+                    // failure, so we need to ask it.
+                    self.offset = None;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Ask the server how much of the body it has received so far.
+    fn query_offset(&self) -> Result<Progress, Error> {
+        match self.protocol {
+            ResumeProtocol::ContentRange => {
+                let request = self
+                    .request(Method::PUT)
+                    .header(CONTENT_RANGE, format!("bytes */{}", self.len))
+                    .header(CONTENT_LENGTH, 0)
+                    .body(Body::empty())?;
+
+                let response = self.http_client().send(request)?;
+
+                Ok(content_range_progress(response))
+            }
+            ResumeProtocol::UploadOffset => {
+                let request = self.request(Method::HEAD).body(Body::empty())?;
+                let response = self.http_client().send(request)?;
+
+                Ok(upload_offset_progress(response, self.len))
+            }
+        }
+    }
+
+    /// Send the remainder of the body starting at the given offset.
+    fn upload_from(&self, offset: u64) -> Result<Progress, Error> {
+        self.source.lock().unwrap().seek(SeekFrom::Start(offset))?;
+
+        let remaining = self.len - offset;
+        let body = Body::from_reader_sized(SharedReader(self.source.clone()), remaining);
+
+        match self.protocol {
+            ResumeProtocol::ContentRange => {
+                let content_range = if remaining == 0 {
+                    format!("bytes */{}", self.len)
+                } else {
+                    format!("bytes {}-{}/{}", offset, self.len - 1, self.len)
+                };
+
+                let request = self
+                    .request(Method::PUT)
+                    .header(CONTENT_RANGE, content_range)
+                    .body(body)?;
+
+                let response = self.http_client().send(request)?;
+
+                Ok(content_range_progress(response))
+            }
+            ResumeProtocol::UploadOffset => {
+                let request = self
+                    .request(Method::PATCH)
+                    .header(CONTENT_TYPE, "application/offset+octet-stream")
+                    .header(UPLOAD_OFFSET, offset)
+                    .body(body)?;
+
+                let response = self.http_client().send(request)?;
+
+                Ok(upload_offset_progress(response, self.len))
+            }
+        }
+    }
+
+    fn request(&self, method: Method) -> http::request::Builder {
+        let mut builder = Request::builder().method(method).uri(self.uri.clone());
+
+        if let Some(headers) = builder.headers_mut() {
+            headers.extend(self.headers.clone());
+        }
+
+        builder
+    }
+
+    fn http_client(&self) -> &HttpClient {
+        self.client.as_ref().unwrap_or_else(|| HttpClient::shared())
+    }
+}
+
+impl<R> fmt::Debug for ResumableUpload<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResumableUpload")
+            .field("uri", &self.uri)
+            .field("protocol", &self.protocol)
+            .field("len", &self.len)
+            .field("offset", &self.offset)
+            .field("max_attempts", &self.max_attempts)
+            .field("retry_delay", &self.retry_delay)
+            .finish()
+    }
+}
+
+/// Interpret a response to a request using the `Content-Range` protocol.
+fn content_range_progress(response: Response<Body>) -> Progress {
+    if response.status() != StatusCode::PERMANENT_REDIRECT {
+        return Progress::Done(response);
+    }
+
+    // A missing range header means nothing has been received yet.
+    let offset = response
+        .headers()
+        .get(RANGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("bytes=0-"))
+        .and_then(|last| last.trim().parse::<u64>().ok())
+        .map(|last| last + 1)
+        .unwrap_or(0);
+
+    Progress::Offset(offset)
+}
+
+/// Interpret a response to a request using the `Upload-Offset` protocol for
+/// an upload of the given length.
+fn upload_offset_progress(response: Response<Body>, len: u64) -> Progress {
+    if !response.status().is_success() {
+        return Progress::Done(response);
+    }
+
+    match response
+        .headers()
+        .get(UPLOAD_OFFSET)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+    {
+        Some(offset) if offset < len => Progress::Offset(offset),
+        _ => Progress::Done(response),
+    }
+}
+
+/// Returns true if an upload request that failed with the given error may be
+/// resumed.
+fn is_retryable(error: &Error) -> bool {
+    error.is_network()
+        || error.is_timeout()
+        // A connection being closed before sending a response counts too.
+        || error.kind() == ErrorKind::ProtocolViolation
+}
+
+/// A reader over a source that is shared between upload attempts.
+struct SharedReader<R>(Arc<Mutex<R>>);
+
+impl<R: Read> Read for SharedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().read(buf)
+    }
+}
@@ -0,0 +1,483 @@
+//! A client for the [tus](https://tus.io/protocols/resumable-upload.html)
+//! resumable upload protocol.
+//!
+//! tus is an open protocol for uploading files in a way that can survive
+//! network interruptions. An upload is first created on the server, then the
+//! body is sent in one or more `PATCH` requests. Whenever a request fails, the
+//! client asks the server how much it has received with a `HEAD` request and
+//! continues from there.
+//!
+//! This implements version 1.0.0 of the core protocol, along with the
+//! `creation` and `checksum` extensions.
+//!
+//! # Availability
+//!
+//! This module is only available when the [`tus`](../../index.html#tus)
+//! feature is enabled.
+
+use super::{is_retryable, SharedReader};
+use crate::{
+    error::{Error, ErrorKind},
+    Body,
+    HttpClient,
+};
+use http::{
+    header::{HeaderName, HeaderValue, CONTENT_LENGTH, CONTENT_TYPE, LOCATION},
+    HeaderMap,
+    Method,
+    Request,
+    Response,
+    StatusCode,
+    Uri,
+};
+use std::{
+    convert::TryFrom,
+    fmt,
+    io::{Read, Seek, SeekFrom},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// The version of the protocol implemented by this client.
+pub const TUS_VERSION: &str = "1.0.0";
+
+/// Default number of times an upload request may fail before giving up.
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+
+/// Default amount of time to wait after a failed attempt before resuming.
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+const TUS_RESUMABLE: &str = "tus-resumable";
+const UPLOAD_CHECKSUM: &str = "upload-checksum";
+const UPLOAD_LENGTH: &str = "upload-length";
+const UPLOAD_METADATA: &str = "upload-metadata";
+const UPLOAD_OFFSET: &str = "upload-offset";
+
+/// Status code returned by servers implementing the checksum extension when
+/// the checksum of a chunk does not match.
+const CHECKSUM_MISMATCH: u16 = 460;
+
+/// An upload of a seekable body using the tus protocol.
+///
+/// An upload is created by sending a `POST` request to a tus endpoint, unless
+/// the URL of an existing upload is provided with [`Upload::location`]. The
+/// body is then sent in chunks of [`Upload::chunk_size`] bytes, resuming from
+/// the last offset acknowledged by the server whenever a request fails because
+/// of a network error.
+///
+/// Responses with an unexpected status code are returned to the caller as-is,
+/// so the final status should still be checked.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::upload::tus::Upload;
+/// use std::fs::File;
+///
+/// let file = File::open("large-video.mp4")?;
+///
+/// let mut upload = Upload::new("https://tusd.example.org/files/", file)?
+///     .metadata("filename", "large-video.mp4")
+///     .chunk_size(8 * 1024 * 1024)
+///     .checksum(true);
+///
+/// // Create the upload up front so that its location can be saved, allowing
+/// // the upload to be continued later by another process if this one dies.
+/// println!("uploading to {}", upload.create()?);
+///
+/// let response = upload.send()?;
+/// assert!(response.status().is_success());
+/// # Ok::<(), isahc::Error>(())
+/// ```
+pub struct Upload<R> {
+    client: Option<HttpClient>,
+    endpoint: Uri,
+    location: Option<Uri>,
+    source: Arc<Mutex<R>>,
+    len: u64,
+    offset: Option<u64>,
+    metadata: Vec<(String, Vec<u8>)>,
+    headers: HeaderMap,
+    chunk_size: Option<u64>,
+    checksum: bool,
+    max_attempts: u32,
+    retry_delay: Duration,
+}
+
+/// Result of sending a single request of an upload.
+enum Progress {
+    /// The server has received this many bytes so far.
+    Offset(u64),
+
+    /// The upload is finished, or cannot continue. Either way, this is the
+    /// response to return to the caller.
+    Done(Response<Body>),
+}
+
+impl<R: Read + Seek + Send + 'static> Upload<R> {
+    /// Create a new upload of the given source to the given tus endpoint.
+    ///
+    /// The length of the upload is determined by seeking to the end of the
+    /// source.
+    pub fn new<U>(endpoint: U, mut source: R) -> Result<Self, Error>
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        let endpoint = Uri::try_from(endpoint).map_err(|e| Error::from(e.into()))?;
+        let len = source.seek(SeekFrom::End(0))?;
+
+        Ok(Self {
+            client: None,
+            endpoint,
+            location: None,
+            source: Arc::new(Mutex::new(source)),
+            len,
+            offset: None,
+            metadata: Vec::new(),
+            headers: HeaderMap::new(),
+            chunk_size: None,
+            checksum: false,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            retry_delay: DEFAULT_RETRY_DELAY,
+        })
+    }
+
+    /// Set the client to send upload requests with. If not set, the same
+    /// default client used by [`isahc::send`](crate::send) is used.
+    #[must_use = "builders have no effect if unused"]
+    pub fn client(mut self, client: HttpClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Continue an upload that was already created at the given URL instead
+    /// of creating a new one.
+    pub fn location<U>(mut self, location: U) -> Result<Self, Error>
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        self.location = Some(Uri::try_from(location).map_err(|e| Error::from(e.into()))?);
+        self.offset = None;
+
+        Ok(self)
+    }
+
+    /// Add a metadata key-value pair to send to the server when creating the
+    /// upload.
+    ///
+    /// Keys must not contain spaces or commas. Metadata is ignored when
+    /// continuing an existing upload.
+    #[must_use = "builders have no effect if unused"]
+    pub fn metadata(mut self, key: impl Into<String>, value: impl AsRef<[u8]>) -> Self {
+        self.metadata.push((key.into(), value.as_ref().to_vec()));
+        self
+    }
+
+    /// Add a header to include in every request sent for this upload.
+    #[must_use = "builders have no effect if unused"]
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    /// Set the maximum number of bytes to send in a single `PATCH` request.
+    ///
+    /// By default the entire body is sent in one request. Smaller chunks mean
+    /// less data has to be sent again after an interruption if the server only
+    /// saves complete requests.
+    #[must_use = "builders have no effect if unused"]
+    pub fn chunk_size(mut self, size: u64) -> Self {
+        self.chunk_size = Some(size.max(1));
+        self
+    }
+
+    /// Enable or disable sending an `Upload-Checksum` header containing the
+    /// SHA-1 digest of each chunk, as defined by the checksum extension.
+    ///
+    /// Each chunk has to be read into memory in order to compute its checksum
+    /// before it is sent, so this should be combined with a reasonable
+    /// [`Upload::chunk_size`]. A chunk rejected by the server because of a
+    /// checksum mismatch is sent again. Disabled by default.
+    #[must_use = "builders have no effect if unused"]
+    pub fn checksum(mut self, enable: bool) -> Self {
+        self.checksum = enable;
+        self
+    }
+
+    /// Set how many times uploading may fail before giving up and returning
+    /// the last error. The default is 5.
+    #[must_use = "builders have no effect if unused"]
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Set how long to wait after a failed attempt before resuming. The
+    /// default is one second.
+    #[must_use = "builders have no effect if unused"]
+    pub fn retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Get the total length of the upload in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns true if the body to upload is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Create the upload on the server if it has not been created already,
+    /// and return its URL.
+    ///
+    /// This is done automatically by [`Upload::send`], but can be called
+    /// beforehand in order to save the upload URL somewhere.
+    pub fn create(&mut self) -> Result<&Uri, Error> {
+        if self.location.is_none() {
+            let mut request = self
+                .request(Method::POST, self.endpoint.clone())
+                .header(UPLOAD_LENGTH, self.len)
+                .header(CONTENT_LENGTH, 0);
+
+            if !self.metadata.is_empty() {
+                request = request.header(UPLOAD_METADATA, self.encode_metadata());
+            }
+
+            let response = self.http_client().send(request.body(Body::empty())?)?;
+
+            if response.status() != StatusCode::CREATED {
+                return Err(Error::with_response(ErrorKind::BadStatus, &response));
+            }
+
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| resolve(&self.endpoint, value))
+                .ok_or_else(|| {
+                    tracing::debug!("tus server did not return a valid upload location");
+                    Error::from(ErrorKind::ProtocolViolation)
+                })?;
+
+            tracing::debug!("created tus upload at {}", location);
+
+            self.location = Some(location);
+            self.offset = Some(0);
+        }
+
+        Ok(self.location.as_ref().unwrap())
+    }
+
+    /// Perform the upload, blocking until it completes or fails.
+    ///
+    /// Returns the final response from the server.
+    pub fn send(mut self) -> Result<Response<Body>, Error> {
+        self.create()?;
+
+        let mut failures = 0;
+
+        loop {
+            let result = match self.offset {
+                Some(offset) => self.upload_chunk(offset),
+                None => self.query_offset(),
+            };
+
+            match result {
+                Ok(Progress::Done(response)) => return Ok(response),
+                Ok(Progress::Offset(offset)) => {
+                    if matches!(self.offset, Some(previous) if offset <= previous) {
+                        failures += 1;
+
+                        if failures >= self.max_attempts {
+                            tracing::debug!("server is not accepting any more of the upload");
+                            return Err(Error::from(ErrorKind::ProtocolViolation));
+                        }
+                    }
+
+                    self.offset = Some(offset.min(self.len));
+                }
+                Err(e) if is_retryable(&e) && failures + 1 < self.max_attempts => {
+                    let uri = self.location.as_ref().unwrap_or(&self.endpoint);
+                    crate::retry::allow_retry(self.http_client(), uri, &e)?;
+                    failures += 1;
+
+                    tracing::debug!(
+                        attempt = failures,
+                        "tus upload request failed, resuming after {:?}: {}",
+                        self.retry_delay,
+                        e
+                    );
+
+                    self.http_client().clock().sleep(self.retry_delay);
+                    self.offset = None;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Ask the server how much of the body it has received so far.
+    fn query_offset(&self) -> Result<Progress, Error> {
+        let request = self
+            .request(Method::HEAD, self.location.clone().unwrap())
+            .body(Body::empty())?;
+
+        let response = self.http_client().send(request)?;
+
+        if !response.status().is_success() {
+            return Ok(Progress::Done(response));
+        }
+
+        match parse_offset(&response) {
+            Some(offset) => Ok(Progress::Offset(offset)),
+            None => {
+                tracing::debug!("tus server did not return a valid upload offset");
+                Err(Error::from(ErrorKind::ProtocolViolation))
+            }
+        }
+    }
+
+    /// Send the next chunk of the body starting at the given offset.
+    fn upload_chunk(&self, offset: u64) -> Result<Progress, Error> {
+        let remaining = self.len - offset;
+        let size = self.chunk_size.map_or(remaining, |size| size.min(remaining));
+
+        let mut request = self
+            .request(Method::PATCH, self.location.clone().unwrap())
+            .header(CONTENT_TYPE, "application/offset+octet-stream")
+            .header(UPLOAD_OFFSET, offset);
+
+        let body = {
+            let mut source = self.source.lock().unwrap();
+            source.seek(SeekFrom::Start(offset))?;
+
+            if self.checksum {
+                let mut chunk = Vec::with_capacity(size as usize);
+                (&mut *source).take(size).read_to_end(&mut chunk)?;
+
+                let digest = sha1_smol::Sha1::from(&chunk).digest().bytes();
+                let checksum = format!("sha1 {}", base64::encode(digest));
+                request = request.header(UPLOAD_CHECKSUM, checksum);
+
+                Body::from(chunk)
+            } else {
+                Body::from_reader_sized(SharedReader(self.source.clone()).take(size), size)
+            }
+        };
+
+        let response = self.http_client().send(request.body(body)?)?;
+
+        if response.status().as_u16() == CHECKSUM_MISMATCH {
+            tracing::debug!(offset, "tus server rejected chunk checksum, sending it again");
+            return Ok(Progress::Offset(offset));
+        }
+
+        if !response.status().is_success() {
+            return Ok(Progress::Done(response));
+        }
+
+        match parse_offset(&response) {
+            Some(offset) if offset < self.len => Ok(Progress::Offset(offset)),
+            Some(_) => Ok(Progress::Done(response)),
+            None => {
+                tracing::debug!("tus server did not return a valid upload offset");
+                Err(Error::from(ErrorKind::ProtocolViolation))
+            }
+        }
+    }
+
+    /// Encode the upload metadata as expected by the `Upload-Metadata` header.
+    fn encode_metadata(&self) -> String {
+        self.metadata
+            .iter()
+            .map(|(key, value)| {
+                if value.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{} {}", key, base64::encode(value))
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    fn request(&self, method: Method, uri: Uri) -> http::request::Builder {
+        let mut builder = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(TUS_RESUMABLE, TUS_VERSION);
+
+        if let Some(headers) = builder.headers_mut() {
+            headers.extend(self.headers.clone());
+        }
+
+        builder
+    }
+
+    fn http_client(&self) -> &HttpClient {
+        self.client.as_ref().unwrap_or_else(|| HttpClient::shared())
+    }
+}
+
+impl<R> fmt::Debug for Upload<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Upload")
+            .field("endpoint", &self.endpoint)
+            .field("location", &self.location)
+            .field("len", &self.len)
+            .field("offset", &self.offset)
+            .field("chunk_size", &self.chunk_size)
+            .field("checksum", &self.checksum)
+            .field("max_attempts", &self.max_attempts)
+            .field("retry_delay", &self.retry_delay)
+            .finish()
+    }
+}
+
+fn parse_offset<B>(response: &Response<B>) -> Option<u64> {
+    response
+        .headers()
+        .get(UPLOAD_OFFSET)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// Resolve an upload location returned by the server, which may be relative,
+/// against the endpoint it was created at.
+fn resolve(endpoint: &Uri, location: &str) -> Option<Uri> {
+    url::Url::parse(&endpoint.to_string())
+        .ok()?
+        .join(location)
+        .ok()?
+        .as_str()
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_location_is_resolved_against_endpoint() {
+        let endpoint = Uri::from_static("http://example.org/files/");
+
+        assert_eq!(
+            resolve(&endpoint, "/files/abc").unwrap(),
+            "http://example.org/files/abc"
+        );
+        assert_eq!(
+            resolve(&endpoint, "abc").unwrap(),
+            "http://example.org/files/abc"
+        );
+        assert_eq!(
+            resolve(&endpoint, "https://cdn.example.org/abc").unwrap(),
+            "https://cdn.example.org/abc"
+        );
+    }
+}
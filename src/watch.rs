@@ -0,0 +1,399 @@
+//! Helpers for consuming long-lived watch streams, such as the ones served by
+//! [Kubernetes](https://kubernetes.io/docs/reference/using-api/api-concepts/#efficient-detection-of-changes).
+//!
+//! A watch is a `GET` request whose response body never ends on purpose.
+//! Instead, the server keeps it open and writes one JSON event per line for
+//! every change to the watched resources. Since servers close watches after a
+//! while and connections break, a [`Watch`] reconnects on its own, resuming
+//! from the `resourceVersion` of the last event it received so that no
+//! changes are missed in between.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use isahc::watch::{EventType, Watch};
+//!
+//! let events = Watch::new("http://localhost:8001/api/v1/namespaces/default/pods")?.events();
+//!
+//! for event in events {
+//!     let event = event?;
+//!
+//!     if event.event_type() == EventType::Added {
+//!         println!("pod created: {}", event.object()["metadata"]["name"]);
+//!     }
+//! }
+//! # Ok::<(), isahc::Error>(())
+//! ```
+//!
+//! # Availability
+//!
+//! This module is only available when the [`watch`](../index.html#watch)
+//! feature is enabled.
+
+use crate::{
+    error::{Error, ErrorKind},
+    Body,
+    HttpClient,
+    ReadResponseExt,
+    RequestExt,
+};
+use http::{Request, Uri};
+use serde_json::Value;
+use std::{
+    convert::TryFrom,
+    fmt,
+    io::{BufRead, BufReader},
+    thread,
+    time::Duration,
+};
+
+/// Default amount of time to wait before reconnecting after a failure.
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Default number of times in a row connecting may fail before giving up.
+const DEFAULT_MAX_RETRIES: u32 = 5;
+
+/// A watch of the resources at a URI.
+///
+/// Requests are sent with the `watch=1` and `allowWatchBookmarks=true` query
+/// parameters added to the URI, and with `resourceVersion` set to the version
+/// to resume from, if known.
+#[derive(Clone)]
+pub struct Watch {
+    client: Option<HttpClient>,
+    uri: Uri,
+    resource_version: Option<String>,
+    retry_delay: Duration,
+    max_retries: u32,
+}
+
+impl Watch {
+    /// Create a watch of the resources at the given URI, using the same
+    /// default client used by [`isahc::send`](crate::send).
+    pub fn new<U>(uri: U) -> Result<Self, Error>
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        Ok(Self {
+            client: None,
+            uri: Uri::try_from(uri).map_err(|e| Error::from(e.into()))?,
+            resource_version: None,
+            retry_delay: DEFAULT_RETRY_DELAY,
+            max_retries: DEFAULT_MAX_RETRIES,
+        })
+    }
+
+    /// Set the client to send watch requests with.
+    #[must_use = "builders have no effect if unused"]
+    pub fn client(mut self, client: HttpClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Start watching from the given resource version, usually the one
+    /// returned by listing the resources beforehand.
+    ///
+    /// If not set, the server first sends an event for each existing resource
+    /// as if it was just added.
+    #[must_use = "builders have no effect if unused"]
+    pub fn resource_version(mut self, version: impl Into<String>) -> Self {
+        self.resource_version = Some(version.into());
+        self
+    }
+
+    /// Set how long to wait before reconnecting after connecting fails or the
+    /// connection breaks. The default is one second.
+    #[must_use = "builders have no effect if unused"]
+    pub fn retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Set how many times in a row reconnecting may fail before giving up.
+    /// The default is 5.
+    ///
+    /// Reconnecting after the server closes a watch that delivered events is
+    /// not a failure, and resets the count.
+    #[must_use = "builders have no effect if unused"]
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
+    }
+
+    /// Start the watch, returning an iterator over the events received.
+    ///
+    /// The iterator only ends after returning an error that could not be
+    /// recovered from by reconnecting.
+    pub fn events(self) -> Events {
+        Events {
+            watch: self,
+            body: None,
+            received: false,
+            failures: 0,
+            done: false,
+        }
+    }
+
+    fn http_client(&self) -> &HttpClient {
+        self.client.as_ref().unwrap_or_else(|| HttpClient::shared())
+    }
+}
+
+impl fmt::Debug for Watch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watch")
+            .field("uri", &self.uri)
+            .field("resource_version", &self.resource_version)
+            .field("retry_delay", &self.retry_delay)
+            .field("max_retries", &self.max_retries)
+            .finish()
+    }
+}
+
+/// The kind of change described by an [`Event`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventType {
+    /// A resource was added.
+    Added,
+
+    /// A resource was changed.
+    Modified,
+
+    /// A resource was deleted.
+    Deleted,
+
+    /// The server reported an error, described by the event object.
+    ///
+    /// An error with code 410 means that the resource version being watched
+    /// from is too old. The watch is then restarted from the current state of
+    /// the resources, so any local copy of them should be listed again.
+    Error,
+}
+
+/// A change to a watched resource.
+#[derive(Clone, Debug)]
+pub struct Event {
+    event_type: EventType,
+    object: Value,
+}
+
+impl Event {
+    /// Get the kind of change.
+    pub fn event_type(&self) -> EventType {
+        self.event_type
+    }
+
+    /// Get the resource that changed, or the error status for
+    /// [`EventType::Error`] events.
+    pub fn object(&self) -> &Value {
+        &self.object
+    }
+
+    /// Take the resource that changed.
+    pub fn into_object(self) -> Value {
+        self.object
+    }
+
+    /// Get the resource version of the resource that changed, if any.
+    pub fn resource_version(&self) -> Option<&str> {
+        self.object["metadata"]["resourceVersion"].as_str()
+    }
+}
+
+/// An iterator over the events of a [`Watch`].
+///
+/// Bookmark events only advance the resource version to resume from, and are
+/// not returned.
+pub struct Events {
+    watch: Watch,
+    body: Option<BufReader<Body>>,
+
+    /// Whether the current connection delivered any events.
+    received: bool,
+
+    /// Number of times in a row connecting has failed.
+    failures: u32,
+
+    done: bool,
+}
+
+impl Events {
+    /// Get the resource version the watch would resume from if reconnected.
+    pub fn resource_version(&self) -> Option<&str> {
+        self.watch.resource_version.as_deref()
+    }
+
+    /// Send a watch request, returning the response body to read events from.
+    ///
+    /// Returns `Ok(None)` if the request failed in a way that reconnecting may
+    /// fix.
+    fn connect(&mut self) -> Result<Option<BufReader<Body>>, Error> {
+        let mut request = Request::get(self.watch.uri.clone()).body(())?;
+
+        {
+            let mut query = request.query_mut();
+            query.set("watch", "1");
+            query.set("allowWatchBookmarks", "true");
+
+            match self.watch.resource_version.as_ref() {
+                Some(version) => query.set("resourceVersion", version.as_str()),
+                None => query.remove("resourceVersion"),
+            };
+        }
+
+        let mut response = match self.watch.http_client().send(request) {
+            Ok(response) => response,
+            Err(e) if e.is_network() || e.is_timeout() => {
+                tracing::debug!("failed to connect watch: {}", e);
+                return self.fail(e).map(|_| None);
+            }
+            Err(e) => return Err(e),
+        };
+
+        // The resource version is too old to resume from.
+        if response.status() == http::StatusCode::GONE {
+            tracing::debug!("watch resource version expired, restarting watch");
+            self.watch.resource_version = None;
+            return self
+                .fail(Error::with_response(ErrorKind::BadStatus, &response))
+                .map(|_| None);
+        }
+
+        if !response.status().is_success() {
+            let error = Error::with_response(ErrorKind::BadStatus, &response);
+
+            // Errors from the server may be caused by overload.
+            if response.status().is_server_error() {
+                return self.fail(error).map(|_| None);
+            }
+
+            let body = response.bytes()?;
+
+            return Err(error.with_response_body(response.map(|_| body)));
+        }
+
+        self.received = false;
+
+        Ok(Some(BufReader::new(response.into_body())))
+    }
+
+    /// Record a failure, and wait before trying again if there are retries
+    /// left.
+    fn fail(&mut self, error: Error) -> Result<(), Error> {
+        self.failures += 1;
+
+        if self.failures > self.watch.max_retries {
+            return Err(error);
+        }
+
+        thread::sleep(self.watch.retry_delay);
+
+        Ok(())
+    }
+
+    /// Parse a line of the stream into an event, updating the resource version
+    /// to resume from.
+    fn parse_line(&mut self, line: &str) -> Result<Option<Event>, Error> {
+        let mut raw = serde_json::from_str::<Value>(line)
+            .map_err(|e| Error::new(ErrorKind::ProtocolViolation, e))?;
+        let object = raw["object"].take();
+
+        let event_type = match raw["type"].as_str().unwrap_or_default() {
+            "ADDED" => EventType::Added,
+            "MODIFIED" => EventType::Modified,
+            "DELETED" => EventType::Deleted,
+            "ERROR" => EventType::Error,
+            "BOOKMARK" => {
+                if let Some(version) = object["metadata"]["resourceVersion"].as_str() {
+                    self.watch.resource_version = Some(version.to_owned());
+                }
+
+                return Ok(None);
+            }
+            other => {
+                tracing::debug!("ignoring watch event of unknown type {}", other);
+                return Ok(None);
+            }
+        };
+
+        let event = Event { event_type, object };
+
+        if event_type == EventType::Error {
+            if event.object["code"].as_u64() == Some(410) {
+                self.watch.resource_version = None;
+            }
+        } else if let Some(version) = event.resource_version() {
+            self.watch.resource_version = Some(version.to_owned());
+        }
+
+        Ok(Some(event))
+    }
+
+    fn next_event(&mut self) -> Result<Event, Error> {
+        loop {
+            let body = match self.body.as_mut() {
+                Some(body) => body,
+                None => {
+                    self.body = self.connect()?;
+                    continue;
+                }
+            };
+
+            let mut line = String::new();
+
+            match body.read_line(&mut line) {
+                Ok(0) => {
+                    self.body = None;
+
+                    // A watch closed without delivering anything counts as a
+                    // failure, so that a server that keeps closing watches
+                    // right away is not reconnected to in a tight loop.
+                    if !self.received {
+                        self.fail(Error::from(ErrorKind::ProtocolViolation))?;
+                    }
+                }
+                Ok(_) if line.trim().is_empty() => {}
+                Ok(_) => {
+                    self.received = true;
+                    self.failures = 0;
+
+                    if let Some(event) = self.parse_line(line.trim())? {
+                        return Ok(event);
+                    }
+                }
+                Err(e) => {
+                    tracing::debug!("watch connection broke: {}", e);
+                    self.body = None;
+                    self.fail(Error::from(e))?;
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for Events {
+    type Item = Result<Event, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let result = self.next_event();
+
+        if result.is_err() {
+            self.done = true;
+        }
+
+        Some(result)
+    }
+}
+
+impl fmt::Debug for Events {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Events")
+            .field("watch", &self.watch)
+            .finish()
+    }
+}
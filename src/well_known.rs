@@ -0,0 +1,676 @@
+//! Helpers for discovering and fetching `.well-known` resources.
+//!
+//! Many protocols publish metadata about a host at a fixed location under the
+//! `/.well-known/` path, as described in [RFC
+//! 8615](https://tools.ietf.org/html/rfc8615). A [`WellKnown`] fetches these
+//! resources with an appropriate `Accept` header and caches them, so that
+//! looking up the same metadata repeatedly does not cost a request each time.
+//!
+//! # Availability
+//!
+//! This module is only available when the [`blocking`](../index.html#blocking)
+//! feature is enabled. Parsing WebFinger and OAuth metadata documents
+//! additionally requires the [`json`](../index.html#json) feature.
+
+use crate::{
+    error::{Error, ErrorKind},
+    HttpClient,
+    ReadResponseExt,
+};
+use http::{
+    header::{ACCEPT, CACHE_CONTROL, CONTENT_TYPE},
+    Request,
+    Uri,
+};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// The prefix of the path of all well-known resources.
+const PREFIX: &str = "/.well-known/";
+
+/// How long a resource is cached for if the server does not say otherwise.
+const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// A fetcher of well-known resources that caches the documents it fetches.
+///
+/// Documents are cached for as long as the server allows with a
+/// `Cache-Control` response header, or for [`WellKnown::ttl`] if no lifetime
+/// is given. Only successful responses are cached.
+///
+/// A `WellKnown` can be shared between threads, and cloning it produces a
+/// handle to the same cache.
+///
+/// # Examples
+///
+/// ```no_run
+/// use isahc::well_known::WellKnown;
+///
+/// let well_known = WellKnown::new();
+/// let security = well_known.security_txt("https://example.org")?;
+///
+/// for contact in security.get_all("Contact") {
+///     println!("report vulnerabilities to {}", contact);
+/// }
+/// # Ok::<(), isahc::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct WellKnown {
+    client: Option<HttpClient>,
+    ttl: Duration,
+    cache: Arc<Mutex<HashMap<String, CacheEntry>>>,
+}
+
+struct CacheEntry {
+    document: Document,
+    expires: Instant,
+}
+
+impl Default for WellKnown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WellKnown {
+    /// Create a new fetcher with an empty cache, using the same default
+    /// client used by [`isahc::send`](crate::send).
+    pub fn new() -> Self {
+        Self {
+            client: None,
+            ttl: DEFAULT_TTL,
+            cache: Default::default(),
+        }
+    }
+
+    /// Set the client to fetch resources with.
+    #[must_use = "builders have no effect if unused"]
+    pub fn client(mut self, client: HttpClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Set how long to cache documents for when the server does not specify
+    /// a lifetime. The default is one hour.
+    #[must_use = "builders have no effect if unused"]
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Remove all cached documents.
+    pub fn clear_cache(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    /// Fetch the well-known resource with the given name from an origin,
+    /// sending the given `Accept` header value.
+    ///
+    /// Only the scheme and authority of the origin are used. The resource is
+    /// returned from the cache if it was fetched before and has not expired.
+    pub fn fetch<U>(&self, origin: U, name: &str, accept: &str) -> Result<Document, Error>
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        let uri = resource_uri(&parse_uri(origin)?, name, "", None)?;
+
+        self.fetch_uri(uri, accept)
+    }
+
+    /// Fetch the [`security.txt`](https://securitytxt.org) file of an origin.
+    pub fn security_txt<U>(&self, origin: U) -> Result<SecurityTxt, Error>
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        let document = self.fetch(origin, "security.txt", "text/plain")?;
+
+        Ok(SecurityTxt::parse(&document.text()))
+    }
+
+    /// Look up a resource, such as `acct:alice@example.org`, using
+    /// [WebFinger](https://tools.ietf.org/html/rfc7033).
+    ///
+    /// If any link relations are given, the server is asked to only include
+    /// links with those relations. Servers are free to ignore this.
+    ///
+    /// # Availability
+    ///
+    /// This method is only available when the [`json`](../index.html#json)
+    /// feature is enabled.
+    #[cfg(feature = "json")]
+    pub fn webfinger<U>(&self, origin: U, resource: &str, rels: &[&str]) -> Result<Jrd, Error>
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        query.append_pair("resource", resource);
+
+        for rel in rels {
+            query.append_pair("rel", rel);
+        }
+
+        let uri = resource_uri(&parse_uri(origin)?, "webfinger", "", Some(&query.finish()))?;
+
+        Jrd::from_value(self.fetch_uri(uri, "application/jrd+json")?.json()?)
+    }
+
+    /// Fetch the metadata of an OAuth 2.0 authorization server with the given
+    /// issuer identifier, as described in [RFC
+    /// 8414](https://tools.ietf.org/html/rfc8414).
+    ///
+    /// If the issuer has a path, the well-known path is inserted between the
+    /// host and the path as the RFC requires.
+    ///
+    /// # Availability
+    ///
+    /// This method is only available when the [`json`](../index.html#json)
+    /// feature is enabled.
+    #[cfg(feature = "json")]
+    pub fn oauth_authorization_server<U>(
+        &self,
+        issuer: U,
+    ) -> Result<AuthorizationServerMetadata, Error>
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        let issuer = parse_uri(issuer)?;
+        let path = issuer.path().trim_end_matches('/');
+        let uri = resource_uri(&issuer, "oauth-authorization-server", path, None)?;
+
+        AuthorizationServerMetadata::from_value(self.fetch_uri(uri, "application/json")?.json()?)
+    }
+
+    /// Fetch the [OpenID Connect provider
+    /// metadata](https://openid.net/specs/openid-connect-discovery-1_0.html)
+    /// of the given issuer.
+    ///
+    /// Unlike other well-known resources, the metadata is located under the
+    /// path of the issuer, if any.
+    ///
+    /// # Availability
+    ///
+    /// This method is only available when the [`json`](../index.html#json)
+    /// feature is enabled.
+    #[cfg(feature = "json")]
+    pub fn openid_configuration<U>(&self, issuer: U) -> Result<AuthorizationServerMetadata, Error>
+    where
+        Uri: TryFrom<U>,
+        <Uri as TryFrom<U>>::Error: Into<http::Error>,
+    {
+        let issuer = parse_uri(issuer)?;
+        let uri = parse_uri::<String>(format!(
+            "{}{}openid-configuration",
+            issuer.to_string().trim_end_matches('/'),
+            PREFIX
+        ))?;
+
+        AuthorizationServerMetadata::from_value(self.fetch_uri(uri, "application/json")?.json()?)
+    }
+
+    fn fetch_uri(&self, uri: Uri, accept: &str) -> Result<Document, Error> {
+        let key = uri.to_string();
+        let clock = self.http_client().clock();
+
+        if let Some(entry) = self.cache.lock().unwrap().get(&key) {
+            if entry.expires > clock.now() {
+                tracing::debug!("using cached well-known resource {}", key);
+                return Ok(entry.document.clone());
+            }
+        }
+
+        let request = Request::get(uri.clone()).header(ACCEPT, accept).body(())?;
+        let mut response = self.http_client().send(request)?;
+
+        if !response.status().is_success() {
+            return Err(Error::with_response(ErrorKind::BadStatus, &response));
+        }
+
+        let document = Document {
+            uri,
+            content_type: response
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from),
+            body: response.bytes()?.into(),
+        };
+
+        let lifetime = response
+            .headers()
+            .get(CACHE_CONTROL)
+            .and_then(|value| value.to_str().ok())
+            .map_or(Some(self.ttl), |value| cache_lifetime(value, self.ttl));
+
+        if let Some(lifetime) = lifetime.filter(|lifetime| *lifetime > Duration::from_secs(0)) {
+            self.cache.lock().unwrap().insert(
+                key,
+                CacheEntry {
+                    document: document.clone(),
+                    expires: clock.now() + lifetime,
+                },
+            );
+        }
+
+        Ok(document)
+    }
+
+    fn http_client(&self) -> &HttpClient {
+        self.client.as_ref().unwrap_or_else(|| HttpClient::shared())
+    }
+}
+
+impl fmt::Debug for WellKnown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WellKnown")
+            .field("ttl", &self.ttl)
+            .field("cached", &self.cache.lock().unwrap().len())
+            .finish()
+    }
+}
+
+/// A fetched well-known resource.
+#[derive(Clone, Debug)]
+pub struct Document {
+    uri: Uri,
+    content_type: Option<String>,
+    body: Arc<[u8]>,
+}
+
+impl Document {
+    /// Get the URI the document was fetched from.
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// Get the content type of the document, if the server sent one.
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    /// Get the raw contents of the document.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Get the contents of the document as text, replacing any invalid UTF-8.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// Deserialize the contents of the document as JSON into a given type.
+    ///
+    /// # Availability
+    ///
+    /// This method is only available when the [`json`](../index.html#json)
+    /// feature is enabled.
+    #[cfg(feature = "json")]
+    pub fn json<T>(&self) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        serde_json::from_slice(&self.body).map_err(|e| Error::new(ErrorKind::ProtocolViolation, e))
+    }
+}
+
+/// The fields of a [`security.txt`](https://tools.ietf.org/html/rfc9116)
+/// file.
+#[derive(Clone, Debug, Default)]
+pub struct SecurityTxt {
+    fields: Vec<(String, String)>,
+}
+
+impl SecurityTxt {
+    /// Parse the contents of a `security.txt` file.
+    ///
+    /// Comments and lines that are not fields are ignored, including the
+    /// signature if the file is signed. The signature is not verified.
+    pub fn parse(text: &str) -> Self {
+        let fields = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| {
+                let (name, value) = line.split_at(line.find(':')?);
+                let name = name.trim();
+
+                if name.is_empty() || name.contains(char::is_whitespace) {
+                    return None;
+                }
+
+                Some((name.to_owned(), value[1..].trim().to_owned()))
+            })
+            .collect();
+
+        Self {
+            fields,
+        }
+    }
+
+    /// Get the value of the first field with the given name, such as
+    /// `Expires`. Field names are case-insensitive.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get the values of all fields with the given name, such as `Contact`.
+    /// Field names are case-insensitive.
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.fields
+            .iter()
+            .filter(move |(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get all fields in the order they appear in the file.
+    pub fn fields(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.fields
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+}
+
+/// A JSON Resource Descriptor returned by a WebFinger lookup.
+///
+/// # Availability
+///
+/// This type is only available when the [`json`](../index.html#json) feature
+/// is enabled.
+#[cfg(feature = "json")]
+#[derive(Clone, Debug)]
+pub struct Jrd {
+    subject: Option<String>,
+    aliases: Vec<String>,
+    links: Vec<JrdLink>,
+    json: serde_json::Value,
+}
+
+#[cfg(feature = "json")]
+impl Jrd {
+    fn from_value(json: serde_json::Value) -> Result<Self, Error> {
+        if !json.is_object() {
+            return Err(Error::from(ErrorKind::ProtocolViolation));
+        }
+
+        let links = json["links"]
+            .as_array()
+            .map(|links| {
+                links
+                    .iter()
+                    .filter_map(|link| {
+                        Some(JrdLink {
+                            rel: link["rel"].as_str()?.to_owned(),
+                            media_type: link["type"].as_str().map(String::from),
+                            href: link["href"].as_str().map(String::from),
+                            template: link["template"].as_str().map(String::from),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            subject: json["subject"].as_str().map(String::from),
+            aliases: json["aliases"]
+                .as_array()
+                .map(|aliases| {
+                    aliases
+                        .iter()
+                        .filter_map(|alias| alias.as_str())
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            links,
+            json,
+        })
+    }
+
+    /// Get the URI of the resource described.
+    pub fn subject(&self) -> Option<&str> {
+        self.subject.as_deref()
+    }
+
+    /// Get other URIs that identify the same resource.
+    pub fn aliases(&self) -> &[String] {
+        &self.aliases
+    }
+
+    /// Get all links to other resources.
+    pub fn links(&self) -> &[JrdLink] {
+        &self.links
+    }
+
+    /// Get the first link with the given relation type.
+    pub fn link(&self, rel: &str) -> Option<&JrdLink> {
+        self.links.iter().find(|link| link.rel == rel)
+    }
+
+    /// Get the raw JSON document, for accessing properties not exposed
+    /// directly.
+    pub fn as_json(&self) -> &serde_json::Value {
+        &self.json
+    }
+}
+
+/// A link in a [`Jrd`].
+///
+/// # Availability
+///
+/// This type is only available when the [`json`](../index.html#json) feature
+/// is enabled.
+#[cfg(feature = "json")]
+#[derive(Clone, Debug)]
+pub struct JrdLink {
+    rel: String,
+    media_type: Option<String>,
+    href: Option<String>,
+    template: Option<String>,
+}
+
+#[cfg(feature = "json")]
+impl JrdLink {
+    /// Get the relation type of the link.
+    pub fn rel(&self) -> &str {
+        &self.rel
+    }
+
+    /// Get the media type of the linked resource, if given.
+    pub fn media_type(&self) -> Option<&str> {
+        self.media_type.as_deref()
+    }
+
+    /// Get the URI of the linked resource, if given.
+    pub fn href(&self) -> Option<&str> {
+        self.href.as_deref()
+    }
+
+    /// Get the URI template of the linked resource, if given instead of a
+    /// URI.
+    pub fn template(&self) -> Option<&str> {
+        self.template.as_deref()
+    }
+}
+
+/// Metadata describing an OAuth 2.0 authorization server or OpenID Connect
+/// provider.
+///
+/// # Availability
+///
+/// This type is only available when the [`json`](../index.html#json) feature
+/// is enabled.
+#[cfg(feature = "json")]
+#[derive(Clone, Debug)]
+pub struct AuthorizationServerMetadata {
+    json: serde_json::Value,
+}
+
+#[cfg(feature = "json")]
+impl AuthorizationServerMetadata {
+    fn from_value(json: serde_json::Value) -> Result<Self, Error> {
+        // The issuer is the only field required to be present.
+        if json["issuer"].as_str().is_none() {
+            tracing::debug!("authorization server metadata is missing an issuer");
+            return Err(Error::from(ErrorKind::ProtocolViolation));
+        }
+
+        Ok(Self {
+            json,
+        })
+    }
+
+    /// Get the issuer identifier of the authorization server.
+    pub fn issuer(&self) -> &str {
+        self.json["issuer"].as_str().unwrap_or_default()
+    }
+
+    /// Get the URL of the authorization endpoint, if any.
+    pub fn authorization_endpoint(&self) -> Option<&str> {
+        self.get_str("authorization_endpoint")
+    }
+
+    /// Get the URL of the token endpoint, if any.
+    pub fn token_endpoint(&self) -> Option<&str> {
+        self.get_str("token_endpoint")
+    }
+
+    /// Get the URL of the JSON Web Key Set of the server, if any.
+    pub fn jwks_uri(&self) -> Option<&str> {
+        self.get_str("jwks_uri")
+    }
+
+    /// Get the scopes supported by the server, if listed.
+    pub fn scopes_supported(&self) -> impl Iterator<Item = &str> {
+        self.json["scopes_supported"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|scope| scope.as_str())
+    }
+
+    /// Get a string metadata field by name.
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        self.json.get(name)?.as_str()
+    }
+
+    /// Get the raw JSON document, for accessing fields not exposed directly.
+    pub fn as_json(&self) -> &serde_json::Value {
+        &self.json
+    }
+}
+
+fn parse_uri<U>(uri: U) -> Result<Uri, Error>
+where
+    Uri: TryFrom<U>,
+    <Uri as TryFrom<U>>::Error: Into<http::Error>,
+{
+    Uri::try_from(uri).map_err(|e| Error::from(e.into()))
+}
+
+/// Build the URI of a well-known resource on the same origin as the given URI,
+/// followed by an optional path suffix and query string.
+fn resource_uri(origin: &Uri, name: &str, suffix: &str, query: Option<&str>) -> Result<Uri, Error> {
+    let authority = origin
+        .authority()
+        .ok_or_else(|| Error::from(ErrorKind::InvalidRequest))?;
+    let scheme = origin.scheme_str().unwrap_or("https");
+
+    let mut uri = format!("{}://{}{}{}{}", scheme, authority, PREFIX, name, suffix);
+
+    if let Some(query) = query {
+        uri.push('?');
+        uri.push_str(query);
+    }
+
+    parse_uri(uri)
+}
+
+/// Determine how long a response may be cached for from its `Cache-Control`
+/// header, or `None` if it may not be cached at all.
+fn cache_lifetime(cache_control: &str, default: Duration) -> Option<Duration> {
+    let mut lifetime = default;
+
+    for directive in cache_control.split(',').map(str::trim) {
+        if directive.eq_ignore_ascii_case("no-store")
+            || directive.eq_ignore_ascii_case("no-cache")
+        {
+            return None;
+        }
+
+        if let Some(index) = directive.find('=') {
+            if directive[..index].trim().eq_ignore_ascii_case("max-age") {
+                lifetime = directive[index + 1..]
+                    .trim()
+                    .trim_matches('"')
+                    .parse()
+                    .map_or(lifetime, Duration::from_secs);
+            }
+        }
+    }
+
+    Some(lifetime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resource_uri_uses_origin_only() {
+        let origin = Uri::from_static("https://example.org:8443/some/path?x=y");
+
+        assert_eq!(
+            resource_uri(&origin, "security.txt", "", None).unwrap(),
+            "https://example.org:8443/.well-known/security.txt"
+        );
+        assert_eq!(
+            resource_uri(&origin, "oauth-authorization-server", "/tenant", Some("a=b")).unwrap(),
+            "https://example.org:8443/.well-known/oauth-authorization-server/tenant?a=b"
+        );
+        assert!(resource_uri(&Uri::from_static("/relative"), "x", "", None).is_err());
+    }
+
+    #[test]
+    fn cache_lifetime_from_cache_control() {
+        let default = Duration::from_secs(3600);
+
+        assert_eq!(cache_lifetime("max-age=60", default), Some(Duration::from_secs(60)));
+        assert_eq!(
+            cache_lifetime("public, max-age=\"5\"", default),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(cache_lifetime("max-age=60, no-store", default), None);
+        assert_eq!(cache_lifetime("no-cache", default), None);
+        assert_eq!(cache_lifetime("public", default), Some(default));
+    }
+
+    #[test]
+    fn parse_security_txt() {
+        let security = SecurityTxt::parse(
+            "# Our security policy\n\
+             Contact: mailto:security@example.org\n\
+             contact: https://example.org/security\n\
+             Expires: 2030-01-01T00:00:00.000Z\n\
+             \n\
+             not a field\n",
+        );
+
+        assert_eq!(
+            security.get_all("Contact").collect::<Vec<_>>(),
+            vec!["mailto:security@example.org", "https://example.org/security"]
+        );
+        assert_eq!(security.get("expires"), Some("2030-01-01T00:00:00.000Z"));
+        assert_eq!(security.get("Encryption"), None);
+        assert_eq!(security.fields().count(), 3);
+    }
+}
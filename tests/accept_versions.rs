@@ -0,0 +1,82 @@
+use isahc::{config::AcceptVersions, error::ErrorKind, prelude::*, Request};
+use testserver::mock;
+
+#[test]
+fn accept_header_lists_versions_in_order() {
+    let m = mock!();
+
+    Request::get(m.url())
+        .accept_versions(AcceptVersions::new("application/vnd.example+json").version(3).version(2))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    m.request().expect_header(
+        "accept",
+        "application/vnd.example.v3+json, application/vnd.example.v2+json; q=0.9",
+    );
+}
+
+#[test]
+fn explicit_accept_header_takes_precedence() {
+    let m = mock!();
+
+    Request::get(m.url())
+        .header("accept", "application/json")
+        .accept_versions(AcceptVersions::new("application/vnd.example+json").version(2))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    m.request().expect_header("accept", "application/json");
+}
+
+#[test]
+fn invalid_media_type_is_rejected() {
+    let m = mock!();
+
+    let error = Request::get(m.url())
+        .accept_versions(AcceptVersions::new("json").version(2))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::InvalidRequest);
+    assert_eq!(m.requests_received(), 0);
+}
+
+#[test]
+fn version_returned_by_server() {
+    let m = mock! {
+        headers {
+            "content-type": "application/vnd.example.v2+json; charset=utf-8",
+        }
+    };
+
+    let response = Request::get(m.url())
+        .accept_versions(AcceptVersions::new("application/vnd.example+json").version(3).version(2))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+    let version = response.media_type_version().unwrap();
+
+    assert_eq!(version.media_type(), "application/vnd.example+json");
+    assert_eq!(version.version(), "2");
+}
+
+#[test]
+fn unversioned_response() {
+    let m = mock! {
+        headers {
+            "content-type": "application/json",
+        }
+    };
+
+    let response = isahc::get(m.url()).unwrap();
+
+    assert_eq!(response.media_type_version(), None);
+}
@@ -0,0 +1,80 @@
+use isahc::{config::AdaptiveConcurrency, prelude::*, HttpClient};
+use std::time::Duration;
+use testserver::mock;
+
+#[test]
+fn limit_is_not_reported_unless_enabled() {
+    let client = HttpClient::new().unwrap();
+
+    assert_eq!(client.agent_stats().concurrency_limit(), None);
+}
+
+#[test]
+fn healthy_responses_raise_limit() {
+    let m = mock!();
+
+    let client = HttpClient::builder()
+        .adaptive_concurrency(AdaptiveConcurrency::new().initial_limit(1))
+        .build()
+        .unwrap();
+
+    assert_eq!(client.agent_stats().concurrency_limit(), Some(1));
+
+    client.get(m.url()).unwrap().consume().unwrap();
+
+    assert_eq!(client.agent_stats().concurrency_limit(), Some(2));
+}
+
+#[test]
+fn overloaded_responses_lower_limit() {
+    let m = mock! {
+        status: 503,
+    };
+
+    let client = HttpClient::builder()
+        .adaptive_concurrency(AdaptiveConcurrency::new().initial_limit(8))
+        .build()
+        .unwrap();
+
+    client.get(m.url()).unwrap().consume().unwrap();
+    assert_eq!(client.agent_stats().concurrency_limit(), Some(4));
+
+    client.get(m.url()).unwrap().consume().unwrap();
+    assert_eq!(client.agent_stats().concurrency_limit(), Some(2));
+}
+
+#[test]
+fn slow_responses_lower_limit() {
+    let m = mock! {
+        delay: 200ms,
+    };
+
+    let client = HttpClient::builder()
+        .adaptive_concurrency(
+            AdaptiveConcurrency::new()
+                .initial_limit(8)
+                .latency_threshold(Duration::from_millis(50)),
+        )
+        .build()
+        .unwrap();
+
+    client.get(m.url()).unwrap().consume().unwrap();
+
+    assert_eq!(client.agent_stats().concurrency_limit(), Some(4));
+}
+
+#[test]
+fn limit_does_not_drop_below_minimum() {
+    let m = mock! {
+        status: 429,
+    };
+
+    let client = HttpClient::builder()
+        .adaptive_concurrency(AdaptiveConcurrency::new().initial_limit(2).min_limit(2))
+        .build()
+        .unwrap();
+
+    client.get(m.url()).unwrap().consume().unwrap();
+
+    assert_eq!(client.agent_stats().concurrency_limit(), Some(2));
+}
@@ -0,0 +1,54 @@
+use isahc::{config::AffinityKey, prelude::*, HttpClient, Request};
+use testserver::mock;
+
+#[test]
+fn requests_with_same_key_reuse_connection() {
+    let m = mock!();
+    let client = HttpClient::new().unwrap();
+
+    let mut first = client
+        .send(
+            Request::get(m.url())
+                .extension(AffinityKey::new("a"))
+                .body(())
+                .unwrap(),
+        )
+        .unwrap();
+    first.consume().unwrap();
+
+    let mut second = client
+        .send(
+            Request::get(m.url())
+                .extension(AffinityKey::new("a"))
+                .body(())
+                .unwrap(),
+        )
+        .unwrap();
+    second.consume().unwrap();
+
+    assert_eq!(m.requests_received(), 2);
+    assert_eq!(first.remote_addr(), Some(m.addr()));
+    assert_eq!(first.local_addr(), second.local_addr());
+}
+
+#[test]
+fn requests_with_dialer_ignore_affinity() {
+    let m = mock!();
+    let client = HttpClient::new().unwrap();
+
+    for _ in 0..2 {
+        client
+            .send(
+                Request::get("http://affinity.test/")
+                    .dial(m.addr())
+                    .extension(AffinityKey::new("a"))
+                    .body(())
+                    .unwrap(),
+            )
+            .unwrap()
+            .consume()
+            .unwrap();
+    }
+
+    assert_eq!(m.requests_received(), 2);
+}
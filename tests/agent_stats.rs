@@ -0,0 +1,19 @@
+use isahc::{prelude::*, HttpClient};
+use testserver::mock;
+
+#[test]
+fn agent_stats_count_event_loop_activity() {
+    let m = mock!();
+    let client = HttpClient::new().unwrap();
+
+    let before = client.agent_stats();
+
+    client.get(m.url()).unwrap().consume().unwrap();
+
+    let after = client.agent_stats();
+
+    assert!(after.iterations() > before.iterations());
+    assert!(after.socket_wakeups() > 0);
+    assert!(after.busy_time() >= before.busy_time());
+    assert!(after.wait_time() >= before.wait_time());
+}
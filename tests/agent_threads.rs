@@ -0,0 +1,118 @@
+use futures_lite::{future::block_on, io::AsyncRead};
+use isahc::{config::AgentDispatch, prelude::*, AsyncBody, HttpClient, Request};
+use std::{
+    collections::HashSet,
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    thread,
+    time::Duration,
+};
+use testserver::mock;
+
+/// A request body that records the name of each thread it is read from.
+struct ThreadRecorder(Arc<Mutex<Vec<String>>>, bool);
+
+impl AsyncRead for ThreadRecorder {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.1 {
+            return Poll::Ready(Ok(0));
+        }
+
+        self.1 = true;
+        self.0
+            .lock()
+            .unwrap()
+            .push(thread::current().name().unwrap_or_default().to_owned());
+        buf[0] = b'x';
+
+        Poll::Ready(Ok(1))
+    }
+}
+
+fn recorded_post(url: String, threads: &Arc<Mutex<Vec<String>>>) -> Request<AsyncBody> {
+    Request::post(url)
+        .body(AsyncBody::from_reader(ThreadRecorder(threads.clone(), false)))
+        .unwrap()
+}
+
+#[test]
+fn round_robin_uses_every_agent_thread() {
+    let m = mock!();
+    let threads = Arc::new(Mutex::new(Vec::new()));
+
+    let client = HttpClient::builder().agent_threads(3).build().unwrap();
+
+    for _ in 0..6 {
+        let response = block_on(client.send_async(recorded_post(m.url(), &threads))).unwrap();
+        assert_eq!(response.status(), 200);
+    }
+
+    let threads = threads.lock().unwrap();
+
+    assert_eq!(threads.len(), 6);
+    assert!(threads.iter().all(|name| name.starts_with("isahc-agent-")));
+    assert_eq!(threads.iter().collect::<HashSet<_>>().len(), 3);
+
+    // Each thread takes its turn in the same order.
+    assert_eq!(threads[..3], threads[3..]);
+}
+
+#[test]
+fn least_loaded_avoids_busy_agent_thread() {
+    let slow = mock! {
+        delay: 1s,
+    };
+    let fast = mock!();
+    let threads = Arc::new(Mutex::new(Vec::new()));
+
+    let client = HttpClient::builder()
+        .agent_threads(2)
+        .agent_dispatch(AgentDispatch::LeastLoaded)
+        .build()
+        .unwrap();
+
+    let slow_request = thread::spawn({
+        let client = client.clone();
+        let request = recorded_post(slow.url(), &threads);
+        move || block_on(client.send_async(request)).unwrap().status()
+    });
+
+    // Wait for the slow request to be in progress.
+    while threads.lock().unwrap().is_empty() {
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    for _ in 0..3 {
+        let mut response =
+            block_on(client.send_async(recorded_post(fast.url(), &threads))).unwrap();
+        assert_eq!(response.status(), 200);
+        block_on(response.consume()).unwrap();
+
+        // Give the agent a moment to finish up the completed request.
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    assert_eq!(slow_request.join().unwrap(), 200);
+
+    let threads = threads.lock().unwrap();
+
+    assert_eq!(threads.len(), 4);
+    assert!(threads[1..].iter().all(|name| *name != threads[0]));
+}
+
+#[test]
+fn agent_stats_include_all_agent_threads() {
+    let m = mock!();
+    let client = HttpClient::builder().agent_threads(2).build().unwrap();
+
+    client.get(m.url()).unwrap().consume().unwrap();
+    client.get(m.url()).unwrap().consume().unwrap();
+
+    assert!(client.agent_stats().socket_wakeups() >= 2);
+}
@@ -32,6 +32,375 @@ fn basic_auth_sends_authorization_header() {
         .expect_header("authorization", "Basic Y2xhcms6cXVlcnR5");
 }
 
+#[test]
+fn aws_sigv4_signs_request() {
+    let m = mock!();
+
+    Request::get(m.url())
+        .aws_sigv4(AwsSigV4::new("us-east-1", "s3"))
+        .credentials(Credentials::new("AKIDEXAMPLE", "secret"))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    m.request().expect_header_regex(
+        "authorization",
+        r"^AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/\d{8}/us-east-1/s3/aws4_request, SignedHeaders=\S+, Signature=[0-9a-f]{64}$",
+    );
+    assert_eq!(m.request().get_header("x-amz-date").count(), 1);
+}
+
+#[test]
+fn token_provider_sends_bearer_token() {
+    let m = mock!();
+
+    Request::get(m.url())
+        .token_provider(|| Ok(String::from("hunter2")))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    m.request().expect_header("authorization", "Bearer hunter2");
+}
+
+#[test]
+fn bearer_token_sends_authorization_header() {
+    let m = mock!();
+
+    Request::get(m.url())
+        .bearer_token("hunter2")
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    m.request().expect_header("authorization", "Bearer hunter2");
+}
+
+#[test]
+fn digest_auth_answers_challenge() {
+    let m = mock! {
+        #0 => {
+            status: 401,
+            headers {
+                "www-authenticate": r#"Digest realm="test", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", qop="auth""#,
+            }
+        },
+        _ => {},
+    };
+
+    let response = Request::get(m.url())
+        .authentication(Authentication::digest())
+        .credentials(Credentials::new("clark", "querty"))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    m.requests()[1].expect_header_regex(
+        "authorization",
+        r#"^Digest username="clark", realm="test", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093""#,
+    );
+}
+
+#[test]
+fn ntlm_auth_starts_handshake() {
+    if !isahc::is_ntlm_supported() {
+        return;
+    }
+
+    let m = mock! {
+        status: 401,
+        headers {
+            "www-authenticate": "NTLM",
+        }
+    };
+
+    Request::get(m.url())
+        .authentication(Authentication::ntlm())
+        .credentials(Credentials::new("clark", "querty"))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    // The first message of the handshake is the base64 of "NTLMSSP\0" and
+    // the message type 1.
+    m.requests()[1].expect_header_regex("authorization", "^NTLM TlRMTVNTUAABAAAA");
+}
+
+#[test]
+fn token_provider_does_not_override_explicit_authorization() {
+    let m = mock!();
+
+    Request::get(m.url())
+        .header("authorization", "Custom abc")
+        .token_provider(|| Ok(String::from("hunter2")))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    m.request().expect_header("authorization", "Custom abc");
+}
+
+#[test]
+fn token_provider_error_fails_request() {
+    let m = mock!();
+
+    let result = Request::get(m.url())
+        .token_provider(|| Err(isahc::error::ErrorKind::InvalidCredentials.into()))
+        .body(())
+        .unwrap()
+        .send();
+
+    assert_eq!(
+        result.unwrap_err().kind(),
+        &isahc::error::ErrorKind::InvalidCredentials
+    );
+    assert_eq!(m.requests_received(), 0);
+}
+
+#[test]
+fn authenticator_answers_basic_challenge() {
+    let m = mock! {
+        #0 => {
+            status: 401,
+            headers {
+                "WWW-Authenticate": r#"Basic realm="test""#,
+            }
+        },
+        _ => {},
+    };
+
+    let response = Request::get(m.url())
+        .authenticator(Credentials::new("clark", "querty"))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+
+    let requests = m.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].get_header("authorization").count(), 0);
+    requests[1].expect_header("authorization", "Basic Y2xhcms6cXVlcnR5");
+}
+
+#[test]
+fn authenticator_prefers_digest_challenge() {
+    let m = mock! {
+        #0 => {
+            status: 401,
+            headers {
+                "WWW-Authenticate": r#"Basic realm="test", Digest realm="test", qop="auth", nonce="abc123""#,
+            }
+        },
+        _ => {},
+    };
+
+    let response = Request::get(format!("{}secret?a=b", m.url()))
+        .authenticator(Credentials::new("clark", "querty"))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    m.requests()[1].expect_header_regex(
+        "authorization",
+        r#"^Digest username="clark", realm="test", nonce="abc123", uri="/secret\?a=b", algorithm=MD5, response="[0-9a-f]{32}", qop=auth, nc=00000001, cnonce="[0-9a-f]{16}"$"#,
+    );
+}
+
+#[test]
+fn authenticator_retries_only_once() {
+    let m = mock! {
+        status: 401,
+        headers {
+            "WWW-Authenticate": "Basic",
+        }
+    };
+
+    let response = Request::get(m.url())
+        .authenticator(Credentials::new("clark", "querty"))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), 401);
+    assert_eq!(m.requests_received(), 2);
+}
+
+#[test]
+fn authenticator_ignores_unsupported_challenges() {
+    let m = mock! {
+        status: 401,
+        headers {
+            "WWW-Authenticate": "Bearer",
+        }
+    };
+
+    let response = Request::get(m.url())
+        .authenticator(Credentials::new("clark", "querty"))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), 401);
+    assert_eq!(m.requests_received(), 1);
+}
+
+#[test]
+fn custom_authenticator_answers_custom_scheme() {
+    let m = mock! {
+        #0 => {
+            status: 401,
+            headers {
+                "WWW-Authenticate": "ApiKey header=X-Key",
+            }
+        },
+        _ => {},
+    };
+
+    let response = Request::post(m.url())
+        .authenticator(|challenges: &[Challenge], request: &Request<()>| {
+            assert_eq!(request.method(), "POST");
+            challenges
+                .iter()
+                .find(|c| c.scheme() == "ApiKey" && c.param("header") == Some("X-Key"))
+                .map(|_| String::from("ApiKey hunter2"))
+        })
+        .body("hello")
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+
+    let requests = m.requests();
+    requests[0].expect_body("hello");
+    requests[1].expect_header("authorization", "ApiKey hunter2");
+    requests[1].expect_body("hello");
+}
+
+#[test]
+fn authenticator_does_not_retry_non_rewindable_body() {
+    let m = mock! {
+        status: 401,
+        headers {
+            "WWW-Authenticate": "Basic",
+        }
+    };
+
+    let response = Request::post(m.url())
+        .authenticator(Credentials::new("clark", "querty"))
+        .body(isahc::Body::from_reader("hello".as_bytes()))
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), 401);
+    assert_eq!(m.requests_received(), 1);
+}
+
+#[test]
+fn credential_store_answers_by_origin_and_realm() {
+    let m = mock! {
+        #0 => {
+            status: 401,
+            headers {
+                "WWW-Authenticate": r#"Basic realm="admin""#,
+            }
+        },
+        _ => {},
+    };
+
+    let store = CredentialStore::new();
+    store
+        .insert(m.url(), None, Credentials::new("clark", "querty"))
+        .unwrap();
+    store
+        .insert(m.url(), Some("admin"), Credentials::new("lois", "hunter2"))
+        .unwrap();
+    store
+        .insert("http://example.org", None, Credentials::new("other", "wrong"))
+        .unwrap();
+
+    let response = Request::get(m.url())
+        .authenticator(store)
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    m.requests()[1].expect_header("authorization", "Basic bG9pczpodW50ZXIy");
+}
+
+#[test]
+fn credential_store_without_matching_origin_does_not_retry() {
+    let m = mock! {
+        status: 401,
+        headers {
+            "WWW-Authenticate": "Basic",
+        }
+    };
+
+    let store = CredentialStore::new();
+    store
+        .insert("http://example.org", None, Credentials::new("clark", "querty"))
+        .unwrap();
+
+    let response = Request::get(m.url())
+        .authenticator(store)
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), 401);
+    assert_eq!(m.requests_received(), 1);
+}
+
+#[test]
+fn credential_store_answers_proxy_challenge() {
+    let m = mock! {
+        #0 => {
+            status: 407,
+            headers {
+                "Proxy-Authenticate": r#"Basic realm="proxy""#,
+            }
+        },
+        _ => {},
+    };
+
+    let store = CredentialStore::new();
+    store
+        .insert(m.url(), None, Credentials::new("clark", "querty"))
+        .unwrap();
+
+    let response = Request::get("http://example.org/")
+        .proxy(Some(m.url().parse().unwrap()))
+        .authenticator(store.clone())
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+
+    let requests = m.requests();
+    requests[1].expect_header("proxy-authorization", "Basic Y2xhcms6cXVlcnR5");
+    assert_eq!(requests[1].get_header("authorization").count(), 0);
+}
+
 #[cfg(feature = "spnego")]
 #[test]
 fn negotiate_auth_exists() {
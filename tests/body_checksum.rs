@@ -0,0 +1,106 @@
+use isahc::{config::BodyChecksum, prelude::*, Body, Request};
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    thread,
+};
+use testserver::mock;
+
+#[test]
+fn content_md5_header_is_sent_for_body_in_memory() {
+    let m = mock!();
+
+    Request::put(m.url())
+        .body_checksum(BodyChecksum::ContentMd5)
+        .body("hello world")
+        .unwrap()
+        .send()
+        .unwrap();
+
+    m.request().expect_header("content-md5", "XrY7u+Ae7tCTyyK7j1rNww==");
+    m.request().expect_body("hello world");
+}
+
+#[test]
+fn digest_header_is_sent_for_body_in_memory() {
+    let m = mock!();
+
+    Request::post(m.url())
+        .body_checksum(BodyChecksum::Sha256)
+        .body("hello world")
+        .unwrap()
+        .send()
+        .unwrap();
+
+    m.request().expect_header(
+        "digest",
+        "SHA-256=uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek=",
+    );
+}
+
+#[test]
+fn existing_checksum_header_is_kept() {
+    let m = mock!();
+
+    Request::put(m.url())
+        .body_checksum(BodyChecksum::ContentMd5)
+        .header("content-md5", "custom")
+        .body("hello world")
+        .unwrap()
+        .send()
+        .unwrap();
+
+    m.request().expect_header("content-md5", "custom");
+}
+
+#[test]
+fn checksum_of_streamed_body_is_sent_in_trailer() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    let server = thread::spawn(move || {
+        let mut stream = listener.accept().unwrap().0;
+        let mut request = Vec::new();
+        let mut byte = [0];
+
+        // Read the header, then the chunked body up to the blank line after
+        // the trailers.
+        for _ in 0..2 {
+            stream.read_exact(&mut byte).unwrap();
+            request.push(byte[0]);
+
+            while !request.ends_with(b"\r\n\r\n") {
+                stream.read_exact(&mut byte).unwrap();
+                request.push(byte[0]);
+            }
+        }
+
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+            .unwrap();
+
+        String::from_utf8(request).unwrap()
+    });
+
+    let response = Request::put(url)
+        .body_checksum(BodyChecksum::ContentMd5)
+        .expect_continue(false)
+        .body(Body::from_reader_sized("hello world".as_bytes(), 11))
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+
+    let request = server.join().unwrap();
+    let (header, body) = request.split_once("\r\n\r\n").unwrap();
+    let header = header.to_ascii_lowercase();
+
+    assert!(header.contains("\r\ntransfer-encoding: chunked"));
+    assert!(header.contains("\r\ntrailer: content-md5"));
+    assert!(!header.contains("content-length"));
+    assert_eq!(
+        body,
+        "b\r\nhello world\r\n0\r\ncontent-md5: XrY7u+Ae7tCTyyK7j1rNww==\r\n\r\n"
+    );
+}
@@ -0,0 +1,76 @@
+use futures_lite::{future::block_on, AsyncReadExt};
+use isahc::{prelude::*, Body, HttpClient, Request};
+use std::io::Cursor;
+use testserver::mock;
+
+fn client() -> HttpClient {
+    HttpClient::builder()
+        .caller_thread_execution(true)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn response_is_received_without_agent() {
+    let m = mock! {
+        body: "hello world",
+    };
+
+    let client = client();
+    let mut response = client.get(m.url()).unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.text().unwrap(), "hello world");
+    assert_eq!(client.agent_stats().iterations(), 0);
+}
+
+#[test]
+fn large_response_body_is_read_completely() {
+    let m = mock! {
+        body: "abcdefghij".repeat(100_000),
+    };
+
+    let mut response = client().get(m.url()).unwrap();
+
+    assert_eq!(response.text().unwrap(), "abcdefghij".repeat(100_000));
+}
+
+#[test]
+fn request_body_from_reader_is_sent() {
+    let m = mock!();
+
+    let body = Body::from_reader(Cursor::new(b"hello from a reader".to_vec()));
+    client().post(m.url(), body).unwrap().consume().unwrap();
+
+    m.request().expect_body("hello from a reader");
+}
+
+#[test]
+fn async_request_is_driven_by_its_future() {
+    let m = mock! {
+        body: "hello async",
+    };
+
+    let client = client();
+
+    block_on(async {
+        let mut response = client.get_async(m.url()).await.unwrap();
+        let mut text = String::new();
+        response.body_mut().read_to_string(&mut text).await.unwrap();
+
+        assert_eq!(text, "hello async");
+    });
+}
+
+#[test]
+fn requests_can_be_sent_one_after_another() {
+    let m = mock!();
+    let client = client();
+
+    for _ in 0..3 {
+        let request = Request::get(m.url()).body(()).unwrap();
+        client.send(request).unwrap().consume().unwrap();
+    }
+
+    assert_eq!(m.requests_received(), 3);
+}
@@ -0,0 +1,161 @@
+use futures_lite::future::{block_on, poll_once};
+use isahc::{error::ErrorKind, prelude::*, CancelHandle, CancelReason, HttpClient, Request};
+use std::{
+    io::Read,
+    net::TcpListener,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+use testserver::mock;
+
+#[macro_use]
+mod utils;
+
+#[test]
+fn cancel_handle_aborts_blocking_send() {
+    let m = mock! {
+        delay: 3s,
+    };
+
+    let cancel = CancelHandle::new();
+
+    thread::spawn({
+        let cancel = cancel.clone();
+        move || {
+            thread::sleep(Duration::from_millis(200));
+            cancel.cancel();
+        }
+    });
+
+    let start = Instant::now();
+    let result = Request::get(m.url())
+        .extension(cancel.clone())
+        .body(())
+        .unwrap()
+        .send();
+
+    assert_matches!(result, Err(e) if e == ErrorKind::Interrupted);
+    assert!(start.elapsed() < Duration::from_millis(900));
+    assert!(cancel.is_canceled());
+}
+
+#[test]
+fn cancel_handle_from_future_aborts_async_request() {
+    let m = mock! {
+        delay: 3s,
+    };
+
+    let client = HttpClient::new().unwrap();
+    let future = client.get_async(m.url());
+    let cancel = future.cancel_handle();
+
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        cancel.cancel();
+    });
+
+    let start = Instant::now();
+    let result = block_on(future);
+
+    assert_matches!(result, Err(e) if e == ErrorKind::Interrupted);
+    assert!(start.elapsed() < Duration::from_millis(900));
+}
+
+#[test]
+fn request_canceled_before_sending_fails() {
+    let m = mock! {
+        body: "hello world",
+    };
+
+    let cancel = CancelHandle::new();
+    cancel.cancel();
+
+    let result = Request::get(m.url())
+        .extension(cancel)
+        .body(())
+        .unwrap()
+        .send();
+
+    assert_matches!(result, Err(e) if e == ErrorKind::Interrupted);
+}
+
+#[test]
+fn cancel_reason_is_carried_in_error() {
+    let m = mock! {
+        delay: 3s,
+    };
+
+    let cancel = CancelHandle::new();
+
+    thread::spawn({
+        let cancel = cancel.clone();
+        move || {
+            thread::sleep(Duration::from_millis(200));
+            cancel.cancel_with_reason(CancelReason::Deadline);
+        }
+    });
+
+    let error = Request::get(m.url())
+        .extension(cancel.clone())
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap_err();
+
+    assert_eq!(error, ErrorKind::Interrupted);
+    assert_eq!(error.cancel_reason(), Some(CancelReason::Deadline));
+    assert_eq!(cancel.reason(), Some(CancelReason::Deadline));
+}
+
+#[test]
+fn first_cancel_reason_is_kept() {
+    let cancel = CancelHandle::new();
+    assert_eq!(cancel.reason(), None);
+
+    cancel.cancel_with_reason(CancelReason::Policy);
+    cancel.cancel();
+
+    assert_eq!(cancel.reason(), Some(CancelReason::Policy));
+}
+
+#[test]
+fn other_errors_have_no_cancel_reason() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    drop(listener);
+
+    let error = isahc::get(url).unwrap_err();
+
+    assert_eq!(error.cancel_reason(), None);
+}
+
+#[test]
+fn dropping_response_future_aborts_transfer() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    let (accepted_tx, accepted_rx) = mpsc::channel();
+    let (closed_tx, closed_rx) = mpsc::channel();
+
+    // Never respond, and report once the client closes the connection.
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        accepted_tx.send(()).unwrap();
+
+        let mut buf = [0; 1024];
+        while stream.read(&mut buf).map(|len| len > 0).unwrap_or(false) {}
+
+        closed_tx.send(()).unwrap();
+    });
+
+    let client = HttpClient::new().unwrap();
+    let mut future = client.get_async(url);
+
+    // Poll once to submit the request.
+    assert!(block_on(poll_once(&mut future)).is_none());
+    accepted_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+
+    drop(future);
+
+    closed_rx.recv_timeout(Duration::from_millis(900)).unwrap();
+}
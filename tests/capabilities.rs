@@ -0,0 +1,43 @@
+use isahc::{config::CapabilityCache, HttpClient, Request};
+use testserver::mock;
+
+#[test]
+fn capabilities_are_learned_from_responses() {
+    let m = mock! {
+        headers {
+            "Accept-Ranges": "bytes",
+            "Allow": "GET, HEAD, OPTIONS",
+        }
+    };
+
+    let cache = CapabilityCache::new();
+    let client = HttpClient::builder()
+        .capability_cache(cache.clone())
+        .build()
+        .unwrap();
+
+    let uri = m.url().parse().unwrap();
+    assert!(cache.get(&uri).is_none());
+
+    client
+        .send(Request::options(m.url()).body(()).unwrap())
+        .unwrap();
+
+    let capabilities = cache.get(&uri).unwrap();
+
+    assert_eq!(capabilities.accepts_ranges(), Some(true));
+    assert_eq!(capabilities.http_version(), Some(http::Version::HTTP_11));
+    assert!(!capabilities.supports_http2());
+    assert_eq!(capabilities.allows_method(&http::Method::HEAD), Some(true));
+    assert_eq!(capabilities.allows_method(&http::Method::PUT), Some(false));
+}
+
+#[test]
+fn no_capabilities_are_learned_without_cache() {
+    let m = mock!();
+
+    let client = HttpClient::new().unwrap();
+    client.get(m.url()).unwrap();
+
+    assert!(client.capability_cache().is_none());
+}
@@ -0,0 +1,135 @@
+use isahc::{prelude::*, HttpClient, HttpClientBuilder, TransferStats};
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+use testserver::mock;
+
+/// Build a client that records the statistics of each transfer. Statistics are
+/// delivered before the response body stream ends, so they are complete once
+/// each response is consumed.
+fn client(builder: HttpClientBuilder) -> (HttpClient, Arc<Mutex<Vec<TransferStats>>>) {
+    let stats = Arc::new(Mutex::new(Vec::new()));
+    let stats_clone = stats.clone();
+
+    let client = builder
+        .on_stats(move |s| stats_clone.lock().unwrap().push(s))
+        .build()
+        .unwrap();
+
+    (client, stats)
+}
+
+#[test]
+fn connections_are_reused_by_default() {
+    let m = mock!();
+
+    let (client, stats) = client(HttpClient::builder());
+
+    for _ in 0..3 {
+        client.get(m.url()).unwrap().consume().unwrap();
+    }
+
+    let stats = stats.lock().unwrap();
+    let reused = stats
+        .iter()
+        .map(TransferStats::connection_reused)
+        .collect::<Vec<_>>();
+
+    assert_eq!(reused, [false, true, true]);
+    assert!(stats.iter().all(|s| !s.connection_retired()));
+}
+
+#[test]
+fn connections_are_closed_after_max_requests() {
+    let m = mock!();
+
+    let (client, stats) = client(HttpClient::builder().connection_max_requests(2));
+
+    for _ in 0..5 {
+        client.get(m.url()).unwrap().consume().unwrap();
+    }
+
+    let stats = stats.lock().unwrap();
+    let reused = stats
+        .iter()
+        .map(TransferStats::connection_reused)
+        .collect::<Vec<_>>();
+    let retired = stats
+        .iter()
+        .map(TransferStats::connection_retired)
+        .collect::<Vec<_>>();
+
+    assert_eq!(reused, [false, true, false, true, false]);
+    assert_eq!(retired, [false, true, false, true, false]);
+}
+
+#[test]
+fn connections_are_not_reused_after_max_lifetime() {
+    let m = mock!();
+
+    let (client, stats) = client(
+        HttpClient::builder().connection_max_lifetime(Duration::from_secs(1)),
+    );
+
+    client.get(m.url()).unwrap().consume().unwrap();
+    client.get(m.url()).unwrap().consume().unwrap();
+    thread::sleep(Duration::from_millis(2100));
+    client.get(m.url()).unwrap().consume().unwrap();
+
+    let reused = stats
+        .lock()
+        .unwrap()
+        .iter()
+        .map(TransferStats::connection_reused)
+        .collect::<Vec<_>>();
+
+    assert_eq!(reused, [false, true, false]);
+}
+
+#[test]
+fn cleared_connections_are_not_reused() {
+    let m = mock!();
+
+    let (client, stats) = client(HttpClient::builder());
+
+    client.get(m.url()).unwrap().consume().unwrap();
+    client.get(m.url()).unwrap().consume().unwrap();
+    client.clear_connections().unwrap();
+    client.get(m.url()).unwrap().consume().unwrap();
+    client.get(m.url()).unwrap().consume().unwrap();
+
+    let stats = stats.lock().unwrap();
+    let reused = stats
+        .iter()
+        .map(TransferStats::connection_reused)
+        .collect::<Vec<_>>();
+
+    assert_eq!(reused, [false, true, false, true]);
+}
+
+#[test]
+fn requests_in_progress_complete_after_clearing_connections() {
+    let m = mock! {
+        body: "hello world",
+    };
+
+    let (client, stats) = client(HttpClient::builder());
+
+    let mut response = client.get(m.url()).unwrap();
+    client.clear_connections().unwrap();
+
+    assert_eq!(response.text().unwrap(), "hello world");
+    drop(response);
+
+    client.get(m.url()).unwrap().consume().unwrap();
+
+    let stats = stats.lock().unwrap();
+    let reused = stats
+        .iter()
+        .map(TransferStats::connection_reused)
+        .collect::<Vec<_>>();
+
+    assert_eq!(reused, [false, false]);
+}
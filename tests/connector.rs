@@ -0,0 +1,103 @@
+use isahc::{config::Connector, error::ErrorKind, prelude::*, HttpClient};
+use std::{
+    io,
+    net::{SocketAddr, TcpStream},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+        Mutex,
+    },
+};
+use testserver::mock;
+
+#[test]
+fn connector_opens_connections() {
+    let m = mock!();
+
+    let addrs = Arc::new(Mutex::new(Vec::new()));
+    let client = HttpClient::builder()
+        .connector({
+            let addrs = addrs.clone();
+
+            move |addr| {
+                addrs.lock().unwrap().push(addr);
+                TcpStream::connect(addr)
+            }
+        })
+        .build()
+        .unwrap();
+
+    client.get(m.url()).unwrap().consume().unwrap();
+    client.get(m.url()).unwrap().consume().unwrap();
+
+    // The connection is reused for the second request.
+    assert_eq!(*addrs.lock().unwrap(), [m.addr()]);
+    assert_eq!(m.requests_received(), 2);
+}
+
+#[test]
+fn connector_can_connect_elsewhere() {
+    let m = mock! {
+        body: "hello world",
+    };
+    let target = m.addr();
+
+    let client = HttpClient::builder()
+        .connector(move |_| TcpStream::connect(target))
+        .build()
+        .unwrap();
+
+    // Nothing should be listening on this port.
+    let mut response = client.get("http://127.0.0.1:1").unwrap();
+
+    assert_eq!(response.text().unwrap(), "hello world");
+    m.request().expect_header("host", "127.0.0.1:1");
+}
+
+#[test]
+fn connector_error_fails_request() {
+    let client = HttpClient::builder()
+        .connector(|_| Err(io::ErrorKind::ConnectionRefused.into()))
+        .build()
+        .unwrap();
+
+    let error = client.get("http://127.0.0.1:1").unwrap_err();
+
+    assert_eq!(error.kind(), &ErrorKind::ConnectionFailed);
+}
+
+#[test]
+fn connector_closes_connections() {
+    #[derive(Clone, Default)]
+    struct Counting {
+        opened: Arc<AtomicUsize>,
+        closed: Arc<AtomicUsize>,
+    }
+
+    impl Connector for Counting {
+        fn connect(&self, addr: SocketAddr) -> io::Result<TcpStream> {
+            self.opened.fetch_add(1, Ordering::SeqCst);
+            TcpStream::connect(addr)
+        }
+
+        fn close(&self, stream: TcpStream) {
+            self.closed.fetch_add(1, Ordering::SeqCst);
+            drop(stream);
+        }
+    }
+
+    let m = mock!();
+    let counting = Counting::default();
+
+    let client = HttpClient::builder()
+        .connector(counting.clone())
+        .connection_cache_size(0)
+        .build()
+        .unwrap();
+
+    client.get(m.url()).unwrap().consume().unwrap();
+    client.get(m.url()).unwrap().consume().unwrap();
+
+    assert_eq!(counting.opened.load(Ordering::SeqCst), 2);
+    assert_eq!(counting.closed.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,96 @@
+#![cfg(feature = "blocking")]
+
+use isahc::{diagnose::Stage, error::ErrorKind, HttpClient};
+use std::{
+    io::Write,
+    net::{Ipv4Addr, Shutdown, TcpListener},
+    thread,
+};
+use testserver::mock;
+
+fn stages(diagnosis: &isahc::diagnose::Diagnosis) -> Vec<Stage> {
+    diagnosis.probes().iter().map(|probe| probe.stage()).collect()
+}
+
+#[test]
+fn reachable_server_passes_every_stage() {
+    let m = mock! {
+        status: 204,
+    };
+
+    let diagnosis = HttpClient::new().unwrap().diagnose(m.url()).unwrap();
+
+    assert!(diagnosis.is_ok());
+    assert_eq!(stages(&diagnosis), [Stage::Dns, Stage::Tcp, Stage::Http]);
+    assert_eq!(diagnosis.status(), Some(http::StatusCode::NO_CONTENT));
+    assert!(!diagnosis.addrs().is_empty());
+    assert_eq!(m.request().method(), "HEAD");
+}
+
+#[test]
+fn refused_connection_fails_tcp_stage() {
+    // Bind and immediately close a port so that nothing is listening on it.
+    let port = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let diagnosis = HttpClient::new()
+        .unwrap()
+        .diagnose(format!("http://127.0.0.1:{}", port))
+        .unwrap();
+
+    assert_eq!(diagnosis.failed_stage(), Some(Stage::Tcp));
+    assert_eq!(stages(&diagnosis), [Stage::Dns, Stage::Tcp]);
+    assert_eq!(
+        diagnosis.probe(Stage::Tcp).unwrap().error().unwrap().kind(),
+        ErrorKind::ConnectionFailed
+    );
+}
+
+#[test]
+fn unknown_host_fails_dns_stage() {
+    let diagnosis = HttpClient::new()
+        .unwrap()
+        .diagnose("http://isahc-diagnose-test.invalid")
+        .unwrap();
+
+    assert_eq!(diagnosis.failed_stage(), Some(Stage::Dns));
+    assert_eq!(stages(&diagnosis), [Stage::Dns]);
+    assert_eq!(
+        diagnosis.probe(Stage::Dns).unwrap().error().unwrap().kind(),
+        ErrorKind::NameResolution
+    );
+}
+
+#[test]
+fn plain_http_server_fails_tls_stage() {
+    let server = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let port = server.local_addr().unwrap().port();
+
+    // Reply to every connection with plain HTTP, including the one opened by
+    // the TCP probe.
+    thread::spawn(move || {
+        for stream in server.incoming() {
+            let mut stream = stream.unwrap();
+            let _ = stream.write_all(b"HTTP/1.1 400 Bad Request\r\n\r\n");
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    });
+
+    let diagnosis = HttpClient::new()
+        .unwrap()
+        .diagnose(format!("https://127.0.0.1:{}", port))
+        .unwrap();
+
+    assert_eq!(diagnosis.failed_stage(), Some(Stage::Tls));
+    assert_eq!(stages(&diagnosis), [Stage::Dns, Stage::Tcp, Stage::Tls]);
+}
+
+#[test]
+fn relative_uri_is_rejected() {
+    let error = HttpClient::new().unwrap().diagnose("/path").unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::InvalidRequest);
+}
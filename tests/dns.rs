@@ -0,0 +1,133 @@
+use isahc::{config::ResolveMap, error::ErrorKind, prelude::*, HttpClient};
+use std::{
+    error::Error,
+    io::{Read, Write},
+    net::{Ipv4Addr, Ipv6Addr, TcpListener},
+    thread,
+    time::Duration,
+};
+use testserver::mock;
+
+#[test]
+fn negative_cache_fails_fast_for_unresolvable_host() {
+    let client = HttpClient::builder()
+        .dns_negative_cache(Duration::from_secs(60))
+        .connect_timeout(Duration::from_secs(5))
+        .build()
+        .unwrap();
+
+    let first = client.get("http://isahc-test.invalid/").unwrap_err();
+    assert!(matches!(
+        first.kind(),
+        ErrorKind::NameResolution | ErrorKind::Timeout
+    ));
+
+    let second = client.get("http://ISAHC-TEST.invalid/other").unwrap_err();
+    assert_eq!(second.kind(), first.kind());
+    assert!(second
+        .source()
+        .unwrap()
+        .to_string()
+        .contains("failed recently"));
+}
+
+#[test]
+fn negative_cache_is_disabled_by_default() {
+    let client = HttpClient::new().unwrap();
+
+    for _ in 0..2 {
+        let error = client.get("http://isahc-test.invalid/").unwrap_err();
+
+        if let Some(source) = error.source() {
+            assert!(!source.to_string().contains("failed recently"));
+        }
+    }
+}
+
+#[test]
+fn resolve_map_overrides_host() {
+    let m = mock!();
+
+    let client = HttpClient::builder()
+        .dns_resolve(ResolveMap::new().add(
+            "example.invalid",
+            m.addr().port(),
+            m.addr().ip(),
+        ))
+        .build()
+        .unwrap();
+
+    let response = client
+        .get(format!("http://example.invalid:{}", m.addr().port()))
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.remote_addr(), Some(m.addr()));
+    assert_eq!(
+        m.request().get_header("host").next(),
+        Some(format!("example.invalid:{}", m.addr().port()))
+    );
+}
+
+#[test]
+fn resolve_map_falls_back_to_next_address() {
+    let m = mock!();
+    let port = m.addr().port();
+
+    // Nothing listens on this loopback address, so connecting to it is
+    // refused.
+    let unused = Ipv4Addr::new(127, 0, 0, 2);
+
+    let client = HttpClient::builder()
+        .dns_resolve(ResolveMap::new().add_all(
+            "example.invalid",
+            port,
+            [unused, Ipv4Addr::LOCALHOST],
+        ))
+        .build()
+        .unwrap();
+
+    let response = client.get(format!("http://example.invalid:{}", port)).unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(m.requests_received(), 1);
+}
+
+#[test]
+fn resolve_map_accepts_ipv6_addresses() {
+    let server = match TcpListener::bind((Ipv6Addr::LOCALHOST, 0)) {
+        Ok(server) => server,
+        Err(_) => {
+            eprintln!("skipping test because host does not support IPv6");
+            return;
+        }
+    };
+    let addr = server.local_addr().unwrap();
+
+    let client = HttpClient::builder()
+        .dns_resolve(ResolveMap::new().add("example.invalid", addr.port(), addr.ip()))
+        .build()
+        .unwrap();
+
+    thread::spawn(move || {
+        let (mut stream, _) = server.accept().unwrap();
+        let mut request = Vec::new();
+        let mut byte = [0];
+
+        while !request.ends_with(b"\r\n\r\n") {
+            stream.read_exact(&mut byte).unwrap();
+            request.push(byte[0]);
+        }
+
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+            .unwrap();
+    });
+
+    let response = client
+        .get(format!("http://example.invalid:{}", addr.port()))
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.remote_addr(), Some(addr));
+}
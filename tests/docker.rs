@@ -0,0 +1,137 @@
+#![cfg(all(unix, feature = "docker"))]
+
+use isahc::{
+    docker::{Docker, Frames, StreamType},
+    error::ErrorKind,
+    prelude::*,
+};
+use std::{
+    io::{Read, Write},
+    os::unix::net::UnixListener,
+    thread,
+};
+use tempfile::TempDir;
+
+/// Spawn a fake daemon on a Unix socket that accepts a single connection and
+/// sends the given response head. If the response switches protocols, stdin
+/// received afterwards is echoed back as a stdout frame.
+///
+/// Returns a client for the daemon, the directory holding its socket, and a
+/// handle resolving to the request head and body the daemon received.
+fn daemon(response: &'static str) -> (Docker, TempDir, thread::JoinHandle<String>) {
+    let temp_dir = TempDir::new().unwrap();
+    let socket_path = temp_dir.path().join("docker.sock");
+    let listener = UnixListener::bind(&socket_path).unwrap();
+
+    let handle = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut byte = [0];
+
+        while !request.ends_with(b"\r\n\r\n") {
+            stream.read_exact(&mut byte).unwrap();
+            request.push(byte[0]);
+        }
+
+        let content_length = String::from_utf8_lossy(&request)
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+            .map(|(_, value)| value.trim().parse().unwrap())
+            .unwrap_or(0);
+
+        let mut body = vec![0; content_length];
+        stream.read_exact(&mut body).unwrap();
+        request.extend_from_slice(&body);
+
+        stream.write_all(response.as_bytes()).unwrap();
+
+        if response.starts_with("HTTP/1.1 101") {
+            let mut buf = [0; 5];
+            stream.read_exact(&mut buf).unwrap();
+            stream.write_all(&[1, 0, 0, 0, 0, 0, 0, 5]).unwrap();
+            stream.write_all(&buf).unwrap();
+        }
+
+        String::from_utf8(request).unwrap()
+    });
+
+    let docker = Docker::unix_socket(socket_path).unwrap().version("1.43");
+
+    (docker, temp_dir, handle)
+}
+
+#[test]
+fn api_requests_are_prefixed_with_version() {
+    let (docker, _dir, daemon) = daemon("HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\n[]");
+
+    let mut response = docker.get("/containers/json?all=1").unwrap();
+
+    assert_eq!(response.text().unwrap(), "[]");
+    assert!(daemon
+        .join()
+        .unwrap()
+        .starts_with("GET /v1.43/containers/json?all=1 HTTP/1.1\r\n"));
+}
+
+#[test]
+fn attach_switches_protocols() {
+    let (docker, _dir, daemon) = daemon(
+        "HTTP/1.1 101 UPGRADED\r\nconnection: Upgrade\r\nupgrade: tcp\r\n\r\n",
+    );
+
+    let mut connection = docker.attach("abc123").unwrap();
+    connection.write_all(b"hello").unwrap();
+
+    let frame = Frames::new(&mut connection).next().unwrap().unwrap();
+
+    assert_eq!(frame.stream(), StreamType::Stdout);
+    assert_eq!(frame.data(), b"hello");
+
+    drop(connection);
+
+    let request = daemon.join().unwrap().to_lowercase();
+
+    assert!(request.starts_with(
+        "post /v1.43/containers/abc123/attach?stream=1&stdin=1&stdout=1&stderr=1 http/1.1\r\n"
+    ));
+    assert!(request.contains("upgrade: tcp\r\n"));
+}
+
+#[test]
+fn exec_start_sends_body_before_switching_protocols() {
+    let (docker, _dir, daemon) = daemon(
+        "HTTP/1.1 101 UPGRADED\r\nconnection: Upgrade\r\nupgrade: tcp\r\n\r\n",
+    );
+
+    let mut connection = docker.exec_start("def456", false).unwrap();
+    connection.write_all(b"hello").unwrap();
+
+    let frame = Frames::new(&mut connection).next().unwrap().unwrap();
+
+    assert_eq!(frame.data(), b"hello");
+
+    drop(connection);
+
+    let request = daemon.join().unwrap();
+
+    assert!(request.starts_with("POST /v1.43/exec/def456/start HTTP/1.1\r\n"));
+    assert!(request.ends_with(r#"{"Detach":false,"Tty":false}"#));
+}
+
+#[test]
+fn attach_error_includes_response_body() {
+    let (docker, _dir, daemon) = daemon(
+        "HTTP/1.1 404 Not Found\r\ncontent-length: 40\r\n\r\n{\"message\":\"No such container: abc123\"}\n",
+    );
+
+    let error = docker.attach("abc123").unwrap_err();
+
+    assert_eq!(error.kind(), &ErrorKind::BadStatus);
+    assert_eq!(
+        error.response().unwrap().body(),
+        b"{\"message\":\"No such container: abc123\"}\n"
+    );
+
+    daemon.join().unwrap();
+}
@@ -0,0 +1,481 @@
+#![cfg(feature = "blocking")]
+
+use isahc::{
+    download::{Digest, FileUpdate, SaveOptions, TempFileOptions},
+    error::ErrorKind,
+    http::{HeaderValue, Response},
+    prelude::*,
+    HttpClient,
+};
+use std::{
+    fs,
+    io::{self, Read, Seek, SeekFrom},
+};
+use testserver::mock;
+
+/// Digest that sums up all bytes, for testing.
+struct Sum(u8);
+
+impl Digest for Sum {
+    fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.0 = self.0.wrapping_add(byte);
+        }
+    }
+
+    fn finish(&mut self) -> Vec<u8> {
+        vec![self.0]
+    }
+}
+
+fn sum(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, &byte| sum.wrapping_add(byte))
+}
+
+#[test]
+fn download_writes_body_into_writer() {
+    let m = mock! {
+        headers {
+            "etag": "\"v1\"",
+        }
+        body: "hello world",
+    };
+
+    let mut buffer = Vec::new();
+    let info = HttpClient::new()
+        .unwrap()
+        .download(m.url())
+        .write_to(&mut buffer)
+        .unwrap();
+
+    assert_eq!(buffer, b"hello world");
+    assert_eq!(info.offset(), 0);
+    assert_eq!(info.len(), 11);
+    assert_eq!(info.total_len(), Some(11));
+    assert_eq!(info.validator().unwrap(), "\"v1\"");
+    assert!(m.request().get_header("range").next().is_none());
+}
+
+#[test]
+fn download_is_resumed_with_range() {
+    let m = mock! {
+        status: 206,
+        headers {
+            "content-range": "bytes 6-10/11",
+        }
+        body: "world",
+    };
+
+    let mut buffer = b"hello ".to_vec();
+    let info = HttpClient::new()
+        .unwrap()
+        .download(m.url())
+        .resume_from(6)
+        .if_range(HeaderValue::from_static("\"v1\""))
+        .write_to(&mut buffer)
+        .unwrap();
+
+    assert_eq!(buffer, b"hello world");
+    assert_eq!(info.offset(), 6);
+    assert_eq!(info.len(), 5);
+    assert_eq!(info.total_len(), Some(11));
+
+    m.request().expect_header("range", "bytes=6-");
+    m.request().expect_header("if-range", "\"v1\"");
+}
+
+#[test]
+fn partial_response_at_wrong_offset_is_rejected() {
+    let m = mock! {
+        status: 206,
+        headers {
+            "content-range": "bytes 0-10/11",
+        }
+        body: "hello world",
+    };
+
+    let mut buffer = b"hello ".to_vec();
+    let error = HttpClient::new()
+        .unwrap()
+        .download(m.url())
+        .resume_from(6)
+        .write_to(&mut buffer)
+        .unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::ProtocolViolation);
+    assert_eq!(buffer, b"hello ");
+}
+
+#[test]
+fn changed_resource_is_not_resumed() {
+    let m = mock! {
+        body: "goodbye world",
+    };
+
+    let mut buffer = b"hello ".to_vec();
+    let error = HttpClient::new()
+        .unwrap()
+        .download(m.url())
+        .resume_from(6)
+        .if_range(HeaderValue::from_static("\"v1\""))
+        .write_to(&mut buffer)
+        .unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::ResourceChanged);
+    assert_eq!(buffer, b"hello ");
+}
+
+#[test]
+fn downloaded_bytes_are_skipped_if_server_ignores_range() {
+    let m = mock! {
+        body: "hello world",
+    };
+
+    let mut buffer = b"hello ".to_vec();
+    let info = HttpClient::new()
+        .unwrap()
+        .download(m.url())
+        .resume_from(6)
+        .write_to(&mut buffer)
+        .unwrap();
+
+    assert_eq!(buffer, b"hello world");
+    assert_eq!(info.offset(), 6);
+    assert_eq!(info.len(), 5);
+
+    let requests = m.requests();
+    assert_eq!(requests.len(), 2);
+    requests[0].expect_header("range", "bytes=6-");
+    assert!(requests[1].get_header("range").next().is_none());
+}
+
+#[test]
+fn completed_download_is_not_downloaded_again() {
+    let m = mock! {
+        status: 416,
+        headers {
+            "content-range": "bytes */11",
+        }
+    };
+
+    let mut buffer = b"hello world".to_vec();
+    let info = HttpClient::new()
+        .unwrap()
+        .download(m.url())
+        .resume_from(11)
+        .write_to(&mut buffer)
+        .unwrap();
+
+    assert_eq!(buffer, b"hello world");
+    assert!(info.is_empty());
+    assert_eq!(info.total_len(), Some(11));
+}
+
+#[test]
+fn error_status_is_not_written() {
+    let m = mock! {
+        status: 404,
+        body: "not found",
+    };
+
+    let mut buffer = Vec::new();
+    let error = HttpClient::new()
+        .unwrap()
+        .download(m.url())
+        .write_to(&mut buffer)
+        .unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::BadStatus);
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn update_file_downloads_missing_file() {
+    let m = mock! {
+        body: "hello world",
+    };
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file.txt");
+
+    let update = HttpClient::new()
+        .unwrap()
+        .download(m.url())
+        .update_file(&path)
+        .unwrap();
+
+    assert!(matches!(update, FileUpdate::Downloaded(info) if info.len() == 11));
+    assert_eq!(fs::read(&path).unwrap(), b"hello world");
+
+    let requests = m.requests();
+    assert_eq!(requests[0].method(), "HEAD");
+    assert_eq!(requests[1].method(), "GET");
+}
+
+#[test]
+fn update_file_skips_unchanged_file() {
+    let m = mock! {
+        headers {
+            "last-modified": "Wed, 21 Oct 2015 07:28:00 GMT",
+        }
+        body: "hello world",
+    };
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file.txt");
+    fs::write(&path, "hello world").unwrap();
+
+    let update = HttpClient::new()
+        .unwrap()
+        .download(m.url())
+        .update_file(&path)
+        .unwrap();
+
+    assert!(matches!(update, FileUpdate::Unchanged));
+    assert_eq!(m.requests_received(), 1);
+}
+
+#[test]
+fn update_file_replaces_file_with_stale_validator() {
+    let m = mock! {
+        headers {
+            "etag": "\"v2\"",
+        }
+        body: "hello world",
+    };
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file.txt");
+    fs::write(&path, "hello earth").unwrap();
+
+    let update = HttpClient::new()
+        .unwrap()
+        .download(m.url())
+        .if_range(HeaderValue::from_static("\"v1\""))
+        .update_file(&path)
+        .unwrap();
+
+    match update {
+        FileUpdate::Downloaded(info) => {
+            assert_eq!(info.validator().unwrap(), "\"v2\"");
+        }
+        update => panic!("unexpected update: {:?}", update),
+    }
+
+    assert_eq!(fs::read(&path).unwrap(), b"hello world");
+}
+
+#[test]
+fn update_file_appends_tail_of_append_only_resource() {
+    let m = mock! {
+        #0 => {
+            body: "hello world",
+        },
+        #1 => {
+            status: 206,
+            headers {
+                "content-range": "bytes 6-10/11",
+            }
+            body: "world",
+        },
+    };
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file.log");
+    fs::write(&path, "hello ").unwrap();
+
+    let update = HttpClient::new()
+        .unwrap()
+        .download(m.url())
+        .append_only(true)
+        .update_file(&path)
+        .unwrap();
+
+    assert!(matches!(update, FileUpdate::Appended(info) if info.offset() == 6));
+    assert_eq!(fs::read(&path).unwrap(), b"hello world");
+
+    m.requests()[1].expect_header("range", "bytes=6-");
+}
+
+#[test]
+fn save_to_reports_progress_and_verifies_digest() {
+    let mut response = Response::builder()
+        .header("content-length", "11")
+        .body(io::Cursor::new("hello world"))
+        .unwrap();
+    let mut progress = Vec::new();
+    let mut buffer = Vec::new();
+
+    let summary = response
+        .save_to(
+            &mut buffer,
+            SaveOptions::new()
+                .max_size(11)
+                .on_progress(|p| progress.push((p.written(), p.total_len())))
+                .verify(Sum(0), vec![sum(b"hello world")]),
+        )
+        .unwrap();
+
+    assert_eq!(buffer, b"hello world");
+    assert_eq!(summary.len(), 11);
+    assert_eq!(summary.digest(), Some(&[sum(b"hello world")][..]));
+    assert_eq!(progress.last(), Some(&(11, Some(11))));
+}
+
+#[test]
+fn save_to_fails_on_digest_mismatch() {
+    let mut response = Response::new(io::Cursor::new("hello world"));
+
+    let error = response
+        .save_to(
+            io::sink(),
+            SaveOptions::new().verify(Sum(0), vec![sum(b"hello world").wrapping_add(1)]),
+        )
+        .unwrap_err();
+
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn save_to_rejects_declared_length_over_limit() {
+    let mut response = Response::builder()
+        .header("content-length", "11")
+        .body(io::Cursor::new("hello world"))
+        .unwrap();
+    let mut buffer = Vec::new();
+
+    let error = response
+        .save_to(&mut buffer, SaveOptions::new().max_size(5))
+        .unwrap_err();
+
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn save_to_file_removes_file_over_limit() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("body");
+    let mut response = Response::new(io::Cursor::new(vec![0; 64 * 1024]));
+
+    let error = response
+        .save_to_file(&path, SaveOptions::new().max_size(1024))
+        .unwrap_err();
+
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    assert!(!path.exists());
+
+    let mut response = Response::new(io::Cursor::new("hello world"));
+    let summary = response.save_to_file(&path, SaveOptions::new()).unwrap();
+
+    assert_eq!(summary.len(), 11);
+    assert_eq!(fs::read(&path).unwrap(), b"hello world");
+}
+
+#[test]
+fn save_to_file_through_temp_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("body");
+    let options = SaveOptions::new().temp_file(TempFileOptions::new());
+
+    let mut response = Response::new(io::Cursor::new(vec![0; 64 * 1024]));
+    response
+        .save_to_file(&path, options.max_size(1024))
+        .unwrap_err();
+
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+
+    let options = SaveOptions::new().temp_file(TempFileOptions::new());
+    let mut response = Response::new(io::Cursor::new("hello world"));
+    response.save_to_file(&path, options).unwrap();
+
+    assert_eq!(fs::read(&path).unwrap(), b"hello world");
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn spooled_file_is_removed_when_dropped() {
+    let dir = tempfile::tempdir().unwrap();
+    let options = SaveOptions::new().temp_file(TempFileOptions::new().dir(dir.path()));
+    let mut response = Response::new(io::Cursor::new("hello world"));
+
+    let mut spooled = response.spool(options).unwrap();
+    let path = spooled.path().unwrap().to_owned();
+
+    assert!(path.starts_with(dir.path()));
+    assert_eq!(spooled.summary().len(), 11);
+
+    let mut body = String::new();
+    spooled.read_to_string(&mut body).unwrap();
+    assert_eq!(body, "hello world");
+
+    spooled.seek(SeekFrom::Start(6)).unwrap();
+    body.clear();
+    spooled.read_to_string(&mut body).unwrap();
+    assert_eq!(body, "world");
+
+    drop(spooled);
+    assert!(!path.exists());
+}
+
+#[test]
+#[cfg(unix)]
+fn anonymous_spooled_file_has_no_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let options = TempFileOptions::new().dir(dir.path()).anonymous(true);
+    let mut response = Response::new(io::Cursor::new("secret"));
+
+    let mut spooled = response.spool(SaveOptions::new().temp_file(options)).unwrap();
+
+    assert_eq!(spooled.path(), None);
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+
+    let mut body = String::new();
+    spooled.read_to_string(&mut body).unwrap();
+    assert_eq!(body, "secret");
+}
+
+#[test]
+#[cfg(unix)]
+fn temp_file_mode_is_applied() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut response = Response::new(io::Cursor::new("hello"));
+    let spooled = response
+        .spool(SaveOptions::new().temp_file(TempFileOptions::new().dir(dir.path())))
+        .unwrap();
+    let mode = fs::metadata(spooled.path().unwrap()).unwrap().permissions().mode();
+
+    assert_eq!(mode & 0o777, 0o600);
+
+    let path = dir.path().join("body");
+    let options = TempFileOptions::new().mode(0o664);
+    let mut response = Response::new(io::Cursor::new("hello"));
+    response
+        .save_to_file(&path, SaveOptions::new().temp_file(options))
+        .unwrap();
+    let mode = fs::metadata(&path).unwrap().permissions().mode();
+
+    assert_eq!(mode & 0o777, 0o664);
+}
+
+#[test]
+fn update_file_through_temp_file() {
+    let m = mock! {
+        body: "hello world",
+    };
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file.txt");
+
+    HttpClient::new()
+        .unwrap()
+        .download(m.url())
+        .temp_file(TempFileOptions::new())
+        .update_file(&path)
+        .unwrap();
+
+    assert_eq!(fs::read(&path).unwrap(), b"hello world");
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
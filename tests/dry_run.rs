@@ -0,0 +1,42 @@
+use futures_lite::{future::block_on, AsyncReadExt};
+use isahc::{HttpClient, Request};
+use testserver::mock;
+
+#[test]
+fn dry_run_returns_prepared_request_without_sending_it() {
+    let m = mock!();
+
+    let client = HttpClient::builder()
+        .default_header("x-api-key", "secret")
+        .build()
+        .unwrap();
+
+    let mut request = client
+        .dry_run(Request::post(m.url()).body("hello").unwrap())
+        .unwrap();
+
+    assert_eq!(request.method(), "POST");
+    assert_eq!(request.uri().to_string(), m.url());
+    assert_eq!(request.headers()["x-api-key"], "secret");
+    assert!(request.headers().contains_key("user-agent"));
+
+    let mut body = String::new();
+    block_on(request.body_mut().read_to_string(&mut body)).unwrap();
+    assert_eq!(body, "hello");
+
+    assert_eq!(m.requests_received(), 0);
+}
+
+#[test]
+fn dry_run_async_returns_prepared_request() {
+    let m = mock!();
+    let client = HttpClient::new().unwrap();
+
+    let request = block_on(
+        client.dry_run_async(Request::get(m.url()).header("x-test", "yes").body(()).unwrap()),
+    )
+    .unwrap();
+
+    assert_eq!(request.headers()["x-test"], "yes");
+    assert_eq!(m.requests_received(), 0);
+}
@@ -0,0 +1,87 @@
+use isahc::{prelude::*, Body, Request};
+use std::{
+    io::{self, Read, Write},
+    net::TcpListener,
+    thread,
+};
+use testserver::mock;
+
+const BODY_LEN: u64 = 256 * 1024 * 1024;
+
+/// Start a server that rejects the first request with the given response as
+/// soon as it has received the request header, then counts how many bytes of
+/// the request body still arrive before the client closes the connection.
+fn rejecting_server(response: &'static [u8]) -> (String, thread::JoinHandle<u64>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    let handle = thread::spawn(move || {
+        let mut stream = listener.accept().unwrap().0;
+        let mut header = Vec::new();
+        let mut byte = [0];
+
+        while !header.ends_with(b"\r\n\r\n") {
+            stream.read_exact(&mut byte).unwrap();
+            header.push(byte[0]);
+        }
+
+        stream.write_all(response).unwrap();
+
+        io::copy(&mut stream, &mut io::sink()).unwrap_or_default()
+    });
+
+    (url, handle)
+}
+
+fn large_upload(url: String) -> Request<Body> {
+    Request::put(url)
+        .expect_continue(false)
+        .body(Body::from_reader_sized(io::repeat(0).take(BODY_LEN), BODY_LEN))
+        .unwrap()
+}
+
+#[test]
+fn upload_stops_when_request_is_rejected() {
+    let (url, server) = rejecting_server(
+        b"HTTP/1.1 413 Payload Too Large\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+    );
+
+    let response = large_upload(url).send().unwrap();
+
+    assert_eq!(response.status(), 413);
+
+    let early = response.early_response().unwrap();
+    assert!(early.bytes_sent() < BODY_LEN);
+    assert_eq!(early.body_len(), Some(BODY_LEN));
+
+    drop(response);
+    assert!(server.join().unwrap() < BODY_LEN);
+}
+
+#[test]
+fn upload_stops_when_request_is_unauthorized() {
+    let (url, server) =
+        rejecting_server(b"HTTP/1.1 401 Unauthorized\r\ncontent-length: 2\r\n\r\nno");
+
+    let mut response = large_upload(url).send().unwrap();
+
+    assert_eq!(response.status(), 401);
+    assert_eq!(response.text().unwrap(), "no");
+    assert!(response.early_response().unwrap().bytes_sent() < BODY_LEN);
+
+    drop(response);
+    assert!(server.join().unwrap() < BODY_LEN);
+}
+
+#[test]
+fn complete_upload_is_not_early() {
+    let m = mock! {
+        status: 413,
+    };
+
+    let response = isahc::put(m.url(), "hello world").unwrap();
+
+    assert_eq!(response.status(), 413);
+    assert_eq!(response.early_response(), None);
+    m.request().expect_body("hello world");
+}
@@ -2,8 +2,13 @@ use flate2::{
     read::{DeflateEncoder, GzEncoder},
     Compression,
 };
-use isahc::{prelude::*, Request};
-use std::io::Read;
+use isahc::{
+    config::{CodingStream, ContentCoding, ContentCodings, DecompressionLimit},
+    prelude::*,
+    HttpClient,
+    Request,
+};
+use std::io::{self, Read};
 use testserver::mock;
 
 #[test]
@@ -25,6 +30,7 @@ fn gzip_encoded_response_is_decoded_automatically() {
     let mut response = isahc::get(m.url()).unwrap();
 
     assert_eq!(response.text().unwrap(), body);
+    assert_eq!(response.content_encoding(), None);
     m.request()
         .expect_header("Accept-Encoding", "deflate, gzip");
 
@@ -63,12 +69,33 @@ fn request_gzip_without_automatic_decompression() {
     response.body_mut().read_to_end(&mut body_received).unwrap();
 
     assert_eq!(body_received, body_encoded);
+    assert_eq!(response.content_encoding(), Some("gzip"));
     m.request().expect_header("Accept-Encoding", "gzip");
 
     // Response body size should be known.
     assert_eq!(response.body().len(), Some(body_encoded.len() as u64));
 }
 
+#[test]
+fn unencoded_response_without_automatic_decompression_has_no_encoding() {
+    let m = mock! {
+        headers {
+            "Content-Encoding": "identity",
+        }
+        body: "hello world",
+    };
+
+    let mut response = Request::get(m.url())
+        .automatic_decompression(false)
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.content_encoding(), None);
+    assert_eq!(response.text().unwrap(), "hello world");
+}
+
 #[test]
 fn deflate_encoded_response_is_decoded_automatically() {
     let body = "hello world";
@@ -145,3 +172,400 @@ fn unknown_content_encoding_returns_error() {
 
     m.request().expect_header("Accept-Encoding", "deflate");
 }
+
+#[test]
+fn passthrough_delivers_encoded_body_with_caller_accept_encoding() {
+    let body = "hello world";
+    let mut body_encoded = Vec::new();
+
+    GzEncoder::new(body.as_bytes(), Compression::default())
+        .read_to_end(&mut body_encoded)
+        .unwrap();
+
+    let m = {
+        let body_encoded = body_encoded.clone();
+        mock! {
+            headers {
+                "Content-Encoding": "gzip",
+            }
+            body: body_encoded.clone(),
+        }
+    };
+
+    let client = HttpClient::builder()
+        .content_encoding_passthrough(true)
+        .build()
+        .unwrap();
+
+    // Pass-through takes precedence even when decompression is enabled on the
+    // request itself.
+    let mut response = client
+        .send(
+            Request::get(m.url())
+                .header("Accept-Encoding", "br, gzip")
+                .automatic_decompression(true)
+                .body(())
+                .unwrap(),
+        )
+        .unwrap();
+
+    assert_eq!(response.headers()["content-encoding"], "gzip");
+    assert_eq!(
+        response.headers()["content-length"],
+        body_encoded.len().to_string().as_str()
+    );
+    assert_eq!(response.content_encoding(), Some("gzip"));
+    assert_eq!(response.body().len(), Some(body_encoded.len() as u64));
+    assert_eq!(response.bytes().unwrap(), body_encoded);
+    m.request().expect_header("Accept-Encoding", "br, gzip");
+}
+
+#[test]
+fn passthrough_does_not_add_accept_encoding() {
+    let m = mock! {
+        body: "hello world",
+    };
+
+    let mut response = Request::get(m.url())
+        .content_encoding_passthrough(true)
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.text().unwrap(), "hello world");
+    assert_eq!(m.request().get_header("Accept-Encoding").count(), 0);
+}
+
+#[test]
+fn passthrough_allows_unknown_content_encoding() {
+    let m = mock! {
+        headers {
+            "Content-Encoding": "foo",
+        }
+        body: "hello world",
+    };
+
+    let mut response = Request::get(m.url())
+        .header("Accept-Encoding", "foo")
+        .content_encoding_passthrough(true)
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.content_encoding(), Some("foo"));
+    assert_eq!(response.text().unwrap(), "hello world");
+}
+
+fn gzip_bomb(len: usize) -> Vec<u8> {
+    let mut body_encoded = Vec::new();
+
+    GzEncoder::new(io::repeat(0).take(len as u64), Compression::best())
+        .read_to_end(&mut body_encoded)
+        .unwrap();
+
+    body_encoded
+}
+
+fn expect_decompression_limit_exceeded(result: io::Result<u64>) {
+    let error = result.unwrap_err();
+
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(
+        error
+            .into_inner()
+            .unwrap()
+            .downcast::<isahc::Error>()
+            .unwrap()
+            .kind(),
+        &isahc::error::ErrorKind::DecompressionLimitExceeded
+    );
+}
+
+#[test]
+fn decompressed_size_limit_aborts_response() {
+    let body_encoded = gzip_bomb(8 * 1024 * 1024);
+
+    let m = mock! {
+        headers {
+            "Content-Encoding": "gzip",
+        }
+        body: body_encoded.clone(),
+    };
+
+    let mut response = Request::get(m.url())
+        .decompression_limit(DecompressionLimit::new().max_size(1024 * 1024))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    expect_decompression_limit_exceeded(response.copy_to(io::sink()));
+}
+
+#[test]
+fn decompression_ratio_limit_aborts_response() {
+    let body_encoded = gzip_bomb(8 * 1024 * 1024);
+
+    let m = mock! {
+        headers {
+            "Content-Encoding": "gzip",
+        }
+        body: body_encoded.clone(),
+    };
+
+    let mut response = Request::get(m.url())
+        .decompression_limit(DecompressionLimit::new().max_ratio(100))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    expect_decompression_limit_exceeded(response.copy_to(io::sink()));
+}
+
+#[test]
+fn decompression_limit_does_not_apply_to_uncompressed_responses() {
+    let m = mock! {
+        body: vec![0; 2 * 1024 * 1024],
+    };
+
+    let mut response = Request::get(m.url())
+        .decompression_limit(DecompressionLimit::new().max_size(1024 * 1024))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.copy_to(io::sink()).unwrap(), 2 * 1024 * 1024);
+}
+
+#[cfg(feature = "content-decoding")]
+#[test]
+fn matching_content_type_is_decoded_with_filter() {
+    let body = "{\"hello\":\"world\"}";
+    let mut body_encoded = Vec::new();
+
+    GzEncoder::new(body.as_bytes(), Compression::default())
+        .read_to_end(&mut body_encoded)
+        .unwrap();
+
+    let m = mock! {
+        headers {
+            "Content-Type": "application/json; charset=utf-8",
+            "Content-Encoding": "gzip",
+        }
+        body: body_encoded.clone(),
+    };
+
+    let mut response = Request::get(m.url())
+        .decompress_content_types(["application/json", "text/*"])
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.body().len(), None);
+    assert_eq!(response.content_encoding(), None);
+    assert_eq!(response.text().unwrap(), body);
+    m.request()
+        .expect_header("Accept-Encoding", "deflate, gzip");
+}
+
+#[cfg(feature = "content-decoding")]
+#[test]
+fn other_content_types_are_left_encoded_with_filter() {
+    let mut body_encoded = Vec::new();
+
+    GzEncoder::new(&b"tar archive"[..], Compression::default())
+        .read_to_end(&mut body_encoded)
+        .unwrap();
+
+    let m = {
+        let body_encoded = body_encoded.clone();
+        mock! {
+            headers {
+                "Content-Type": "application/gzip",
+                "Content-Encoding": "gzip",
+            }
+            body: body_encoded.clone(),
+        }
+    };
+
+    let mut response = Request::get(m.url())
+        .decompress_content_types(["application/json", "text/*"])
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.body().len(), Some(body_encoded.len() as u64));
+    assert_eq!(response.content_encoding(), Some("gzip"));
+    assert_eq!(response.bytes().unwrap(), body_encoded);
+}
+
+#[cfg(feature = "content-decoding")]
+#[test]
+fn raw_deflate_is_decoded_with_filter() {
+    let body = "hello world";
+    let mut body_encoded = Vec::new();
+
+    DeflateEncoder::new(body.as_bytes(), Compression::default())
+        .read_to_end(&mut body_encoded)
+        .unwrap();
+
+    let m = mock! {
+        headers {
+            "Content-Type": "text/plain",
+            "Content-Encoding": "deflate",
+        }
+        body: body_encoded.clone(),
+    };
+
+    let mut response = Request::get(m.url())
+        .decompress_content_types(["text/*"])
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.text().unwrap(), body);
+}
+
+#[cfg(feature = "content-decoding")]
+#[test]
+fn decompressed_size_limit_applies_with_filter() {
+    let body_encoded = gzip_bomb(8 * 1024 * 1024);
+
+    let m = mock! {
+        headers {
+            "Content-Type": "text/plain",
+            "Content-Encoding": "gzip",
+        }
+        body: body_encoded.clone(),
+    };
+
+    let mut response = Request::get(m.url())
+        .decompress_content_types(["text/plain"])
+        .decompression_limit(DecompressionLimit::new().max_size(1024 * 1024))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    expect_decompression_limit_exceeded(response.copy_to(io::sink()));
+}
+
+/// A content coding that flips every bit.
+struct Invert;
+
+impl CodingStream for Invert {
+    fn write(&mut self, input: &[u8], output: &mut Vec<u8>) -> io::Result<()> {
+        output.extend(input.iter().map(|byte| !byte));
+        Ok(())
+    }
+
+    fn finish(&mut self, _output: &mut Vec<u8>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl ContentCoding for Invert {
+    fn decoder(&self) -> Box<dyn CodingStream> {
+        Box::new(Invert)
+    }
+
+    fn encoder(&self) -> Box<dyn CodingStream> {
+        Box::new(Invert)
+    }
+}
+
+fn invert(data: &[u8]) -> Vec<u8> {
+    data.iter().map(|byte| !byte).collect()
+}
+
+#[test]
+fn custom_content_coding_is_decoded() {
+    let m = mock! {
+        headers {
+            "Content-Encoding": "x-invert",
+        }
+        body: invert(b"hello world"),
+    };
+
+    let client = HttpClient::builder()
+        .content_codings(ContentCodings::new().register("x-invert", Invert))
+        .build()
+        .unwrap();
+
+    let mut response = client.get(m.url()).unwrap();
+
+    assert_eq!(response.content_encoding(), None);
+    assert_eq!(response.text().unwrap(), "hello world");
+
+    if cfg!(feature = "content-decoding") {
+        m.request()
+            .expect_header("Accept-Encoding", "deflate, gzip, x-invert");
+    } else {
+        m.request().expect_header("Accept-Encoding", "x-invert");
+    }
+}
+
+#[cfg(feature = "content-decoding")]
+#[test]
+fn built_in_codings_are_decoded_with_custom_codings() {
+    let mut body_encoded = Vec::new();
+
+    GzEncoder::new(&b"hello world"[..], Compression::default())
+        .read_to_end(&mut body_encoded)
+        .unwrap();
+
+    let m = mock! {
+        headers {
+            "Content-Encoding": "gzip",
+        }
+        body: body_encoded.clone(),
+    };
+
+    let mut response = Request::get(m.url())
+        .content_codings(ContentCodings::new().register("x-invert", Invert))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.text().unwrap(), "hello world");
+}
+
+#[test]
+fn request_body_is_encoded_with_custom_coding() {
+    let m = mock!();
+
+    Request::post(m.url())
+        .content_codings(ContentCodings::new().register("x-invert", Invert))
+        .header("Content-Encoding", "x-invert")
+        .body("hello world")
+        .unwrap()
+        .send()
+        .unwrap();
+
+    m.request().expect_header("Content-Encoding", "x-invert");
+    m.request().expect_header("Transfer-Encoding", "chunked");
+    m.request().expect_body(invert(b"hello world"));
+}
+
+#[test]
+fn invalid_custom_coding_name_is_rejected() {
+    let m = mock!();
+
+    let error = Request::get(m.url())
+        .content_codings(ContentCodings::new().register("x invert", Invert))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap_err();
+
+    assert_eq!(error.kind(), &isahc::error::ErrorKind::InvalidRequest);
+    assert_eq!(m.requests_received(), 0);
+}
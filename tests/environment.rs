@@ -0,0 +1,72 @@
+use isahc::{config::Environment, prelude::*, HttpClient, Request};
+use testserver::mock;
+
+/// A proxy that refuses connections.
+const DEAD_PROXY: &str = "http://127.0.0.1:1";
+
+#[test]
+fn proxy_is_read_from_environment() {
+    let m = mock!();
+    let upstream = "http://127.0.0.2:1234/";
+
+    let client = HttpClient::builder()
+        .environment(Environment::isolated().var("http_proxy", m.url()))
+        .build()
+        .unwrap();
+
+    client.get(upstream).unwrap();
+
+    assert_eq!(m.request().url(), upstream);
+}
+
+#[test]
+fn no_proxy_is_read_from_environment() {
+    let m = mock!();
+
+    let client = HttpClient::builder()
+        .environment(
+            Environment::isolated()
+                .var("http_proxy", DEAD_PROXY)
+                .var("no_proxy", "localhost,127.0.0.1"),
+        )
+        .build()
+        .unwrap();
+
+    client.get(m.url()).unwrap();
+
+    assert_eq!(m.requests_received(), 1);
+}
+
+#[test]
+fn explicit_proxy_takes_precedence() {
+    let m = mock!();
+
+    let client = HttpClient::builder()
+        .environment(Environment::isolated().var("http_proxy", DEAD_PROXY))
+        .build()
+        .unwrap();
+
+    client
+        .send(Request::get(m.url()).proxy(None).body(()).unwrap())
+        .unwrap();
+
+    assert_eq!(m.requests_received(), 1);
+}
+
+#[test]
+fn isolated_environment_ignores_process_proxy() {
+    let m = mock!();
+
+    // Only clients with an environment of their own are used in this file, so
+    // this does not affect the other tests.
+    std::env::set_var("http_proxy", DEAD_PROXY);
+
+    let client = HttpClient::builder()
+        .environment(Environment::isolated())
+        .build()
+        .unwrap();
+
+    client.get(m.url()).unwrap();
+
+    assert_eq!(m.requests_received(), 1);
+}
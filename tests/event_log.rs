@@ -0,0 +1,99 @@
+use isahc::{prelude::*, HttpClient};
+use serde_json::Value;
+use std::{
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+use testserver::mock;
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn events(&self) -> Vec<Value> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn request_lifecycle_is_logged() {
+    let m = mock! {
+        body: "hello world",
+    };
+
+    let buffer = SharedBuffer::default();
+    let client = HttpClient::builder()
+        .event_log(buffer.clone())
+        .build()
+        .unwrap();
+
+    let mut response = client.get(m.url()).unwrap();
+    response.text().unwrap();
+
+    let events = buffer.events();
+    let names = events
+        .iter()
+        .map(|event| event["event"].as_str().unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(names, ["request", "response", "body_complete"]);
+    assert!(events.iter().all(|event| event["id"] == events[0]["id"]));
+
+    assert_eq!(events[0]["method"], "GET");
+    assert_eq!(events[0]["uri"], m.url());
+    assert_eq!(events[1]["status"], 200);
+    assert_eq!(events[1]["version"], "HTTP/1.1");
+    assert_eq!(events[2]["bytes"], 11);
+}
+
+#[test]
+fn errors_are_logged() {
+    let buffer = SharedBuffer::default();
+    let client = HttpClient::builder()
+        .event_log(buffer.clone())
+        .build()
+        .unwrap();
+
+    // Nothing should be listening on this port.
+    assert!(client.get("http://127.0.0.1:1").is_err());
+
+    let events = buffer.events();
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[1]["event"], "error");
+    assert_eq!(events[1]["kind"], "ConnectionFailed");
+    assert!(events[1]["message"].is_string());
+}
+
+#[test]
+fn unread_body_is_logged_as_dropped() {
+    let m = mock! {
+        body: "hello world",
+    };
+
+    let buffer = SharedBuffer::default();
+    let client = HttpClient::builder()
+        .event_log(buffer.clone())
+        .build()
+        .unwrap();
+
+    drop(client.get(m.url()).unwrap());
+
+    let events = buffer.events();
+
+    assert_eq!(events.last().unwrap()["event"], "body_dropped");
+}
@@ -1,4 +1,4 @@
-use isahc::{Body, Request, prelude::*};
+use isahc::{config::ExpectContinue, Body, Request, prelude::*};
 use testserver::mock;
 
 #[test]
@@ -27,3 +27,39 @@ fn expect_header_is_not_sent_when_disabled() {
 
     assert!(m.request().get_header("expect").next().is_none());
 }
+
+#[test]
+fn expect_header_is_not_sent_for_small_bodies() {
+    let m = mock!();
+
+    isahc::put(m.url(), "hello world").unwrap();
+
+    assert!(m.request().get_header("expect").next().is_none());
+    m.request().expect_body("hello world");
+}
+
+#[test]
+fn small_post_body_is_sent_without_expect_header() {
+    let m = mock!();
+
+    isahc::post(m.url(), "hello world").unwrap();
+
+    assert!(m.request().get_header("expect").next().is_none());
+    m.request().expect_header("content-length", "11");
+    m.request().expect_body("hello world");
+}
+
+#[test]
+fn expect_header_is_sent_for_bodies_above_threshold() {
+    let m = mock!();
+
+    Request::post(m.url())
+        .expect_continue(ExpectContinue::enabled().threshold(4))
+        .body("hello world")
+        .unwrap()
+        .send()
+        .unwrap();
+
+    m.request().expect_header("expect", "100-continue");
+    m.request().expect_body("hello world");
+}
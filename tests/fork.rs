@@ -0,0 +1,45 @@
+#![cfg(unix)]
+
+use isahc::{prelude::*, HttpClient};
+use testserver::mock;
+
+#[test]
+fn client_can_be_used_after_fork() {
+    let m = mock! {
+        body: "hello world",
+    };
+
+    // Make sure the agent thread is up and running before forking.
+    let client = HttpClient::new().unwrap();
+    assert_eq!(client.get(m.url()).unwrap().text().unwrap(), "hello world");
+
+    match unsafe { libc::fork() } {
+        -1 => panic!("fork failed"),
+
+        // In the child, try to use the inherited client. Exit without
+        // unwinding or running destructors so that the child never returns to
+        // the test harness.
+        0 => {
+            let ok = client
+                .get(m.url())
+                .and_then(|mut response| Ok(response.text()?))
+                .map(|text| text == "hello world")
+                .unwrap_or(false);
+
+            unsafe {
+                libc::_exit(if ok { 0 } else { 1 });
+            }
+        }
+
+        pid => {
+            let mut status = 0;
+
+            assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+            assert!(libc::WIFEXITED(status));
+            assert_eq!(libc::WEXITSTATUS(status), 0);
+
+            // The client should keep working in the parent too.
+            assert_eq!(client.get(m.url()).unwrap().text().unwrap(), "hello world");
+        }
+    }
+}
@@ -0,0 +1,36 @@
+#![cfg(feature = "form")]
+
+use isahc::{error::ErrorKind, prelude::*, Request};
+use serde_json::json;
+use testserver::mock;
+
+#[macro_use]
+mod utils;
+
+#[test]
+fn serialize_form_request_body() {
+    let m = mock!();
+
+    Request::post(m.url())
+        .form(&json!({
+            "name": "Jane Doe",
+            "tags": ["a", "b&c"],
+            "age": 42,
+        }))
+        .unwrap()
+        .send()
+        .unwrap();
+
+    m.request()
+        .expect_header("content-type", "application/x-www-form-urlencoded");
+    m.request().expect_body("age=42&name=Jane+Doe&tags=a&tags=b%26c");
+}
+
+#[test]
+fn nested_form_values_are_rejected() {
+    let result = Request::post("http://localhost").form(&json!({
+        "user": {"name": "Jane"},
+    }));
+
+    assert_matches!(result, Err(e) if e == ErrorKind::InvalidRequest);
+}
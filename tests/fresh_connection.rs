@@ -0,0 +1,49 @@
+use isahc::{prelude::*, HttpClient, Request};
+use testserver::mock;
+
+#[test]
+fn fresh_connection_is_not_reused() {
+    let m = mock!();
+    let client = HttpClient::new().unwrap();
+
+    let mut first = client.get(m.url()).unwrap();
+    first.consume().unwrap();
+
+    let mut fresh = client
+        .send(
+            Request::get(m.url())
+                .fresh_connection(true)
+                .body(())
+                .unwrap(),
+        )
+        .unwrap();
+    fresh.consume().unwrap();
+
+    let mut last = client.get(m.url()).unwrap();
+    last.consume().unwrap();
+
+    assert_eq!(m.requests_received(), 3);
+    assert_ne!(first.local_addr(), fresh.local_addr());
+    assert_ne!(fresh.local_addr(), last.local_addr());
+}
+
+#[test]
+fn connections_are_reused_when_disabled() {
+    let m = mock!();
+    let client = HttpClient::new().unwrap();
+
+    let mut first = client.get(m.url()).unwrap();
+    first.consume().unwrap();
+
+    let mut second = client
+        .send(
+            Request::get(m.url())
+                .fresh_connection(false)
+                .body(())
+                .unwrap(),
+        )
+        .unwrap();
+    second.consume().unwrap();
+
+    assert_eq!(first.local_addr(), second.local_addr());
+}
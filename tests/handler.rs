@@ -0,0 +1,78 @@
+use futures_lite::{future::block_on, AsyncReadExt};
+use isahc::{
+    config::RedirectPolicy,
+    prelude::*,
+    AsyncBody,
+    HttpClient,
+    Request,
+    Response,
+};
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn handler_answers_requests() {
+    let client = HttpClient::with_handler(|mut request| {
+        assert_eq!(request.method(), "POST");
+        assert_eq!(request.uri(), "http://example.org/echo");
+        assert_eq!(request.headers()["x-test"], "yes");
+
+        let mut body = String::new();
+        block_on(request.body_mut().read_to_string(&mut body)).unwrap();
+
+        Response::builder()
+            .status(201)
+            .header("content-type", "text/plain")
+            .body(AsyncBody::from(body))
+            .unwrap()
+    })
+    .unwrap();
+
+    let mut response = client
+        .send(
+            Request::post("http://example.org/echo")
+                .header("x-test", "yes")
+                .body("hello")
+                .unwrap(),
+        )
+        .unwrap();
+
+    assert_eq!(response.status(), 201);
+    assert_eq!(response.headers()["content-type"], "text/plain");
+    assert_eq!(response.text().unwrap(), "hello");
+}
+
+#[test]
+fn handler_responses_go_through_client_configuration() {
+    let uris = Arc::new(Mutex::new(Vec::new()));
+
+    let client = HttpClient::builder()
+        .handler({
+            let uris = uris.clone();
+
+            move |request| {
+                uris.lock().unwrap().push(request.uri().to_string());
+
+                if request.uri().path() == "/old" {
+                    Response::builder()
+                        .status(301)
+                        .header("location", "/new")
+                        .body(AsyncBody::empty())
+                        .unwrap()
+                } else {
+                    Response::new(AsyncBody::from("moved"))
+                }
+            }
+        })
+        .redirect_policy(RedirectPolicy::Follow)
+        .build()
+        .unwrap();
+
+    let mut response = client.get("http://example.org/old").unwrap();
+
+    assert_eq!(response.text().unwrap(), "moved");
+    assert_eq!(response.effective_uri().unwrap(), "http://example.org/new");
+    assert_eq!(
+        *uris.lock().unwrap(),
+        ["http://example.org/old", "http://example.org/new"]
+    );
+}
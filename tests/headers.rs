@@ -1,8 +1,9 @@
 use futures_lite::future::block_on;
-use isahc::{prelude::*, HttpClient, Request};
+use isahc::{http, prelude::*, HttpClient, Request};
 use std::{
     io::{self, Write},
     net::{Shutdown, TcpListener, TcpStream},
+    sync::mpsc,
     thread,
     time::Duration,
 };
@@ -221,7 +222,6 @@ fn trailer_headers() {
     let mut body = None;
     let response = isahc::get(url).unwrap().map(|b| {
         body = Some(b);
-        ()
     });
 
     thread::spawn(move || {
@@ -264,7 +264,6 @@ fn trailer_headers_async() {
         let mut body = None;
         let response = isahc::get_async(url).await.unwrap().map(|b| {
             body = Some(b);
-            ()
         });
 
         thread::spawn(move || {
@@ -328,6 +327,60 @@ fn trailer_headers_timeout() {
     );
 }
 
+#[test]
+fn response_is_returned_before_body_arrives() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (sender, receiver) = mpsc::channel::<()>();
+
+    thread::spawn(move || {
+        let mut stream = listener.accept().unwrap().0;
+
+        consume_request_in_background(&stream);
+
+        stream
+            .write_all(
+                b"\
+            HTTP/1.1 200 OK\r\n\
+            content-type: text/event-stream\r\n\
+            transfer-encoding: chunked\r\n\
+            \r\n",
+            )
+            .unwrap();
+
+        // Only send the body once the client has the response.
+        receiver.recv().unwrap();
+
+        stream.write_all(b"c\r\ndata: hello\n\r\n0\r\n\r\n").unwrap();
+
+        let _ = stream.shutdown(Shutdown::Write);
+    });
+
+    let mut response = isahc::get(url).unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+    sender.send(()).unwrap();
+
+    assert_eq!(response.text().unwrap(), "data: hello\n");
+}
+
+#[test]
+fn content_disposition_filename_is_sanitized() {
+    let m = mock! {
+        headers {
+            "content-disposition": "attachment; filename=\"fallback.txt\"; filename*=UTF-8''..%2F%E2%82%AC.txt",
+        }
+    };
+
+    let response = isahc::get(m.url()).unwrap();
+    let disposition = response.content_disposition().unwrap();
+
+    assert!(disposition.is_attachment());
+    assert_eq!(disposition.filename(), Some("€.txt"));
+}
+
 fn consume_request_in_background(stream: &TcpStream) {
     let mut stream = stream.try_clone().unwrap();
 
@@ -336,3 +389,52 @@ fn consume_request_in_background(stream: &TcpStream) {
         let _ = stream.shutdown(Shutdown::Read);
     });
 }
+
+#[test]
+fn client_default_accept_header_can_be_overridden_or_omitted_per_request() {
+    let m = mock!();
+
+    let client = HttpClient::builder()
+        .default_header("accept", "application/json")
+        .default_header("accept-language", "en")
+        .build()
+        .unwrap();
+
+    client.get(m.url()).unwrap();
+
+    m.request().expect_header("accept", "application/json");
+    m.request().expect_header("accept-language", "en");
+
+    let request = Request::get(m.url())
+        .header("accept", "text/plain")
+        .body(())
+        .unwrap();
+    client.send(request).unwrap();
+
+    let request = Request::get(m.url())
+        .omit_default_header(http::header::ACCEPT)
+        .omit_default_header(http::header::ACCEPT_LANGUAGE)
+        .body(())
+        .unwrap();
+    client.send(request).unwrap();
+
+    let requests = m.requests();
+
+    requests[1].expect_header("accept", "text/plain");
+    assert_eq!(requests[2].get_header("accept").count(), 0);
+    assert_eq!(requests[2].get_header("accept-language").count(), 0);
+}
+
+#[test]
+fn curl_default_accept_header_can_be_omitted() {
+    let m = mock!();
+
+    let client = HttpClient::builder()
+        .omit_default_header(http::header::ACCEPT)
+        .build()
+        .unwrap();
+
+    client.get(m.url()).unwrap();
+
+    assert_eq!(m.request().get_header("accept").count(), 0);
+}
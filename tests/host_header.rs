@@ -0,0 +1,89 @@
+use isahc::{
+    config::{HostHeader, RedirectPolicy},
+    error::ErrorKind,
+    prelude::*,
+    Request,
+};
+use testserver::mock;
+
+#[test]
+fn host_header_is_overridden() {
+    let m = mock!();
+
+    Request::get(m.url())
+        .host_header("other.example")
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    m.request().expect_header("host", "other.example");
+}
+
+#[test]
+fn host_header_used_for_sni_still_connects_to_uri() {
+    let m = mock!();
+
+    Request::get(m.url())
+        .host_header(HostHeader::new("other.example:8080").sni(true))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    m.request().expect_header("host", "other.example:8080");
+}
+
+#[test]
+fn explicit_host_header_takes_precedence() {
+    let m = mock!();
+
+    Request::get(m.url())
+        .host_header("other.example")
+        .header("host", "explicit.example")
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    m.request().expect_header("host", "explicit.example");
+}
+
+#[test]
+fn invalid_host_header_is_rejected() {
+    let m = mock!();
+
+    let error = Request::get(m.url())
+        .host_header("user@other.example")
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap_err();
+
+    assert_eq!(error.kind(), &ErrorKind::InvalidRequest);
+    assert_eq!(m.requests_received(), 0);
+}
+
+#[test]
+fn host_header_is_not_sent_to_other_servers_after_redirect() {
+    let m2 = mock!();
+    let location = m2.url();
+
+    let m1 = mock! {
+        status: 302,
+        headers {
+            "Location": location,
+        }
+    };
+
+    Request::get(m1.url())
+        .host_header("other.example")
+        .redirect_policy(RedirectPolicy::Follow)
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    m1.request().expect_header("host", "other.example");
+    m2.request().expect_header("host", m2.addr().to_string());
+}
@@ -0,0 +1,158 @@
+use isahc::{
+    config::{HostPolicy, IpVersion, RedirectPolicy},
+    error::ErrorKind,
+    prelude::*,
+    HttpClient,
+    Request,
+};
+use std::error::Error;
+use testserver::mock;
+
+fn client(policy: HostPolicy) -> HttpClient {
+    HttpClient::builder()
+        .host_policy(policy)
+        .redirect_policy(RedirectPolicy::Follow)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn loopback_addresses_are_blocked_by_default() {
+    let m = mock!();
+
+    let error = client(HostPolicy::new()).get(m.url()).unwrap_err();
+
+    assert_eq!(error.kind(), &ErrorKind::RequestBlocked);
+    assert!(error.is_client());
+    assert_eq!(m.requests_received(), 0);
+}
+
+#[test]
+fn host_names_are_checked_after_resolution() {
+    let m = mock!();
+    let url = format!("http://localhost:{}/", m.addr().port());
+
+    let error = client(HostPolicy::new()).get(url).unwrap_err();
+
+    assert_eq!(error.kind(), &ErrorKind::RequestBlocked);
+    assert!(
+        error
+            .source()
+            .unwrap()
+            .to_string()
+            .contains("host `localhost` resolves to address")
+    );
+    assert_eq!(m.requests_received(), 0);
+}
+
+#[test]
+fn private_addresses_can_be_allowed() {
+    let m = mock!();
+
+    let response = client(HostPolicy::new().block_private_addresses(false))
+        .get(m.url())
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(m.requests_received(), 1);
+}
+
+#[test]
+fn resolved_host_names_are_connected_to() {
+    let m = mock!();
+    let url = format!("http://localhost:{}/", m.addr().port());
+
+    // The mock server only listens on IPv4.
+    let request = Request::get(url)
+        .ip_version(IpVersion::V4)
+        .body(())
+        .unwrap();
+    let response = client(HostPolicy::new().block_private_addresses(false))
+        .send(request)
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(m.requests_received(), 1);
+}
+
+#[test]
+fn unexpected_ports_are_blocked() {
+    let m = mock!();
+
+    let error = client(
+        HostPolicy::new()
+            .block_private_addresses(false)
+            .allow_ports([80, 443]),
+    )
+    .get(m.url())
+    .unwrap_err();
+
+    assert_eq!(error.kind(), &ErrorKind::RequestBlocked);
+    assert_eq!(m.requests_received(), 0);
+}
+
+#[test]
+fn blocked_hosts_are_blocked() {
+    let m = mock!();
+
+    let error = client(
+        HostPolicy::new()
+            .block_private_addresses(false)
+            .block_host("127.0.0.1"),
+    )
+    .get(m.url())
+    .unwrap_err();
+
+    assert_eq!(error.kind(), &ErrorKind::RequestBlocked);
+    assert_eq!(m.requests_received(), 0);
+}
+
+#[test]
+fn redirects_to_blocked_hosts_are_blocked() {
+    let m2 = mock!();
+    let location = format!("http://localhost:{}/", m2.addr().port());
+
+    let m1 = mock! {
+        status: 302,
+        headers {
+            "Location": location,
+        }
+    };
+
+    let error = client(
+        HostPolicy::new()
+            .block_private_addresses(false)
+            .block_host("localhost"),
+    )
+    .get(m1.url())
+    .unwrap_err();
+
+    assert_eq!(error.kind(), &ErrorKind::RequestBlocked);
+    assert_eq!(m1.requests_received(), 1);
+    assert_eq!(m2.requests_received(), 0);
+}
+
+#[test]
+fn redirects_to_the_same_host_connect_to_the_checked_address() {
+    let m2 = mock!();
+    let location = format!("http://localhost:{}/", m2.addr().port());
+
+    let m1 = mock! {
+        status: 302,
+        headers {
+            "Location": location,
+        }
+    };
+
+    let request = Request::get(format!("http://localhost:{}/", m1.addr().port()))
+        .ip_version(IpVersion::V4)
+        .body(())
+        .unwrap();
+    let response = client(HostPolicy::new().block_private_addresses(false))
+        .send(request)
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(m1.requests_received(), 1);
+    assert_eq!(m2.requests_received(), 1);
+}
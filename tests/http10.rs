@@ -0,0 +1,62 @@
+use isahc::{error::ErrorKind, prelude::*, Body, Request};
+use testserver::mock;
+
+#[test]
+fn request_is_sent_using_http10() {
+    let m = mock!();
+
+    Request::post(m.url())
+        .http10_compatibility(false)
+        .body("hello")
+        .unwrap()
+        .send()
+        .unwrap();
+
+    m.request().expect_header("connection", "close");
+    m.request().expect_header("content-length", "5");
+    m.request().expect_body("hello");
+}
+
+#[test]
+fn keep_alive_is_requested() {
+    let m = mock!();
+
+    Request::get(m.url())
+        .http10_compatibility(true)
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    m.request().expect_header("connection", "keep-alive");
+}
+
+#[test]
+fn body_of_unknown_length_is_rejected() {
+    let m = mock!();
+
+    let error = Request::post(m.url())
+        .http10_compatibility(true)
+        .body(Body::from_reader("hello".as_bytes()))
+        .unwrap()
+        .send()
+        .unwrap_err();
+
+    assert_eq!(error.kind(), &ErrorKind::InvalidRequest);
+    assert_eq!(m.requests_received(), 0);
+}
+
+#[test]
+fn body_of_unknown_length_with_content_length_is_allowed() {
+    let m = mock!();
+
+    Request::post(m.url())
+        .http10_compatibility(true)
+        .header("content-length", "5")
+        .body(Body::from_reader("hello".as_bytes()))
+        .unwrap()
+        .send()
+        .unwrap();
+
+    m.request().expect_body("hello");
+}
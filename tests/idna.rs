@@ -0,0 +1,51 @@
+use isahc::{
+    config::{IdnaPolicy, RedirectPolicy},
+    error::ErrorKind,
+    prelude::*,
+    HttpClient,
+};
+use testserver::mock;
+
+#[test]
+fn host_is_recorded_on_response() {
+    let m = mock!();
+    let url = format!("http://localhost:{}/", m.addr().port());
+
+    let response = isahc::get(url).unwrap();
+    let host = response.idn_host().unwrap();
+
+    assert_eq!(host.ascii(), "localhost");
+    assert_eq!(host.unicode(), "localhost");
+    assert!(!host.is_internationalized());
+}
+
+#[test]
+fn ip_addresses_have_no_host() {
+    let m = mock!();
+
+    let response = isahc::get(m.url()).unwrap();
+
+    assert_eq!(response.idn_host(), None);
+}
+
+#[test]
+fn confusable_redirect_target_is_rejected_in_strict_mode() {
+    // Redirect to "аpple.com" with a Cyrillic "а".
+    let m = mock! {
+        status: 302,
+        headers {
+            "Location": "http://xn--pple-43d.com/",
+        }
+    };
+
+    let client = HttpClient::builder()
+        .idna_policy(IdnaPolicy::new().strict(true))
+        .redirect_policy(RedirectPolicy::Follow)
+        .build()
+        .unwrap();
+
+    let error = client.get(m.url()).unwrap_err();
+
+    assert_eq!(error.kind(), &ErrorKind::InvalidRequest);
+    assert_eq!(m.requests_received(), 1);
+}
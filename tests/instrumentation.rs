@@ -0,0 +1,210 @@
+use isahc::{
+    config::{AgentEvent, AgentEventKind, ConnectAttemptResult},
+    error::ErrorKind,
+    prelude::*,
+    CancelHandle,
+    CancelReason,
+    HttpClient,
+    Request,
+};
+use std::{
+    net::TcpListener,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+use testserver::mock;
+
+/// Create a client that records every event it emits.
+fn recording_client() -> (HttpClient, Arc<Mutex<Vec<AgentEvent>>>) {
+    let events = Arc::new(Mutex::new(Vec::new()));
+
+    let client = HttpClient::builder()
+        .instrumentation({
+            let events = events.clone();
+            move |event: &AgentEvent| events.lock().unwrap().push(event.clone())
+        })
+        .build()
+        .unwrap();
+
+    (client, events)
+}
+
+/// Get the kinds of the events about the given request, waiting for it to
+/// complete first. Durations of connection attempts vary from run to run, so
+/// they are replaced with zero.
+fn request_events(events: &Mutex<Vec<AgentEvent>>, id: u64) -> Vec<AgentEventKind> {
+    for _ in 0..100 {
+        let kinds = events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|event| event.request_id() == Some(id))
+            .map(|event| match event.kind().clone() {
+                AgentEventKind::ConnectAttempt { addr, result, .. } => {
+                    AgentEventKind::ConnectAttempt {
+                        addr,
+                        result,
+                        duration: Duration::ZERO,
+                    }
+                }
+                kind => kind,
+            })
+            .collect::<Vec<_>>();
+
+        if matches!(
+            kinds.last(),
+            Some(AgentEventKind::RequestCompleted | AgentEventKind::RequestFailed { .. })
+        ) {
+            return kinds;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    panic!("request {} did not complete", id);
+}
+
+/// Get the IDs of all requests that events were emitted for, in order.
+fn request_ids(events: &Mutex<Vec<AgentEvent>>) -> Vec<u64> {
+    let mut ids = Vec::new();
+
+    for id in events.lock().unwrap().iter().filter_map(AgentEvent::request_id) {
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+
+    ids
+}
+
+#[test]
+fn request_lifecycle_events_are_emitted_in_order() {
+    let m = mock! {
+        body: "hello world",
+    };
+
+    let (client, events) = recording_client();
+
+    client
+        .send(Request::put(m.url()).body("ping").unwrap())
+        .unwrap()
+        .consume()
+        .unwrap();
+    client.get(m.url()).unwrap().consume().unwrap();
+
+    let ids = request_ids(&events);
+    assert_eq!(ids.len(), 2);
+
+    assert_eq!(
+        request_events(&events, ids[0]),
+        [
+            AgentEventKind::RequestEnqueued,
+            AgentEventKind::ConnectAttempt {
+                addr: m.addr(),
+                result: ConnectAttemptResult::Connected,
+                duration: Duration::ZERO,
+            },
+            AgentEventKind::ConnectionEstablished { reused: false },
+            AgentEventKind::RequestBodyRead { len: 4 },
+            AgentEventKind::HeadersReceived { status: 200 },
+            AgentEventKind::ResponseBodyWritten { len: 11 },
+            AgentEventKind::RequestCompleted,
+        ]
+    );
+
+    assert_eq!(
+        request_events(&events, ids[1]),
+        [
+            AgentEventKind::RequestEnqueued,
+            AgentEventKind::ConnectionEstablished { reused: true },
+            AgentEventKind::HeadersReceived { status: 200 },
+            AgentEventKind::ResponseBodyWritten { len: 11 },
+            AgentEventKind::RequestCompleted,
+        ]
+    );
+
+    let events = events.lock().unwrap();
+
+    // Timings are measured from when each request was enqueued.
+    assert!(events
+        .iter()
+        .filter(|event| event.request_id().is_some())
+        .all(|event| event.elapsed().is_some()));
+
+    // The agent woke up at least once to handle the requests.
+    assert!(events.iter().any(|event| {
+        event.request_id().is_none()
+            && matches!(event.kind(), AgentEventKind::AgentWakeup { .. })
+    }));
+}
+
+#[test]
+fn failed_request_is_reported() {
+    // Bind and immediately close a port so that nothing is listening on it.
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let (client, events) = recording_client();
+
+    client
+        .get(format!("http://127.0.0.1:{}", port))
+        .unwrap_err();
+
+    let ids = request_ids(&events);
+
+    assert_eq!(
+        request_events(&events, ids[0]),
+        [
+            AgentEventKind::RequestEnqueued,
+            AgentEventKind::ConnectAttempt {
+                addr: ([127, 0, 0, 1], port).into(),
+                result: ConnectAttemptResult::Failed,
+                duration: Duration::ZERO,
+            },
+            AgentEventKind::RequestFailed {
+                error: ErrorKind::ConnectionFailed,
+            },
+        ]
+    );
+}
+
+#[test]
+fn canceled_request_reports_reason() {
+    let m = mock! {
+        delay: 3s,
+    };
+
+    let (client, events) = recording_client();
+    let cancel = CancelHandle::new();
+
+    thread::spawn({
+        let cancel = cancel.clone();
+        move || {
+            thread::sleep(Duration::from_millis(200));
+            cancel.cancel_with_reason(CancelReason::Policy);
+        }
+    });
+
+    client
+        .send(Request::get(m.url()).extension(cancel).body(()).unwrap())
+        .unwrap_err();
+
+    let ids = request_ids(&events);
+    let kinds = request_events(&events, ids[0]);
+
+    assert_eq!(
+        kinds[kinds.len() - 2..],
+        [
+            AgentEventKind::RequestCanceled {
+                reason: CancelReason::Policy,
+            },
+            AgentEventKind::RequestFailed {
+                error: ErrorKind::Interrupted,
+            },
+        ]
+    );
+}
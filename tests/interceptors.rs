@@ -1,6 +1,19 @@
 #![cfg(feature = "unstable-interceptors")]
 
-use isahc::HttpClient;
+use isahc::{
+    config::RedirectPolicy,
+    interceptor::{Context, Interceptor, InterceptorFuture},
+    prelude::*,
+    AsyncBody,
+    HttpClient,
+};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 use testserver::mock;
 
 #[test]
@@ -19,3 +32,166 @@ fn change_http_method_with_interceptor() {
 
     assert_eq!(m.request().method(), "HEAD");
 }
+
+#[test]
+fn add_query_parameter_with_interceptor() {
+    let m = mock!();
+
+    let client = HttpClient::builder()
+        .interceptor(isahc::interceptor!(request, cx, {
+            request.query_mut().set("api_key", "secret value");
+            cx.send(request).await
+        }))
+        .build()
+        .unwrap();
+
+    client.get(format!("{}search?q=cats", m.url())).unwrap();
+
+    assert_eq!(m.request().url(), "/search?q=cats&api_key=secret+value");
+}
+
+#[test]
+fn transform_response_body_with_interceptor() {
+    let m = mock! {
+        body: "hello world",
+    };
+
+    let client = HttpClient::builder()
+        .interceptor(isahc::interceptor!(request, cx, {
+            let response = cx.send(request).await?;
+
+            Ok::<_, isahc::Error>(isahc::interceptor::map_body(response, |body| {
+                futures_lite::io::AsyncReadExt::take(body, 5)
+            }))
+        }))
+        .build()
+        .unwrap();
+
+    let mut response = client.get(m.url()).unwrap();
+
+    assert!(response.headers().get("content-length").is_none());
+    assert_eq!(response.text().unwrap(), "hello");
+}
+
+#[test]
+fn interceptors_apply_to_async_requests() {
+    let m = mock!();
+
+    let client = HttpClient::builder()
+        .interceptor(isahc::interceptor!(request, cx, {
+            request
+                .headers_mut()
+                .insert("authorization", "Bearer abc".parse().unwrap());
+            cx.send(request).await
+        }))
+        .build()
+        .unwrap();
+
+    futures_lite::future::block_on(client.get_async(m.url())).unwrap();
+
+    m.request().expect_header("authorization", "Bearer abc");
+}
+
+#[test]
+fn interceptors_run_in_registration_order() {
+    let m = mock!();
+
+    let client = HttpClient::builder()
+        .interceptor(isahc::interceptor!(request, cx, {
+            request
+                .headers_mut()
+                .append("x-order", "first".parse().unwrap());
+            cx.send(request).await
+        }))
+        .interceptor(isahc::interceptor!(request, cx, {
+            request
+                .headers_mut()
+                .append("x-order", "second".parse().unwrap());
+            cx.send(request).await
+        }))
+        .build()
+        .unwrap();
+
+    client.get(m.url()).unwrap();
+
+    assert_eq!(
+        m.request().get_header("x-order").collect::<Vec<_>>(),
+        ["first", "second"]
+    );
+}
+
+#[test]
+fn interceptors_see_each_redirect() {
+    struct Counter(Arc<AtomicUsize>);
+
+    impl isahc::interceptor::Interceptor for Counter {
+        type Err = isahc::Error;
+
+        fn intercept<'a>(
+            &'a self,
+            request: isahc::Request<isahc::AsyncBody>,
+            cx: isahc::interceptor::Context<'a>,
+        ) -> isahc::interceptor::InterceptorFuture<'a, Self::Err> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move { cx.send(request).await })
+        }
+    }
+
+    let m2 = mock!();
+    let location = m2.url();
+
+    let m1 = mock! {
+        status: 301,
+        headers {
+            "Location": location,
+        }
+    };
+
+    let count = Arc::new(AtomicUsize::new(0));
+
+    let client = HttpClient::builder()
+        .redirect_policy(RedirectPolicy::Follow)
+        .interceptor(Counter(count.clone()))
+        .build()
+        .unwrap();
+
+    client.get(m1.url()).unwrap();
+
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn interceptors_are_notified_of_shutdown() {
+    struct CountShutdowns(Arc<AtomicUsize>);
+
+    impl Interceptor for CountShutdowns {
+        type Err = isahc::Error;
+
+        fn intercept<'a>(
+            &'a self,
+            request: http::Request<AsyncBody>,
+            ctx: Context<'a>,
+        ) -> InterceptorFuture<'a, Self::Err> {
+            Box::pin(async move { ctx.send(request).await })
+        }
+
+        fn shutdown(&self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let m = mock!();
+    let shutdowns = Arc::new(AtomicUsize::new(0));
+
+    let client = HttpClient::builder()
+        .interceptor(CountShutdowns(shutdowns.clone()))
+        .build()
+        .unwrap();
+
+    client.get(m.url()).unwrap();
+    assert_eq!(shutdowns.load(Ordering::SeqCst), 0);
+
+    assert!(client.shutdown(Duration::from_secs(5)));
+    assert!(client.shutdown(Duration::from_secs(5)));
+    assert_eq!(shutdowns.load(Ordering::SeqCst), 1);
+}
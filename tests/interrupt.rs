@@ -0,0 +1,97 @@
+use isahc::{error::ErrorKind, prelude::*, Request};
+use std::{
+    io::{self, Read},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use testserver::mock;
+
+#[macro_use]
+mod utils;
+
+#[test]
+fn setting_interrupt_flag_aborts_blocking_send() {
+    let m = mock! {
+        delay: 3s,
+    };
+
+    let flag = Arc::new(AtomicBool::new(false));
+
+    thread::spawn({
+        let flag = flag.clone();
+        move || {
+            thread::sleep(Duration::from_millis(200));
+            flag.store(true, Ordering::SeqCst);
+        }
+    });
+
+    let start = Instant::now();
+    let result = Request::get(m.url())
+        .interrupt_flag(flag)
+        .body(())
+        .unwrap()
+        .send();
+
+    assert_matches!(result, Err(e) if e == ErrorKind::Interrupted);
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[test]
+fn request_with_interrupt_flag_already_set_fails() {
+    let m = mock! {
+        body: "hello world",
+    };
+
+    let result = Request::get(m.url())
+        .interrupt_flag(Arc::new(AtomicBool::new(true)))
+        .body(())
+        .unwrap()
+        .send();
+
+    assert_matches!(result, Err(e) if e == ErrorKind::Interrupted);
+}
+
+#[test]
+fn setting_interrupt_flag_aborts_response_body() {
+    struct SlowReader;
+
+    impl Read for SlowReader {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            thread::sleep(Duration::from_secs(3));
+            Ok(0)
+        }
+    }
+
+    let m = mock! {
+        _ => {
+            body_reader: io::Cursor::new(vec![0; 100_000]).chain(SlowReader),
+        },
+    };
+
+    let flag = Arc::new(AtomicBool::new(false));
+
+    let mut response = Request::get(m.url())
+        .interrupt_flag(flag.clone())
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    flag.store(true, Ordering::SeqCst);
+
+    let error = response.copy_to(io::sink()).unwrap_err();
+
+    assert_eq!(
+        error
+            .into_inner()
+            .unwrap()
+            .downcast::<isahc::Error>()
+            .unwrap()
+            .kind(),
+        &ErrorKind::Interrupted
+    );
+}
@@ -1,8 +1,8 @@
 #![cfg(feature = "json")]
 
 use futures_lite::{future::block_on, io::AsyncRead};
-use isahc::prelude::*;
-use serde_json::Value;
+use isahc::{prelude::*, Request};
+use serde_json::{json, Value};
 use std::{
     io,
     pin::Pin,
@@ -63,3 +63,33 @@ fn deserialize_json_async_io_error() {
         assert_matches!(response.json::<Value>().await, Err(e) if e.is_io());
     });
 }
+
+#[test]
+fn serialize_json_request_body() {
+    let m = mock!();
+
+    Request::post(m.url())
+        .json(&json!({"foo": "bar"}))
+        .unwrap()
+        .send()
+        .unwrap();
+
+    m.request().expect_header("content-type", "application/json");
+    m.request().expect_body(r#"{"foo":"bar"}"#);
+}
+
+#[test]
+fn json_request_body_keeps_explicit_content_type() {
+    let m = mock!();
+
+    Request::post(m.url())
+        .header("content-type", "application/merge-patch+json")
+        .json(&json!(null))
+        .unwrap()
+        .send()
+        .unwrap();
+
+    m.request()
+        .expect_header("content-type", "application/merge-patch+json");
+    m.request().expect_body("null");
+}
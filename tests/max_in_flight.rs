@@ -0,0 +1,70 @@
+use isahc::{prelude::*, HttpClient, Request};
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use testserver::mock;
+
+#[test]
+fn requests_over_limit_wait_for_their_turn() {
+    let m = mock! {
+        delay: 100ms,
+    };
+
+    let client = HttpClient::builder().max_in_flight(2).build().unwrap();
+    let start = Instant::now();
+
+    let threads = (0..4)
+        .map(|_| {
+            let client = client.clone();
+            let url = m.url();
+
+            thread::spawn(move || client.get(url).unwrap().consume().unwrap())
+        })
+        .collect::<Vec<_>>();
+
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    assert!(start.elapsed() >= Duration::from_millis(200));
+    assert_eq!(m.requests_received(), 4);
+}
+
+#[test]
+fn higher_priority_requests_begin_first() {
+    let slow = mock! {
+        delay: 200ms,
+    };
+    let low = mock!();
+    let high = mock!();
+
+    let client = HttpClient::builder().max_in_flight(1).build().unwrap();
+    let completed = Arc::new(Mutex::new(Vec::new()));
+
+    let threads = vec![(slow.url(), 0), (low.url(), 0), (high.url(), 10)]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (url, priority))| {
+            let client = client.clone();
+            let completed = completed.clone();
+
+            thread::spawn(move || {
+                // Stagger the requests so that the slow one is in progress
+                // while the others are sent.
+                thread::sleep(Duration::from_millis(50 * i as u64));
+
+                let request = Request::get(url).priority(priority).body(()).unwrap();
+                client.send(request).unwrap().consume().unwrap();
+                completed.lock().unwrap().push(i);
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    assert_eq!(*completed.lock().unwrap(), [0, 2, 1]);
+}
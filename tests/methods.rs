@@ -19,6 +19,29 @@ fn head_request() {
     assert_eq!(m.request().method(), "HEAD");
 }
 
+#[test]
+fn get_request_after_head_request_has_body() {
+    let m1 = mock! {
+        body: "hello world",
+    };
+    let m2 = mock! {
+        body: "hello world",
+    };
+
+    // Use a dedicated client so that the second request is likely to reuse
+    // the handle from the first.
+    let client = isahc::HttpClient::new().unwrap();
+
+    let response = client.head(m1.url()).unwrap();
+    assert_eq!(response.body().len(), Some(0));
+
+    let mut response = client.get(m2.url()).unwrap();
+    assert_eq!(response.text().unwrap(), "hello world");
+
+    assert_eq!(m1.request().method(), "HEAD");
+    assert_eq!(m2.request().method(), "GET");
+}
+
 #[test]
 fn post_request() {
     let m = mock!();
@@ -1,5 +1,14 @@
 use isahc::{prelude::*, HttpClient, Request};
-use std::{io, time::Duration};
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
 use testserver::mock;
 
 #[test]
@@ -34,3 +43,228 @@ fn enabling_metrics_causes_metrics_to_be_collected() {
     assert_eq!(metrics.download_progress().0, 11);
     assert!(metrics.total_time() > Duration::default());
 }
+
+#[test]
+fn metrics_hold_final_values_once_body_is_consumed() {
+    let m = mock! {
+        body: "hello world",
+    };
+
+    let client = HttpClient::builder().metrics(true).build().unwrap();
+
+    let mut response = client.get(m.url()).unwrap();
+    let metrics = response.metrics().unwrap().clone();
+
+    response.copy_to(io::sink()).unwrap();
+
+    assert_eq!(metrics.remote_addr(), Some(m.addr()));
+    assert_eq!(metrics.download_progress().0, 11);
+    assert!(metrics.transfer_start_time() > Duration::ZERO);
+    assert!(metrics.total_time() >= metrics.transfer_start_time());
+}
+
+#[test]
+fn on_stats_receives_final_stats() {
+    let m = mock! {
+        body: "hello world",
+    };
+
+    let stats = Arc::new(Mutex::new(Vec::new()));
+    let stats_clone = stats.clone();
+
+    let mut response = Request::post(m.url())
+        .on_stats(move |s| stats_clone.lock().unwrap().push(s))
+        .body("hello server")
+        .unwrap()
+        .send()
+        .unwrap();
+
+    response.copy_to(io::sink()).unwrap();
+    drop(response);
+
+    let stats = stats.lock().unwrap();
+    assert_eq!(stats.len(), 1);
+
+    let stats = &stats[0];
+    assert_eq!(stats.status(), Some(isahc::http::StatusCode::OK));
+    assert_eq!(stats.bytes_uploaded(), 12);
+    assert_eq!(stats.bytes_downloaded(), 11);
+    assert_eq!(stats.remote_addr(), Some(m.addr()));
+    assert_eq!(stats.connects(), 1);
+    assert!(stats.header_bytes() > 0);
+    assert!(stats.request_bytes() > 12);
+    assert!(stats.total_time() > Duration::ZERO);
+    assert!(stats.error().is_none());
+}
+
+#[test]
+fn on_progress_receives_download_progress() {
+    let body = vec![b'a'; 20_000];
+    let m = mock! {
+        body: body.clone(),
+    };
+
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let progress_clone = progress.clone();
+
+    let mut response = Request::get(m.url())
+        .on_progress(move |p| progress_clone.lock().unwrap().push(p))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    response.consume().unwrap();
+
+    // Progress alone does not enable metrics.
+    assert!(response.metrics().is_none());
+
+    let progress = progress.lock().unwrap();
+    let last = progress.last().unwrap();
+
+    assert_eq!(last.bytes_received(), 20_000);
+    assert_eq!(last.download_total(), Some(20_000));
+    assert!(progress
+        .windows(2)
+        .all(|w| w[0] != w[1] && w[0].bytes_received() <= w[1].bytes_received()));
+}
+
+#[test]
+fn on_progress_receives_upload_progress() {
+    let m = mock!();
+
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let progress_clone = progress.clone();
+
+    Request::put(m.url())
+        .on_progress(move |p| progress_clone.lock().unwrap().push(p))
+        .body(vec![b'a'; 50_000])
+        .unwrap()
+        .send()
+        .unwrap()
+        .consume()
+        .unwrap();
+
+    let last = *progress.lock().unwrap().last().unwrap();
+
+    assert_eq!(last.bytes_sent(), 50_000);
+    assert_eq!(last.upload_total(), Some(50_000));
+}
+
+#[test]
+fn on_stats_receives_error() {
+    // Bind and immediately close a port so that nothing is listening on it.
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let stats = Arc::new(Mutex::new(Vec::new()));
+    let stats_clone = stats.clone();
+
+    let result = Request::get(format!("http://127.0.0.1:{}", port))
+        .on_stats(move |s| stats_clone.lock().unwrap().push(s))
+        .body(())
+        .unwrap()
+        .send();
+
+    assert!(result.is_err());
+
+    let stats = stats.lock().unwrap();
+    assert_eq!(stats.len(), 1);
+    assert_eq!(
+        stats[0].error().map(|e| e.kind().clone()),
+        Some(isahc::error::ErrorKind::ConnectionFailed)
+    );
+    assert_eq!(stats[0].status(), None);
+}
+
+#[test]
+fn callback_executor_runs_callbacks_and_completes_responses() {
+    let m = mock!();
+
+    let executed = Arc::new(AtomicUsize::new(0));
+    let stats_thread = Arc::new(Mutex::new(None));
+
+    let client = HttpClient::builder()
+        .callback_executor({
+            let executed = executed.clone();
+
+            move |task| {
+                executed.fetch_add(1, Ordering::SeqCst);
+                thread::Builder::new()
+                    .name(String::from("test-executor"))
+                    .spawn(task)
+                    .unwrap();
+            }
+        })
+        .build()
+        .unwrap();
+
+    let mut response = client
+        .send(
+            Request::get(m.url())
+                .on_stats({
+                    let stats_thread = stats_thread.clone();
+
+                    move |_| {
+                        *stats_thread.lock().unwrap() =
+                            thread::current().name().map(String::from);
+                    }
+                })
+                .body(())
+                .unwrap(),
+        )
+        .unwrap();
+
+    response.consume().unwrap();
+    drop(response);
+
+    // The statistics callback may still be running.
+    for _ in 0..100 {
+        if stats_thread.lock().unwrap().is_some() {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(executed.load(Ordering::SeqCst), 2);
+    assert_eq!(
+        stats_thread.lock().unwrap().as_deref(),
+        Some("test-executor")
+    );
+}
+
+#[test]
+fn sending_from_agent_thread_fails_instead_of_deadlocking() {
+    let m = mock!();
+
+    let client = HttpClient::new().unwrap();
+    let result = Arc::new(Mutex::new(None));
+
+    client
+        .send(
+            Request::get(m.url())
+                .on_stats({
+                    let client = client.clone();
+                    let result = result.clone();
+                    let url = m.url();
+
+                    move |_| {
+                        *result.lock().unwrap() = Some(client.get(&url).map(|_| ()));
+                    }
+                })
+                .body(())
+                .unwrap(),
+        )
+        .unwrap()
+        .consume()
+        .unwrap();
+
+    let error = result.lock().unwrap().take().unwrap().unwrap_err();
+
+    assert_eq!(error.kind(), &isahc::error::ErrorKind::InvalidRequest);
+    assert_eq!(m.requests_received(), 1);
+}
@@ -0,0 +1,167 @@
+#![cfg(feature = "metrics")]
+
+use isahc::{prelude::*, HttpClient};
+use metrics::{
+    Counter,
+    CounterFn,
+    Gauge,
+    Histogram,
+    HistogramFn,
+    Key,
+    KeyName,
+    Recorder,
+    SharedString,
+    Unit,
+};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+        Mutex,
+    },
+};
+use testserver::mock;
+
+/// Recorder that keeps the values of counters and histograms in memory.
+#[derive(Clone, Default)]
+struct TestRecorder {
+    counters: Arc<Mutex<HashMap<String, Arc<TestCounter>>>>,
+    histograms: Arc<Mutex<HashMap<String, Arc<TestHistogram>>>>,
+}
+
+#[derive(Default)]
+struct TestCounter(AtomicU64);
+
+#[derive(Default)]
+struct TestHistogram(Mutex<Vec<f64>>);
+
+impl CounterFn for TestCounter {
+    fn increment(&self, value: u64) {
+        self.0.fetch_add(value, Ordering::SeqCst);
+    }
+
+    fn absolute(&self, value: u64) {
+        self.0.fetch_max(value, Ordering::SeqCst);
+    }
+}
+
+impl HistogramFn for TestHistogram {
+    fn record(&self, value: f64) {
+        self.0.lock().unwrap().push(value);
+    }
+}
+
+/// Render a key as `name{label=value,...}` with labels sorted by name.
+fn render(key: &Key) -> String {
+    let mut labels = key
+        .labels()
+        .map(|label| format!("{}={}", label.key(), label.value()))
+        .collect::<Vec<_>>();
+    labels.sort();
+
+    format!("{}{{{}}}", key.name(), labels.join(","))
+}
+
+impl TestRecorder {
+    fn counter(&self, key: &str) -> u64 {
+        self.counters
+            .lock()
+            .unwrap()
+            .get(key)
+            .map_or(0, |counter| counter.0.load(Ordering::SeqCst))
+    }
+
+    fn histogram(&self, key: &str) -> Vec<f64> {
+        self.histograms
+            .lock()
+            .unwrap()
+            .get(key)
+            .map_or_else(Vec::new, |histogram| histogram.0.lock().unwrap().clone())
+    }
+}
+
+impl Recorder for TestRecorder {
+    fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn register_counter(&self, key: &Key) -> Counter {
+        Counter::from_arc(
+            self.counters
+                .lock()
+                .unwrap()
+                .entry(render(key))
+                .or_default()
+                .clone(),
+        )
+    }
+
+    fn register_gauge(&self, _: &Key) -> Gauge {
+        Gauge::noop()
+    }
+
+    fn register_histogram(&self, key: &Key) -> Histogram {
+        Histogram::from_arc(
+            self.histograms
+                .lock()
+                .unwrap()
+                .entry(render(key))
+                .or_default()
+                .clone(),
+        )
+    }
+}
+
+// The recorder is global to the process, so all assertions are made in a
+// single test.
+#[test]
+fn transfer_statistics_are_exported() {
+    let recorder = TestRecorder::default();
+    metrics::set_boxed_recorder(Box::new(recorder.clone())).unwrap();
+
+    let ok = mock! {
+        body: "hello world",
+    };
+    let unavailable = mock! {
+        status: 503,
+    };
+
+    let client = HttpClient::new().unwrap();
+
+    client.get(ok.url()).unwrap().text().unwrap();
+    client.get(ok.url()).unwrap().text().unwrap();
+    client.get(unavailable.url()).unwrap();
+    client.get("http://127.0.0.1:1").unwrap_err();
+
+    assert_eq!(
+        recorder.counter("isahc_requests_total{host=127.0.0.1,status_class=2xx,version=1.1}"),
+        2
+    );
+    assert_eq!(
+        recorder.counter("isahc_requests_total{host=127.0.0.1,status_class=5xx,version=1.1}"),
+        1
+    );
+    assert_eq!(
+        recorder.counter("isahc_requests_total{host=127.0.0.1,status_class=error,version=none}"),
+        1
+    );
+
+    let durations = recorder.histogram(
+        "isahc_request_duration_seconds{host=127.0.0.1,status_class=2xx,version=1.1}",
+    );
+    assert_eq!(durations.len(), 2);
+    assert!(durations.iter().all(|&d| d > 0.0));
+
+    assert_eq!(
+        recorder
+            .histogram("isahc_time_to_first_byte_seconds{host=127.0.0.1}")
+            .len(),
+        4
+    );
+    assert!(recorder.counter("isahc_connections_opened_total{host=127.0.0.1}") >= 2);
+    assert!(recorder.counter("isahc_bytes_sent_total{host=127.0.0.1}") > 0);
+    assert!(recorder.counter("isahc_bytes_received_total{host=127.0.0.1}") >= 22);
+}
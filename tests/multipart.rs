@@ -0,0 +1,103 @@
+use isahc::{
+    multipart::{Form, Part},
+    prelude::*,
+    AsyncBody,
+    Request,
+};
+use std::{fs, io::Write};
+use testserver::mock;
+
+#[test]
+fn form_is_sent_with_boundary() {
+    let m = mock!();
+
+    let form = Form::new()
+        .text("name", "Ferris")
+        .part("data", Part::bytes(vec![1, 2, 3]));
+    let content_type = form.content_type();
+    let boundary = form.boundary().to_owned();
+
+    Request::post(m.url())
+        .header("content-type", &content_type)
+        .body(form)
+        .unwrap()
+        .send()
+        .unwrap();
+
+    let expected = format!(
+        "--{b}\r\n\
+         Content-Disposition: form-data; name=\"name\"\r\n\r\n\
+         Ferris\r\n\
+         --{b}\r\n\
+         Content-Disposition: form-data; name=\"data\"\r\n\r\n\
+         \x01\x02\x03\r\n\
+         --{b}--\r\n",
+        b = boundary
+    );
+
+    m.request().expect_header("content-type", content_type);
+    m.request()
+        .expect_header("content-length", expected.len().to_string());
+    m.request().expect_body(expected);
+}
+
+#[test]
+fn file_part_has_file_name_and_content_type() {
+    let m = mock!();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.txt");
+    fs::File::create(&path)
+        .unwrap()
+        .write_all(b"hello world")
+        .unwrap();
+
+    let form = Form::new().part("file", Part::file(&path).unwrap());
+    let boundary = form.boundary().to_owned();
+
+    Request::post(m.url())
+        .header("content-type", form.content_type())
+        .body(form)
+        .unwrap()
+        .send()
+        .unwrap();
+
+    m.request().expect_body(format!(
+        "--{b}\r\n\
+         Content-Disposition: form-data; name=\"file\"; filename=\"notes.txt\"\r\n\
+         Content-Type: application/octet-stream\r\n\r\n\
+         hello world\r\n\
+         --{b}--\r\n",
+        b = boundary
+    ));
+}
+
+#[test]
+fn streaming_part_is_sent_chunked() {
+    let m = mock!();
+
+    let form = Form::new().part(
+        "log",
+        Part::reader(futures_lite::io::Cursor::new(b"line 1\nline 2\n".to_vec()))
+            .file_name("app.log")
+            .content_type("text/plain"),
+    );
+    let boundary = form.boundary().to_owned();
+
+    let request = Request::post(m.url())
+        .header("content-type", form.content_type())
+        .body(AsyncBody::from(form))
+        .unwrap();
+
+    futures_lite::future::block_on(request.send_async()).unwrap();
+
+    m.request().expect_header("transfer-encoding", "chunked");
+    m.request().expect_body(format!(
+        "--{b}\r\n\
+         Content-Disposition: form-data; name=\"log\"; filename=\"app.log\"\r\n\
+         Content-Type: text/plain\r\n\r\n\
+         line 1\nline 2\n\r\n\
+         --{b}--\r\n",
+        b = boundary
+    ));
+}
@@ -1,4 +1,9 @@
-use isahc::{config::IpVersion, error::ErrorKind, prelude::*, Request};
+use isahc::{
+    config::{IpVersion, ProxyProtocol},
+    error::ErrorKind,
+    prelude::*,
+    Request,
+};
 use std::{
     io::{self, Read, Write},
     net::{Ipv4Addr, Ipv6Addr, Shutdown, TcpListener, TcpStream, ToSocketAddrs},
@@ -33,6 +38,32 @@ fn local_addr_returns_expected_address() {
     assert!(response.local_addr().unwrap().port() > 0);
 }
 
+#[test]
+fn local_port_range_binds_to_port_in_range() {
+    let m = mock!();
+
+    // Find a port that is likely free to start the range at.
+    let port = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+        .min(u16::MAX - 20);
+
+    let response = Request::get(m.url())
+        .interface(Ipv4Addr::LOCALHOST)
+        .local_port_range(port..=port + 20)
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    let local_addr = response.local_addr().unwrap();
+
+    assert_eq!(local_addr.ip(), Ipv4Addr::LOCALHOST);
+    assert!((port..=port + 20).contains(&local_addr.port()));
+}
+
 #[test]
 fn remote_addr_returns_expected_address() {
     let m = mock!();
@@ -111,7 +142,7 @@ fn any_ip_version_uses_ipv4_or_ipv6() {
     let server_v6 = TcpListener::bind((Ipv6Addr::LOCALHOST, port)).unwrap();
 
     fn respond(client: &mut TcpStream, response: &[u8]) -> io::Result<()> {
-        client.read(&mut [0; 8192])?;
+        let _ = client.read(&mut [0; 8192])?;
         client.write_all(response)?;
         client.flush()?;
         client.shutdown(Shutdown::Both)
@@ -155,3 +186,96 @@ fn any_ip_version_uses_ipv4_or_ipv6() {
         assert!(response.local_addr().unwrap().is_ipv4());
     }
 }
+
+#[test]
+fn curl_error_details_are_included_in_error() {
+    // Find a port that nothing is listening on.
+    let port = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let error = isahc::get(format!("http://127.0.0.1:{}", port)).unwrap_err();
+    let detail = format!("port {}", port);
+
+    assert_eq!(error.kind(), ErrorKind::ConnectionFailed);
+    assert!(error.to_string().contains(&detail), "{}", error);
+    assert!(
+        std::error::Error::source(&error)
+            .unwrap()
+            .to_string()
+            .contains(&detail),
+        "{:?}",
+        error
+    );
+}
+
+#[test]
+fn tls_handshake_with_plain_http_server_is_classified() {
+    let server = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let port = server.local_addr().unwrap().port();
+
+    // Reply to the TLS client hello with plain HTTP.
+    thread::spawn(move || {
+        let (mut stream, _) = server.accept().unwrap();
+        let _ = stream.write_all(b"HTTP/1.1 400 Bad Request\r\n\r\n");
+        let _ = stream.shutdown(Shutdown::Both);
+    });
+
+    let error = Request::get(format!("https://127.0.0.1:{}", port))
+        .timeout(std::time::Duration::from_secs(5))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap_err();
+
+    assert_eq!(
+        error.tls_error_kind(),
+        Some(isahc::error::TlsErrorKind::Handshake),
+        "{:?}",
+        error
+    );
+}
+
+#[test]
+fn proxy_protocol_header_is_sent_before_request() {
+    let server = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let port = server.local_addr().unwrap().port();
+
+    let handle = thread::spawn(move || {
+        let (mut stream, _) = server.accept().unwrap();
+        let mut received = Vec::new();
+        let mut buf = [0; 1024];
+
+        while !received.ends_with(b"\r\n\r\n") {
+            let len = stream.read(&mut buf).unwrap();
+            assert!(len > 0);
+            received.extend_from_slice(&buf[..len]);
+        }
+
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+            .unwrap();
+
+        String::from_utf8(received).unwrap()
+    });
+
+    Request::get(format!("http://127.0.0.1:{}", port))
+        .proxy_protocol(ProxyProtocol::new().client_ip(Ipv4Addr::new(203, 0, 113, 7)))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    let received = handle.join().unwrap();
+    let preamble = received.lines().next().unwrap();
+
+    assert!(
+        preamble.starts_with("PROXY TCP4 203.0.113.7 127.0.0.1 "),
+        "{}",
+        preamble
+    );
+    assert!(preamble.ends_with(&format!(" {}", port)), "{}", preamble);
+    assert!(received.contains("GET / HTTP/1.1\r\n"));
+}
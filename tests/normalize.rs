@@ -0,0 +1,80 @@
+use isahc::{
+    config::{TrailingSlash, UrlNormalization},
+    prelude::*,
+    Request,
+};
+use testserver::mock;
+
+#[test]
+fn dot_segments_are_removed_by_default() {
+    let m = mock!();
+
+    Request::get(format!("{}a/../b", m.url()))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(m.request().url(), "/b");
+}
+
+#[test]
+fn dot_segments_can_be_kept() {
+    let m = mock!();
+    let url = format!("{}a/../b", m.url());
+
+    let response = Request::get(&url)
+        .url_normalization(UrlNormalization::new().remove_dot_segments(false))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(m.request().url(), "/a/../b");
+    assert_eq!(response.effective_uri().unwrap().to_string(), url);
+}
+
+#[test]
+fn effective_uri_is_normalized_url() {
+    let m = mock!();
+
+    let response = Request::get(format!("{}%7euser/a(b)?q=%2f", m.url()))
+        .url_normalization(
+            UrlNormalization::new()
+                .decode_unreserved(true)
+                .percent_encode_path("()"),
+        )
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(m.request().url(), "/~user/a%28b%29?q=%2F");
+    assert_eq!(
+        response.effective_uri().unwrap().to_string(),
+        format!("{}~user/a%28b%29?q=%2F", m.url())
+    );
+}
+
+#[test]
+fn url_hygiene_transforms_are_applied() {
+    let m = mock!();
+
+    let response = Request::get(format!("{}a//b?z=1&a=2", m.url()))
+        .url_normalization(
+            UrlNormalization::new()
+                .merge_slashes(true)
+                .trailing_slash(TrailingSlash::Add)
+                .sort_query(true),
+        )
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(m.request().url(), "/a/b/?a=2&z=1");
+    assert_eq!(
+        response.effective_uri().unwrap().to_string(),
+        format!("{}a/b/?a=2&z=1", m.url())
+    );
+}
@@ -0,0 +1,166 @@
+#![cfg(feature = "openapi")]
+
+use isahc::{
+    error::ErrorKind,
+    http::{Method, Uri},
+    openapi::{OpenApi, Violation},
+    prelude::*,
+    ResponseExt,
+};
+use testserver::mock;
+
+const DOCUMENT: &[u8] = br##"{
+    "openapi": "3.0.3",
+    "servers": [{"url": "https://api.example.org/v1"}],
+    "paths": {
+        "/users/{id}": {
+            "get": {
+                "responses": {
+                    "200": {
+                        "content": {
+                            "application/json": {
+                                "schema": {"$ref": "#/components/schemas/User"}
+                            }
+                        }
+                    },
+                    "4XX": {"$ref": "#/components/responses/Error"}
+                }
+            }
+        },
+        "/users/me": {
+            "get": {
+                "responses": {"204": {"description": "no content"}}
+            }
+        }
+    },
+    "components": {
+        "schemas": {
+            "User": {
+                "type": "object",
+                "required": ["id", "name"],
+                "additionalProperties": false,
+                "properties": {
+                    "id": {"type": "integer"},
+                    "name": {"type": "string"},
+                    "role": {"type": "string", "enum": ["admin", "member"]},
+                    "manager": {"allOf": [{"$ref": "#/components/schemas/User"}], "nullable": true},
+                    "tags": {"type": "array", "items": {"type": "string"}}
+                }
+            }
+        },
+        "responses": {
+            "Error": {
+                "content": {
+                    "application/problem+json": {
+                        "schema": {"type": "object", "required": ["title"]}
+                    }
+                }
+            }
+        }
+    }
+}"##;
+
+fn validate(status: u16, content_type: &'static str, body: &'static str) -> Vec<Violation> {
+    let api = OpenApi::from_slice(DOCUMENT).unwrap();
+
+    let m = mock! {
+        status: status,
+        headers {
+            "Content-Type": content_type,
+        }
+        body: body,
+    };
+
+    let mut response = isahc::get(format!("{}v1/users/1", m.url())).unwrap();
+    let uri = response.effective_uri().unwrap().clone();
+    let body = response.bytes().unwrap();
+    let response = response.map(|_| body);
+
+    api.validate(&Method::GET, &uri, &response)
+}
+
+#[test]
+fn valid_response_has_no_violations() {
+    assert_eq!(
+        validate(
+            200,
+            "application/json; charset=utf-8",
+            r#"{"id": 1, "name": "jane", "role": "admin", "manager": null, "tags": ["a"]}"#
+        ),
+        []
+    );
+    assert_eq!(
+        validate(404, "application/problem+json", r#"{"title": "not found"}"#),
+        []
+    );
+}
+
+#[test]
+fn schema_violations_are_reported() {
+    let violations = validate(
+        200,
+        "application/json",
+        r#"{"id": "1", "role": "owner", "extra": true, "manager": {"id": 2}, "tags": [1]}"#,
+    )
+    .iter()
+    .map(ToString::to_string)
+    .collect::<Vec<_>>();
+
+    assert_eq!(
+        violations,
+        [
+            "body at `$`: missing required property `name`",
+            "body at `$.extra`: property is not allowed",
+            "body at `$.id`: expected integer, got string",
+            "body at `$.manager`: missing required property `name`",
+            "body at `$.role`: \"owner\" is not one of the allowed values",
+            "body at `$.tags[0]`: expected string, got number",
+        ]
+    );
+}
+
+#[test]
+fn undocumented_status_and_content_type() {
+    assert_eq!(
+        validate(500, "application/json", "{}"),
+        [Violation::UndocumentedStatus {
+            status: 500
+        }]
+    );
+    assert_eq!(
+        validate(200, "text/html", "<html>"),
+        [Violation::UndocumentedContentType {
+            content_type: Some("text/html".into())
+        }]
+    );
+    assert!(matches!(
+        validate(200, "application/json", "{").as_slice(),
+        [Violation::InvalidJson { .. }]
+    ));
+}
+
+#[test]
+fn literal_path_is_preferred_and_operations_must_exist() {
+    let api = OpenApi::from_slice(DOCUMENT).unwrap();
+    let response = isahc::http::Response::builder().status(204).body("").unwrap();
+
+    let uri = Uri::from_static("https://api.example.org/v1/users/me");
+    assert_eq!(api.validate(&Method::GET, &uri, &response), []);
+
+    let error = api.check(&Method::DELETE, &uri, &response).unwrap_err();
+    assert_eq!(
+        error.violations(),
+        [Violation::UndocumentedOperation {
+            method: "DELETE".into(),
+            path: "/v1/users/me".into(),
+        }]
+    );
+    assert_eq!(isahc::Error::from(error).kind(), &ErrorKind::ProtocolViolation);
+}
+
+#[test]
+fn document_without_paths_is_rejected() {
+    let error = OpenApi::from_slice(br#"{"openapi": "3.0.3"}"#).unwrap_err();
+
+    assert_eq!(error.kind(), &ErrorKind::ProtocolViolation);
+}
@@ -0,0 +1,85 @@
+use isahc::{config::Pacing, HttpClient};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+use testserver::mock;
+
+#[test]
+fn requests_to_same_host_are_spaced_out() {
+    let m = mock!();
+
+    let client = HttpClient::builder()
+        .pacing(Pacing::per_host(Duration::from_millis(200)))
+        .build()
+        .unwrap();
+
+    let start = Instant::now();
+
+    let threads = (0..3)
+        .map(|_| {
+            let client = client.clone();
+            let url = m.url();
+
+            thread::spawn(move || client.get(url).unwrap().status())
+        })
+        .collect::<Vec<_>>();
+
+    for thread in threads {
+        assert_eq!(thread.join().unwrap(), 200);
+    }
+
+    // The first request begins right away, then one every interval.
+    assert!(start.elapsed() >= Duration::from_millis(400));
+    assert_eq!(m.requests_received(), 3);
+}
+
+#[test]
+fn requests_to_other_hosts_are_not_held_up() {
+    let m1 = mock!();
+    let m2 = mock!();
+
+    let client = HttpClient::builder()
+        .pacing(Pacing::per_host(Duration::from_secs(5)))
+        .build()
+        .unwrap();
+
+    client.get(m1.url()).unwrap();
+
+    let start = Instant::now();
+    client.get(m2.url()).unwrap();
+
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[test]
+fn pacing_respects_in_flight_limit() {
+    let m = mock! {
+        delay: 100ms,
+    };
+
+    let client = HttpClient::builder()
+        .max_in_flight(1)
+        .pacing(Pacing::per_host(Duration::from_millis(50)))
+        .build()
+        .unwrap();
+
+    let start = Instant::now();
+
+    let threads = (0..3)
+        .map(|_| {
+            let client = client.clone();
+            let url = m.url();
+
+            thread::spawn(move || client.get(url).unwrap().status())
+        })
+        .collect::<Vec<_>>();
+
+    for thread in threads {
+        assert_eq!(thread.join().unwrap(), 200);
+    }
+
+    // Requests still run one at a time.
+    assert!(start.elapsed() >= Duration::from_millis(300));
+    assert_eq!(m.requests_received(), 3);
+}
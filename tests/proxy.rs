@@ -1,4 +1,10 @@
-use isahc::{prelude::*, Request};
+use isahc::{
+    auth::{Authentication, Credentials},
+    config::{CaCertificate, SslOption, TlsVersion},
+    prelude::*,
+    HttpClient,
+    Request,
+};
 use testserver::{mock, socks4::Socks4Server};
 
 #[test]
@@ -42,6 +48,26 @@ fn http_proxy() {
     m.request().expect_header("proxy-connection", "Keep-Alive");
 }
 
+#[test]
+fn proxy_tls_options_do_not_apply_to_plain_http_proxy() {
+    let m = mock!();
+    let proxy = m.url().parse::<http::Uri>().unwrap();
+
+    let upstream = "http://127.0.0.2:1234/".parse::<http::Uri>().unwrap();
+
+    Request::get(upstream.clone())
+        .proxy(proxy)
+        .proxy_ssl_ca_certificate(CaCertificate::file("does-not-exist.pem"))
+        .proxy_ssl_min_version(TlsVersion::Tls13)
+        .proxy_ssl_options(SslOption::DANGER_ACCEPT_INVALID_CERTS)
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(m.request().url(), upstream.to_string());
+}
+
 #[test]
 #[cfg_attr(tarpaulin, ignore)]
 fn socks4_proxy() {
@@ -95,3 +121,39 @@ fn proxy_blacklist_works() {
 
     assert_eq!(m.requests_received(), 1);
 }
+
+#[test]
+fn proxy_credentials_are_sent_to_proxy() {
+    let m = mock!();
+    let proxy = m.url().parse::<http::Uri>().unwrap();
+
+    Request::get("http://127.0.0.2:1234/")
+        .proxy(proxy)
+        .proxy_authentication(Authentication::basic())
+        .proxy_credentials(Credentials::new("clark", "querty"))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    m.request()
+        .expect_header("proxy-authorization", "Basic Y2xhcms6cXVlcnR5");
+}
+
+#[test]
+fn request_proxy_overrides_client_proxy() {
+    let m = mock!();
+
+    // The client proxy does not exist, so the request would fail if it were
+    // used.
+    let client = HttpClient::builder()
+        .proxy("http://127.0.0.2:1234/".parse::<http::Uri>().unwrap())
+        .build()
+        .unwrap();
+
+    client
+        .send(Request::get(m.url()).proxy(None).body(()).unwrap())
+        .unwrap();
+
+    assert_eq!(m.requests_received(), 1);
+}
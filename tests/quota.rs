@@ -0,0 +1,107 @@
+use isahc::{
+    config::{Quota, QuotaTag},
+    HttpClient,
+    Request,
+};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+use testserver::mock;
+
+fn tagged(url: String, name: &'static str) -> Request<()> {
+    Request::get(url)
+        .extension(QuotaTag::new(name))
+        .body(())
+        .unwrap()
+}
+
+#[test]
+fn concurrent_requests_are_limited_by_quota() {
+    let m = mock! {
+        delay: 200ms,
+    };
+
+    let client = HttpClient::builder()
+        .quota("slow", Quota::new().max_concurrent(1))
+        .build()
+        .unwrap();
+
+    let start = Instant::now();
+
+    let threads = (0..3)
+        .map(|_| {
+            let client = client.clone();
+            let url = m.url();
+
+            thread::spawn(move || client.send(tagged(url, "slow")).unwrap().status())
+        })
+        .collect::<Vec<_>>();
+
+    for thread in threads {
+        assert_eq!(thread.join().unwrap(), 200);
+    }
+
+    assert!(start.elapsed() >= Duration::from_millis(600));
+    assert_eq!(m.requests_received(), 3);
+}
+
+#[test]
+fn request_rate_is_limited_by_quota() {
+    let m = mock!();
+
+    let client = HttpClient::builder()
+        .quota("limited", Quota::new().max_per_second(2))
+        .build()
+        .unwrap();
+
+    let start = Instant::now();
+
+    for _ in 0..4 {
+        client.send(tagged(m.url(), "limited")).unwrap();
+    }
+
+    // Two requests may begin right away, then one every half second.
+    assert!(start.elapsed() >= Duration::from_millis(900));
+    assert_eq!(m.requests_received(), 4);
+}
+
+#[test]
+fn requests_for_other_quotas_are_not_limited() {
+    let m = mock!();
+
+    let client = HttpClient::builder()
+        .quota("limited", Quota::new().max_per_second(1))
+        .build()
+        .unwrap();
+
+    client.send(tagged(m.url(), "limited")).unwrap();
+
+    let start = Instant::now();
+
+    client.get(m.url()).unwrap();
+    client.send(tagged(m.url(), "unregistered")).unwrap();
+
+    assert!(start.elapsed() < Duration::from_millis(500));
+    assert_eq!(m.requests_received(), 3);
+}
+
+#[test]
+fn quota_is_shared_across_agent_threads() {
+    let m = mock!();
+
+    let client = HttpClient::builder()
+        .agent_threads(4)
+        .quota("limited", Quota::new().max_per_second(2))
+        .build()
+        .unwrap();
+
+    let start = Instant::now();
+
+    for _ in 0..4 {
+        client.send(tagged(m.url(), "limited")).unwrap();
+    }
+
+    assert!(start.elapsed() >= Duration::from_millis(900));
+    assert_eq!(m.requests_received(), 4);
+}
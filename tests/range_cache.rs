@@ -0,0 +1,154 @@
+use isahc::{config::RangeCache, prelude::*, HttpClient, Request};
+use testserver::mock;
+
+fn get_range(client: &HttpClient, url: &str, range: &str) -> (u16, String, String) {
+    let mut response = client
+        .send(Request::get(url).header("range", range).body(()).unwrap())
+        .unwrap();
+    let content_range = response
+        .headers()
+        .get("content-range")
+        .map(|value| value.to_str().unwrap().to_owned())
+        .unwrap_or_default();
+
+    (response.status().as_u16(), content_range, response.text().unwrap())
+}
+
+#[test]
+fn cached_range_is_served_without_asking_server() {
+    let m = mock! {
+        status: 206,
+        headers {
+            "content-range": "bytes 0-4/11",
+            "etag": "\"v1\"",
+        }
+        body: "hello",
+    };
+    let cache = RangeCache::new();
+    let client = HttpClient::builder().range_cache(cache.clone()).build().unwrap();
+
+    assert_eq!(
+        get_range(&client, &m.url(), "bytes=0-4"),
+        (206, "bytes 0-4/11".into(), "hello".into())
+    );
+    assert_eq!(cache.ranges(&m.url().parse().unwrap()), [0..=4]);
+
+    assert_eq!(
+        get_range(&client, &m.url(), "bytes=1-3"),
+        (206, "bytes 1-3/11".into(), "ell".into())
+    );
+    assert_eq!(m.requests_received(), 1);
+}
+
+#[test]
+fn missing_part_is_requested_and_stitched() {
+    let m = mock! {
+        #0 => {
+            status: 206,
+            headers {
+                "content-range": "bytes 0-4/11",
+                "etag": "\"v1\"",
+            }
+            body: "hello",
+        },
+        #1 => {
+            status: 206,
+            headers {
+                "content-range": "bytes 5-10/11",
+                "etag": "\"v1\"",
+            }
+            body: " world",
+        },
+    };
+    let cache = RangeCache::new();
+    let client = HttpClient::builder().range_cache(cache.clone()).build().unwrap();
+
+    get_range(&client, &m.url(), "bytes=0-4");
+
+    assert_eq!(
+        get_range(&client, &m.url(), "bytes=2-"),
+        (206, "bytes 2-10/11".into(), "llo world".into())
+    );
+
+    let request = &m.requests()[1];
+    request.expect_header("range", "bytes=5-10");
+    request.expect_header("if-range", "\"v1\"");
+
+    assert_eq!(cache.ranges(&m.url().parse().unwrap()), [0..=10]);
+
+    assert_eq!(
+        get_range(&client, &m.url(), "bytes=-3"),
+        (206, "bytes 8-10/11".into(), "rld".into())
+    );
+    assert_eq!(m.requests_received(), 2);
+}
+
+#[test]
+fn changed_resource_is_not_stitched() {
+    let m = mock! {
+        #0 => {
+            status: 206,
+            headers {
+                "content-range": "bytes 0-4/11",
+                "etag": "\"v1\"",
+            }
+            body: "hello",
+        },
+        #1 => {
+            status: 200,
+            headers {
+                "etag": "\"v2\"",
+            }
+            body: "HELLO WORLD",
+        },
+    };
+    let cache = RangeCache::new();
+    let client = HttpClient::builder().range_cache(cache.clone()).build().unwrap();
+
+    get_range(&client, &m.url(), "bytes=0-4");
+
+    assert_eq!(
+        get_range(&client, &m.url(), "bytes=0-10"),
+        (200, String::new(), "HELLO WORLD".into())
+    );
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn responses_without_validator_are_not_cached() {
+    let m = mock! {
+        status: 206,
+        headers {
+            "content-range": "bytes 0-4/11",
+        }
+        body: "hello",
+    };
+    let cache = RangeCache::new();
+    let client = HttpClient::builder().range_cache(cache.clone()).build().unwrap();
+
+    get_range(&client, &m.url(), "bytes=0-4");
+    get_range(&client, &m.url(), "bytes=0-4");
+
+    assert!(cache.is_empty());
+    assert_eq!(m.requests_received(), 2);
+}
+
+#[test]
+fn unsafe_request_forgets_ranges() {
+    let m = mock! {
+        status: 206,
+        headers {
+            "content-range": "bytes 0-4/11",
+            "etag": "\"v1\"",
+        }
+        body: "hello",
+    };
+    let cache = RangeCache::new();
+    let client = HttpClient::builder().range_cache(cache.clone()).build().unwrap();
+
+    get_range(&client, &m.url(), "bytes=0-4");
+    assert_eq!(cache.len(), 1);
+
+    client.put(m.url(), "new").unwrap();
+    assert!(cache.is_empty());
+}
@@ -1,4 +1,11 @@
-use isahc::{config::RedirectPolicy, prelude::*, Body, HttpClient, Request};
+use isahc::{
+    config::{CacheControl, CacheStatus, RedirectPolicy},
+    prelude::*,
+    Body,
+    HttpClient,
+    Request,
+};
+use std::time::Duration;
 use test_case::test_case;
 use testserver::mock;
 
@@ -317,3 +324,310 @@ fn redirect_with_unencoded_utf8_bytes_in_location() {
     assert_eq!(m1.requests_received(), 1);
     assert_eq!(m2.requests_received(), 1);
 }
+
+#[test_case(301)]
+#[test_case(308)]
+fn permanent_redirect_is_cached(status: u16) {
+    let m2 = mock! {
+        status: 200,
+        body: "ok",
+    };
+    let location = m2.url();
+
+    let m1 = mock! {
+        status: status,
+        headers {
+            "Location": location,
+        }
+    };
+
+    let client = HttpClient::builder()
+        .redirect_policy(RedirectPolicy::Follow)
+        .build()
+        .unwrap();
+
+    for cache_status in [CacheStatus::Miss, CacheStatus::Hit] {
+        let mut response = client.get(m1.url()).unwrap();
+
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().unwrap(), "ok");
+        assert_eq!(response.effective_uri().unwrap().to_string(), m2.url());
+        assert_eq!(response.cache_status(), Some(cache_status));
+    }
+
+    assert_eq!(m1.requests_received(), 1);
+    assert_eq!(m2.requests_received(), 2);
+
+    let cache = client.redirect_cache().unwrap();
+    assert_eq!(cache.count(CacheStatus::Hit), 1);
+    assert_eq!(cache.count(CacheStatus::Miss), 1);
+    assert_eq!(
+        cache.entries(),
+        vec![(m1.url().parse().unwrap(), m2.url().parse().unwrap())]
+    );
+
+    // The cache is not used when not following redirects.
+    let response = Request::get(m1.url())
+        .redirect_policy(RedirectPolicy::None)
+        .body(())
+        .unwrap();
+    let response = client.send(response).unwrap();
+    assert_eq!(response.status(), status);
+    assert_eq!(response.cache_status(), Some(CacheStatus::Bypass));
+    assert_eq!(m1.requests_received(), 2);
+    assert_eq!(cache.count(CacheStatus::Bypass), 1);
+
+    cache.clear();
+    client.get(m1.url()).unwrap();
+    assert_eq!(m1.requests_received(), 3);
+}
+
+#[test_case(302)]
+#[test_case(307)]
+fn temporary_redirect_is_not_cached(status: u16) {
+    let m2 = mock!();
+    let location = m2.url();
+
+    let m1 = mock! {
+        status: status,
+        headers {
+            "Location": location,
+        }
+    };
+
+    let client = HttpClient::builder()
+        .redirect_policy(RedirectPolicy::Follow)
+        .build()
+        .unwrap();
+
+    client.get(m1.url()).unwrap();
+    client.get(m1.url()).unwrap();
+
+    assert_eq!(m1.requests_received(), 2);
+    assert!(client.redirect_cache().unwrap().is_empty());
+}
+
+#[test]
+fn cached_redirect_varies_by_request_headers() {
+    let m2 = mock!();
+    let location = m2.url();
+
+    let m1 = mock! {
+        status: 301,
+        headers {
+            "Location": location,
+            "Vary": "Accept-Language",
+        }
+    };
+
+    let client = HttpClient::builder()
+        .redirect_policy(RedirectPolicy::Follow)
+        .build()
+        .unwrap();
+
+    let get = |language: &str| {
+        let request = Request::get(m1.url())
+            .header("Accept-Language", language)
+            .body(())
+            .unwrap();
+
+        client.send(request).unwrap();
+    };
+
+    get("en");
+    get("en");
+    assert_eq!(m1.requests_received(), 1);
+
+    get("de");
+    get("de");
+    assert_eq!(m1.requests_received(), 2);
+
+    let variants = client
+        .redirect_cache()
+        .unwrap()
+        .variants(&m1.url().parse().unwrap());
+
+    assert_eq!(variants.len(), 2);
+    assert_eq!(variants[0].request_header(&"accept-language".parse().unwrap()).unwrap(), "en");
+    assert_eq!(variants[1].request_header(&"accept-language".parse().unwrap()).unwrap(), "de");
+}
+
+#[test]
+fn unsafe_request_invalidates_cached_redirect() {
+    let m2 = mock!();
+    let location = m2.url();
+
+    let m1 = mock! {
+        status: 301,
+        headers {
+            "Location": location,
+        }
+    };
+
+    let client = HttpClient::builder()
+        .redirect_policy(RedirectPolicy::Follow)
+        .build()
+        .unwrap();
+
+    client.get(m1.url()).unwrap();
+    assert_eq!(client.redirect_cache().unwrap().len(), 1);
+
+    // Redirect responses count as successful.
+    let request = Request::delete(m1.url())
+        .redirect_policy(RedirectPolicy::None)
+        .body(())
+        .unwrap();
+    client.send(request).unwrap();
+
+    assert!(client.redirect_cache().unwrap().is_empty());
+
+    client.get(m1.url()).unwrap();
+    assert_eq!(m1.requests_received(), 3);
+}
+
+#[test]
+fn cache_control_directs_redirect_cache() {
+    let m2 = mock!();
+    let location = m2.url();
+
+    let m1 = mock! {
+        status: 301,
+        headers {
+            "Location": location,
+        }
+    };
+
+    let client = HttpClient::builder()
+        .redirect_policy(RedirectPolicy::Follow)
+        .build()
+        .unwrap();
+
+    let send = |directives: CacheControl| {
+        let request = Request::get(m1.url())
+            .cache_control(directives)
+            .body(())
+            .unwrap();
+
+        client.send(request).unwrap()
+    };
+
+    send(CacheControl::new().no_store());
+    m1.request().expect_header("Cache-Control", "no-store");
+    m2.request().expect_header("Cache-Control", "no-store");
+    assert!(client.redirect_cache().unwrap().is_empty());
+
+    send(CacheControl::new());
+    assert_eq!(m1.requests_received(), 2);
+
+    let response = send(CacheControl::new().no_cache());
+    assert_eq!(response.cache_status(), Some(CacheStatus::Bypass));
+    assert_eq!(m1.requests_received(), 3);
+
+    let response = send(CacheControl::new().max_age(Duration::from_secs(60)));
+    assert_eq!(response.cache_status(), Some(CacheStatus::Hit));
+    m2.requests()[3].expect_header("Cache-Control", "max-age=60");
+
+    let response = send(CacheControl::new().max_age(Duration::from_secs(0)));
+    assert_eq!(response.cache_status(), Some(CacheStatus::Miss));
+    assert_eq!(m1.requests_received(), 4);
+}
+
+#[test]
+fn vary_star_redirect_is_not_cached() {
+    let m2 = mock!();
+    let location = m2.url();
+
+    let m1 = mock! {
+        status: 301,
+        headers {
+            "Location": location,
+            "Vary": "*",
+        }
+    };
+
+    let client = HttpClient::builder()
+        .redirect_policy(RedirectPolicy::Follow)
+        .build()
+        .unwrap();
+
+    client.get(m1.url()).unwrap();
+    client.get(m1.url()).unwrap();
+
+    assert_eq!(m1.requests_received(), 2);
+}
+
+#[test]
+fn no_store_redirect_is_not_cached() {
+    let m2 = mock!();
+    let location = m2.url();
+
+    let m1 = mock! {
+        status: 301,
+        headers {
+            "Location": location,
+            "Cache-Control": "private, no-store",
+        }
+    };
+
+    let client = HttpClient::builder()
+        .redirect_policy(RedirectPolicy::Follow)
+        .build()
+        .unwrap();
+
+    client.get(m1.url()).unwrap();
+    client.get(m1.url()).unwrap();
+
+    assert_eq!(m1.requests_received(), 2);
+}
+
+#[test]
+fn post_redirect_is_not_cached() {
+    let m2 = mock!();
+    let location = m2.url();
+
+    let m1 = mock! {
+        status: 308,
+        headers {
+            "Location": location,
+        }
+    };
+
+    let client = HttpClient::builder()
+        .redirect_policy(RedirectPolicy::Follow)
+        .build()
+        .unwrap();
+
+    let response = client.post(m1.url(), "hello").unwrap();
+    assert_eq!(response.cache_status(), Some(CacheStatus::Bypass));
+
+    let response = client.get(m1.url()).unwrap();
+    assert_eq!(response.cache_status(), Some(CacheStatus::Miss));
+
+    assert_eq!(m1.requests_received(), 2);
+}
+
+#[test]
+fn redirect_cache_can_be_disabled() {
+    let m2 = mock!();
+    let location = m2.url();
+
+    let m1 = mock! {
+        status: 301,
+        headers {
+            "Location": location,
+        }
+    };
+
+    let client = HttpClient::builder()
+        .redirect_policy(RedirectPolicy::Follow)
+        .redirect_cache(None)
+        .build()
+        .unwrap();
+
+    client.get(m1.url()).unwrap();
+    let response = client.get(m1.url()).unwrap();
+
+    assert_eq!(m1.requests_received(), 2);
+    assert!(client.redirect_cache().is_none());
+    assert_eq!(response.cache_status(), None);
+}
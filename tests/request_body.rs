@@ -1,9 +1,16 @@
-use futures_lite::{future::block_on, AsyncRead};
-use isahc::{prelude::*, AsyncBody, Body, Request};
+use futures_lite::{
+    future::{block_on, zip},
+    AsyncRead,
+};
+use isahc::{config::RedirectPolicy, prelude::*, AsyncBody, Body, Request};
 use std::{
     error::Error,
     io::{self, Read},
     pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
     task::{Context, Poll},
 };
 use test_case::test_case;
@@ -93,6 +100,36 @@ fn content_length_header_takes_precedence_over_body_objects_length(method: &str)
     m.request().expect_body("abc"); // truncated to 3 bytes
 }
 
+#[test]
+fn deferred_body_is_produced_again_when_redirected() {
+    let m2 = mock!();
+    let location = m2.url();
+
+    let m1 = mock! {
+        status: 307,
+        headers {
+            "Location": location,
+        }
+    };
+
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let response = Request::post(m1.url())
+        .redirect_policy(RedirectPolicy::Follow)
+        .body(Body::from_fn({
+            let calls = calls.clone();
+            move || format!("attempt {}", calls.fetch_add(1, Ordering::SeqCst) + 1)
+        }))
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    m1.request().expect_body("attempt 1");
+    m2.request().expect_body("attempt 2");
+}
+
 #[test]
 fn upload_from_bad_reader_returns_error_with_original_cause() {
     let m = mock!();
@@ -151,3 +188,96 @@ fn upload_from_bad_async_reader_returns_error_with_original_cause() {
         io::ErrorKind::UnexpectedEof
     );
 }
+
+#[test]
+fn response_body_can_be_streamed_into_another_request() {
+    let body = "abcdefgh".repeat(64 * 1024);
+
+    let source_body = body.clone();
+    let source = mock! {
+        body: source_body.clone(),
+    };
+    let destination = mock!();
+
+    let response = isahc::get(source.url()).unwrap();
+
+    Request::put(destination.url())
+        .body(response)
+        .unwrap()
+        .send()
+        .unwrap();
+
+    destination.request().expect_body(body);
+}
+
+#[test]
+fn async_response_body_can_be_streamed_into_another_request() {
+    let body = "abcdefgh".repeat(1024);
+
+    let source_body = body.clone();
+    let source = mock! {
+        body: source_body.clone(),
+    };
+    let destination = mock!();
+
+    block_on(async {
+        let response = isahc::get_async(source.url()).await.unwrap();
+
+        Request::put(destination.url())
+            .body(response)
+            .unwrap()
+            .send_async()
+            .await
+            .unwrap();
+    });
+
+    destination
+        .request()
+        .expect_header("content-length", body.len().to_string());
+    destination.request().expect_body(body);
+}
+
+#[test]
+fn response_body_can_be_fanned_out_to_several_requests() {
+    let body = "abcdefgh".repeat(64 * 1024);
+
+    let source_body = body.clone();
+    let source = mock! {
+        body: source_body.clone(),
+    };
+    let first = mock!();
+    let second = mock!();
+
+    block_on(async {
+        let response = isahc::get_async(source.url()).await.unwrap();
+        let mut bodies = AsyncBody::from(response).fan_out(2);
+
+        let (a, b) = zip(
+            isahc::put_async(first.url(), bodies.remove(0)),
+            isahc::put_async(second.url(), bodies.remove(0)),
+        )
+        .await;
+
+        a.unwrap();
+        b.unwrap();
+    });
+
+    first.request().expect_body(&body);
+    second.request().expect_body(body);
+}
+
+#[test]
+fn large_body_of_known_size_is_streamed_from_reader() {
+    const LEN: u64 = 8 * 1024 * 1024;
+
+    let m = mock!();
+
+    Request::put(m.url())
+        .body(Body::from_reader_sized(io::repeat(b'x').take(LEN), LEN))
+        .unwrap()
+        .send()
+        .unwrap();
+
+    m.request().expect_header("content-length", LEN.to_string());
+    m.request().expect_body(vec![b'x'; LEN as usize]);
+}
@@ -0,0 +1,139 @@
+use isahc::{
+    config::Configurable,
+    prelude::*,
+    testing::{Difference, Replay, RequestRecorder},
+    Body,
+    HttpClient,
+    Request,
+};
+use testserver::mock;
+
+#[test]
+fn requests_are_recorded_in_order() {
+    let recorder = RequestRecorder::new().unwrap();
+    recorder.respond_with(404, "not found");
+
+    let client = HttpClient::builder().dial(recorder.dialer()).build().unwrap();
+
+    let mut response = client.get("http://api.example.org/users?page=2").unwrap();
+
+    assert_eq!(response.status(), 404);
+    assert_eq!(response.text().unwrap(), "not found");
+
+    recorder.ignore_header("accept-encoding");
+
+    client
+        .send(
+            Request::put("http://api.example.org/users/1")
+                .header("X-Custom", "a")
+                .header("X-Custom", "b")
+                .body(Body::from_reader("streamed body".as_bytes()))
+                .unwrap(),
+        )
+        .unwrap();
+
+    let requests = recorder.requests();
+
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].method(), "GET");
+    assert_eq!(requests[0].target(), "/users?page=2");
+    assert!(requests[0].header("user-agent").is_some());
+    assert!(!requests[0].snapshot().contains("User-Agent"));
+    assert!(requests[0].snapshot().contains("Accept-Encoding"));
+
+    requests[1].assert_snapshot(
+        "
+        PUT /users/1 HTTP/1.1
+        Host: api.example.org
+        Accept: */*
+        x-custom: a
+        x-custom: b
+        transfer-encoding: chunked
+        Expect: 100-continue
+
+        streamed body
+        ",
+    );
+}
+
+#[test]
+#[should_panic(expected = "request does not match snapshot")]
+fn mismatched_snapshot_panics() {
+    let recorder = RequestRecorder::new().unwrap();
+
+    HttpClient::builder()
+        .dial(recorder.dialer())
+        .build()
+        .unwrap()
+        .get("http://api.example.org/")
+        .unwrap();
+
+    recorder
+        .last_request()
+        .unwrap()
+        .assert_snapshot("GET /other HTTP/1.1");
+}
+
+#[test]
+fn recorded_session_can_be_replayed() {
+    let v1 = mock! {
+        headers {
+            "X-Version": "1",
+        }
+        body: "hello",
+    };
+
+    let recorder = RequestRecorder::new().unwrap();
+    recorder.forward_to(v1.addr());
+
+    let client = HttpClient::builder().dial(recorder.dialer()).build().unwrap();
+
+    let mut response = client.post("http://api.example.org/greet", "hi").unwrap();
+    assert_eq!(response.text().unwrap(), "hello");
+    client.get("http://api.example.org/other").unwrap();
+
+    let recording = recorder.requests();
+    assert_eq!(recording[0].response().unwrap().body(), b"hello");
+    assert!(recording[0].offset() <= recording[1].offset());
+
+    // Replaying against the same server matches, and the server receives the
+    // exact same requests.
+    let results = Replay::new(v1.addr()).run(&recording).unwrap();
+
+    assert!(results.iter().all(|result| result.matches()));
+    assert_eq!(v1.requests_received(), 4);
+    v1.requests()[2].expect_body("hi");
+
+    let v2 = mock! {
+        status: 201,
+        headers {
+            "X-Version": "2",
+        }
+        body: "hello",
+    };
+
+    let results = Replay::new(v2.addr())
+        .preserve_timing(true)
+        .run(&recording)
+        .unwrap();
+
+    assert!(!results[0].matches());
+    assert_eq!(results[0].response().status(), 201);
+    assert_eq!(
+        results[0].diff().to_string(),
+        "status: expected 200, got 201\nheader `x-version`: expected [\"1\"], got [\"2\"]\n"
+    );
+
+    let results = Replay::new(v2.addr())
+        .ignore_header("X-Version")
+        .run(&recording)
+        .unwrap();
+
+    assert_eq!(
+        results[1].diff().differences(),
+        [Difference::Status {
+            expected: 200,
+            actual: 201,
+        }]
+    );
+}
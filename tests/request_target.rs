@@ -0,0 +1,31 @@
+use isahc::{config::RequestTarget, error::ErrorKind, prelude::*, Request};
+use testserver::mock;
+
+#[test]
+fn request_target_is_sent_as_given() {
+    let m = mock!();
+
+    Request::get(m.url())
+        .extension(RequestTarget::new("/a/../b?%zz=%"))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(m.request().url(), "/a/../b?%zz=%");
+}
+
+#[test]
+fn request_target_with_line_break_is_rejected() {
+    let m = mock!();
+
+    let error = Request::get(m.url())
+        .extension(RequestTarget::new("/\r\nx-injected: true"))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap_err();
+
+    assert_eq!(error.kind(), &ErrorKind::InvalidRequest);
+    assert_eq!(m.requests_received(), 0);
+}
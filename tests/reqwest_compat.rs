@@ -0,0 +1,94 @@
+#![cfg(feature = "reqwest-compat")]
+
+use futures_lite::future::block_on;
+use isahc::{compat::reqwest::Client, error::ErrorKind};
+use testserver::mock;
+
+#[test]
+fn get_text() {
+    let m = mock! {
+        body: "hello world",
+    };
+
+    let text = block_on(async {
+        Client::new()
+            .get(m.url())
+            .header("x-test", "1")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap()
+    });
+
+    assert_eq!(text, "hello world");
+    m.request().expect_header("x-test", "1");
+}
+
+#[test]
+fn redirects_are_followed_by_default() {
+    let m2 = mock! {
+        body: "ok",
+    };
+    let location = m2.url();
+
+    let m1 = mock! {
+        status: 301,
+        headers {
+            "Location": location,
+        }
+    };
+
+    let response = block_on(Client::new().get(m1.url()).send()).unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.url().to_string(), m2.url());
+}
+
+#[test]
+fn error_for_status_fails_for_error_responses() {
+    let m = mock! {
+        status: 404,
+    };
+
+    let error = block_on(Client::new().get(m.url()).send())
+        .unwrap()
+        .error_for_status()
+        .unwrap_err();
+
+    assert_eq!(error.kind(), &ErrorKind::BadStatus);
+}
+
+#[test]
+fn bearer_auth_sets_authorization_header() {
+    let m = mock!();
+
+    block_on(Client::new().get(m.url()).bearer_auth("token").send()).unwrap();
+
+    m.request().expect_header("authorization", "Bearer token");
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn post_json() {
+    let m = mock! {
+        body: r#"{"ok":true}"#,
+    };
+
+    let value: serde_json::Value = block_on(async {
+        Client::new()
+            .post(m.url())
+            .json(&serde_json::json!({"hello": "world"}))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap()
+    });
+
+    assert_eq!(value["ok"], true);
+    m.request().expect_header("content-type", "application/json");
+    m.request().expect_body(r#"{"hello":"world"}"#);
+}
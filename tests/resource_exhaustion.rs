@@ -0,0 +1,86 @@
+#![cfg(unix)]
+
+//! These tests lower the file descriptor limit of the whole process, so they
+//! live in a test binary of their own.
+
+use isahc::{
+    config::{AgentEvent, AgentEventKind, ResourceLimit},
+    HttpClient,
+};
+use std::{
+    fs::File,
+    io,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+use testserver::mock;
+
+/// Open files until the process runs out of file descriptors.
+fn exhaust_file_descriptors() -> Vec<File> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+
+    // Keep the limit low so that hitting it is quick.
+    unsafe {
+        assert_eq!(libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit), 0);
+        limit.rlim_cur = limit.rlim_cur.min(512);
+        assert_eq!(libc::setrlimit(libc::RLIMIT_NOFILE, &limit), 0);
+    }
+
+    let mut files = Vec::new();
+
+    loop {
+        match File::open("/dev/null") {
+            Ok(file) => files.push(file),
+            Err(e) if e.raw_os_error() == Some(libc::EMFILE) => return files,
+            Err(e) => panic!("unexpected error: {}", e),
+        }
+    }
+}
+
+#[test]
+fn request_is_retried_once_file_descriptors_are_available() {
+    let m1 = mock!();
+    let m2 = mock!();
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let events_clone = events.clone();
+
+    let client = HttpClient::builder()
+        .retry_on_resource_exhaustion(Duration::from_secs(10))
+        .instrumentation(move |event: &AgentEvent| {
+            if let AgentEventKind::ResourceExhausted { .. } = event.kind() {
+                events_clone.lock().unwrap().push(event.kind().clone());
+            }
+        })
+        .build()
+        .unwrap();
+
+    // Get the agent thread going before running out of file descriptors.
+    client.get(m1.url()).unwrap();
+
+    let files = exhaust_file_descriptors();
+
+    let release = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        drop(files);
+    });
+
+    let result = client.get(m2.url());
+    release.join().unwrap();
+
+    assert_eq!(result.map_err(io::Error::from).unwrap().status(), 200);
+    assert_eq!(m2.requests_received(), 1);
+
+    let events = events.lock().unwrap();
+    assert!(!events.is_empty());
+    assert!(events.iter().all(|kind| matches!(
+        kind,
+        AgentEventKind::ResourceExhausted {
+            limit: ResourceLimit::FileDescriptors,
+            retry_in: Some(_),
+        }
+    )));
+}
@@ -1,7 +1,18 @@
 use futures_lite::{future::block_on, io::AsyncReadExt};
-use isahc::prelude::*;
-use std::{io, io::Read};
-use testserver::mock;
+use isahc::{config::ShutdownPolicy, prelude::*};
+use std::{
+    io,
+    io::Read,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+        Arc,
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use testserver::{mock, NetworkConditions};
 
 #[macro_use]
 mod utils;
@@ -44,6 +55,28 @@ fn response_body_bytes_async() {
     });
 }
 
+#[test]
+fn concurrent_async_responses_on_one_thread() {
+    let m = mock! {
+        body: "hello world",
+    };
+
+    block_on(async move {
+        let client = isahc::HttpClient::new().unwrap();
+
+        // Both requests are in flight at once, driven only by this thread.
+        let (first, second) = futures_lite::future::zip(
+            async { client.get_async(m.url()).await.unwrap().text().await.unwrap() },
+            async { client.get_async(m.url()).await.unwrap().text().await.unwrap() },
+        )
+        .await;
+
+        assert_eq!(first, "hello world");
+        assert_eq!(second, "hello world");
+        assert_eq!(m.requests_received(), 2);
+    });
+}
+
 #[test]
 fn zero_length_response_body() {
     let m = mock! {
@@ -73,6 +106,84 @@ fn large_response_body() {
     assert_eq!(response_text, body);
 }
 
+#[test]
+fn large_response_bodies_complete_with_small_transfer_quantum() {
+    let body = "wow so large ".repeat(10_000);
+
+    let m = {
+        let body = body.clone();
+        mock! {
+            body: body.clone(),
+        }
+    };
+
+    let client = isahc::HttpClient::builder()
+        .transfer_quantum(1024)
+        .build()
+        .unwrap();
+
+    // Interleave two transfers so that they compete for the agent.
+    let mut first = client.get(m.url()).unwrap();
+    let mut second = client.get(m.url()).unwrap();
+
+    assert_eq!(first.text().unwrap(), body);
+    assert_eq!(second.text().unwrap(), body);
+}
+
+#[test]
+fn many_concurrent_requests_complete_with_tiny_request_queue() {
+    let m = mock! {
+        body: "hello world",
+    };
+
+    let client = isahc::HttpClient::builder()
+        .request_queue_capacity(1)
+        // The mock server can starve connections when many are opened at
+        // once, so share one connection. Requests are still submitted
+        // concurrently.
+        .max_connections(1)
+        .build()
+        .unwrap();
+
+    let threads = (0..16)
+        .map(|_| {
+            let client = client.clone();
+            let url = m.url();
+
+            std::thread::spawn(move || client.get(url).unwrap().text().unwrap())
+        })
+        .collect::<Vec<_>>();
+
+    for thread in threads {
+        assert_eq!(thread.join().unwrap(), "hello world");
+    }
+}
+
+#[test]
+fn concurrent_responses_complete_with_small_buffer_limit() {
+    let body = "wow so large ".repeat(10_000);
+
+    let m = {
+        let body = body.clone();
+        mock! {
+            body: body.clone(),
+        }
+    };
+
+    let client = isahc::HttpClient::builder()
+        .max_buffered_response_bytes(1024)
+        .build()
+        .unwrap();
+
+    // Receive both responses before reading either body, so that the budget
+    // is exhausted by the first while the second is waiting.
+    let mut first = client.get(m.url()).unwrap();
+    let mut second = client.get(m.url()).unwrap();
+
+    assert_eq!(second.text().unwrap(), body);
+    assert_eq!(first.text().unwrap(), body);
+}
+
 #[test]
 fn response_body_with_content_length_knows_its_size() {
     let m = mock! {
@@ -128,6 +239,35 @@ fn dropping_client_does_not_abort_response_transfer() {
     assert_eq!(response.text().unwrap().len(), body.len());
 }
 
+#[test]
+fn dropping_client_with_abort_policy_aborts_response_transfer() {
+    struct SlowReader;
+
+    impl Read for SlowReader {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            thread::sleep(Duration::from_secs(2));
+            Ok(0)
+        }
+    }
+
+    let m = mock! {
+        _ => {
+            body_reader: io::Cursor::new(vec![0; 100_000]).chain(SlowReader),
+        },
+    };
+
+    let client = isahc::HttpClient::builder()
+        .shutdown_policy(ShutdownPolicy::Abort)
+        .build()
+        .unwrap();
+    let mut response = client.get(m.url()).unwrap();
+    drop(client);
+
+    let error = isahc::Error::from(response.copy_to(io::sink()).unwrap_err());
+
+    assert_eq!(error.kind(), &isahc::error::ErrorKind::ClientClosed);
+}
+
 // See issue #72.
 #[test]
 fn reading_from_response_body_after_eof_continues_to_return_eof() {
@@ -189,3 +329,102 @@ fn consume_unread_response_body_async() {
         assert_matches!(response.body_mut().read(&mut buf).await, Ok(0));
     });
 }
+
+#[test]
+fn response_body_received_in_small_pieces_is_complete() {
+    let body = "wow so large ".repeat(5_000);
+
+    let m = {
+        let body = body.clone();
+        mock! {
+            network: NetworkConditions::new()
+                .max_write_size(7)
+                .bandwidth(1024 * 1024),
+            body: body.clone(),
+        }
+    };
+
+    let mut response = isahc::get(m.url()).unwrap();
+
+    assert_eq!(response.text().unwrap(), body);
+}
+
+#[test]
+fn connection_closed_during_response_body_produces_error() {
+    let m = mock! {
+        network: NetworkConditions::new().disconnect_after(10_000),
+        body: vec![b'x'; 100_000],
+    };
+
+    let mut response = isahc::get(m.url()).unwrap();
+
+    assert!(response.copy_to(io::sink()).is_err());
+}
+
+#[test]
+fn response_is_delayed_by_network_latency() {
+    let m = mock! {
+        network: NetworkConditions::new()
+            .latency_range(Duration::from_millis(100)..Duration::from_millis(200)),
+        body: "hello world",
+    };
+
+    let start = Instant::now();
+    let mut response = isahc::get(m.url()).unwrap();
+
+    assert_eq!(response.text().unwrap(), "hello world");
+    assert!(start.elapsed() >= Duration::from_millis(100));
+}
+
+#[test]
+fn body_chunks_are_pushed_to_callback() {
+    let m = mock! {
+        body: "hello world\n".repeat(10_000),
+    };
+
+    let chunks = Arc::new(Mutex::new(Vec::new()));
+
+    let mut response = isahc::Request::get(m.url())
+        .on_body_chunk({
+            let chunks = chunks.clone();
+            move |chunk| chunks.lock().unwrap().push(chunk)
+        })
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    // The body is delivered to the callback instead.
+    assert_eq!(response.text().unwrap(), "");
+
+    let received = chunks.lock().unwrap().concat();
+    assert_eq!(received, "hello world\n".repeat(10_000).as_bytes());
+}
+
+#[test]
+fn body_chunk_callback_receives_body_after_response_is_dropped() {
+    let m = mock! {
+        body: vec![b'a'; 100_000],
+    };
+
+    let (done_tx, done_rx) = mpsc::channel();
+    let received = Arc::new(AtomicUsize::new(0));
+
+    let response = isahc::Request::get(m.url())
+        .on_body_chunk({
+            let received = received.clone();
+            move |chunk| {
+                received.fetch_add(chunk.len(), Ordering::SeqCst);
+            }
+        })
+        .on_stats(move |_| done_tx.send(()).unwrap())
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    drop(response);
+
+    done_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(received.load(Ordering::SeqCst), 100_000);
+}
@@ -0,0 +1,234 @@
+use isahc::{
+    config::{Clock, RetryBudget, RetryPolicy},
+    error::ErrorKind,
+    prelude::*,
+    testing::TestClock,
+    AsyncBody,
+    HttpClient,
+    Request,
+};
+use std::time::Duration;
+use testserver::mock;
+
+fn client(policy: RetryPolicy, clock: &TestClock) -> HttpClient {
+    HttpClient::builder()
+        .retry_policy(policy)
+        .clock(clock.clone())
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn requests_are_not_retried_by_default() {
+    let m = mock! {
+        status: 503,
+    };
+
+    let response = isahc::get(m.url()).unwrap();
+
+    assert_eq!(response.status(), 503);
+    assert_eq!(m.requests_received(), 1);
+}
+
+#[test]
+fn service_unavailable_is_retried() {
+    let m = mock! {
+        #0 => {
+            status: 503,
+        },
+        _ => {
+            body: "ok",
+        },
+    };
+
+    let clock = TestClock::new();
+    let mut response = client(RetryPolicy::new(3), &clock).get(m.url()).unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.text().unwrap(), "ok");
+    assert_eq!(m.requests_received(), 2);
+}
+
+#[test]
+fn last_response_is_returned_after_max_attempts() {
+    let m = mock! {
+        status: 429,
+    };
+
+    let clock = TestClock::new();
+    let start = clock.now();
+    let policy = RetryPolicy::new(3)
+        .backoff(Duration::from_secs(1), Duration::from_secs(10))
+        .jitter(false);
+
+    let response = client(policy, &clock).get(m.url()).unwrap();
+
+    assert_eq!(response.status(), 429);
+    assert_eq!(m.requests_received(), 3);
+
+    // Backoff of 1 second, then 2 seconds.
+    assert_eq!(clock.now() - start, Duration::from_secs(3));
+}
+
+#[test]
+fn retry_after_is_honored() {
+    let m = mock! {
+        #0 => {
+            status: 503,
+            headers {
+                "retry-after": "7",
+            }
+        },
+        _ => {},
+    };
+
+    let clock = TestClock::new();
+    let start = clock.now();
+
+    let response = client(RetryPolicy::new(2), &clock).get(m.url()).unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(clock.now() - start, Duration::from_secs(7));
+}
+
+#[test]
+fn retry_after_longer_than_max_backoff_is_not_retried() {
+    let m = mock! {
+        status: 503,
+        headers {
+            "retry-after": "3600",
+        }
+    };
+
+    let clock = TestClock::new();
+    let response = client(RetryPolicy::new(2), &clock).get(m.url()).unwrap();
+
+    assert_eq!(response.status(), 503);
+    assert_eq!(m.requests_received(), 1);
+}
+
+#[test]
+fn non_idempotent_requests_are_not_retried_on_status() {
+    let m = mock! {
+        status: 503,
+    };
+
+    let clock = TestClock::new();
+    let response = client(RetryPolicy::new(3), &clock)
+        .post(m.url(), "hello")
+        .unwrap();
+
+    assert_eq!(response.status(), 503);
+    assert_eq!(m.requests_received(), 1);
+}
+
+#[test]
+fn request_body_is_sent_again() {
+    let m = mock! {
+        #0 => {
+            status: 502,
+        },
+        _ => {},
+    };
+
+    let clock = TestClock::new();
+    let response = client(RetryPolicy::new(2), &clock)
+        .put(m.url(), "hello")
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(m.requests_received(), 2);
+
+    for request in m.requests() {
+        request.expect_body("hello");
+    }
+}
+
+#[test]
+fn streaming_body_is_not_retried() {
+    let m = mock! {
+        status: 503,
+    };
+
+    let clock = TestClock::new();
+    let request = Request::put(m.url())
+        .body(AsyncBody::from_reader(futures_lite::io::Cursor::new(
+            b"hello".to_vec(),
+        )))
+        .unwrap();
+
+    let response = futures_lite::future::block_on(
+        client(RetryPolicy::new(3), &clock).send_async(request),
+    )
+    .unwrap();
+
+    assert_eq!(response.status(), 503);
+    assert_eq!(m.requests_received(), 1);
+}
+
+#[test]
+fn connection_failures_are_retried() {
+    let clock = TestClock::new();
+    let budget = RetryBudget::new(0.2);
+
+    let client = HttpClient::builder()
+        .retry_policy(RetryPolicy::new(3))
+        .retry_budget(budget.clone())
+        .clock(clock.clone())
+        .build()
+        .unwrap();
+
+    // Nothing should be listening on this port.
+    let error = client.post("http://127.0.0.1:1", "hello").unwrap_err();
+
+    assert_eq!(error.kind(), &ErrorKind::ConnectionFailed);
+    assert_eq!(budget.retries(), 2);
+}
+
+#[test]
+fn retries_are_limited_by_retry_budget() {
+    let m = mock! {
+        status: 503,
+    };
+
+    let clock = TestClock::new();
+    let budget = RetryBudget::new(0.0).min_per_second(0).burst(1);
+
+    let client = HttpClient::builder()
+        .retry_policy(RetryPolicy::new(5))
+        .retry_budget(budget.clone())
+        .clock(clock)
+        .build()
+        .unwrap();
+
+    let response = client.get(m.url()).unwrap();
+
+    assert_eq!(response.status(), 503);
+    assert_eq!(m.requests_received(), 2);
+    assert_eq!(budget.exhausted(), 1);
+}
+
+#[test]
+fn policy_can_be_set_per_request() {
+    let m = mock! {
+        #0 => {
+            status: 500,
+        },
+        _ => {},
+    };
+
+    let clock = TestClock::new();
+    let client = HttpClient::builder().clock(clock).build().unwrap();
+
+    let response = client
+        .send(
+            Request::get(m.url())
+                .retry_policy(RetryPolicy::new(2))
+                .body(())
+                .unwrap(),
+        )
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(m.requests_received(), 2);
+}
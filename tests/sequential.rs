@@ -0,0 +1,79 @@
+use isahc::{prelude::*, HttpClient, Request};
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use testserver::mock;
+
+#[test]
+fn requests_are_executed_in_submission_order() {
+    let slow = mock! {
+        delay: 200ms,
+    };
+    let fast = mock!();
+
+    let client = HttpClient::builder()
+        .sequential_execution(true)
+        .build()
+        .unwrap();
+    let completed = Arc::new(Mutex::new(Vec::new()));
+
+    let threads = (0..4)
+        .map(|i| {
+            let client = client.clone();
+            let completed = completed.clone();
+            let url = if i == 0 { slow.url() } else { fast.url() };
+
+            thread::spawn(move || {
+                // Stagger the requests so that they are sent in order.
+                thread::sleep(Duration::from_millis(20 * i));
+                client.get(url).unwrap().consume().unwrap();
+                completed.lock().unwrap().push(i);
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    assert_eq!(*completed.lock().unwrap(), [0, 1, 2, 3]);
+}
+
+#[test]
+fn only_one_request_is_in_flight() {
+    let m = mock! {
+        delay: 100ms,
+    };
+
+    let client = HttpClient::builder()
+        .sequential_execution(true)
+        .agent_threads(4)
+        .build()
+        .unwrap();
+
+    let start = Instant::now();
+
+    let threads = (0..3)
+        .map(|_| {
+            let client = client.clone();
+            let url = m.url();
+
+            thread::spawn(move || {
+                client
+                    .send(Request::get(url).body(()).unwrap())
+                    .unwrap()
+                    .consume()
+                    .unwrap()
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    assert!(start.elapsed() >= Duration::from_millis(300));
+    assert_eq!(m.requests_received(), 3);
+}
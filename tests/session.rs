@@ -0,0 +1,78 @@
+use isahc::{error::ErrorKind, prelude::*, HttpClient, Request};
+use testserver::mock;
+
+#[test]
+fn requests_in_session_share_one_connection() {
+    let m = mock!();
+
+    let mut session = HttpClient::builder().build_session().unwrap();
+
+    let mut first = session
+        .send(Request::get(m.url()).body(()).unwrap())
+        .unwrap();
+    first.consume().unwrap();
+
+    let mut second = session
+        .send(Request::get(m.url()).body(()).unwrap())
+        .unwrap();
+    second.consume().unwrap();
+
+    assert_eq!(m.requests_received(), 2);
+    assert_eq!(first.local_addr(), second.local_addr());
+    assert!(!session.is_lost());
+}
+
+#[test]
+fn closed_connection_fails_the_session() {
+    let m = mock!();
+
+    let mut session = HttpClient::builder().build_session().unwrap();
+
+    session
+        .send(
+            Request::get(m.url())
+                .header("connection", "close")
+                .body(())
+                .unwrap(),
+        )
+        .unwrap()
+        .consume()
+        .unwrap();
+
+    let error = session
+        .send(Request::get(m.url()).body(()).unwrap())
+        .unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::ConnectionFailed);
+    assert!(session.is_lost());
+
+    let error = session
+        .send(Request::get(m.url()).body(()).unwrap())
+        .unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::ConnectionFailed);
+    assert_eq!(m.requests_received(), 2);
+}
+
+#[test]
+fn async_requests_in_session_share_one_connection() {
+    let m = mock!();
+
+    let mut session = HttpClient::builder().build_session().unwrap();
+
+    futures_lite::future::block_on(async {
+        let mut first = session
+            .send_async(Request::get(m.url()).body(()).unwrap())
+            .await
+            .unwrap();
+        first.consume().await.unwrap();
+
+        let mut second = session
+            .send_async(Request::get(m.url()).body(()).unwrap())
+            .await
+            .unwrap();
+        second.consume().await.unwrap();
+
+        assert_eq!(first.local_addr(), second.local_addr());
+    });
+}
@@ -0,0 +1,177 @@
+use isahc::{
+    config::{AgentEvent, AgentEventKind, Instrumentation},
+    error::ErrorKind,
+    prelude::*,
+    CancelReason,
+    HttpClient,
+};
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use testserver::mock;
+
+#[macro_use]
+mod utils;
+
+#[test]
+fn shutdown_idle_client_is_clean() {
+    let client = HttpClient::new().unwrap();
+
+    assert!(client.shutdown(Duration::from_secs(5)));
+}
+
+#[test]
+fn shutdown_waits_for_requests_in_progress() {
+    let m = mock! {
+        delay: 200ms,
+        body: "hello world",
+    };
+
+    let client = HttpClient::new().unwrap();
+
+    let request = thread::spawn({
+        let client = client.clone();
+        let url = m.url();
+        move || client.get(url).unwrap().text().unwrap()
+    });
+
+    // Give the request time to begin.
+    thread::sleep(Duration::from_millis(50));
+
+    assert!(client.shutdown(Duration::from_secs(5)));
+    assert_eq!(request.join().unwrap(), "hello world");
+}
+
+#[test]
+fn shutdown_aborts_requests_after_timeout() {
+    let m = mock! {
+        delay: 5s,
+    };
+
+    let client = HttpClient::new().unwrap();
+
+    let request = thread::spawn({
+        let client = client.clone();
+        let url = m.url();
+        move || client.get(url)
+    });
+
+    thread::sleep(Duration::from_millis(50));
+
+    let start = Instant::now();
+
+    assert!(!client.shutdown(Duration::from_millis(200)));
+    assert!(start.elapsed() < Duration::from_secs(2));
+
+    let error = request.join().unwrap().unwrap_err();
+    assert_eq!(error, ErrorKind::ClientClosed);
+    assert_eq!(error.cancel_reason(), Some(CancelReason::Shutdown));
+}
+
+#[test]
+fn requests_after_shutdown_fail() {
+    let m = mock!();
+
+    let client = HttpClient::new().unwrap();
+
+    assert!(client.shutdown(Duration::from_secs(5)));
+    assert_matches!(client.get(m.url()), Err(e) if e == ErrorKind::ClientClosed);
+    assert_matches!(client.clear_connections(), Err(e) if e == ErrorKind::ClientClosed);
+    assert_eq!(m.requests_received(), 0);
+}
+
+#[test]
+fn shutdown_hooks_run_once_in_order() {
+    let calls = Arc::new(Mutex::new(Vec::new()));
+
+    let client = HttpClient::builder()
+        .on_shutdown({
+            let calls = calls.clone();
+            move || calls.lock().unwrap().push("first")
+        })
+        .on_shutdown({
+            let calls = calls.clone();
+            move || calls.lock().unwrap().push("second")
+        })
+        .build()
+        .unwrap();
+
+    assert!(calls.lock().unwrap().is_empty());
+    assert!(client.shutdown(Duration::from_secs(5)));
+    assert_eq!(*calls.lock().unwrap(), ["first", "second"]);
+
+    assert!(client.clone().shutdown(Duration::from_secs(5)));
+    assert_eq!(*calls.lock().unwrap(), ["first", "second"]);
+}
+
+#[test]
+fn shutdown_hooks_run_after_requests_complete() {
+    let m = mock! {
+        delay: 200ms,
+    };
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+
+    let client = HttpClient::builder()
+        .on_shutdown({
+            let calls = calls.clone();
+            move || calls.lock().unwrap().push("shutdown")
+        })
+        .build()
+        .unwrap();
+
+    let request = thread::spawn({
+        let client = client.clone();
+        let calls = calls.clone();
+        let url = m.url();
+        move || {
+            client.get(url).unwrap();
+            calls.lock().unwrap().push("response");
+        }
+    });
+
+    thread::sleep(Duration::from_millis(50));
+
+    assert!(client.shutdown(Duration::from_secs(5)));
+    request.join().unwrap();
+
+    assert_eq!(*calls.lock().unwrap(), ["response", "shutdown"]);
+}
+
+#[test]
+fn instrumentation_is_notified_of_shutdown_after_last_event() {
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<Option<AgentEventKind>>>>);
+
+    impl Instrumentation for Recorder {
+        fn on_event(&self, event: &AgentEvent) {
+            if event.request_id().is_some() {
+                self.0.lock().unwrap().push(Some(event.kind().clone()));
+            }
+        }
+
+        fn on_shutdown(&self) {
+            self.0.lock().unwrap().push(None);
+        }
+    }
+
+    let m = mock!();
+    let recorder = Recorder::default();
+
+    let client = HttpClient::builder()
+        .instrumentation(recorder.clone())
+        .build()
+        .unwrap();
+
+    client.get(m.url()).unwrap();
+
+    assert!(client.shutdown(Duration::from_secs(5)));
+
+    let events = recorder.0.lock().unwrap();
+
+    assert_eq!(events.iter().filter(|event| event.is_none()).count(), 1);
+    assert_eq!(events.last(), Some(&None));
+    assert!(events.contains(&Some(AgentEventKind::RequestCompleted)));
+}
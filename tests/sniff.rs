@@ -0,0 +1,92 @@
+use isahc::{prelude::*, Request};
+use testserver::mock;
+
+#[test]
+fn content_type_is_not_sniffed_by_default() {
+    let m = mock! {
+        body: "<!DOCTYPE html><html></html>",
+    };
+
+    let response = isahc::get(m.url()).unwrap();
+
+    assert_eq!(response.sniffed_content_type(), None);
+}
+
+#[test]
+fn missing_content_type_is_sniffed() {
+    let m = mock! {
+        body: "<!DOCTYPE html><html></html>",
+    };
+
+    let response = Request::get(m.url())
+        .sniff_content_type(true)
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.headers().get("content-type"), None);
+    assert_eq!(response.sniffed_content_type(), Some("text/html"));
+}
+
+#[test]
+fn wrong_content_type_is_sniffed() {
+    let m = mock! {
+        headers {
+            "content-type": "text/plain",
+        }
+        body: &b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR"[..],
+    };
+
+    let response = Request::get(m.url())
+        .sniff_content_type(true)
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.sniffed_content_type(), Some("image/png"));
+}
+
+#[test]
+fn correct_content_type_is_trusted() {
+    let m = mock! {
+        headers {
+            "content-type": "text/css",
+        }
+        body: "body { color: red; }",
+    };
+
+    let response = Request::get(m.url())
+        .sniff_content_type(true)
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.sniffed_content_type(), None);
+}
+
+#[cfg(feature = "text-decoding")]
+#[test]
+fn sniffed_charset_is_used_to_decode_text() {
+    let m = mock! {
+        body: &b"<html><meta charset=\"iso-8859-1\">caf\xe9</html>"[..],
+    };
+
+    let mut response = Request::get(m.url())
+        .sniff_content_type(true)
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(
+        response.sniffed_content_type(),
+        Some("text/html; charset=iso-8859-1")
+    );
+    assert_eq!(
+        response.text().unwrap(),
+        "<html><meta charset=\"iso-8859-1\">café</html>"
+    );
+}
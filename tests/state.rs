@@ -0,0 +1,166 @@
+#![cfg(feature = "state")]
+
+use isahc::{
+    config::RedirectPolicy,
+    error::ErrorKind,
+    prelude::*,
+    ClientState,
+    HttpClient,
+    Request,
+};
+use std::{error::Error, time::Duration};
+use testserver::mock;
+
+fn round_trip(state: ClientState) -> ClientState {
+    serde_json::from_str(&serde_json::to_string(&state).unwrap()).unwrap()
+}
+
+#[test]
+fn learned_redirects_survive_export_and_import() {
+    let m2 = mock!();
+    let location = m2.url();
+
+    let m1 = mock! {
+        status: 301,
+        headers {
+            "Location": location,
+        }
+    };
+
+    let client = HttpClient::builder()
+        .redirect_policy(RedirectPolicy::Follow)
+        .build()
+        .unwrap();
+
+    client.get(m1.url()).unwrap();
+    assert_eq!(m1.requests_received(), 1);
+
+    let state = round_trip(client.export_state());
+    assert!(!state.is_empty());
+
+    let client = HttpClient::builder()
+        .redirect_policy(RedirectPolicy::Follow)
+        .build()
+        .unwrap();
+    client.import_state(state);
+
+    let response = client.get(m1.url()).unwrap();
+
+    assert_eq!(response.effective_uri().unwrap().to_string(), m2.url());
+    assert_eq!(m1.requests_received(), 1);
+    assert_eq!(m2.requests_received(), 2);
+}
+
+#[test]
+fn varying_redirects_survive_export_and_import() {
+    let m2 = mock!();
+    let location = m2.url();
+
+    let m1 = mock! {
+        status: 301,
+        headers {
+            "Location": location,
+            "Vary": "Accept-Language",
+        }
+    };
+
+    let builder = || HttpClient::builder().redirect_policy(RedirectPolicy::Follow);
+    let get = |client: &HttpClient, language: &str| {
+        let request = Request::get(m1.url())
+            .header("Accept-Language", language)
+            .body(())
+            .unwrap();
+
+        client.send(request).unwrap();
+    };
+
+    let client = builder().build().unwrap();
+    get(&client, "en");
+    assert_eq!(m1.requests_received(), 1);
+
+    let state = round_trip(client.export_state());
+
+    let client = builder().build().unwrap();
+    client.import_state(state);
+
+    get(&client, "en");
+    assert_eq!(m1.requests_received(), 1);
+
+    get(&client, "de");
+    assert_eq!(m1.requests_received(), 2);
+}
+
+#[test]
+fn negative_dns_cache_survives_export_and_import() {
+    let builder = || {
+        HttpClient::builder()
+            .dns_negative_cache(Duration::from_secs(60))
+            .connect_timeout(Duration::from_secs(5))
+    };
+
+    let client = builder().build().unwrap();
+    let first = client.get("http://isahc-test.invalid/").unwrap_err();
+    assert!(matches!(
+        first.kind(),
+        ErrorKind::NameResolution | ErrorKind::Timeout
+    ));
+
+    let state = round_trip(client.export_state());
+
+    let client = builder().build().unwrap();
+    client.import_state(state);
+
+    let error = client.get("http://isahc-test.invalid/").unwrap_err();
+    assert_eq!(error.kind(), first.kind());
+    assert!(error
+        .source()
+        .unwrap()
+        .to_string()
+        .contains("failed recently"));
+}
+
+#[test]
+fn empty_state_can_be_imported() {
+    let client = HttpClient::new().unwrap();
+    let state: ClientState = serde_json::from_str("{}").unwrap();
+
+    assert!(state.is_empty());
+    client.import_state(state);
+    assert!(client.export_state().is_empty());
+}
+
+#[cfg(feature = "cookies")]
+#[test]
+fn cookies_survive_export_and_import() {
+    use isahc::cookies::CookieJar;
+
+    let m1 = mock! {
+        headers {
+            "set-cookie": "session=abc",
+            "set-cookie": "persistent=xyz; Max-Age=3600",
+            "set-cookie": "expired=old; Expires=Thu, 01 Jan 1970 00:00:00 GMT",
+        }
+    };
+    let m2 = mock!();
+
+    let client = HttpClient::builder()
+        .cookie_jar(CookieJar::new())
+        .build()
+        .unwrap();
+    client.get(m1.url()).unwrap();
+
+    let state = round_trip(client.export_state());
+
+    let jar = CookieJar::new();
+    let client = HttpClient::builder().cookie_jar(jar.clone()).build().unwrap();
+    client.import_state(state);
+
+    client.get(m2.url()).unwrap();
+
+    m2.request()
+        .expect_header("cookie", "persistent=xyz; session=abc");
+
+    let uri = m2.url().parse().unwrap();
+    assert_eq!(jar.get_by_name(&uri, "session").unwrap().value(), "abc");
+    assert!(jar.get_by_name(&uri, "expired").is_none());
+}
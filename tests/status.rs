@@ -1,3 +1,4 @@
+use isahc::{error::ErrorKind, prelude::*, Request};
 use test_case::test_case;
 use testserver::mock;
 
@@ -24,3 +25,61 @@ fn returns_correct_response_code(status: u16) {
     assert_eq!(response.status(), status);
     assert_eq!(m.requests_received(), 1);
 }
+
+#[test]
+fn fail_with_body_returns_error_with_body_for_error_status() {
+    let m = mock! {
+        status: 404,
+        body: "no such thing",
+    };
+
+    let error = Request::get(m.url())
+        .fail_with_body(1024)
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::BadStatus);
+    assert!(error.is_client());
+    assert!(!error.is_server());
+
+    let response = error.response().unwrap();
+    assert_eq!(response.status(), 404);
+    assert_eq!(response.body(), b"no such thing");
+}
+
+#[test]
+fn fail_with_body_truncates_body_to_limit() {
+    let m = mock! {
+        status: 503,
+        body: "service unavailable",
+    };
+
+    let error = Request::get(m.url())
+        .fail_with_body(7)
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap_err();
+
+    assert!(error.is_server());
+    assert_eq!(error.response().unwrap().body(), b"service");
+}
+
+#[test]
+fn fail_with_body_does_not_affect_successful_responses() {
+    let m = mock! {
+        body: "hello world",
+    };
+
+    let mut response = Request::get(m.url())
+        .fail_with_body(1024)
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.text().unwrap(), "hello world");
+}
@@ -0,0 +1,135 @@
+#![cfg(feature = "blocking")]
+
+use isahc::{error::ErrorKind, tail::TailEvent, HttpClient};
+use std::time::Duration;
+use testserver::mock;
+
+fn appended(bytes: &'static str) -> TailEvent {
+    TailEvent::Appended(bytes.as_bytes().into())
+}
+
+#[test]
+fn appended_bytes_are_followed() {
+    let m = mock! {
+        #0 => {
+            status: 206,
+            headers {
+                "content-range": "bytes 0-4/5",
+            }
+            body: "hello",
+        },
+        #1 => {
+            status: 416,
+            headers {
+                "content-range": "bytes */5",
+            }
+        },
+        _ => {
+            status: 206,
+            headers {
+                "content-range": "bytes 5-10/11",
+            }
+            body: " world",
+        },
+    };
+
+    let mut tail = HttpClient::new()
+        .unwrap()
+        .tail(m.url())
+        .interval(Duration::from_millis(10));
+
+    assert_eq!(tail.next().unwrap().unwrap(), appended("hello"));
+    assert_eq!(tail.next().unwrap().unwrap(), appended(" world"));
+    assert_eq!(tail.offset(), 11);
+
+    let requests = m.requests();
+    requests[0].expect_header("range", "bytes=0-");
+    requests[1].expect_header("range", "bytes=5-");
+    requests[2].expect_header("range", "bytes=5-");
+}
+
+#[test]
+fn shrunk_resource_is_rotated() {
+    let m = mock! {
+        #0 => {
+            status: 416,
+            headers {
+                "content-range": "bytes */3",
+            }
+        },
+        _ => {
+            status: 206,
+            headers {
+                "content-range": "bytes 0-2/3",
+            }
+            body: "new",
+        },
+    };
+
+    let mut tail = HttpClient::new()
+        .unwrap()
+        .tail(m.url())
+        .resume_from(11)
+        .interval(Duration::from_millis(10));
+
+    assert_eq!(tail.next().unwrap().unwrap(), TailEvent::Rotated);
+    assert_eq!(tail.next().unwrap().unwrap(), appended("new"));
+
+    m.requests()[1].expect_header("range", "bytes=0-");
+}
+
+#[test]
+fn replaced_resource_with_new_etag_is_rotated() {
+    let m = mock! {
+        #0 => {
+            status: 206,
+            headers {
+                "content-range": "bytes 0-4/5",
+                "etag": "\"a\"",
+            }
+            body: "hello",
+        },
+        _ => {
+            status: 416,
+            headers {
+                "content-range": "bytes */5",
+                "etag": "\"b\"",
+            }
+        },
+    };
+
+    let mut tail = HttpClient::new()
+        .unwrap()
+        .tail(m.url())
+        .interval(Duration::from_millis(10));
+
+    assert_eq!(tail.next().unwrap().unwrap(), appended("hello"));
+    assert_eq!(tail.next().unwrap().unwrap(), TailEvent::Rotated);
+    assert_eq!(tail.offset(), 0);
+}
+
+#[test]
+fn seen_bytes_are_skipped_if_server_ignores_range() {
+    let m = mock! {
+        body: "hello world",
+    };
+
+    let mut tail = HttpClient::new()
+        .unwrap()
+        .tail(m.url())
+        .resume_from(6);
+
+    assert_eq!(tail.next().unwrap().unwrap(), appended("world"));
+    assert_eq!(tail.offset(), 11);
+}
+
+#[test]
+fn error_status_is_returned() {
+    let m = mock! {
+        status: 404,
+    };
+
+    let mut tail = HttpClient::new().unwrap().tail(m.url());
+
+    assert_eq!(tail.next().unwrap().unwrap_err().kind(), ErrorKind::BadStatus);
+}
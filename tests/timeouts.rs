@@ -1,4 +1,4 @@
-use isahc::{prelude::*, Request};
+use isahc::{prelude::*, HttpClient, Request};
 use std::{
     io::{self, Cursor, Read},
     thread,
@@ -62,3 +62,149 @@ fn timeout_during_response_body_produces_error() {
         std::io::ErrorKind::TimedOut
     );
 }
+
+#[test]
+fn client_timeout_applies_to_requests() {
+    let m = mock! {
+        delay: 1s,
+    };
+
+    let client = HttpClient::builder()
+        .timeout(Duration::from_millis(500))
+        .build()
+        .unwrap();
+
+    let result = client.get(m.url());
+
+    assert_matches!(result, Err(e) if e == isahc::error::ErrorKind::Timeout);
+}
+
+#[test]
+fn low_speed_timeout_aborts_stalled_transfer() {
+    let m = mock! {
+        delay: 3s,
+    };
+
+    let result = Request::get(m.url())
+        .low_speed_timeout(1, Duration::from_secs(1))
+        .body(())
+        .unwrap()
+        .send();
+
+    assert_matches!(result, Err(e) if e == isahc::error::ErrorKind::Timeout);
+}
+
+#[test]
+fn headers_timeout_is_reached_if_server_does_not_respond() {
+    let m = mock! {
+        delay: 1s,
+    };
+
+    let result = Request::get(m.url())
+        .headers_timeout(Duration::from_millis(300))
+        .body(())
+        .unwrap()
+        .send();
+
+    assert_matches!(result, Err(e) if e == isahc::error::ErrorKind::Timeout);
+}
+
+#[test]
+fn headers_timeout_does_not_apply_to_response_body() {
+    struct SlowReader;
+
+    impl Read for SlowReader {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            thread::sleep(Duration::from_millis(600));
+            Ok(0)
+        }
+    }
+
+    let m = mock! {
+        _ => {
+            body_reader: Cursor::new(vec![0; 100_000]).chain(SlowReader),
+        },
+    };
+
+    let mut response = Request::get(m.url())
+        .headers_timeout(Duration::from_millis(300))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    // The body takes longer than the headers timeout to complete.
+    assert_eq!(response.copy_to(io::sink()).unwrap(), 100_000);
+}
+
+#[test]
+fn read_timeout_is_reached_if_response_body_stalls() {
+    struct SlowReader;
+
+    impl Read for SlowReader {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            thread::sleep(Duration::from_secs(2));
+            Ok(0)
+        }
+    }
+
+    let m = mock! {
+        _ => {
+            body_reader: Cursor::new(vec![0; 100_000]).chain(SlowReader),
+        },
+    };
+
+    let mut response = Request::get(m.url())
+        .read_timeout(Duration::from_millis(300))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    let error = response.copy_to(io::sink()).unwrap_err();
+
+    assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    assert_eq!(
+        isahc::Error::from(error).kind(),
+        isahc::error::ErrorKind::ReadTimeout
+    );
+}
+
+#[test]
+fn canceling_request_interrupts_blocking_body_read() {
+    struct SlowReader;
+
+    impl Read for SlowReader {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            thread::sleep(Duration::from_secs(2));
+            Ok(0)
+        }
+    }
+
+    let m = mock! {
+        _ => {
+            body_reader: Cursor::new(vec![0; 100_000]).chain(SlowReader),
+        },
+    };
+
+    let cancel = isahc::CancelHandle::new();
+
+    let mut response = Request::get(m.url())
+        .extension(cancel.clone())
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(300));
+        cancel.cancel();
+    });
+
+    let error = response.copy_to(io::sink()).unwrap_err();
+
+    assert_eq!(
+        isahc::Error::from(error).kind(),
+        isahc::error::ErrorKind::Interrupted
+    );
+}
@@ -0,0 +1,113 @@
+use isahc::{
+    config::{Quota, QuotaTag},
+    prelude::*,
+    HttpClient,
+    Request,
+};
+use futures_lite::future::block_on;
+use std::{
+    io::{Read, Write},
+    net::{Shutdown, TcpListener},
+    sync::{mpsc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use testserver::mock;
+
+#[test]
+fn timestamps_are_ordered() {
+    let m = mock!();
+
+    let response = isahc::get(m.url()).unwrap();
+    let timestamps = response.timestamps().unwrap();
+
+    assert!(timestamps.submitted() <= timestamps.dequeued());
+    assert!(timestamps.dequeued() <= timestamps.started().unwrap());
+}
+
+#[test]
+fn time_waiting_for_quota_is_queue_time() {
+    let m = mock!();
+
+    let client = HttpClient::builder()
+        .quota("limited", Quota::new().max_per_second(1))
+        .build()
+        .unwrap();
+
+    let send = || {
+        client
+            .send(
+                Request::get(m.url())
+                    .extension(QuotaTag::new("limited"))
+                    .body(())
+                    .unwrap(),
+            )
+            .unwrap()
+    };
+
+    let first = send().timestamps().unwrap();
+    let second = send().timestamps().unwrap();
+
+    assert!(first.queue_time() < Duration::from_millis(500));
+    assert!(second.queue_time() >= Duration::from_millis(500));
+}
+
+#[test]
+fn failed_transfer_has_not_started() {
+    let (tx, rx) = mpsc::channel();
+    let tx = Mutex::new(tx);
+
+    let client = HttpClient::builder()
+        .on_stats(move |stats| {
+            tx.lock().unwrap().send(stats.timestamps()).unwrap();
+        })
+        .build()
+        .unwrap();
+
+    // Nothing should be listening on this port.
+    client.get("http://127.0.0.1:1").unwrap_err();
+
+    let timestamps = rx.recv_timeout(Duration::from_secs(5)).unwrap().unwrap();
+
+    assert!(timestamps.submitted() <= timestamps.dequeued());
+    assert_eq!(timestamps.started(), None);
+    assert_eq!(timestamps.headers_received(), None);
+}
+
+#[test]
+fn async_response_resolves_when_headers_are_received() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (sender, receiver) = mpsc::channel::<()>();
+
+    thread::spawn(move || {
+        let mut stream = listener.accept().unwrap().0;
+        let mut buf = [0; 1024];
+        let _ = stream.read(&mut buf).unwrap();
+
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n")
+            .unwrap();
+
+        // Only send the body once the client has the response.
+        receiver.recv().unwrap();
+
+        stream.write_all(b"5\r\nhello\r\n0\r\n\r\n").unwrap();
+        let _ = stream.shutdown(Shutdown::Write);
+    });
+
+    block_on(async {
+        let mut response = isahc::get_async(url).await.unwrap();
+        let returned_at = Instant::now();
+
+        let headers_received_at = response.headers_received_at().unwrap();
+        let timestamps = response.timestamps().unwrap();
+
+        assert!(timestamps.started().unwrap() <= headers_received_at);
+        assert!(headers_received_at <= returned_at);
+
+        sender.send(()).unwrap();
+
+        assert_eq!(response.text().await.unwrap(), "hello");
+    });
+}
@@ -0,0 +1,150 @@
+#![cfg(feature = "trace-context")]
+
+use isahc::{
+    config::RedirectPolicy,
+    prelude::*,
+    trace_context::{Propagation, TraceContext},
+    HttpClient,
+    Request,
+};
+use testserver::mock;
+
+const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+#[test]
+fn no_headers_are_sent_by_default() {
+    let m = mock!();
+
+    let response = HttpClient::new().unwrap().get(m.url()).unwrap();
+
+    assert_eq!(m.request().get_header("traceparent").count(), 0);
+    assert_eq!(m.request().get_header("b3").count(), 0);
+    assert!(response.extensions().get::<TraceContext>().is_none());
+}
+
+#[test]
+fn new_trace_is_started_without_parent() {
+    let m = mock!();
+
+    let client = HttpClient::builder()
+        .trace_propagation(Propagation::W3C)
+        .build()
+        .unwrap();
+
+    let response = client.get(m.url()).unwrap();
+    let context = response.extensions().get::<TraceContext>().unwrap();
+
+    m.request().expect_header("traceparent", context.traceparent());
+    assert_eq!(context.parent_span_id(), None);
+    assert_eq!(m.request().get_header("b3").count(), 0);
+}
+
+#[test]
+fn current_context_is_parent() {
+    let m = mock!();
+
+    let client = HttpClient::builder()
+        .trace_propagation(Propagation::W3CAndB3)
+        .build()
+        .unwrap();
+
+    let parent = TraceContext::from_w3c(TRACEPARENT, Some("congo=t61rcWkgMzE")).unwrap();
+    let guard = parent.clone().attach();
+
+    let response = client.get(m.url()).unwrap();
+
+    drop(guard);
+
+    let context = response.extensions().get::<TraceContext>().unwrap();
+
+    assert_eq!(context.trace_id(), parent.trace_id());
+    assert_eq!(context.parent_span_id(), Some(parent.span_id()));
+
+    m.request().expect_header_regex(
+        "traceparent",
+        "^00-4bf92f3577b34da6a3ce929d0e0e4736-[0-9a-f]{16}-01$",
+    );
+    m.request().expect_header("traceparent", context.traceparent());
+    m.request().expect_header("tracestate", "congo=t61rcWkgMzE");
+    m.request().expect_header("b3", context.b3());
+}
+
+#[test]
+fn request_context_overrides_current_context() {
+    let m = mock!();
+
+    let client = HttpClient::builder()
+        .trace_propagation(Propagation::B3)
+        .build()
+        .unwrap();
+
+    let parent = TraceContext::new_root().with_sampled(false);
+    let _guard = TraceContext::from_w3c(TRACEPARENT, None).unwrap().attach();
+
+    let request = Request::get(m.url())
+        .extension(parent.clone())
+        .body(())
+        .unwrap();
+    client.send(request).unwrap();
+
+    m.request().expect_header_regex(
+        "b3",
+        &format!(
+            "^{:032x}-[0-9a-f]{{16}}-0-{:016x}$",
+            parent.trace_id(),
+            parent.span_id()
+        ),
+    );
+    assert_eq!(m.request().get_header("traceparent").count(), 0);
+}
+
+#[test]
+fn existing_headers_are_not_replaced() {
+    let m = mock!();
+
+    let client = HttpClient::builder()
+        .trace_propagation(Propagation::W3C)
+        .build()
+        .unwrap();
+
+    let request = Request::get(m.url())
+        .header("traceparent", TRACEPARENT)
+        .body(())
+        .unwrap();
+    client.send(request).unwrap();
+
+    m.request().expect_header("traceparent", TRACEPARENT);
+}
+
+#[test]
+fn each_redirect_is_a_sibling_span() {
+    let m2 = mock!();
+    let location = m2.url();
+
+    let m1 = mock! {
+        status: 301,
+        headers {
+            "Location": location,
+        }
+    };
+
+    let client = HttpClient::builder()
+        .trace_propagation(Propagation::W3C)
+        .redirect_policy(RedirectPolicy::Follow)
+        .build()
+        .unwrap();
+
+    let parent = TraceContext::from_w3c(TRACEPARENT, None).unwrap();
+    let _guard = parent.attach();
+
+    client.get(m1.url()).unwrap();
+
+    let first = m1.request().get_header("traceparent").next().unwrap();
+    let second = m2.request().get_header("traceparent").next().unwrap();
+    let first = TraceContext::from_w3c(&first, None).unwrap();
+    let second = TraceContext::from_w3c(&second, None).unwrap();
+
+    assert_eq!(first.trace_id(), second.trace_id());
+    assert_ne!(first.span_id(), second.span_id());
+    assert_eq!(first.trace_id(), 0x4bf92f3577b34da6a3ce929d0e0e4736);
+}
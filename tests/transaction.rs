@@ -0,0 +1,92 @@
+#![cfg(feature = "blocking")]
+
+use isahc::{
+    error::ErrorKind,
+    prelude::*,
+    transaction::{Step, Transaction},
+    HttpClient,
+    Request,
+};
+use std::cell::RefCell;
+use testserver::mock;
+
+#[test]
+fn steps_are_executed_in_order_with_previous_responses() {
+    let m1 = mock! {
+        body: "42",
+    };
+    let m2 = mock!();
+
+    let responses = Transaction::new()
+        .client(HttpClient::new().unwrap())
+        .step(Step::request(Request::post(m1.url()).body("alice").unwrap()))
+        .step(Step::new(|client, responses| {
+            let id = responses[0].text()?;
+
+            client.put(m2.url(), id)
+        }))
+        .execute()
+        .unwrap();
+
+    assert_eq!(responses.len(), 2);
+    m1.request().expect_body("alice");
+    m2.request().expect_body("42");
+}
+
+#[test]
+fn succeeded_steps_are_compensated_in_reverse_order_on_failure() {
+    let m1 = mock!();
+    let m2 = mock!();
+    let m3 = mock! {
+        status: 409,
+    };
+    let m4 = mock!();
+
+    let compensated = RefCell::new(Vec::new());
+
+    let error = Transaction::new()
+        .step(Step::new(|client, _| client.put(m1.url(), "a")).compensate(|_, _| {
+            compensated.borrow_mut().push(1);
+            Ok(())
+        }))
+        .step(Step::new(|client, _| client.put(m2.url(), "b")).compensate(|client, response| {
+            assert_eq!(response.status(), 200);
+            compensated.borrow_mut().push(2);
+            client.delete(m2.url())?;
+            Ok(())
+        }))
+        .step(Step::new(|client, _| client.put(m3.url(), "c")).compensate(|_, _| {
+            compensated.borrow_mut().push(3);
+            Ok(())
+        }))
+        .step(Step::new(|client, _| client.put(m4.url(), "d")))
+        .execute()
+        .unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::BadStatus);
+    assert_eq!(*compensated.borrow(), [2, 1]);
+    assert_eq!(m2.requests()[1].method(), "DELETE");
+    assert_eq!(m4.requests_received(), 0);
+}
+
+#[test]
+fn failed_compensation_does_not_stop_rollback() {
+    let m1 = mock!();
+    let compensated = RefCell::new(Vec::new());
+
+    let error = Transaction::new()
+        .step(Step::new(|client, _| client.put(m1.url(), "a")).compensate(|_, _| {
+            compensated.borrow_mut().push(1);
+            Ok(())
+        }))
+        .step(Step::new(|client, _| client.put(m1.url(), "b")).compensate(|_, _| {
+            compensated.borrow_mut().push(2);
+            Err(ErrorKind::ConnectionFailed.into())
+        }))
+        .step(Step::new(|_, _| Err(ErrorKind::Timeout.into())))
+        .execute()
+        .unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::Timeout);
+    assert_eq!(*compensated.borrow(), [2, 1]);
+}
@@ -0,0 +1,62 @@
+use isahc::{error::TransferMessageKind, prelude::*, Request};
+use std::{net::TcpListener, time::Duration};
+use testserver::mock;
+
+#[test]
+fn failed_transfer_has_last_messages() {
+    let m = mock! {
+        delay: 1s,
+    };
+
+    let error = Request::get(m.url())
+        .header("authorization", "Bearer secret")
+        .timeout(Duration::from_millis(500))
+        .transfer_messages(64)
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap_err();
+
+    let messages = error.transfer_messages();
+
+    assert!(messages.iter().any(|m| m.kind() == TransferMessageKind::Info));
+    assert!(messages
+        .iter()
+        .any(|m| m.kind() == TransferMessageKind::HeaderOut && m.text() == "GET / HTTP/1.1"));
+    assert!(messages.iter().any(|m| m.to_string() == "> authorization: <redacted>"));
+    assert!(!messages.iter().any(|m| m.text().contains("secret")));
+}
+
+#[test]
+fn messages_are_bounded() {
+    // Nothing listens on the port once the listener is dropped.
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let error = Request::get(format!("http://{}", addr))
+        .transfer_messages(1)
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap_err();
+
+    assert_eq!(error.transfer_messages().len(), 1);
+}
+
+#[test]
+fn messages_are_not_kept_by_default() {
+    let m = mock! {
+        delay: 1s,
+    };
+
+    let error = Request::get(m.url())
+        .timeout(Duration::from_millis(500))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap_err();
+
+    assert!(error.transfer_messages().is_empty());
+}
@@ -0,0 +1,115 @@
+use isahc::{config::Transport, error::ErrorKind, prelude::*, HttpClient, Request};
+use std::net::TcpStream;
+use testserver::mock;
+
+#[test]
+fn request_is_sent_over_given_stream() {
+    let m = mock! {
+        body: "hello world",
+    };
+
+    let stream = TcpStream::connect(m.addr()).unwrap();
+
+    // The host name does not need to resolve.
+    let mut response = Request::get("http://tunnel.invalid/path")
+        .extension(Transport::new(stream))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.text().unwrap(), "hello world");
+    assert_eq!(m.request().url(), "/path");
+    m.request().expect_header("host", "tunnel.invalid");
+}
+
+#[test]
+fn transport_can_only_be_used_once() {
+    let m = mock!();
+
+    let client = HttpClient::new().unwrap();
+    let transport = Transport::new(TcpStream::connect(m.addr()).unwrap());
+
+    client
+        .send(
+            Request::get(m.url())
+                .extension(transport.clone())
+                .body(())
+                .unwrap(),
+        )
+        .unwrap()
+        .consume()
+        .unwrap();
+
+    let error = client
+        .send(Request::get(m.url()).extension(transport).body(()).unwrap())
+        .unwrap_err();
+
+    assert_eq!(error.kind(), &ErrorKind::InvalidRequest);
+    assert_eq!(m.requests_received(), 1);
+}
+
+#[test]
+fn stream_is_not_reused_by_other_requests() {
+    let m = mock!();
+
+    let client = HttpClient::new().unwrap();
+    let stream = TcpStream::connect(m.addr()).unwrap();
+    let local_addr = stream.local_addr().unwrap();
+
+    let response = client
+        .send(
+            Request::get(m.url())
+                .extension(Transport::new(stream))
+                .body(())
+                .unwrap(),
+        )
+        .unwrap();
+
+    assert_eq!(response.local_addr(), Some(local_addr));
+    drop(response);
+
+    let response = client.get(m.url()).unwrap();
+
+    assert_ne!(response.local_addr(), Some(local_addr));
+    assert_eq!(m.requests_received(), 2);
+}
+
+#[test]
+#[cfg(unix)]
+fn request_is_sent_over_unix_stream() {
+    use std::{
+        io::{Read, Write},
+        os::unix::net::UnixStream,
+        thread,
+    };
+
+    let (client_end, mut server_end) = UnixStream::pair().unwrap();
+
+    let server = thread::spawn(move || {
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+
+        while !request.ends_with(b"\r\n\r\n") {
+            let len = server_end.read(&mut buf).unwrap();
+            assert!(len > 0);
+            request.extend_from_slice(&buf[..len]);
+        }
+
+        server_end
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\nhello")
+            .unwrap();
+
+        String::from_utf8(request).unwrap()
+    });
+
+    let mut response = Request::get("http://tunnel.invalid/")
+        .extension(Transport::unix(client_end))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.text().unwrap(), "hello");
+    assert!(server.join().unwrap().starts_with("GET / HTTP/1.1\r\n"));
+}
@@ -0,0 +1,158 @@
+#![cfg(feature = "tus")]
+
+use isahc::upload::tus::Upload;
+use std::{
+    io::{Cursor, Write},
+    time::Duration,
+};
+use testserver::mock;
+
+fn upload(url: String, body: &'static [u8]) -> Upload<Cursor<&'static [u8]>> {
+    Upload::new(url, Cursor::new(body))
+        .unwrap()
+        .retry_delay(Duration::from_millis(10))
+}
+
+#[test]
+fn upload_is_created_then_sent_in_chunks() {
+    let m = mock! {
+        #0 => {
+            status: 201,
+            headers {
+                "location": "/files/abc",
+            }
+        },
+        #1 => {
+            status: 204,
+            headers {
+                "upload-offset": "6",
+            }
+        },
+        #2 => {
+            status: 204,
+            headers {
+                "upload-offset": "11",
+            }
+        },
+    };
+
+    let response = upload(m.url(), b"hello world")
+        .metadata("filename", "hello.txt")
+        .chunk_size(6)
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), 204);
+
+    let requests = m.requests();
+    assert_eq!(requests.len(), 3);
+
+    assert_eq!(requests[0].method(), "POST");
+    requests[0].expect_header("tus-resumable", "1.0.0");
+    requests[0].expect_header("upload-length", "11");
+    requests[0].expect_header("upload-metadata", "filename aGVsbG8udHh0");
+
+    assert_eq!(requests[1].method(), "PATCH");
+    assert_eq!(requests[1].url(), "/files/abc");
+    requests[1].expect_header("tus-resumable", "1.0.0");
+    requests[1].expect_header("upload-offset", "0");
+    requests[1].expect_body("hello ");
+
+    requests[2].expect_header("upload-offset", "6");
+    requests[2].expect_body("world");
+}
+
+#[test]
+fn existing_upload_is_resumed_from_server_offset() {
+    let m = mock! {
+        #0 => {
+            status: 200,
+            headers {
+                "upload-offset": "6",
+                "upload-length": "11",
+            }
+        },
+        #1 => writer |w| {
+            // Not a valid HTTP response, so the transfer fails right away.
+            w.write_all(b"HTTP/1.1 oops\r\n\r\n").unwrap();
+            w.flush().unwrap();
+        },
+        #2 => {
+            status: 200,
+            headers {
+                "upload-offset": "8",
+            }
+        },
+        #3 => {
+            status: 204,
+            headers {
+                "upload-offset": "11",
+            }
+        },
+    };
+
+    let response = upload(m.url(), b"hello world")
+        .location(format!("{}files/abc", m.url()))
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), 204);
+
+    let requests = m.requests();
+    assert_eq!(requests.len(), 4);
+
+    assert_eq!(requests[0].method(), "HEAD");
+    requests[1].expect_header("upload-offset", "6");
+    assert_eq!(requests[2].method(), "HEAD");
+    requests[3].expect_header("upload-offset", "8");
+    requests[3].expect_body("rld");
+}
+
+#[test]
+fn chunk_with_checksum_mismatch_is_sent_again() {
+    let m = mock! {
+        #0 => {
+            status: 201,
+            headers {
+                "location": "/files/abc",
+            }
+        },
+        #1 => {
+            status: 460,
+        },
+        #2 => {
+            status: 204,
+            headers {
+                "upload-offset": "11",
+            }
+        },
+    };
+
+    let response = upload(m.url(), b"hello world")
+        .checksum(true)
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), 204);
+
+    let requests = m.requests();
+    assert_eq!(requests.len(), 3);
+
+    for request in &requests[1..] {
+        request.expect_header("upload-checksum", "sha1 Kq5sNclPz7QV2+lfQIuc6R7oRu0=");
+        request.expect_body("hello world");
+    }
+}
+
+#[test]
+fn failed_creation_returns_error() {
+    let m = mock! {
+        status: 413,
+    };
+
+    let error = upload(m.url(), b"hello world").send().unwrap_err();
+
+    assert_eq!(error.kind(), &isahc::error::ErrorKind::BadStatus);
+    assert_eq!(m.requests_received(), 1);
+}
@@ -1,6 +1,6 @@
 #![cfg(unix)]
 
-use isahc::{config::Dialer, prelude::*, Request};
+use isahc::{config::Dialer, error::ErrorKind, prelude::*, HttpClient, Request};
 use std::{
     io::{self, Write},
     os::unix::net::UnixListener,
@@ -40,3 +40,51 @@ fn send_request_to_unix_socket() {
 
     assert_eq!(response.text().unwrap(), "success\n");
 }
+
+#[test]
+#[cfg(target_os = "linux")]
+#[rustfmt::skip]
+fn send_request_to_abstract_unix_socket() {
+    use std::os::{linux::net::SocketAddrExt, unix::net::SocketAddr};
+
+    let name = format!("isahc-test-{}", std::process::id());
+    let addr = SocketAddr::from_abstract_name(name.as_bytes()).unwrap();
+    let listener = UnixListener::bind_addr(&addr).unwrap();
+
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = stream.try_clone().unwrap();
+
+        thread::spawn(move || {
+            io::copy(&mut reader, &mut io::sink()).unwrap();
+        });
+
+        stream.write_all(b"\
+            HTTP/1.1 200 OK\r\n\
+            Content-Length: 8\r\n\
+            \r\n\
+            success\n\
+        ").unwrap();
+    });
+
+    let mut response = Request::get("http://localhost")
+        .dial(Dialer::abstract_unix_socket(name))
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.text().unwrap(), "success\n");
+}
+
+#[test]
+fn unix_socket_with_proxy_fails_to_build() {
+    let error = HttpClient::builder()
+        .dial(Dialer::unix_socket("/path/to/my.sock"))
+        .proxy(Some("http://proxy.example.org".parse().unwrap()))
+        .build()
+        .unwrap_err();
+
+    assert_eq!(error.kind(), &ErrorKind::ClientInitialization);
+    assert!(error.to_string().contains("the proxy would never be used"));
+}
@@ -0,0 +1,177 @@
+use futures_lite::future::block_on;
+use isahc::{prelude::*, Request};
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    thread,
+};
+
+/// Spawn a server that accepts a single connection, switches protocols, and
+/// then echoes back the given number of bytes before closing the connection.
+///
+/// Returns the URL of the server and a handle resolving to the request head
+/// and body the server received.
+fn echo_server(remaining: usize) -> (String, thread::JoinHandle<String>) {
+    server(
+        "HTTP/1.1 101 Switching Protocols\r\nconnection: upgrade\r\nupgrade: echo\r\n\r\n",
+        remaining,
+    )
+}
+
+/// Spawn a server that accepts a single connection, replies to the request
+/// with the given response head, and then echoes back the given number of
+/// bytes before closing the connection.
+fn server(response: &'static str, mut remaining: usize) -> (String, thread::JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+
+    let handle = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut head = Vec::new();
+        let mut byte = [0];
+
+        while !head.ends_with(b"\r\n\r\n") {
+            stream.read_exact(&mut byte).unwrap();
+            head.push(byte[0]);
+        }
+
+        let content_length = String::from_utf8_lossy(&head)
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+            .map(|(_, value)| value.trim().parse().unwrap())
+            .unwrap_or(0);
+
+        let mut body = vec![0; content_length];
+        stream.read_exact(&mut body).unwrap();
+        head.extend_from_slice(&body);
+
+        stream.write_all(response.as_bytes()).unwrap();
+
+        let mut buf = [0; 1024];
+
+        while remaining > 0 {
+            let len = stream.read(&mut buf[..remaining.min(1024)]).unwrap();
+            assert!(len > 0);
+            stream.write_all(&buf[..len]).unwrap();
+            remaining -= len;
+        }
+
+        String::from_utf8(head).unwrap()
+    });
+
+    (url, handle)
+}
+
+#[test]
+fn upgraded_connection_is_bidirectional() {
+    let (url, server) = echo_server(10);
+
+    let response = Request::get(url)
+        .header("connection", "upgrade")
+        .header("upgrade", "echo")
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), 101);
+
+    let mut connection = response.upgrade().unwrap();
+    let mut buf = [0; 5];
+
+    connection.write_all(b"hello").unwrap();
+    connection.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"hello");
+
+    connection.write_all(b"world").unwrap();
+    connection.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"world");
+
+    drop(connection);
+
+    let head = server.join().unwrap().to_lowercase();
+
+    assert!(head.starts_with("get / http/1.1\r\n"));
+    assert!(head.contains("upgrade: echo\r\n"));
+    assert!(!head.contains("transfer-encoding"));
+    assert!(!head.contains("expect"));
+}
+
+#[test]
+fn request_body_is_sent_before_switching_protocols() {
+    let (url, server) = echo_server(5);
+
+    let response = Request::post(url)
+        .header("connection", "upgrade")
+        .header("upgrade", "echo")
+        .body(r#"{"hello":"world"}"#)
+        .unwrap()
+        .send()
+        .unwrap();
+
+    let mut connection = response.upgrade().unwrap();
+    let mut buf = [0; 5];
+
+    connection.write_all(b"hello").unwrap();
+    connection.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"hello");
+
+    drop(connection);
+
+    let request = server.join().unwrap().to_lowercase();
+
+    assert!(request.starts_with("post / http/1.1\r\n"));
+    assert!(request.contains("content-length: 17\r\n"));
+    assert!(request.ends_with("\r\n\r\n{\"hello\":\"world\"}"));
+    assert!(!request.contains("transfer-encoding"));
+}
+
+#[test]
+fn upgraded_connection_can_be_used_asynchronously() {
+    use futures_lite::io::{AsyncReadExt, AsyncWriteExt};
+
+    let (url, server) = echo_server(11);
+
+    block_on(async {
+        let response = Request::get(url)
+            .header("connection", "upgrade")
+            .header("upgrade", "echo")
+            .body(())
+            .unwrap()
+            .send_async()
+            .await
+            .unwrap();
+
+        let mut connection = response.upgrade().unwrap();
+        let mut echoed = String::new();
+
+        connection.write_all(b"hello world").await.unwrap();
+        connection.close().await.unwrap();
+        connection.read_to_string(&mut echoed).await.unwrap();
+
+        assert_eq!(echoed, "hello world");
+    });
+
+    server.join().unwrap();
+}
+
+#[test]
+fn response_is_returned_if_server_does_not_switch() {
+    let (url, server) = server("HTTP/1.1 200 OK\r\ncontent-length: 9\r\n\r\nno thanks", 0);
+
+    let response = Request::get(url)
+        .header("connection", "upgrade")
+        .header("upgrade", "echo")
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    let mut response = response.upgrade().unwrap_err();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.text().unwrap(), "no thanks");
+
+    server.join().unwrap();
+}
@@ -0,0 +1,370 @@
+#![cfg(feature = "blocking")]
+
+use isahc::{
+    config::RetryBudget,
+    error::ErrorKind,
+    upload::{s3::MultipartUpload, ResumableUpload, ResumeProtocol},
+    HttpClient,
+};
+use std::{
+    io::{Cursor, Write},
+    time::Duration,
+};
+use testserver::mock;
+
+fn upload(url: String, body: &'static [u8]) -> ResumableUpload<Cursor<&'static [u8]>> {
+    ResumableUpload::new(url, Cursor::new(body))
+        .unwrap()
+        .retry_delay(Duration::from_millis(10))
+}
+
+#[test]
+fn content_range_upload_is_resumed_after_failure() {
+    let m = mock! {
+        #0 => writer |w| {
+            // Not a valid HTTP response, so the transfer fails right away.
+            w.write_all(b"HTTP/1.1 oops\r\n\r\n").unwrap();
+            w.flush().unwrap();
+        },
+        #1 => {
+            status: 308,
+            headers {
+                "range": "bytes=0-4",
+            }
+        },
+        #2 => {
+            status: 201,
+        },
+    };
+
+    let response = upload(m.url(), b"hello world")
+        .start_offset(0)
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), 201);
+
+    let requests = m.requests();
+    assert_eq!(requests.len(), 3);
+
+    requests[0].expect_header("content-range", "bytes 0-10/11");
+    requests[0].expect_body("hello world");
+
+    assert_eq!(requests[1].method(), "PUT");
+    requests[1].expect_header("content-range", "bytes */11");
+    requests[1].expect_body("");
+
+    requests[2].expect_header("content-range", "bytes 5-10/11");
+    requests[2].expect_body(" world");
+}
+
+#[test]
+fn content_range_upload_queries_offset_first_by_default() {
+    let m = mock! {
+        #0 => {
+            status: 308,
+            headers {
+                "range": "bytes=0-5",
+            }
+        },
+        #1 => {
+            status: 200,
+        },
+    };
+
+    let response = upload(m.url(), b"hello world").send().unwrap();
+
+    assert_eq!(response.status(), 200);
+
+    let requests = m.requests();
+    requests[0].expect_header("content-range", "bytes */11");
+    requests[1].expect_header("content-range", "bytes 6-10/11");
+    requests[1].expect_body("world");
+}
+
+#[test]
+fn upload_offset_upload_is_resumed_after_failure() {
+    let m = mock! {
+        #0 => {
+            status: 200,
+            headers {
+                "upload-offset": "0",
+            }
+        },
+        #1 => writer |w| {
+            // Not a valid HTTP response, so the transfer fails right away.
+            w.write_all(b"HTTP/1.1 oops\r\n\r\n").unwrap();
+            w.flush().unwrap();
+        },
+        #2 => {
+            status: 200,
+            headers {
+                "upload-offset": "6",
+            }
+        },
+        #3 => {
+            status: 204,
+            headers {
+                "upload-offset": "11",
+            }
+        },
+    };
+
+    let response = upload(m.url(), b"hello world")
+        .protocol(ResumeProtocol::UploadOffset)
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), 204);
+
+    let requests = m.requests();
+    assert_eq!(requests.len(), 4);
+
+    assert_eq!(requests[0].method(), "HEAD");
+    assert_eq!(requests[1].method(), "PATCH");
+    requests[1].expect_header("upload-offset", "0");
+    requests[1].expect_header("content-type", "application/offset+octet-stream");
+
+    assert_eq!(requests[2].method(), "HEAD");
+
+    requests[3].expect_header("upload-offset", "6");
+    requests[3].expect_body("world");
+}
+
+#[test]
+fn upload_gives_up_after_max_attempts() {
+    let m = mock! {
+        #0 => writer |w| {
+            // Not a valid HTTP response, so the transfer fails right away.
+            w.write_all(b"HTTP/1.1 oops\r\n\r\n").unwrap();
+            w.flush().unwrap();
+        },
+        #1 => writer |w| {
+            // Not a valid HTTP response, so the transfer fails right away.
+            w.write_all(b"HTTP/1.1 oops\r\n\r\n").unwrap();
+            w.flush().unwrap();
+        },
+        #2 => writer |w| {
+            // Not a valid HTTP response, so the transfer fails right away.
+            w.write_all(b"HTTP/1.1 oops\r\n\r\n").unwrap();
+            w.flush().unwrap();
+        },
+    };
+
+    let result = upload(m.url(), b"hello world")
+        .start_offset(0)
+        .max_attempts(3)
+        .send();
+
+    assert!(result.is_err());
+    assert_eq!(m.requests_received(), 3);
+}
+
+#[test]
+fn upload_stops_retrying_when_retry_budget_is_exhausted() {
+    let m = mock! {
+        #0 => writer |w| {
+            // Not a valid HTTP response, so the transfer fails right away.
+            w.write_all(b"HTTP/1.1 oops\r\n\r\n").unwrap();
+            w.flush().unwrap();
+        },
+        #1 => writer |w| {
+            w.write_all(b"HTTP/1.1 oops\r\n\r\n").unwrap();
+            w.flush().unwrap();
+        },
+    };
+
+    let budget = RetryBudget::new(0.0).min_per_second(0).burst(1);
+    let client = HttpClient::builder()
+        .retry_budget(budget.clone())
+        .build()
+        .unwrap();
+
+    let error = upload(m.url(), b"hello world")
+        .client(client)
+        .start_offset(0)
+        .max_attempts(5)
+        .send()
+        .unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::RetryBudgetExhausted);
+    assert_eq!(m.requests_received(), 2);
+    assert_eq!(budget.retries(), 1);
+    assert_eq!(budget.exhausted(), 1);
+}
+
+#[test]
+fn unexpected_status_is_returned_to_caller() {
+    let m = mock! {
+        status: 404,
+    };
+
+    let response = upload(m.url(), b"hello world").send().unwrap();
+
+    assert_eq!(response.status(), 404);
+    assert_eq!(m.requests_received(), 1);
+}
+
+fn multipart_upload(url: String, body: &'static [u8]) -> MultipartUpload<Cursor<&'static [u8]>> {
+    MultipartUpload::new(format!("{}bucket/key", url), Cursor::new(body))
+        .unwrap()
+        .part_size(5)
+        .concurrency(1)
+        .retry_delay(Duration::from_millis(10))
+}
+
+#[test]
+fn multipart_upload_sends_parts_and_completes() {
+    let m = mock! {
+        #0 => {
+            body: "<InitiateMultipartUploadResult><UploadId>a/b+c</UploadId></InitiateMultipartUploadResult>",
+        },
+        #1 => {
+            headers {
+                "etag": "\"e1\"",
+            }
+        },
+        #2 => {
+            headers {
+                "etag": "\"e2\"",
+            }
+        },
+        #3 => {
+            headers {
+                "etag": "\"e3\"",
+            }
+        },
+        #4 => {
+            body: "<CompleteMultipartUploadResult></CompleteMultipartUploadResult>",
+        },
+    };
+
+    let response = multipart_upload(m.url(), b"hello world").send().unwrap();
+
+    assert_eq!(response.status(), 200);
+
+    let requests = m.requests();
+    assert_eq!(requests.len(), 5);
+
+    assert_eq!(requests[0].method(), "POST");
+    assert_eq!(requests[0].url(), "/bucket/key?uploads");
+
+    assert_eq!(requests[1].method(), "PUT");
+    assert_eq!(requests[1].url(), "/bucket/key?partNumber=1&uploadId=a%2Fb%2Bc");
+    requests[1].expect_body("hello");
+    requests[2].expect_body(" worl");
+    requests[3].expect_body("d");
+
+    assert_eq!(requests[4].method(), "POST");
+    assert_eq!(requests[4].url(), "/bucket/key?uploadId=a%2Fb%2Bc");
+    requests[4].expect_body(
+        "<CompleteMultipartUpload>\
+        <Part><PartNumber>1</PartNumber><ETag>&quot;e1&quot;</ETag></Part>\
+        <Part><PartNumber>2</PartNumber><ETag>&quot;e2&quot;</ETag></Part>\
+        <Part><PartNumber>3</PartNumber><ETag>&quot;e3&quot;</ETag></Part>\
+        </CompleteMultipartUpload>",
+    );
+}
+
+#[test]
+fn multipart_upload_retries_part_after_server_error() {
+    let m = mock! {
+        #0 => {
+            body: "<UploadId>abc</UploadId>",
+        },
+        #1 => {
+            status: 503,
+        },
+        #2 => {
+            headers {
+                "etag": "\"e1\"",
+            }
+        },
+        #3 => {},
+    };
+
+    multipart_upload(m.url(), b"hello").send().unwrap();
+
+    let requests = m.requests();
+    assert_eq!(requests.len(), 4);
+    requests[1].expect_body("hello");
+    requests[2].expect_body("hello");
+}
+
+#[test]
+fn multipart_upload_is_aborted_when_part_fails() {
+    let m = mock! {
+        #0 => {
+            body: "<UploadId>abc</UploadId>",
+        },
+        #1 => {
+            status: 403,
+        },
+        #2 => {
+            status: 204,
+        },
+    };
+
+    let error = multipart_upload(m.url(), b"hello world").send().unwrap_err();
+
+    assert_eq!(error.kind(), &ErrorKind::BadStatus);
+
+    let requests = m.requests();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[2].method(), "DELETE");
+    assert_eq!(requests[2].url(), "/bucket/key?uploadId=abc");
+}
+
+#[test]
+fn multipart_upload_error_in_complete_response_body_fails() {
+    let m = mock! {
+        #0 => {
+            body: "<UploadId>abc</UploadId>",
+        },
+        #1 => {
+            headers {
+                "etag": "\"e1\"",
+            }
+        },
+        #2 => {
+            body: "<Error><Code>InternalError</Code></Error>",
+        },
+    };
+
+    let error = multipart_upload(m.url(), b"hello").send().unwrap_err();
+
+    assert_eq!(error.kind(), &ErrorKind::BadStatus);
+}
+
+#[test]
+fn multipart_upload_parts_are_sent_concurrently() {
+    let m = mock! {
+        _ => {
+            body: "<UploadId>abc</UploadId>",
+            headers {
+                "etag": "\"e\"",
+            }
+        },
+    };
+
+    multipart_upload(m.url(), b"hello world, goodbye world")
+        .concurrency(3)
+        .send()
+        .unwrap();
+
+    let mut parts = m
+        .requests()
+        .iter()
+        .filter(|request| request.method() == "PUT")
+        .map(|request| request.url().to_owned())
+        .collect::<Vec<_>>();
+    parts.sort();
+
+    assert_eq!(
+        parts,
+        (1..=6)
+            .map(|n| format!("/bucket/key?partNumber={}&uploadId=abc", n))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(m.requests_received(), 8);
+}
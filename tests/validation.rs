@@ -0,0 +1,54 @@
+use isahc::{config::RedirectPolicy, error::ErrorKind, prelude::*, Request};
+use testserver::mock;
+
+#[test]
+fn user_info_is_allowed_by_default() {
+    let m = mock!();
+    let url = m.url().replace("http://", "http://user:pass@");
+
+    let response = Request::get(url).body(()).unwrap().send().unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(m.requests_received(), 1);
+}
+
+#[test]
+fn user_info_is_rejected_with_strict_validation() {
+    let m = mock!();
+    let url = m.url().replace("http://", "http://user:pass@");
+
+    let error = Request::get(url)
+        .strict_validation(true)
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap_err();
+
+    assert_eq!(error.kind(), &ErrorKind::InvalidRequest);
+    assert_eq!(m.requests_received(), 0);
+}
+
+#[test]
+fn redirect_targets_are_validated() {
+    let m2 = mock!();
+    let location = m2.url().replace("http://", "http://user:pass@");
+
+    let m1 = mock! {
+        status: 302,
+        headers {
+            "Location": location,
+        }
+    };
+
+    let error = Request::get(m1.url())
+        .redirect_policy(RedirectPolicy::Follow)
+        .strict_validation(true)
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap_err();
+
+    assert_eq!(error.kind(), &ErrorKind::InvalidRequest);
+    assert_eq!(m1.requests_received(), 1);
+    assert_eq!(m2.requests_received(), 0);
+}
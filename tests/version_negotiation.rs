@@ -0,0 +1,68 @@
+use futures_lite::future::block_on;
+use isahc::{config::VersionNegotiation, http::Version, prelude::*, HttpClient, Request};
+use std::time::Duration;
+use testserver::mock;
+
+#[test]
+fn negotiated_version_is_reported() {
+    let m = mock!();
+
+    let response = isahc::get(m.url()).unwrap();
+
+    assert_eq!(response.version(), Version::HTTP_11);
+}
+
+#[test]
+fn http11_only_is_used() {
+    let m = mock!();
+
+    let response = Request::get(m.url())
+        .version_negotiation(VersionNegotiation::http11())
+        .body(())
+        .unwrap()
+        .send()
+        .unwrap();
+
+    assert_eq!(response.version(), Version::HTTP_11);
+    assert_eq!(m.request().get_header("upgrade").count(), 0);
+}
+
+#[test]
+fn concurrent_requests_fall_back_to_separate_connections() {
+    let m = mock!();
+    let client = HttpClient::new().unwrap();
+
+    // The server does not support multiplexing, so requests waiting to see if
+    // they can share a connection must go on to open their own.
+    let responses = block_on(async {
+        let a = client.get_async(m.url());
+        let b = client.get_async(m.url());
+        let c = client.get_async(m.url());
+
+        futures_lite::future::zip(a, futures_lite::future::zip(b, c)).await
+    });
+
+    assert_eq!(responses.0.unwrap().version(), Version::HTTP_11);
+    assert_eq!((responses.1).0.unwrap().version(), Version::HTTP_11);
+    assert_eq!((responses.1).1.unwrap().version(), Version::HTTP_11);
+    assert_eq!(m.requests_received(), 3);
+}
+
+#[test]
+fn http2_prior_knowledge_fails_with_http11_server() {
+    if !isahc::is_http_version_supported(Version::HTTP_2) {
+        return;
+    }
+
+    let m = mock!();
+
+    let result = Request::get(m.url())
+        .version_negotiation(VersionNegotiation::http2())
+        // The server may wait for more data instead of rejecting the request.
+        .timeout(Duration::from_secs(1))
+        .body(())
+        .unwrap()
+        .send();
+
+    assert!(result.is_err());
+}
@@ -0,0 +1,124 @@
+#![cfg(feature = "watch")]
+
+use isahc::{
+    error::ErrorKind,
+    watch::{EventType, Watch},
+};
+use std::time::Duration;
+use testserver::mock;
+
+#[test]
+fn events_are_decoded_and_watch_resumes_after_close() {
+    let m = mock! {
+        #0 => {
+            body: concat!(
+                r#"{"type":"ADDED","object":{"metadata":{"name":"a","resourceVersion":"1"}}}"#, "\n",
+                r#"{"type":"MODIFIED","object":{"metadata":{"name":"a","resourceVersion":"2"}}}"#, "\n",
+                r#"{"type":"BOOKMARK","object":{"metadata":{"resourceVersion":"5"}}}"#, "\n",
+            ),
+        },
+        #1 => {
+            body: concat!(
+                r#"{"type":"DELETED","object":{"metadata":{"name":"a","resourceVersion":"6"}}}"#, "\n",
+            ),
+        },
+    };
+
+    let mut events = Watch::new(format!("{}pods?labelSelector=app", m.url()))
+        .unwrap()
+        .events();
+
+    let event = events.next().unwrap().unwrap();
+    assert_eq!(event.event_type(), EventType::Added);
+    assert_eq!(event.object()["metadata"]["name"], "a");
+    assert_eq!(event.resource_version(), Some("1"));
+
+    let event = events.next().unwrap().unwrap();
+    assert_eq!(event.event_type(), EventType::Modified);
+
+    let event = events.next().unwrap().unwrap();
+    assert_eq!(event.event_type(), EventType::Deleted);
+    assert_eq!(events.resource_version(), Some("6"));
+
+    let requests = m.requests();
+    assert_eq!(
+        requests[0].url(),
+        "/pods?labelSelector=app&watch=1&allowWatchBookmarks=true"
+    );
+    assert_eq!(
+        requests[1].url(),
+        "/pods?labelSelector=app&watch=1&allowWatchBookmarks=true&resourceVersion=5"
+    );
+}
+
+#[test]
+fn expired_resource_version_restarts_watch() {
+    let m = mock! {
+        #0 => {
+            body: concat!(
+                r#"{"type":"ERROR","object":{"kind":"Status","code":410,"reason":"Expired"}}"#, "\n",
+            ),
+        },
+        #1 => {
+            body: concat!(
+                r#"{"type":"ADDED","object":{"metadata":{"name":"a","resourceVersion":"20"}}}"#, "\n",
+            ),
+        },
+    };
+
+    let mut events = Watch::new(m.url())
+        .unwrap()
+        .resource_version("10")
+        .events();
+
+    let event = events.next().unwrap().unwrap();
+    assert_eq!(event.event_type(), EventType::Error);
+    assert_eq!(event.object()["code"], 410);
+    assert_eq!(events.resource_version(), None);
+
+    let event = events.next().unwrap().unwrap();
+    assert_eq!(event.event_type(), EventType::Added);
+
+    let requests = m.requests();
+    assert_eq!(
+        requests[0].url(),
+        "/?watch=1&allowWatchBookmarks=true&resourceVersion=10"
+    );
+    assert_eq!(requests[1].url(), "/?watch=1&allowWatchBookmarks=true");
+}
+
+#[test]
+fn watch_gives_up_after_too_many_failures() {
+    let m = mock! {
+        status: 503,
+    };
+
+    let mut events = Watch::new(m.url())
+        .unwrap()
+        .retry_delay(Duration::from_millis(10))
+        .max_retries(2)
+        .events();
+
+    let error = events.next().unwrap().unwrap_err();
+
+    assert_eq!(error.kind(), &ErrorKind::BadStatus);
+    assert_eq!(m.requests_received(), 3);
+    assert!(events.next().is_none());
+}
+
+#[test]
+fn client_error_is_returned_without_retrying() {
+    let m = mock! {
+        status: 403,
+        body: "forbidden",
+    };
+
+    let mut events = Watch::new(m.url()).unwrap().events();
+
+    let error = events.next().unwrap().unwrap_err();
+
+    assert_eq!(error.kind(), &ErrorKind::BadStatus);
+    assert_eq!(error.response().unwrap().body(), b"forbidden");
+    assert_eq!(m.requests_received(), 1);
+    assert!(events.next().is_none());
+}
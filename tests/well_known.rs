@@ -0,0 +1,186 @@
+#![cfg(feature = "blocking")]
+
+use isahc::{error::ErrorKind, testing::TestClock, well_known::WellKnown, HttpClient};
+use std::time::Duration;
+use testserver::mock;
+
+#[test]
+fn security_txt_is_fetched_and_cached() {
+    let m = mock! {
+        body: "Contact: mailto:security@example.org\nExpires: 2030-01-01T00:00:00.000Z\n",
+    };
+
+    let well_known = WellKnown::new();
+
+    for _ in 0..2 {
+        let security = well_known.security_txt(m.url()).unwrap();
+
+        assert_eq!(security.get("contact"), Some("mailto:security@example.org"));
+    }
+
+    assert_eq!(m.requests_received(), 1);
+    assert_eq!(m.request().url(), "/.well-known/security.txt");
+    m.request().expect_header("accept", "text/plain");
+}
+
+#[test]
+fn uncacheable_documents_are_fetched_again() {
+    let m = mock! {
+        headers {
+            "cache-control": "no-store",
+        }
+        body: "Contact: mailto:security@example.org\n",
+    };
+
+    let well_known = WellKnown::new();
+
+    well_known.security_txt(m.url()).unwrap();
+    well_known.security_txt(m.url()).unwrap();
+
+    assert_eq!(m.requests_received(), 2);
+}
+
+#[test]
+fn clearing_cache_fetches_again() {
+    let m = mock! {
+        body: "{}",
+    };
+
+    let well_known = WellKnown::new();
+
+    well_known.fetch(m.url(), "thing", "*/*").unwrap();
+    well_known.clear_cache();
+    let document = well_known.fetch(m.url(), "thing", "*/*").unwrap();
+
+    assert_eq!(document.body(), b"{}");
+    assert_eq!(m.requests_received(), 2);
+}
+
+#[test]
+fn cached_documents_expire() {
+    let m = mock! {
+        headers {
+            "cache-control": "max-age=60",
+        }
+        body: "{}",
+    };
+
+    let clock = TestClock::new();
+    let client = HttpClient::builder().clock(clock.clone()).build().unwrap();
+    let well_known = WellKnown::new().client(client);
+
+    well_known.fetch(m.url(), "thing", "*/*").unwrap();
+    clock.advance(Duration::from_secs(59));
+    well_known.fetch(m.url(), "thing", "*/*").unwrap();
+
+    assert_eq!(m.requests_received(), 1);
+
+    clock.advance(Duration::from_secs(1));
+    well_known.fetch(m.url(), "thing", "*/*").unwrap();
+
+    assert_eq!(m.requests_received(), 2);
+}
+
+#[test]
+fn error_status_is_returned_as_error() {
+    let m = mock! {
+        status: 404,
+    };
+
+    let error = WellKnown::new().security_txt(m.url()).unwrap_err();
+
+    assert_eq!(error.kind(), &ErrorKind::BadStatus);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn webfinger_lookup() {
+    let m = mock! {
+        headers {
+            "content-type": "application/jrd+json",
+        }
+        body: r#"{
+            "subject": "acct:alice@example.org",
+            "aliases": ["https://example.org/@alice"],
+            "links": [
+                {"rel": "self", "type": "application/activity+json", "href": "https://example.org/users/alice"},
+                {"rel": "http://ostatus.org/schema/1.0/subscribe", "template": "https://example.org/follow?uri={uri}"}
+            ]
+        }"#,
+    };
+
+    let jrd = WellKnown::new()
+        .webfinger(m.url(), "acct:alice@example.org", &["self"])
+        .unwrap();
+
+    assert_eq!(
+        m.request().url(),
+        "/.well-known/webfinger?resource=acct%3Aalice%40example.org&rel=self"
+    );
+    m.request().expect_header("accept", "application/jrd+json");
+
+    assert_eq!(jrd.subject(), Some("acct:alice@example.org"));
+    assert_eq!(jrd.aliases(), &["https://example.org/@alice".to_owned()]);
+    assert_eq!(jrd.links().len(), 2);
+
+    let link = jrd.link("self").unwrap();
+    assert_eq!(link.media_type(), Some("application/activity+json"));
+    assert_eq!(link.href(), Some("https://example.org/users/alice"));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn oauth_metadata_path_is_inserted_before_issuer_path() {
+    let m = mock! {
+        body: r#"{
+            "issuer": "https://example.org/tenant",
+            "token_endpoint": "https://example.org/tenant/token",
+            "scopes_supported": ["read", "write"]
+        }"#,
+    };
+
+    let metadata = WellKnown::new()
+        .oauth_authorization_server(format!("{}tenant", m.url()))
+        .unwrap();
+
+    assert_eq!(
+        m.request().url(),
+        "/.well-known/oauth-authorization-server/tenant"
+    );
+    assert_eq!(metadata.issuer(), "https://example.org/tenant");
+    assert_eq!(metadata.token_endpoint(), Some("https://example.org/tenant/token"));
+    assert_eq!(metadata.authorization_endpoint(), None);
+    assert_eq!(metadata.scopes_supported().collect::<Vec<_>>(), vec!["read", "write"]);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn oauth_metadata_without_issuer_is_rejected() {
+    let m = mock! {
+        body: r#"{"token_endpoint": "https://example.org/token"}"#,
+    };
+
+    let error = WellKnown::new()
+        .oauth_authorization_server(m.url())
+        .unwrap_err();
+
+    assert_eq!(error.kind(), &ErrorKind::ProtocolViolation);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn openid_configuration_is_under_issuer_path() {
+    let m = mock! {
+        body: r#"{"issuer": "https://example.org/realms/main"}"#,
+    };
+
+    let metadata = WellKnown::new()
+        .openid_configuration(format!("{}realms/main/", m.url()))
+        .unwrap();
+
+    assert_eq!(
+        m.request().url(),
+        "/realms/main/.well-known/openid-configuration"
+    );
+    assert_eq!(metadata.issuer(), "https://example.org/realms/main");
+}
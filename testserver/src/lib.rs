@@ -3,6 +3,7 @@
 #[macro_use]
 mod macros;
 mod mock;
+mod network;
 mod pool;
 mod request;
 mod responder;
@@ -12,6 +13,7 @@ pub mod socks4;
 
 pub use macros::macro_api;
 pub use mock::Mock;
+pub use network::NetworkConditions;
 pub use request::Request;
 pub use responder::{RequestContext, Responder};
 pub use response::Response;
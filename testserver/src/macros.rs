@@ -35,12 +35,13 @@ macro_rules! __mock_impl {
 
     (
         @responders($builder:ident)
-        #$num:expr => writer |$writer:ident| {},
+        #$num:expr => writer |$writer:ident| $body:block,
         $($tail:tt)*
     ) => {
         $builder = $builder.responder($crate::macro_api::ClosureResponder::new(move |ctx| {
             if ctx.request().number() == $num {
                 let mut $writer = ctx.into_raw();
+                $body
             }
         }));
 
@@ -97,6 +98,16 @@ macro_rules! __mock_impl {
         $crate::__mock_impl!(@responders($builder) $($tail)*);
     };
 
+    (
+        @responders($builder:ident)
+        network: $conditions:expr,
+        $($tail:tt)*
+    ) => {
+        $builder = $builder.network($conditions);
+
+        $crate::__mock_impl!(@responders($builder) $($tail)*);
+    };
+
     // For backwards compatibility.
     (@responders($builder:ident) $($response_attrs:tt)+) => {
         // $crate::__mock_impl!(@responders($builder) _ => {
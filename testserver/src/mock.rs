@@ -7,7 +7,13 @@
 //! Only HTTP/1.x is implemented, as newer HTTP versions are mostly the same
 //! semantically and are far more complex to deal with.
 
-use crate::{pool::pool, request::Request, responder::*, response::Response};
+use crate::{
+    network::{Link, NetworkConditions},
+    pool::pool,
+    request::Request,
+    responder::*,
+    response::Response,
+};
 use std::{
     collections::VecDeque,
     io::{Cursor, Read, Write},
@@ -29,6 +35,9 @@ pub struct Mock(Arc<Inner>);
 struct Inner {
     server: Server,
 
+    /// Relay clients connect through if network conditions are simulated.
+    link: Option<Link>,
+
     requests: Mutex<VecDeque<Request>>,
 
     /// Number of requests received since the mock was created.
@@ -49,12 +58,16 @@ impl Mock {
     pub fn builder() -> Builder {
         Builder {
             responders: vec![],
+            network: None,
         }
     }
 
     /// Get the socket address of this mock server.
     pub fn addr(&self) -> SocketAddr {
-        self.0.server.server_addr()
+        match self.0.link.as_ref() {
+            Some(link) => link.addr(),
+            None => self.0.server.server_addr(),
+        }
     }
 
     /// Get the HTTP URL of this mock server.
@@ -67,6 +80,12 @@ impl Mock {
         self.0.request_counter.load(Ordering::SeqCst)
     }
 
+    /// Get all requests received by this mock so far, in the order they were
+    /// received.
+    pub fn requests(&self) -> Vec<Request> {
+        self.0.requests.lock().unwrap().iter().cloned().collect()
+    }
+
     /// Get the first request received by this mock.
     pub fn request(&self) -> Request {
         let request = self.0.requests.lock().unwrap().front().cloned();
@@ -76,7 +95,8 @@ impl Mock {
 
     #[rustfmt::skip]
     fn is_ready(&self) -> bool {
-        TcpStream::connect(self.addr())
+        // Bypass any simulated network conditions.
+        TcpStream::connect(self.0.server.server_addr())
             .and_then(|mut stream| {
                 stream.write_all(b"\
                     GET /health HTTP/1.1\r\n\
@@ -183,6 +203,7 @@ impl Mock {
 /// A builder for creating mock servers.
 pub struct Builder {
     responders: Vec<Box<dyn Responder>>,
+    network: Option<NetworkConditions>,
 }
 
 impl Builder {
@@ -193,10 +214,22 @@ impl Builder {
         self
     }
 
+    /// Simulate the given network conditions between clients and the mock.
+    pub fn network(mut self, conditions: NetworkConditions) -> Self {
+        self.network = Some(conditions);
+        self
+    }
+
     /// Start a new mock server.
     pub fn build(self) -> Mock {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let link = self
+            .network
+            .map(|conditions| Link::spawn(server.server_addr(), conditions).unwrap());
+
         let mock = Mock(Arc::new(Inner {
-            server: Server::http("127.0.0.1:0").unwrap(),
+            server,
+            link,
             requests: Default::default(),
             request_counter: AtomicU32::new(0),
             responders: self.responders,
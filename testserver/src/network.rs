@@ -0,0 +1,197 @@
+//! Simulated network conditions between a client and a mock server.
+//!
+//! Conditions are applied by a relay that sits in front of the mock server and
+//! forwards each connection to it. Bytes sent by the client are forwarded
+//! as-is, while bytes sent back by the server are delayed, throttled, split
+//! up, or cut off according to the configured conditions.
+
+use std::{
+    io::{self, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    ops::Range,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::pool::pool;
+
+/// Conditions of the simulated network between a client and a mock server.
+///
+/// By default bytes are forwarded as quickly as possible, like a regular
+/// loopback connection.
+#[derive(Clone, Debug, Default)]
+pub struct NetworkConditions {
+    latency: Option<Range<Duration>>,
+    bandwidth: Option<u64>,
+    max_write_size: Option<usize>,
+    disconnect_after: Option<u64>,
+}
+
+impl NetworkConditions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delay every write to the client by the given amount of time.
+    pub fn latency(self, latency: Duration) -> Self {
+        self.latency_range(latency..latency)
+    }
+
+    /// Delay every write to the client by an amount of time chosen uniformly
+    /// at random from the given range.
+    pub fn latency_range(mut self, latency: Range<Duration>) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Limit the rate at which bytes are sent to the client, in bytes per
+    /// second.
+    pub fn bandwidth(mut self, bytes_per_second: u64) -> Self {
+        self.bandwidth = Some(bytes_per_second.max(1));
+        self
+    }
+
+    /// Send bytes to the client in writes of at most the given size, so that
+    /// the client receives them in small pieces.
+    pub fn max_write_size(mut self, size: usize) -> Self {
+        self.max_write_size = Some(size.max(1));
+        self
+    }
+
+    /// Abruptly close each connection after the given number of bytes have
+    /// been sent to the client, including response headers.
+    pub fn disconnect_after(mut self, bytes: u64) -> Self {
+        self.disconnect_after = Some(bytes);
+        self
+    }
+}
+
+/// A relay that forwards connections to a server under simulated network
+/// conditions.
+pub(crate) struct Link {
+    addr: SocketAddr,
+}
+
+impl Link {
+    /// Start relaying connections to the given upstream address.
+    pub(crate) fn spawn(upstream: SocketAddr, conditions: NetworkConditions) -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+
+        pool().execute(move || {
+            for connection in listener.incoming().flatten() {
+                let conditions = conditions.clone();
+
+                // Connections may be kept open for a long time, so give them
+                // their own threads rather than tying up the pool.
+                thread::spawn(move || {
+                    let _ = relay(connection, upstream, conditions);
+                });
+            }
+        });
+
+        Ok(Self { addr })
+    }
+
+    /// Get the address clients should connect to.
+    pub(crate) fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+fn relay(client: TcpStream, upstream: SocketAddr, conditions: NetworkConditions) -> io::Result<()> {
+    let server = TcpStream::connect(upstream)?;
+    client.set_nodelay(true)?;
+
+    let mut client_reader = client.try_clone()?;
+    let mut server_writer = server.try_clone()?;
+
+    thread::spawn(move || {
+        let _ = io::copy(&mut client_reader, &mut server_writer);
+        let _ = server_writer.shutdown(Shutdown::Write);
+    });
+
+    let result = forward(&server, &client, &conditions);
+
+    let _ = client.shutdown(Shutdown::Both);
+    let _ = server.shutdown(Shutdown::Both);
+
+    result
+}
+
+/// Forward bytes from the server to the client, applying network conditions.
+fn forward(
+    mut server: &TcpStream,
+    mut client: &TcpStream,
+    conditions: &NetworkConditions,
+) -> io::Result<()> {
+    let mut rng = Rng::new();
+    let mut buf = [0; 8192];
+    let mut sent = 0u64;
+    let start = Instant::now();
+
+    loop {
+        let len = server.read(&mut buf)?;
+
+        if len == 0 {
+            return Ok(());
+        }
+
+        for chunk in buf[..len].chunks(conditions.max_write_size.unwrap_or(len)) {
+            if let Some(latency) = conditions.latency.as_ref() {
+                thread::sleep(rng.duration(latency));
+            }
+
+            let chunk = match conditions.disconnect_after {
+                Some(limit) if sent + chunk.len() as u64 >= limit => {
+                    client.write_all(&chunk[..(limit - sent) as usize])?;
+                    return Ok(());
+                }
+                _ => chunk,
+            };
+
+            client.write_all(chunk)?;
+            sent += chunk.len() as u64;
+
+            // Wait until the total amount sent so far is within the bandwidth
+            // limit.
+            if let Some(bandwidth) = conditions.bandwidth {
+                let due = Duration::from_secs_f64(sent as f64 / bandwidth as f64);
+
+                if let Some(wait) = due.checked_sub(start.elapsed()) {
+                    thread::sleep(wait);
+                }
+            }
+        }
+    }
+}
+
+/// Tiny xorshift random number generator, good enough for jittering delays.
+struct Rng(u64);
+
+impl Rng {
+    fn new() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+
+        Self(seed | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn duration(&mut self, range: &Range<Duration>) -> Duration {
+        match range.end.checked_sub(range.start) {
+            Some(span) if !span.is_zero() => {
+                range.start + span.mul_f64((self.next() >> 11) as f64 / (1u64 << 53) as f64)
+            }
+            _ => range.start,
+        }
+    }
+}
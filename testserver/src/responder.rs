@@ -53,6 +53,7 @@ pub trait Responder: Send + Sync + 'static {
 }
 
 /// Simple responder that returns a general response.
+#[allow(dead_code)]
 pub struct DefaultResponder;
 
 impl Responder for DefaultResponder {